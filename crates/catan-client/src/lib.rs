@@ -0,0 +1,372 @@
+//! Rust client for the Kopiatan multiplayer server.
+//!
+//! Wraps the WebSocket protocol so bots and tests can play without handling
+//! frames themselves. Requests that get an answer, like creating a room or
+//! making a move, wait for it and return it; everything else the server
+//! sends comes out of [`Client::next_event`] in order. Handlers added with
+//! [`Client::on_event`] see every message as it arrives, answers included.
+//!
+//! ```no_run
+//! # async fn play() -> Result<(), catan_client::ClientError> {
+//! use catan_client::{Client, ServerMessage};
+//!
+//! let mut client = Client::connect("ws://127.0.0.1:8080", "my-bot").await?;
+//! client.create_room("Ana", 4, Default::default()).await?;
+//! client.on_event(|msg| println!("{:?}", msg));
+//! loop {
+//!     match client.next_event().await? {
+//!         ServerMessage::ValidActions { actions } if !actions.is_empty() => {
+//!             client.submit_action(actions[0].clone()).await?;
+//!         }
+//!         ServerMessage::GameOver { .. } => return Ok(()),
+//!         _ => {}
+//!     }
+//! }
+//! # }
+//! ```
+
+use catan_core::{BotDifficulty, BotPersonality, GameAction, GameEvent, GameStateJson};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::VecDeque;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::{self, Message};
+use uuid::Uuid;
+
+pub use catan_server::protocol::{
+    ClientMessage, ErrorCode, RoomInfo, RoomSettings, ServerMessage, PROTOCOL_VERSION,
+};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Could not connect: {0}")]
+    Connect(#[from] Box<tungstenite::Error>),
+    #[error("Connection closed")]
+    Closed,
+    #[error("The server said: {message}")]
+    Server {
+        message: String,
+        code: Option<ErrorCode>,
+    },
+    #[error("Move rejected: {0}")]
+    Rejected(String),
+}
+
+/// A handler for every message the server sends
+type Handler = Box<dyn FnMut(&ServerMessage) + Send>;
+
+/// A connection to the server, as one player
+pub struct Client {
+    player_id: Uuid,
+    ticket: Option<String>,
+    outgoing: mpsc::UnboundedSender<ClientMessage>,
+    incoming: mpsc::UnboundedReceiver<ServerMessage>,
+    /// Messages that came while we waited on an answer
+    backlog: VecDeque<ServerMessage>,
+    handlers: Vec<Handler>,
+    /// Numbers the moves we make, to match them with their results
+    next_request: u64,
+}
+
+impl Client {
+    /// Connect and say hello. A returning player adds their ticket to the
+    /// URL as `?ticket=...` to keep their player ID.
+    pub async fn connect(url: &str, client_name: &str) -> Result<Self, ClientError> {
+        let (socket, _) = connect_async(url).await.map_err(Box::new)?;
+        let (outgoing, to_send) = mpsc::unbounded_channel();
+        let (received, incoming) = mpsc::unbounded_channel();
+        tokio::spawn(pump(socket, to_send, received));
+
+        let mut client = Self {
+            player_id: Uuid::nil(),
+            ticket: None,
+            outgoing,
+            incoming,
+            backlog: VecDeque::new(),
+            handlers: Vec::new(),
+            next_request: 0,
+        };
+        client.send(ClientMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: client_name.to_string(),
+            features: Vec::new(),
+        })?;
+        let (player_id, ticket) = client
+            .answer(|msg| match msg {
+                ServerMessage::Welcome {
+                    player_id, ticket, ..
+                } => Some(Ok((*player_id, ticket.clone()))),
+                ServerMessage::Error { message, code } => Some(Err(ClientError::Server {
+                    message: message.clone(),
+                    code: *code,
+                })),
+                _ => None,
+            })
+            .await?;
+        client.player_id = player_id;
+        client.ticket = ticket;
+        Ok(client)
+    }
+
+    /// Who the server knows us as
+    pub fn player_id(&self) -> Uuid {
+        self.player_id
+    }
+
+    /// What brings our player ID back on the next connection, when the
+    /// server hands them out
+    pub fn ticket(&self) -> Option<&str> {
+        self.ticket.as_deref()
+    }
+
+    /// Call `handler` with every message the server sends from now on, as
+    /// it arrives
+    pub fn on_event(&mut self, handler: impl FnMut(&ServerMessage) + Send + 'static) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    /// Send any message, without waiting for an answer
+    pub fn send(&self, msg: ClientMessage) -> Result<(), ClientError> {
+        self.outgoing.send(msg).map_err(|_| ClientError::Closed)
+    }
+
+    /// The next message not taken as an answer
+    pub async fn next_event(&mut self) -> Result<ServerMessage, ClientError> {
+        match self.backlog.pop_front() {
+            Some(msg) => Ok(msg),
+            None => self.receive().await,
+        }
+    }
+
+    /// Open a room with us as host, returning its ID
+    pub async fn create_room(
+        &mut self,
+        player_name: &str,
+        max_players: u8,
+        settings: RoomSettings,
+    ) -> Result<Uuid, ClientError> {
+        self.send(ClientMessage::CreateRoom {
+            player_name: player_name.to_string(),
+            max_players,
+            settings,
+        })?;
+        self.answer(|msg| match msg {
+            ServerMessage::RoomCreated { room_id } => Some(Ok(*room_id)),
+            _ => refused(msg),
+        })
+        .await
+    }
+
+    /// Take a seat in a room
+    pub async fn join(
+        &mut self,
+        room_id: Uuid,
+        player_name: &str,
+    ) -> Result<RoomInfo, ClientError> {
+        self.send(ClientMessage::JoinRoom {
+            room_id,
+            player_name: player_name.to_string(),
+        })?;
+        self.answer(|msg| match msg {
+            ServerMessage::JoinedRoom { room, .. } if room.id == room_id => Some(Ok(room.clone())),
+            _ => refused(msg),
+        })
+        .await
+    }
+
+    /// Leave the room we are in
+    pub async fn leave(&mut self) -> Result<(), ClientError> {
+        self.send(ClientMessage::LeaveRoom)?;
+        self.answer(|msg| match msg {
+            ServerMessage::LeftRoom => Some(Ok(())),
+            _ => refused(msg),
+        })
+        .await
+    }
+
+    /// Fill a seat with a bot (host only)
+    pub async fn add_bot(
+        &mut self,
+        difficulty: BotDifficulty,
+        personality: BotPersonality,
+    ) -> Result<RoomInfo, ClientError> {
+        self.send(ClientMessage::AddBot {
+            difficulty,
+            personality,
+        })?;
+        self.answer(|msg| match msg {
+            ServerMessage::RoomUpdated { room } => Some(Ok(room.clone())),
+            _ => refused(msg),
+        })
+        .await
+    }
+
+    /// Start the game (host only), returning where it begins
+    pub async fn start_game(&mut self) -> Result<GameStateJson, ClientError> {
+        self.send(ClientMessage::StartGame)?;
+        self.answer(|msg| match msg {
+            ServerMessage::GameStarted { state, .. } => Some(Ok((**state).clone())),
+            _ => refused(msg),
+        })
+        .await
+    }
+
+    /// Make a move, returning what it caused
+    pub async fn submit_action(
+        &mut self,
+        action: GameAction,
+    ) -> Result<Vec<GameEvent>, ClientError> {
+        self.next_request += 1;
+        let request_id = self.next_request.to_string();
+        self.send(ClientMessage::GameAction {
+            action,
+            request_id: Some(request_id.clone()),
+        })?;
+        self.answer(|msg| match msg {
+            ServerMessage::ActionResult {
+                success,
+                events,
+                error,
+                request_id: Some(id),
+                ..
+            } if *id == request_id => Some(if *success {
+                Ok(events.clone())
+            } else {
+                Err(ClientError::Rejected(error.clone().unwrap_or_default()))
+            }),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Read until `answer` picks a message out, keeping the rest for
+    /// `next_event`
+    async fn answer<T>(
+        &mut self,
+        mut answer: impl FnMut(&ServerMessage) -> Option<Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        loop {
+            let msg = self.receive().await?;
+            match answer(&msg) {
+                Some(result) => return result,
+                None => self.backlog.push_back(msg),
+            }
+        }
+    }
+
+    async fn receive(&mut self) -> Result<ServerMessage, ClientError> {
+        let msg = self.incoming.recv().await.ok_or(ClientError::Closed)?;
+        for handler in &mut self.handlers {
+            handler(&msg);
+        }
+        Ok(msg)
+    }
+}
+
+/// An error from the server, taken as the answer to a request
+fn refused<T>(msg: &ServerMessage) -> Option<Result<T, ClientError>> {
+    match msg {
+        ServerMessage::Error { message, code } => Some(Err(ClientError::Server {
+            message: message.clone(),
+            code: *code,
+        })),
+        _ => None,
+    }
+}
+
+/// Move messages between the socket and the client until either side
+/// goes away. Reading also answers the server's pings.
+async fn pump<S>(
+    socket: S,
+    mut to_send: mpsc::UnboundedReceiver<ClientMessage>,
+    received: mpsc::UnboundedSender<ServerMessage>,
+) where
+    S: futures_util::Stream<Item = Result<Message, tungstenite::Error>>
+        + futures_util::Sink<Message>
+        + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+    loop {
+        tokio::select! {
+            msg = to_send.recv() => {
+                let Some(msg) = msg else {
+                    let _ = sink.close().await;
+                    return;
+                };
+                let Ok(text) = serde_json::to_string(&msg) else {
+                    continue;
+                };
+                if sink.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            frame = stream.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                };
+                // Messages from a newer server that we can't read are skipped
+                if let Ok(msg) = serde_json::from_str(&text) {
+                    if received.send(msg).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catan_server::server::{serve, ServerState};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_two_players_sit_down_and_play() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, Arc::new(ServerState::new())));
+
+        let mut host = Client::connect(&url, "test").await.unwrap();
+        let mut guest = Client::connect(&url, "test").await.unwrap();
+        assert_ne!(host.player_id(), guest.player_id());
+        let seen = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&seen);
+        guest.on_event(move |_| *counter.lock().unwrap() += 1);
+
+        let room_id = host
+            .create_room("Ana", 2, RoomSettings::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            guest.join(Uuid::new_v4(), "Ben").await,
+            Err(ClientError::Server { .. })
+        ));
+        let room = guest.join(room_id, "Ben").await.unwrap();
+        assert_eq!(room.players.len(), 2);
+        host.start_game().await.unwrap();
+
+        // Whoever goes first makes a setup move
+        let (mut first, mut other) = (host, guest);
+        let actions = loop {
+            match first.next_event().await.unwrap() {
+                ServerMessage::ValidActions { actions } if actions.is_empty() => {
+                    std::mem::swap(&mut first, &mut other);
+                }
+                ServerMessage::ValidActions { actions } => break actions,
+                _ => {}
+            }
+        };
+        let events = first.submit_action(actions[0].clone()).await.unwrap();
+        assert!(!events.is_empty());
+        assert!(matches!(
+            first.submit_action(actions[0].clone()).await,
+            Err(ClientError::Rejected(_))
+        ));
+        assert!(*seen.lock().unwrap() > 0);
+    }
+}
@@ -0,0 +1,10 @@
+//! Arbitrary move sequences against seeded games. Run from
+//! `crates/catan-core` with `cargo +nightly fuzz run actions`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    catan_core::fuzzing::run(data);
+});
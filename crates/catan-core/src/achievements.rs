@@ -0,0 +1,175 @@
+//! Milestones for UIs to celebrate.
+//!
+//! After every action the engine looks over the events it produced and
+//! adds an [`GameEvent::AchievementUnlocked`] for each milestone reached.
+//! Unlocks are recorded on the [`Player`](crate::player::Player), so each
+//! milestone is announced at most once per player.
+
+use crate::actions::GameEvent;
+use crate::board::PlayerId;
+use crate::game::GameState;
+use serde::{Deserialize, Serialize};
+
+/// Resources a Monopoly must take to count as a big haul
+pub const MONOPOLY_HAUL: u32 = 5;
+
+/// A milestone a player can reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Achievement {
+    /// Built their first city
+    FirstCity,
+    /// Took at least [`MONOPOLY_HAUL`] resources with one Monopoly
+    MonopolyHaul,
+    /// Won without holding Largest Army
+    WonWithoutArmy,
+    /// Has a settlement or city on every harbor
+    Kiasu,
+}
+
+impl Achievement {
+    /// Stable key, also used to look up its name in a
+    /// [`ThemePack`](crate::theme::ThemePack)
+    pub fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstCity => "first_city",
+            Achievement::MonopolyHaul => "monopoly_haul",
+            Achievement::WonWithoutArmy => "won_without_army",
+            Achievement::Kiasu => "kiasu",
+        }
+    }
+}
+
+/// Record and return the achievements unlocked by `events`, which `state`
+/// has just applied. Milestones a player already holds are skipped.
+pub fn unlock(state: &mut GameState, events: &[GameEvent]) -> Vec<GameEvent> {
+    let reached: Vec<(PlayerId, Achievement)> = events
+        .iter()
+        .filter_map(|event| match *event {
+            GameEvent::CityBuilt { player, .. } => Some((player, Achievement::FirstCity)),
+            GameEvent::MonopolyPlayed {
+                player,
+                total_stolen,
+                ..
+            } if total_stolen >= MONOPOLY_HAUL => Some((player, Achievement::MonopolyHaul)),
+            GameEvent::GameWon { player, .. } => state
+                .get_player(player)
+                .filter(|p| !p.has_largest_army)
+                .map(|_| (player, Achievement::WonWithoutArmy)),
+            // A settlement is the only way onto a new harbor
+            GameEvent::SettlementBuilt { player, location } => {
+                let board = &state.board;
+                let on_every_harbor = board.harbor_at(&location).is_some()
+                    && board.player_harbors(player).len() == board.harbors().len();
+                on_every_harbor.then_some((player, Achievement::Kiasu))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut unlocked = Vec::new();
+    for (player, achievement) in reached {
+        let Some(p) = state.players.iter_mut().find(|p| p.id == player) else {
+            continue;
+        };
+        if p.achievements.contains(&achievement) {
+            continue;
+        }
+        p.achievements.push(achievement);
+        unlocked.push(GameEvent::AchievementUnlocked {
+            player,
+            achievement,
+        });
+    }
+    unlocked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Resource;
+
+    fn unlocked(player: PlayerId, achievement: Achievement) -> Vec<GameEvent> {
+        vec![GameEvent::AchievementUnlocked {
+            player,
+            achievement,
+        }]
+    }
+
+    fn new_game() -> GameState {
+        let names = vec!["Ah Boy".into(), "Ah Girl".into(), "Uncle".into()];
+        GameState::new(3, names)
+    }
+
+    #[test]
+    fn test_first_city_unlocks_once() {
+        let mut state = new_game();
+        // Player 0 already started with a city from the scenario
+        state.players[0].cities_remaining = 3;
+        let city = |location| GameEvent::CityBuilt {
+            player: 0,
+            location,
+        };
+        let spots = state.board.vertices_by_id()[..2].to_vec();
+        assert_eq!(
+            unlock(&mut state, &[city(spots[0])]),
+            unlocked(0, Achievement::FirstCity)
+        );
+        assert!(unlock(&mut state, &[city(spots[1])]).is_empty());
+        assert_eq!(state.players[0].achievements, vec![Achievement::FirstCity]);
+    }
+
+    #[test]
+    fn test_monopoly_haul_unlocks_once() {
+        let mut state = new_game();
+        let monopoly = |total_stolen| GameEvent::MonopolyPlayed {
+            player: 1,
+            resource: Resource::Ore,
+            total_stolen,
+        };
+        assert!(unlock(&mut state, &[monopoly(MONOPOLY_HAUL - 1)]).is_empty());
+        assert_eq!(
+            unlock(&mut state, &[monopoly(MONOPOLY_HAUL), monopoly(MONOPOLY_HAUL)]),
+            unlocked(1, Achievement::MonopolyHaul)
+        );
+        assert!(unlock(&mut state, &[monopoly(MONOPOLY_HAUL + 3)]).is_empty());
+    }
+
+    #[test]
+    fn test_won_without_army_skips_the_army_holder() {
+        let mut state = new_game();
+        let won = |player| GameEvent::GameWon {
+            player,
+            victory_points: 10,
+        };
+        state.players[2].has_largest_army = true;
+        assert!(unlock(&mut state, &[won(2)]).is_empty());
+        assert_eq!(
+            unlock(&mut state, &[won(1)]),
+            unlocked(1, Achievement::WonWithoutArmy)
+        );
+        assert!(unlock(&mut state, &[won(1)]).is_empty());
+    }
+
+    #[test]
+    fn test_kiasu_unlocks_once() {
+        let mut state = new_game();
+        // Settling the last harbor makes player 0 kiasu
+        let harbors: Vec<_> = state.board.harbors().iter().map(|h| h.edge.endpoints()).collect();
+        for [spot, _] in &harbors {
+            state.board.place_settlement(*spot, 0);
+        }
+        let settle = |location| GameEvent::SettlementBuilt {
+            player: 0,
+            location,
+        };
+        let [last, other_end] = *harbors.last().unwrap();
+        assert_eq!(
+            unlock(&mut state, &[settle(last)]),
+            unlocked(0, Achievement::Kiasu)
+        );
+
+        // The same harbor's other endpoint doesn't count again
+        state.board.place_settlement(other_end, 0);
+        assert!(unlock(&mut state, &[settle(other_end)]).is_empty());
+    }
+}
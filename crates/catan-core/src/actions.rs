@@ -3,9 +3,12 @@
 //! This module defines all possible actions in the game and the events
 //! that result from those actions.
 
+use crate::achievements::Achievement;
+use crate::barbarians::EventFace;
 use crate::board::{PlayerId, Resource};
+use crate::cities_knights::{Commodity, Improvement, ProgressCard};
 use crate::hex::{EdgeCoord, HexCoord, VertexCoord};
-use crate::player::ResourceHand;
+use crate::player::{DevelopmentCard, ResourceHand};
 use serde::{Deserialize, Serialize};
 
 /// All possible actions a player can take
@@ -24,6 +27,8 @@ pub enum GameAction {
     // ==================== Robber Actions ====================
     /// Move the robber to a new hex (after rolling 7 or playing knight)
     MoveRobber(HexCoord),
+    /// Move the pirate to an ocean hex instead of moving the robber
+    MovePirate(HexCoord),
     /// Choose a player to steal from (after moving robber)
     StealFrom(PlayerId),
     /// Discard cards when you have more than 7 after a 7 is rolled
@@ -49,6 +54,10 @@ pub enum GameAction {
     /// Play monopoly (take all of one resource from all players)
     PlayMonopoly(Resource),
 
+    // ==================== Cities & Knights ====================
+    /// Raise a city improvement track one level, paying in commodities
+    BuildCityImprovement(Improvement),
+
     // ==================== Trading Actions ====================
     /// Propose a trade to other players
     ProposeTrade(TradeOffer),
@@ -70,6 +79,17 @@ pub enum GameAction {
     // ==================== Turn Management ====================
     /// End your turn
     EndTurn,
+    /// Take over the shared device after a hot-seat handoff
+    ConfirmNextPlayer,
+    /// Propose ending the game early with a ranking by victory points.
+    /// Allowed at any time, so it is never listed in `valid_actions`.
+    ProposeEndGame,
+    /// Agree (true) or refuse (false) to end the game early
+    VoteEndGame(bool),
+    /// Resign from the game. The player's pieces stay on the board but they
+    /// are skipped in turn order from now on. Allowed at any time, so it is
+    /// never listed in `valid_actions`.
+    Concede,
 }
 
 /// A trade offer between players
@@ -117,6 +137,38 @@ pub enum GameEvent {
         total: u8,
     },
 
+    /// The balanced dice deck ran out and was shuffled again
+    DiceDeckReshuffled { cards: u32 },
+
+    /// The event die was rolled alongside the dice
+    EventDieRolled { player: PlayerId, face: EventFace },
+
+    /// A ship face moved the barbarians one step closer
+    BarbariansAdvanced { position: u32, length: u32 },
+
+    /// The barbarians reached the island. `strength` is the number of
+    /// cities on the board.
+    BarbarianAttack { strength: u32 },
+
+    /// Cities made commodities after a dice roll
+    CommoditiesProduced {
+        distributions: Vec<(PlayerId, Commodity, u32)>,
+    },
+
+    /// A city improvement track went up a level
+    CityImproved {
+        player: PlayerId,
+        track: Improvement,
+        level: u32,
+    },
+
+    /// A progress card was drawn. Only the player who drew it sees which.
+    ProgressCardDrawn {
+        player: PlayerId,
+        track: Improvement,
+        card: Option<ProgressCard>,
+    },
+
     /// Resources were distributed after a dice roll
     ResourcesDistributed {
         distributions: Vec<(PlayerId, Resource, u32)>,
@@ -141,7 +193,18 @@ pub enum GameEvent {
     },
 
     /// A development card was purchased
-    DevelopmentCardPurchased { player: PlayerId },
+    DevelopmentCardPurchased {
+        player: PlayerId,
+        /// Cards left in the deck after this one
+        #[serde(default)]
+        deck_remaining: u32,
+    },
+
+    /// The card a purchase drew, shown only to the buyer
+    DevelopmentCardDrawn {
+        player: PlayerId,
+        card: DevelopmentCard,
+    },
 
     /// A knight was played
     KnightPlayed { player: PlayerId },
@@ -162,8 +225,15 @@ pub enum GameEvent {
         total_stolen: u32,
     },
 
-    /// The robber was moved
+    /// The robber was moved; `from` is None when it comes onto the board
     RobberMoved {
+        player: PlayerId,
+        from: Option<HexCoord>,
+        to: HexCoord,
+    },
+
+    /// The pirate was moved
+    PirateMoved {
         player: PlayerId,
         from: HexCoord,
         to: HexCoord,
@@ -224,9 +294,70 @@ pub enum GameEvent {
         next_player: PlayerId,
     },
 
+    /// A player resigned from the game
+    PlayerConceded { player: PlayerId },
+
+    /// A player proposed ending the game early
+    EndGameProposed { player: PlayerId },
+
+    /// A player voted on ending the game early
+    EndGameVoteCast { player: PlayerId, accept: bool },
+
+    /// The vote to end the game was refused or interrupted
+    EndGameVoteFailed,
+
+    /// All remaining players agreed to end the game; no single winner
+    GameEndedByAgreement { ranking: Vec<PlayerId> },
+
+    /// The next player took over the shared device
+    NextPlayerConfirmed { player: PlayerId },
+
+    /// A player's time bank ran out
+    TimeExpired { player: PlayerId },
+
+    /// The turn limit from the game options was reached
+    TurnLimitReached { turns: u32 },
+
+    /// A team won the game (sent before `GameWon` for the deciding player)
+    TeamWon { team: u8, players: Vec<PlayerId> },
+
+    /// A player reached a milestone
+    AchievementUnlocked {
+        player: PlayerId,
+        achievement: Achievement,
+    },
+
     /// A player won the game
     GameWon {
         player: PlayerId,
         victory_points: u32,
     },
 }
+
+impl GameEvent {
+    /// The event as `viewer` may see it (`None` for a spectator), or None
+    /// if it isn't theirs to see: only the thief and the victim learn what
+    /// was stolen, and only the buyer or drawer which card they drew
+    pub fn visible_to(&self, viewer: Option<PlayerId>) -> Option<GameEvent> {
+        match self {
+            GameEvent::ResourceStolen { thief, victim, .. }
+                if viewer != Some(*thief) && viewer != Some(*victim) =>
+            {
+                Some(GameEvent::ResourceStolen {
+                    thief: *thief,
+                    victim: *victim,
+                    resource: None,
+                })
+            }
+            GameEvent::DevelopmentCardDrawn { player, .. } if viewer != Some(*player) => None,
+            GameEvent::ProgressCardDrawn { player, track, .. } if viewer != Some(*player) => {
+                Some(GameEvent::ProgressCardDrawn {
+                    player: *player,
+                    track: *track,
+                    card: None,
+                })
+            }
+            event => Some(event.clone()),
+        }
+    }
+}
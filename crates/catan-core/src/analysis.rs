@@ -0,0 +1,345 @@
+//! Post-game analysis: replay a game and grade every decision.
+//!
+//! Wherever a player had a real choice, each candidate move is tried on a
+//! copy of the position and scored with [`evaluate_position`]. The gap
+//! between the best candidate and the move actually played is that move's
+//! loss in win chance, and the losses over a game give each player an
+//! accuracy percentage, much like a chess engine's game report.
+
+use crate::actions::GameAction;
+use crate::board::PlayerId;
+use crate::evaluation::{evaluate_position, production};
+use crate::game::{GameError, GameState};
+use crate::options::GameOptions;
+use crate::mcts::candidate_actions;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Loss in win chance from which a move counts as a blunder
+pub const BLUNDER_LOSS: f32 = 0.1;
+
+/// A game as it was played: the starting position and every move since.
+/// Actions a game clock takes for a player whose time ran out are not
+/// moves, so a game decided on time only replays up to that point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub start: GameState,
+    pub moves: Vec<RecordedMove>,
+}
+
+/// One applied action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub player: PlayerId,
+    pub action: GameAction,
+}
+
+/// Errors from replaying a record
+#[derive(Debug, Clone, Error)]
+pub enum AnalysisError {
+    #[error("Move {index} no longer applies: {error}")]
+    IllegalMove { index: usize, error: GameError },
+}
+
+impl GameRecord {
+    /// Start recording from `start`
+    pub fn new(start: &GameState) -> Self {
+        Self {
+            start: start.clone(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Note an action the game accepted
+    pub fn push(&mut self, player: PlayerId, action: GameAction) {
+        self.moves.push(RecordedMove { player, action });
+    }
+
+    /// Play every move again from the start
+    pub fn replay(&self) -> Result<GameState, AnalysisError> {
+        let mut game = self.start.clone();
+        for (index, m) in self.moves.iter().enumerate() {
+            game.apply_action(m.player, m.action.clone())
+                .map_err(|error| AnalysisError::IllegalMove { index, error })?;
+        }
+        Ok(game)
+    }
+}
+
+/// A record of a game started from a seed, in a form JSON can carry: the
+/// seed, names and rules set up the same starting position again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayRecord {
+    pub seed: u64,
+    /// Names in seat order
+    pub players: Vec<String>,
+    pub options: GameOptions,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl ReplayRecord {
+    pub fn new(record: &GameRecord) -> Self {
+        let start = &record.start;
+        Self {
+            seed: start.seed(),
+            players: start.players.iter().map(|p| p.name.clone()).collect(),
+            options: start.options.clone(),
+            moves: record.moves.clone(),
+        }
+    }
+
+    /// The record to play through again or analyse
+    pub fn to_record(&self) -> GameRecord {
+        let start = GameState::new_with_seed(
+            self.players.len() as u8,
+            self.players.clone(),
+            self.options.clone(),
+            self.seed,
+        );
+        GameRecord {
+            start,
+            moves: self.moves.clone(),
+        }
+    }
+}
+
+/// How one decision compares with the evaluator's choice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// Position of the move in the record
+    pub index: usize,
+    pub turn: u32,
+    pub player: PlayerId,
+    pub played: GameAction,
+    /// The evaluator's preferred move (the played one when it ties)
+    pub best: GameAction,
+    /// Estimated win chance after the played and the preferred move
+    pub played_value: f32,
+    pub best_value: f32,
+    /// Moves that were compared, the played one included
+    pub candidates: usize,
+    /// Change in the player's victory points from the played move
+    pub vp_swing: i32,
+    /// Change in the player's production (pips per roll) from the played move
+    pub production_swing: i32,
+    /// Score for the move out of 100
+    pub accuracy: f32,
+}
+
+impl Decision {
+    /// Win chance given up against the preferred move
+    pub fn loss(&self) -> f32 {
+        (self.best_value - self.played_value).max(0.0)
+    }
+
+    pub fn is_blunder(&self) -> bool {
+        self.loss() >= BLUNDER_LOSS
+    }
+}
+
+/// One player's totals over a game
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerAccuracy {
+    pub player: PlayerId,
+    pub decisions: u32,
+    /// Mean move accuracy out of 100 (100 with no decisions)
+    pub accuracy: f32,
+    pub average_loss: f32,
+    /// Decisions that matched the evaluator's choice
+    pub best_moves: u32,
+    pub blunders: u32,
+}
+
+/// Result of [`analyze_game`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameAnalysis {
+    /// Every graded decision, in the order played
+    pub decisions: Vec<Decision>,
+    /// Totals by seat
+    pub players: Vec<PlayerAccuracy>,
+}
+
+/// Replay `record` and grade each decision
+pub fn analyze_game(record: &GameRecord) -> Result<GameAnalysis, AnalysisError> {
+    let mut game = record.start.clone();
+    let mut decisions = Vec::new();
+
+    for (index, m) in record.moves.iter().enumerate() {
+        if let Some(decision) = grade(&game, index, m) {
+            decisions.push(decision);
+        }
+        game.apply_action(m.player, m.action.clone())
+            .map_err(|error| AnalysisError::IllegalMove { index, error })?;
+    }
+
+    let players = (0..game.players.len() as PlayerId)
+        .map(|player| totals(player, &decisions))
+        .collect();
+    Ok(GameAnalysis { decisions, players })
+}
+
+/// Grade one move, if the player had anything else worth playing
+fn grade(game: &GameState, index: usize, m: &RecordedMove) -> Option<Decision> {
+    // Leaving or ending the game is not a playing decision
+    if matches!(
+        m.action,
+        GameAction::Concede | GameAction::ProposeEndGame | GameAction::VoteEndGame(_)
+    ) {
+        return None;
+    }
+
+    let mut candidates = candidate_actions(game, m.player);
+    if !candidates.contains(&m.action) {
+        candidates.push(m.action.clone());
+    }
+    if candidates.len() < 2 {
+        return None;
+    }
+
+    // Chance moves see the same dice and steals on every copy, so the
+    // played move scores what it actually got
+    let value = |action: &GameAction| {
+        let mut after = game.clone();
+        after
+            .apply_action(m.player, action.clone())
+            .ok()
+            .map(|_| (evaluate_position(&after, m.player), after))
+    };
+    let (played_value, after) = value(&m.action)?;
+    let (mut best, mut best_value) = (m.action.clone(), played_value);
+    for action in candidates.iter().filter(|a| **a != m.action) {
+        if let Some((v, _)) = value(action) {
+            if v > best_value {
+                best = action.clone();
+                best_value = v;
+            }
+        }
+    }
+
+    let loss = (best_value - played_value).max(0.0);
+    Some(Decision {
+        index,
+        turn: game.turn_number,
+        player: m.player,
+        played: m.action.clone(),
+        best,
+        played_value,
+        best_value,
+        candidates: candidates.len(),
+        vp_swing: game_vp(&after, m.player) - game_vp(game, m.player),
+        production_swing: production(&after, m.player) as i32
+            - production(game, m.player) as i32,
+        accuracy: move_accuracy(loss),
+    })
+}
+
+fn game_vp(game: &GameState, player: PlayerId) -> i32 {
+    game.total_victory_points(player) as i32
+}
+
+/// Accuracy out of 100 for a loss in win chance, on the curve chess
+/// sites use for win-percentage drops
+fn move_accuracy(loss: f32) -> f32 {
+    (103.1668 * (-4.354 * loss).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+fn totals(player: PlayerId, decisions: &[Decision]) -> PlayerAccuracy {
+    let own: Vec<&Decision> = decisions.iter().filter(|d| d.player == player).collect();
+    if own.is_empty() {
+        return PlayerAccuracy {
+            player,
+            accuracy: 100.0,
+            ..PlayerAccuracy::default()
+        };
+    }
+
+    let count = own.len() as f32;
+    PlayerAccuracy {
+        player,
+        decisions: own.len() as u32,
+        accuracy: own.iter().map(|d| d.accuracy).sum::<f32>() / count,
+        average_loss: own.iter().map(|d| d.loss()).sum::<f32>() / count,
+        best_moves: own.iter().filter(|d| d.played == d.best).count() as u32,
+        blunders: own.iter().filter(|d| d.is_blunder()).count() as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, BotDifficulty, BotPlayer};
+    use crate::options::GameOptions;
+
+    /// A short game: Hard bots against a player who always takes the
+    /// first valid action
+    fn recorded_game() -> GameRecord {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let mut game = GameState::new_with_seed(3, names, GameOptions::default(), 21);
+        let mut record = GameRecord::new(&game);
+        let mut bots: Vec<Bot> = (0..3)
+            .map(|p| Bot::with_seed(p, BotDifficulty::Hard, 40 + p as u64))
+            .collect();
+
+        for _ in 0..250 {
+            let Some(&player) = game.waiting_on().first() else {
+                break;
+            };
+            let action = if player == 2 {
+                game.auto_action(player).unwrap()
+            } else {
+                bots[player as usize].choose_action(&game).unwrap()
+            };
+            let events = game.apply_action(player, action.clone()).unwrap();
+            for bot in bots.iter_mut() {
+                bot.observe(&game, &events);
+            }
+            record.push(player, action);
+        }
+        record
+    }
+
+    #[test]
+    fn test_analysis_grades_every_choice() {
+        let record = recorded_game();
+        let analysis = analyze_game(&record).unwrap();
+
+        assert_eq!(analysis.players.len(), 3);
+        assert!(!analysis.decisions.is_empty());
+        for d in &analysis.decisions {
+            assert!(d.candidates >= 2);
+            assert!(d.best_value >= d.played_value);
+            assert!((0.0..=100.0).contains(&d.accuracy));
+            if d.played == d.best {
+                assert_eq!(d.loss(), 0.0);
+            }
+        }
+        for p in &analysis.players {
+            assert!(p.decisions > 0);
+            assert!(p.best_moves <= p.decisions);
+        }
+
+        // Replays are exact, so analysis is too
+        let replayed = record.replay().unwrap();
+        assert!(replayed.turn_number >= analysis.decisions.last().unwrap().turn);
+        let again = analyze_game(&record).unwrap();
+        assert_eq!(analysis.decisions, again.decisions);
+    }
+
+    #[test]
+    fn test_setup_moves_show_production_and_bad_records_fail() {
+        let record = recorded_game();
+        let analysis = analyze_game(&record).unwrap();
+        let first = &analysis.decisions[0];
+        assert_eq!(first.index, 0);
+        assert!(matches!(first.played, GameAction::PlaceInitialSettlement(_)));
+        assert_eq!(first.vp_swing, 1);
+        assert!(first.production_swing > 0);
+
+        let mut broken = record.clone();
+        broken.moves.insert(1, broken.moves[0].clone());
+        assert!(matches!(
+            analyze_game(&broken),
+            Err(AnalysisError::IllegalMove { index: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+//! Dense storage for pieces on vertices and edges.
+//!
+//! Every vertex and edge near the board gets a stable integer ID derived
+//! from its canonical coordinate, so lookups are a bounds check and an array
+//! index instead of a hash. IDs stay fixed until a piece lands outside the
+//! covered area, which only happens on hand-built boards.
+
+use crate::hex::{EdgeCoord, EdgeDirection, HexCoord, VertexCoord, VertexDirection};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A coordinate that owns a fixed slot within a hex cell
+pub(crate) trait Slot: Copy {
+    /// Slots per hex cell
+    const PER_HEX: usize;
+
+    /// The cell and slot this coordinate lives in
+    fn locate(&self) -> (HexCoord, usize);
+
+    /// The coordinate living in a slot
+    fn from_slot(hex: HexCoord, slot: usize) -> Self;
+}
+
+impl Slot for VertexCoord {
+    const PER_HEX: usize = 2;
+
+    fn locate(&self) -> (HexCoord, usize) {
+        let slot = match self.direction {
+            VertexDirection::North => 0,
+            VertexDirection::South => 1,
+        };
+        (self.hex, slot)
+    }
+
+    fn from_slot(hex: HexCoord, slot: usize) -> Self {
+        let direction = if slot == 0 {
+            VertexDirection::North
+        } else {
+            VertexDirection::South
+        };
+        VertexCoord { hex, direction }
+    }
+}
+
+impl Slot for EdgeCoord {
+    const PER_HEX: usize = 3;
+
+    fn locate(&self) -> (HexCoord, usize) {
+        // Canonical edges always point NE, E or SE
+        let edge = self.canonical();
+        let slot = match edge.direction {
+            EdgeDirection::NorthEast => 0,
+            EdgeDirection::East => 1,
+            _ => 2,
+        };
+        (edge.hex, slot)
+    }
+
+    fn from_slot(hex: HexCoord, slot: usize) -> Self {
+        let direction = match slot {
+            0 => EdgeDirection::NorthEast,
+            1 => EdgeDirection::East,
+            _ => EdgeDirection::SouthEast,
+        };
+        EdgeCoord { hex, direction }
+    }
+}
+
+/// Rectangle of hex cells in axial coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Layout {
+    min_q: i32,
+    min_r: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Layout {
+    /// Bounding box of `hexes`, padded by one hex so the canonical vertices
+    /// and edges of every hex inside also fit
+    fn covering(hexes: impl IntoIterator<Item = HexCoord>) -> Self {
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+        for hex in hexes {
+            let (min_q, max_q, min_r, max_r) = bounds.unwrap_or((hex.q, hex.q, hex.r, hex.r));
+            bounds = Some((min_q.min(hex.q), max_q.max(hex.q), min_r.min(hex.r), max_r.max(hex.r)));
+        }
+
+        match bounds {
+            Some((min_q, max_q, min_r, max_r)) => Self {
+                min_q: min_q - 1,
+                min_r: min_r - 1,
+                width: max_q - min_q + 3,
+                height: max_r - min_r + 3,
+            },
+            None => Self::default(),
+        }
+    }
+
+    fn cells(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    fn cell(&self, hex: HexCoord) -> Option<usize> {
+        let dq = hex.q - self.min_q;
+        let dr = hex.r - self.min_r;
+        if (0..self.width).contains(&dq) && (0..self.height).contains(&dr) {
+            Some((dr * self.width + dq) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn hex(&self, cell: usize) -> HexCoord {
+        let cell = cell as i32;
+        HexCoord::new(self.min_q + cell % self.width, self.min_r + cell / self.width)
+    }
+}
+
+/// Values stored densely by coordinate; unset slots hold `V::default()`
+#[derive(Debug, Clone)]
+pub(crate) struct Arena<K, V> {
+    layout: Layout,
+    slots: Vec<V>,
+    _key: PhantomData<K>,
+}
+
+impl<K: Slot, V: Copy + Default + PartialEq> Arena<K, V> {
+    pub fn new() -> Self {
+        Self {
+            layout: Layout::default(),
+            slots: Vec::new(),
+            _key: PhantomData,
+        }
+    }
+
+    fn id(&self, key: &K) -> Option<usize> {
+        let (hex, slot) = key.locate();
+        self.layout.cell(hex).map(|cell| cell * K::PER_HEX + slot)
+    }
+
+    /// Whether `hex` has IDs in the current layout
+    pub fn covers(&self, hex: HexCoord) -> bool {
+        self.layout.cell(hex).is_some()
+    }
+
+    pub fn get(&self, key: &K) -> V {
+        self.id(key).map(|id| self.slots[id]).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, key: K, value: V) {
+        if self.id(&key).is_none() {
+            self.refit([key.locate().0]);
+        }
+        let id = self.id(&key).expect("layout covers the key after refit");
+        self.slots[id] = value;
+    }
+
+    /// Rebuild the layout to cover `hexes` as well as every stored value.
+    /// This reassigns IDs, so do it while building a board.
+    pub fn refit(&mut self, hexes: impl IntoIterator<Item = HexCoord>) {
+        let stored: Vec<(K, V)> = self.iter().collect();
+        let layout = Layout::covering(
+            hexes
+                .into_iter()
+                .chain(stored.iter().map(|(key, _)| key.locate().0)),
+        );
+
+        self.layout = layout;
+        self.slots = vec![V::default(); layout.cells() * K::PER_HEX];
+        for (key, value) in stored {
+            let id = self.id(&key).expect("layout covers stored keys");
+            self.slots[id] = value;
+        }
+    }
+
+    /// Set slots in ID order
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != V::default())
+            .map(|(id, value)| {
+                let hex = self.layout.hex(id / K::PER_HEX);
+                (K::from_slot(hex, id % K::PER_HEX), *value)
+            })
+    }
+}
+
+impl<K: Slot, V: Copy + Default + PartialEq> Default for Arena<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Serialized as a map of the set slots, the same shape as a `HashMap<K, V>`
+impl<K, V> Serialize for Arena<K, V>
+where
+    K: Slot + Serialize,
+    V: Copy + Default + PartialEq + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for Arena<K, V>
+where
+    K: Slot + Eq + Hash + Deserialize<'de>,
+    V: Copy + Default + PartialEq + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = HashMap::<K, V>::deserialize(deserializer)?;
+        let mut arena = Self::new();
+        arena.refit(map.keys().map(|key| key.locate().0));
+        for (key, value) in map {
+            arena.set(key, value);
+        }
+        Ok(arena)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_round_trip_and_survive_refit() {
+        let mut arena: Arena<EdgeCoord, u8> = Arena::new();
+        arena.refit((-3..=3).map(|q| HexCoord::new(q, 0)));
+
+        let west = EdgeCoord::new(HexCoord::new(0, 0), EdgeDirection::West);
+        arena.set(west, 7);
+        // The same edge seen from the other hex
+        let east = EdgeCoord::new(HexCoord::new(-1, 0), EdgeDirection::East);
+        assert_eq!(arena.get(&east), 7);
+
+        // Out of the layout: the arena grows and keeps what it had
+        let far = EdgeCoord::new(HexCoord::new(10, 10), EdgeDirection::SouthWest);
+        arena.set(far, 3);
+        assert_eq!(arena.get(&west), 7);
+        assert_eq!(arena.get(&far), 3);
+        assert_eq!(arena.iter().count(), 2);
+        assert!(arena.iter().all(|(edge, _)| edge == west.canonical() || edge == far.canonical()));
+    }
+}
@@ -0,0 +1,103 @@
+//! Event die and barbarian track, the first pieces of Cities & Knights.
+//!
+//! With `GameOptions::event_die` set, every roll also throws an event die.
+//! Each ship face moves the barbarians one step closer to the island; when
+//! they land they attack and sail back to the start. For now an attack is
+//! only announced, with the strength it comes at, since there are no
+//! knights yet to meet it.
+
+use serde::{Deserialize, Serialize};
+
+/// A face of the event die
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventFace {
+    /// The barbarian ship advances
+    Ship,
+    /// Blue city gate
+    Trade,
+    /// Yellow city gate
+    Politics,
+    /// Green city gate
+    Science,
+}
+
+/// Event die settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventDie {
+    /// Faces of the die, each equally likely
+    pub faces: Vec<EventFace>,
+    /// Ship faces it takes the barbarians to reach the island
+    pub track_length: u32,
+}
+
+impl Default for EventDie {
+    /// Three ships and one city gate of each color, on a seven step track
+    fn default() -> Self {
+        Self {
+            faces: vec![
+                EventFace::Ship,
+                EventFace::Ship,
+                EventFace::Ship,
+                EventFace::Trade,
+                EventFace::Politics,
+                EventFace::Science,
+            ],
+            track_length: 7,
+        }
+    }
+}
+
+/// How far the barbarians have sailed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BarbarianTrack {
+    /// Steps taken since the last attack
+    pub position: u32,
+    /// Steps from the start to the island
+    pub length: u32,
+    /// Attacks so far
+    pub attacks: u32,
+}
+
+impl BarbarianTrack {
+    /// Barbarians at the start of a track `length` steps long
+    pub fn new(length: u32) -> Self {
+        Self {
+            position: 0,
+            length,
+            attacks: 0,
+        }
+    }
+
+    /// Move one step. Returns true if the barbarians reached the island,
+    /// which sends them back to the start.
+    pub fn advance(&mut self) -> bool {
+        self.position += 1;
+        if self.position < self.length {
+            return false;
+        }
+        self.position = 0;
+        self.attacks += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_barbarians_attack_at_the_end_of_the_track() {
+        let mut track = BarbarianTrack::new(3);
+        assert!(!track.advance());
+        assert!(!track.advance());
+        assert!(track.advance());
+        assert_eq!((track.position, track.attacks), (0, 1));
+
+        // A track with no steps attacks on every ship
+        let mut short = BarbarianTrack::new(0);
+        assert!(short.advance());
+        assert!(short.advance());
+        assert_eq!(short.attacks, 2);
+    }
+}
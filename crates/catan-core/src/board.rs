@@ -7,11 +7,16 @@
 //! - Harbor trading bonuses
 //! - Board validation and query methods
 
-use crate::hex::{EdgeCoord, HexCoord, VertexCoord};
+use crate::arena::Arena;
+use crate::districts::DistrictPool;
+use crate::hex::{EdgeCoord, EdgeDirection, HexCoord, VertexCoord};
+use crate::scenario::{Scenario, ScenarioError, StartingPiece};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
 
 /// Player identifier (0-3 for a 4-player game)
 pub type PlayerId = u8;
@@ -24,7 +29,7 @@ pub type PlayerId = u8;
 /// - Ore: Jurong Industrial (heavy industry)
 /// - Grain: Hawker Centers (food culture)
 /// - Wool: Sentosa (leisure/tourism)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Resource {
     /// HDB estates - construction materials
     Brick,
@@ -48,7 +53,8 @@ impl Resource {
         Resource::Wool,
     ];
 
-    /// Singapore-themed name for this resource
+    /// Singapore-themed name for this resource; see
+    /// [`ThemePack`](crate::theme::ThemePack) for other names
     pub fn singapore_name(&self) -> &'static str {
         match self {
             Resource::Brick => "HDB Estate",
@@ -214,7 +220,7 @@ impl EdgeBuilding {
 }
 
 /// Harbor placement on the board
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HarborPlacement {
     /// The edge where ships dock
     pub edge: EdgeCoord,
@@ -222,19 +228,196 @@ pub struct HarborPlacement {
     pub harbor_type: Harbor,
 }
 
+/// Number of ways to roll a dice number with two dice (the "pips" on its token)
+pub fn dice_pips(number: u8) -> u32 {
+    match number {
+        2..=12 => 6 - (7 - number as i32).unsigned_abs(),
+        _ => 0,
+    }
+}
+
+/// Placement rules for randomly generated boards
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardGenConfig {
+    /// No 6 or 8 next to another 6 or 8
+    pub forbid_adjacent_6_8: bool,
+    /// No 2 or 12 next to another 2 or 12
+    pub forbid_adjacent_2_12: bool,
+    /// No two tiles of the same resource next to each other
+    pub forbid_same_resource_clusters: bool,
+    /// Maximum total pips of the tiles around any corner
+    pub max_vertex_pips: Option<u32>,
+    /// Rings of land around the center hex (2 for the standard 19-hex board)
+    pub radius: u32,
+    /// Layouts to try before settling for the one with the fewest violations
+    pub max_attempts: usize,
+    /// Names for the land tiles
+    pub districts: DistrictPool,
+}
+
+impl Default for BoardGenConfig {
+    fn default() -> Self {
+        Self {
+            forbid_adjacent_6_8: true,
+            forbid_adjacent_2_12: false,
+            forbid_same_resource_clusters: false,
+            max_vertex_pips: None,
+            radius: 2,
+            max_attempts: 100,
+            districts: DistrictPool::default(),
+        }
+    }
+}
+
+impl BoardGenConfig {
+    /// Count same-resource neighbors, if clusters are forbidden
+    fn resource_violations(&self, resources: &HashMap<HexCoord, Resource>) -> usize {
+        if !self.forbid_same_resource_clusters {
+            return 0;
+        }
+        resources
+            .iter()
+            .flat_map(|(coord, resource)| {
+                coord
+                    .neighbors()
+                    .into_iter()
+                    .filter(move |n| resources.get(n) == Some(resource))
+            })
+            .count()
+    }
+
+    /// Count number placements breaking the adjacency and pip rules
+    fn number_violations(&self, numbers: &HashMap<HexCoord, u8>) -> usize {
+        let mut violations = 0;
+
+        for (coord, &number) in numbers {
+            for neighbor in coord.neighbors() {
+                let Some(&other) = numbers.get(&neighbor) else {
+                    continue;
+                };
+                let both = |a: u8, b: u8| {
+                    (number == a || number == b) && (other == a || other == b)
+                };
+                if self.forbid_adjacent_6_8 && both(6, 8) {
+                    violations += 1;
+                }
+                if self.forbid_adjacent_2_12 && both(2, 12) {
+                    violations += 1;
+                }
+            }
+        }
+
+        if let Some(cap) = self.max_vertex_pips {
+            let corners: HashSet<VertexCoord> =
+                numbers.keys().flat_map(|coord| coord.vertices()).collect();
+            for corner in corners {
+                let pips: u32 = corner
+                    .touching_hexes()
+                    .iter()
+                    .filter_map(|hex| numbers.get(hex))
+                    .map(|&number| dice_pips(number))
+                    .sum();
+                if pips > cap {
+                    violations += 1;
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// A broken board invariant, reported by [`Board::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum BoardError {
+    #[error("Tile stored at {key:?} claims to be at {coord:?}")]
+    TileCoordMismatch { key: HexCoord, coord: HexCoord },
+
+    #[error("Robber is not on a land tile: {0:?}")]
+    RobberNotOnLand(HexCoord),
+
+    #[error("Tile robber flags don't match the robber location")]
+    RobberFlagMismatch,
+
+    #[error("Pirate is not on an ocean tile: {0:?}")]
+    PirateNotOnOcean(HexCoord),
+
+    #[error("Building is not on land: {0:?}")]
+    BuildingNotOnLand(VertexCoord),
+
+    #[error("Buildings too close together: {0:?} and {1:?}")]
+    DistanceRule(VertexCoord, VertexCoord),
+
+    #[error("Road is not on land: {0:?}")]
+    RoadNotOnLand(EdgeCoord),
+
+    #[error("Harbor is not on a coastal edge: {0:?}")]
+    HarborNotCoastal(EdgeCoord),
+}
+
+/// Which layout to generate for a new game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardTemplate {
+    /// Shuffled tiles, numbers and harbors
+    #[default]
+    Random,
+    /// The fixed layout recommended for first games
+    Beginner,
+}
+
 /// The complete game board
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
     /// All tiles indexed by coordinate
     tiles: HashMap<HexCoord, Tile>,
     /// Buildings on vertices
-    vertices: HashMap<VertexCoord, VertexBuilding>,
+    vertices: Arena<VertexCoord, VertexBuilding>,
     /// Roads on edges
-    edges: HashMap<EdgeCoord, EdgeBuilding>,
+    edges: Arena<EdgeCoord, EdgeBuilding>,
     /// Harbors on coastal edges
     harbors: Vec<HarborPlacement>,
-    /// Current robber location
-    robber_location: HexCoord,
+    /// Current robber location; None while the robber is off the board
+    robber_location: Option<HexCoord>,
+    /// Current pirate location (an ocean hex), when playing with the pirate
+    #[serde(default)]
+    pirate_location: Option<HexCoord>,
+    /// Compact location IDs, built on first use. They only depend on the
+    /// tile layout, so clones of the board share them.
+    #[serde(skip)]
+    location_ids: OnceLock<Arc<LocationIds>>,
+    /// Placement frontiers, built on first use and kept up to date by the
+    /// mutation methods after that
+    #[serde(skip)]
+    placement: OnceLock<PlacementCache>,
+}
+
+/// Compact ID of a land vertex on a particular board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct VertexId(pub u16);
+
+/// Compact ID of a land edge on a particular board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EdgeId(pub u16);
+
+/// Land vertices and edges in ID order, with the reverse lookups
+#[derive(Debug, Clone)]
+struct LocationIds {
+    vertices: Vec<VertexCoord>,
+    edges: Vec<EdgeCoord>,
+    vertex_ids: HashMap<VertexCoord, VertexId>,
+    edge_ids: HashMap<EdgeCoord, EdgeId>,
+}
+
+/// Where pieces can go, maintained incrementally as pieces are placed
+#[derive(Debug, Clone, Default)]
+struct PlacementCache {
+    /// Land vertices that are empty and clear of the distance rule
+    open_vertices: BTreeSet<VertexId>,
+    /// Per player: empty land edges that connect to their network
+    road_frontier: HashMap<PlayerId, BTreeSet<EdgeId>>,
+    /// Per player: land vertices at the end of one of their roads
+    road_ends: HashMap<PlayerId, BTreeSet<VertexId>>,
 }
 
 impl Board {
@@ -242,130 +425,273 @@ impl Board {
     pub fn new() -> Self {
         Self {
             tiles: HashMap::new(),
-            vertices: HashMap::new(),
-            edges: HashMap::new(),
+            vertices: Arena::new(),
+            edges: Arena::new(),
             harbors: Vec::new(),
-            robber_location: HexCoord::new(0, 0),
+            robber_location: Some(HexCoord::new(0, 0)),
+            pirate_location: None,
+            location_ids: OnceLock::new(),
+            placement: OnceLock::new(),
         }
     }
 
-    /// Create the standard Catan board layout with randomized tiles and numbers
-    pub fn standard() -> Self {
-        let mut rng = rand::thread_rng();
-        Self::standard_with_rng(&mut rng)
+    /// Create a board from a scenario's fixed layout, harbors and starting pieces
+    pub fn from_scenario(scenario: &Scenario) -> Result<Self, ScenarioError> {
+        let mut board = Self::new();
+
+        for spec in &scenario.tiles {
+            let coord = spec.coord;
+            if board.tiles.contains_key(&coord) {
+                return Err(ScenarioError::DuplicateTile(coord));
+            }
+            let tile = match spec.tile_type {
+                TileType::Resource(resource) => {
+                    let number = spec.dice_number.ok_or(ScenarioError::MissingNumber(coord))?;
+                    Tile::new_resource(coord, resource, number)
+                }
+                TileType::Desert => Tile {
+                    has_robber: false,
+                    ..Tile::desert(coord)
+                },
+                TileType::Ocean => Tile::ocean(coord),
+            };
+            board.tiles.insert(coord, tile);
+        }
+
+        for coord in board.get_ocean_ring() {
+            board.tiles.insert(coord, Tile::ocean(coord));
+        }
+
+        let robber = scenario.robber.or_else(|| {
+            scenario
+                .tiles
+                .iter()
+                .find(|t| t.tile_type == TileType::Desert)
+                .map(|t| t.coord)
+        });
+        match robber {
+            Some(robber) => {
+                if !board.get_tile(&robber).is_some_and(|t| t.tile_type != TileType::Ocean) {
+                    return Err(ScenarioError::RobberNotOnLand);
+                }
+                board.move_robber(robber);
+            }
+            // No desert to start on, so the robber waits off the board
+            None => board.remove_robber(),
+        }
+
+        board.harbors = scenario.harbors.clone();
+
+        for piece in &scenario.starting_pieces {
+            match *piece {
+                StartingPiece::Settlement { player, vertex } => {
+                    let vertex = vertex.canonical();
+                    if !board.is_land_vertex(&vertex) {
+                        return Err(ScenarioError::PieceNotOnLand);
+                    }
+                    board.place_settlement(vertex, player);
+                }
+                StartingPiece::City { player, vertex } => {
+                    let vertex = vertex.canonical();
+                    if !board.is_land_vertex(&vertex) {
+                        return Err(ScenarioError::PieceNotOnLand);
+                    }
+                    board.upgrade_to_city(vertex, player);
+                }
+                StartingPiece::Road { player, edge } => {
+                    let edge = edge.canonical();
+                    if !board.is_land_edge(&edge) {
+                        return Err(ScenarioError::PieceNotOnLand);
+                    }
+                    board.place_road(edge, player);
+                }
+            }
+        }
+
+        Ok(board)
     }
 
-    /// Create the standard Catan board layout with a provided RNG
-    /// This allows for deterministic board generation when needed
-    pub fn standard_with_rng<R: Rng>(rng: &mut R) -> Self {
+    /// Create a board for a template; random boards follow `config`
+    pub fn from_template(template: BoardTemplate, config: &BoardGenConfig) -> Self {
+        Self::from_template_with_rng(template, config, &mut rand::thread_rng())
+    }
+
+    /// Create a board for a template, shuffling random boards with `rng`
+    pub fn from_template_with_rng<R: Rng>(
+        template: BoardTemplate,
+        config: &BoardGenConfig,
+        rng: &mut R,
+    ) -> Self {
+        match template {
+            BoardTemplate::Random => Self::standard_with_config(config, rng),
+            BoardTemplate::Beginner => {
+                let mut board = Self::beginner();
+                board.name_districts(&config.districts, 2);
+                board
+            }
+        }
+    }
+
+    /// Label every unnamed land tile from `pool`, center first. `radius` is
+    /// the number of land rings, which decides the regions.
+    pub fn name_districts(&mut self, pool: &DistrictPool, radius: u32) {
+        let unnamed: Vec<HexCoord> = Self::land_spiral(radius)
+            .into_iter()
+            .filter(|coord| {
+                self.tiles
+                    .get(coord)
+                    .is_some_and(|t| t.tile_type != TileType::Ocean && t.label.is_none())
+            })
+            .collect();
+        for (coord, name) in unnamed.iter().zip(pool.names(&unnamed, radius)) {
+            if let Some(tile) = self.tiles.get_mut(coord) {
+                tile.label = name;
+            }
+        }
+    }
+
+    /// Create the fixed beginner layout from the base-game rulebook
+    pub fn beginner() -> Self {
+        use Resource::*;
+
         let mut board = Self::new();
 
-        // Standard Catan has 19 land hexes in a specific pattern
-        // Ring 0: center (1 hex)
-        // Ring 1: 6 hexes
-        // Ring 2: 12 hexes
-        let land_coords: Vec<HexCoord> = vec![
-            // Center
-            HexCoord::new(0, 0),
-            // Ring 1
-            HexCoord::new(1, 0),
-            HexCoord::new(1, -1),
-            HexCoord::new(0, -1),
-            HexCoord::new(-1, 0),
-            HexCoord::new(-1, 1),
-            HexCoord::new(0, 1),
-            // Ring 2
-            HexCoord::new(2, 0),
-            HexCoord::new(2, -1),
-            HexCoord::new(2, -2),
-            HexCoord::new(1, -2),
-            HexCoord::new(0, -2),
-            HexCoord::new(-1, -1),
-            HexCoord::new(-2, 0),
-            HexCoord::new(-2, 1),
-            HexCoord::new(-2, 2),
-            HexCoord::new(-1, 2),
-            HexCoord::new(0, 2),
-            HexCoord::new(1, 1),
+        // Rows from top to bottom (r = -2..=2), each listed west to east
+        let rows: [&[Option<(Resource, u8)>]; 5] = [
+            &[Some((Ore, 10)), Some((Wool, 2)), Some((Lumber, 9))],
+            &[Some((Grain, 12)), Some((Brick, 6)), Some((Wool, 4)), Some((Brick, 10))],
+            &[Some((Grain, 9)), Some((Lumber, 11)), None, Some((Lumber, 3)), Some((Ore, 8))],
+            &[Some((Lumber, 8)), Some((Ore, 3)), Some((Grain, 4)), Some((Wool, 5))],
+            &[Some((Brick, 5)), Some((Grain, 6)), Some((Wool, 11))],
         ];
 
-        // Standard Catan tile distribution:
-        // 4 Lumber (Wood), 4 Grain (Wheat), 4 Wool (Sheep), 3 Ore, 3 Brick, 1 Desert
-        let mut tile_types: Vec<Option<Resource>> = vec![
-            // 4 Lumber
-            Some(Resource::Lumber),
-            Some(Resource::Lumber),
-            Some(Resource::Lumber),
-            Some(Resource::Lumber),
-            // 4 Grain
-            Some(Resource::Grain),
-            Some(Resource::Grain),
-            Some(Resource::Grain),
-            Some(Resource::Grain),
-            // 4 Wool
-            Some(Resource::Wool),
-            Some(Resource::Wool),
-            Some(Resource::Wool),
-            Some(Resource::Wool),
-            // 3 Ore
-            Some(Resource::Ore),
-            Some(Resource::Ore),
-            Some(Resource::Ore),
-            // 3 Brick
-            Some(Resource::Brick),
-            Some(Resource::Brick),
-            Some(Resource::Brick),
-            // 1 Desert (represented as None)
-            None,
+        for (r, row) in (-2..=2).zip(rows) {
+            let first_q = (-2 - r).max(-2);
+            for (q, spec) in (first_q..).zip(row) {
+                let coord = HexCoord::new(q, r);
+                let tile = match spec {
+                    Some((resource, number)) => Tile::new_resource(coord, *resource, *number),
+                    None => {
+                        board.robber_location = Some(coord);
+                        Tile::desert(coord)
+                    }
+                };
+                board.tiles.insert(coord, tile);
+            }
+        }
+
+        for coord in board.get_ocean_ring() {
+            board.tiles.insert(coord, Tile::ocean(coord));
+        }
+
+        // Harbors clockwise from the top-left of the island
+        let harbors = [
+            (HexCoord::new(0, -2), EdgeDirection::NorthWest, Harbor::Generic),
+            (HexCoord::new(1, -2), EdgeDirection::NorthEast, Harbor::Specific(Wool)),
+            (HexCoord::new(2, -2), EdgeDirection::East, Harbor::Generic),
+            (HexCoord::new(2, 0), EdgeDirection::East, Harbor::Generic),
+            (HexCoord::new(1, 1), EdgeDirection::SouthEast, Harbor::Specific(Brick)),
+            (HexCoord::new(0, 2), EdgeDirection::SouthWest, Harbor::Specific(Lumber)),
+            (HexCoord::new(-2, 2), EdgeDirection::SouthWest, Harbor::Generic),
+            (HexCoord::new(-2, 1), EdgeDirection::West, Harbor::Specific(Grain)),
+            (HexCoord::new(-2, 0), EdgeDirection::NorthWest, Harbor::Specific(Ore)),
         ];
+        for (hex, direction, harbor_type) in harbors {
+            board.harbors.push(HarborPlacement {
+                edge: EdgeCoord::new(hex, direction),
+                harbor_type,
+            });
+        }
 
-        // Shuffle tile types randomly
-        tile_types.shuffle(rng);
+        board
+    }
 
-        // Standard dice number distribution (one of each except 2 and 12, two of 3-6 and 8-11)
-        // Numbers: 2, 3, 3, 4, 4, 5, 5, 6, 6, 8, 8, 9, 9, 10, 10, 11, 11, 12
-        let mut numbers: Vec<u8> = vec![2, 3, 3, 4, 4, 5, 5, 6, 6, 8, 8, 9, 9, 10, 10, 11, 11, 12];
+    /// Create the standard Catan board layout with randomized tiles and numbers
+    pub fn standard() -> Self {
+        let mut rng = rand::thread_rng();
+        Self::standard_with_rng(&mut rng)
+    }
 
-        // Shuffle numbers and assign to non-desert tiles
-        // We need to avoid placing 6 and 8 adjacent to each other
-        numbers.shuffle(rng);
+    /// Create the standard Catan board layout with a provided RNG
+    /// This allows for deterministic board generation when needed
+    pub fn standard_with_rng<R: Rng>(rng: &mut R) -> Self {
+        Self::standard_with_config(&BoardGenConfig::default(), rng)
+    }
 
-        // Find which positions are resource tiles (not desert)
-        let mut resource_positions: Vec<usize> = Vec::new();
-        let mut desert_position: Option<usize> = None;
+    /// Create the standard Catan board layout, reshuffling tiles and numbers
+    /// until the layout satisfies `config`. If no attempt does, the attempt
+    /// with the fewest violations is used, so the result only depends on the RNG.
+    pub fn standard_with_config<R: Rng>(config: &BoardGenConfig, rng: &mut R) -> Self {
+        let mut board = Self::new();
 
-        for (i, tile_type) in tile_types.iter().enumerate() {
-            if tile_type.is_some() {
-                resource_positions.push(i);
-            } else {
-                desert_position = Some(i);
+        // The standard board is radius 2: 19 land hexes in rings around the center
+        let land_coords = Self::land_spiral(config.radius);
+        let (mut tile_types, mut numbers) = Self::scaled_distribution(land_coords.len());
+
+        // Shuffle resources until they pass the configured rules, keeping the
+        // best attempt if none does
+        let mut best_resources: Option<(usize, Vec<Option<Resource>>)> = None;
+        for _ in 0..config.max_attempts.max(1) {
+            tile_types.shuffle(rng);
+            let resources = land_coords
+                .iter()
+                .zip(&tile_types)
+                .filter_map(|(coord, tile_type)| tile_type.map(|r| (*coord, r)))
+                .collect();
+
+            let violations = config.resource_violations(&resources);
+            if best_resources.as_ref().is_none_or(|(fewest, _)| violations < *fewest) {
+                best_resources = Some((violations, tile_types.clone()));
+            }
+            if violations == 0 {
+                break;
             }
         }
+        let (_, tile_types) = best_resources.unwrap();
 
-        // Try to assign numbers while avoiding 6 and 8 adjacency
-        // Use multiple attempts with reshuffling if needed
-        let number_assignment = board.assign_numbers_avoiding_adjacent_68(
-            &land_coords,
-            &resource_positions,
-            &numbers,
-            rng,
-        );
+        // Then numbers for the resource tiles, the same way
+        let resource_coords: Vec<HexCoord> = land_coords
+            .iter()
+            .zip(&tile_types)
+            .filter(|(_, tile_type)| tile_type.is_some())
+            .map(|(coord, _)| *coord)
+            .collect();
+        let mut best_numbers: Option<(usize, Vec<u8>)> = None;
+        for _ in 0..config.max_attempts.max(1) {
+            numbers.shuffle(rng);
+            let placement = resource_coords.iter().copied().zip(numbers.iter().copied()).collect();
+
+            let violations = config.number_violations(&placement);
+            if best_numbers.as_ref().is_none_or(|(fewest, _)| violations < *fewest) {
+                best_numbers = Some((violations, numbers.clone()));
+            }
+            if violations == 0 {
+                break;
+            }
+        }
+        let (_, numbers) = best_numbers.unwrap();
 
         // Place tiles on the board
-        for (i, coord) in land_coords.iter().enumerate() {
-            if Some(i) == desert_position {
-                let tile = Tile::desert(*coord);
-                board.robber_location = *coord;
-                board.tiles.insert(*coord, tile);
-            } else {
-                let resource = tile_types[i].unwrap();
-                let resource_idx = resource_positions.iter().position(|&x| x == i).unwrap();
-                let number = number_assignment[resource_idx];
-                board
-                    .tiles
-                    .insert(*coord, Tile::new_resource(*coord, resource, number));
+        let mut remaining_numbers = numbers.into_iter();
+        for (coord, tile_type) in land_coords.iter().zip(tile_types) {
+            match tile_type {
+                Some(resource) => {
+                    let number = remaining_numbers.next().unwrap();
+                    board
+                        .tiles
+                        .insert(*coord, Tile::new_resource(*coord, resource, number));
+                }
+                None => {
+                    board.robber_location = Some(*coord);
+                    board.tiles.insert(*coord, Tile::desert(*coord));
+                }
             }
         }
+        // Bigger boards have several deserts, but only the last one placed
+        // keeps the robber
+        for tile in board.tiles.values_mut() {
+            tile.has_robber = Some(tile.coord) == board.robber_location;
+        }
 
         // Add ocean tiles around the perimeter
         let ocean_coords = board.get_ocean_ring();
@@ -374,69 +700,69 @@ impl Board {
         }
 
         // Add standard harbors
-        board.add_standard_harbors();
+        board.add_standard_harbors_with_rng(rng);
 
+        board.name_districts(&config.districts, config.radius);
         board
     }
 
-    /// Assign dice numbers to positions while trying to avoid 6 and 8 being adjacent
-    fn assign_numbers_avoiding_adjacent_68<R: Rng>(
-        &self,
-        land_coords: &[HexCoord],
-        resource_positions: &[usize],
-        numbers: &[u8],
-        rng: &mut R,
-    ) -> Vec<u8> {
-        const MAX_ATTEMPTS: usize = 100;
+    /// Hexes within `radius` of the center, ring by ring, each ring walked
+    /// from its east corner
+    fn land_spiral(radius: u32) -> Vec<HexCoord> {
+        const STEPS: [(i32, i32); 6] = [(0, -1), (-1, 0), (-1, 1), (0, 1), (1, 0), (1, -1)];
 
-        for _ in 0..MAX_ATTEMPTS {
-            let mut shuffled_numbers = numbers.to_vec();
-            shuffled_numbers.shuffle(rng);
-
-            if self.is_valid_number_placement(land_coords, resource_positions, &shuffled_numbers) {
-                return shuffled_numbers;
+        let mut coords = vec![HexCoord::new(0, 0)];
+        for ring in 1..=radius as i32 {
+            let mut coord = HexCoord::new(ring, 0);
+            for (dq, dr) in STEPS {
+                for _ in 0..ring {
+                    coords.push(coord);
+                    coord = HexCoord::new(coord.q + dq, coord.r + dr);
+                }
             }
         }
+        coords
+    }
+
+    /// Tile types (None for desert) and dice numbers for a board with
+    /// `land_count` hexes, scaled from the standard 19-hex distribution:
+    /// one desert per 19 hexes, resources split 4:4:4:3:3, and whole sets
+    /// of the 18 standard numbers topped up with balanced pairs
+    fn scaled_distribution(land_count: usize) -> (Vec<Option<Resource>>, Vec<u8>) {
+        const SHARES: [(Resource, usize); 5] = [
+            (Resource::Lumber, 4),
+            (Resource::Grain, 4),
+            (Resource::Wool, 4),
+            (Resource::Ore, 3),
+            (Resource::Brick, 3),
+        ];
+        // The standard numbers ordered so that every prefix stays near 7 on average
+        const BALANCED_NUMBERS: [u8; 18] = [5, 9, 4, 10, 3, 11, 6, 8, 2, 12, 5, 9, 4, 10, 3, 11, 6, 8];
 
-        // If we couldn't find a valid placement after many attempts,
-        // return the last shuffle (this is rare but ensures we always return something)
-        let mut shuffled_numbers = numbers.to_vec();
-        shuffled_numbers.shuffle(rng);
-        shuffled_numbers
-    }
+        let deserts = ((land_count + 9) / 19).clamp(1, land_count);
+        let resource_count = land_count - deserts;
 
-    /// Check if a number placement is valid (no adjacent 6 and 8)
-    fn is_valid_number_placement(
-        &self,
-        land_coords: &[HexCoord],
-        resource_positions: &[usize],
-        numbers: &[u8],
-    ) -> bool {
-        // Build a map from coordinate to number
-        let mut coord_to_number: HashMap<HexCoord, u8> = HashMap::new();
-
-        for (resource_idx, &position_idx) in resource_positions.iter().enumerate() {
-            let coord = land_coords[position_idx];
-            let number = numbers[resource_idx];
-            coord_to_number.insert(coord, number);
-        }
-
-        // Check each tile with 6 or 8
-        for (coord, &number) in &coord_to_number {
-            if number == 6 || number == 8 {
-                // Check all neighbors
-                for neighbor in coord.neighbors() {
-                    if let Some(&neighbor_number) = coord_to_number.get(&neighbor) {
-                        // If neighbor is also 6 or 8, invalid
-                        if neighbor_number == 6 || neighbor_number == 8 {
-                            return false;
-                        }
-                    }
-                }
-            }
+        // Largest remainder split of the resource tiles
+        let mut counts: Vec<(Resource, usize, usize)> = SHARES
+            .iter()
+            .map(|&(r, share)| (r, resource_count * share / 18, resource_count * share % 18))
+            .collect();
+        let assigned: usize = counts.iter().map(|c| c.1).sum();
+        let mut by_remainder: Vec<usize> = (0..counts.len()).collect();
+        by_remainder.sort_by_key(|&i| std::cmp::Reverse(counts[i].2));
+        for &i in by_remainder.iter().take(resource_count - assigned) {
+            counts[i].1 += 1;
         }
 
-        true
+        let mut tile_types: Vec<Option<Resource>> = counts
+            .iter()
+            .flat_map(|&(r, n, _)| std::iter::repeat_n(Some(r), n))
+            .collect();
+        tile_types.extend(std::iter::repeat_n(None, deserts));
+
+        let numbers = BALANCED_NUMBERS.iter().copied().cycle().take(resource_count).collect();
+
+        (tile_types, numbers)
     }
 
     /// Get coordinates for ocean tiles surrounding the land
@@ -452,12 +778,6 @@ impl Board {
         ocean.into_iter().collect()
     }
 
-    /// Add standard harbors to the board with randomized positions
-    fn add_standard_harbors(&mut self) {
-        let mut rng = rand::thread_rng();
-        self.add_standard_harbors_with_rng(&mut rng);
-    }
-
     /// Add standard harbors with a provided RNG for deterministic placement
     fn add_standard_harbors_with_rng<R: Rng>(&mut self, rng: &mut R) {
         // Standard harbors: 4 generic (3:1) and 5 specific (2:1, one per resource)
@@ -466,28 +786,33 @@ impl Board {
         // Get all valid coastal edges (edges between land and ocean)
         let coastal_edges = self.get_coastal_edges();
 
-        // Create the harbor types we need to place
-        let mut harbor_types = vec![
-            Harbor::Generic,
-            Harbor::Generic,
-            Harbor::Generic,
+        // Create the harbor types we need to place, repeating the standard
+        // set of nine for every 30 coastal edges on larger boards
+        let harbor_count = (coastal_edges.len() * 9 + 15) / 30;
+        let standard_types = [
             Harbor::Generic,
             Harbor::Specific(Resource::Brick),
+            Harbor::Generic,
             Harbor::Specific(Resource::Lumber),
+            Harbor::Generic,
             Harbor::Specific(Resource::Ore),
+            Harbor::Generic,
             Harbor::Specific(Resource::Grain),
             Harbor::Specific(Resource::Wool),
         ];
+        let mut harbor_types: Vec<Harbor> =
+            standard_types.iter().copied().cycle().take(harbor_count).collect();
 
         // Shuffle harbor types
         harbor_types.shuffle(rng);
 
-        // Select 9 coastal edges that are spread around the board
+        // Select coastal edges that are spread around the board
         // We want them distributed, not all clustered together
-        let selected_edges = self.select_distributed_coastal_edges(&coastal_edges, 9, rng);
+        let selected_edges =
+            self.select_distributed_coastal_edges(&coastal_edges, harbor_count, rng);
 
         // Assign harbor types to the selected edges
-        for (edge, harbor_type) in selected_edges.into_iter().zip(harbor_types.into_iter()) {
+        for (edge, harbor_type) in selected_edges.into_iter().zip(harbor_types) {
             self.harbors.push(HarborPlacement { edge, harbor_type });
         }
     }
@@ -519,6 +844,8 @@ impl Board {
             }
         }
 
+        // Tiles come out of a HashMap; fix the order so seeded harbors repeat
+        coastal.sort_by_key(|e| (e.hex.r, e.hex.q, e.direction as u8));
         coastal
     }
 
@@ -588,19 +915,36 @@ impl Board {
 
     /// Get building at a vertex
     pub fn get_vertex(&self, coord: &VertexCoord) -> VertexBuilding {
-        self.vertices.get(coord).copied().unwrap_or_default()
+        self.vertices.get(coord)
     }
 
     /// Get road at an edge
     pub fn get_edge(&self, coord: &EdgeCoord) -> EdgeBuilding {
-        self.edges.get(coord).copied().unwrap_or_default()
+        self.edges.get(coord)
     }
 
-    /// Get the robber's current location
-    pub fn robber_location(&self) -> HexCoord {
+    /// Get the robber's current location, if it is on the board
+    pub fn robber_location(&self) -> Option<HexCoord> {
         self.robber_location
     }
 
+    /// Get the pirate's current location, if the pirate is in play
+    pub fn pirate_location(&self) -> Option<HexCoord> {
+        self.pirate_location
+    }
+
+    /// Get all ocean tiles
+    pub fn ocean_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles.values().filter(|t| matches!(t.tile_type, TileType::Ocean))
+    }
+
+    /// Check if an edge borders the pirate's hex. Ships can't be built or
+    /// moved on these edges.
+    pub fn is_edge_blocked_by_pirate(&self, edge: &EdgeCoord) -> bool {
+        self.pirate_location
+            .is_some_and(|pirate| edge.touching_hexes().contains(&pirate))
+    }
+
     /// Get all vertices that are on land (adjacent to at least one land tile)
     pub fn land_vertices(&self) -> HashSet<VertexCoord> {
         let mut vertices = HashSet::new();
@@ -623,6 +967,54 @@ impl Board {
         edges
     }
 
+    /// Number every land vertex and edge in reading order (top row first,
+    /// west to east), which depends only on the tile layout
+    fn location_ids(&self) -> &LocationIds {
+        self.location_ids.get_or_init(|| {
+            let mut vertices: Vec<VertexCoord> = self.land_vertices().into_iter().collect();
+            vertices.sort_by_key(|v| (v.hex.r, v.hex.q, v.direction as u8));
+            let mut edges: Vec<EdgeCoord> = self.land_edges().into_iter().collect();
+            edges.sort_by_key(|e| (e.hex.r, e.hex.q, e.direction as u8));
+
+            Arc::new(LocationIds {
+                vertex_ids: (0..).map(VertexId).zip(&vertices).map(|(id, v)| (*v, id)).collect(),
+                edge_ids: (0..).map(EdgeId).zip(&edges).map(|(id, e)| (*e, id)).collect(),
+                vertices,
+                edges,
+            })
+        })
+    }
+
+    /// Get the compact ID of a land vertex
+    pub fn vertex_id(&self, vertex: &VertexCoord) -> Option<VertexId> {
+        self.location_ids().vertex_ids.get(vertex).copied()
+    }
+
+    /// Get the vertex with a compact ID
+    pub fn vertex_at(&self, id: VertexId) -> Option<VertexCoord> {
+        self.location_ids().vertices.get(id.0 as usize).copied()
+    }
+
+    /// Get the compact ID of a land edge
+    pub fn edge_id(&self, edge: &EdgeCoord) -> Option<EdgeId> {
+        self.location_ids().edge_ids.get(&edge.canonical()).copied()
+    }
+
+    /// Get the edge with a compact ID
+    pub fn edge_at(&self, id: EdgeId) -> Option<EdgeCoord> {
+        self.location_ids().edges.get(id.0 as usize).copied()
+    }
+
+    /// All land vertices, indexed by `VertexId`
+    pub fn vertices_by_id(&self) -> &[VertexCoord] {
+        &self.location_ids().vertices
+    }
+
+    /// All land edges, indexed by `EdgeId`
+    pub fn edges_by_id(&self) -> &[EdgeCoord] {
+        &self.location_ids().edges
+    }
+
     /// Get tiles adjacent to a vertex
     pub fn tiles_at_vertex(&self, vertex: &VertexCoord) -> Vec<&Tile> {
         vertex
@@ -633,17 +1025,20 @@ impl Board {
             .collect()
     }
 
+    /// Every harbor on the board
+    pub fn harbors(&self) -> &[HarborPlacement] {
+        &self.harbors
+    }
+
     /// Get all harbors a player has access to (through their buildings)
     pub fn player_harbors(&self, player: PlayerId) -> Vec<Harbor> {
         let mut harbors = Vec::new();
         for harbor in &self.harbors {
             let endpoints = harbor.edge.endpoints();
             for endpoint in endpoints {
-                if let Some(building) = self.vertices.get(&endpoint) {
-                    if building.owner() == Some(player) {
-                        harbors.push(harbor.harbor_type);
-                        break;
-                    }
+                if self.vertices.get(&endpoint).owner() == Some(player) {
+                    harbors.push(harbor.harbor_type);
+                    break;
                 }
             }
         }
@@ -677,21 +1072,203 @@ impl Board {
             .any(|h| self.tiles.get(h).is_some_and(|t| !matches!(t.tile_type, TileType::Ocean)))
     }
 
-    /// Get valid settlement spots for a player
-    pub fn valid_settlement_spots(&self, player: PlayerId, is_setup: bool) -> Vec<VertexCoord> {
-        self.land_vertices()
-            .into_iter()
+    /// Expected production at a vertex: the pips of the adjacent resource
+    /// tiles, ignoring the robber
+    pub fn vertex_pip_count(&self, vertex: &VertexCoord) -> u32 {
+        self.vertex_pips(vertex, None)
+    }
+
+    /// Pips of one resource at a vertex, ignoring the robber
+    pub fn vertex_resource_pips(&self, vertex: &VertexCoord, resource: Resource) -> u32 {
+        self.vertex_pips(vertex, Some(resource))
+    }
+
+    /// The harbor a settlement on `vertex` would use, if any
+    pub fn harbor_at(&self, vertex: &VertexCoord) -> Option<Harbor> {
+        self.harbors
+            .iter()
+            .find(|harbor| harbor.edge.endpoints().contains(vertex))
+            .map(|harbor| harbor.harbor_type)
+    }
+
+    /// The `n` open settlement spots (empty and clear of the distance rule,
+    /// whoever could reach them) with the most pips, best first. Equal spots
+    /// keep `VertexId` order.
+    pub fn best_open_vertices(&self, n: usize) -> Vec<(VertexCoord, u32)> {
+        let mut spots: Vec<(VertexCoord, u32)> = self
+            .vertices_by_id()
+            .iter()
             .filter(|v| {
-                // Must be empty
-                self.get_vertex(v) == VertexBuilding::Empty
-                    // Must satisfy distance rule
-                    && self.satisfies_distance_rule(v)
-                    // During normal play, must be connected to player's road
-                    && (is_setup || self.is_connected_to_road(v, player))
+                self.get_vertex(v) == VertexBuilding::Empty && self.satisfies_distance_rule(v)
             })
+            .map(|v| (*v, self.vertex_pip_count(v)))
+            .collect();
+        spots.sort_by_key(|&(_, pips)| std::cmp::Reverse(pips));
+        spots.truncate(n);
+        spots
+    }
+
+    /// Get valid settlement spots for a player, in `VertexId` order
+    pub fn valid_settlement_spots(&self, player: PlayerId, is_setup: bool) -> Vec<VertexCoord> {
+        let cache = self.placement();
+        let spots: Vec<VertexId> = if is_setup {
+            cache.open_vertices.iter().copied().collect()
+        } else {
+            cache
+                .road_ends
+                .get(&player)
+                .into_iter()
+                .flatten()
+                .filter(|id| cache.open_vertices.contains(id))
+                .copied()
+                .collect()
+        };
+        spots.into_iter().filter_map(|id| self.vertex_at(id)).collect()
+    }
+
+    /// Get valid road spots for a player, in `EdgeId` order
+    pub fn valid_road_spots(&self, player: PlayerId) -> Vec<EdgeCoord> {
+        self.placement()
+            .road_frontier
+            .get(&player)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.edge_at(*id))
             .collect()
     }
 
+    /// Every pair of roads a Road Building card could place: two frontier
+    /// edges, or a frontier edge and an edge continuing from it. Each pair
+    /// appears once, the frontier edge first.
+    pub fn road_building_pairs(&self, player: PlayerId) -> Vec<(EdgeCoord, EdgeCoord)> {
+        let frontier = self.valid_road_spots(player);
+        let mut pairs = Vec::new();
+        for (i, first) in frontier.iter().enumerate() {
+            for second in &frontier[i + 1..] {
+                pairs.push((*first, *second));
+            }
+
+            // Edges only reachable through `first`, past endpoints no
+            // opponent has built on
+            let mut extensions: Vec<EdgeCoord> = first
+                .endpoints()
+                .iter()
+                .filter(|end| self.get_vertex(end).owner().is_none_or(|owner| owner == player))
+                .flat_map(|end| end.touching_edges())
+                .filter(|edge| {
+                    edge != first
+                        && !frontier.contains(edge)
+                        && self.is_land_edge(edge)
+                        && self.get_edge(edge) == EdgeBuilding::Empty
+                })
+                .collect();
+            extensions.sort_by_key(|edge| self.edge_id(edge));
+            extensions.dedup();
+            pairs.extend(extensions.into_iter().map(|second| (*first, second)));
+        }
+        pairs
+    }
+
+    fn placement(&self) -> &PlacementCache {
+        self.placement.get_or_init(|| {
+            let mut players: BTreeSet<PlayerId> =
+                self.vertices.iter().filter_map(|(_, b)| b.owner()).collect();
+            players.extend(self.edges.iter().filter_map(|(_, b)| b.owner()));
+
+            let mut cache = PlacementCache {
+                open_vertices: self
+                    .vertices_by_id()
+                    .iter()
+                    .filter(|v| self.is_open_vertex(v))
+                    .filter_map(|v| self.vertex_id(v))
+                    .collect(),
+                ..PlacementCache::default()
+            };
+            for player in players {
+                cache.road_frontier.insert(
+                    player,
+                    self.scan_road_spots(player)
+                        .iter()
+                        .filter_map(|e| self.edge_id(e))
+                        .collect(),
+                );
+                cache.road_ends.insert(
+                    player,
+                    self.vertices_by_id()
+                        .iter()
+                        .filter(|v| self.is_connected_to_road(v, player))
+                        .filter_map(|v| self.vertex_id(v))
+                        .collect(),
+                );
+            }
+            cache
+        })
+    }
+
+    /// Update the placement cache (if built) after `player` put a piece on
+    /// `vertex` or `edge`, rechecking only the nearby spots
+    fn refresh_placement(
+        &mut self,
+        player: PlayerId,
+        vertex: Option<VertexCoord>,
+        edge: Option<EdgeCoord>,
+    ) {
+        let Some(mut cache) = self.placement.take() else {
+            return;
+        };
+
+        // Vertices whose openness may have changed, and edges whose
+        // connection to some network may have changed
+        let mut vertices: Vec<VertexCoord> = Vec::new();
+        let mut edges: Vec<EdgeCoord> = Vec::new();
+        if let Some(vertex) = vertex {
+            vertices.push(vertex);
+            vertices.extend(vertex.adjacent_vertices());
+            edges.extend(vertex.touching_edges());
+        }
+        if let Some(edge) = edge {
+            edges.push(edge);
+            for end in edge.endpoints() {
+                edges.extend(end.touching_edges());
+                if let Some(id) = self.vertex_id(&end) {
+                    cache.road_ends.entry(player).or_default().insert(id);
+                }
+            }
+        }
+
+        for vertex in vertices {
+            if let Some(id) = self.vertex_id(&vertex) {
+                if self.is_open_vertex(&vertex) {
+                    cache.open_vertices.insert(id);
+                } else {
+                    cache.open_vertices.remove(&id);
+                }
+            }
+        }
+
+        cache.road_frontier.entry(player).or_default();
+        for edge in edges {
+            let Some(id) = self.edge_id(&edge) else {
+                continue;
+            };
+            let empty = self.get_edge(&edge) == EdgeBuilding::Empty;
+            for (owner, frontier) in cache.road_frontier.iter_mut() {
+                if empty && self.is_connected_to_network(&edge, *owner) {
+                    frontier.insert(id);
+                } else {
+                    frontier.remove(&id);
+                }
+            }
+        }
+
+        self.placement = OnceLock::from(cache);
+    }
+
+    /// Whether a vertex is empty and clear of the distance rule
+    fn is_open_vertex(&self, vertex: &VertexCoord) -> bool {
+        self.get_vertex(vertex) == VertexBuilding::Empty && self.satisfies_distance_rule(vertex)
+    }
+
     /// Check if a vertex is connected to a player's road network
     fn is_connected_to_road(&self, vertex: &VertexCoord, player: PlayerId) -> bool {
         for edge in vertex.touching_edges() {
@@ -702,8 +1279,8 @@ impl Board {
         false
     }
 
-    /// Get valid road spots for a player
-    pub fn valid_road_spots(&self, player: PlayerId) -> Vec<EdgeCoord> {
+    /// Road spots for a player, found by scanning every land edge
+    fn scan_road_spots(&self, player: PlayerId) -> Vec<EdgeCoord> {
         self.land_edges()
             .into_iter()
             .filter(|e| {
@@ -740,8 +1317,8 @@ impl Board {
         self.vertices
             .iter()
             .filter_map(|(coord, building)| {
-                if *building == VertexBuilding::Settlement(player) {
-                    Some(*coord)
+                if building == VertexBuilding::Settlement(player) {
+                    Some(coord)
                 } else {
                     None
                 }
@@ -753,30 +1330,59 @@ impl Board {
 
     /// Place a settlement (assumes validation already done)
     pub fn place_settlement(&mut self, vertex: VertexCoord, player: PlayerId) {
-        self.vertices.insert(vertex, VertexBuilding::Settlement(player));
+        self.fit_pieces(vertex.hex);
+        self.vertices.set(vertex, VertexBuilding::Settlement(player));
+        self.refresh_placement(player, Some(vertex), None);
     }
 
     /// Upgrade a settlement to a city
     pub fn upgrade_to_city(&mut self, vertex: VertexCoord, player: PlayerId) {
-        self.vertices.insert(vertex, VertexBuilding::City(player));
+        self.fit_pieces(vertex.hex);
+        self.vertices.set(vertex, VertexBuilding::City(player));
+        self.refresh_placement(player, Some(vertex), None);
     }
 
     /// Place a road
     pub fn place_road(&mut self, edge: EdgeCoord, player: PlayerId) {
-        self.edges.insert(edge, EdgeBuilding::Road(player));
+        self.fit_pieces(edge.hex);
+        self.edges.set(edge, EdgeBuilding::Road(player));
+        self.refresh_placement(player, None, Some(edge));
+    }
+
+    /// Lay the piece arenas out over the whole board (plus `hex`) the first
+    /// time a piece goes down, so IDs don't change for the rest of the game
+    fn fit_pieces(&mut self, hex: HexCoord) {
+        if !self.vertices.covers(hex) || !self.edges.covers(hex) {
+            let hexes: Vec<HexCoord> = self.tiles.keys().copied().chain([hex]).collect();
+            self.vertices.refit(hexes.iter().copied());
+            self.edges.refit(hexes);
+        }
     }
 
     /// Move the robber to a new location
     pub fn move_robber(&mut self, new_location: HexCoord) {
-        // Remove robber from old location
-        if let Some(tile) = self.tiles.get_mut(&self.robber_location) {
-            tile.has_robber = false;
-        }
+        self.remove_robber();
         // Place robber at new location
         if let Some(tile) = self.tiles.get_mut(&new_location) {
             tile.has_robber = true;
         }
-        self.robber_location = new_location;
+        self.robber_location = Some(new_location);
+    }
+
+    /// Take the robber off the board
+    pub fn remove_robber(&mut self) {
+        if let Some(tile) = self
+            .robber_location
+            .take()
+            .and_then(|old| self.tiles.get_mut(&old))
+        {
+            tile.has_robber = false;
+        }
+    }
+
+    /// Move the pirate to an ocean hex
+    pub fn move_pirate(&mut self, new_location: HexCoord) {
+        self.pirate_location = Some(new_location);
     }
 
     // ==================== Resource Distribution ====================
@@ -833,8 +1439,8 @@ impl Board {
             .edges
             .iter()
             .filter_map(|(coord, building)| {
-                if *building == EdgeBuilding::Road(player) {
-                    Some(*coord)
+                if building == EdgeBuilding::Road(player) {
+                    Some(coord)
                 } else {
                     None
                 }
@@ -902,59 +1508,438 @@ impl Board {
                 tile_type: tile.tile_type,
                 dice_number: tile.dice_number,
                 has_robber: tile.has_robber,
+                label: tile.label.clone(),
             }).collect(),
-            vertices: self.vertices.iter().filter_map(|(coord, building)| {
-                if *building == VertexBuilding::Empty {
-                    None
-                } else {
-                    Some(VertexJson {
-                        hex_q: coord.hex.q,
-                        hex_r: coord.hex.r,
-                        direction: coord.direction,
-                        building: *building,
-                    })
-                }
+            vertices: self.vertices.iter().map(|(coord, building)| VertexJson {
+                hex_q: coord.hex.q,
+                hex_r: coord.hex.r,
+                direction: coord.direction,
+                building,
             }).collect(),
-            edges: self.edges.iter().filter_map(|(coord, building)| {
-                if *building == EdgeBuilding::Empty {
-                    None
-                } else {
-                    Some(EdgeJson {
-                        hex_q: coord.hex.q,
-                        hex_r: coord.hex.r,
-                        direction: coord.direction,
-                        building: *building,
-                    })
-                }
+            edges: self.edges.iter().map(|(coord, building)| EdgeJson {
+                hex_q: coord.hex.q,
+                hex_r: coord.hex.r,
+                direction: coord.direction,
+                building,
             }).collect(),
             harbors: self.harbors.clone(),
-            robber_q: self.robber_location.q,
-            robber_r: self.robber_location.r,
+            robber: self.robber_location,
+            pirate: self.pirate_location,
+        }
+    }
+
+    /// Pixel positions of every tile, land vertex and land edge together with
+    /// what sits on them, for clients that draw the board. Hexes are pointy-top
+    /// with radius `hex_size`, and the center hex lands on the layout origin.
+    pub fn render_geometry(&self, hex_size: f64, layout: RenderLayout) -> BoardGeometry {
+        let point = |(x, y): (f64, f64)| (x + layout.origin_x, y + layout.origin_y);
+
+        let mut tiles: Vec<&Tile> = self.tiles.values().collect();
+        tiles.sort_by_key(|t| (t.coord.r, t.coord.q));
+        let hexes = tiles
+            .into_iter()
+            .map(|tile| {
+                let (x, y) = point(tile.coord.to_pixel(hex_size));
+                HexGeometry {
+                    coord: tile.coord,
+                    x,
+                    y,
+                    corners: tile.coord.vertices().map(|v| point(v.to_pixel(hex_size))),
+                    tile_type: tile.tile_type,
+                    dice_number: tile.dice_number,
+                    has_robber: tile.has_robber,
+                    has_pirate: self.pirate_location == Some(tile.coord),
+                }
+            })
+            .collect();
+
+        let vertices = self
+            .vertices_by_id()
+            .iter()
+            .enumerate()
+            .map(|(id, vertex)| {
+                let (x, y) = point(vertex.to_pixel(hex_size));
+                VertexGeometry {
+                    id: VertexId(id as u16),
+                    coord: *vertex,
+                    x,
+                    y,
+                    building: self.get_vertex(vertex),
+                }
+            })
+            .collect();
+
+        let edges = self
+            .edges_by_id()
+            .iter()
+            .enumerate()
+            .map(|(id, edge)| {
+                let [a, b] = edge.endpoints().map(|v| point(v.to_pixel(hex_size)));
+                EdgeGeometry {
+                    id: EdgeId(id as u16),
+                    coord: *edge,
+                    from: a,
+                    to: b,
+                    building: self.get_edge(edge),
+                    harbor: self
+                        .harbors
+                        .iter()
+                        .find(|h| h.edge == *edge)
+                        .map(|h| h.harbor_type),
+                }
+            })
+            .collect();
+
+        BoardGeometry {
+            hex_size,
+            hexes,
+            vertices,
+            edges,
+        }
+    }
+
+    /// Draw the board as text, for debugging and logs. Hexes are pointy-top,
+    /// nine columns wide and five rows tall, sharing their sides:
+    ///
+    /// ```text
+    ///   / . \
+    /// .       .
+    /// |Lum  8 |
+    /// . \   / .
+    ///     .
+    /// ```
+    ///
+    /// Tiles show a short name and their number, with `R` and `P` under it
+    /// for the robber and pirate. Harbors are labelled in the sea beside
+    /// them. A settlement is its owner's seat as a letter (`a` for seat 0),
+    /// a city the same letter in capitals, and a road the seat's digit.
+    pub fn render_ascii(&self) -> String {
+        fn center(hex: HexCoord) -> (i32, i32) {
+            (8 * hex.q + 4 * hex.r, 3 * hex.r)
+        }
+        fn seat_char(base: u8, player: PlayerId) -> char {
+            (base + player) as char
+        }
+
+        let centers: Vec<(i32, i32)> = self.tiles.keys().map(|h| center(*h)).collect();
+        let left = centers.iter().map(|c| c.0).min().unwrap_or(0) - 4;
+        let right = centers.iter().map(|c| c.0).max().unwrap_or(0) + 4;
+        let top = centers.iter().map(|c| c.1).min().unwrap_or(0) - 2;
+        let bottom = centers.iter().map(|c| c.1).max().unwrap_or(0) + 2;
+        let mut grid = vec![vec![' '; (right - left + 1) as usize]; (bottom - top + 1) as usize];
+        let mut put = |x: i32, y: i32, text: &str| {
+            for (i, c) in text.chars().enumerate() {
+                grid[(y - top) as usize][(x - left) as usize + i] = c;
+            }
+        };
+
+        for tile in self.tiles.values() {
+            let (x, y) = center(tile.coord);
+            let name = match tile.tile_type {
+                TileType::Resource(Resource::Brick) => "Bri",
+                TileType::Resource(Resource::Lumber) => "Lum",
+                TileType::Resource(Resource::Ore) => "Ore",
+                TileType::Resource(Resource::Grain) => "Gra",
+                TileType::Resource(Resource::Wool) => "Woo",
+                TileType::Desert => "Des",
+                TileType::Ocean => "~~~",
+            };
+            put(x - 3, y, name);
+            if let Some(number) = tile.dice_number {
+                put(x + 1, y, &format!("{:>2}", number));
+            }
+            if tile.has_robber {
+                put(x - 1, y + 1, "R");
+            }
+            if self.pirate_location == Some(tile.coord) {
+                put(x - 1, y + 1, "P");
+            }
+        }
+
+        for harbor in &self.harbors {
+            let sea = harbor.edge.touching_hexes().into_iter().find(|h| {
+                self.tiles.get(h).is_some_and(|t| t.tile_type == TileType::Ocean)
+            });
+            if let Some(sea) = sea {
+                let (x, y) = center(sea);
+                let label = match harbor.harbor_type {
+                    Harbor::Generic => "3:1".to_string(),
+                    Harbor::Specific(r) => format!("2:{}", &format!("{:?}", r)[..2]),
+                };
+                put(x - 2, y - 1, &label);
+            }
+        }
+
+        // Every hex names a shared side or corner the same way, so each is
+        // drawn once
+        for edge in self.land_edges() {
+            let (x, y) = center(edge.hex);
+            let (x, y, side) = match edge.direction {
+                EdgeDirection::NorthEast => (x + 2, y - 2, '\\'),
+                EdgeDirection::East => (x + 4, y, '|'),
+                EdgeDirection::SouthEast => (x + 2, y + 1, '/'),
+                EdgeDirection::SouthWest => (x - 2, y + 1, '\\'),
+                EdgeDirection::West => (x - 4, y, '|'),
+                EdgeDirection::NorthWest => (x - 2, y - 2, '/'),
+            };
+            let c = match self.get_edge(&edge) {
+                EdgeBuilding::Road(p) => seat_char(b'0', p),
+                EdgeBuilding::Empty => side,
+            };
+            put(x, y, &c.to_string());
+        }
+        for vertex in self.land_vertices() {
+            let (x, y) = center(vertex.hex);
+            let y = match vertex.direction {
+                crate::hex::VertexDirection::North => y - 2,
+                crate::hex::VertexDirection::South => y + 2,
+            };
+            let c = match self.get_vertex(&vertex) {
+                VertexBuilding::Settlement(p) => seat_char(b'a', p),
+                VertexBuilding::City(p) => seat_char(b'A', p),
+                VertexBuilding::Empty => '.',
+            };
+            put(x, y, &c.to_string());
         }
+
+        // The sea's outer rows are left blank, so they are dropped
+        let lines: Vec<String> = grid
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>().trim_end().to_string())
+            .collect();
+        let first = lines.iter().position(|l| !l.is_empty()).unwrap_or(0);
+        let last = lines.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+        let mut text = String::new();
+        for line in &lines[first..last.max(first)] {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Check the board's invariants, collecting every violation. Run this on
+    /// boards deserialized from untrusted input before using them.
+    pub fn validate(&self) -> Result<(), Vec<BoardError>> {
+        let mut errors = Vec::new();
+
+        for (key, tile) in &self.tiles {
+            if *key != tile.coord {
+                errors.push(BoardError::TileCoordMismatch {
+                    key: *key,
+                    coord: tile.coord,
+                });
+            }
+        }
+
+        if let Some(robber) = self.robber_location {
+            if !self.tiles.get(&robber).is_some_and(|t| t.tile_type != TileType::Ocean) {
+                errors.push(BoardError::RobberNotOnLand(robber));
+            }
+        }
+        if self
+            .tiles
+            .values()
+            .any(|t| t.has_robber != (Some(t.coord) == self.robber_location))
+        {
+            errors.push(BoardError::RobberFlagMismatch);
+        }
+
+        if let Some(pirate) = self.pirate_location {
+            if !self.tiles.get(&pirate).is_some_and(|t| t.tile_type == TileType::Ocean) {
+                errors.push(BoardError::PirateNotOnOcean(pirate));
+            }
+        }
+
+        // Report each too-close pair once, from its first vertex
+        let order = |v: &VertexCoord| {
+            (v.hex.q, v.hex.r, v.direction == crate::hex::VertexDirection::South)
+        };
+        for (vertex, _) in self.vertices.iter() {
+            if !self.is_land_vertex(&vertex) {
+                errors.push(BoardError::BuildingNotOnLand(vertex));
+            }
+            for adj in vertex.adjacent_vertices() {
+                if order(&adj) > order(&vertex) && self.get_vertex(&adj).owner().is_some() {
+                    errors.push(BoardError::DistanceRule(vertex, adj));
+                }
+            }
+        }
+
+        for (edge, _) in self.edges.iter() {
+            if !self.is_land_edge(&edge) {
+                errors.push(BoardError::RoadNotOnLand(edge));
+            }
+        }
+
+        let coastal = self.get_coastal_edges();
+        for harbor in &self.harbors {
+            if !coastal.contains(&harbor.edge) {
+                errors.push(BoardError::HarborNotCoastal(harbor.edge));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Measure how fair the layout is: pips per resource, the richest
+    /// corners, and 2:1 harbors sitting next to their own resource.
+    /// Hosts can show the score or regenerate boards that score poorly.
+    pub fn balance_report(&self) -> BalanceReport {
+        let resources: Vec<ResourceBalance> = Resource::ALL
+            .iter()
+            .map(|&resource| {
+                let tiles: Vec<&Tile> = self
+                    .land_tiles()
+                    .filter(|t| t.resource() == Some(resource))
+                    .collect();
+                let pips = tiles
+                    .iter()
+                    .filter_map(|t| t.dice_number)
+                    .map(dice_pips)
+                    .sum();
+                ResourceBalance {
+                    resource,
+                    tiles: tiles.len() as u32,
+                    pips,
+                }
+            })
+            .collect();
+
+        let mut vertex_pips: Vec<(VertexCoord, u32)> = self
+            .land_vertices()
+            .into_iter()
+            .map(|vertex| (vertex, self.vertex_pips(&vertex, None)))
+            .collect();
+        vertex_pips.sort_by_key(|&(vertex, pips)| {
+            let south = vertex.direction == crate::hex::VertexDirection::South;
+            (std::cmp::Reverse(pips), vertex.hex.q, vertex.hex.r, south)
+        });
+        vertex_pips.truncate(5);
+
+        let harbor_alignment: Vec<HarborAlignment> = self
+            .harbors
+            .iter()
+            .filter_map(|h| match h.harbor_type {
+                Harbor::Specific(resource) => Some((h, resource)),
+                Harbor::Generic => None,
+            })
+            .map(|(harbor, resource)| HarborAlignment {
+                edge: harbor.edge,
+                resource,
+                pips: harbor
+                    .edge
+                    .endpoints()
+                    .iter()
+                    .map(|v| self.vertex_pips(v, Some(resource)))
+                    .max()
+                    .unwrap_or(0),
+            })
+            .collect();
+
+        // Evenness: how close each resource's pips per tile are to each other
+        let per_tile: Vec<f64> = resources
+            .iter()
+            .filter(|r| r.tiles > 0)
+            .map(|r| r.pips as f64 / r.tiles as f64)
+            .collect();
+        let mean = per_tile.iter().sum::<f64>() / per_tile.len().max(1) as f64;
+        let spread = per_tile.iter().cloned().fold(f64::MIN, f64::max)
+            - per_tile.iter().cloned().fold(f64::MAX, f64::min);
+        let evenness = if mean > 0.0 {
+            (1.0 - spread / mean).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        // Concentration: corners above 10 pips get steadily worse up to 15
+        let best_vertex = vertex_pips.first().map_or(0, |&(_, pips)| pips);
+        let concentration = 1.0 - (best_vertex.saturating_sub(10) as f64 / 5.0).min(1.0);
+
+        // Alignment: a 2:1 harbor touching 10+ pips of its resource is a strong combo
+        let best_alignment = harbor_alignment.iter().map(|h| h.pips).max().unwrap_or(0);
+        let alignment = 1.0 - (best_alignment as f64 / 10.0).min(1.0);
+
+        BalanceReport {
+            resources,
+            top_vertices: vertex_pips,
+            harbor_alignment,
+            score: 100.0 * (0.5 * evenness + 0.3 * concentration + 0.2 * alignment),
+        }
+    }
+
+    /// Total pips of the productive tiles around a vertex, optionally only
+    /// counting one resource
+    fn vertex_pips(&self, vertex: &VertexCoord, resource: Option<Resource>) -> u32 {
+        vertex
+            .touching_hexes()
+            .iter()
+            .filter_map(|hex| self.tiles.get(hex))
+            .filter(|t| t.resource().is_some() && (resource.is_none() || t.resource() == resource))
+            .filter_map(|t| t.dice_number)
+            .map(dice_pips)
+            .sum()
     }
 }
 
+/// Pips produced by one resource across the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceBalance {
+    pub resource: Resource,
+    pub tiles: u32,
+    pub pips: u32,
+}
+
+/// A 2:1 harbor and the pips of its resource at the best adjacent corner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarborAlignment {
+    pub edge: EdgeCoord,
+    pub resource: Resource,
+    pub pips: u32,
+}
+
+/// Board fairness summary from [`Board::balance_report`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceReport {
+    /// Pips per resource
+    pub resources: Vec<ResourceBalance>,
+    /// The five richest corners and their pip totals
+    pub top_vertices: Vec<(VertexCoord, u32)>,
+    /// Resource pips next to each 2:1 harbor
+    pub harbor_alignment: Vec<HarborAlignment>,
+    /// Overall fairness from 0 (lopsided) to 100 (even)
+    pub score: f64,
+}
+
 /// JSON-friendly board representation with arrays instead of HashMaps
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BoardJson {
     pub tiles: Vec<TileJson>,
     pub vertices: Vec<VertexJson>,
     pub edges: Vec<EdgeJson>,
     pub harbors: Vec<HarborPlacement>,
-    pub robber_q: i32,
-    pub robber_r: i32,
+    #[serde(default)]
+    pub robber: Option<HexCoord>,
+    #[serde(default)]
+    pub pirate: Option<HexCoord>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TileJson {
     pub q: i32,
     pub r: i32,
     pub tile_type: TileType,
     pub dice_number: Option<u8>,
     pub has_robber: bool,
+    /// District name to show on the tile
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VertexJson {
     pub hex_q: i32,
     pub hex_r: i32,
@@ -962,7 +1947,7 @@ pub struct VertexJson {
     pub building: VertexBuilding,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EdgeJson {
     pub hex_q: i32,
     pub hex_r: i32,
@@ -970,6 +1955,58 @@ pub struct EdgeJson {
     pub building: EdgeBuilding,
 }
 
+/// Where to draw the board for `Board::render_geometry`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderLayout {
+    /// Pixel position of the center hex
+    pub origin_x: f64,
+    pub origin_y: f64,
+}
+
+/// Everything needed to draw a board, in pixels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardGeometry {
+    pub hex_size: f64,
+    /// All tiles, ocean included, top row first
+    pub hexes: Vec<HexGeometry>,
+    /// Land vertices in `VertexId` order
+    pub vertices: Vec<VertexGeometry>,
+    /// Land edges in `EdgeId` order
+    pub edges: Vec<EdgeGeometry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexGeometry {
+    pub coord: HexCoord,
+    pub x: f64,
+    pub y: f64,
+    /// Corner points clockwise from the top
+    pub corners: [(f64, f64); 6],
+    pub tile_type: TileType,
+    pub dice_number: Option<u8>,
+    pub has_robber: bool,
+    pub has_pirate: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VertexGeometry {
+    pub id: VertexId,
+    pub coord: VertexCoord,
+    pub x: f64,
+    pub y: f64,
+    pub building: VertexBuilding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeGeometry {
+    pub id: EdgeId,
+    pub coord: EdgeCoord,
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    pub building: EdgeBuilding,
+    pub harbor: Option<Harbor>,
+}
+
 impl Default for Board {
     fn default() -> Self {
         Self::new()
@@ -1001,7 +2038,7 @@ mod tests {
     #[test]
     fn test_robber_starts_on_desert() {
         let board = Board::standard();
-        let robber_tile = board.get_tile(&board.robber_location()).unwrap();
+        let robber_tile = board.get_tile(&board.robber_location().unwrap()).unwrap();
         assert!(matches!(robber_tile.tile_type, TileType::Desert));
         assert!(robber_tile.has_robber);
     }
@@ -1055,6 +2092,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_road_building_pairs() {
+        let mut board = Board::standard();
+        let vertex = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
+        board.place_settlement(vertex, 0);
+
+        let frontier = board.valid_road_spots(0);
+        let pairs = board.road_building_pairs(0);
+        assert!(pairs.iter().all(|(first, second)| first != second));
+        assert!(pairs.iter().all(|(first, _)| frontier.contains(first)));
+
+        // Every pair can actually be built, in order
+        for (first, second) in &pairs {
+            let mut after = board.clone();
+            after.place_road(*first, 0);
+            assert!(after.valid_road_spots(0).contains(second));
+        }
+
+        // Both frontier edges together, and two-road spurs from each
+        let spurs = pairs.iter().filter(|(_, second)| !frontier.contains(second)).count();
+        assert_eq!(pairs.len(), 3 + spurs);
+        assert_eq!(spurs, 6);
+    }
+
     #[test]
     fn test_resource_distribution() {
         let mut board = Board::standard();
@@ -1251,8 +2312,7 @@ mod tests {
                     for neighbor in coord.neighbors() {
                         if let Some(&neighbor_number) = coord_to_number.get(&neighbor) {
                             assert!(
-                                !(number == 6 && neighbor_number == 8)
-                                    && !(number == 8 && neighbor_number == 6),
+                                !matches!((number, neighbor_number), (6, 8) | (8, 6)),
                                 "Found adjacent 6 and 8 at {:?} and {:?}",
                                 coord,
                                 neighbor
@@ -1399,4 +2459,346 @@ mod tests {
 
         assert!(found_different, "Harbor positions/types should be randomized");
     }
+
+    #[test]
+    fn test_beginner_board_layout() {
+        let board = Board::beginner();
+
+        assert_eq!(board.land_tiles().count(), 19);
+        assert_eq!(board.robber_location(), Some(HexCoord::new(0, 0)));
+        assert_eq!(
+            board.get_tile(&HexCoord::new(0, -2)).unwrap().tile_type,
+            TileType::Resource(Resource::Ore)
+        );
+        assert_eq!(board.get_tile(&HexCoord::new(0, 2)).unwrap().dice_number, Some(11));
+
+        let mut numbers: Vec<u8> = board.land_tiles().filter_map(|t| t.dice_number).collect();
+        numbers.sort();
+        assert_eq!(numbers, vec![2, 3, 3, 4, 4, 5, 5, 6, 6, 8, 8, 9, 9, 10, 10, 11, 11, 12]);
+
+        let coastal = board.get_coastal_edges();
+        assert_eq!(board.harbors.len(), 9);
+        assert!(board.harbors.iter().all(|h| coastal.contains(&h.edge)));
+    }
+
+    #[test]
+    fn test_board_gen_config_rules_are_respected() {
+        use rand::SeedableRng;
+
+        let config = BoardGenConfig {
+            forbid_adjacent_2_12: true,
+            forbid_same_resource_clusters: true,
+            max_attempts: 5000,
+            ..BoardGenConfig::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let board = Board::standard_with_config(&config, &mut rng);
+        assert!(board.validate().is_ok());
+
+        for tile in board.land_tiles() {
+            for neighbor in tile.coord.neighbors() {
+                let Some(other) = board.get_tile(&neighbor) else {
+                    continue;
+                };
+                if tile.resource().is_some() {
+                    assert_ne!(tile.resource(), other.resource());
+                }
+                let low_high = |n: Option<u8>| matches!(n, Some(2) | Some(12));
+                assert!(!(low_high(tile.dice_number) && low_high(other.dice_number)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_board_gen_impossible_config_falls_back() {
+        use rand::SeedableRng;
+
+        let config = BoardGenConfig {
+            max_vertex_pips: Some(0),
+            max_attempts: 10,
+            ..BoardGenConfig::default()
+        };
+        let generate = || {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+            Board::standard_with_config(&config, &mut rng)
+        };
+        let board_a = generate();
+        let board_b = generate();
+        assert!(board_a.validate().is_ok());
+
+        assert_eq!(board_a.land_tiles().count(), 19);
+        let layout = |b: &Board| {
+            let mut tiles: Vec<_> = b
+                .land_tiles()
+                .map(|t| (t.coord.q, t.coord.r, t.dice_number))
+                .collect();
+            tiles.sort();
+            tiles
+        };
+        assert_eq!(layout(&board_a), layout(&board_b));
+    }
+
+    #[test]
+    fn test_dice_pips() {
+        assert_eq!(dice_pips(2), 1);
+        assert_eq!(dice_pips(6), 5);
+        assert_eq!(dice_pips(8), 5);
+        assert_eq!(dice_pips(12), 1);
+        assert_eq!(dice_pips(7), 6);
+    }
+
+    #[test]
+    fn test_balance_report() {
+        let board = Board::beginner();
+        let report = board.balance_report();
+
+        let total_pips: u32 = report.resources.iter().map(|r| r.pips).sum();
+        assert_eq!(total_pips, 58);
+        assert_eq!(report.resources.iter().map(|r| r.tiles).sum::<u32>(), 18);
+        assert_eq!(report.top_vertices.len(), 5);
+        assert!(report.top_vertices.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(report.harbor_alignment.len(), 5);
+        assert!((0.0..=100.0).contains(&report.score));
+        assert_eq!(report, board.balance_report());
+    }
+
+    #[test]
+    fn test_validate_accepts_generated_boards() {
+        assert_eq!(Board::standard().validate(), Ok(()));
+        assert_eq!(Board::beginner().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_broken_invariants() {
+        let mut board = Board::beginner();
+        let vertex = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
+        let neighbor = vertex.adjacent_vertices()[0];
+        board.place_settlement(vertex, 0);
+        board.upgrade_to_city(neighbor, 1);
+
+        let sea_vertex = VertexCoord::new(HexCoord::new(5, 5), VertexDirection::North);
+        board.place_settlement(sea_vertex, 2);
+        let sea_edge = EdgeCoord::new(HexCoord::new(5, 5), EdgeDirection::East);
+        board.place_road(sea_edge, 2);
+        board.harbors[0].edge = EdgeCoord::new(HexCoord::new(0, 0), EdgeDirection::East);
+        board.robber_location = Some(HexCoord::new(4, 0));
+
+        let errors = board.validate().unwrap_err();
+        assert!(errors.contains(&BoardError::RobberNotOnLand(HexCoord::new(4, 0))));
+        assert!(errors.contains(&BoardError::RobberFlagMismatch));
+        assert!(errors.contains(&BoardError::BuildingNotOnLand(sea_vertex)));
+        assert!(errors.contains(&BoardError::RoadNotOnLand(sea_edge)));
+        assert!(errors.contains(&BoardError::HarborNotCoastal(board.harbors[0].edge)));
+        let distance = errors
+            .iter()
+            .filter(|e| matches!(e, BoardError::DistanceRule(..)))
+            .count();
+        assert_eq!(distance, 1);
+    }
+
+    #[test]
+    fn test_location_ids_round_trip() {
+        let board = Board::beginner();
+        assert_eq!(board.vertices_by_id().len(), 54);
+        assert_eq!(board.edges_by_id().len(), 72);
+
+        for (i, vertex) in board.vertices_by_id().iter().enumerate() {
+            let id = board.vertex_id(vertex).unwrap();
+            assert_eq!(id, VertexId(i as u16));
+            assert_eq!(board.vertex_at(id), Some(*vertex));
+        }
+        for edge in board.edges_by_id() {
+            assert_eq!(board.edge_at(board.edge_id(edge).unwrap()), Some(*edge));
+        }
+        let sea = VertexCoord::new(HexCoord::new(5, 5), VertexDirection::North);
+        assert_eq!(board.vertex_id(&sea), None);
+        assert_eq!(board.vertex_at(VertexId(54)), None);
+
+        // Same layout, same IDs
+        let copy = Board::beginner();
+        assert_eq!(copy.vertices_by_id(), board.vertices_by_id());
+        assert_eq!(copy.edges_by_id(), board.edges_by_id());
+
+        // Clones share the tables rather than copying them
+        let clone = board.clone();
+        assert!(std::ptr::eq(clone.vertices_by_id(), board.vertices_by_id()));
+    }
+
+    #[test]
+    fn test_render_geometry() {
+        let mut board = Board::beginner();
+        let vertex = board.vertices_by_id()[10];
+        board.place_settlement(vertex, 2);
+        let layout = RenderLayout {
+            origin_x: 300.0,
+            origin_y: 200.0,
+        };
+        let geometry = board.render_geometry(50.0, layout);
+
+        assert_eq!(geometry.hexes.len(), board.tiles.len());
+        let center = geometry
+            .hexes
+            .iter()
+            .find(|h| h.coord == HexCoord::new(0, 0))
+            .unwrap();
+        assert_eq!((center.x, center.y), (300.0, 200.0));
+        assert!(center.has_robber);
+        assert_eq!(center.corners[0], (300.0, 150.0));
+
+        assert_eq!(geometry.vertices.len(), 54);
+        assert_eq!(geometry.vertices[10].building, VertexBuilding::Settlement(2));
+        assert_eq!(geometry.edges.len(), 72);
+        assert_eq!(geometry.edges.iter().filter(|e| e.harbor.is_some()).count(), 9);
+        for edge in &geometry.edges {
+            let (dx, dy) = (edge.to.0 - edge.from.0, edge.to.1 - edge.from.1);
+            assert!(((dx * dx + dy * dy).sqrt() - 50.0).abs() < 1e-9);
+        }
+        assert!(serde_json::to_string(&geometry).is_ok());
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let mut board = Board::beginner();
+        board.place_settlement(board.vertices_by_id()[10], 1);
+        board.upgrade_to_city(board.vertices_by_id()[30], 2);
+        board.place_road(board.edges_by_id()[5], 0);
+        let text = board.render_ascii();
+
+        // Each corner and side is drawn once, whichever hexes share it
+        assert_eq!(text.matches('.').count(), 52);
+        assert_eq!(text.matches(['/', '\\', '|']).count(), 71);
+        assert_eq!(text.matches('b').count(), 1);
+        assert_eq!(text.matches('C').count(), 1);
+        assert_eq!(text.matches('R').count(), 1);
+        assert_eq!(text.matches("3:1").count() + text.matches("2:").count(), 9);
+        assert!(text.contains("Des"));
+        assert!(text.lines().all(|l| l == l.trim_end()));
+        assert!(!text.starts_with('\n') && !text.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_vertex_pips_and_best_open_vertices() {
+        let mut board = Board::beginner();
+        // Between the 6 brick, the 11 lumber and the desert
+        let vertex = VertexCoord::new(HexCoord::new(0, -1), VertexDirection::South);
+        assert_eq!(board.vertex_pip_count(&vertex), 5 + 2);
+
+        let best = board.best_open_vertices(5);
+        assert_eq!(best.len(), 5);
+        assert!(best.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(best[0].1, board.balance_report().top_vertices[0].1);
+
+        // Taking the best spot removes it and its neighbors
+        let (top, _) = best[0];
+        board.place_settlement(top, 0);
+        let after = board.best_open_vertices(usize::MAX);
+        assert!(after.iter().all(|(v, _)| *v != top && !top.adjacent_vertices().contains(v)));
+    }
+
+    #[test]
+    fn test_placement_cache_matches_full_scan() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let scan_settlements = |board: &Board, player: PlayerId| -> HashSet<VertexCoord> {
+            board
+                .land_vertices()
+                .into_iter()
+                .filter(|v| board.is_open_vertex(v) && board.is_connected_to_road(v, player))
+                .collect()
+        };
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut board = Board::standard_with_rng(&mut rng);
+        // Build the cache up front so every placement goes through the updates
+        assert_eq!(board.valid_settlement_spots(0, true).len(), 54);
+
+        for step in 0..120 {
+            let player = step % 3;
+            let settlements = board.valid_settlement_spots(player, step < 6);
+            let roads = board.valid_road_spots(player);
+            if step < 6 || (rng.gen_bool(0.2) && !settlements.is_empty()) {
+                let vertex = *settlements.choose(&mut rng).unwrap();
+                board.place_settlement(vertex, player);
+                if rng.gen_bool(0.3) {
+                    board.upgrade_to_city(vertex, player);
+                }
+            } else if let Some(edge) = roads.choose(&mut rng) {
+                board.place_road(*edge, player);
+            }
+
+            for p in 0..3 {
+                let cached: HashSet<EdgeCoord> = board.valid_road_spots(p).into_iter().collect();
+                assert_eq!(cached, board.scan_road_spots(p).into_iter().collect());
+                let cached: HashSet<VertexCoord> =
+                    board.valid_settlement_spots(p, false).into_iter().collect();
+                assert_eq!(cached, scan_settlements(&board, p));
+            }
+            let fresh = Board {
+                placement: OnceLock::new(),
+                ..board.clone()
+            };
+            assert_eq!(
+                board.valid_settlement_spots(0, true),
+                fresh.valid_settlement_spots(0, true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_radius_three_board_scales_distribution() {
+        use rand::SeedableRng;
+
+        let config = BoardGenConfig {
+            radius: 3,
+            ..BoardGenConfig::default()
+        };
+        let board = Board::standard_with_config(&config, &mut rand::thread_rng());
+        assert!(board.validate().is_ok());
+
+        assert_eq!(board.land_tiles().count(), 37);
+        let deserts = board
+            .land_tiles()
+            .filter(|t| t.tile_type == TileType::Desert)
+            .count();
+        assert_eq!(deserts, 2);
+        assert_eq!(board.land_tiles().filter(|t| t.dice_number.is_some()).count(), 35);
+        assert_eq!(board.harbors.len(), 13);
+        assert!(board.land_tiles().all(|t| t.coord.distance_to(&HexCoord::new(0, 0)) <= 3));
+
+        // Only one of the deserts has the robber
+        for seed in 0..8 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let board = Board::standard_with_config(&config, &mut rng);
+            assert!(board.validate().is_ok());
+            assert_eq!(board.land_tiles().filter(|t| t.has_robber).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_land_spiral_matches_standard_order() {
+        let spiral = Board::land_spiral(2);
+        assert_eq!(spiral.len(), 19);
+        assert_eq!(spiral[1], HexCoord::new(1, 0));
+        assert_eq!(spiral[7], HexCoord::new(2, 0));
+        assert_eq!(spiral[18], HexCoord::new(1, 1));
+    }
+
+    #[test]
+    fn test_generated_boards_name_their_districts() {
+        let board = Board::standard_with_rng(&mut rand::thread_rng());
+        assert!(board.land_tiles().all(|t| t.label.is_some()));
+        let desert = board.land_tiles().find(|t| t.tile_type == TileType::Desert).unwrap();
+        assert_eq!(desert.label.as_deref(), Some("Bukit Timah"));
+        let json = board.to_json_friendly();
+        let mut ocean = json.tiles.iter().filter(|t| t.tile_type == TileType::Ocean);
+        assert!(ocean.all(|t| t.label.is_none()));
+
+        let config = BoardGenConfig {
+            districts: DistrictPool::None,
+            ..BoardGenConfig::default()
+        };
+        let beginner = Board::from_template(BoardTemplate::Beginner, &config);
+        assert_eq!(beginner.land_tiles().filter(|t| t.label.is_some()).count(), 1);
+    }
 }
@@ -3,22 +3,315 @@
 //! This module provides different difficulty levels of AI players:
 //! - Easy: Random valid moves
 //! - Medium: Basic heuristics (prioritize settlements, balance resources)
-//! - Hard: Strategic planning with lookahead
+//! - Hard: Strategic planning with lookahead and a setup evaluator (see [`crate::opening`])
+//! - Expert: Monte Carlo Tree Search over sampled futures (see [`crate::mcts`])
+//!
+//! Custom AIs implement [`BotPlayer`]; the built-in [`Bot`] is one implementation.
 
-use crate::actions::GameAction;
-use crate::board::{PlayerId, Resource};
-use crate::game::GameState;
+use crate::actions::{GameAction, GameEvent, TradeOffer};
+use crate::board::{dice_pips, PlayerId, Resource, VertexBuilding};
+use crate::game::{GamePhase, GameState};
+use crate::hand_tracker::HandTracker;
 use crate::hex::{EdgeCoord, HexCoord, VertexCoord};
-use crate::player::ResourceHand;
+use crate::mcts::MctsConfig;
+use crate::opening::evaluate_opening;
+use crate::player::{costs, ResourceHand};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Instant;
 
-/// Bot difficulty level
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Bot difficulty level, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum BotDifficulty {
     Easy,
     Medium,
     Hard,
+    Expert,
+}
+
+/// Tunable numbers behind the built-in bot's heuristics. The defaults are
+/// the hand-picked values; load others with [`BotWeights::from_json`] to
+/// tune by self-play without recompiling.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotWeights {
+    /// Medium: chance of building an affordable road
+    pub medium_road_chance: f64,
+    /// Medium: chance of buying an affordable development card
+    pub medium_dev_card_chance: f64,
+    /// Medium: settlements are picked at random among this many best spots
+    pub medium_settlement_choices: usize,
+
+    /// Hard: chance of buying an affordable development card
+    pub hard_dev_card_chance: f64,
+    /// Hard: chance of taking the second-best settlement spot
+    pub hard_second_best_chance: f64,
+    /// Hard: settlements on the board before cities come first
+    pub hard_city_after_settlements: u32,
+    /// Hard: only build roads while at least this many are left
+    pub hard_min_roads_remaining: u32,
+    /// Hard: knights already played before playing more
+    pub hard_knights_before_playing: u32,
+    /// Hard: bonus per distinct resource at a settlement spot
+    pub settlement_diversity: i32,
+    /// Hard: bonus per resource at a spot that we hold none of
+    pub settlement_missing_resource: i32,
+
+    /// Robber: score per pip of the blocked number
+    pub robber_pips: i32,
+    /// Robber: score per opponent on the tile
+    pub robber_opponent: i32,
+    /// Robber: penalty for blocking ourselves
+    pub robber_self: i32,
+    /// Hard steals: score per victory point of the victim
+    pub steal_victory_points: i32,
+    /// Hard steals: score per card in the victim's hand
+    pub steal_cards: i32,
+    /// Hard steals: score for a victim sure to hand over a card we need,
+    /// going by the tracked hands
+    pub steal_needed: i32,
+
+    /// Roads: how far past a new road to look for settlement spots; a
+    /// spot's pips are multiplied by the roads left to spare
+    pub road_reach: i32,
+    /// Roads: score per road added to our longest road
+    pub road_length: i32,
+    /// Roads: score per pip of contested spots at the road's ends
+    pub road_contested: i32,
+    /// Roads: score per opponent denied the road spot
+    pub road_denied: i32,
+
+    /// Monopoly: expected cards taken before playing it
+    pub monopoly_min_haul: f64,
+    /// Monopoly: extra expected cards credited to a resource we need
+    pub monopoly_needed: f64,
+    /// Road Building: score per pip of the best new settlement spot
+    pub road_building_spot: i32,
+    /// Road Building: bonus for taking Longest Road
+    pub road_building_longest: i32,
+    /// Road Building: hold the card unless a pair scores above this
+    pub road_building_min_score: i32,
+
+    /// Easy: chance of accepting an affordable trade
+    pub easy_trade_accept_chance: f64,
+    /// Hard: refuse trades with players this close to winning
+    pub hard_trade_leader_margin: u32,
+    /// Medium and Hard: accept offers giving up to this many more cards
+    /// than they bring (negative demands a profit)
+    pub trade_generosity: i32,
+    /// Trade with the bank or harbors when one trade finishes the next build
+    pub maritime_trade: bool,
+    /// Hard setup: extra weight on the opening harbor score
+    pub opening_harbor: i32,
+    /// Hard setup: extra weight on denying opponents' best spots
+    pub opening_denial: i32,
+}
+
+impl Default for BotWeights {
+    fn default() -> Self {
+        Self {
+            medium_road_chance: 0.3,
+            medium_dev_card_chance: 0.2,
+            medium_settlement_choices: 3,
+            hard_dev_card_chance: 0.35,
+            hard_second_best_chance: 0.1,
+            hard_city_after_settlements: 3,
+            hard_min_roads_remaining: 8,
+            hard_knights_before_playing: 2,
+            settlement_diversity: 3,
+            settlement_missing_resource: 5,
+            robber_pips: 2,
+            robber_opponent: 5,
+            robber_self: 20,
+            steal_victory_points: 3,
+            steal_cards: 1,
+            steal_needed: 4,
+            road_reach: 3,
+            road_length: 2,
+            road_contested: 1,
+            road_denied: 1,
+            monopoly_min_haul: 3.0,
+            monopoly_needed: 1.0,
+            road_building_spot: 2,
+            road_building_longest: 15,
+            road_building_min_score: 2,
+            easy_trade_accept_chance: 0.5,
+            hard_trade_leader_margin: 2,
+            trade_generosity: 0,
+            maritime_trade: false,
+            opening_harbor: 0,
+            opening_denial: 0,
+        }
+    }
+}
+
+impl BotWeights {
+    /// Parse weights from JSON; missing fields keep their defaults
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Parse weights from RON; missing fields keep their defaults
+    #[cfg(feature = "ron")]
+    pub fn from_ron(source: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(source)
+    }
+}
+
+/// A build a bot saves its cards for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildTarget {
+    Road,
+    Settlement,
+    City,
+    DevelopmentCard,
+}
+
+impl BuildTarget {
+    /// Resources the build costs
+    pub fn cost(self) -> ResourceHand {
+        match self {
+            BuildTarget::Road => costs::road(),
+            BuildTarget::Settlement => costs::settlement(),
+            BuildTarget::City => costs::city(),
+            BuildTarget::DevelopmentCard => costs::development_card(),
+        }
+    }
+}
+
+/// A move the bot weighed and its heuristic score (higher is better)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateScore {
+    pub action: GameAction,
+    pub score: f64,
+}
+
+/// Why a bot chose its move, from [`Bot::choose_action_explained`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotExplanation {
+    /// The move, or None if the bot had nothing to do
+    pub action: Option<GameAction>,
+    /// Scored alternatives of the same kind (spots, roads, robber tiles,
+    /// victims, Monopoly resources), best first; empty for moves the bot
+    /// doesn't score
+    pub candidates: Vec<CandidateScore>,
+    /// What the bot is saving for
+    pub target_build: Option<BuildTarget>,
+    /// The opponent the move works against, if any
+    pub threatened: Option<PlayerId>,
+    /// One line on what the move is for
+    pub reason: String,
+}
+
+/// Play style layered on top of difficulty. Each personality is a set of
+/// [`BotWeights`]; difficulty still decides how the bot plays, this decides
+/// what it prefers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotPersonality {
+    /// The default weights
+    #[default]
+    Balanced,
+    /// Roads and settlements first, few development cards
+    AggressiveExpander,
+    /// Buys and plays development cards early
+    DevCardRush,
+    /// Settles on harbors and trades freely
+    PortTrader,
+    /// Robs the leader and builds in opponents' way
+    Blocker,
+}
+
+impl BotPersonality {
+    pub const ALL: [BotPersonality; 5] = [
+        BotPersonality::Balanced,
+        BotPersonality::AggressiveExpander,
+        BotPersonality::DevCardRush,
+        BotPersonality::PortTrader,
+        BotPersonality::Blocker,
+    ];
+
+    /// The heuristic weights for this play style
+    pub fn weights(self) -> BotWeights {
+        let base = BotWeights::default();
+        match self {
+            BotPersonality::Balanced => base,
+            BotPersonality::AggressiveExpander => BotWeights {
+                medium_road_chance: 0.6,
+                medium_dev_card_chance: 0.1,
+                hard_dev_card_chance: 0.15,
+                hard_min_roads_remaining: 3,
+                hard_city_after_settlements: 4,
+                road_reach: 4,
+                road_building_min_score: 0,
+                ..base
+            },
+            BotPersonality::DevCardRush => BotWeights {
+                medium_road_chance: 0.15,
+                medium_dev_card_chance: 0.8,
+                hard_dev_card_chance: 0.9,
+                hard_min_roads_remaining: 11,
+                hard_city_after_settlements: 1,
+                hard_knights_before_playing: 0,
+                ..base
+            },
+            BotPersonality::PortTrader => BotWeights {
+                easy_trade_accept_chance: 0.8,
+                hard_trade_leader_margin: 1,
+                trade_generosity: 1,
+                maritime_trade: true,
+                opening_harbor: 2,
+                ..base
+            },
+            BotPersonality::Blocker => BotWeights {
+                robber_opponent: 10,
+                steal_victory_points: 6,
+                road_contested: 3,
+                road_denied: 4,
+                hard_trade_leader_margin: 4,
+                trade_generosity: -1,
+                opening_denial: 2,
+                ..base
+            },
+        }
+    }
+}
+
+/// A computer player. Anything that drives bots (the server, the WASM
+/// bindings, simulations) works with this trait, so custom AIs can be
+/// plugged in alongside the built-in [`Bot`].
+pub trait BotPlayer {
+    /// The seat this bot plays
+    fn player_id(&self) -> PlayerId;
+
+    /// Pick the next action, or None if the bot has nothing to do
+    fn choose_action(&mut self, game: &GameState) -> Option<GameAction>;
+
+    /// Like [`choose_action`](Self::choose_action), but answer by `deadline`
+    /// with the best action found so far. By default the deadline is
+    /// ignored, which suits bots that decide quickly. There is no clock on
+    /// `wasm32-unknown-unknown`, so pass None there.
+    fn choose_action_until(
+        &mut self,
+        game: &GameState,
+        _deadline: Option<Instant>,
+    ) -> Option<GameAction> {
+        self.choose_action(game)
+    }
+
+    /// Cards to give up when a 7 is rolled; by default the largest piles
+    fn choose_discard(&mut self, game: &GameState) -> ResourceHand {
+        bot_discard(game, self.player_id())
+    }
+
+    /// Whether to accept another player's trade offer; by default never
+    fn respond_to_trade(&mut self, _game: &GameState, _offer: &TradeOffer) -> bool {
+        false
+    }
+
+    /// See the events of an action just applied by anyone; `game` is the
+    /// state after them. By default ignored.
+    fn observe(&mut self, _game: &GameState, _events: &[GameEvent]) {}
 }
 
 /// A bot player that can decide on actions
@@ -26,6 +319,12 @@ pub struct Bot {
     pub player_id: PlayerId,
     pub difficulty: BotDifficulty,
     rng: StdRng,
+    mcts: MctsConfig,
+    weights: BotWeights,
+    /// Roads still to place from a Road Building card, in order
+    planned_roads: Vec<EdgeCoord>,
+    /// What we've seen of the other hands
+    hands: HandTracker,
 }
 
 impl Bot {
@@ -34,6 +333,10 @@ impl Bot {
             player_id,
             difficulty,
             rng: StdRng::from_entropy(),
+            mcts: MctsConfig::default(),
+            weights: BotWeights::default(),
+            planned_roads: Vec::new(),
+            hands: HandTracker::new(player_id),
         }
     }
 
@@ -42,21 +345,33 @@ impl Bot {
             player_id,
             difficulty,
             rng: StdRng::seed_from_u64(seed),
+            mcts: MctsConfig::default(),
+            weights: BotWeights::default(),
+            planned_roads: Vec::new(),
+            hands: HandTracker::new(player_id),
         }
     }
 
-    /// Choose an action from the valid actions
-    pub fn choose_action(&mut self, game: &GameState) -> Option<GameAction> {
-        let valid_actions = game.valid_actions(self.player_id);
-        if valid_actions.is_empty() {
-            return None;
-        }
+    /// Set the search budget used at Expert difficulty
+    pub fn with_mcts_config(mut self, config: MctsConfig) -> Self {
+        self.mcts = config;
+        self
+    }
 
-        match self.difficulty {
-            BotDifficulty::Easy => self.choose_easy(&valid_actions),
-            BotDifficulty::Medium => self.choose_medium(game, &valid_actions),
-            BotDifficulty::Hard => self.choose_hard(game, &valid_actions),
-        }
+    /// Play with a personality's weights
+    pub fn with_personality(self, personality: BotPersonality) -> Self {
+        self.with_weights(personality.weights())
+    }
+
+    /// Use tuned heuristic weights instead of the defaults
+    pub fn with_weights(mut self, weights: BotWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// What the bot has worked out about the other hands
+    pub fn hands(&self) -> &HandTracker {
+        &self.hands
     }
 
     /// Easy: Just pick a random valid action
@@ -94,7 +409,7 @@ impl Bot {
             .filter(|a| matches!(a, GameAction::PlaceInitialRoad(_)))
             .collect();
         if !road_actions.is_empty() {
-            return road_actions.choose(&mut self.rng).map(|a| (*a).clone());
+            return self.rank_road_spots(game, &road_actions).cloned();
         }
 
         // Move robber away from self, prefer opponents with most resources
@@ -116,6 +431,11 @@ impl Bot {
             return self.choose_steal_target(game, &steal_actions);
         }
 
+        // Finish placing a Road Building card
+        if let Some(road) = self.next_planned_road(game, actions) {
+            return Some(road);
+        }
+
         // Build settlements
         let build_settlement_actions: Vec<_> = actions
             .iter()
@@ -135,17 +455,29 @@ impl Bot {
             return city_actions.choose(&mut self.rng).map(|a| (*a).clone());
         }
 
+        // Development cards that pay off this turn
+        if let Some(play) = self.choose_dev_card_play(game, actions) {
+            return Some(play);
+        }
+
+        // A harbor trade that finishes the next build
+        if let Some(trade) = self.plan_maritime_trade(game, actions) {
+            return Some(trade);
+        }
+
         // Build roads with 30% chance
         let road_build_actions: Vec<_> = actions
             .iter()
             .filter(|a| matches!(a, GameAction::BuildRoad(_)))
             .collect();
-        if !road_build_actions.is_empty() && self.rng.gen_bool(0.3) {
-            return road_build_actions.choose(&mut self.rng).map(|a| (*a).clone());
+        if !road_build_actions.is_empty() && self.rng.gen_bool(self.weights.medium_road_chance) {
+            return self.rank_road_spots(game, &road_build_actions).cloned();
         }
 
         // Buy dev cards with 20% chance
-        if actions.contains(&GameAction::BuyDevelopmentCard) && self.rng.gen_bool(0.2) {
+        if actions.contains(&GameAction::BuyDevelopmentCard)
+            && self.rng.gen_bool(self.weights.medium_dev_card_chance)
+        {
             return Some(GameAction::BuyDevelopmentCard);
         }
 
@@ -170,14 +502,24 @@ impl Bot {
             return Some(GameAction::RollDice);
         }
 
-        // During setup, pick best spots based on resource diversity and probability
+        // During setup, weigh both settlements together, harbors and denial
         let settlement_actions: Vec<_> = actions
             .iter()
             .filter(|a| matches!(a, GameAction::PlaceInitialSettlement(_)))
             .collect();
         if !settlement_actions.is_empty() {
-            let best = self.rank_settlement_spots_advanced(game, &settlement_actions);
-            return best.cloned();
+            return settlement_actions
+                .into_iter()
+                .max_by_key(|action| match action {
+                    GameAction::PlaceInitialSettlement(v) => {
+                        let score = evaluate_opening(game, self.player_id, v);
+                        score.total()
+                            + self.weights.opening_harbor * score.harbor
+                            + self.weights.opening_denial * score.denial
+                    }
+                    _ => i32::MIN,
+                })
+                .cloned();
         }
 
         let road_actions: Vec<_> = actions
@@ -208,6 +550,11 @@ impl Bot {
             return self.choose_steal_target_strategic(game, &steal_actions);
         }
 
+        // Finish placing a Road Building card
+        if let Some(road) = self.next_planned_road(game, actions) {
+            return Some(road);
+        }
+
         // Build priority: City > Settlement > Road
         // But consider resource balance
 
@@ -218,7 +565,9 @@ impl Bot {
             .iter()
             .filter(|a| matches!(a, GameAction::BuildCity(_)))
             .collect();
-        if !city_actions.is_empty() && (5 - player.settlements_remaining) >= 3 {
+        if !city_actions.is_empty()
+            && (5 - player.settlements_remaining) >= self.weights.hard_city_after_settlements
+        {
             return city_actions.choose(&mut self.rng).map(|a| (*a).clone());
         }
 
@@ -232,24 +581,40 @@ impl Bot {
             return best.cloned();
         }
 
+        // Development cards that pay off this turn
+        if let Some(play) = self.choose_dev_card_play(game, actions) {
+            return Some(play);
+        }
+
+        // A harbor trade that finishes the next build
+        if let Some(trade) = self.plan_maritime_trade(game, actions) {
+            return Some(trade);
+        }
+
         // Roads toward expansion
         let road_build_actions: Vec<_> = actions
             .iter()
             .filter(|a| matches!(a, GameAction::BuildRoad(_)))
             .collect();
-        if !road_build_actions.is_empty() && player.roads_remaining >= 8 {
+        if !road_build_actions.is_empty()
+            && player.roads_remaining >= self.weights.hard_min_roads_remaining
+        {
             // Only build roads early game
             let best = self.rank_road_spots(game, &road_build_actions);
             return best.cloned();
         }
 
         // Play knight if we're close to largest army
-        if actions.contains(&GameAction::PlayKnight) && player.played_knights >= 2 {
+        if actions.contains(&GameAction::PlayKnight)
+            && player.played_knights >= self.weights.hard_knights_before_playing
+        {
             return Some(GameAction::PlayKnight);
         }
 
         // Buy dev cards with controlled probability
-        if actions.contains(&GameAction::BuyDevelopmentCard) && self.rng.gen_bool(0.35) {
+        if actions.contains(&GameAction::BuyDevelopmentCard)
+            && self.rng.gen_bool(self.weights.hard_dev_card_chance)
+        {
             return Some(GameAction::BuyDevelopmentCard);
         }
 
@@ -285,10 +650,13 @@ impl Bot {
             })
             .collect();
 
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.1));
 
-        // Pick from top 3 with some randomness
-        let top = scored.iter().take(3).collect::<Vec<_>>();
+        // Pick from the top few with some randomness
+        let top = scored
+            .iter()
+            .take(self.weights.medium_settlement_choices.max(1))
+            .collect::<Vec<_>>();
         top.choose(&mut self.rng).map(|(a, _)| *a)
     }
 
@@ -311,7 +679,8 @@ impl Bot {
                         let resources = self.vertex_resources(game, v);
                         let unique_resources: std::collections::HashSet<_> =
                             resources.iter().collect();
-                        base_score += (unique_resources.len() as i32) * 3;
+                        base_score +=
+                            (unique_resources.len() as i32) * self.weights.settlement_diversity;
 
                         // Bonus for resources we're lacking
                         for res in &resources {
@@ -323,7 +692,7 @@ impl Bot {
                                 Resource::Wool => player.resources.wool,
                             };
                             if current == 0 {
-                                base_score += 5;
+                                base_score += self.weights.settlement_missing_resource;
                             }
                         }
 
@@ -335,10 +704,10 @@ impl Bot {
             })
             .collect();
 
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.1));
 
         // Pick best with small chance for second best
-        if scored.len() >= 2 && self.rng.gen_bool(0.1) {
+        if scored.len() >= 2 && self.rng.gen_bool(self.weights.hard_second_best_chance) {
             Some(scored[1].0)
         } else {
             scored.first().map(|(a, _)| *a)
@@ -347,24 +716,7 @@ impl Bot {
 
     /// Score a vertex based on adjacent tiles
     fn score_vertex(&self, game: &GameState, vertex: &VertexCoord) -> i32 {
-        let tiles = game.board.tiles_at_vertex(vertex);
-        let mut score = 0;
-
-        for tile in tiles {
-            if let Some(dice) = tile.dice_number {
-                // 6 and 8 are most valuable, then 5/9, 4/10, 3/11, 2/12
-                score += match dice {
-                    6 | 8 => 5,
-                    5 | 9 => 4,
-                    4 | 10 => 3,
-                    3 | 11 => 2,
-                    2 | 12 => 1,
-                    _ => 0,
-                };
-            }
-        }
-
-        score
+        game.board.vertex_pip_count(vertex) as i32
     }
 
     /// Get resources at a vertex
@@ -396,15 +748,293 @@ impl Bot {
             })
             .collect();
 
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.1));
         scored.first().map(|(a, _)| *a)
     }
 
-    /// Score edge for expansion potential
-    fn score_edge_expansion(&self, _game: &GameState, _edge: &EdgeCoord) -> i32 {
-        // Simplified: random for now
-        // Full implementation would check if endpoints lead to valid settlement spots
-        rand::thread_rng().gen_range(0..10)
+    /// Score edge for expansion potential: how close it brings us to a good
+    /// open settlement spot, what it adds to our longest road, and how much
+    /// it gets in opponents' way
+    fn score_edge_expansion(&self, game: &GameState, edge: &EdgeCoord) -> i32 {
+        let board = &game.board;
+        let me = self.player_id;
+        let open = |v: &VertexCoord| {
+            board.get_vertex(v) == VertexBuilding::Empty
+                && board.is_land_vertex(v)
+                && board.satisfies_distance_rule(v)
+        };
+        let ours = |v: &VertexCoord| {
+            board.get_vertex(v).owner() == Some(me)
+                || v.touching_edges()
+                    .iter()
+                    .any(|e| e != edge && board.get_edge(e).owner() == Some(me))
+        };
+
+        // Walk out from the new end of the edge; a spot's pips count in full
+        // right there and fade with each further road it would take
+        let mut reach = 0;
+        let mut frontier: Vec<VertexCoord> =
+            edge.endpoints().into_iter().filter(|v| !ours(v)).collect();
+        let mut seen: HashSet<VertexCoord> = edge.endpoints().into_iter().collect();
+        for fade in (1..=self.weights.road_reach).rev() {
+            let mut next = Vec::new();
+            for vertex in &frontier {
+                if open(vertex) {
+                    reach = reach.max(fade * self.score_vertex(game, vertex));
+                }
+                // Roads can't run through someone else's building
+                if board.get_vertex(vertex).owner().is_some_and(|owner| owner != me) {
+                    continue;
+                }
+                for road in vertex.touching_edges() {
+                    let passable = board.is_land_edge(&road)
+                        && board.get_edge(&road).owner().is_none_or(|owner| owner == me);
+                    if !passable {
+                        continue;
+                    }
+                    for end in road.endpoints() {
+                        if seen.insert(end) {
+                            next.push(end);
+                        }
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        // Longest road only matters once we have roads to extend
+        let mut length = 0;
+        let current = board.longest_road(me);
+        if current > 0 {
+            let mut after = board.clone();
+            after.place_road(*edge, me);
+            length = after.longest_road(me) as i32 - current as i32;
+        }
+
+        // Blocking: open spots at its ends that opponents' roads already
+        // touch, and road spots we take out of their frontier
+        let mut blocking = 0;
+        for end in edge.endpoints() {
+            let contested = end.touching_edges().iter().any(|e| {
+                board.get_edge(e).owner().is_some_and(|owner| owner != me)
+            });
+            if contested && open(&end) {
+                blocking += self.weights.road_contested * self.score_vertex(game, &end);
+            }
+        }
+        for other in (0..game.players.len() as PlayerId).filter(|p| *p != me) {
+            if board.valid_road_spots(other).contains(edge) {
+                blocking += self.weights.road_denied;
+            }
+        }
+
+        reach + self.weights.road_length * length + blocking
+    }
+
+    /// Play Year of Plenty, Monopoly or Road Building if one helps right
+    /// now; otherwise keep the cards
+    fn choose_dev_card_play(
+        &mut self,
+        game: &GameState,
+        actions: &[GameAction],
+    ) -> Option<GameAction> {
+        self.plan_year_of_plenty(game, actions)
+            .or_else(|| self.plan_monopoly(game, actions))
+            .or_else(|| self.plan_road_building(game, actions))
+    }
+
+    /// The build we're working toward: whichever of a settlement or city is
+    /// closer, else a road, else a development card
+    fn next_build(&self, game: &GameState) -> Option<BuildTarget> {
+        let player = game.get_player(self.player_id)?;
+        let hand = &player.resources;
+
+        let mut options = Vec::new();
+        if player.settlements_remaining > 0
+            && !game.board.valid_settlement_spots(self.player_id, false).is_empty()
+        {
+            options.push(BuildTarget::Settlement);
+        }
+        if player.cities_remaining > 0 && !game.board.valid_city_spots(self.player_id).is_empty() {
+            options.push(BuildTarget::City);
+        }
+        if let Some(best) = options.into_iter().min_by_key(|b| missing(hand, &b.cost()).len()) {
+            return Some(best);
+        }
+
+        if player.roads_remaining > 0 && !game.board.valid_road_spots(self.player_id).is_empty() {
+            Some(BuildTarget::Road)
+        } else if !game.dev_card_deck.is_empty() {
+            Some(BuildTarget::DevelopmentCard)
+        } else {
+            None
+        }
+    }
+
+    /// Maritime trade: when one trade would finish the next build, give
+    /// cards we don't need for it at the best rate we have
+    fn plan_maritime_trade(&self, game: &GameState, actions: &[GameAction]) -> Option<GameAction> {
+        if !self.weights.maritime_trade {
+            return None;
+        }
+        let hand = &game.get_player(self.player_id)?.resources;
+        let cost = self.next_build(game)?.cost();
+        let [short] = missing(hand, &cost)[..] else {
+            return None;
+        };
+
+        actions
+            .iter()
+            .filter(|action| match action {
+                GameAction::MaritimeTrade {
+                    give,
+                    give_count,
+                    receive,
+                } => *receive == short && hand.get(*give) >= cost.get(*give) + give_count,
+                _ => false,
+            })
+            .min_by_key(|action| match action {
+                GameAction::MaritimeTrade { give_count, .. } => *give_count,
+                _ => u32::MAX,
+            })
+            .cloned()
+    }
+
+    /// Year of Plenty: take the cards that complete the next build
+    fn plan_year_of_plenty(&self, game: &GameState, actions: &[GameAction]) -> Option<GameAction> {
+        if !actions.iter().any(|a| matches!(a, GameAction::PlayYearOfPlenty(..))) {
+            return None;
+        }
+        let hand = &game.get_player(self.player_id)?.resources;
+        let short = missing(hand, &self.next_build(game)?.cost());
+        if short.is_empty() || short.len() > 2 {
+            return None;
+        }
+
+        // With one card to spare, take what we hold least of
+        let spare = Resource::ALL.into_iter().min_by_key(|r| hand.get(*r))?;
+        let action = GameAction::PlayYearOfPlenty(short[0], short.get(1).copied().unwrap_or(spare));
+        actions.contains(&action).then_some(action)
+    }
+
+    /// Monopoly: name the resource the others most likely hold, favoring
+    /// one we need, once the expected haul is worth a card
+    fn plan_monopoly(&self, game: &GameState, actions: &[GameAction]) -> Option<GameAction> {
+        if !actions.iter().any(|a| matches!(a, GameAction::PlayMonopoly(_))) {
+            return None;
+        }
+        let hand = &game.get_player(self.player_id)?.resources;
+        let needed = self
+            .next_build(game)
+            .map(|build| missing(hand, &build.cost()))
+            .unwrap_or_default();
+
+        let (resource, haul) = Resource::ALL
+            .into_iter()
+            .zip(self.expected_monopoly(game))
+            .max_by(|(a, x), (b, y)| {
+                let bonus = |r: &Resource| {
+                    if needed.contains(r) {
+                        self.weights.monopoly_needed
+                    } else {
+                        0.0
+                    }
+                };
+                (x + bonus(a)).total_cmp(&(y + bonus(b)))
+            })?;
+        (haul >= self.weights.monopoly_min_haul)
+            .then_some(GameAction::PlayMonopoly(resource))
+            .filter(|action| actions.contains(action))
+    }
+
+    /// Cards a Monopoly would take, by resource in `Resource::ALL` order
+    fn expected_monopoly(&self, game: &GameState) -> [f64; 5] {
+        let mut expected = [0.0; 5];
+        for other in 0..game.players.len() as PlayerId {
+            if other != self.player_id {
+                for (total, estimate) in expected.iter_mut().zip(self.hands.estimate(game, other)) {
+                    *total += estimate;
+                }
+            }
+        }
+        expected
+    }
+
+    /// Road Building: pick the pair that opens the best new settlement spot
+    /// or takes Longest Road, and remember it for the placement phase
+    fn plan_road_building(
+        &mut self,
+        game: &GameState,
+        actions: &[GameAction],
+    ) -> Option<GameAction> {
+        let board = &game.board;
+        let current = board.longest_road(self.player_id);
+        let rival = (0..game.players.len() as PlayerId)
+            .filter(|p| *p != self.player_id)
+            .map(|p| board.longest_road(p))
+            .max()
+            .unwrap_or(0);
+        let spots_before = board.valid_settlement_spots(self.player_id, false);
+
+        let mut best: Option<(i32, EdgeCoord, EdgeCoord)> = None;
+        for action in actions {
+            let GameAction::PlayRoadBuilding(first, second) = action else {
+                continue;
+            };
+            let mut after = board.clone();
+            after.place_road(*first, self.player_id);
+            after.place_road(*second, self.player_id);
+
+            let new_spot = after
+                .valid_settlement_spots(self.player_id, false)
+                .iter()
+                .filter(|v| !spots_before.contains(v))
+                .map(|v| self.score_vertex(game, v))
+                .max()
+                .unwrap_or(0);
+            let length = after.longest_road(self.player_id);
+            let takes_longest = length >= 5 && length > rival && current <= rival;
+            let score = self.weights.road_building_spot * new_spot
+                + (length - current) as i32
+                + if takes_longest { self.weights.road_building_longest } else { 0 };
+
+            if best.is_none_or(|(top, _, _)| score > top) {
+                best = Some((score, *first, *second));
+            }
+        }
+
+        // Growing the road alone isn't worth the card
+        let (score, first, second) = best?;
+        if score <= self.weights.road_building_min_score {
+            return None;
+        }
+        self.planned_roads = vec![first, second];
+        Some(GameAction::PlayRoadBuilding(first, second))
+    }
+
+    /// While placing Road Building roads, follow the plan if it still
+    /// stands, else take the best expansion edge
+    fn next_planned_road(
+        &mut self,
+        game: &GameState,
+        actions: &[GameAction],
+    ) -> Option<GameAction> {
+        if !matches!(game.phase, GamePhase::RoadBuildingInProgress { .. }) {
+            self.planned_roads.clear();
+            return None;
+        }
+
+        while !self.planned_roads.is_empty() {
+            let action = GameAction::BuildRoad(self.planned_roads.remove(0));
+            if actions.contains(&action) {
+                return Some(action);
+            }
+        }
+        let roads: Vec<_> = actions
+            .iter()
+            .filter(|a| matches!(a, GameAction::BuildRoad(_)))
+            .collect();
+        self.rank_road_spots(game, &roads).cloned()
     }
 
     /// Rank robber spots to hurt opponents
@@ -424,7 +1054,7 @@ impl Bot {
             })
             .collect();
 
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.1));
         scored.first().map(|(a, _)| *a)
     }
 
@@ -439,22 +1069,15 @@ impl Bot {
 
         // Prefer high-value tiles
         if let Some(dice) = tile.dice_number {
-            score += match dice {
-                6 | 8 => 10,
-                5 | 9 => 8,
-                4 | 10 => 6,
-                3 | 11 => 4,
-                2 | 12 => 2,
-                _ => 0,
-            };
+            score += dice_pips(dice) as i32 * self.weights.robber_pips;
         }
 
         // Prefer tiles with opponent buildings, avoid our own
         for player_id in game.board.players_adjacent_to_hex(hex) {
             if player_id == self.player_id {
-                score -= 20; // Don't hurt ourselves
+                score -= self.weights.robber_self; // Don't hurt ourselves
             } else {
-                score += 5;
+                score += self.weights.robber_opponent;
             }
         }
 
@@ -491,20 +1114,229 @@ impl Bot {
             .iter()
             .filter_map(|action| {
                 if let GameAction::StealFrom(victim) = action {
-                    let player = game.get_player(*victim)?;
-                    let vp = game.total_victory_points(*victim);
-                    let resources = player.resources.total();
-                    let score = (vp as i32) * 3 + resources as i32;
-                    Some((*victim, score))
+                    Some((*victim, self.score_steal_target(game, *victim)?))
                 } else {
                     None
                 }
             })
             .collect();
 
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.sort_by_key(|s| std::cmp::Reverse(s.1));
         scored.first().map(|(victim, _)| GameAction::StealFrom(*victim))
     }
+
+    /// Score a steal victim by their victory points, hand size and the
+    /// chance of drawing a card we're short of
+    fn score_steal_target(&self, game: &GameState, victim: PlayerId) -> Option<i32> {
+        let player = game.get_player(victim)?;
+        let vp = game.total_victory_points(victim);
+        let resources = player.resources.total();
+
+        let hand = &game.get_player(self.player_id)?.resources;
+        let mut needed = self
+            .next_build(game)
+            .map(|build| missing(hand, &build.cost()))
+            .unwrap_or_default();
+        needed.dedup();
+        let useful: f64 = needed
+            .into_iter()
+            .map(|r| self.hands.steal_chance(game, victim, r))
+            .sum();
+
+        Some(
+            (vp as i32) * self.weights.steal_victory_points
+                + resources as i32 * self.weights.steal_cards
+                + (useful * self.weights.steal_needed as f64).round() as i32,
+        )
+    }
+
+    /// Pick an action as [`BotPlayer::choose_action`] does, and say why
+    pub fn choose_action_explained(&mut self, game: &GameState) -> BotExplanation {
+        let action = self.choose_action(game);
+        self.explain(game, action)
+    }
+
+    /// Rebuild the reasoning behind `action`: score the alternatives of the
+    /// same kind the way the heuristics do
+    fn explain(&self, game: &GameState, action: Option<GameAction>) -> BotExplanation {
+        let me = self.player_id;
+        let hard = matches!(self.difficulty, BotDifficulty::Hard | BotDifficulty::Expert);
+        let monopoly = self.expected_monopoly(game);
+
+        let score = |candidate: &GameAction| -> Option<f64> {
+            let score = match candidate {
+                GameAction::PlaceInitialSettlement(v) if hard => {
+                    evaluate_opening(game, me, v).total()
+                }
+                GameAction::PlaceInitialSettlement(v)
+                | GameAction::BuildSettlement(v)
+                | GameAction::BuildCity(v) => self.score_vertex(game, v),
+                GameAction::PlaceInitialRoad(e) | GameAction::BuildRoad(e) => {
+                    self.score_edge_expansion(game, e)
+                }
+                GameAction::MoveRobber(hex) => self.score_robber_spot(game, hex),
+                GameAction::StealFrom(victim) if hard => self.score_steal_target(game, *victim)?,
+                GameAction::StealFrom(victim) => game.get_player(*victim)?.resources.total() as i32,
+                GameAction::PlayMonopoly(resource) => {
+                    let i = Resource::ALL.iter().position(|r| r == resource)?;
+                    return Some(monopoly[i]);
+                }
+                _ => return None,
+            };
+            Some(score as f64)
+        };
+
+        let mut candidates: Vec<CandidateScore> = match &action {
+            Some(chosen) => game
+                .valid_actions(me)
+                .into_iter()
+                .filter(|a| std::mem::discriminant(a) == std::mem::discriminant(chosen))
+                .filter_map(|a| score(&a).map(|score| CandidateScore { action: a, score }))
+                .collect(),
+            None => Vec::new(),
+        };
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        // The opponent on the receiving end: a steal victim, the leader on
+        // the robber's tile, or whoever else wanted the road spot
+        let threatened = match &action {
+            Some(GameAction::StealFrom(victim)) => Some(*victim),
+            Some(GameAction::MoveRobber(hex)) => game
+                .board
+                .players_adjacent_to_hex(hex)
+                .into_iter()
+                .filter(|p| *p != me)
+                .max_by_key(|p| (game.total_victory_points(*p), std::cmp::Reverse(*p))),
+            Some(GameAction::BuildRoad(edge)) | Some(GameAction::PlaceInitialRoad(edge)) => {
+                (0..game.players.len() as PlayerId)
+                    .filter(|p| *p != me)
+                    .find(|p| game.board.valid_road_spots(*p).contains(edge))
+            }
+            _ => None,
+        };
+
+        BotExplanation {
+            reason: describe(action.as_ref()).to_string(),
+            action,
+            candidates,
+            target_build: self.next_build(game),
+            threatened,
+        }
+    }
+}
+
+/// One line on what a kind of move is for
+fn describe(action: Option<&GameAction>) -> &'static str {
+    let Some(action) = action else {
+        return "Nothing to do";
+    };
+    match action {
+        GameAction::PlaceInitialSettlement(_) => "Settle the best opening spot",
+        GameAction::PlaceInitialRoad(_) => "Point the road at the next settlement spot",
+        GameAction::RollDice => "Roll to start the turn",
+        GameAction::MoveRobber(_) | GameAction::MovePirate(_) => "Block an opponent's best tile",
+        GameAction::StealFrom(_) => "Steal from the most dangerous opponent",
+        GameAction::DiscardCards(_) => "Discard from the largest piles",
+        GameAction::BuildRoad(_) => "Extend toward new settlement spots",
+        GameAction::BuildSettlement(_) => "Settle the best reachable spot",
+        GameAction::BuildCity(_) => "Upgrade a settlement for double production",
+        GameAction::BuyDevelopmentCard => "Buy a development card",
+        GameAction::PlayKnight => "Play a knight toward Largest Army",
+        GameAction::PlayRoadBuilding(..) => "Two free roads toward a new spot or Longest Road",
+        GameAction::PlayYearOfPlenty(..) => "Take the cards that finish the next build",
+        GameAction::PlayMonopoly(_) => "Take the resource opponents most likely hold",
+        GameAction::MaritimeTrade { .. } => "Trade spare cards to finish the next build",
+        GameAction::AcceptTrade => "The offer is worth taking",
+        GameAction::RejectTrade => "The offer isn't worth taking",
+        GameAction::EndTurn => "Nothing worth building; end the turn",
+        _ => "Take a valid action",
+    }
+}
+
+impl BotPlayer for Bot {
+    fn player_id(&self) -> PlayerId {
+        self.player_id
+    }
+
+    fn observe(&mut self, game: &GameState, events: &[GameEvent]) {
+        self.hands.observe(game, events);
+    }
+
+    /// Choose an action from the valid actions
+    fn choose_action(&mut self, game: &GameState) -> Option<GameAction> {
+        self.choose_action_until(game, None)
+    }
+
+    /// Only the Expert search takes long enough to need the deadline; when
+    /// it runs out before the search gets going the Hard heuristics answer
+    fn choose_action_until(
+        &mut self,
+        game: &GameState,
+        deadline: Option<Instant>,
+    ) -> Option<GameAction> {
+        let valid_actions = game.valid_actions(self.player_id);
+        if valid_actions.is_empty() {
+            return None;
+        }
+
+        // Discards and trade answers need more than picking from the list
+        if valid_actions.iter().any(|a| matches!(a, GameAction::DiscardCards(_))) {
+            return Some(GameAction::DiscardCards(self.choose_discard(game)));
+        }
+        if let Some(trade) = &game.pending_trade {
+            if valid_actions.contains(&GameAction::AcceptTrade) {
+                return Some(if self.respond_to_trade(game, &trade.offer) {
+                    GameAction::AcceptTrade
+                } else {
+                    GameAction::RejectTrade
+                });
+            }
+        }
+
+        match self.difficulty {
+            BotDifficulty::Easy => self.choose_easy(&valid_actions),
+            BotDifficulty::Medium => self.choose_medium(game, &valid_actions),
+            BotDifficulty::Hard => self.choose_hard(game, &valid_actions),
+            BotDifficulty::Expert => {
+                let player = self.player_id;
+                crate::mcts::search_until(game, player, &self.mcts, deadline, &mut self.rng)
+                    .or_else(|| self.choose_hard(game, &valid_actions))
+            }
+        }
+    }
+
+    fn respond_to_trade(&mut self, game: &GameState, offer: &TradeOffer) -> bool {
+        let Some(player) = game.get_player(self.player_id) else {
+            return false;
+        };
+        if !player.resources.can_afford(&offer.requesting) {
+            return false;
+        }
+        let fair = offer.offering.total() as i32 + self.weights.trade_generosity
+            >= offer.requesting.total() as i32;
+
+        match self.difficulty {
+            BotDifficulty::Easy => self.rng.gen_bool(self.weights.easy_trade_accept_chance),
+            // Take trades that don't shrink our hand (by more than we're
+            // willing to give)
+            BotDifficulty::Medium => fair,
+            // ...unless they help someone close to winning
+            BotDifficulty::Hard | BotDifficulty::Expert => {
+                let margin = self.weights.hard_trade_leader_margin;
+                let leader = game.total_victory_points(offer.from) + margin
+                    >= game.victory_points_to_win();
+                fair && !leader
+            }
+        }
+    }
+}
+
+/// Cards short of `cost`, one entry per missing card
+fn missing(hand: &ResourceHand, cost: &ResourceHand) -> Vec<Resource> {
+    Resource::ALL
+        .into_iter()
+        .flat_map(|r| std::iter::repeat_n(r, cost.get(r).saturating_sub(hand.get(r)) as usize))
+        .collect()
 }
 
 /// Handle discard phase for bot
@@ -576,6 +1408,7 @@ pub fn bot_discard(game: &GameState, player_id: PlayerId) -> ResourceHand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hex::VertexDirection;
 
     #[test]
     fn test_bot_creation() {
@@ -618,6 +1451,217 @@ mod tests {
         }
     }
 
+    /// Always takes the first valid action
+    struct FirstAction(PlayerId);
+
+    impl BotPlayer for FirstAction {
+        fn player_id(&self) -> PlayerId {
+            self.0
+        }
+
+        fn choose_action(&mut self, game: &GameState) -> Option<GameAction> {
+            game.valid_actions(self.0).into_iter().next()
+        }
+    }
+
+    #[test]
+    fn test_custom_and_builtin_bots_share_the_trait() {
+        let mut game = GameState::new(2, vec!["Custom".into(), "Builtin".into()]);
+        let mut bots: Vec<Box<dyn BotPlayer>> = vec![
+            Box::new(FirstAction(0)),
+            Box::new(Bot::with_seed(1, BotDifficulty::Medium, 3)),
+        ];
+
+        while matches!(game.phase, crate::game::GamePhase::Setup { .. }) {
+            let bot = &mut bots[game.current_player as usize];
+            let action = bot.choose_action(&game).unwrap();
+            game.apply_action(bot.player_id(), action).unwrap();
+        }
+        assert_eq!(game.phase, crate::game::GamePhase::PreRoll);
+        let offer = TradeOffer::new(1, Some(0), ResourceHand::new(), ResourceHand::new());
+        assert!(!bots[0].respond_to_trade(&game, &offer));
+    }
+
+    /// A seeded game just past setup, in the current player's main phase
+    /// with `card` in hand
+    fn main_phase_with(card: crate::player::DevelopmentCard) -> GameState {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let mut game =
+            GameState::new_with_seed(3, names, crate::options::GameOptions::default(), 8);
+        complete_setup(&mut game);
+        game.phase = GamePhase::MainPhase;
+        for player in &mut game.players {
+            player.resources = ResourceHand::new();
+        }
+        game.players[game.current_player as usize].dev_cards.push(card);
+        game
+    }
+
+    #[test]
+    fn test_year_of_plenty_completes_next_build() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::YearOfPlenty);
+        let me = game.current_player;
+        // One ore short of a city
+        game.players[me as usize].resources = ResourceHand::with_amounts(0, 0, 2, 2, 0);
+
+        let mut bot = Bot::with_seed(me, BotDifficulty::Medium, 1);
+        let action = bot.choose_action(&game);
+        assert!(matches!(action, Some(GameAction::PlayYearOfPlenty(Resource::Ore, _))));
+
+        // Three cards short: keep the card for later
+        game.players[me as usize].resources = ResourceHand::new();
+        assert_eq!(bot.choose_action(&game), Some(GameAction::EndTurn));
+    }
+
+    #[test]
+    fn test_monopoly_waits_for_a_haul() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::Monopoly);
+        let me = game.current_player;
+        let mut bot = Bot::with_seed(me, BotDifficulty::Hard, 1);
+        assert_eq!(bot.choose_action(&game), Some(GameAction::EndTurn));
+
+        let other = (me + 1) % 3;
+        game.players[other as usize].resources = ResourceHand::with_amounts(4, 4, 4, 4, 4);
+        assert!(matches!(bot.choose_action(&game), Some(GameAction::PlayMonopoly(_))));
+    }
+
+    #[test]
+    fn test_road_building_reaches_a_new_settlement_spot() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::RoadBuilding);
+        let me = game.current_player;
+        assert!(game.board.valid_settlement_spots(me, false).is_empty());
+
+        let mut bot = Bot::with_seed(me, BotDifficulty::Hard, 1);
+        let Some(GameAction::PlayRoadBuilding(first, second)) = bot.choose_action(&game) else {
+            panic!("expected the bot to play Road Building");
+        };
+        game.apply_action(me, GameAction::PlayRoadBuilding(first, second)).unwrap();
+        for planned in [first, second] {
+            let action = bot.choose_action(&game).unwrap();
+            assert_eq!(action, GameAction::BuildRoad(planned));
+            game.apply_action(me, action).unwrap();
+        }
+
+        assert_eq!(game.phase, GamePhase::MainPhase);
+        assert!(!game.board.valid_settlement_spots(me, false).is_empty());
+    }
+
+    #[test]
+    fn test_edge_scoring_avoids_blocked_roads() {
+        let mut game =
+            GameState::new_with_seed(2, vec!["A".into(), "B".into()], Default::default(), 2);
+        let home = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
+        game.board.place_settlement(home, 0);
+        let bot = Bot::with_seed(0, BotDifficulty::Hard, 1);
+
+        // Every road out of a central settlement leads somewhere
+        let edges = home.touching_edges();
+        assert!(edges.iter().all(|e| bot.score_edge_expansion(&game, e) > 0));
+
+        // ...until an opponent settles at the far end of one
+        let blocked = edges[0];
+        let far = blocked.endpoints().into_iter().find(|v| *v != home).unwrap();
+        game.board.place_settlement(far, 1);
+        let score = |e: &EdgeCoord| bot.score_edge_expansion(&game, e);
+        assert!(edges[1..].iter().all(|e| score(e) > score(&blocked)));
+    }
+
+    #[test]
+    fn test_weights_load_with_defaults_for_missing_fields() {
+        let weights = BotWeights::from_json(r#"{ "robber_self": 0, "medium_road_chance": 1.0 }"#)
+            .unwrap();
+        assert_eq!(weights.robber_self, 0);
+        assert_eq!(weights.medium_road_chance, 1.0);
+        assert_eq!(weights.robber_pips, BotWeights::default().robber_pips);
+        assert!(BotWeights::from_json(r#"{ "robber_self": "lots" }"#).is_err());
+
+        // A bot that doesn't mind robbing itself scores its own tiles higher
+        let names = vec!["A".into(), "B".into()];
+        let mut game = GameState::new_with_seed(2, names, Default::default(), 5);
+        let home = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
+        game.board.place_settlement(home, 0);
+        let hex = HexCoord::new(0, 0);
+        let careful = Bot::with_seed(0, BotDifficulty::Hard, 1);
+        let careless = Bot::with_seed(0, BotDifficulty::Hard, 1).with_weights(weights);
+        assert_eq!(
+            careless.score_robber_spot(&game, &hex) - careful.score_robber_spot(&game, &hex),
+            BotWeights::default().robber_self
+        );
+    }
+
+    #[test]
+    fn test_port_trader_trades_to_finish_a_build() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::VictoryPoint);
+        let me = game.current_player;
+        // One grain short of a city, with spare brick for the bank
+        game.players[me as usize].resources = ResourceHand::with_amounts(4, 0, 3, 1, 0);
+
+        let mut balanced = Bot::with_seed(me, BotDifficulty::Hard, 1);
+        assert_eq!(balanced.choose_action(&game), Some(GameAction::EndTurn));
+
+        let mut trader =
+            Bot::with_seed(me, BotDifficulty::Hard, 1).with_personality(BotPersonality::PortTrader);
+        let Some(GameAction::MaritimeTrade { give, receive, .. }) = trader.choose_action(&game)
+        else {
+            panic!("expected a maritime trade");
+        };
+        assert_eq!((give, receive), (Resource::Brick, Resource::Grain));
+    }
+
+    #[test]
+    fn test_personalities_change_trade_willingness() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::VictoryPoint);
+        let me = game.current_player;
+        let other = (me + 1) % 3;
+        game.players[me as usize].resources = ResourceHand::with_amounts(2, 0, 0, 0, 0);
+        // Two brick for one ore
+        let offer = TradeOffer::new(
+            other,
+            Some(me),
+            ResourceHand::with_amounts(0, 0, 1, 0, 0),
+            ResourceHand::with_amounts(2, 0, 0, 0, 0),
+        );
+
+        let bot = |personality| {
+            Bot::with_seed(me, BotDifficulty::Medium, 1).with_personality(personality)
+        };
+        assert!(!bot(BotPersonality::Balanced).respond_to_trade(&game, &offer));
+        assert!(bot(BotPersonality::PortTrader).respond_to_trade(&game, &offer));
+        assert!(BotPersonality::ALL.iter().all(|p| p.weights() == bot(*p).weights));
+    }
+
+    #[test]
+    fn test_explained_robber_move_names_its_target() {
+        let mut game = main_phase_with(crate::player::DevelopmentCard::VictoryPoint);
+        let me = game.current_player;
+        game.phase = GamePhase::RobberMoveRequired;
+
+        let mut bot = Bot::with_seed(me, BotDifficulty::Hard, 1);
+        let explained = bot.choose_action_explained(&game);
+        let Some(GameAction::MoveRobber(hex)) = explained.action else {
+            panic!("expected a robber move");
+        };
+
+        // The chosen tile tops the scored alternatives
+        assert_eq!(explained.candidates[0].action, GameAction::MoveRobber(hex));
+        let robber_moves = game
+            .valid_actions(me)
+            .iter()
+            .filter(|a| matches!(a, GameAction::MoveRobber(_)))
+            .count();
+        assert_eq!(explained.candidates.len(), robber_moves);
+        assert!(explained
+            .candidates
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+
+        // ...and hits an opponent living there
+        let threatened = explained.threatened.expect("robber should target someone");
+        assert_ne!(threatened, me);
+        assert!(game.board.players_adjacent_to_hex(&hex).contains(&threatened));
+        assert_eq!(explained.target_build, Some(BuildTarget::City));
+    }
+
     #[test]
     fn test_discard_logic() {
         let game = GameState::new(2, vec!["A".into(), "B".into()]);
@@ -0,0 +1,403 @@
+//! Cities & Knights commodities, city improvements and progress cards.
+//!
+//! With `GameOptions::expansion` set to [`Expansion::CitiesAndKnights`],
+//! a city next to a forest, pasture or mountain produces one resource and
+//! one commodity (paper, cloth or coin) instead of two resources.
+//! Commodities buy city improvements on three tracks. Whenever the event
+//! die shows a city gate, every player who has improved that gate's track
+//! far enough draws from its progress card deck: level 1 draws on a red
+//! die of 1 or 2, and each level adds one more number. The first of the
+//! two dice is the red die.
+//!
+//! Knights, metropolises and playing progress cards are still to come.
+//!
+//! [`Expansion::CitiesAndKnights`]: crate::options::Expansion::CitiesAndKnights
+
+use crate::barbarians::EventFace;
+use crate::board::{Board, PlayerId, Resource, VertexBuilding};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Highest level of a city improvement track
+pub const MAX_IMPROVEMENT_LEVEL: u32 = 5;
+
+/// A commodity, made by cities on some resource tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Commodity {
+    /// From forests
+    Paper,
+    /// From pastures
+    Cloth,
+    /// From mountains
+    Coin,
+}
+
+impl Commodity {
+    pub const ALL: [Commodity; 3] = [Commodity::Paper, Commodity::Cloth, Commodity::Coin];
+
+    /// The commodity a city makes from a tile of `resource`, if any
+    pub fn from_resource(resource: Resource) -> Option<Commodity> {
+        match resource {
+            Resource::Lumber => Some(Commodity::Paper),
+            Resource::Wool => Some(Commodity::Cloth),
+            Resource::Ore => Some(Commodity::Coin),
+            Resource::Brick | Resource::Grain => None,
+        }
+    }
+
+    /// The resource whose tiles make this commodity
+    pub fn resource(self) -> Resource {
+        match self {
+            Commodity::Paper => Resource::Lumber,
+            Commodity::Cloth => Resource::Wool,
+            Commodity::Coin => Resource::Ore,
+        }
+    }
+}
+
+/// Commodity cards held by a player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommodityHand {
+    pub paper: u32,
+    pub cloth: u32,
+    pub coin: u32,
+}
+
+impl CommodityHand {
+    pub fn get(&self, commodity: Commodity) -> u32 {
+        match commodity {
+            Commodity::Paper => self.paper,
+            Commodity::Cloth => self.cloth,
+            Commodity::Coin => self.coin,
+        }
+    }
+
+    fn get_mut(&mut self, commodity: Commodity) -> &mut u32 {
+        match commodity {
+            Commodity::Paper => &mut self.paper,
+            Commodity::Cloth => &mut self.cloth,
+            Commodity::Coin => &mut self.coin,
+        }
+    }
+
+    pub fn add(&mut self, commodity: Commodity, amount: u32) {
+        *self.get_mut(commodity) += amount;
+    }
+
+    /// Take `amount` away. Returns false, leaving the hand alone, if there
+    /// aren't that many.
+    pub fn remove(&mut self, commodity: Commodity, amount: u32) -> bool {
+        let held = self.get_mut(commodity);
+        if *held < amount {
+            return false;
+        }
+        *held -= amount;
+        true
+    }
+
+    pub fn total(&self) -> u32 {
+        self.paper + self.cloth + self.coin
+    }
+}
+
+impl fmt::Display for CommodityHand {
+    /// The non-zero counts, like `2 Cloth, 1 Coin`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = Commodity::ALL
+            .into_iter()
+            .filter(|&c| self.get(c) > 0)
+            .map(|c| format!("{} {:?}", self.get(c), c))
+            .collect();
+        if counts.is_empty() {
+            write!(f, "nothing")
+        } else {
+            write!(f, "{}", counts.join(", "))
+        }
+    }
+}
+
+/// A city improvement track, each with its own city gate and deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Improvement {
+    /// Paid in cloth
+    Trade,
+    /// Paid in coin
+    Politics,
+    /// Paid in paper
+    Science,
+}
+
+impl Improvement {
+    pub const ALL: [Improvement; 3] =
+        [Improvement::Trade, Improvement::Politics, Improvement::Science];
+
+    /// The commodity the track is paid in
+    pub fn commodity(self) -> Commodity {
+        match self {
+            Improvement::Trade => Commodity::Cloth,
+            Improvement::Politics => Commodity::Coin,
+            Improvement::Science => Commodity::Paper,
+        }
+    }
+
+    /// The track whose city gate is on this face of the event die
+    pub fn for_face(face: EventFace) -> Option<Improvement> {
+        match face {
+            EventFace::Ship => None,
+            EventFace::Trade => Some(Improvement::Trade),
+            EventFace::Politics => Some(Improvement::Politics),
+            EventFace::Science => Some(Improvement::Science),
+        }
+    }
+
+    /// Commodities it takes to reach `level`
+    pub fn cost(level: u32) -> u32 {
+        level
+    }
+}
+
+/// How far a player has improved their cities on each track
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Improvements {
+    pub trade: u32,
+    pub politics: u32,
+    pub science: u32,
+}
+
+impl Improvements {
+    pub fn level(&self, track: Improvement) -> u32 {
+        match track {
+            Improvement::Trade => self.trade,
+            Improvement::Politics => self.politics,
+            Improvement::Science => self.science,
+        }
+    }
+
+    fn raise(&mut self, track: Improvement) {
+        match track {
+            Improvement::Trade => self.trade += 1,
+            Improvement::Politics => self.politics += 1,
+            Improvement::Science => self.science += 1,
+        }
+    }
+
+    /// Whether a red die of `red` lets this player draw a card from the
+    /// track's deck
+    pub fn draws_on(&self, track: Improvement, red: u8) -> bool {
+        let level = self.level(track);
+        level > 0 && u32::from(red) <= level + 1
+    }
+}
+
+/// A progress card
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProgressCard {
+    // Trade
+    CommercialHarbor,
+    MasterMerchant,
+    Merchant,
+    MerchantFleet,
+    ResourceMonopoly,
+    TradeMonopoly,
+    // Politics
+    Bishop,
+    Constitution,
+    Deserter,
+    Diplomat,
+    Intrigue,
+    Saboteur,
+    Spy,
+    Warlord,
+    Wedding,
+    // Science
+    Alchemist,
+    Crane,
+    Engineer,
+    Inventor,
+    Irrigation,
+    Medicine,
+    Mining,
+    Printer,
+    RoadBuilding,
+    Smith,
+}
+
+impl ProgressCard {
+    /// The 18 cards of a track's deck, unshuffled
+    pub fn standard_deck(track: Improvement) -> Vec<ProgressCard> {
+        use ProgressCard::*;
+        let counts: &[(ProgressCard, usize)] = match track {
+            Improvement::Trade => &[
+                (CommercialHarbor, 2),
+                (MasterMerchant, 2),
+                (Merchant, 6),
+                (MerchantFleet, 2),
+                (ResourceMonopoly, 4),
+                (TradeMonopoly, 2),
+            ],
+            Improvement::Politics => &[
+                (Bishop, 2),
+                (Constitution, 1),
+                (Deserter, 2),
+                (Diplomat, 2),
+                (Intrigue, 2),
+                (Saboteur, 2),
+                (Spy, 3),
+                (Warlord, 2),
+                (Wedding, 2),
+            ],
+            Improvement::Science => &[
+                (Alchemist, 2),
+                (Crane, 2),
+                (Engineer, 1),
+                (Inventor, 2),
+                (Irrigation, 2),
+                (Medicine, 2),
+                (Mining, 2),
+                (Printer, 1),
+                (RoadBuilding, 2),
+                (Smith, 2),
+            ],
+        };
+        counts
+            .iter()
+            .flat_map(|&(card, n)| std::iter::repeat_n(card, n))
+            .collect()
+    }
+}
+
+/// A player's commodities, improvements and progress cards
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CitiesKnightsPlayer {
+    pub commodities: CommodityHand,
+    pub improvements: Improvements,
+    pub progress_cards: Vec<ProgressCard>,
+}
+
+/// Everything the expansion adds to a game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CitiesKnights {
+    /// Indexed by player ID
+    pub players: Vec<CitiesKnightsPlayer>,
+    /// Progress card decks, shuffled, drawn from the end
+    decks: BTreeMap<Improvement, Vec<ProgressCard>>,
+}
+
+impl CitiesKnights {
+    /// Empty hands for everyone and three shuffled decks
+    pub fn new<R: Rng>(player_count: usize, rng: &mut R) -> Self {
+        let decks = Improvement::ALL
+            .into_iter()
+            .map(|track| {
+                let mut deck = ProgressCard::standard_deck(track);
+                deck.shuffle(rng);
+                (track, deck)
+            })
+            .collect();
+        Self {
+            players: vec![CitiesKnightsPlayer::default(); player_count],
+            decks,
+        }
+    }
+
+    pub fn player(&self, player: PlayerId) -> Option<&CitiesKnightsPlayer> {
+        self.players.get(player as usize)
+    }
+
+    pub fn player_mut(&mut self, player: PlayerId) -> Option<&mut CitiesKnightsPlayer> {
+        self.players.get_mut(player as usize)
+    }
+
+    /// Cards left in a track's deck
+    pub fn deck_remaining(&self, track: Improvement) -> u32 {
+        self.decks.get(&track).map_or(0, |deck| deck.len() as u32)
+    }
+
+    /// The level `player` would reach next on `track`, if it isn't complete
+    pub fn next_level(&self, player: PlayerId, track: Improvement) -> Option<u32> {
+        let level = self.player(player)?.improvements.level(track) + 1;
+        (level <= MAX_IMPROVEMENT_LEVEL).then_some(level)
+    }
+
+    /// Commodities `player` lacks for the next level of `track`
+    pub fn shortfall(&self, player: PlayerId, track: Improvement) -> CommodityHand {
+        let mut missing = CommodityHand::default();
+        if let (Some(p), Some(level)) = (self.player(player), self.next_level(player, track)) {
+            let held = p.commodities.get(track.commodity());
+            missing.add(track.commodity(), Improvement::cost(level).saturating_sub(held));
+        }
+        missing
+    }
+
+    /// Pay for the next level of `track`. Returns false, changing nothing,
+    /// if the track is complete or the player can't pay.
+    pub fn improve(&mut self, player: PlayerId, track: Improvement) -> bool {
+        let Some(level) = self.next_level(player, track) else {
+            return false;
+        };
+        let Some(p) = self.player_mut(player) else {
+            return false;
+        };
+        if !p.commodities.remove(track.commodity(), Improvement::cost(level)) {
+            return false;
+        }
+        p.improvements.raise(track);
+        true
+    }
+
+    /// Draw the top card of a track's deck for `player`
+    pub fn draw(&mut self, player: PlayerId, track: Improvement) -> Option<ProgressCard> {
+        let card = self.decks.get_mut(&track)?.pop()?;
+        self.player_mut(player)?.progress_cards.push(card);
+        Some(card)
+    }
+}
+
+/// Commodities made by each player's cities for a roll of `roll`
+pub fn commodities_for_roll(board: &Board, roll: u8) -> HashMap<PlayerId, CommodityHand> {
+    let mut made: HashMap<PlayerId, CommodityHand> = HashMap::new();
+    for tile in board.land_tiles() {
+        if tile.dice_number != Some(roll) || tile.has_robber {
+            continue;
+        }
+        let Some(commodity) = tile.resource().and_then(Commodity::from_resource) else {
+            continue;
+        };
+        for vertex in tile.coord.vertices() {
+            if let VertexBuilding::City(owner) = board.get_vertex(&vertex) {
+                made.entry(owner).or_default().add(commodity, 1);
+            }
+        }
+    }
+    made
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+
+    #[test]
+    fn test_improvements_cost_their_level_in_commodities() {
+        let mut ck = CitiesKnights::new(2, &mut GameRng::new(1));
+        assert_eq!(ck.deck_remaining(Improvement::Politics), 18);
+        ck.players[0].commodities.add(Commodity::Cloth, 3);
+
+        assert!(ck.improve(0, Improvement::Trade));
+        assert!(ck.improve(0, Improvement::Trade));
+        // Level 3 would take three more
+        assert!(!ck.improve(0, Improvement::Trade));
+        assert!(!ck.improve(0, Improvement::Science));
+        assert_eq!(ck.players[0].improvements.trade, 2);
+        assert_eq!(ck.players[0].commodities, CommodityHand::default());
+
+        // Level 2 draws on a red 1, 2 or 3
+        let levels = ck.players[0].improvements;
+        assert!(levels.draws_on(Improvement::Trade, 3));
+        assert!(!levels.draws_on(Improvement::Trade, 4));
+        assert!(!levels.draws_on(Improvement::Science, 1));
+    }
+}
@@ -0,0 +1,95 @@
+//! Chess-clock style time banks.
+//!
+//! The engine never reads the system clock. Hosts (the server or the browser
+//! via WASM) measure elapsed time themselves and feed it in through
+//! `GameState::tick`, which charges whoever the game is waiting on.
+
+use crate::board::PlayerId;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a player's time bank runs out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlagFallPolicy {
+    /// Make default choices for the player until the game no longer waits on
+    /// them (which ends their turn if it is theirs)
+    #[default]
+    AutoPass,
+    /// Like `AutoPass`, but a player holding more than 7 cards first loses
+    /// half of them, as if a 7 had been rolled
+    AutoDiscard,
+    /// The player concedes the game
+    Forfeit,
+}
+
+/// Time bank settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    /// Starting time for each player, in milliseconds
+    pub initial_ms: u64,
+    /// Time added to a player's bank at the start of each of their turns
+    #[serde(default)]
+    pub increment_ms: u64,
+    /// Behavior on flag fall
+    #[serde(default)]
+    pub on_flag_fall: FlagFallPolicy,
+}
+
+/// Remaining time for each player
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    /// Milliseconds left, indexed by player ID
+    pub remaining_ms: Vec<u64>,
+}
+
+impl GameClock {
+    /// Start every player with the initial bank
+    pub fn new(control: &TimeControl, player_count: usize) -> Self {
+        Self {
+            remaining_ms: vec![control.initial_ms; player_count],
+        }
+    }
+
+    /// Time left for a player
+    pub fn remaining(&self, player: PlayerId) -> u64 {
+        self.remaining_ms.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Charge elapsed time to a player. Returns true if their flag has fallen.
+    pub fn spend(&mut self, player: PlayerId, elapsed_ms: u64) -> bool {
+        match self.remaining_ms.get_mut(player as usize) {
+            Some(ms) => {
+                *ms = ms.saturating_sub(elapsed_ms);
+                *ms == 0
+            }
+            None => false,
+        }
+    }
+
+    /// Add time to a player's bank
+    pub fn add(&mut self, player: PlayerId, ms: u64) {
+        if let Some(remaining) = self.remaining_ms.get_mut(player as usize) {
+            *remaining += ms;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spend_saturates_and_reports_flag() {
+        let control = TimeControl {
+            initial_ms: 1000,
+            increment_ms: 0,
+            on_flag_fall: FlagFallPolicy::AutoPass,
+        };
+        let mut clock = GameClock::new(&control, 2);
+
+        assert!(!clock.spend(0, 400));
+        assert_eq!(clock.remaining(0), 600);
+        assert!(clock.spend(0, 5000));
+        assert_eq!(clock.remaining(0), 0);
+        assert_eq!(clock.remaining(1), 1000);
+    }
+}
@@ -0,0 +1,348 @@
+//! What changed between two views of a game.
+//!
+//! A [`StateDelta`] carries only the parts of a [`GameStateJson`] that
+//! differ from an earlier one, so a server can send each change instead of
+//! the whole state. Players are sent one by one; everything else goes
+//! whole when it changes. Applying the delta to the earlier state gives
+//! the later one exactly.
+//!
+//! [`differences`] is for people rather than the wire: it lists every
+//! field that differs between two states, down to single cards and board
+//! cells, to track down where a client fell out of step with the server or
+//! a replay went another way.
+
+use crate::barbarians::{BarbarianTrack, EventFace};
+use crate::board::{BoardJson, PlayerId};
+use crate::cities_knights::CitiesKnightsPlayer;
+use crate::clock::GameClock;
+use crate::game::{GamePhase, GameStateJson, TradeState};
+use crate::player::{DevCardCounts, Player};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The changes from one game state to another. Fields left out did not
+/// change. A field that was set and is now cleared is sent as null.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<BoardJson>,
+    /// Players who changed, whole
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<Player>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_player: Option<PlayerId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<GamePhase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_number: Option<u32>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub dice_roll: Option<Option<(u8, u8)>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_card_deck_size: Option<usize>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub pending_trade: Option<Option<TradeState>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub victory_points: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hand_sizes: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_card_counts: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_cards_revealed: Option<DevCardCounts>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub clock: Option<Option<GameClock>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub event_roll: Option<Option<EventFace>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub barbarians: Option<Option<BarbarianTrack>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub cities_knights: Option<Option<Vec<CitiesKnightsPlayer>>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub dice_deck_remaining: Option<Option<u32>>,
+}
+
+/// A field that is there, even as null, changed
+fn present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// The new value, if it differs from the old
+fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> Option<T> {
+    (old != new).then(|| new.clone())
+}
+
+impl StateDelta {
+    /// What changed from `old` to `new`
+    pub fn between(old: &GameStateJson, new: &GameStateJson) -> Self {
+        let GameStateJson {
+            board,
+            players,
+            current_player,
+            phase,
+            turn_number,
+            dice_roll,
+            dev_card_deck_size,
+            pending_trade,
+            victory_points,
+            hand_sizes,
+            dev_card_counts,
+            dev_cards_revealed,
+            clock,
+            event_roll,
+            barbarians,
+            cities_knights,
+            dice_deck_remaining,
+        } = new;
+
+        Self {
+            board: changed(&old.board, board),
+            players: players
+                .iter()
+                .filter(|p| old.players.get(p.id as usize) != Some(p))
+                .cloned()
+                .collect(),
+            current_player: changed(&old.current_player, current_player),
+            phase: changed(&old.phase, phase),
+            turn_number: changed(&old.turn_number, turn_number),
+            dice_roll: changed(&old.dice_roll, dice_roll),
+            dev_card_deck_size: changed(&old.dev_card_deck_size, dev_card_deck_size),
+            pending_trade: changed(&old.pending_trade, pending_trade),
+            victory_points: changed(&old.victory_points, victory_points),
+            hand_sizes: changed(&old.hand_sizes, hand_sizes),
+            dev_card_counts: changed(&old.dev_card_counts, dev_card_counts),
+            dev_cards_revealed: changed(&old.dev_cards_revealed, dev_cards_revealed),
+            clock: changed(&old.clock, clock),
+            event_roll: changed(&old.event_roll, event_roll),
+            barbarians: changed(&old.barbarians, barbarians),
+            cities_knights: changed(&old.cities_knights, cities_knights),
+            dice_deck_remaining: changed(&old.dice_deck_remaining, dice_deck_remaining),
+        }
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Bring `state` up to date with the changes
+    pub fn apply(self, state: &mut GameStateJson) {
+        if let Some(board) = self.board {
+            state.board = board;
+        }
+        for player in self.players {
+            match state.players.get_mut(player.id as usize) {
+                Some(slot) => *slot = player,
+                None => state.players.push(player),
+            }
+        }
+        if let Some(current_player) = self.current_player {
+            state.current_player = current_player;
+        }
+        if let Some(phase) = self.phase {
+            state.phase = phase;
+        }
+        if let Some(turn_number) = self.turn_number {
+            state.turn_number = turn_number;
+        }
+        if let Some(dice_roll) = self.dice_roll {
+            state.dice_roll = dice_roll;
+        }
+        if let Some(size) = self.dev_card_deck_size {
+            state.dev_card_deck_size = size;
+        }
+        if let Some(pending_trade) = self.pending_trade {
+            state.pending_trade = pending_trade;
+        }
+        if let Some(victory_points) = self.victory_points {
+            state.victory_points = victory_points;
+        }
+        if let Some(hand_sizes) = self.hand_sizes {
+            state.hand_sizes = hand_sizes;
+        }
+        if let Some(dev_card_counts) = self.dev_card_counts {
+            state.dev_card_counts = dev_card_counts;
+        }
+        if let Some(dev_cards_revealed) = self.dev_cards_revealed {
+            state.dev_cards_revealed = dev_cards_revealed;
+        }
+        if let Some(clock) = self.clock {
+            state.clock = clock;
+        }
+        if let Some(event_roll) = self.event_roll {
+            state.event_roll = event_roll;
+        }
+        if let Some(barbarians) = self.barbarians {
+            state.barbarians = barbarians;
+        }
+        if let Some(cities_knights) = self.cities_knights {
+            state.cities_knights = cities_knights;
+        }
+        if let Some(dice_deck_remaining) = self.dice_deck_remaining {
+            state.dice_deck_remaining = dice_deck_remaining;
+        }
+    }
+}
+
+/// One field that differs between two states
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDifference {
+    /// Where, e.g. `players[1].resources.ore` or
+    /// `board.vertices.(0, -1) North.building`
+    pub field: String,
+    /// The value on each side as JSON, or `missing`
+    pub left: String,
+    pub right: String,
+}
+
+impl fmt::Display for StateDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} vs {}", self.field, self.left, self.right)
+    }
+}
+
+/// Every field that differs from `left` to `right`
+pub fn differences(left: &GameStateJson, right: &GameStateJson) -> Vec<StateDifference> {
+    let json = |state| serde_json::to_value(state).unwrap_or_default();
+    diff_json(json(left), json(right))
+}
+
+/// Every field that differs between two serialized states. Board cells are
+/// matched by coordinate, since the board lists them in no fixed order.
+pub(crate) fn diff_json(mut left: Value, mut right: Value) -> Vec<StateDifference> {
+    for state in [&mut left, &mut right] {
+        for cells in ["tiles", "vertices", "edges"] {
+            if let Some(list) = state["board"].get_mut(cells) {
+                *list = by_coordinate(list.take());
+            }
+        }
+    }
+    let mut found = Vec::new();
+    diff_values(String::new(), Some(&left), Some(&right), &mut found);
+    found
+}
+
+/// Board cells as an object keyed like `(q, r)` or `(q, r) North`
+fn by_coordinate(list: Value) -> Value {
+    let Value::Array(cells) = list else {
+        return list;
+    };
+    let mut keyed = Map::new();
+    for mut cell in cells {
+        let Some(fields) = cell.as_object_mut() else {
+            continue;
+        };
+        let q = fields.remove("q").or_else(|| fields.remove("hex_q"));
+        let r = fields.remove("r").or_else(|| fields.remove("hex_r"));
+        let mut key = format!("({}, {})", q.unwrap_or_default(), r.unwrap_or_default());
+        if let Some(Value::String(direction)) = fields.remove("direction") {
+            key = format!("{} {}", key, direction);
+        }
+        keyed.insert(key, cell);
+    }
+    Value::Object(keyed)
+}
+
+fn diff_values(
+    path: String,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    found: &mut Vec<StateDifference>,
+) {
+    let field = |key: &str| match path.as_str() {
+        "" => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    };
+    match (left, right) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+            for key in keys {
+                diff_values(field(key), l.get(key), r.get(key), found);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                diff_values(format!("{}[{}]", path, i), l.get(i), r.get(i), found);
+            }
+        }
+        _ if left == right => {}
+        _ => {
+            let show = |v: Option<&Value>| v.map_or("missing".to_string(), Value::to_string);
+            found.push(StateDifference {
+                field: path,
+                left: show(left),
+                right: show(right),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::options::GameOptions;
+
+    #[test]
+    fn test_deltas_rebuild_each_state() {
+        let names = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+        let mut game = GameState::new_with_seed(3, names, GameOptions::default(), 7);
+        let mut client = game.to_json_friendly();
+        let mut cleared = false;
+
+        for _ in 0..300 {
+            let Some(&player) = game.waiting_on().first() else {
+                break;
+            };
+            let action = game.auto_action(player).unwrap();
+            let before = game.to_json_friendly();
+            game.apply_action(player, action).unwrap();
+            let after = game.to_json_friendly();
+
+            let delta = StateDelta::between(&before, &after);
+            assert!(delta.players.len() <= after.players.len());
+            cleared |= delta.dice_roll == Some(None);
+            // Sent over the wire and applied on the other side
+            let json = serde_json::to_string(&delta).unwrap();
+            serde_json::from_str::<StateDelta>(&json)
+                .unwrap()
+                .apply(&mut client);
+            assert_eq!(client, after);
+        }
+        assert!(cleared, "a cleared dice roll should have been sent as null");
+        assert!(StateDelta::between(&client, &client).is_empty());
+    }
+}
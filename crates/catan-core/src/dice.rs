@@ -0,0 +1,135 @@
+//! How the dice are rolled, and how they have behaved over a game.
+//!
+//! [`DiceMode`] picks between real dice and a balanced deck of the 36
+//! possible rolls. [`DiceStats`] summarizes the rolls in
+//! `GameState::dice_history`: how often each total came up against the
+//! odds, a chi-square measure of how far the whole spread strays from
+//! them, and how long each number has gone without being rolled.
+
+use crate::board::dice_pips;
+use serde::{Deserialize, Serialize};
+
+/// Where dice rolls come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiceMode {
+    /// Two independent dice
+    #[default]
+    Random,
+    /// Draw from a shuffled deck holding each of the 36 rolls once,
+    /// reshuffled when it runs out, so every total comes up as often as
+    /// the odds say over each 36 rolls
+    Balanced,
+}
+
+/// The 36 rolls of two dice, unshuffled
+pub fn balanced_deck() -> Vec<(u8, u8)> {
+    (1..=6).flat_map(|a| (1..=6).map(move |b| (a, b))).collect()
+}
+
+/// Chi-square above which a spread of totals is unusual: only one fair
+/// game in twenty strays this far (10 degrees of freedom, 5% level)
+pub const CHI_SQUARE_UNUSUAL: f64 = 18.307;
+
+/// Distribution of the dice totals rolled so far. The arrays are indexed
+/// by total, from 2 at index 0 to 12 at index 10.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiceStats {
+    /// Rolls counted
+    pub rolls: u32,
+    /// Times each total came up
+    pub counts: [u32; 11],
+    /// Times each total should have come up with fair dice
+    pub expected: [f64; 11],
+    /// Pearson's chi-square of the counts against the expected counts;
+    /// 0 before the first roll
+    pub chi_square: f64,
+    /// Most rolls in a row without each total, the current run included
+    pub longest_droughts: [u32; 11],
+    /// Rolls since each total last came up
+    pub current_droughts: [u32; 11],
+}
+
+impl DiceStats {
+    /// Stats for a sequence of rolls
+    pub fn from_rolls(rolls: &[(u8, u8)]) -> Self {
+        let mut stats = Self::default();
+
+        for &(die1, die2) in rolls {
+            let rolled = (die1 + die2).saturating_sub(2) as usize;
+            stats.rolls += 1;
+            for (index, drought) in stats.current_droughts.iter_mut().enumerate() {
+                if index == rolled {
+                    *drought = 0;
+                } else {
+                    *drought += 1;
+                }
+            }
+            if let Some(count) = stats.counts.get_mut(rolled) {
+                *count += 1;
+            }
+            for (longest, current) in stats.longest_droughts.iter_mut().zip(stats.current_droughts)
+            {
+                *longest = (*longest).max(current);
+            }
+        }
+
+        for (index, expected) in stats.expected.iter_mut().enumerate() {
+            *expected = stats.rolls as f64 * dice_pips(index as u8 + 2) as f64 / 36.0;
+        }
+        stats.chi_square = stats
+            .counts
+            .iter()
+            .zip(stats.expected)
+            .filter(|(_, expected)| *expected > 0.0)
+            .map(|(&count, expected)| (count as f64 - expected).powi(2) / expected)
+            .sum();
+
+        stats
+    }
+
+    /// Times `total` came up
+    pub fn count(&self, total: u8) -> u32 {
+        Self::index(total).map_or(0, |i| self.counts[i])
+    }
+
+    /// Most rolls in a row without `total`
+    pub fn longest_drought(&self, total: u8) -> u32 {
+        Self::index(total).map_or(0, |i| self.longest_droughts[i])
+    }
+
+    /// Whether the spread of totals is unlikely for fair dice
+    pub fn is_unusual(&self) -> bool {
+        self.chi_square > CHI_SQUARE_UNUSUAL
+    }
+
+    fn index(total: u8) -> Option<usize> {
+        (2..=12).contains(&total).then(|| total as usize - 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dice_stats_count_totals_and_droughts() {
+        let rolls = [(3, 4), (1, 1), (4, 4), (2, 5), (6, 6), (5, 2)];
+        let stats = DiceStats::from_rolls(&rolls);
+
+        assert_eq!(stats.rolls, 6);
+        assert_eq!(stats.count(7), 3);
+        assert_eq!(stats.count(2), 1);
+        assert_eq!(stats.count(13), 0);
+        assert_eq!(stats.longest_drought(7), 2);
+        // 6 never came up, so its drought is the whole game
+        assert_eq!(stats.longest_drought(6), 6);
+        assert_eq!(stats.current_droughts[12 - 2], 1);
+        assert!((stats.expected[7 - 2] - 1.0).abs() < 1e-9);
+        assert!(stats.chi_square > 0.0);
+
+        let stats = DiceStats::from_rolls(&balanced_deck());
+        assert_eq!(stats.chi_square, 0.0);
+        assert!(!stats.is_unusual());
+        assert_eq!(DiceStats::from_rolls(&[]).chi_square, 0.0);
+    }
+}
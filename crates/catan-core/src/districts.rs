@@ -0,0 +1,213 @@
+//! Singapore district names for land tiles.
+//!
+//! Board generation only labels the desert ("Bukit Timah"). A
+//! [`DistrictPool`] in `BoardGenConfig` names every other land tile after a
+//! neighborhood, so clients can show "Tampines" instead of a bare grid
+//! coordinate. Naming is a pure function of the layout and never touches
+//! the RNG, so the same seed always gives the same board.
+
+use crate::hex::HexCoord;
+use serde::{Deserialize, Serialize};
+
+/// Singapore's planning regions, each with some of its neighborhoods
+const REGIONS: [(Region, &[&str]); 5] = [
+    (
+        Region::Central,
+        &[
+            "Orchard", "Tanjong Pagar", "Bishan", "Toa Payoh", "Queenstown", "Novena",
+            "Bukit Merah", "Kallang", "Marine Parade", "Geylang",
+        ],
+    ),
+    (
+        Region::North,
+        &["Woodlands", "Yishun", "Sembawang", "Mandai", "Admiralty", "Kranji"],
+    ),
+    (
+        Region::NorthEast,
+        &["Punggol", "Sengkang", "Hougang", "Serangoon", "Ang Mo Kio", "Seletar"],
+    ),
+    (
+        Region::East,
+        &["Tampines", "Bedok", "Pasir Ris", "Changi", "Simei", "Tanah Merah"],
+    ),
+    (
+        Region::West,
+        &[
+            "Jurong East", "Clementi", "Bukit Batok", "Choa Chu Kang", "Boon Lay",
+            "Tuas", "Bukit Panjang",
+        ],
+    ),
+];
+
+/// A planning region of Singapore
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Region {
+    Central,
+    North,
+    NorthEast,
+    East,
+    West,
+}
+
+impl Region {
+    /// Region for a land tile on a board with `radius` rings: the inner
+    /// rings are Central, the rest is split by compass direction
+    pub fn for_hex(coord: HexCoord, radius: u32) -> Self {
+        if coord.distance_to(&HexCoord::new(0, 0)) <= radius / 2 {
+            return Region::Central;
+        }
+        // Pixel y grows downward, so flip it to measure angles north of east
+        let (x, y) = coord.to_pixel(1.0);
+        let degrees = (-y).atan2(x).to_degrees();
+        match degrees {
+            d if (-75.0..30.0).contains(&d) => Region::East,
+            d if (30.0..80.0).contains(&d) => Region::NorthEast,
+            d if (80.0..140.0).contains(&d) => Region::North,
+            _ => Region::West,
+        }
+    }
+
+    /// Neighborhoods in this region
+    pub fn neighborhoods(self) -> &'static [&'static str] {
+        REGIONS
+            .iter()
+            .find(|(region, _)| *region == self)
+            .map_or(&[], |(_, names)| *names)
+    }
+}
+
+/// An MRT line, for naming tiles after its stations in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MrtLine {
+    EastWest,
+    NorthSouth,
+    NorthEast,
+    Circle,
+}
+
+impl MrtLine {
+    /// Stations along the line, end to end
+    pub fn stations(self) -> &'static [&'static str] {
+        match self {
+            MrtLine::EastWest => &[
+                "Pasir Ris", "Tampines", "Simei", "Tanah Merah", "Bedok", "Kembangan",
+                "Eunos", "Paya Lebar", "Aljunied", "Kallang", "Lavender", "Bugis",
+                "City Hall", "Raffles Place", "Tanjong Pagar", "Outram Park", "Tiong Bahru",
+                "Redhill", "Queenstown", "Commonwealth", "Buona Vista", "Dover", "Clementi",
+                "Jurong East", "Chinese Garden", "Lakeside", "Boon Lay", "Pioneer",
+                "Joo Koon", "Gul Circle", "Tuas Link",
+            ],
+            MrtLine::NorthSouth => &[
+                "Jurong East", "Bukit Batok", "Bukit Gombak", "Choa Chu Kang", "Yew Tee",
+                "Kranji", "Marsiling", "Woodlands", "Admiralty", "Sembawang", "Canberra",
+                "Yishun", "Khatib", "Yio Chu Kang", "Ang Mo Kio", "Bishan", "Braddell",
+                "Toa Payoh", "Novena", "Newton", "Orchard", "Somerset", "Dhoby Ghaut",
+                "City Hall", "Raffles Place", "Marina Bay", "Marina South Pier",
+            ],
+            MrtLine::NorthEast => &[
+                "HarbourFront", "Outram Park", "Chinatown", "Clarke Quay", "Dhoby Ghaut",
+                "Little India", "Farrer Park", "Boon Keng", "Potong Pasir", "Woodleigh",
+                "Serangoon", "Kovan", "Hougang", "Buangkok", "Sengkang", "Punggol",
+                "Punggol Coast",
+            ],
+            MrtLine::Circle => &[
+                "Dhoby Ghaut", "Bras Basah", "Esplanade", "Promenade", "Nicoll Highway",
+                "Stadium", "Mountbatten", "Dakota", "Paya Lebar", "MacPherson", "Tai Seng",
+                "Bartley", "Serangoon", "Lorong Chuan", "Bishan", "Marymount", "Caldecott",
+                "Botanic Gardens", "Farrer Road", "Holland Village", "Buona Vista",
+                "one-north", "Kent Ridge", "Haw Par Villa", "Pasir Panjang", "Labrador Park",
+                "Telok Blangah", "HarbourFront",
+            ],
+        }
+    }
+}
+
+/// Where land tile names come from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistrictPool {
+    /// Leave tiles unnamed, apart from the desert
+    None,
+    /// Neighborhoods of the region each tile sits in
+    #[default]
+    Regions,
+    /// Stations of one MRT line, from the center of the board outward
+    MrtLine(MrtLine),
+    /// The given names, from the center of the board outward
+    Custom(Vec<String>),
+}
+
+impl DistrictPool {
+    /// A name for each of `tiles` (land hexes in spiral order from the
+    /// center) on a board with `radius` rings. Names are never repeated
+    /// while the pool lasts; after that they come round again with a
+    /// number ("Tampines 2").
+    pub fn names(&self, tiles: &[HexCoord], radius: u32) -> Vec<Option<String>> {
+        match self {
+            DistrictPool::None => vec![None; tiles.len()],
+            DistrictPool::Regions => {
+                let mut used = [0usize; 5];
+                tiles
+                    .iter()
+                    .map(|coord| {
+                        let region = Region::for_hex(*coord, radius);
+                        let index = REGIONS.iter().position(|(r, _)| *r == region)?;
+                        let names = REGIONS[index].1;
+                        let name = numbered(names, used[index]);
+                        used[index] += 1;
+                        name
+                    })
+                    .collect()
+            }
+            DistrictPool::MrtLine(line) => {
+                (0..tiles.len()).map(|i| numbered(line.stations(), i)).collect()
+            }
+            DistrictPool::Custom(names) => {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                (0..tiles.len()).map(|i| numbered(&names, i)).collect()
+            }
+        }
+    }
+}
+
+/// The `index`th name from a pool that repeats with a round number
+fn numbered(names: &[&str], index: usize) -> Option<String> {
+    let name = names.get(index % names.len().max(1))?;
+    match index / names.len() {
+        0 => Some(name.to_string()),
+        round => Some(format!("{name} {}", round + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_district_pools_name_every_tile_once() {
+        let tiles: Vec<HexCoord> = (-2..=2)
+            .flat_map(|q| (-2..=2).map(move |r| HexCoord::new(q, r)))
+            .filter(|c| c.distance_to(&HexCoord::new(0, 0)) <= 2)
+            .collect();
+
+        let names = DistrictPool::Regions.names(&tiles, 2);
+        let mut unique: Vec<&String> = names.iter().flatten().collect();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), tiles.len());
+        let center = tiles.iter().position(|c| *c == HexCoord::new(0, 0)).unwrap();
+        assert!(Region::Central
+            .neighborhoods()
+            .contains(&names[center].as_deref().unwrap()));
+
+        let custom = DistrictPool::Custom(vec!["Void Deck".into(), "Kopitiam".into()]);
+        assert_eq!(
+            custom.names(&tiles[..3], 2),
+            vec![Some("Void Deck".into()), Some("Kopitiam".into()), Some("Void Deck 2".into())]
+        );
+        assert_eq!(DistrictPool::Custom(vec![]).names(&tiles[..1], 2), vec![None]);
+        assert_eq!(
+            DistrictPool::MrtLine(MrtLine::Circle).names(&tiles[..1], 2),
+            vec![Some("Dhoby Ghaut".into())]
+        );
+    }
+}
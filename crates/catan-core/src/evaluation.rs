@@ -0,0 +1,156 @@
+//! Position evaluation: how likely each player is to win from here.
+//!
+//! Each player's position is boiled down to a strength score (victory
+//! points above all, then production, room to expand, development cards
+//! and cards in hand) and the scores are turned into probabilities with a
+//! softmax. The numbers are a heuristic, not a trained model, but they move
+//! the right way and are cheap enough to run at every search leaf.
+
+use crate::board::PlayerId;
+use crate::game::{GamePhase, GameState};
+use crate::player::DevelopmentCard;
+
+/// How sharply strength differences turn into win chances
+const SHARPNESS: f32 = 1.0;
+
+/// Estimated chance that `player` (or their team) wins, between 0 and 1
+pub fn evaluate_position(game: &GameState, player: PlayerId) -> f32 {
+    win_probabilities(game)
+        .get(player as usize)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Estimated win chance for every seat. Teammates share their team's
+/// chance, so in team games the values add up to more than 1.
+pub fn win_probabilities(game: &GameState) -> Vec<f32> {
+    let seats = game.players.len() as PlayerId;
+
+    let individual: Vec<f32> = if matches!(game.phase, GamePhase::Finished { .. }) {
+        match game.get_winner() {
+            Some(winner) => (0..seats).map(|p| if p == winner { 1.0 } else { 0.0 }).collect(),
+            // Agreed to end early: nobody won
+            None => vec![0.0; seats as usize],
+        }
+    } else {
+        let weights: Vec<f32> = (0..seats)
+            .map(|p| {
+                if game.is_active(p) {
+                    (SHARPNESS * strength(game, p)).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total == 0.0 {
+            return vec![0.0; seats as usize];
+        }
+        weights.iter().map(|w| w / total).collect()
+    };
+
+    (0..seats)
+        .map(|p| {
+            (0..seats)
+                .filter(|q| *q == p || game.are_teammates(*q, p))
+                .map(|q| individual[q as usize])
+                .sum::<f32>()
+                .min(1.0)
+        })
+        .collect()
+}
+
+/// Pips per roll across a player's buildings (cities count double),
+/// leaving out the tile the robber sits on
+pub(crate) fn production(game: &GameState, player: PlayerId) -> u32 {
+    let board = &game.board;
+    board
+        .vertices_by_id()
+        .iter()
+        .filter(|v| board.get_vertex(v).owner() == Some(player))
+        .map(|v| {
+            let multiplier = board.get_vertex(v).resource_multiplier();
+            let pips: u32 = board
+                .tiles_at_vertex(v)
+                .iter()
+                .filter(|t| t.is_productive())
+                .filter_map(|t| t.dice_number)
+                .map(crate::board::dice_pips)
+                .sum();
+            pips * multiplier
+        })
+        .sum()
+}
+
+/// Position strength; about 8 for a player one step from winning
+fn strength(game: &GameState, player: PlayerId) -> f32 {
+    let Some(p) = game.get_player(player) else {
+        return 0.0;
+    };
+    let board = &game.board;
+
+    let target = game.victory_points_to_win().max(1) as f32;
+    let progress = (game.total_victory_points(player) as f32 / target).min(1.0);
+
+    let expansion = if p.settlements_remaining > 0 {
+        board.valid_settlement_spots(player, false).len().min(3) as f32 / 3.0
+    } else {
+        0.0
+    };
+    // Victory point cards already count in `progress`
+    let dev_cards = p
+        .dev_cards
+        .iter()
+        .chain(&p.dev_cards_bought_this_turn)
+        .filter(|c| **c != DevelopmentCard::VictoryPoint)
+        .count();
+    let hand = p.resources.total().min(7) as f32 / 7.0;
+
+    8.0 * progress
+        + 2.0 * (production(game, player) as f32 / 36.0).min(1.5)
+        + expansion
+        + 0.5 * (dev_cards.min(5) as f32 / 5.0)
+        + 0.3 * hand
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::GameOptions;
+    use crate::player::ResourceHand;
+
+    fn game(players: u8) -> GameState {
+        let names = (0..players).map(|i| format!("P{}", i)).collect();
+        GameState::new_with_seed(players, names, GameOptions::default(), 3)
+    }
+
+    #[test]
+    fn test_even_start_and_probabilities_sum_to_one() {
+        let game = game(4);
+        let odds = win_probabilities(&game);
+        assert_eq!(odds.len(), 4);
+        assert!(odds.iter().all(|p| (p - 0.25).abs() < 1e-6));
+        assert!((odds.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_points_cards_and_the_finish_line() {
+        let mut game = game(3);
+        let before = evaluate_position(&game, 0);
+        // Three cities' worth of points
+        game.players[0].cities_remaining = 1;
+        let ahead = evaluate_position(&game, 0);
+        assert!(ahead > before);
+        assert!(evaluate_position(&game, 1) < 1.0 / 3.0);
+
+        game.players[1].resources = ResourceHand::with_amounts(1, 1, 1, 1, 1);
+        game.players[1].dev_cards.push(DevelopmentCard::Knight);
+        assert!(evaluate_position(&game, 1) > evaluate_position(&game, 2));
+
+        game.phase = GamePhase::Finished {
+            winner: Some(0),
+            ranking: vec![0, 1, 2],
+        };
+        assert_eq!(win_probabilities(&game), vec![1.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Numbered history of a game's events.
+//!
+//! Every event gets the next sequence number, starting from 1, so a client
+//! that drops its connection can say which event it saw last and be sent
+//! everything after it. The log lives beside the game rather than inside
+//! [`GameState`](crate::game::GameState), which is cloned far too often
+//! (by bots and searches) to carry its whole history.
+
+use crate::actions::GameEvent;
+use crate::board::PlayerId;
+use serde::{Deserialize, Serialize};
+
+/// An event and its place in the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: GameEvent,
+}
+
+impl SequencedEvent {
+    /// The event as `viewer` may see it (see [`GameEvent::visible_to`]).
+    /// Skipping a private event leaves a gap in the sequence numbers.
+    pub fn visible_to(&self, viewer: Option<PlayerId>) -> Option<SequencedEvent> {
+        Some(SequencedEvent {
+            seq: self.seq,
+            event: self.event.visible_to(viewer)?,
+        })
+    }
+}
+
+/// Every event of a game, in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventLog {
+    events: Vec<SequencedEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number `events` and add them, returning them as logged
+    pub fn record(&mut self, events: &[GameEvent]) -> &[SequencedEvent] {
+        let start = self.events.len();
+        for event in events {
+            let seq = self.events.len() as u64 + 1;
+            self.events.push(SequencedEvent {
+                seq,
+                event: event.clone(),
+            });
+        }
+        &self.events[start..]
+    }
+
+    /// Sequence number of the latest event, 0 before the first
+    pub fn last_seq(&self) -> u64 {
+        self.events.len() as u64
+    }
+
+    /// Events after `seq`, oldest first
+    pub fn since(&self, seq: u64) -> &[SequencedEvent] {
+        let start = (seq as usize).min(self.events.len());
+        &self.events[start..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Resource;
+
+    #[test]
+    fn test_log_numbers_events_and_hides_steals() {
+        let mut log = EventLog::new();
+        assert_eq!(log.last_seq(), 0);
+        let logged = log.record(&[
+            GameEvent::KnightPlayed { player: 0 },
+            GameEvent::ResourceStolen {
+                thief: 0,
+                victim: 1,
+                resource: Some(Resource::Brick),
+            },
+        ]);
+        assert_eq!(logged[1].seq, 2);
+        log.record(&[GameEvent::TradeCancelled]);
+
+        assert_eq!(log.last_seq(), 3);
+        assert_eq!(log.since(0).len(), 3);
+        assert_eq!(log.since(1)[0].seq, 2);
+        assert!(log.since(3).is_empty());
+        assert!(log.since(10).is_empty());
+
+        let steal = &log.since(1)[0];
+        assert_eq!(steal.visible_to(Some(1)).as_ref(), Some(steal));
+        assert_eq!(
+            steal.visible_to(Some(2)).unwrap().event,
+            GameEvent::ResourceStolen {
+                thief: 0,
+                victim: 1,
+                resource: None,
+            }
+        );
+    }
+}
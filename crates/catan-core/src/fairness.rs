@@ -0,0 +1,186 @@
+//! Provably fair dice, by committing to a game's seed and revealing it.
+//!
+//! Everything random in a game follows from its seed: the board, the deck,
+//! who goes first and every roll and steal after. When the game starts the
+//! host publishes a commitment, the SHA-256 of a random salt and the seed,
+//! which binds it to the seed without giving it away. Once the game is over
+//! it reveals both, and anyone holding the record and the rolls they saw
+//! can check that the game began from that seed and rolled exactly the dice
+//! it calls for.
+
+use crate::actions::GameEvent;
+use crate::analysis::{AnalysisError, GameRecord};
+use crate::game::GameState;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// A game's seed and the salt that hid it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedReveal {
+    pub seed: u64,
+    /// Hex, 32 random bytes
+    pub salt: String,
+}
+
+/// Errors from checking a game's dice
+#[derive(Debug, Clone, Error)]
+pub enum FairnessError {
+    #[error("The seed and salt don't match the commitment")]
+    WrongCommitment,
+    #[error("The game didn't start from the revealed seed")]
+    WrongStart,
+    #[error(transparent)]
+    Replay(#[from] AnalysisError),
+    #[error("Roll {index} was {rolled:?}, but the seed gives {expected:?}")]
+    WrongRoll {
+        index: usize,
+        rolled: (u8, u8),
+        expected: (u8, u8),
+    },
+    #[error("{rolled} rolls were made, but the seed gives {expected}")]
+    WrongRollCount { rolled: usize, expected: usize },
+}
+
+impl SeedReveal {
+    /// Hide `seed` behind a fresh random salt
+    pub fn new(seed: u64) -> Self {
+        let salt: [u8; 32] = rand::thread_rng().gen();
+        Self {
+            seed,
+            salt: salt.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+
+    /// What is published before the first roll: hex SHA-256 of the salt's
+    /// text followed by the seed in decimal
+    pub fn commitment(&self) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update(self.seed.to_string().as_bytes())
+            .finalize();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// The dice rolled in `events`, in order
+pub fn dice_rolls<'a>(events: impl IntoIterator<Item = &'a GameEvent>) -> Vec<(u8, u8)> {
+    events
+        .into_iter()
+        .filter_map(|e| match e {
+            GameEvent::DiceRolled { roll, .. } => Some(*roll),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Check that `reveal` opens `commitment`, that `record` began from its
+/// seed, and that `rolls` are the dice that seed gives over the record's
+/// moves
+pub fn verify_dice(
+    record: &GameRecord,
+    commitment: &str,
+    reveal: &SeedReveal,
+    rolls: &[(u8, u8)],
+) -> Result<(), FairnessError> {
+    if !reveal.commitment().eq_ignore_ascii_case(commitment) {
+        return Err(FairnessError::WrongCommitment);
+    }
+
+    let start = &record.start;
+    let names = start.players.iter().map(|p| p.name.clone()).collect();
+    let mut game = GameState::new_with_seed(
+        start.players.len() as u8,
+        names,
+        start.options.clone(),
+        reveal.seed,
+    );
+    // Same board, seating and deck; the dice are checked below
+    let board = |game: &GameState| {
+        let mut board = game.board.to_json_friendly();
+        board.tiles.sort_by_key(|t| (t.q, t.r));
+        board
+    };
+    if board(&game) != board(start)
+        || game.players != start.players
+        || game.current_player != start.current_player
+        || game.dev_card_deck != start.dev_card_deck
+    {
+        return Err(FairnessError::WrongStart);
+    }
+
+    let mut expected = Vec::new();
+    for (index, m) in record.moves.iter().enumerate() {
+        let events = game
+            .apply_action(m.player, m.action.clone())
+            .map_err(|error| AnalysisError::IllegalMove { index, error })?;
+        expected.extend(dice_rolls(&events));
+    }
+    if let Some(index) = (0..rolls.len().min(expected.len())).find(|&i| rolls[i] != expected[i]) {
+        return Err(FairnessError::WrongRoll {
+            index,
+            rolled: rolls[index],
+            expected: expected[index],
+        });
+    }
+    if rolls.len() != expected.len() {
+        return Err(FairnessError::WrongRollCount {
+            rolled: rolls.len(),
+            expected: expected.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, BotDifficulty, BotPlayer};
+    use crate::options::GameOptions;
+
+    #[test]
+    fn test_revealed_seed_accounts_for_every_roll() {
+        let reveal = SeedReveal::new(1234);
+        let commitment = reveal.commitment();
+        assert_eq!(commitment.len(), 64);
+        assert_ne!(SeedReveal::new(1234).commitment(), commitment);
+
+        let names = vec!["A".to_string(), "B".to_string()];
+        let mut game = GameState::new_with_seed(2, names, GameOptions::default(), reveal.seed);
+        let mut record = GameRecord::new(&game);
+        let mut rolls = Vec::new();
+        let mut bots = [
+            Bot::with_seed(0, BotDifficulty::Easy, 1),
+            Bot::with_seed(1, BotDifficulty::Easy, 2),
+        ];
+        while rolls.len() < 10 {
+            let player = (0..2).find(|&p| !game.valid_actions(p).is_empty()).unwrap();
+            let action = bots[player as usize].choose_action(&game).unwrap();
+            let events = game.apply_action(player, action.clone()).unwrap();
+            rolls.extend(dice_rolls(&events));
+            record.push(player, action);
+        }
+        verify_dice(&record, &commitment, &reveal, &rolls).unwrap();
+
+        let mut loaded = rolls.clone();
+        loaded[3] = if loaded[3] == (6, 6) { (1, 1) } else { (6, 6) };
+        assert!(matches!(
+            verify_dice(&record, &commitment, &reveal, &loaded),
+            Err(FairnessError::WrongRoll { index: 3, .. })
+        ));
+        assert!(matches!(
+            verify_dice(&record, &commitment, &reveal, &rolls[..9]),
+            Err(FairnessError::WrongRollCount { .. })
+        ));
+        let other = SeedReveal::new(4321);
+        assert!(matches!(
+            verify_dice(&record, &other.commitment(), &other, &rolls),
+            Err(FairnessError::WrongStart)
+        ));
+        assert!(matches!(
+            verify_dice(&record, &other.commitment(), &reveal, &rolls),
+            Err(FairnessError::WrongCommitment)
+        ));
+    }
+}
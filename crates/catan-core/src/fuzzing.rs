@@ -0,0 +1,307 @@
+//! Throwing arbitrary moves at the engine.
+//!
+//! [`run`] turns a string of bytes into a seeded game and a sequence of
+//! moves from any seat, some legal and some not, and applies them one by
+//! one with [`apply_checked`]. That panics if the engine panics, if a
+//! refused move changed the game, if a hand went negative (a count so
+//! large it can only have wrapped below zero) or if an accepted move broke
+//! one of the [`invariants`](crate::invariants). The cargo-fuzz target in
+//! `fuzz/` feeds it from libFuzzer; the tests here feed it random bytes.
+
+use crate::actions::{GameAction, GameEvent, TradeOffer};
+use crate::board::{PlayerId, Resource};
+use crate::cities_knights::Improvement;
+use crate::delta;
+use crate::dice::DiceMode;
+use crate::game::{GameError, GameState, GameStateJson};
+use crate::hex::{EdgeCoord, EdgeDirection, HexCoord, VertexCoord, VertexDirection};
+use crate::invariants;
+use crate::options::{Expansion, GameOptions};
+use crate::player::{DevelopmentCard, ResourceHand};
+use crate::rng::GameRng;
+
+/// Most moves one input plays, so a long input can't run forever
+pub const MAX_MOVES: usize = 4000;
+
+/// More cards of one kind than any hand can really hold
+const WRAPPED: u32 = u32::MAX / 2;
+
+/// Fuzzer input, read a byte at a time, with zeros once it runs out
+pub struct Input<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn byte(&mut self) -> u8 {
+        let byte = self.data.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        byte
+    }
+
+    fn u64(&mut self) -> u64 {
+        (0..8).fold(0, |n, _| n << 8 | self.byte() as u64)
+    }
+
+    fn coin(&mut self) -> bool {
+        self.byte() & 1 == 0
+    }
+
+    /// A number below `n`
+    fn below(&mut self, n: usize) -> usize {
+        self.byte() as usize % n.max(1)
+    }
+
+    fn hex(&mut self) -> HexCoord {
+        HexCoord::new(self.below(9) as i32 - 4, self.below(9) as i32 - 4)
+    }
+
+    fn vertex(&mut self) -> VertexCoord {
+        let direction = if self.coin() {
+            VertexDirection::North
+        } else {
+            VertexDirection::South
+        };
+        VertexCoord::new(self.hex(), direction)
+    }
+
+    fn edge(&mut self) -> EdgeCoord {
+        let hex = self.hex();
+        EdgeCoord::new(hex, EdgeDirection::ALL[self.below(6)])
+    }
+
+    fn resource(&mut self) -> Resource {
+        Resource::ALL[self.below(5)]
+    }
+
+    fn hand(&mut self) -> ResourceHand {
+        let mut hand = ResourceHand::new();
+        for resource in Resource::ALL {
+            hand.add(resource, self.below(12) as u32);
+        }
+        hand
+    }
+
+    fn offer(&mut self) -> TradeOffer {
+        TradeOffer {
+            from: self.byte() % 8,
+            to: (self.coin()).then(|| self.byte() % 8),
+            offering: self.hand(),
+            requesting: self.hand(),
+        }
+    }
+}
+
+/// The next move to try: usually one the game is waiting for, otherwise
+/// any move from any seat, including one past the last to try a player
+/// who isn't there. Resigning and calling a vote to end are kept rare so
+/// games get somewhere.
+pub fn next_move(game: &GameState, input: &mut Input) -> (PlayerId, GameAction) {
+    let mode = input.byte();
+    let waiting = game.waiting_on();
+    let player = if mode < 224 && !waiting.is_empty() {
+        waiting[input.below(waiting.len())]
+    } else {
+        input.below(game.players.len() + 1) as PlayerId
+    };
+    if mode & 3 != 0 {
+        let rare = input.byte() < 4;
+        let valid: Vec<GameAction> = game
+            .valid_actions(player)
+            .into_iter()
+            .filter(|a| rare || !ends_game(a))
+            .collect();
+        if !valid.is_empty() {
+            let action = match valid[input.below(valid.len())].clone() {
+                // Discards are offered as an empty hand to fill in
+                GameAction::DiscardCards(_) => game
+                    .suggest_discard(player)
+                    .map_or(GameAction::EndTurn, GameAction::DiscardCards),
+                action => action,
+            };
+            return (player, action);
+        }
+    }
+    let action = match input.below(28) {
+        0 => GameAction::PlaceInitialSettlement(input.vertex()),
+        1 => GameAction::PlaceInitialRoad(input.edge()),
+        2 => GameAction::RollDice,
+        3 => GameAction::MoveRobber(input.hex()),
+        4 => GameAction::MovePirate(input.hex()),
+        5 => GameAction::StealFrom(input.byte() % 8),
+        6 => GameAction::DiscardCards(input.hand()),
+        7 => GameAction::BuildRoad(input.edge()),
+        8 => GameAction::BuildSettlement(input.vertex()),
+        9 => GameAction::BuildCity(input.vertex()),
+        10 => GameAction::BuyDevelopmentCard,
+        11 => GameAction::PlayKnight,
+        12 => GameAction::PlayRoadBuilding(input.edge(), input.edge()),
+        13 => GameAction::PlayYearOfPlenty(input.resource(), input.resource()),
+        14 => GameAction::PlayMonopoly(input.resource()),
+        15 => GameAction::ProposeTrade(input.offer()),
+        16 => GameAction::AcceptTrade,
+        17 => GameAction::RejectTrade,
+        18 => GameAction::CounterTrade(input.offer()),
+        19 => GameAction::CancelTrade,
+        20 => GameAction::MaritimeTrade {
+            give: input.resource(),
+            give_count: input.below(6) as u32,
+            receive: input.resource(),
+        },
+        21 => GameAction::EndTurn,
+        22 => GameAction::ConfirmNextPlayer,
+        23 if input.byte() < 4 => GameAction::ProposeEndGame,
+        23 => GameAction::CancelTrade,
+        24 => GameAction::VoteEndGame(input.coin()),
+        25 => GameAction::DiscardCards(ResourceHand::new()),
+        26 => GameAction::BuildCityImprovement(Improvement::ALL[input.below(3)]),
+        _ if input.byte() < 4 => GameAction::Concede,
+        _ => GameAction::EndTurn,
+    };
+    (player, action)
+}
+
+fn ends_game(action: &GameAction) -> bool {
+    matches!(action, GameAction::Concede | GameAction::ProposeEndGame)
+}
+
+/// Everything a refused move must leave alone
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    state: GameStateJson,
+    deck: Vec<DevelopmentCard>,
+    dev_card_played: bool,
+    rng: GameRng,
+}
+
+impl Snapshot {
+    fn of(game: &GameState) -> Self {
+        Self {
+            state: game.to_json_friendly(),
+            deck: game.dev_card_deck.clone(),
+            dev_card_played: game.dev_card_played_this_turn,
+            rng: game.rng().clone(),
+        }
+    }
+}
+
+/// Apply a move, panicking if the engine broke a rule of its own while
+/// doing so
+pub fn apply_checked(
+    game: &mut GameState,
+    player: PlayerId,
+    action: GameAction,
+) -> Result<Vec<GameEvent>, GameError> {
+    let before = Snapshot::of(game);
+    let start = invariants::Before::of(game);
+    let result = game.apply_action(player, action.clone());
+    match &result {
+        Err(error) => {
+            let after = Snapshot::of(game);
+            if before != after {
+                let changes: Vec<String> = delta::differences(&before.state, &after.state)
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect();
+                panic!(
+                    "seat {} was refused {:?} ({}) but the game changed:\n  {}",
+                    player,
+                    action,
+                    error,
+                    changes.join("\n  ")
+                );
+            }
+        }
+        Ok(events) => {
+            for p in &game.players {
+                for resource in Resource::ALL {
+                    assert!(
+                        p.resources.get(resource) < WRAPPED,
+                        "seat {} went below zero {:?} after seat {} played {:?}",
+                        p.id,
+                        resource,
+                        player,
+                        action
+                    );
+                }
+            }
+            invariants::assert_move(&start, game, player, &action, events);
+        }
+    }
+    result
+}
+
+/// Set up a game from the start of `data` and play the rest of it as moves
+pub fn run(data: &[u8]) {
+    let mut input = Input::new(data);
+    let seed = input.u64();
+    let players = 2 + input.below(3) as u8;
+    let flags = input.byte();
+    let options = GameOptions {
+        pirate: flags & 1 != 0,
+        hot_seat: flags & 2 != 0,
+        max_turns: (flags & 4 != 0).then_some(30),
+        expansion: if flags & 8 != 0 {
+            Expansion::CitiesAndKnights
+        } else {
+            Expansion::Base
+        },
+        dice: if flags & 16 != 0 {
+            DiceMode::Balanced
+        } else {
+            DiceMode::Random
+        },
+        quick_start: flags & 32 != 0,
+        ..GameOptions::default()
+    };
+    let names = (0..players).map(|i| format!("P{}", i)).collect();
+    let mut game = GameState::new_with_seed(players, names, options, seed);
+
+    for _ in 0..MAX_MOVES {
+        if input.is_empty() || game.is_finished() {
+            break;
+        }
+        let (player, action) = next_move(&game, &mut input);
+        let _ = apply_checked(&mut game, player, action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    fn test_random_move_sequences_keep_the_game_sound() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let mut data = vec![0; 6000];
+            rng.fill_bytes(&mut data);
+            run(&data);
+        }
+    }
+
+    #[test]
+    fn test_refused_moves_leave_the_game_alone() {
+        let mut game = GameState::new_with_seed(
+            3,
+            vec!["A".into(), "B".into(), "C".into()],
+            GameOptions::default(),
+            1,
+        );
+        let wrong = (game.current_player + 1) % 3;
+        assert!(apply_checked(&mut game, wrong, GameAction::RollDice).is_err());
+        assert!(apply_checked(&mut game, 9, GameAction::EndTurn).is_err());
+        let huge = ResourceHand::with_amounts(50, 0, 0, 0, 0);
+        assert!(apply_checked(&mut game, 0, GameAction::DiscardCards(huge)).is_err());
+    }
+}
@@ -3,12 +3,26 @@
 //! This module contains the main `GameState` struct and all game logic.
 
 use crate::actions::{GameAction, GameEvent, TradeOffer};
-use crate::board::{Board, EdgeBuilding, Harbor, PlayerId, Resource, TileType};
+use crate::barbarians::{BarbarianTrack, EventDie, EventFace};
+use crate::board::{
+    Board, BoardTemplate, EdgeBuilding, Harbor, PlayerId, Resource, TileType, VertexBuilding,
+};
+use crate::bot::{Bot, BotDifficulty, BotPlayer};
+use crate::cities_knights::{
+    self, CitiesKnights, CitiesKnightsPlayer, CommodityHand, Improvement,
+};
+use crate::clock::{FlagFallPolicy, GameClock};
+use crate::dice::{self, DiceMode, DiceStats};
 use crate::hex::{EdgeCoord, HexCoord, VertexCoord};
-use crate::player::{DevelopmentCard, Player, ResourceHand};
+use crate::options::{Expansion, GameOptions, TeamVictory};
+use crate::rng::GameRng;
+use crate::scenario::{self, Scenario, ScenarioError, StartingPiece};
+use crate::player::{DevCardCounts, DevelopmentCard, Player, ResourceHand};
+use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::Digest;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 /// Minimum road length for Longest Road
@@ -20,6 +34,17 @@ const MIN_LARGEST_ARMY: u32 = 3;
 /// Victory points needed to win
 const VICTORY_POINTS_TO_WIN: u32 = 10;
 
+/// Coaching for a human player: what a bot would do in their place
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hint {
+    /// The suggested next action
+    pub action: Option<GameAction>,
+    /// Cards to give up, when a discard is due
+    pub discard: Option<ResourceHand>,
+    /// Whether to take the trade on the table, when it's ours to answer
+    pub accept_trade: Option<bool>,
+}
+
 /// Game phase
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GamePhase {
@@ -60,9 +85,30 @@ pub enum GamePhase {
         roads_remaining: u8,
     },
 
+    /// Hot-seat screen lock: the turn has passed and the device should be
+    /// handed to the current player before anything is shown
+    AwaitingNextPlayer {
+        /// Phase to enter once the next player confirms
+        resume: Box<GamePhase>,
+    },
+
+    /// A player proposed ending the game early; waiting for every active
+    /// player to agree
+    EndGameVote {
+        /// Who proposed ending the game
+        proposer: PlayerId,
+        /// Players who have agreed so far (includes the proposer)
+        accepted: Vec<PlayerId>,
+        /// Phase to return to if the vote fails
+        resume: Box<GamePhase>,
+    },
+
     /// Game is over
     Finished {
-        winner: PlayerId,
+        /// The winner, or None if the game ended by agreement
+        winner: Option<PlayerId>,
+        /// All players ordered from first to last place
+        ranking: Vec<PlayerId>,
     },
 }
 
@@ -73,29 +119,98 @@ pub enum SetupPlacing {
     Road,
 }
 
-/// Errors that can occur when applying actions
-#[derive(Debug, Clone, Error, Serialize, Deserialize)]
+/// Which kind of phase a game is in, without the details
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PhaseKind {
+    SetupSettlement,
+    SetupRoad,
+    PreRoll,
+    RobberMoveRequired,
+    RobberSteal,
+    DiscardRequired,
+    MainPhase,
+    RoadBuildingInProgress,
+    AwaitingNextPlayer,
+    EndGameVote,
+    Finished,
+}
+
+impl GamePhase {
+    /// The kind of this phase
+    pub fn kind(&self) -> PhaseKind {
+        match self {
+            GamePhase::Setup {
+                placing: SetupPlacing::Settlement,
+                ..
+            } => PhaseKind::SetupSettlement,
+            GamePhase::Setup {
+                placing: SetupPlacing::Road,
+                ..
+            } => PhaseKind::SetupRoad,
+            GamePhase::PreRoll => PhaseKind::PreRoll,
+            GamePhase::RobberMoveRequired => PhaseKind::RobberMoveRequired,
+            GamePhase::RobberSteal { .. } => PhaseKind::RobberSteal,
+            GamePhase::DiscardRequired { .. } => PhaseKind::DiscardRequired,
+            GamePhase::MainPhase => PhaseKind::MainPhase,
+            GamePhase::RoadBuildingInProgress { .. } => PhaseKind::RoadBuildingInProgress,
+            GamePhase::AwaitingNextPlayer { .. } => PhaseKind::AwaitingNextPlayer,
+            GamePhase::EndGameVote { .. } => PhaseKind::EndGameVote,
+            GamePhase::Finished { .. } => PhaseKind::Finished,
+        }
+    }
+}
+
+/// A spot on the board an action pointed at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Location {
+    Vertex(VertexCoord),
+    Edge(EdgeCoord),
+    Hex(HexCoord),
+}
+
+/// A building piece a player keeps a supply of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Piece {
+    Road,
+    Settlement,
+    City,
+}
+
+/// Errors that can occur when applying actions.
+///
+/// Each error has a stable numeric [`code`](GameError::code) for clients
+/// that show their own messages; codes are never renumbered or reused.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
 pub enum GameError {
+    #[error("No such player: {0}")]
+    InvalidPlayer(PlayerId),
+
     #[error("Not your turn")]
     NotYourTurn,
 
-    #[error("Invalid action for current phase")]
-    InvalidPhase,
+    #[error("Can't do that during {actual:?}, only during {expected:?}")]
+    InvalidPhase {
+        expected: Vec<PhaseKind>,
+        actual: PhaseKind,
+    },
+
+    #[error("Invalid location: {0:?}")]
+    InvalidLocation(Location),
 
-    #[error("Invalid placement location")]
-    InvalidLocation,
+    #[error("Cannot afford this: missing {missing}")]
+    CannotAfford { missing: ResourceHand },
 
-    #[error("Cannot afford this")]
-    CannotAfford,
+    #[error("Not enough resources: missing {missing}")]
+    InsufficientResources { missing: ResourceHand },
 
-    #[error("No pieces remaining")]
-    NoPiecesRemaining,
+    #[error("No {0:?} pieces remaining")]
+    NoPiecesRemaining(Piece),
 
     #[error("No development cards left in deck")]
     EmptyDeck,
 
-    #[error("Don't have that card")]
-    NoSuchCard,
+    #[error("Don't have a {0:?} card to play")]
+    NoSuchCard(DevelopmentCard),
 
     #[error("Invalid trade")]
     InvalidTrade,
@@ -103,15 +218,72 @@ pub enum GameError {
     #[error("No active trade")]
     NoActiveTrade,
 
-    #[error("Invalid discard")]
-    InvalidDiscard,
+    #[error("Must discard exactly {required} cards")]
+    InvalidDiscard { required: u32 },
 
     #[error("Game is over")]
     GameOver,
+
+    #[error("Player has conceded")]
+    PlayerConceded,
+
+    #[error("Already voted")]
+    AlreadyVoted,
+
+    #[error("Already played a development card this turn")]
+    DevCardAlreadyPlayed,
+
+    #[error("The game is paused during {0:?}")]
+    Paused(PhaseKind),
+
+    #[error("Can't rob player {0}")]
+    CannotRob(PlayerId),
+
+    #[error("{resource:?} trades with the bank at {rate}:1")]
+    WrongTradeRate { resource: Resource, rate: u32 },
+
+    #[error("Only with the Cities & Knights expansion")]
+    ExpansionRequired,
+
+    #[error("Not enough commodities: missing {missing}")]
+    MissingCommodities { missing: CommodityHand },
+
+    #[error("Can't improve {0:?}: it needs a city and stops at level 5")]
+    CannotImprove(Improvement),
+}
+
+impl GameError {
+    /// Stable number identifying the kind of error
+    pub fn code(&self) -> u16 {
+        match self {
+            GameError::InvalidPlayer(_) => 1,
+            GameError::NotYourTurn => 2,
+            GameError::InvalidPhase { .. } => 3,
+            GameError::InvalidLocation(_) => 4,
+            GameError::CannotAfford { .. } => 5,
+            GameError::InsufficientResources { .. } => 6,
+            GameError::NoPiecesRemaining(_) => 7,
+            GameError::EmptyDeck => 8,
+            GameError::NoSuchCard(_) => 9,
+            GameError::InvalidTrade => 10,
+            GameError::NoActiveTrade => 11,
+            GameError::InvalidDiscard { .. } => 12,
+            GameError::GameOver => 13,
+            GameError::PlayerConceded => 14,
+            GameError::AlreadyVoted => 15,
+            GameError::DevCardAlreadyPlayed => 16,
+            GameError::Paused(_) => 17,
+            GameError::CannotRob(_) => 18,
+            GameError::WrongTradeRate { .. } => 19,
+            GameError::ExpansionRequired => 20,
+            GameError::MissingCommodities { .. } => 21,
+            GameError::CannotImprove(_) => 22,
+        }
+    }
 }
 
 /// Trade state during a turn
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TradeState {
     /// Current active offer
     pub offer: TradeOffer,
@@ -129,7 +301,7 @@ pub enum TradeResponse {
 
 /// JSON-friendly game state representation
 /// Uses BoardJson instead of Board to avoid HashMap serialization issues
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameStateJson {
     pub board: crate::board::BoardJson,
     pub players: Vec<Player>,
@@ -141,6 +313,27 @@ pub struct GameStateJson {
     pub pending_trade: Option<TradeState>,
     /// Total victory points for each player (computed, includes buildings)
     pub victory_points: Vec<u32>,
+    /// Resource cards in each player's hand
+    pub hand_sizes: Vec<u32>,
+    /// Development cards held by each player (including ones bought this turn)
+    pub dev_card_counts: Vec<u32>,
+    /// Development cards everyone has seen (see `GameState::dev_cards_revealed`)
+    #[serde(default)]
+    pub dev_cards_revealed: DevCardCounts,
+    /// Remaining time for each player, when playing with time control
+    pub clock: Option<GameClock>,
+    /// Last event die roll, when playing with the event die
+    #[serde(default)]
+    pub event_roll: Option<EventFace>,
+    /// The barbarians' progress, when playing with the event die
+    #[serde(default)]
+    pub barbarians: Option<BarbarianTrack>,
+    /// Each player's Cities & Knights holdings, when playing the expansion
+    #[serde(default)]
+    pub cities_knights: Option<Vec<CitiesKnightsPlayer>>,
+    /// Rolls left before the balanced dice deck is reshuffled
+    #[serde(default)]
+    pub dice_deck_remaining: Option<u32>,
 }
 
 /// The complete game state
@@ -158,45 +351,252 @@ pub struct GameState {
     pub turn_number: u32,
     /// Last dice roll
     pub dice_roll: Option<(u8, u8)>,
+    /// Every dice roll so far, in order
+    #[serde(default)]
+    pub dice_history: Vec<(u8, u8)>,
     /// Development card deck
     pub dev_card_deck: Vec<DevelopmentCard>,
     /// Active trade offer
     pub pending_trade: Option<TradeState>,
     /// Whether a dev card has been played this turn
     pub dev_card_played_this_turn: bool,
+    /// Development cards played so far, in the open
+    #[serde(default)]
+    pub dev_cards_played: DevCardCounts,
+    /// Rule options chosen at creation
+    #[serde(default)]
+    pub options: GameOptions,
+    /// Remaining time for each player, when playing with time control
+    #[serde(default)]
+    pub clock: Option<GameClock>,
+    /// Last event die roll, when playing with the event die
+    #[serde(default)]
+    pub event_roll: Option<EventFace>,
+    /// The barbarians' progress, when playing with the event die
+    #[serde(default)]
+    pub barbarians: Option<BarbarianTrack>,
+    /// Commodities, improvements and progress cards, when playing Cities &
+    /// Knights
+    #[serde(default)]
+    pub cities_knights: Option<CitiesKnights>,
     /// Setup phase tracking: which settlement was just placed
     setup_settlement: Option<VertexCoord>,
+    /// Settlements each player has placed during setup
+    #[serde(default)]
+    setup_placements: Vec<u32>,
+    /// Rolls left in the balanced dice deck, drawn from the end
+    #[serde(default)]
+    dice_deck: Vec<(u8, u8)>,
     /// Random number generator seed (for deterministic replays)
     rng_seed: u64,
+    /// Source of dice rolls and steals, continuing the stream that shuffled
+    /// the deck
+    #[serde(default)]
+    rng: GameRng,
 }
 
 impl GameState {
     /// Create a new game with the given number of players
     pub fn new(player_count: u8, player_names: Vec<String>) -> Self {
+        Self::new_with_options(player_count, player_names, GameOptions::default())
+    }
+
+    /// Create a new game with custom rule options
+    pub fn new_with_options(
+        player_count: u8,
+        player_names: Vec<String>,
+        options: GameOptions,
+    ) -> Self {
+        Self::new_with_seed(player_count, player_names, options, rand::thread_rng().gen())
+    }
+
+    /// Create a new game whose board, deck, seating, dice and steals all
+    /// follow from `seed`, so the same seed and actions replay the same game
+    pub fn new_with_seed(
+        player_count: u8,
+        player_names: Vec<String>,
+        options: GameOptions,
+        seed: u64,
+    ) -> Self {
+        let mut rng = GameRng::new(seed);
+        let template = if options.quick_start {
+            BoardTemplate::Beginner
+        } else {
+            options.board_template
+        };
+        let board = Board::from_template_with_rng(template, &options.board_gen, &mut rng);
+        let mut game = Self::with_board(player_count, player_names, options, board, seed, rng);
+        if game.options.quick_start {
+            game.quick_start();
+        }
+        game
+    }
+
+    /// Create a game from a scenario definition, one name per player.
+    /// Starting pieces are placed and taken from their owners' supply, and
+    /// the finished board must pass [`Board::validate`].
+    pub fn from_scenario(
+        scenario: &Scenario,
+        player_names: Vec<String>,
+    ) -> Result<Self, ScenarioError> {
+        if let Some(piece) = scenario
+            .starting_pieces
+            .iter()
+            .find(|p| p.player() as usize >= player_names.len())
+        {
+            return Err(ScenarioError::UnknownPlayer(piece.player()));
+        }
+        if !(2..=4).contains(&player_names.len()) {
+            return Err(ScenarioError::PlayerCount(player_names.len()));
+        }
+        let player_count = player_names.len() as u8;
+        let teams = &scenario.options.teams;
+        if !teams.is_empty() && teams.len() != player_names.len() {
+            return Err(ScenarioError::TeamsMismatch);
+        }
+
+        let board = Board::from_scenario(scenario)?;
+        let options = scenario.options.clone();
+        let seed = rand::thread_rng().gen();
+        let mut game =
+            Self::with_board(player_count, player_names, options, board, seed, GameRng::new(seed));
+
+        for piece in &scenario.starting_pieces {
+            let owner = piece.player();
+            let player = game
+                .get_player_mut(owner)
+                .ok_or(ScenarioError::UnknownPlayer(owner))?;
+            let supply = match piece {
+                StartingPiece::Settlement { .. } => &mut player.settlements_remaining,
+                StartingPiece::City { .. } => &mut player.cities_remaining,
+                StartingPiece::Road { .. } => &mut player.roads_remaining,
+            };
+            *supply = supply
+                .checked_sub(1)
+                .ok_or(ScenarioError::TooManyPieces(owner))?;
+        }
+
+        // Pieces in the same place overwrite each other, so look for those
+        // before checking the rest of the board
+        let mut vertices = HashSet::new();
+        let mut edges = HashSet::new();
+        for piece in &scenario.starting_pieces {
+            let fresh = match *piece {
+                StartingPiece::Settlement { vertex, .. } | StartingPiece::City { vertex, .. } => {
+                    vertices.insert(vertex.canonical())
+                }
+                StartingPiece::Road { edge, .. } => edges.insert(edge.canonical()),
+            };
+            if !fresh {
+                return Err(ScenarioError::PiecesOverlap);
+            }
+        }
+        game.board.validate().map_err(ScenarioError::InvalidBoard)?;
+
+        if scenario.skip_setup {
+            game.phase = GamePhase::PreRoll;
+            game.turn_number = 1;
+        }
+
+        Ok(game)
+    }
+
+    /// Put everyone on their beginner quick-start positions and go straight
+    /// to the first roll. Each player's second settlement pays out, as it
+    /// would in setup.
+    fn quick_start(&mut self) {
+        for piece in scenario::beginner_quick_start(self.players.len() as u8) {
+            let player = &mut self.players[piece.player() as usize];
+            match piece {
+                StartingPiece::Settlement { player: id, vertex } => {
+                    player.settlements_remaining -= 1;
+                    self.board.place_settlement(vertex, id);
+                    self.setup_placements[id as usize] += 1;
+                    if self.setup_placements[id as usize] == 2 {
+                        for tile in self.board.tiles_at_vertex(&vertex) {
+                            if let Some(resource) = tile.resource() {
+                                self.players[id as usize].resources.add(resource, 1);
+                            }
+                        }
+                    }
+                }
+                StartingPiece::City { player: id, vertex } => {
+                    player.cities_remaining -= 1;
+                    self.board.upgrade_to_city(vertex, id);
+                }
+                StartingPiece::Road { player: id, edge } => {
+                    player.roads_remaining -= 1;
+                    self.board.place_road(edge, id);
+                }
+            }
+        }
+        self.phase = GamePhase::PreRoll;
+        self.turn_number = 1;
+    }
+
+    fn with_board(
+        player_count: u8,
+        player_names: Vec<String>,
+        mut options: GameOptions,
+        mut board: Board,
+        rng_seed: u64,
+        mut rng: GameRng,
+    ) -> Self {
         assert!((2..=4).contains(&player_count), "Must have 2-4 players");
         assert_eq!(
             player_names.len(),
             player_count as usize,
             "Must provide names for all players"
         );
+        assert!(
+            options.teams.is_empty() || options.teams.len() == player_count as usize,
+            "Teams must assign every player"
+        );
 
         let players: Vec<Player> = player_names
             .into_iter()
             .enumerate()
-            .map(|(i, name)| Player::new(i as PlayerId, name))
+            .map(|(i, name)| {
+                let mut player = Player::new(i as PlayerId, name);
+                player.resources.add_hand(&options.starting_hand);
+                player
+            })
             .collect();
 
         // Create and shuffle dev card deck
         let mut dev_card_deck = DevelopmentCard::standard_deck();
-        let mut rng = rand::thread_rng();
-        let rng_seed = rng.gen();
         DevelopmentCard::shuffle_deck(&mut dev_card_deck, &mut rng);
 
         // First player is random
         let current_player = rng.gen_range(0..player_count);
 
+        if options.pirate {
+            // In a fixed order, so the same seed always starts it in one place
+            let mut ocean: Vec<HexCoord> = board.ocean_tiles().map(|t| t.coord).collect();
+            ocean.sort_unstable_by_key(|h| (h.q, h.r));
+            if !ocean.is_empty() {
+                board.move_pirate(ocean[rng.gen_range(0..ocean.len())]);
+            }
+        }
+        if options.robber_from_turn.is_some() {
+            board.remove_robber();
+        }
+        let cities_knights = (options.expansion == Expansion::CitiesAndKnights).then(|| {
+            options.event_die.get_or_insert_with(EventDie::default);
+            CitiesKnights::new(player_count as usize, &mut rng)
+        });
+
+        let clock = options
+            .time_control
+            .as_ref()
+            .map(|t| GameClock::new(t, player_count as usize));
+        let barbarians = options
+            .event_die
+            .as_ref()
+            .map(|die| BarbarianTrack::new(die.track_length));
+
         Self {
-            board: Board::standard(),
+            board,
             players,
             current_player,
             phase: GamePhase::Setup {
@@ -205,14 +605,41 @@ impl GameState {
             },
             turn_number: 0,
             dice_roll: None,
+            dice_history: Vec::new(),
             dev_card_deck,
             pending_trade: None,
             dev_card_played_this_turn: false,
+            dev_cards_played: DevCardCounts::default(),
+            clock,
+            event_roll: None,
+            barbarians,
+            cities_knights,
+            options,
             setup_settlement: None,
+            setup_placements: vec![0; player_count as usize],
+            dice_deck: Vec::new(),
             rng_seed,
+            rng,
         }
     }
 
+    /// The seed the game was created with
+    pub fn seed(&self) -> u64 {
+        self.rng_seed
+    }
+
+    /// Restart the random stream for future dice and steals from `seed`.
+    /// Bots use this on cloned states so their lookahead samples its own
+    /// futures instead of the real game's.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = GameRng::new(seed);
+    }
+
+    /// Where the random stream stands, to tell whether anything drew from it
+    pub(crate) fn rng(&self) -> &GameRng {
+        &self.rng
+    }
+
     /// Create a standard 4-player game
     pub fn new_standard_4player() -> Self {
         Self::new(
@@ -241,6 +668,38 @@ impl GameState {
         self.players.get_mut(id as usize)
     }
 
+    /// A player named in an action, who may not exist
+    fn player(&self, id: PlayerId) -> Result<&Player, GameError> {
+        self.get_player(id).ok_or(GameError::InvalidPlayer(id))
+    }
+
+    fn player_mut(&mut self, id: PlayerId) -> Result<&mut Player, GameError> {
+        self.get_player_mut(id).ok_or(GameError::InvalidPlayer(id))
+    }
+
+    /// The action needs one of the `expected` phases instead of this one
+    fn wrong_phase(&self, expected: &[PhaseKind]) -> GameError {
+        GameError::InvalidPhase {
+            expected: expected.to_vec(),
+            actual: self.phase.kind(),
+        }
+    }
+
+    /// Where a rolled 7 leads once the discards are in: moving the robber,
+    /// unless it is still waiting off the board
+    fn phase_after_seven(&self) -> GamePhase {
+        let waiting = self.board.robber_location().is_none()
+            && self
+                .options
+                .robber_from_turn
+                .is_some_and(|turn| self.turn_number <= turn);
+        if waiting {
+            GamePhase::MainPhase
+        } else {
+            GamePhase::RobberMoveRequired
+        }
+    }
+
     /// Calculate total victory points for a player (including buildings on board)
     pub fn total_victory_points(&self, player_id: PlayerId) -> u32 {
         let player = match self.get_player(player_id) {
@@ -250,8 +709,8 @@ impl GameState {
 
         let mut vp = player.victory_points(); // Achievements + VP cards
 
-        // Count buildings on board
-        let settlements = 5 - player.settlements_remaining + player.cities_remaining - 4;
+        // Count buildings on board (upgrading returns the settlement piece)
+        let settlements = 5 - player.settlements_remaining;
         let cities = 4 - player.cities_remaining;
 
         vp += settlements; // 1 VP each
@@ -260,20 +719,106 @@ impl GameState {
         vp
     }
 
+    /// Settlements a player has placed in setup, not counting any the
+    /// scenario started them with
+    pub fn setup_settlements_placed(&self, player: PlayerId) -> u32 {
+        self.setup_placements.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Victory points a player needs to win
+    pub fn victory_points_to_win(&self) -> u32 {
+        self.options
+            .victory_points_to_win
+            .unwrap_or(VICTORY_POINTS_TO_WIN)
+    }
+
+    /// Team of a player, if playing with teams
+    pub fn team_of(&self, player: PlayerId) -> Option<u8> {
+        self.options.teams.get(player as usize).copied()
+    }
+
+    /// Whether two players are on the same side (a player is always their own teammate)
+    pub fn are_teammates(&self, a: PlayerId, b: PlayerId) -> bool {
+        a == b || (self.team_of(a).is_some() && self.team_of(a) == self.team_of(b))
+    }
+
+    /// Whether a player is still playing (exists and has not conceded)
+    pub fn is_active(&self, id: PlayerId) -> bool {
+        self.get_player(id).is_some_and(|p| !p.conceded)
+    }
+
+    /// IDs of all players who have not conceded
+    pub fn active_players(&self) -> Vec<PlayerId> {
+        self.players.iter().filter(|p| !p.conceded).map(|p| p.id).collect()
+    }
+
+    /// Next active player after `from` in turn order (wraps around).
+    /// Returns `from` itself if nobody else is active.
+    fn next_active_player(&self, from: PlayerId) -> PlayerId {
+        let count = self.player_count() as PlayerId;
+        (1..=count)
+            .map(|offset| (from + offset) % count)
+            .find(|&id| self.is_active(id))
+            .unwrap_or(from)
+    }
+
+    /// Previous active player before `from` in turn order (wraps around)
+    fn previous_active_player(&self, from: PlayerId) -> PlayerId {
+        let count = self.player_count() as PlayerId;
+        (1..=count)
+            .map(|offset| (from + count - offset) % count)
+            .find(|&id| self.is_active(id))
+            .unwrap_or(from)
+    }
+
     /// Check if the game is finished
     pub fn is_finished(&self) -> bool {
         matches!(self.phase, GamePhase::Finished { .. })
     }
 
-    /// Get the winner if the game is finished
+    /// Get the winner if the game is finished (None for games ended by agreement)
     pub fn get_winner(&self) -> Option<PlayerId> {
-        if let GamePhase::Finished { winner } = self.phase {
-            Some(winner)
+        if let GamePhase::Finished { winner, .. } = self.phase {
+            winner
+        } else {
+            None
+        }
+    }
+
+    /// Final standings if the game is finished
+    pub fn get_ranking(&self) -> Option<&[PlayerId]> {
+        if let GamePhase::Finished { ranking, .. } = &self.phase {
+            Some(ranking)
         } else {
             None
         }
     }
 
+    /// Order players by current standing: the given winner first, then active
+    /// players by victory points, then conceded players. VP ties go to the
+    /// most knights played, then the most resource cards, then seat order.
+    fn standings(&self, winner: Option<PlayerId>) -> Vec<PlayerId> {
+        let mut ranking: Vec<PlayerId> = self.players.iter().map(|p| p.id).collect();
+        ranking.sort_by_key(|&id| {
+            let player = &self.players[id as usize];
+            (
+                Some(id) != winner,
+                !winner.is_some_and(|w| self.are_teammates(id, w)),
+                player.conceded,
+                std::cmp::Reverse(self.total_victory_points(id)),
+                std::cmp::Reverse(player.played_knights),
+                std::cmp::Reverse(player.resources.total()),
+            )
+        });
+        ranking
+    }
+
+    /// End the game, recording final standings
+    fn finish_game(&mut self, winner: Option<PlayerId>) {
+        let ranking = self.standings(winner);
+        self.phase = GamePhase::Finished { winner, ranking };
+    }
+
     /// Convert to a JSON-friendly representation with arrays instead of HashMaps
     /// This is needed because JSON doesn't support complex types as keys
     pub fn to_json_friendly(&self) -> GameStateJson {
@@ -282,6 +827,13 @@ impl GameState {
             .map(|id| self.total_victory_points(id))
             .collect();
 
+        let hand_sizes = self.players.iter().map(|p| p.resources.total()).collect();
+        let dev_card_counts = self
+            .players
+            .iter()
+            .map(|p| (p.dev_cards.len() + p.dev_cards_bought_this_turn.len()) as u32)
+            .collect();
+
         GameStateJson {
             board: self.board.to_json_friendly(),
             players: self.players.clone(),
@@ -292,13 +844,166 @@ impl GameState {
             dev_card_deck_size: self.dev_card_deck.len(),
             pending_trade: self.pending_trade.clone(),
             victory_points,
+            hand_sizes,
+            dev_card_counts,
+            dev_cards_revealed: self.dev_cards_revealed(),
+            clock: self.clock.clone(),
+            event_roll: self.event_roll,
+            barbarians: self.barbarians.clone(),
+            cities_knights: self.cities_knights.as_ref().map(|ck| ck.players.clone()),
+            dice_deck_remaining: self.dice_deck_remaining(),
+        }
+    }
+
+    /// Rolls left before the balanced dice deck is reshuffled, when
+    /// playing with one
+    pub fn dice_deck_remaining(&self) -> Option<u32> {
+        (self.options.dice == DiceMode::Balanced).then_some(self.dice_deck.len() as u32)
+    }
+
+    /// How the dice have fallen so far this game
+    pub fn dice_stats(&self) -> DiceStats {
+        DiceStats::from_rolls(&self.dice_history)
+    }
+
+    /// Development cards left to buy
+    pub fn dev_deck_remaining(&self) -> u32 {
+        self.dev_card_deck.len() as u32
+    }
+
+    /// Development cards everyone has seen: those played, and the victory
+    /// point cards once they are shown at the end
+    pub fn dev_cards_revealed(&self) -> DevCardCounts {
+        let mut revealed = self.dev_cards_played;
+        if self.is_finished() {
+            for player in &self.players {
+                revealed.add(DevelopmentCard::VictoryPoint, player.hidden_vp());
+            }
+        }
+        revealed
+    }
+
+    /// Development cards `viewer` hasn't seen, in the deck or in other
+    /// players' hands, by type: what a bot may reason about when guessing
+    /// what others hold or what comes next
+    pub fn unseen_dev_cards(&self, viewer: PlayerId) -> DevCardCounts {
+        let all = DevCardCounts::of(&DevelopmentCard::standard_deck());
+        let mut seen = self.dev_cards_revealed();
+        if let Some(player) = self.get_player(viewer) {
+            for &card in player.dev_cards.iter().chain(&player.dev_cards_bought_this_turn) {
+                seen.add(card, 1);
+            }
+        }
+        all.minus(&seen)
+    }
+
+    /// Hex SHA-256 of everything that decides how the game goes on: the
+    /// state, with the board in a fixed order, plus the deck and the random
+    /// stream. Games with the same hash play on identically.
+    pub fn state_hash(&self) -> String {
+        fn sorted<T: Serialize>(items: &[T]) -> Vec<String> {
+            let mut json: Vec<String> = items
+                .iter()
+                .map(|item| serde_json::to_string(item).unwrap_or_default())
+                .collect();
+            json.sort();
+            json
+        }
+        let mut state = serde_json::to_value(self.to_json_friendly()).unwrap_or_default();
+        let board = &self.board.to_json_friendly();
+        state["board"]["tiles"] = sorted(&board.tiles).into();
+        state["board"]["vertices"] = sorted(&board.vertices).into();
+        state["board"]["edges"] = sorted(&board.edges).into();
+        state["board"]["harbors"] = sorted(&board.harbors).into();
+        state["deck"] = serde_json::to_value(&self.dev_card_deck).unwrap_or_default();
+        state["rng"] = serde_json::to_value(&self.rng).unwrap_or_default();
+
+        let digest = sha2::Sha256::digest(state.to_string().as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Every field that differs from `other`, hidden ones (the deck, the
+    /// random stream, the rules) included, for tracking down why two games
+    /// that should match don't
+    pub fn diff_debug(&self, other: &GameState) -> Vec<crate::delta::StateDifference> {
+        let json = |game: &GameState| {
+            let mut state = serde_json::to_value(game.to_json_friendly()).unwrap_or_default();
+            state["dev_card_deck"] = serde_json::to_value(&game.dev_card_deck).unwrap_or_default();
+            state["dev_card_played_this_turn"] = game.dev_card_played_this_turn.into();
+            state["options"] = serde_json::to_value(&game.options).unwrap_or_default();
+            state["setup_settlement"] =
+                serde_json::to_value(game.setup_settlement).unwrap_or_default();
+            state["setup_placements"] =
+                serde_json::to_value(&game.setup_placements).unwrap_or_default();
+            state["rng_seed"] = game.rng_seed.into();
+            state["rng"] = serde_json::to_value(&game.rng).unwrap_or_default();
+            state
+        };
+        crate::delta::diff_json(json(self), json(other))
+    }
+
+    /// JSON-friendly view as seen by one player: other players' resources and
+    /// development cards are hidden (only their counts remain) and their
+    /// victory points exclude unrevealed VP cards. `None` hides every hand.
+    /// Everything is revealed once the game is finished.
+    pub fn view_for(&self, viewer: Option<PlayerId>) -> GameStateJson {
+        let mut view = self.to_json_friendly();
+        if self.is_finished() {
+            return view;
+        }
+
+        for (player, vp) in view.players.iter_mut().zip(view.victory_points.iter_mut()) {
+            if Some(player.id) == viewer {
+                continue;
+            }
+            *vp -= player.hidden_vp();
+            player.resources = ResourceHand::new();
+            player.dev_cards.clear();
+            player.dev_cards_bought_this_turn.clear();
+        }
+        for (id, holdings) in view.cities_knights.iter_mut().flatten().enumerate() {
+            if Some(id as PlayerId) != viewer {
+                holdings.commodities = CommodityHand::default();
+                holdings.progress_cards.clear();
+            }
+        }
+
+        view
+    }
+
+    /// What a shared hot-seat screen may show: nothing private while waiting
+    /// for the handoff, otherwise the current player's view
+    pub fn hot_seat_view(&self) -> GameStateJson {
+        if matches!(self.phase, GamePhase::AwaitingNextPlayer { .. }) {
+            self.view_for(None)
+        } else {
+            self.view_for(Some(self.current_player))
         }
     }
 
     /// Check if any player has won
     fn check_winner(&self) -> Option<PlayerId> {
-        for player in &self.players {
-            if self.total_victory_points(player.id) >= VICTORY_POINTS_TO_WIN {
+        if let TeamVictory::Combined { points } = self.options.team_victory {
+            if !self.options.teams.is_empty() {
+                // Credit the win to the current player when their team made it
+                let candidates = std::iter::once(self.current_player).chain(self.active_players());
+                for player in candidates.filter(|&p| self.is_active(p)) {
+                    let team_vp: u32 = self
+                        .active_players()
+                        .into_iter()
+                        .filter(|&p| self.are_teammates(p, player))
+                        .map(|p| self.total_victory_points(p))
+                        .sum();
+                    if team_vp >= points {
+                        return Some(player);
+                    }
+                }
+                return None;
+            }
+        }
+
+        for player in self.players.iter().filter(|p| !p.conceded) {
+            if self.total_victory_points(player.id) >= self.victory_points_to_win() {
                 return Some(player.id);
             }
         }
@@ -309,11 +1014,28 @@ impl GameState {
     pub fn valid_actions(&self, player: PlayerId) -> Vec<GameAction> {
         let mut actions = Vec::new();
 
+        if !self.is_active(player) {
+            return actions;
+        }
+
         match &self.phase {
             GamePhase::Finished { .. } => {
                 // No actions when game is over
             }
 
+            GamePhase::EndGameVote { accepted, .. } => {
+                if !accepted.contains(&player) {
+                    actions.push(GameAction::VoteEndGame(true));
+                    actions.push(GameAction::VoteEndGame(false));
+                }
+            }
+
+            GamePhase::AwaitingNextPlayer { .. } => {
+                if player == self.current_player {
+                    actions.push(GameAction::ConfirmNextPlayer);
+                }
+            }
+
             GamePhase::Setup { placing, .. } => {
                 if player != self.current_player {
                     return actions;
@@ -375,13 +1097,28 @@ impl GameState {
                     return actions;
                 }
 
-                // Can move robber to any land tile except current location
-                for tile in self.board.land_tiles() {
-                    if tile.coord != self.board.robber_location()
-                        && !matches!(tile.tile_type, TileType::Ocean)
-                    {
-                        actions.push(GameAction::MoveRobber(tile.coord));
-                    }
+                // Can move robber to any land tile except current location.
+                // Tiles are listed in board order so seeded games replay the
+                // same choices.
+                let mut land: Vec<HexCoord> = self
+                    .board
+                    .land_tiles()
+                    .filter(|tile| !matches!(tile.tile_type, TileType::Ocean))
+                    .map(|tile| tile.coord)
+                    .filter(|coord| Some(*coord) != self.board.robber_location())
+                    .collect();
+                land.sort_by_key(|coord| (coord.r, coord.q));
+                actions.extend(land.into_iter().map(GameAction::MoveRobber));
+
+                if let Some(pirate) = self.board.pirate_location() {
+                    let mut ocean: Vec<HexCoord> = self
+                        .board
+                        .ocean_tiles()
+                        .map(|tile| tile.coord)
+                        .filter(|coord| *coord != pirate)
+                        .collect();
+                    ocean.sort_by_key(|coord| (coord.r, coord.q));
+                    actions.extend(ocean.into_iter().map(GameAction::MovePirate));
                 }
             }
 
@@ -434,6 +1171,18 @@ impl GameState {
                         actions.push(GameAction::BuyDevelopmentCard);
                     }
 
+                    if let Some(ck) = &self.cities_knights {
+                        if self.has_city(player) {
+                            for track in Improvement::ALL {
+                                if ck.next_level(player, track).is_some()
+                                    && ck.shortfall(player, track).total() == 0
+                                {
+                                    actions.push(GameAction::BuildCityImprovement(track));
+                                }
+                            }
+                        }
+                    }
+
                     // Development cards
                     if !self.dev_card_played_this_turn {
                         if p.has_playable_dev_card(DevelopmentCard::Knight) {
@@ -442,8 +1191,9 @@ impl GameState {
                         if p.has_playable_dev_card(DevelopmentCard::RoadBuilding)
                             && p.roads_remaining >= 2
                         {
-                            // Road building requires specifying both roads
-                            // We'd need to enumerate pairs - simplified for now
+                            for (first, second) in self.board.road_building_pairs(player) {
+                                actions.push(GameAction::PlayRoadBuilding(first, second));
+                            }
                         }
                         if p.has_playable_dev_card(DevelopmentCard::YearOfPlenty) {
                             for r1 in Resource::ALL {
@@ -489,8 +1239,7 @@ impl GameState {
                 }
 
                 // Must place roads
-                let p = self.get_player(player).unwrap();
-                if p.roads_remaining > 0 {
+                if self.get_player(player).is_some_and(|p| p.roads_remaining > 0) {
                     for edge in self.board.valid_road_spots(player) {
                         actions.push(GameAction::BuildRoad(edge));
                     }
@@ -501,6 +1250,129 @@ impl GameState {
         actions
     }
 
+    /// Players the game is currently waiting on to act
+    pub fn waiting_on(&self) -> Vec<PlayerId> {
+        match &self.phase {
+            GamePhase::Finished { .. } => Vec::new(),
+            GamePhase::DiscardRequired { players_remaining } => players_remaining.clone(),
+            GamePhase::EndGameVote { accepted, .. } => self
+                .active_players()
+                .into_iter()
+                .filter(|p| !accepted.contains(p))
+                .collect(),
+            _ => vec![self.current_player],
+        }
+    }
+
+    /// A default move for a player who cannot or will not choose one:
+    /// roll, end the turn, discard the largest piles, refuse votes, move
+    /// the robber where it blocks nobody, and otherwise take the first
+    /// valid action.
+    pub fn auto_action(&self, player: PlayerId) -> Option<GameAction> {
+        let valid = self.valid_actions(player);
+
+        match &self.phase {
+            GamePhase::PreRoll if valid.contains(&GameAction::RollDice) => {
+                Some(GameAction::RollDice)
+            }
+            GamePhase::MainPhase if valid.contains(&GameAction::EndTurn) => {
+                Some(GameAction::EndTurn)
+            }
+            GamePhase::DiscardRequired { .. } if !valid.is_empty() => Some(
+                GameAction::DiscardCards(crate::bot::bot_discard(self, player)),
+            ),
+            GamePhase::EndGameVote { .. } if !valid.is_empty() => {
+                Some(GameAction::VoteEndGame(false))
+            }
+            GamePhase::RobberMoveRequired => {
+                // Prefer a tile nobody builds on, then one that doesn't
+                // block our own production
+                let adjacent = |action: &GameAction| match action {
+                    GameAction::MoveRobber(hex) => Some(self.board.players_adjacent_to_hex(hex)),
+                    _ => None,
+                };
+                valid
+                    .iter()
+                    .find(|a| adjacent(a).is_some_and(|p| p.is_empty()))
+                    .or_else(|| {
+                        valid
+                            .iter()
+                            .find(|a| adjacent(a).is_some_and(|p| !p.contains(&player)))
+                    })
+                    .or(valid.first())
+                    .cloned()
+            }
+            _ => valid.into_iter().next(),
+        }
+    }
+
+    /// The move a bot of the given difficulty would make in `player`'s
+    /// place, for a hint button. None if the game isn't waiting on them.
+    pub fn suggest_action(
+        &self,
+        player: PlayerId,
+        difficulty: BotDifficulty,
+    ) -> Option<GameAction> {
+        self.hint_bot(player, difficulty).choose_action(self)
+    }
+
+    /// Cards to give up, if `player` has a discard to make
+    pub fn suggest_discard(&self, player: PlayerId) -> Option<ResourceHand> {
+        let due = self
+            .valid_actions(player)
+            .iter()
+            .any(|a| matches!(a, GameAction::DiscardCards(_)));
+        due.then(|| self.hint_bot(player, BotDifficulty::Hard).choose_discard(self))
+    }
+
+    /// Whether to accept the trade on the table, if it's `player`'s to answer
+    pub fn suggest_trade_response(
+        &self,
+        player: PlayerId,
+        difficulty: BotDifficulty,
+    ) -> Option<bool> {
+        let trade = self.pending_trade.as_ref()?;
+        if !self.valid_actions(player).contains(&GameAction::AcceptTrade) {
+            return None;
+        }
+        Some(self.hint_bot(player, difficulty).respond_to_trade(self, &trade.offer))
+    }
+
+    /// All the suggestions for `player` at once
+    pub fn hint(&self, player: PlayerId, difficulty: BotDifficulty) -> Hint {
+        Hint {
+            action: self.suggest_action(player, difficulty),
+            discard: self.suggest_discard(player),
+            accept_trade: self.suggest_trade_response(player, difficulty),
+        }
+    }
+
+    /// A bot for hints, seeded from the position so asking twice gives the
+    /// same answer
+    fn hint_bot(&self, player: PlayerId, difficulty: BotDifficulty) -> Bot {
+        let seed = self.rng_seed ^ ((self.turn_number as u64) << 8) ^ player as u64;
+        Bot::with_seed(player, difficulty, seed)
+    }
+
+    /// Advance the time banks by the time elapsed since the last tick.
+    /// Everyone the game is waiting on is charged; a player whose bank
+    /// runs out is handled according to the flag-fall policy.
+    pub fn tick(&mut self, elapsed: std::time::Duration) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let elapsed_ms = elapsed.as_millis() as u64;
+
+        for player in self.waiting_on() {
+            let Some(clock) = self.clock.as_mut() else {
+                break;
+            };
+            if clock.spend(player, elapsed_ms) && self.waiting_on().contains(&player) {
+                events.extend(self.flag_fall(player));
+            }
+        }
+
+        events
+    }
+
     /// Get the maritime trade rate for a resource
     fn get_maritime_rate(
         &self,
@@ -521,34 +1393,90 @@ impl GameState {
     }
 
     /// Apply an action to the game state
+    #[cfg(not(feature = "strict-invariants"))]
+    pub fn apply_action(
+        &mut self,
+        player: PlayerId,
+        action: GameAction,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        self.apply(player, action)
+    }
+
+    /// Apply an action to the game state, panicking if the engine broke
+    /// one of its own invariants doing so
+    #[cfg(feature = "strict-invariants")]
     pub fn apply_action(
         &mut self,
         player: PlayerId,
         action: GameAction,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        let before = crate::invariants::Before::of(self);
+        let events = self.apply(player, action.clone())?;
+        crate::invariants::assert_move(&before, self, player, &action, &events);
+        Ok(events)
+    }
+
+    /// Apply an action and add the achievements it unlocked
+    fn apply(
+        &mut self,
+        player: PlayerId,
+        action: GameAction,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        let mut events = self.apply_move(player, action)?;
+        let unlocked = crate::achievements::unlock(self, &events);
+        events.extend(unlocked);
+        Ok(events)
+    }
+
+    fn apply_move(
+        &mut self,
+        player: PlayerId,
+        action: GameAction,
     ) -> Result<Vec<GameEvent>, GameError> {
         // Check game not over
         if matches!(self.phase, GamePhase::Finished { .. }) {
             return Err(GameError::GameOver);
         }
-
-        let mut events = Vec::new();
+        if self.player(player)?.conceded {
+            return Err(GameError::PlayerConceded);
+        }
+        // The game is suspended while an end-game vote is running
+        if matches!(self.phase, GamePhase::EndGameVote { .. })
+            && !matches!(action, GameAction::VoteEndGame(_) | GameAction::Concede)
+        {
+            return Err(GameError::Paused(PhaseKind::EndGameVote));
+        }
+        // Nothing happens behind the hot-seat screen lock
+        if matches!(self.phase, GamePhase::AwaitingNextPlayer { .. })
+            && !matches!(action, GameAction::ConfirmNextPlayer | GameAction::Concede)
+        {
+            return Err(GameError::Paused(PhaseKind::AwaitingNextPlayer));
+        }
+
+        let mut events = Vec::new();
 
         match action {
             // ==================== Setup Phase ====================
             GameAction::PlaceInitialSettlement(vertex) => {
                 self.validate_setup_settlement(player, vertex)?;
 
+                let p = self.player_mut(player)?;
+                p.settlements_remaining = p
+                    .settlements_remaining
+                    .checked_sub(1)
+                    .ok_or(GameError::NoPiecesRemaining(Piece::Settlement))?;
                 self.board.place_settlement(vertex, player);
-                self.get_player_mut(player).unwrap().settlements_remaining -= 1;
                 self.setup_settlement = Some(vertex);
+                self.setup_placements.resize(self.players.len(), 0);
+                self.setup_placements[player as usize] += 1;
 
                 events.push(GameEvent::SettlementBuilt {
                     player,
                     location: vertex,
                 });
 
-                // In round 2, give resources for second settlement
-                if let GamePhase::Setup { round: 2, .. } = self.phase {
+                // The second settlement brings in its first resources
+                if self.setup_settlements_placed(player) == 2 {
                     // Collect resources first to avoid borrow issues
                     let resources_to_give: Vec<Resource> = self
                         .board
@@ -559,7 +1487,7 @@ impl GameState {
 
                     let mut resources_given = Vec::new();
                     for resource in resources_to_give {
-                        self.get_player_mut(player).unwrap().resources.add(resource, 1);
+                        self.player_mut(player)?.resources.add(resource, 1);
                         resources_given.push((player, resource, 1));
                     }
                     if !resources_given.is_empty() {
@@ -581,8 +1509,12 @@ impl GameState {
             GameAction::PlaceInitialRoad(edge) => {
                 self.validate_setup_road(player, edge)?;
 
+                let p = self.player_mut(player)?;
+                p.roads_remaining = p
+                    .roads_remaining
+                    .checked_sub(1)
+                    .ok_or(GameError::NoPiecesRemaining(Piece::Road))?;
                 self.board.place_road(edge, player);
-                self.get_player_mut(player).unwrap().roads_remaining -= 1;
                 self.setup_settlement = None;
 
                 events.push(GameEvent::RoadBuilt {
@@ -600,28 +1532,28 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::PreRoll {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::PreRoll]));
                 }
 
-                let mut rng = rand::thread_rng();
-                let die1 = rng.gen_range(1..=6);
-                let die2 = rng.gen_range(1..=6);
+                let (die1, die2) = self.roll_dice(&mut events);
                 let total = die1 + die2;
 
                 self.dice_roll = Some((die1, die2));
+                self.dice_history.push((die1, die2));
 
                 events.push(GameEvent::DiceRolled {
                     player,
                     roll: (die1, die2),
                     total,
                 });
+                events.extend(self.roll_event_die(player, die1));
 
                 if total == 7 {
                     // Check for players who need to discard
                     let must_discard: Vec<PlayerId> = self
                         .players
                         .iter()
-                        .filter(|p| p.resources.total() > 7)
+                        .filter(|p| !p.conceded && p.resources.total() > 7)
                         .map(|p| p.id)
                         .collect();
 
@@ -630,20 +1562,57 @@ impl GameState {
                             players_remaining: must_discard,
                         };
                     } else {
-                        self.phase = GamePhase::RobberMoveRequired;
+                        self.phase = self.phase_after_seven();
                     }
                 } else {
                     // Distribute resources
-                    let distribution = self.board.resources_for_roll(total);
+                    let mut distribution = self.board.resources_for_roll(total);
                     let mut dist_events = Vec::new();
 
+                    // Cities & Knights cities make a commodity in place of
+                    // their second resource on some tiles
+                    if let Some(ck) = self.cities_knights.as_mut() {
+                        let mut made = Vec::new();
+                        for (pid, commodities) in
+                            cities_knights::commodities_for_roll(&self.board, total)
+                        {
+                            for commodity in cities_knights::Commodity::ALL {
+                                let amount = commodities.get(commodity);
+                                if amount == 0 {
+                                    continue;
+                                }
+                                if let Some(count) = distribution
+                                    .get_mut(&pid)
+                                    .and_then(|r| r.get_mut(&commodity.resource()))
+                                {
+                                    *count -= amount;
+                                }
+                                if let Some(holdings) = ck.player_mut(pid) {
+                                    holdings.commodities.add(commodity, amount);
+                                }
+                                made.push((pid, commodity, amount));
+                            }
+                        }
+                        made.sort_unstable();
+                        if !made.is_empty() {
+                            events.push(GameEvent::CommoditiesProduced {
+                                distributions: made,
+                            });
+                        }
+                    }
+
                     for (pid, resources) in distribution {
                         for (resource, amount) in resources {
-                            self.get_player_mut(pid).unwrap().resources.add(resource, amount);
+                            if amount == 0 {
+                                continue;
+                            }
+                            self.player_mut(pid)?.resources.add(resource, amount);
                             dist_events.push((pid, resource, amount));
                         }
                     }
 
+                    // Same order every time, for replays
+                    dist_events.sort_unstable();
                     if !dist_events.is_empty() {
                         events.push(GameEvent::ResourcesDistributed {
                             distributions: dist_events,
@@ -666,28 +1635,27 @@ impl GameState {
                             return Err(GameError::NotYourTurn);
                         }
 
-                        let p = self.get_player(player).unwrap();
+                        let p = self.player(player)?;
                         let required = p.resources.total() / 2;
 
                         if cards.total() != required {
-                            return Err(GameError::InvalidDiscard);
+                            return Err(GameError::InvalidDiscard { required });
                         }
 
                         if !p.resources.can_afford(&cards) {
-                            return Err(GameError::InvalidDiscard);
+                            return Err(GameError::InsufficientResources {
+                                missing: p.resources.shortfall(&cards),
+                            });
                         }
 
                         required
                     } else {
-                        return Err(GameError::InvalidPhase);
+                        return Err(self.wrong_phase(&[PhaseKind::DiscardRequired]));
                     }
                 };
 
                 // Now do the mutations
-                self.get_player_mut(player)
-                    .unwrap()
-                    .resources
-                    .subtract(&cards);
+                self.player_mut(player)?.resources.subtract(&cards)?;
 
                 events.push(GameEvent::CardsDiscarded {
                     player,
@@ -695,6 +1663,7 @@ impl GameState {
                 });
 
                 // Update phase
+                let after_seven = self.phase_after_seven();
                 if let GamePhase::DiscardRequired {
                     ref mut players_remaining,
                 } = self.phase
@@ -702,7 +1671,7 @@ impl GameState {
                     players_remaining.retain(|&p| p != player);
 
                     if players_remaining.is_empty() {
-                        self.phase = GamePhase::RobberMoveRequired;
+                        self.phase = after_seven;
                     }
                 }
             }
@@ -713,16 +1682,17 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::RobberMoveRequired {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::RobberMoveRequired]));
                 }
 
-                // Validate: must be land tile, not current location
+                // Validate: must be land tile, not current location. A robber
+                // coming onto the board may go anywhere on land.
                 let tile = self.board.get_tile(&hex);
                 if tile.is_none() || matches!(tile.unwrap().tile_type, TileType::Ocean) {
-                    return Err(GameError::InvalidLocation);
+                    return Err(GameError::InvalidLocation(Location::Hex(hex)));
                 }
-                if hex == self.board.robber_location() {
-                    return Err(GameError::InvalidLocation);
+                if Some(hex) == self.board.robber_location() {
+                    return Err(GameError::InvalidLocation(Location::Hex(hex)));
                 }
 
                 let old_location = self.board.robber_location();
@@ -735,12 +1705,17 @@ impl GameState {
                 });
 
                 // Find potential victims
-                let victims: Vec<PlayerId> = self
+                let mut victims: Vec<PlayerId> = self
                     .board
                     .players_adjacent_to_hex(&hex)
                     .into_iter()
-                    .filter(|&p| p != player && self.get_player(p).unwrap().resources.total() > 0)
+                    .filter(|&p| {
+                        !self.are_teammates(p, player)
+                            && self.is_active(p)
+                            && self.get_player(p).is_some_and(|v| v.resources.total() > 0)
+                    })
                     .collect();
+                victims.sort_unstable();
 
                 if victims.is_empty() {
                     self.phase = GamePhase::MainPhase;
@@ -757,16 +1732,50 @@ impl GameState {
                 }
             }
 
+            GameAction::MovePirate(hex) => {
+                if player != self.current_player {
+                    return Err(GameError::NotYourTurn);
+                }
+                if self.phase != GamePhase::RobberMoveRequired {
+                    return Err(self.wrong_phase(&[PhaseKind::RobberMoveRequired]));
+                }
+                // Without the pirate rule there is nothing to move
+                let Some(old_location) = self.board.pirate_location() else {
+                    return Err(GameError::InvalidLocation(Location::Hex(hex)));
+                };
+
+                // Validate: must be an ocean tile, not current location
+                let tile = self.board.get_tile(&hex);
+                if !tile.is_some_and(|t| matches!(t.tile_type, TileType::Ocean)) {
+                    return Err(GameError::InvalidLocation(Location::Hex(hex)));
+                }
+                if hex == old_location {
+                    return Err(GameError::InvalidLocation(Location::Hex(hex)));
+                }
+
+                self.board.move_pirate(hex);
+
+                events.push(GameEvent::PirateMoved {
+                    player,
+                    from: old_location,
+                    to: hex,
+                });
+
+                // The pirate robs ship owners next to its hex; with no ships on
+                // the board yet there is never anyone to steal from
+                self.phase = GamePhase::MainPhase;
+            }
+
             GameAction::StealFrom(victim) => {
                 if player != self.current_player {
                     return Err(GameError::NotYourTurn);
                 }
                 if let GamePhase::RobberSteal { victims, .. } = &self.phase {
                     if !victims.contains(&victim) {
-                        return Err(GameError::InvalidLocation);
+                        return Err(GameError::CannotRob(victim));
                     }
                 } else {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::RobberSteal]));
                 }
 
                 events.extend(self.steal_from_player(player, victim)?);
@@ -782,24 +1791,24 @@ impl GameState {
                 let is_road_building =
                     matches!(self.phase, GamePhase::RoadBuildingInProgress { .. });
                 if !is_road_building && self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[
+                        PhaseKind::MainPhase,
+                        PhaseKind::RoadBuildingInProgress,
+                    ]));
                 }
 
                 // Validate location
                 if !self.board.valid_road_spots(player).contains(&edge) {
-                    return Err(GameError::InvalidLocation);
+                    return Err(GameError::InvalidLocation(Location::Edge(edge)));
                 }
 
-                let p = self.get_player_mut(player).unwrap();
+                let p = self.player_mut(player)?;
                 if p.roads_remaining == 0 {
-                    return Err(GameError::NoPiecesRemaining);
+                    return Err(GameError::NoPiecesRemaining(Piece::Road));
                 }
 
                 if !is_road_building {
-                    if !p.can_afford_road() {
-                        return Err(GameError::CannotAfford);
-                    }
-                    p.buy_road();
+                    p.buy_road()?;
                 } else {
                     p.roads_remaining -= 1;
                 }
@@ -828,19 +1837,14 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
                 if !self.board.valid_settlement_spots(player, false).contains(&vertex) {
-                    return Err(GameError::InvalidLocation);
-                }
-
-                let p = self.get_player_mut(player).unwrap();
-                if !p.can_afford_settlement() {
-                    return Err(GameError::CannotAfford);
+                    return Err(GameError::InvalidLocation(Location::Vertex(vertex)));
                 }
 
-                p.buy_settlement();
+                self.player_mut(player)?.buy_settlement()?;
                 self.board.place_settlement(vertex, player);
 
                 events.push(GameEvent::SettlementBuilt {
@@ -858,19 +1862,14 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
                 if !self.board.valid_city_spots(player).contains(&vertex) {
-                    return Err(GameError::InvalidLocation);
+                    return Err(GameError::InvalidLocation(Location::Vertex(vertex)));
                 }
 
-                let p = self.get_player_mut(player).unwrap();
-                if !p.can_afford_city() {
-                    return Err(GameError::CannotAfford);
-                }
-
-                p.buy_city();
+                self.player_mut(player)?.buy_city()?;
                 self.board.upgrade_to_city(vertex, player);
 
                 events.push(GameEvent::CityBuilt {
@@ -881,30 +1880,55 @@ impl GameState {
                 events.extend(self.check_win_condition());
             }
 
-            GameAction::BuyDevelopmentCard => {
+            GameAction::BuildCityImprovement(track) => {
+                if self.cities_knights.is_none() {
+                    return Err(GameError::ExpansionRequired);
+                }
                 if player != self.current_player {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
-
-                if self.dev_card_deck.is_empty() {
-                    return Err(GameError::EmptyDeck);
+                let has_city = self.has_city(player);
+                let Some(ck) = self.cities_knights.as_mut() else {
+                    return Err(GameError::ExpansionRequired);
+                };
+                let Some(level) = ck.next_level(player, track).filter(|_| has_city) else {
+                    return Err(GameError::CannotImprove(track));
+                };
+                if !ck.improve(player, track) {
+                    return Err(GameError::MissingCommodities {
+                        missing: ck.shortfall(player, track),
+                    });
                 }
 
-                // Check affordability first
-                {
-                    let p = self.get_player(player).unwrap();
-                    if !p.can_afford_dev_card() {
-                        return Err(GameError::CannotAfford);
-                    }
+                events.push(GameEvent::CityImproved {
+                    player,
+                    track,
+                    level,
+                });
+            }
+
+            GameAction::BuyDevelopmentCard => {
+                if player != self.current_player {
+                    return Err(GameError::NotYourTurn);
+                }
+                if self.phase != GamePhase::MainPhase {
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
-                let card = self.dev_card_deck.pop().unwrap();
-                self.get_player_mut(player).unwrap().buy_dev_card(card);
+                let Some(&card) = self.dev_card_deck.last() else {
+                    return Err(GameError::EmptyDeck);
+                };
+                self.player_mut(player)?.buy_dev_card(card)?;
+                self.dev_card_deck.pop();
 
-                events.push(GameEvent::DevelopmentCardPurchased { player });
+                events.push(GameEvent::DevelopmentCardPurchased {
+                    player,
+                    deck_remaining: self.dev_deck_remaining(),
+                });
+                events.push(GameEvent::DevelopmentCardDrawn { player, card });
 
                 events.extend(self.check_win_condition());
             }
@@ -915,26 +1939,31 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.dev_card_played_this_turn {
-                    return Err(GameError::InvalidPhase);
+                    return Err(GameError::DevCardAlreadyPlayed);
                 }
                 if !matches!(self.phase, GamePhase::PreRoll | GamePhase::MainPhase) {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::PreRoll, PhaseKind::MainPhase]));
                 }
 
-                let p = self.get_player_mut(player).unwrap();
+                let p = self.player_mut(player)?;
                 if !p.play_dev_card(DevelopmentCard::Knight) {
-                    return Err(GameError::NoSuchCard);
+                    return Err(GameError::NoSuchCard(DevelopmentCard::Knight));
                 }
 
                 self.dev_card_played_this_turn = true;
+                self.dev_cards_played.add(DevelopmentCard::Knight, 1);
 
                 events.push(GameEvent::KnightPlayed { player });
 
                 // Check largest army
                 events.extend(self.check_largest_army());
 
-                // Move robber
-                self.phase = GamePhase::RobberMoveRequired;
+                if self.options.knight_returns_robber_to_desert {
+                    events.extend(self.return_robber_to_desert(player));
+                } else {
+                    // Move robber
+                    self.phase = GamePhase::RobberMoveRequired;
+                }
             }
 
             GameAction::PlayRoadBuilding(_edge1, _edge2) => {
@@ -942,18 +1971,19 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
                 if self.dev_card_played_this_turn {
-                    return Err(GameError::InvalidPhase);
+                    return Err(GameError::DevCardAlreadyPlayed);
                 }
 
-                let p = self.get_player_mut(player).unwrap();
+                let p = self.player_mut(player)?;
                 if !p.play_dev_card(DevelopmentCard::RoadBuilding) {
-                    return Err(GameError::NoSuchCard);
+                    return Err(GameError::NoSuchCard(DevelopmentCard::RoadBuilding));
                 }
 
                 self.dev_card_played_this_turn = true;
+                self.dev_cards_played.add(DevelopmentCard::RoadBuilding, 1);
 
                 events.push(GameEvent::RoadBuildingPlayed { player });
 
@@ -966,22 +1996,20 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
                 if self.dev_card_played_this_turn {
-                    return Err(GameError::InvalidPhase);
+                    return Err(GameError::DevCardAlreadyPlayed);
                 }
 
-                {
-                    let p = self.get_player_mut(player).unwrap();
-                    if !p.play_dev_card(DevelopmentCard::YearOfPlenty) {
-                        return Err(GameError::NoSuchCard);
-                    }
+                if !self.player_mut(player)?.play_dev_card(DevelopmentCard::YearOfPlenty) {
+                    return Err(GameError::NoSuchCard(DevelopmentCard::YearOfPlenty));
                 }
 
                 self.dev_card_played_this_turn = true;
+                self.dev_cards_played.add(DevelopmentCard::YearOfPlenty, 1);
 
-                let p = self.get_player_mut(player).unwrap();
+                let p = self.player_mut(player)?;
                 p.resources.add(r1, 1);
                 p.resources.add(r2, 1);
 
@@ -996,20 +2024,18 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
                 if self.dev_card_played_this_turn {
-                    return Err(GameError::InvalidPhase);
+                    return Err(GameError::DevCardAlreadyPlayed);
                 }
 
-                {
-                    let p = self.get_player_mut(player).unwrap();
-                    if !p.play_dev_card(DevelopmentCard::Monopoly) {
-                        return Err(GameError::NoSuchCard);
-                    }
+                if !self.player_mut(player)?.play_dev_card(DevelopmentCard::Monopoly) {
+                    return Err(GameError::NoSuchCard(DevelopmentCard::Monopoly));
                 }
 
                 self.dev_card_played_this_turn = true;
+                self.dev_cards_played.add(DevelopmentCard::Monopoly, 1);
 
                 // Take all of that resource from other players
                 let mut total_stolen = 0;
@@ -1021,10 +2047,7 @@ impl GameState {
                     }
                 }
 
-                self.get_player_mut(player)
-                    .unwrap()
-                    .resources
-                    .add(resource, total_stolen);
+                self.player_mut(player)?.resources.add(resource, total_stolen);
 
                 events.push(GameEvent::MonopolyPlayed {
                     player,
@@ -1039,7 +2062,7 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
                 if !offer.is_valid() || offer.from != player {
@@ -1047,8 +2070,11 @@ impl GameState {
                 }
 
                 // Check player has the resources
-                if !self.get_player(player).unwrap().resources.can_afford(&offer.offering) {
-                    return Err(GameError::CannotAfford);
+                let hand = &self.player(player)?.resources;
+                if !hand.can_afford(&offer.offering) {
+                    return Err(GameError::CannotAfford {
+                        missing: hand.shortfall(&offer.offering),
+                    });
                 }
 
                 self.pending_trade = Some(TradeState {
@@ -1070,35 +2096,31 @@ impl GameState {
                 }
 
                 // Check responder has the resources
-                if !self
-                    .get_player(player)
-                    .unwrap()
-                    .resources
-                    .can_afford(&trade.offer.requesting)
-                {
-                    return Err(GameError::CannotAfford);
+                let hand = &self.player(player)?.resources;
+                if !hand.can_afford(&trade.offer.requesting) {
+                    return Err(GameError::CannotAfford {
+                        missing: hand.shortfall(&trade.offer.requesting),
+                    });
                 }
 
                 let offer = trade.offer.clone();
 
+                // The proposer may have spent the cards since offering them
+                let proposer = &self.player(offer.from)?.resources;
+                if !proposer.can_afford(&offer.offering) {
+                    return Err(GameError::InsufficientResources {
+                        missing: proposer.shortfall(&offer.offering),
+                    });
+                }
+
                 // Execute trade
-                self.get_player_mut(offer.from)
-                    .unwrap()
-                    .resources
-                    .subtract(&offer.offering);
-                self.get_player_mut(offer.from)
-                    .unwrap()
-                    .resources
-                    .add_hand(&offer.requesting);
-
-                self.get_player_mut(player)
-                    .unwrap()
-                    .resources
-                    .subtract(&offer.requesting);
-                self.get_player_mut(player)
-                    .unwrap()
-                    .resources
-                    .add_hand(&offer.offering);
+                let proposer = &mut self.player_mut(offer.from)?.resources;
+                proposer.subtract(&offer.offering)?;
+                proposer.add_hand(&offer.requesting);
+
+                let responder = &mut self.player_mut(player)?.resources;
+                responder.subtract(&offer.requesting)?;
+                responder.add_hand(&offer.offering);
 
                 self.pending_trade = None;
 
@@ -1158,19 +2180,24 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
                 let harbors = self.board.player_harbors(player);
                 let required = self.get_maritime_rate(player, give, &harbors);
 
                 if give_count != required {
-                    return Err(GameError::InvalidTrade);
+                    return Err(GameError::WrongTradeRate {
+                        resource: give,
+                        rate: required,
+                    });
                 }
 
-                let p = self.get_player_mut(player).unwrap();
+                let p = self.player_mut(player)?;
                 if p.resources.get(give) < give_count {
-                    return Err(GameError::CannotAfford);
+                    return Err(GameError::CannotAfford {
+                        missing: ResourceHand::single(give, give_count - p.resources.get(give)),
+                    });
                 }
 
                 p.resources.set(give, p.resources.get(give) - give_count);
@@ -1190,27 +2217,133 @@ impl GameState {
                     return Err(GameError::NotYourTurn);
                 }
                 if self.phase != GamePhase::MainPhase {
-                    return Err(GameError::InvalidPhase);
+                    return Err(self.wrong_phase(&[PhaseKind::MainPhase]));
                 }
 
-                // Cancel any pending trade
-                self.pending_trade = None;
-
-                // Move bought dev cards to hand
-                self.get_player_mut(player).unwrap().end_turn();
-
-                // Advance to next player
-                let next_player = (self.current_player + 1) % self.player_count() as PlayerId;
-                self.current_player = next_player;
-                self.turn_number += 1;
-                self.dice_roll = None;
-                self.dev_card_played_this_turn = false;
-                self.phase = GamePhase::PreRoll;
+                let next_player = self.advance_turn();
 
                 events.push(GameEvent::TurnEnded {
                     player,
                     next_player,
                 });
+                events.extend(self.check_turn_limit());
+            }
+
+            GameAction::ConfirmNextPlayer => {
+                if player != self.current_player {
+                    return Err(GameError::NotYourTurn);
+                }
+                let GamePhase::AwaitingNextPlayer { resume } = &self.phase else {
+                    return Err(self.wrong_phase(&[PhaseKind::AwaitingNextPlayer]));
+                };
+
+                self.phase = (**resume).clone();
+                events.push(GameEvent::NextPlayerConfirmed { player });
+            }
+
+            GameAction::ProposeEndGame => {
+                if !self.is_active(player) {
+                    return Err(GameError::NotYourTurn);
+                }
+
+                let resume = Box::new(self.phase.clone());
+                self.phase = GamePhase::EndGameVote {
+                    proposer: player,
+                    accepted: vec![player],
+                    resume,
+                };
+
+                events.push(GameEvent::EndGameProposed { player });
+            }
+
+            GameAction::VoteEndGame(accept) => {
+                let GamePhase::EndGameVote {
+                    accepted, resume, ..
+                } = &mut self.phase
+                else {
+                    return Err(self.wrong_phase(&[PhaseKind::EndGameVote]));
+                };
+                if accepted.contains(&player) {
+                    return Err(GameError::AlreadyVoted);
+                }
+
+                events.push(GameEvent::EndGameVoteCast { player, accept });
+
+                if !accept {
+                    self.phase = (**resume).clone();
+                    events.push(GameEvent::EndGameVoteFailed);
+                } else {
+                    accepted.push(player);
+                    let accepted = accepted.clone();
+                    if self.active_players().iter().all(|p| accepted.contains(p)) {
+                        self.finish_game(None);
+                        events.push(GameEvent::GameEndedByAgreement {
+                            ranking: self.get_ranking().unwrap_or_default().to_vec(),
+                        });
+                    }
+                }
+            }
+
+            GameAction::Concede => {
+                // A resignation cancels any running vote before it takes effect
+                if let GamePhase::EndGameVote { resume, .. } = &self.phase {
+                    self.phase = (**resume).clone();
+                    events.push(GameEvent::EndGameVoteFailed);
+                }
+                // The turn moves on anyway, so drop a handoff to the resigning player
+                if player == self.current_player {
+                    if let GamePhase::AwaitingNextPlayer { resume } = &self.phase {
+                        self.phase = (**resume).clone();
+                    }
+                }
+
+                self.player_mut(player)?.conceded = true;
+                events.push(GameEvent::PlayerConceded { player });
+                let after_seven = self.phase_after_seven();
+
+                // Offers to or from a resigned player can no longer complete
+                if self.pending_trade.as_ref().is_some_and(|t| {
+                    t.offer.from == player || t.offer.to == Some(player)
+                }) {
+                    self.pending_trade = None;
+                    events.push(GameEvent::TradeCancelled);
+                }
+
+                let remaining = self.active_players();
+                if remaining.iter().all(|&p| self.are_teammates(p, remaining[0])) {
+                    let winner = self.standings(None)[0];
+                    self.finish_game(Some(winner));
+                    events.extend(self.team_won_event(winner));
+                    events.push(GameEvent::GameWon {
+                        player: winner,
+                        victory_points: self.total_victory_points(winner),
+                    });
+                    return Ok(events);
+                }
+
+                if let GamePhase::Setup { .. } = self.phase {
+                    if player == self.current_player {
+                        // Any half-finished placement stays on the board
+                        self.setup_settlement = None;
+                        events.extend(self.advance_setup_phase());
+                    }
+                } else if player == self.current_player {
+                    // Resigning ends the turn, dropping whatever was pending
+                    let next_player = self.advance_turn();
+                    events.push(GameEvent::TurnEnded {
+                        player,
+                        next_player,
+                    });
+                    events.extend(self.check_turn_limit());
+                } else if let GamePhase::DiscardRequired {
+                    ref mut players_remaining,
+                } = self.phase
+                {
+                    players_remaining.retain(|&p| p != player);
+                    if players_remaining.is_empty() {
+                        self.phase = after_seven;
+                    }
+                }
             }
         }
 
@@ -1219,6 +2352,82 @@ impl GameState {
 
     // ==================== Helper Methods ====================
 
+    /// Apply the flag-fall policy to a player who ran out of time
+    fn flag_fall(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let mut events = vec![GameEvent::TimeExpired { player }];
+        let policy = self
+            .options
+            .time_control
+            .as_ref()
+            .map(|t| t.on_flag_fall)
+            .unwrap_or_default();
+
+        if policy == FlagFallPolicy::Forfeit {
+            if let Ok(e) = self.apply_action(player, GameAction::Concede) {
+                events.extend(e);
+            }
+            return events;
+        }
+
+        if policy == FlagFallPolicy::AutoDiscard
+            && !matches!(self.phase, GamePhase::DiscardRequired { .. })
+        {
+            let discard = crate::bot::bot_discard(self, player);
+            if !discard.is_empty()
+                && self
+                    .get_player_mut(player)
+                    .is_some_and(|p| p.resources.subtract(&discard).is_ok())
+            {
+                events.push(GameEvent::CardsDiscarded {
+                    player,
+                    count: discard.total(),
+                });
+            }
+        }
+
+        // Keep choosing defaults until the game moves on from this player.
+        // The cap guards against a phase that offers no way forward.
+        for _ in 0..16 {
+            if !self.waiting_on().contains(&player) {
+                break;
+            }
+            let Some(action) = self.auto_action(player) else {
+                break;
+            };
+            match self.apply_action(player, action) {
+                Ok(e) => events.extend(e),
+                Err(_) => break,
+            }
+        }
+
+        events
+    }
+
+    /// Hand the turn to the next active player and reset per-turn state
+    fn advance_turn(&mut self) -> PlayerId {
+        // Cancel any pending trade
+        self.pending_trade = None;
+
+        // Move bought dev cards to hand
+        let current = self.current_player;
+        if let Some(p) = self.get_player_mut(current) {
+            p.end_turn();
+        }
+
+        let next_player = self.next_active_player(current);
+        self.current_player = next_player;
+        self.turn_number += 1;
+        if let (Some(clock), Some(control)) = (&mut self.clock, &self.options.time_control) {
+            clock.add(next_player, control.increment_ms);
+        }
+        self.dice_roll = None;
+        self.dev_card_played_this_turn = false;
+        self.phase = GamePhase::PreRoll;
+        self.lock_screen();
+
+        next_player
+    }
+
     fn validate_setup_settlement(
         &self,
         player: PlayerId,
@@ -1235,11 +2444,11 @@ impl GameState {
                 ..
             }
         ) {
-            return Err(GameError::InvalidPhase);
+            return Err(self.wrong_phase(&[PhaseKind::SetupSettlement]));
         }
 
         if !self.board.valid_settlement_spots(player, true).contains(&vertex) {
-            return Err(GameError::InvalidLocation);
+            return Err(GameError::InvalidLocation(Location::Vertex(vertex)));
         }
 
         Ok(())
@@ -1257,17 +2466,19 @@ impl GameState {
                 ..
             }
         ) {
-            return Err(GameError::InvalidPhase);
+            return Err(self.wrong_phase(&[PhaseKind::SetupRoad]));
         }
 
         // Road must connect to just-placed settlement
-        let settlement = self.setup_settlement.ok_or(GameError::InvalidPhase)?;
+        let settlement = self
+            .setup_settlement
+            .ok_or_else(|| self.wrong_phase(&[PhaseKind::SetupSettlement]))?;
         if !settlement.touching_edges().contains(&edge) {
-            return Err(GameError::InvalidLocation);
+            return Err(GameError::InvalidLocation(Location::Edge(edge)));
         }
 
         if !self.board.is_land_edge(&edge) {
-            return Err(GameError::InvalidLocation);
+            return Err(GameError::InvalidLocation(Location::Edge(edge)));
         }
 
         Ok(())
@@ -1275,38 +2486,45 @@ impl GameState {
 
     fn advance_setup_phase(&mut self) -> Vec<GameEvent> {
         let events = Vec::new();
+        let previous_player = self.current_player;
 
         if let GamePhase::Setup { round, .. } = self.phase {
-            let player_count = self.player_count() as PlayerId;
-
             // Setup uses snake draft order: 0,1,2,3,3,2,1,0
-            let placements_done = self.count_setup_placements();
-
-            if placements_done >= player_count * 2 {
+            // Conceded players are skipped and don't need to finish placing
+            let all_placed = |count: u32| {
+                self.players
+                    .iter()
+                    .filter(|p| !p.conceded)
+                    .all(|p| self.setup_settlements_placed(p.id) >= count)
+            };
+
+            if all_placed(2) {
                 // Setup complete, start normal play
+                if !self.is_active(self.current_player) {
+                    self.current_player = self.next_active_player(self.current_player);
+                }
                 self.phase = GamePhase::PreRoll;
                 self.turn_number = 1;
-            } else if round == 1 && placements_done >= player_count {
+            } else if round == 1 && all_placed(1) {
                 // End of round 1, start round 2 (reverse order)
                 self.phase = GamePhase::Setup {
                     round: 2,
                     placing: SetupPlacing::Settlement,
                 };
                 // Stay on same player (snake draft)
+                if !self.is_active(self.current_player) {
+                    self.current_player = self.previous_active_player(self.current_player);
+                }
             } else if round == 1 {
                 // Continue round 1 forward
-                self.current_player = (self.current_player + 1) % player_count;
+                self.current_player = self.next_active_player(self.current_player);
                 self.phase = GamePhase::Setup {
                     round: 1,
                     placing: SetupPlacing::Settlement,
                 };
             } else {
                 // Round 2 goes backward
-                self.current_player = if self.current_player == 0 {
-                    player_count - 1
-                } else {
-                    self.current_player - 1
-                };
+                self.current_player = self.previous_active_player(self.current_player);
                 self.phase = GamePhase::Setup {
                     round: 2,
                     placing: SetupPlacing::Settlement,
@@ -1314,15 +2532,122 @@ impl GameState {
             }
         }
 
+        if self.current_player != previous_player {
+            self.lock_screen();
+        }
+
         events
     }
 
-    fn count_setup_placements(&self) -> PlayerId {
-        // Count total settlements placed
-        self.players
+    /// In hot-seat games, hold the new phase until the next player confirms
+    fn lock_screen(&mut self) {
+        if self.options.hot_seat {
+            let resume = Box::new(self.phase.clone());
+            self.phase = GamePhase::AwaitingNextPlayer { resume };
+        }
+    }
+
+    /// Roll two dice, or draw the next roll from the balanced deck,
+    /// shuffling a fresh deck first if it has run out
+    fn roll_dice(&mut self, events: &mut Vec<GameEvent>) -> (u8, u8) {
+        if self.options.dice == DiceMode::Random {
+            return (self.rng.gen_range(1..=6), self.rng.gen_range(1..=6));
+        }
+        if self.dice_deck.is_empty() {
+            self.dice_deck = dice::balanced_deck();
+            self.dice_deck.shuffle(&mut self.rng);
+            events.push(GameEvent::DiceDeckReshuffled {
+                cards: self.dice_deck.len() as u32,
+            });
+        }
+        self.dice_deck.pop().unwrap_or((1, 1))
+    }
+
+    /// Roll the event die, if playing with one. A ship sails the
+    /// barbarians on; a city gate lets players far enough along its
+    /// improvement track draw a progress card, given the `red` die.
+    fn roll_event_die(&mut self, player: PlayerId, red: u8) -> Vec<GameEvent> {
+        let Some(die) = &self.options.event_die else {
+            return Vec::new();
+        };
+        if die.faces.is_empty() {
+            return Vec::new();
+        }
+        let face = die.faces[self.rng.gen_range(0..die.faces.len())];
+        self.event_roll = Some(face);
+        let mut events = vec![GameEvent::EventDieRolled { player, face }];
+
+        if face == EventFace::Ship {
+            if let Some(track) = self.barbarians.as_mut() {
+                let attack = track.advance();
+                events.push(GameEvent::BarbariansAdvanced {
+                    position: track.position,
+                    length: track.length,
+                });
+                if attack {
+                    let strength = self
+                        .board
+                        .vertices_by_id()
+                        .iter()
+                        .filter(|v| matches!(self.board.get_vertex(v), VertexBuilding::City(_)))
+                        .count() as u32;
+                    events.push(GameEvent::BarbarianAttack { strength });
+                }
+            }
+        }
+
+        if let Some(track) = Improvement::for_face(face) {
+            // Clockwise from the player who rolled
+            let count = self.players.len() as PlayerId;
+            let order: Vec<PlayerId> = (0..count)
+                .map(|i| (player + i) % count)
+                .filter(|&p| self.is_active(p))
+                .collect();
+            if let Some(ck) = self.cities_knights.as_mut() {
+                for p in order {
+                    let draws = ck
+                        .player(p)
+                        .is_some_and(|h| h.improvements.draws_on(track, red));
+                    if let Some(card) = draws.then(|| ck.draw(p, track)).flatten() {
+                        events.push(GameEvent::ProgressCardDrawn {
+                            player: p,
+                            track,
+                            card: Some(card),
+                        });
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Whether `player` has a city on the board
+    fn has_city(&self, player: PlayerId) -> bool {
+        self.board
+            .vertices_by_id()
             .iter()
-            .map(|p| (5 - p.settlements_remaining) as PlayerId)
-            .sum()
+            .any(|v| self.board.get_vertex(v) == VertexBuilding::City(player))
+    }
+
+    /// Send the robber back to the desert after a knight. Nothing moves
+    /// while it is off the board or already on a desert.
+    fn return_robber_to_desert(&mut self, player: PlayerId) -> Option<GameEvent> {
+        let from = self.board.robber_location()?;
+        if self.board.get_tile(&from)?.tile_type == TileType::Desert {
+            return None;
+        }
+        let desert = self
+            .board
+            .land_tiles()
+            .filter(|tile| tile.tile_type == TileType::Desert)
+            .map(|tile| tile.coord)
+            .min_by_key(|coord| (coord.r, coord.q))?;
+        self.board.move_robber(desert);
+        Some(GameEvent::RobberMoved {
+            player,
+            from: Some(from),
+            to: desert,
+        })
     }
 
     fn steal_from_player(
@@ -1330,15 +2655,15 @@ impl GameState {
         thief: PlayerId,
         victim: PlayerId,
     ) -> Result<Vec<GameEvent>, GameError> {
-        let mut rng = rand::thread_rng();
         let stolen = self
-            .get_player_mut(victim)
-            .unwrap()
+            .players
+            .get_mut(victim as usize)
+            .ok_or(GameError::InvalidPlayer(victim))?
             .resources
-            .steal_random(&mut rng);
+            .steal_random(&mut self.rng);
 
         if let Some(resource) = stolen {
-            self.get_player_mut(thief).unwrap().resources.add(resource, 1);
+            self.player_mut(thief)?.resources.add(resource, 1);
         }
 
         Ok(vec![GameEvent::ResourceStolen {
@@ -1370,7 +2695,7 @@ impl GameState {
         let current_holder = self.players.iter().find(|p| p.has_longest_road).map(|p| p.id);
 
         // Determine new holder
-        let new_holder = if longest_players.len() == 1 {
+        let mut new_holder = if longest_players.len() == 1 {
             Some(longest_players[0])
         } else if longest_players.contains(&current_holder.unwrap_or(255)) {
             // Ties keep current holder
@@ -1382,6 +2707,19 @@ impl GameState {
             None
         };
 
+        // The award only leaves the holder's team when another team strictly
+        // exceeds it; a tied teammate takes over instead
+        if let Some(holder) = current_holder {
+            if new_holder.is_none_or(|h| !self.are_teammates(h, holder)) {
+                if let Some(&mate) = longest_players
+                    .iter()
+                    .find(|&&p| self.are_teammates(p, holder))
+                {
+                    new_holder = Some(mate);
+                }
+            }
+        }
+
         if new_holder != current_holder {
             // Update player flags
             for player in &mut self.players {
@@ -1398,35 +2736,47 @@ impl GameState {
         events
     }
 
+    /// Largest Army goes to the first player with 3 knights played, and
+    /// moves only to a player with strictly more than its holder: a tie
+    /// never moves it, and as played knights never go down it is never
+    /// taken back. In team play a holder's teammate is preferred to a tied
+    /// opponent, keeping the award on that side.
     fn check_largest_army(&mut self) -> Vec<GameEvent> {
-        let mut events = Vec::new();
-
-        let mut most_knights = 0;
-        let mut leader: Option<PlayerId> = None;
+        let current_holder = self.players.iter().find(|p| p.has_largest_army).map(|p| p.id);
+        let to_beat = current_holder
+            .and_then(|holder| self.get_player(holder))
+            .map_or(MIN_LARGEST_ARMY, |holder| {
+                (holder.played_knights + 1).max(MIN_LARGEST_ARMY)
+            });
 
-        for player in &self.players {
-            if player.played_knights >= MIN_LARGEST_ARMY && player.played_knights > most_knights {
-                most_knights = player.played_knights;
-                leader = Some(player.id);
-            }
+        let most_knights = self.players.iter().map(|p| p.played_knights).max().unwrap_or(0);
+        if most_knights < to_beat {
+            return Vec::new();
         }
+        let leaders: Vec<PlayerId> = self
+            .players
+            .iter()
+            .filter(|p| p.played_knights == most_knights)
+            .map(|p| p.id)
+            .collect();
 
-        let current_holder = self.players.iter().find(|p| p.has_largest_army).map(|p| p.id);
-
-        // Only change if new leader has strictly more
-        if leader != current_holder && leader.is_some() {
-            for player in &mut self.players {
-                player.has_largest_army = Some(player.id) == leader;
-            }
+        let teammate = current_holder
+            .and_then(|holder| leaders.iter().copied().find(|&p| self.are_teammates(p, holder)));
+        let new_holder = match (teammate, leaders.as_slice()) {
+            (Some(mate), _) => mate,
+            (None, &[leader]) => leader,
+            // Tied leaders: nobody has more than everyone else
+            (None, _) => return Vec::new(),
+        };
 
-            events.push(GameEvent::LargestArmyChanged {
-                previous: current_holder,
-                current: leader,
-                knights: most_knights,
-            });
+        for player in &mut self.players {
+            player.has_largest_army = player.id == new_holder;
         }
-
-        events
+        vec![GameEvent::LargestArmyChanged {
+            previous: current_holder,
+            current: Some(new_holder),
+            knights: most_knights,
+        }]
     }
 
     fn check_win_condition(&mut self) -> Vec<GameEvent> {
@@ -1434,7 +2784,8 @@ impl GameState {
 
         if let Some(winner) = self.check_winner() {
             let vp = self.total_victory_points(winner);
-            self.phase = GamePhase::Finished { winner };
+            self.finish_game(Some(winner));
+            events.extend(self.team_won_event(winner));
             events.push(GameEvent::GameWon {
                 player: winner,
                 victory_points: vp,
@@ -1443,11 +2794,86 @@ impl GameState {
 
         events
     }
+
+    /// `TeamWon` for the winner's team, when playing with teams
+    fn team_won_event(&self, winner: PlayerId) -> Option<GameEvent> {
+        let team = self.team_of(winner)?;
+        let players = (0..self.players.len() as PlayerId)
+            .filter(|&p| self.team_of(p) == Some(team))
+            .collect();
+        Some(GameEvent::TeamWon { team, players })
+    }
+
+    /// Finish the game once the turn limit from the options has been played
+    fn check_turn_limit(&mut self) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+
+        let Some(max_turns) = self.options.max_turns else {
+            return events;
+        };
+        if self.turn_number <= max_turns {
+            return events;
+        }
+
+        let winner = self.standings(None)[0];
+        let vp = self.total_victory_points(winner);
+        self.finish_game(Some(winner));
+        events.push(GameEvent::TurnLimitReached { turns: max_turns });
+        events.extend(self.team_won_event(winner));
+        events.push(GameEvent::GameWon {
+            player: winner,
+            victory_points: vp,
+        });
+
+        events
+    }
+}
+
+/// A summary for logs and failing tests: the turn, each player's standing
+/// and hand, and the board
+impl std::fmt::Display for GameState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Turn {}, {:?}", self.turn_number, self.phase)?;
+        if let Some((a, b)) = self.dice_roll {
+            write!(f, ", rolled {}+{}", a, b)?;
+        }
+        writeln!(f)?;
+        for player in &self.players {
+            let marker = if player.id == self.current_player { '>' } else { ' ' };
+            write!(
+                f,
+                "{} {} {}: {} VP,",
+                marker,
+                player.id,
+                player.name,
+                self.total_victory_points(player.id)
+            )?;
+            for resource in Resource::ALL {
+                write!(f, " {:?} {}", resource, player.resources.get(resource))?;
+            }
+            let dev_cards = player.dev_cards.len() + player.dev_cards_bought_this_turn.len();
+            write!(f, ", {} dev cards", dev_cards)?;
+            if player.has_longest_road {
+                write!(f, ", longest road")?;
+            }
+            if player.has_largest_army {
+                write!(f, ", largest army")?;
+            }
+            if player.conceded {
+                write!(f, ", resigned")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{}", self.board.render_ascii())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::barbarians::EventDie;
+    use crate::cities_knights::Commodity;
+    use std::time::Duration;
 
     #[test]
     fn test_new_game_starts_in_setup() {
@@ -1473,12 +2899,117 @@ mod tests {
         assert!(!actions.is_empty());
     }
 
+    /// Play out setup, returning each player's settlements in the order
+    /// placed and the resources they were given
+    fn setup_grants(game: &mut GameState) -> Vec<(Vec<VertexCoord>, ResourceHand)> {
+        let mut placed = vec![(Vec::new(), ResourceHand::new()); game.players.len()];
+        while matches!(game.phase, GamePhase::Setup { .. }) {
+            let player = game.current_player;
+            let action = game.valid_actions(player).into_iter().next().unwrap();
+            for event in game.apply_action(player, action).unwrap() {
+                match event {
+                    GameEvent::SettlementBuilt { player, location } => {
+                        placed[player as usize].0.push(location)
+                    }
+                    GameEvent::ResourcesDistributed { distributions } => {
+                        for (player, resource, amount) in distributions {
+                            placed[player as usize].1.add(resource, amount);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        placed
+    }
+
+    #[test]
+    fn test_setup_pays_out_for_exactly_the_second_settlement() {
+        // Only 2-4 players for now: five and six need the extension board,
+        // which `GameState::new` doesn't build yet
+        for count in 2..=4u8 {
+            let names = (0..count).map(|i| format!("P{}", i)).collect();
+            let mut game = GameState::new(count, names);
+            // A settlement the scenario started player 0 with isn't a
+            // setup placement
+            let head_start = game.board.valid_settlement_spots(0, true)[0];
+            game.board.place_settlement(head_start, 0);
+            game.players[0].settlements_remaining -= 1;
+
+            for (player, (settlements, given)) in setup_grants(&mut game).into_iter().enumerate() {
+                assert_eq!(settlements.len(), 2, "{} players, seat {}", count, player);
+                let mut expected = ResourceHand::new();
+                for tile in game.board.tiles_at_vertex(&settlements[1]) {
+                    if let Some(resource) = tile.resource() {
+                        expected.add(resource, 1);
+                    }
+                }
+                assert_eq!(given, expected, "{} players, seat {}", count, player);
+                assert_eq!(game.setup_settlements_placed(player as PlayerId), 2);
+            }
+        }
+    }
+
     #[test]
     fn test_dev_card_deck_size() {
         let game = GameState::new(2, vec!["A".into(), "B".into()]);
         assert_eq!(game.dev_card_deck.len(), 25);
     }
 
+    #[test]
+    fn test_buying_a_card_tells_only_the_buyer_what_it_is() {
+        let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].resources = crate::player::costs::development_card();
+        let card = *game.dev_card_deck.last().unwrap();
+
+        let events = game.apply_action(player, GameAction::BuyDevelopmentCard).unwrap();
+        let drawn = GameEvent::DevelopmentCardDrawn { player, card };
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::DevelopmentCardPurchased {
+                    player,
+                    deck_remaining: 24,
+                },
+                drawn.clone(),
+            ]
+        );
+        assert_eq!(drawn.visible_to(Some(player)), Some(drawn.clone()));
+        assert_eq!(drawn.visible_to(Some(1 - player)), None);
+        assert_eq!(drawn.visible_to(None), None);
+    }
+
+    #[test]
+    fn test_played_cards_are_counted_but_the_deck_stays_hidden() {
+        let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].dev_cards =
+            vec![DevelopmentCard::Monopoly, DevelopmentCard::Knight];
+
+        game.apply_action(player, GameAction::PlayMonopoly(Resource::Ore)).unwrap();
+        let played = DevCardCounts {
+            monopoly: 1,
+            ..Default::default()
+        };
+        assert_eq!(game.dev_cards_revealed(), played);
+        assert_eq!(game.dev_deck_remaining(), 25);
+
+        // The knight still in hand is only unseen by the other player
+        let unseen = game.unseen_dev_cards(player);
+        assert_eq!((unseen.knight, unseen.monopoly, unseen.total()), (13, 1, 23));
+        assert_eq!(game.unseen_dev_cards(1 - player).total(), 24);
+
+        let view = serde_json::to_value(game.view_for(Some(1 - player))).unwrap();
+        assert_eq!(view["dev_cards_revealed"]["monopoly"], 1);
+        assert_eq!(view["dev_card_deck_size"], 25);
+        assert!(view.get("dev_card_deck").is_none());
+    }
+
     #[test]
     fn test_victory_points_calculation() {
         let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
@@ -1495,6 +3026,719 @@ mod tests {
         assert_eq!(game.total_victory_points(0), 4);
     }
 
+    fn complete_setup(game: &mut GameState) {
+        while matches!(game.phase, GamePhase::Setup { .. }) {
+            let player = game.current_player;
+            let action = game.valid_actions(player).into_iter().next().unwrap();
+            game.apply_action(player, action).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_seeded_games_replay_identically() {
+        let play = |seed: u64| {
+            let names = vec!["A".into(), "B".into(), "C".into()];
+            let mut game = GameState::new_with_seed(3, names, GameOptions::default(), seed);
+            complete_setup(&mut game);
+            let mut rolls = Vec::new();
+            for _ in 0..10 {
+                let player = game.current_player;
+                game.apply_action(player, GameAction::RollDice).unwrap();
+                rolls.push(game.dice_roll.unwrap());
+                let action = game.auto_action(player).unwrap();
+                game.apply_action(player, action).unwrap();
+                while let Some(&p) = game.waiting_on().first() {
+                    if game.phase == GamePhase::PreRoll {
+                        break;
+                    }
+                    let action = game.auto_action(p).unwrap();
+                    game.apply_action(p, action).unwrap();
+                }
+            }
+            (game.seed(), game.dev_card_deck.clone(), rolls)
+        };
+
+        assert_eq!(play(99), play(99));
+        assert_ne!(play(99).2, play(100).2);
+    }
+
+    #[test]
+    fn test_hints_follow_the_phase() {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let mut game = GameState::new_with_seed(3, names, GameOptions::default(), 7);
+        let player = game.current_player;
+        let other = (player + 1) % 3;
+
+        let hint = game.hint(player, BotDifficulty::Hard);
+        assert!(game.valid_actions(player).contains(hint.action.as_ref().unwrap()));
+        assert_eq!(hint.discard, None);
+        assert_eq!(hint.accept_trade, None);
+        assert_eq!(game.hint(player, BotDifficulty::Hard), hint);
+        assert_eq!(game.suggest_action(other, BotDifficulty::Hard), None);
+
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let other = (player + 1) % 3;
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].resources = ResourceHand::with_amounts(2, 0, 0, 0, 0);
+        game.players[other as usize].resources = ResourceHand::with_amounts(0, 1, 0, 0, 0);
+        let offer = TradeOffer::new(
+            player,
+            Some(other),
+            ResourceHand::with_amounts(2, 0, 0, 0, 0),
+            ResourceHand::with_amounts(0, 1, 0, 0, 0),
+        );
+        game.apply_action(player, GameAction::ProposeTrade(offer)).unwrap();
+        // Two cards for one is a good deal
+        assert_eq!(game.suggest_trade_response(other, BotDifficulty::Medium), Some(true));
+        assert_eq!(game.suggest_trade_response(player, BotDifficulty::Medium), None);
+
+        game.pending_trade = None;
+        game.players[other as usize].resources = ResourceHand::with_amounts(3, 3, 3, 0, 0);
+        game.phase = GamePhase::DiscardRequired {
+            players_remaining: vec![other],
+        };
+        let discard = game.suggest_discard(other).unwrap();
+        assert_eq!(discard.total(), 4);
+        assert_eq!(game.suggest_discard(player), None);
+    }
+
+    #[test]
+    fn test_concede_with_two_players_ends_game() {
+        let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
+        complete_setup(&mut game);
+
+        let loser = game.current_player;
+        let events = game.apply_action(loser, GameAction::Concede).unwrap();
+
+        assert!(events.contains(&GameEvent::PlayerConceded { player: loser }));
+        assert_eq!(game.get_winner(), Some(1 - loser));
+    }
+
+    #[test]
+    fn test_conceded_player_is_skipped() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        complete_setup(&mut game);
+
+        let current = game.current_player;
+        let quitter = (current + 1) % 3;
+        game.apply_action(quitter, GameAction::Concede).unwrap();
+
+        assert!(!game.is_finished());
+        assert!(game.valid_actions(quitter).is_empty());
+        assert!(matches!(
+            game.apply_action(quitter, GameAction::RollDice),
+            Err(GameError::PlayerConceded)
+        ));
+
+        game.phase = GamePhase::MainPhase;
+        game.apply_action(current, GameAction::EndTurn).unwrap();
+        assert_eq!(game.current_player, (current + 2) % 3);
+    }
+
+    #[test]
+    fn test_concede_on_own_turn_passes_turn() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        complete_setup(&mut game);
+
+        let current = game.current_player;
+        let events = game.apply_action(current, GameAction::Concede).unwrap();
+
+        let next_player = (current + 1) % 3;
+        assert!(events.contains(&GameEvent::TurnEnded {
+            player: current,
+            next_player,
+        }));
+        assert_eq!(game.current_player, next_player);
+        assert_eq!(game.phase, GamePhase::PreRoll);
+    }
+
+    #[test]
+    fn test_concede_during_setup() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+
+        // First player places settlement and road, the second one resigns
+        for _ in 0..2 {
+            let player = game.current_player;
+            let action = game.valid_actions(player).into_iter().next().unwrap();
+            game.apply_action(player, action).unwrap();
+        }
+        let quitter = game.current_player;
+        game.apply_action(quitter, GameAction::Concede).unwrap();
+
+        complete_setup(&mut game);
+
+        assert_eq!(game.phase, GamePhase::PreRoll);
+        assert!(game.is_active(game.current_player));
+        for player in &game.players {
+            let expected = if player.id == quitter { 5 } else { 3 };
+            assert_eq!(player.settlements_remaining, expected);
+        }
+    }
+
+    #[test]
+    fn test_end_game_vote_ranks_by_victory_points() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        complete_setup(&mut game);
+        game.players[2].has_longest_road = true;
+
+        game.apply_action(0, GameAction::ProposeEndGame).unwrap();
+        assert!(matches!(
+            game.apply_action(0, GameAction::VoteEndGame(true)),
+            Err(GameError::AlreadyVoted)
+        ));
+        assert!(matches!(
+            game.apply_action(game.current_player, GameAction::RollDice),
+            Err(GameError::Paused(PhaseKind::EndGameVote))
+        ));
+
+        game.apply_action(1, GameAction::VoteEndGame(true)).unwrap();
+        let events = game.apply_action(2, GameAction::VoteEndGame(true)).unwrap();
+
+        assert!(game.is_finished());
+        assert_eq!(game.get_winner(), None);
+        assert_eq!(game.get_ranking().unwrap()[0], 2);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, GameEvent::GameEndedByAgreement { .. })));
+    }
+
+    #[test]
+    fn test_turn_limit_awards_highest_vp() {
+        let mut game = GameState::new_with_options(
+            2,
+            vec!["A".into(), "B".into()],
+            GameOptions::with_max_turns(2),
+        );
+        complete_setup(&mut game);
+        let leader = 1 - game.current_player;
+        game.players[leader as usize].played_knights = 3;
+        game.players[leader as usize].has_largest_army = true;
+
+        for _ in 0..2 {
+            assert!(!game.is_finished());
+            game.phase = GamePhase::MainPhase;
+            let events = game.apply_action(game.current_player, GameAction::EndTurn).unwrap();
+            if game.is_finished() {
+                assert!(events.contains(&GameEvent::TurnLimitReached { turns: 2 }));
+            }
+        }
+
+        assert_eq!(game.get_winner(), Some(leader));
+    }
+
+    #[test]
+    fn test_turn_limit_tie_break_on_knights() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        complete_setup(&mut game);
+        game.players[1].played_knights = 2;
+
+        assert_eq!(game.standings(None)[0], 1);
+        assert_eq!(game.standings(Some(2))[0], 2);
+    }
+
+    fn timed_game(policy: FlagFallPolicy) -> GameState {
+        let options = GameOptions {
+            time_control: Some(crate::clock::TimeControl {
+                initial_ms: 1000,
+                increment_ms: 500,
+                on_flag_fall: policy,
+            }),
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        complete_setup(&mut game);
+        game
+    }
+
+    #[test]
+    fn test_flag_fall_auto_pass_ends_turn() {
+        let mut game = timed_game(FlagFallPolicy::AutoPass);
+        let slow = game.current_player;
+        let other = 1 - slow;
+
+        assert!(game.tick(Duration::from_millis(400)).is_empty());
+        let events = game.tick(Duration::from_millis(600));
+
+        assert!(events.contains(&GameEvent::TimeExpired { player: slow }));
+        assert_ne!(game.current_player, slow);
+        // Only the waiting player is charged; the next player gets the increment
+        let clock = game.clock.as_ref().unwrap();
+        assert_eq!(clock.remaining(slow), 0);
+        assert_eq!(clock.remaining(other), 1500);
+    }
+
+    #[test]
+    fn test_flag_fall_forfeit_concedes() {
+        let mut game = timed_game(FlagFallPolicy::Forfeit);
+        let slow = game.current_player;
+
+        let events = game.tick(Duration::from_secs(2));
+
+        assert!(events.contains(&GameEvent::PlayerConceded { player: slow }));
+        assert_eq!(game.get_winner(), Some(1 - slow));
+    }
+
+    #[test]
+    fn test_hot_seat_locks_between_players() {
+        let options = GameOptions {
+            hot_seat: true,
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        let first = game.current_player;
+        let settlement = game.valid_actions(first)[0].clone();
+        game.apply_action(first, settlement).unwrap();
+        let road = game.valid_actions(first)[0].clone();
+        game.apply_action(first, road).unwrap();
+
+        let next = game.current_player;
+        assert_ne!(next, first);
+        assert!(matches!(game.phase, GamePhase::AwaitingNextPlayer { .. }));
+        assert_eq!(game.valid_actions(next), vec![GameAction::ConfirmNextPlayer]);
+        assert!(game.hot_seat_view().players.iter().all(|p| p.resources.is_empty()));
+
+        game.apply_action(next, GameAction::ConfirmNextPlayer).unwrap();
+        assert!(matches!(game.phase, GamePhase::Setup { round: 1, .. }));
+    }
+
+    #[test]
+    fn test_view_for_hides_other_hands() {
+        let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
+        game.players[0].resources = ResourceHand::with_amounts(1, 1, 0, 0, 0);
+        game.players[1].resources = ResourceHand::with_amounts(0, 0, 2, 1, 0);
+        game.players[1].dev_cards.push(DevelopmentCard::VictoryPoint);
+
+        let view = game.view_for(Some(0));
+
+        assert_eq!(view.players[0].resources.total(), 2);
+        assert!(view.players[1].resources.is_empty());
+        assert!(view.players[1].dev_cards.is_empty());
+        assert_eq!(view.hand_sizes, vec![2, 3]);
+        assert_eq!(view.dev_card_counts, vec![0, 1]);
+        assert_eq!(view.victory_points[1], game.total_victory_points(1) - 1);
+    }
+
+    fn team_game(team_victory: TeamVictory) -> GameState {
+        let options = GameOptions {
+            teams: vec![0, 1, 0, 1],
+            team_victory,
+            ..GameOptions::default()
+        };
+        let names = vec!["A".into(), "B".into(), "C".into(), "D".into()];
+        GameState::new_with_options(4, names, options)
+    }
+
+    #[test]
+    fn test_teammates_are_not_robbed() {
+        let mut game = team_game(TeamVictory::Individual);
+        let thief = game.current_player;
+        let mate = (thief + 2) % 4;
+        assert!(game.are_teammates(thief, mate));
+
+        let target = game
+            .board
+            .land_tiles()
+            .map(|t| t.coord)
+            .find(|&h| Some(h) != game.board.robber_location())
+            .unwrap();
+        game.board.place_settlement(target.vertices()[0], mate);
+        game.players[mate as usize].settlements_remaining -= 1;
+        game.players[mate as usize].resources = ResourceHand::with_amounts(1, 1, 1, 1, 1);
+        game.phase = GamePhase::RobberMoveRequired;
+
+        let events = game.apply_action(thief, GameAction::MoveRobber(target)).unwrap();
+
+        assert_eq!(game.phase, GamePhase::MainPhase);
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, GameEvent::ResourceStolen { .. })));
+    }
+
+    #[test]
+    fn test_combined_team_victory() {
+        let mut game = team_game(TeamVictory::Combined { points: 4 });
+        let player = game.current_player;
+        let mate = (player + 2) % 4;
+        game.players[player as usize].has_longest_road = true;
+        assert!(game.check_win_condition().is_empty());
+
+        game.players[mate as usize].has_largest_army = true;
+        let events = game.check_win_condition();
+
+        assert_eq!(game.get_winner(), Some(player));
+        assert_eq!(game.get_ranking().unwrap()[1], mate);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, GameEvent::TeamWon { players, .. } if players.contains(&mate))));
+    }
+
+    #[test]
+    fn test_largest_army_tie_stays_with_team() {
+        let mut game = team_game(TeamVictory::Individual);
+        game.players[0].played_knights = 3;
+        game.players[0].has_largest_army = true;
+        game.players[1].played_knights = 4;
+        game.players[2].played_knights = 4;
+
+        game.check_largest_army();
+
+        assert!(game.players[2].has_largest_army);
+        assert!(!game.players[1].has_largest_army);
+    }
+
+    #[test]
+    fn test_largest_army_moves_only_to_strictly_more_knights() {
+        // (knights played by each player, holder before, holder after)
+        let cases: [([u32; 3], Option<PlayerId>, Option<PlayerId>); 11] = [
+            ([2, 2, 0], None, None),
+            ([3, 2, 0], None, Some(0)),
+            // Tied at the start, nobody is ahead of everyone else
+            ([3, 3, 0], None, None),
+            ([4, 4, 5], None, Some(2)),
+            // Never taken back, however far behind the others fall
+            ([3, 0, 0], Some(0), Some(0)),
+            ([3, 3, 0], Some(0), Some(0)),
+            ([3, 4, 0], Some(0), Some(1)),
+            // Surpassed, then tied again by the old holder: stays put
+            ([4, 4, 0], Some(1), Some(1)),
+            ([5, 4, 0], Some(1), Some(0)),
+            // Two players tied ahead of the holder: neither is ahead
+            ([3, 4, 4], Some(0), Some(0)),
+            ([3, 4, 5], Some(0), Some(2)),
+        ];
+        for (knights, before, after) in cases {
+            let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+            for (player, &played) in game.players.iter_mut().zip(&knights) {
+                player.played_knights = played;
+                player.has_largest_army = Some(player.id) == before;
+            }
+
+            let events = game.check_largest_army();
+
+            let holders: Vec<PlayerId> = game
+                .players
+                .iter()
+                .filter(|p| p.has_largest_army)
+                .map(|p| p.id)
+                .collect();
+            assert_eq!(holders, after.into_iter().collect::<Vec<_>>(), "{:?}", knights);
+            let changed = GameEvent::LargestArmyChanged {
+                previous: before,
+                current: after,
+                knights: knights.into_iter().max().unwrap(),
+            };
+            let expected = if before == after { vec![] } else { vec![changed] };
+            assert_eq!(events, expected, "{:?} held by {:?}", knights, before);
+        }
+    }
+
+    #[test]
+    fn test_pirate_moves_on_seven() {
+        let options = GameOptions {
+            pirate: true,
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        let player = game.current_player;
+        let start = game.board.pirate_location().unwrap();
+        game.phase = GamePhase::RobberMoveRequired;
+
+        let target = game
+            .valid_actions(player)
+            .into_iter()
+            .find_map(|a| match a {
+                GameAction::MovePirate(hex) => Some(hex),
+                _ => None,
+            })
+            .unwrap();
+        assert_ne!(target, start);
+        let land = game.board.robber_location().unwrap();
+        assert!(matches!(
+            game.apply_action(player, GameAction::MovePirate(land)),
+            Err(GameError::InvalidLocation(Location::Hex(_)))
+        ));
+
+        game.apply_action(player, GameAction::MovePirate(target)).unwrap();
+
+        assert_eq!(game.board.pirate_location(), Some(target));
+        assert_eq!(game.phase, GamePhase::MainPhase);
+        assert!(target.edges().iter().all(|e| game.board.is_edge_blocked_by_pirate(e)));
+    }
+
+    #[test]
+    fn test_cities_and_knights_commodities_improvements_and_progress_cards() {
+        let options = GameOptions {
+            expansion: Expansion::CitiesAndKnights,
+            event_die: Some(EventDie {
+                faces: vec![EventFace::Trade],
+                track_length: 7,
+            }),
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let forest = game
+            .board
+            .land_tiles()
+            .find(|t| t.resource() == Some(Resource::Lumber) && !t.has_robber)
+            .unwrap();
+        let number = forest.dice_number.unwrap();
+        let city = forest
+            .coord
+            .vertices()
+            .into_iter()
+            .find(|v| game.board.get_vertex(v) == VertexBuilding::Empty)
+            .unwrap();
+        game.board.upgrade_to_city(city, player);
+        game.players[player as usize].cities_remaining -= 1;
+
+        // Roll until the forest comes up; its city makes paper. The city
+        // may touch another forest, so only count this roll's paper.
+        let paper = |game: &GameState| {
+            game.cities_knights.as_ref().unwrap().players[player as usize].commodities.paper
+        };
+        let (events, paper_made) = loop {
+            game.phase = GamePhase::PreRoll;
+            let before = paper(&game);
+            let events = game.apply_action(player, GameAction::RollDice).unwrap();
+            if game.dice_roll.is_some_and(|(a, b)| a + b == number) {
+                break (events, paper(&game) - before);
+            }
+        };
+        assert!(paper_made >= 1);
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GameEvent::CommoditiesProduced { distributions }
+                if distributions.contains(&(player, Commodity::Paper, paper_made))
+        )));
+
+        game.phase = GamePhase::MainPhase;
+        let holdings = &mut game.cities_knights.as_mut().unwrap().players[player as usize];
+        holdings.commodities = CommodityHand {
+            cloth: 1,
+            ..CommodityHand::default()
+        };
+        let improve = GameAction::BuildCityImprovement(Improvement::Trade);
+        assert!(game.valid_actions(player).contains(&improve));
+        let events = game.apply_action(player, improve.clone()).unwrap();
+        assert_eq!(
+            events,
+            vec![GameEvent::CityImproved {
+                player,
+                track: Improvement::Trade,
+                level: 1,
+            }]
+        );
+        assert_eq!(
+            game.apply_action(player, improve),
+            Err(GameError::MissingCommodities {
+                missing: CommodityHand {
+                    cloth: 2,
+                    ..CommodityHand::default()
+                }
+            })
+        );
+
+        // At the top of the track every trade gate draws a card, seen only
+        // by the player who drew it
+        let holdings = &mut game.cities_knights.as_mut().unwrap().players[player as usize];
+        holdings.improvements.trade = 5;
+        game.phase = GamePhase::PreRoll;
+        let events = game.apply_action(player, GameAction::RollDice).unwrap();
+        let drawn = events
+            .iter()
+            .find(|e| matches!(e, GameEvent::ProgressCardDrawn { player: p, .. } if *p == player))
+            .unwrap();
+        let hand = &game.cities_knights.as_ref().unwrap().players[player as usize];
+        assert_eq!(hand.progress_cards.len(), 1);
+        assert_eq!(
+            drawn.visible_to(Some(1 - player)),
+            Some(GameEvent::ProgressCardDrawn {
+                player,
+                track: Improvement::Trade,
+                card: None,
+            })
+        );
+
+        let mut plain = GameState::new(2, vec!["A".into(), "B".into()]);
+        let science = GameAction::BuildCityImprovement(Improvement::Science);
+        assert_eq!(
+            plain.apply_action(plain.current_player, science),
+            Err(GameError::ExpansionRequired)
+        );
+    }
+
+    #[test]
+    fn test_event_die_ships_bring_the_barbarians() {
+        let options = GameOptions {
+            event_die: Some(EventDie {
+                faces: vec![EventFace::Ship],
+                track_length: 2,
+            }),
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let city = *game
+            .board
+            .vertices_by_id()
+            .iter()
+            .find(|v| game.board.get_vertex(v) == VertexBuilding::Empty)
+            .unwrap();
+        game.board.upgrade_to_city(city, player);
+        game.players[player as usize].cities_remaining -= 1;
+
+        let first = game.apply_action(player, GameAction::RollDice).unwrap();
+        assert_eq!(first[1], GameEvent::EventDieRolled { player, face: EventFace::Ship });
+        assert_eq!(first[2], GameEvent::BarbariansAdvanced { position: 1, length: 2 });
+        assert_eq!(game.to_json_friendly().event_roll, Some(EventFace::Ship));
+
+        game.phase = GamePhase::PreRoll;
+        let second = game.apply_action(player, GameAction::RollDice).unwrap();
+        assert!(second.contains(&GameEvent::BarbarianAttack { strength: 1 }));
+        assert_eq!(game.barbarians, Some(BarbarianTrack { position: 0, length: 2, attacks: 1 }));
+
+        // Standard rules roll only the two dice
+        let mut plain = GameState::new(2, vec!["A".into(), "B".into()]);
+        complete_setup(&mut plain);
+        let events = plain.apply_action(plain.current_player, GameAction::RollDice).unwrap();
+        assert!(!events.iter().any(|e| matches!(e, GameEvent::EventDieRolled { .. })));
+        assert_eq!(plain.barbarians, None);
+    }
+
+    #[test]
+    fn test_balanced_dice_deal_every_roll_once_per_deck() {
+        let options = GameOptions {
+            dice: DiceMode::Balanced,
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        complete_setup(&mut game);
+        let player = game.current_player;
+
+        let mut reshuffles = 0;
+        for _ in 0..37 {
+            game.phase = GamePhase::PreRoll;
+            let events = game.apply_action(player, GameAction::RollDice).unwrap();
+            reshuffles += events
+                .iter()
+                .filter(|e| **e == GameEvent::DiceDeckReshuffled { cards: 36 })
+                .count();
+        }
+
+        assert_eq!(reshuffles, 2);
+        let mut first_deck = game.dice_history[..36].to_vec();
+        first_deck.sort_unstable();
+        assert_eq!(first_deck, dice::balanced_deck());
+        assert_eq!(DiceStats::from_rolls(&game.dice_history[..36]).chi_square, 0.0);
+        assert_eq!(game.to_json_friendly().dice_deck_remaining, Some(35));
+    }
+
+    #[test]
+    fn test_robber_stays_off_the_board_until_its_turn() {
+        let options = GameOptions {
+            robber_from_turn: Some(3),
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        assert_eq!(game.board.robber_location(), None);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let desert = game
+            .board
+            .land_tiles()
+            .find(|t| t.tile_type == TileType::Desert)
+            .unwrap()
+            .coord;
+
+        // A 7 up to turn 3 only makes players discard
+        let seven_on_turn = |game: &mut GameState, turn| {
+            game.turn_number = turn;
+            game.players[player as usize].resources = ResourceHand {
+                ore: 8,
+                ..ResourceHand::new()
+            };
+            game.phase = GamePhase::DiscardRequired {
+                players_remaining: vec![player],
+            };
+            let half = ResourceHand {
+                ore: 4,
+                ..ResourceHand::new()
+            };
+            game.apply_action(player, GameAction::DiscardCards(half)).unwrap();
+        };
+        seven_on_turn(&mut game, 3);
+        assert_eq!(game.phase, GamePhase::MainPhase);
+        assert_eq!(game.board.robber_location(), None);
+
+        // The first one after it brings the robber in, even onto the desert
+        seven_on_turn(&mut game, 4);
+        assert_eq!(game.phase, GamePhase::RobberMoveRequired);
+        assert!(game.valid_actions(player).contains(&GameAction::MoveRobber(desert)));
+        let events = game.apply_action(player, GameAction::MoveRobber(desert)).unwrap();
+        assert_eq!(
+            events[0],
+            GameEvent::RobberMoved {
+                player,
+                from: None,
+                to: desert,
+            }
+        );
+        assert_eq!(game.board.robber_location(), Some(desert));
+    }
+
+    #[test]
+    fn test_knight_can_send_the_robber_back_to_the_desert() {
+        let options = GameOptions {
+            knight_returns_robber_to_desert: true,
+            ..GameOptions::default()
+        };
+        let mut game = GameState::new_with_options(2, vec!["A".into(), "B".into()], options);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let desert = game.board.robber_location().unwrap();
+        let away = game
+            .board
+            .land_tiles()
+            .map(|t| t.coord)
+            .find(|&h| h != desert)
+            .unwrap();
+        game.board.move_robber(away);
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].dev_cards = vec![DevelopmentCard::Knight];
+
+        let events = game.apply_action(player, GameAction::PlayKnight).unwrap();
+
+        assert!(events.contains(&GameEvent::RobberMoved {
+            player,
+            from: Some(away),
+            to: desert,
+        }));
+        assert_eq!(game.board.robber_location(), Some(desert));
+        // Nobody is robbed and the turn carries on
+        assert_eq!(game.phase, GamePhase::MainPhase);
+    }
+
+    #[test]
+    fn test_end_game_vote_refused_resumes_play() {
+        let mut game = GameState::new(2, vec!["A".into(), "B".into()]);
+        complete_setup(&mut game);
+        let before = game.phase.clone();
+
+        game.apply_action(1, GameAction::ProposeEndGame).unwrap();
+        assert_eq!(
+            game.valid_actions(0),
+            vec![GameAction::VoteEndGame(true), GameAction::VoteEndGame(false)]
+        );
+        let events = game.apply_action(0, GameAction::VoteEndGame(false)).unwrap();
+
+        assert!(events.contains(&GameEvent::EndGameVoteFailed));
+        assert_eq!(game.phase, before);
+    }
+
     #[test]
     fn test_maritime_trade_rate() {
         let game = GameState::new(2, vec!["A".into(), "B".into()]);
@@ -1517,4 +3761,173 @@ mod tests {
             2
         );
     }
+
+    #[test]
+    fn test_display_summary() {
+        let mut game = GameState::new(2, vec!["Ana".into(), "Ben".into()]);
+        game.current_player = 0;
+        game.players[1].resources.add(Resource::Ore, 3);
+        let text = game.to_string();
+
+        assert!(text.starts_with("Turn 0, Setup"));
+        assert!(text.contains("> 0 Ana: 0 VP"));
+        assert!(text.contains("  1 Ben: 0 VP, Brick 0 Lumber 0 Ore 3"));
+        assert!(text.ends_with(&game.board.render_ascii()));
+    }
+
+    #[test]
+    fn test_errors_say_what_went_wrong() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        complete_setup(&mut game);
+        let player = game.current_player;
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].resources = ResourceHand::with_amounts(0, 1, 0, 1, 0);
+
+        let error = game.apply_action(player, GameAction::RollDice).unwrap_err();
+        assert_eq!(
+            error,
+            GameError::InvalidPhase {
+                expected: vec![PhaseKind::PreRoll],
+                actual: PhaseKind::MainPhase,
+            }
+        );
+        let spot = game.board.valid_road_spots(player)[0];
+        let error = game.apply_action(player, GameAction::BuildRoad(spot)).unwrap_err();
+        assert_eq!(
+            error,
+            GameError::CannotAfford {
+                missing: ResourceHand::with_amounts(1, 0, 0, 0, 0)
+            }
+        );
+        assert_eq!(error.to_string(), "Cannot afford this: missing 1 Brick");
+
+        // Clients key their own messages on these, so they never change
+        let codes = [
+            (GameError::InvalidPlayer(0), 1),
+            (GameError::NotYourTurn, 2),
+            (error, 5),
+            (GameError::InvalidDiscard { required: 4 }, 12),
+            (GameError::AlreadyVoted, 15),
+            (GameError::DevCardAlreadyPlayed, 16),
+            (GameError::Paused(PhaseKind::EndGameVote), 17),
+            (
+                GameError::WrongTradeRate {
+                    resource: Resource::Ore,
+                    rate: 2,
+                },
+                19,
+            ),
+        ];
+        for (error, code) in codes {
+            assert_eq!(error.code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_bad_actions_are_refused_without_panicking() {
+        let mut game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        assert_eq!(game.apply_action(7, GameAction::RollDice), Err(GameError::InvalidPlayer(7)));
+        assert_eq!(game.apply_action(7, GameAction::Concede), Err(GameError::InvalidPlayer(7)));
+
+        complete_setup(&mut game);
+        let player = game.current_player;
+        let other = (player + 1) % 3;
+        game.phase = GamePhase::MainPhase;
+        game.players[player as usize].resources = ResourceHand::with_amounts(1, 1, 0, 0, 0);
+        game.players[other as usize].resources = ResourceHand::with_amounts(0, 0, 1, 0, 0);
+        let offer = TradeOffer::new(
+            player,
+            Some(other),
+            ResourceHand::with_amounts(1, 0, 0, 0, 0),
+            ResourceHand::with_amounts(0, 0, 1, 0, 0),
+        );
+        game.apply_action(player, GameAction::ProposeTrade(offer)).unwrap();
+
+        // The offered brick goes into a road before the trade is accepted
+        let road = game.board.valid_road_spots(player)[0];
+        game.apply_action(player, GameAction::BuildRoad(road)).unwrap();
+        assert_eq!(
+            game.apply_action(other, GameAction::AcceptTrade),
+            Err(GameError::InsufficientResources {
+                missing: ResourceHand::with_amounts(1, 0, 0, 0, 0)
+            })
+        );
+        assert_eq!(game.players[other as usize].resources.ore, 1);
+    }
+
+    #[test]
+    fn test_state_hash_follows_the_game() {
+        let names = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+        let mut game = GameState::new_with_seed(3, names.clone(), GameOptions::default(), 5);
+        // Built separately, so its board maps iterate in another order
+        let twin = GameState::new_with_seed(3, names.clone(), GameOptions::default(), 5);
+        assert_eq!(game.state_hash(), twin.state_hash());
+
+        let other = GameState::new_with_seed(3, names, GameOptions::default(), 6);
+        assert_ne!(game.state_hash(), other.state_hash());
+        let action = game.valid_actions(game.current_player)[0].clone();
+        game.apply_action(game.current_player, action).unwrap();
+        assert_ne!(game.state_hash(), twin.state_hash());
+    }
+
+    #[test]
+    fn test_quick_start_skips_setup_with_starting_hands() {
+        let options = GameOptions {
+            quick_start: true,
+            starting_hand: ResourceHand::single(Resource::Brick, 2),
+            ..GameOptions::default()
+        };
+        let names = (0..4).map(|i| format!("P{i}")).collect();
+        let mut game = GameState::new_with_options(4, names, options);
+
+        assert_eq!(game.phase, GamePhase::PreRoll);
+        // Always on the beginner board
+        let tile = game.board.get_tile(&HexCoord::new(-1, -1)).unwrap();
+        assert_eq!((tile.resource(), tile.dice_number), (Some(Resource::Grain), Some(12)));
+        for player in &game.players {
+            assert_eq!((player.settlements_remaining, player.roads_remaining), (3, 13));
+            assert_eq!(game.setup_settlements_placed(player.id), 2);
+            // Two brick to start, and a card from each tile by the second
+            // settlement
+            assert_eq!(player.resources.total(), 5);
+            assert!(player.resources.brick >= 2);
+        }
+        // Player 0's second settlement sits on lumber, grain and wool
+        let first = &game.players[0].resources;
+        assert_eq!((first.lumber, first.grain, first.wool), (1, 1, 1));
+        for piece in scenario::beginner_quick_start(4) {
+            if let StartingPiece::Settlement { vertex, .. } = piece {
+                assert!(game.board.satisfies_distance_rule(&vertex));
+            }
+        }
+
+        let player = game.current_player;
+        game.apply_action(player, GameAction::RollDice).unwrap();
+    }
+
+    #[test]
+    fn test_diff_debug_names_each_change() {
+        let names = vec!["A".to_string(), "B".to_string()];
+        let game = GameState::new_with_seed(2, names.clone(), GameOptions::default(), 5);
+        let twin = GameState::new_with_seed(2, names, GameOptions::default(), 5);
+        assert!(game.diff_debug(&twin).is_empty());
+
+        let mut other = twin.clone();
+        other.players[1].resources.add(Resource::Ore, 2);
+        other.dev_card_deck.pop();
+        let vertex = VertexCoord::new(HexCoord::new(0, -1), crate::hex::VertexDirection::North);
+        other.board.place_settlement(vertex, 0);
+
+        let found: Vec<String> = game.diff_debug(&other).iter().map(|d| d.to_string()).collect();
+        assert!(found.contains(&"players[1].resources.ore: 0 vs 2".to_string()));
+        assert!(found.contains(&"hand_sizes[1]: 0 vs 2".to_string()));
+        // Only occupied corners are listed
+        assert!(found.contains(&format!(
+            "board.vertices.(0, -1) North: missing vs {}",
+            r#"{"building":{"Settlement":0}}"#
+        )));
+        assert!(found.contains(&"dev_card_deck_size: 25 vs 24".to_string()));
+        assert!(found.iter().any(|d| d.starts_with("dev_card_deck[24]: ")));
+        assert_eq!(found.len(), 5);
+    }
 }
@@ -0,0 +1,356 @@
+//! Opponent hand tracking for bots.
+//!
+//! Hand sizes are public but their contents are not. A [`HandTracker`]
+//! follows the events one player gets to see (production, builds, trades,
+//! discards and steals) and keeps an expected count of every resource in
+//! every other hand. Whatever the events can't explain, such as cards held
+//! before the tracker started watching, is filled in from what the player's
+//! buildings produce.
+
+use crate::actions::{GameEvent, TradeOffer};
+use crate::board::{dice_pips, PlayerId, Resource};
+use crate::game::GameState;
+use crate::player::{costs, ResourceHand};
+
+/// Expected hand contents of every player, as seen by one of them
+#[derive(Debug, Clone)]
+pub struct HandTracker {
+    observer: PlayerId,
+    /// Expected count per resource, in `Resource::ALL` order
+    hands: Vec<[f64; 5]>,
+    /// Free roads left from a Road Building card, per player
+    free_roads: Vec<u32>,
+    /// The offer on the table, so a completed trade can be applied
+    offer: Option<TradeOffer>,
+}
+
+impl HandTracker {
+    /// Start tracking from `observer`'s point of view
+    pub fn new(observer: PlayerId) -> Self {
+        Self {
+            observer,
+            hands: Vec::new(),
+            free_roads: Vec::new(),
+            offer: None,
+        }
+    }
+
+    /// The player whose point of view this is
+    pub fn observer(&self) -> PlayerId {
+        self.observer
+    }
+
+    /// Update from events just applied; `game` is the state after them
+    pub fn observe(&mut self, game: &GameState, events: &[GameEvent]) {
+        let seats = game.players.len();
+        if self.hands.len() < seats {
+            self.hands.resize(seats, [0.0; 5]);
+            self.free_roads.resize(seats, 0);
+        }
+
+        for event in events {
+            match event {
+                GameEvent::ResourcesDistributed { distributions } => {
+                    for (player, resource, amount) in distributions {
+                        self.add(*player, *resource, *amount as f64);
+                    }
+                }
+                GameEvent::SettlementBuilt { player, .. } => {
+                    // The two setup settlements are free
+                    let p = &game.players[*player as usize];
+                    if (5 - p.settlements_remaining) + (4 - p.cities_remaining) > 2 {
+                        self.pay(*player, &costs::settlement());
+                    }
+                }
+                GameEvent::CityBuilt { player, .. } => self.pay(*player, &costs::city()),
+                GameEvent::RoadBuilt { player, .. } => {
+                    let free = &mut self.free_roads[*player as usize];
+                    if *free > 0 {
+                        *free -= 1;
+                    } else if 15 - game.players[*player as usize].roads_remaining > 2 {
+                        self.pay(*player, &costs::road());
+                    }
+                }
+                GameEvent::DevelopmentCardPurchased { player, .. } => {
+                    self.pay(*player, &costs::development_card());
+                }
+                GameEvent::RoadBuildingPlayed { player } => {
+                    let p = &game.players[*player as usize];
+                    self.free_roads[*player as usize] = p.roads_remaining.min(2);
+                }
+                GameEvent::YearOfPlentyPlayed {
+                    player,
+                    resources: (a, b),
+                } => {
+                    self.add(*player, *a, 1.0);
+                    self.add(*player, *b, 1.0);
+                }
+                GameEvent::MonopolyPlayed {
+                    player,
+                    resource,
+                    total_stolen,
+                } => {
+                    let i = index(*resource);
+                    for hand in &mut self.hands {
+                        hand[i] = 0.0;
+                    }
+                    self.add(*player, *resource, *total_stolen as f64);
+                }
+                GameEvent::ResourceStolen {
+                    thief,
+                    victim,
+                    resource,
+                } => self.steal(game, *thief, *victim, *resource),
+                GameEvent::CardsDiscarded { player, count } => {
+                    // We only learn how many, so each pile shrinks evenly
+                    let held = game.players[*player as usize].resources.total() + count;
+                    let before = self.estimate_holding(game, *player, held as f64);
+                    let kept = if held > 0 { 1.0 - *count as f64 / held as f64 } else { 0.0 };
+                    self.hands[*player as usize] = before.map(|n| n * kept);
+                }
+                GameEvent::TradeProposed { offer } => self.offer = Some(offer.clone()),
+                GameEvent::TradeCompleted { player1, player2 } => {
+                    if let Some(offer) = self.offer.take() {
+                        self.pay(*player1, &offer.offering);
+                        self.gain(*player1, &offer.requesting);
+                        self.pay(*player2, &offer.requesting);
+                        self.gain(*player2, &offer.offering);
+                    }
+                }
+                GameEvent::TradeCancelled => self.offer = None,
+                GameEvent::MaritimeTradeCompleted {
+                    player,
+                    gave,
+                    gave_count,
+                    received,
+                } => {
+                    self.remove(*player, *gave, *gave_count as f64);
+                    self.add(*player, *received, 1.0);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Expected count of each resource (in `Resource::ALL` order) that
+    /// `player` holds. Our own hand is known exactly; other hands are
+    /// scaled to their public size.
+    pub fn estimate(&self, game: &GameState, player: PlayerId) -> [f64; 5] {
+        let Some(p) = game.get_player(player) else {
+            return [0.0; 5];
+        };
+        if player == self.observer {
+            return Resource::ALL.map(|r| p.resources.get(r) as f64);
+        }
+        self.estimate_holding(game, player, p.resources.total() as f64)
+    }
+
+    /// Tracked hand of `player`, made to add up to `total` cards
+    fn estimate_holding(&self, game: &GameState, player: PlayerId, total: f64) -> [f64; 5] {
+        let tracked = self.hands.get(player as usize).copied().unwrap_or([0.0; 5]);
+        let known: f64 = tracked.iter().sum();
+        if known > total {
+            return tracked.map(|n| n * total / known);
+        }
+
+        // Cards the events don't explain are split by production
+        let shares = production_shares(game, player);
+        let mut estimate = tracked;
+        for (n, share) in estimate.iter_mut().zip(shares) {
+            *n += (total - known) * share;
+        }
+        estimate
+    }
+
+    /// Expected number of `resource` cards in `player`'s hand
+    pub fn expected(&self, game: &GameState, player: PlayerId, resource: Resource) -> f64 {
+        self.estimate(game, player)[index(resource)]
+    }
+
+    /// Chance that one card stolen from `player` is `resource`
+    pub fn steal_chance(&self, game: &GameState, player: PlayerId, resource: Resource) -> f64 {
+        let estimate = self.estimate(game, player);
+        let total: f64 = estimate.iter().sum();
+        if total > 0.0 {
+            estimate[index(resource)] / total
+        } else {
+            0.0
+        }
+    }
+
+    fn steal(
+        &mut self,
+        game: &GameState,
+        thief: PlayerId,
+        victim: PlayerId,
+        resource: Option<Resource>,
+    ) {
+        let known = resource.filter(|_| self.observer == thief || self.observer == victim);
+        if let Some(resource) = known {
+            self.remove(victim, resource, 1.0);
+            self.add(thief, resource, 1.0);
+            return;
+        }
+
+        // Unseen: the thief gets a card in proportion to the victim's hand,
+        // as it looked before the steal
+        let total = game.players[victim as usize].resources.total() as f64 + 1.0;
+        let before = self.estimate_holding(game, victim, total);
+        for (i, resource) in Resource::ALL.into_iter().enumerate() {
+            let chance = before[i] / total;
+            self.remove(victim, resource, chance);
+            self.add(thief, resource, chance);
+        }
+    }
+
+    fn add(&mut self, player: PlayerId, resource: Resource, amount: f64) {
+        if let Some(hand) = self.hands.get_mut(player as usize) {
+            hand[index(resource)] += amount;
+        }
+    }
+
+    fn remove(&mut self, player: PlayerId, resource: Resource, amount: f64) {
+        if let Some(hand) = self.hands.get_mut(player as usize) {
+            let n = &mut hand[index(resource)];
+            *n = (*n - amount).max(0.0);
+        }
+    }
+
+    fn gain(&mut self, player: PlayerId, cards: &ResourceHand) {
+        for resource in Resource::ALL {
+            self.add(player, resource, cards.get(resource) as f64);
+        }
+    }
+
+    fn pay(&mut self, player: PlayerId, cost: &ResourceHand) {
+        for resource in Resource::ALL {
+            self.remove(player, resource, cost.get(resource) as f64);
+        }
+    }
+}
+
+fn index(resource: Resource) -> usize {
+    Resource::ALL.iter().position(|r| *r == resource).unwrap_or(0)
+}
+
+/// Share of each resource (in `Resource::ALL` order) in what a player's
+/// buildings produce; even shares if they produce nothing
+fn production_shares(game: &GameState, player: PlayerId) -> [f64; 5] {
+    let mut production = [0.0; 5];
+    for vertex in game.board.vertices_by_id() {
+        let building = game.board.get_vertex(vertex);
+        if building.owner() != Some(player) {
+            continue;
+        }
+        for tile in game.board.tiles_at_vertex(vertex) {
+            if let (Some(resource), Some(number)) = (tile.resource(), tile.dice_number) {
+                production[index(resource)] +=
+                    (dice_pips(number) * building.resource_multiplier()) as f64;
+            }
+        }
+    }
+
+    let produced: f64 = production.iter().sum();
+    if produced == 0.0 {
+        return [0.2; 5];
+    }
+    production.map(|p| p / produced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, BotDifficulty, BotPlayer};
+    use crate::options::GameOptions;
+
+    #[test]
+    fn test_public_events_pin_down_hands() {
+        let names = vec!["A".into(), "B".into()];
+        let mut game = GameState::new_with_seed(2, names, GameOptions::default(), 5);
+        let mut tracker = HandTracker::new(0);
+
+        // Give player 1 a known hand through production
+        game.players[1].resources = ResourceHand::with_amounts(0, 0, 3, 2, 0);
+        let distributions = vec![(1, Resource::Ore, 3), (1, Resource::Grain, 2)];
+        tracker.observe(&game, &[GameEvent::ResourcesDistributed { distributions }]);
+        assert_eq!(tracker.estimate(&game, 1), [0.0, 0.0, 3.0, 2.0, 0.0]);
+
+        // A maritime trade moves cards between piles exactly
+        game.players[1].resources = ResourceHand::with_amounts(0, 0, 3, 1, 1);
+        let trade = GameEvent::MaritimeTradeCompleted {
+            player: 1,
+            gave: Resource::Grain,
+            gave_count: 1,
+            received: Resource::Wool,
+        };
+        tracker.observe(&game, &[trade]);
+        assert_eq!(tracker.estimate(&game, 1), [0.0, 0.0, 3.0, 1.0, 1.0]);
+
+        // A Monopoly empties everyone else's pile
+        game.players[1].resources = ResourceHand::with_amounts(0, 0, 0, 1, 1);
+        let monopoly = GameEvent::MonopolyPlayed {
+            player: 0,
+            resource: Resource::Ore,
+            total_stolen: 3,
+        };
+        tracker.observe(&game, &[monopoly]);
+        assert_eq!(tracker.expected(&game, 1, Resource::Ore), 0.0);
+        assert_eq!(tracker.steal_chance(&game, 1, Resource::Wool), 0.5);
+    }
+
+    #[test]
+    fn test_unseen_steal_is_shared_out_and_totals_match() {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let mut game = GameState::new_with_seed(3, names, GameOptions::default(), 5);
+        let mut tracker = HandTracker::new(0);
+        game.players[1].resources = ResourceHand::with_amounts(2, 2, 0, 0, 0);
+        let distributions = vec![(1, Resource::Brick, 2), (1, Resource::Lumber, 2)];
+        tracker.observe(&game, &[GameEvent::ResourcesDistributed { distributions }]);
+
+        // Player 2 steals from player 1 out of our sight
+        game.players[1].resources = ResourceHand::with_amounts(1, 2, 0, 0, 0);
+        game.players[2].resources = ResourceHand::with_amounts(1, 0, 0, 0, 0);
+        let steal = GameEvent::ResourceStolen {
+            thief: 2,
+            victim: 1,
+            resource: Some(Resource::Brick),
+        };
+        tracker.observe(&game, &[steal]);
+        let victim = tracker.estimate(&game, 1);
+        let thief = tracker.estimate(&game, 2);
+        assert!((victim.iter().sum::<f64>() - 3.0).abs() < 1e-9);
+        assert!((thief[0] - 0.5).abs() < 1e-9 && (thief[1] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tracking_through_real_games_stays_consistent() {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let mut game = GameState::new_with_seed(3, names, GameOptions::default(), 11);
+        let mut bots: Vec<Bot> = (0..3)
+            .map(|p| Bot::with_seed(p, BotDifficulty::Hard, 40 + p as u64))
+            .collect();
+
+        for _ in 0..600 {
+            if game.is_finished() {
+                break;
+            }
+            let player = game.waiting_on()[0];
+            let Some(action) = bots[player as usize].choose_action(&game) else {
+                break;
+            };
+            let events = game.apply_action(player, action).unwrap();
+            for bot in &mut bots {
+                bot.observe(&game, &events);
+            }
+        }
+
+        for bot in &bots {
+            for other in 0..3 {
+                let estimate = bot.hands().estimate(&game, other);
+                let total = game.players[other as usize].resources.total() as f64;
+                assert!(estimate.iter().all(|n| *n >= 0.0));
+                assert!((estimate.iter().sum::<f64>() - total).abs() < 1e-6);
+            }
+        }
+    }
+}
@@ -9,7 +9,8 @@
 //! avoid the wasted space of offset coordinates.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
 /// Direction of a vertex relative to a hex (North or South pole)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -20,6 +21,29 @@ pub enum VertexDirection {
     South,
 }
 
+/// One of the six corners of a pointy-top hex, clockwise from the top
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HexCorner {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl HexCorner {
+    /// All six corners, clockwise from the top
+    pub const ALL: [HexCorner; 6] = [
+        HexCorner::North,
+        HexCorner::NorthEast,
+        HexCorner::SouthEast,
+        HexCorner::South,
+        HexCorner::SouthWest,
+        HexCorner::NorthWest,
+    ];
+}
+
 /// Direction of an edge relative to a hex
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EdgeDirection {
@@ -106,18 +130,87 @@ impl HexCoord {
         ((dq + dr + ds) / 2) as u32
     }
 
+    /// Hexes on the straight line to another hex, both ends included. Each
+    /// hex is a neighbor of the one before it.
+    pub fn line_to(&self, other: &HexCoord) -> Vec<HexCoord> {
+        let steps = self.distance_to(other);
+        if steps == 0 {
+            return vec![*self];
+        }
+
+        // Nudge off the exact midpoints between hexes so ties round the same way
+        let (q0, r0) = (self.q as f64 + 1e-6, self.r as f64 + 2e-6);
+        let (q1, r1) = (other.q as f64 + 1e-6, other.r as f64 + 2e-6);
+        (0..=steps)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                Self::axial_round(q0 + (q1 - q0) * t, r0 + (r1 - r0) * t)
+            })
+            .collect()
+    }
+
+    /// Shortest path to `goal` through hexes accepted by `passable`, both ends
+    /// included (the start itself doesn't need to be passable). Paths longer
+    /// than `max_steps` are not searched, which keeps the search finite on an
+    /// unbounded grid. Ties between equally short paths break the same way
+    /// every time.
+    pub fn path_to<F>(&self, goal: &HexCoord, max_steps: u32, passable: F) -> Option<Vec<HexCoord>>
+    where
+        F: Fn(&HexCoord) -> bool,
+    {
+        if self == goal {
+            return Some(vec![*self]);
+        }
+        if !passable(goal) {
+            return None;
+        }
+
+        // A* with hex distance as the heuristic
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<HexCoord, HexCoord> = HashMap::new();
+        let mut cost: HashMap<HexCoord, u32> = HashMap::new();
+        cost.insert(*self, 0);
+        open.push(Reverse((self.distance_to(goal), 0, self.q, self.r)));
+
+        while let Some(Reverse((_, steps, q, r))) = open.pop() {
+            let current = HexCoord::new(q, r);
+            if current == *goal {
+                let mut path = vec![current];
+                let mut at = current;
+                while let Some(prev) = came_from.get(&at) {
+                    path.push(*prev);
+                    at = *prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost.get(&current).is_some_and(|&best| steps > best) {
+                continue;
+            }
+
+            for next in current.neighbors() {
+                let next_steps = steps + 1;
+                if next_steps + next.distance_to(goal) > max_steps
+                    || !passable(&next)
+                    || cost.get(&next).is_some_and(|&best| best <= next_steps)
+                {
+                    continue;
+                }
+                cost.insert(next, next_steps);
+                came_from.insert(next, current);
+                let estimate = next_steps + next.distance_to(goal);
+                open.push(Reverse((estimate, next_steps, next.q, next.r)));
+            }
+        }
+
+        None
+    }
+
     /// Get all six vertices of this hex
     pub fn vertices(&self) -> [VertexCoord; 6] {
         // Each hex has 6 vertices, but we only use North and South as canonical
         // The other 4 are expressed as N/S of neighboring hexes
-        [
-            VertexCoord::new(*self, VertexDirection::North),
-            VertexCoord::new(self.neighbor(EdgeDirection::NorthEast), VertexDirection::South),
-            VertexCoord::new(self.neighbor(EdgeDirection::SouthEast), VertexDirection::North),
-            VertexCoord::new(*self, VertexDirection::South),
-            VertexCoord::new(self.neighbor(EdgeDirection::SouthWest), VertexDirection::North),
-            VertexCoord::new(self.neighbor(EdgeDirection::NorthWest), VertexDirection::South),
-        ]
+        HexCorner::ALL.map(|corner| VertexCoord::at_corner(*self, corner))
     }
 
     /// Get all six edges of this hex
@@ -181,56 +274,37 @@ impl VertexCoord {
         Self { hex, direction }.canonical()
     }
 
-    /// Get the canonical form of this vertex coordinate.
-    ///
-    /// Each vertex can be described from 3 different hexes. We pick a canonical
-    /// representation to ensure equality works correctly.
+    /// Name a vertex by any of the three hexes that share it.
     ///
-    /// Canonical form: the hex with the smallest (q, r) tuple that has this as N or S vertex
-    pub fn canonical(self) -> Self {
-        let equivalent_hexes = self.touching_hexes_raw();
-        let mut candidates: Vec<(HexCoord, VertexDirection)> = Vec::new();
-
-        // For each hex that touches this vertex, determine if it's the N or S vertex
-        for hex in equivalent_hexes {
-            let north_vertex = self.vertex_position_for_hex(hex, VertexDirection::North);
-            let south_vertex = self.vertex_position_for_hex(hex, VertexDirection::South);
-            let my_position = self.absolute_position();
-
-            const EPSILON: f64 = 0.001;
-            if (north_vertex.0 - my_position.0).abs() < EPSILON
-                && (north_vertex.1 - my_position.1).abs() < EPSILON
-            {
-                candidates.push((hex, VertexDirection::North));
-            }
-            if (south_vertex.0 - my_position.0).abs() < EPSILON
-                && (south_vertex.1 - my_position.1).abs() < EPSILON
-            {
-                candidates.push((hex, VertexDirection::South));
-            }
-        }
-
-        // Pick the one with smallest (q, r)
-        candidates.sort_by_key(|(h, d)| (h.q, h.r, *d as u8));
-        candidates
-            .first()
-            .map(|(h, d)| Self { hex: *h, direction: *d })
-            .unwrap_or(self)
-    }
-
-    fn vertex_position_for_hex(&self, hex: HexCoord, direction: VertexDirection) -> (f64, f64) {
-        let (cx, cy) = hex.to_pixel(1.0);
-        match direction {
-            VertexDirection::North => (cx, cy - 1.0),
-            VertexDirection::South => (cx, cy + 1.0),
-        }
+    /// The top corner of a hex is also the south-east corner of its north-west
+    /// neighbor and the south-west corner of its north-east neighbor (and
+    /// mirrored for the bottom corner), so side corners map to the North or
+    /// South corner of the neighbor above or below them.
+    pub fn at_corner(hex: HexCoord, corner: HexCorner) -> Self {
+        let (hex, direction) = match corner {
+            HexCorner::North => (hex, VertexDirection::North),
+            HexCorner::NorthEast => (hex.neighbor(EdgeDirection::NorthEast), VertexDirection::South),
+            HexCorner::SouthEast => (hex.neighbor(EdgeDirection::SouthEast), VertexDirection::North),
+            HexCorner::South => (hex, VertexDirection::South),
+            HexCorner::SouthWest => (hex.neighbor(EdgeDirection::SouthWest), VertexDirection::North),
+            HexCorner::NorthWest => (hex.neighbor(EdgeDirection::NorthWest), VertexDirection::South),
+        };
+        Self { hex, direction }
     }
 
-    fn absolute_position(&self) -> (f64, f64) {
-        self.vertex_position_for_hex(self.hex, self.direction)
+    /// Get the canonical form of this vertex coordinate.
+    ///
+    /// Every vertex is the North corner of exactly one hex or the South corner
+    /// of exactly one hex, never both: hex centers sit on rows 1.5 sizes apart
+    /// while a hex's poles are 2 sizes apart. So `(hex, North | South)` already
+    /// names each vertex once and this is the identity; use
+    /// [`VertexCoord::at_corner`] to fold in the other descriptions.
+    pub fn canonical(self) -> Self {
+        self
     }
 
-    fn touching_hexes_raw(&self) -> [HexCoord; 3] {
+    /// Get the 3 hexes that touch this vertex
+    pub fn touching_hexes(&self) -> [HexCoord; 3] {
         match self.direction {
             VertexDirection::North => [
                 self.hex,
@@ -245,11 +319,6 @@ impl VertexCoord {
         }
     }
 
-    /// Get the 3 hexes that touch this vertex
-    pub fn touching_hexes(&self) -> [HexCoord; 3] {
-        self.touching_hexes_raw()
-    }
-
     /// Get the 3 adjacent vertices (for distance rule checking)
     ///
     /// Adjacent vertices are those connected by exactly one edge.
@@ -428,6 +497,40 @@ mod tests {
         assert_eq!(a.distance_to(&c), 3);
     }
 
+    #[test]
+    fn test_line_to() {
+        let start = HexCoord::new(-2, 0);
+        let end = HexCoord::new(3, -2);
+        let line = start.line_to(&end);
+
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&end));
+        assert!(line.windows(2).all(|w| w[0].distance_to(&w[1]) == 1));
+        assert_eq!(start.line_to(&start), vec![start]);
+    }
+
+    #[test]
+    fn test_path_to_goes_around_walls() {
+        // A wall along q = 1 with a gap at r = 3
+        let wall = |h: &HexCoord| h.q == 1 && h.r != 3;
+        let start = HexCoord::new(0, 0);
+        let goal = HexCoord::new(2, 0);
+
+        let path = start.path_to(&goal, 20, |h| !wall(h)).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| w[0].distance_to(&w[1]) == 1));
+        assert!(path.iter().all(|h| !wall(h)));
+        assert!(path.contains(&HexCoord::new(1, 3)));
+        assert_eq!(path, start.path_to(&goal, 20, |h| !wall(h)).unwrap());
+
+        // Open ground: as short as the distance
+        assert_eq!(start.path_to(&goal, 20, |_| true).unwrap().len(), 3);
+        // Too far to get around within the limit
+        assert_eq!(start.path_to(&goal, 4, |h| !wall(h)), None);
+    }
+
     #[test]
     fn test_vertex_canonical_equality() {
         // The same vertex can be described from different hexes
@@ -442,6 +545,43 @@ mod tests {
         assert_eq!(v1_canon, v1_canon2, "Canonicalization should be idempotent");
     }
 
+    #[test]
+    fn test_vertex_corners_collapse_exhaustively() {
+        let hexes: Vec<HexCoord> = (-4..=4)
+            .flat_map(|q| (-4..=4).map(move |r| HexCoord::new(q, r)))
+            .collect();
+
+        // Every description of every corner, grouped by the vertex it names
+        let mut named_by: HashMap<VertexCoord, Vec<HexCoord>> = HashMap::new();
+        for hex in &hexes {
+            for corner in HexCorner::ALL {
+                let vertex = VertexCoord::at_corner(*hex, corner);
+                assert_eq!(vertex.canonical(), vertex);
+                assert!(vertex.touching_hexes().contains(hex));
+                named_by.entry(vertex).or_default().push(*hex);
+
+                // Same point in space as the corner it was built from
+                let (cx, cy) = hex.to_pixel(1.0);
+                let angle = std::f64::consts::PI / 3.0 * corner as usize as f64;
+                let (x, y) = vertex.to_pixel(1.0);
+                assert!((x - (cx + angle.sin())).abs() < 1e-9);
+                assert!((y - (cy - angle.cos())).abs() < 1e-9);
+            }
+            let unique: HashSet<_> = hex.vertices().into_iter().collect();
+            assert_eq!(unique.len(), 6);
+        }
+
+        // Vertices with all three hexes in range were named once from each
+        for (vertex, mut from) in named_by {
+            let mut touching = vertex.touching_hexes().to_vec();
+            if touching.iter().all(|h| hexes.contains(h)) {
+                from.sort_by_key(|h| (h.q, h.r));
+                touching.sort_by_key(|h| (h.q, h.r));
+                assert_eq!(from, touching);
+            }
+        }
+    }
+
     #[test]
     fn test_vertex_touching_hexes() {
         let v = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
@@ -0,0 +1,293 @@
+//! Message keys for translating errors and events.
+//!
+//! `Display` strings are English and may be reworded at any time. Clients
+//! that show the game in Mandarin, Malay, Tamil or Singlish instead look up
+//! a [`Message`]: a stable key such as `event.road_built` and the values to
+//! fill into it (`{player}`, `{location}`). Keys are never renamed; a new
+//! error or event gets a new key.
+
+use crate::actions::GameEvent;
+use crate::game::GameError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A translatable message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    /// Stable key, `error.*` or `event.*`
+    pub key: String,
+    /// Values for the placeholders, as JSON
+    pub args: BTreeMap<String, Value>,
+}
+
+impl Message {
+    fn new(key: &str, args: &[(&str, Value)]) -> Self {
+        Self {
+            key: key.to_string(),
+            args: args
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        }
+    }
+
+    /// Fill a translated template, replacing each `{name}` with its
+    /// argument. Strings go in bare and anything else as JSON; unknown
+    /// placeholders are left as they are.
+    pub fn render(&self, template: &str) -> String {
+        self.args.iter().fold(template.to_string(), |text, (name, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            text.replace(&format!("{{{name}}}"), &value)
+        })
+    }
+}
+
+/// JSON for a message argument
+fn arg<T: Serialize>(value: T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Key and arguments for an error
+pub fn error_message(error: &GameError) -> Message {
+    use GameError::*;
+
+    match error {
+        InvalidPlayer(player) => Message::new("error.invalid_player", &[("player", arg(player))]),
+        NotYourTurn => Message::new("error.not_your_turn", &[]),
+        InvalidPhase { expected, actual } => Message::new(
+            "error.invalid_phase",
+            &[("expected", arg(expected)), ("actual", arg(actual))],
+        ),
+        InvalidLocation(location) => {
+            Message::new("error.invalid_location", &[("location", arg(location))])
+        }
+        CannotAfford { missing } => {
+            Message::new("error.cannot_afford", &[("missing", arg(missing))])
+        }
+        InsufficientResources { missing } => {
+            Message::new("error.insufficient_resources", &[("missing", arg(missing))])
+        }
+        NoPiecesRemaining(piece) => {
+            Message::new("error.no_pieces_remaining", &[("piece", arg(piece))])
+        }
+        EmptyDeck => Message::new("error.empty_deck", &[]),
+        NoSuchCard(card) => Message::new("error.no_such_card", &[("card", arg(card))]),
+        InvalidTrade => Message::new("error.invalid_trade", &[]),
+        NoActiveTrade => Message::new("error.no_active_trade", &[]),
+        InvalidDiscard { required } => {
+            Message::new("error.invalid_discard", &[("required", arg(required))])
+        }
+        GameOver => Message::new("error.game_over", &[]),
+        PlayerConceded => Message::new("error.player_conceded", &[]),
+        AlreadyVoted => Message::new("error.already_voted", &[]),
+        DevCardAlreadyPlayed => Message::new("error.dev_card_already_played", &[]),
+        Paused(phase) => Message::new("error.paused", &[("phase", arg(phase))]),
+        CannotRob(player) => Message::new("error.cannot_rob", &[("player", arg(player))]),
+        WrongTradeRate { resource, rate } => Message::new(
+            "error.wrong_trade_rate",
+            &[("resource", arg(resource)), ("rate", arg(rate))],
+        ),
+        ExpansionRequired => Message::new("error.expansion_required", &[]),
+        MissingCommodities { missing } => {
+            Message::new("error.missing_commodities", &[("missing", arg(missing))])
+        }
+        CannotImprove(track) => Message::new("error.cannot_improve", &[("track", arg(track))]),
+    }
+}
+
+/// Key and arguments for an event
+pub fn event_message(event: &GameEvent) -> Message {
+    use GameEvent::*;
+
+    match event {
+        DiceRolled { player, roll, total } => Message::new(
+            "event.dice_rolled",
+            &[("player", arg(player)), ("roll", arg(roll)), ("total", arg(total))],
+        ),
+        DiceDeckReshuffled { cards } => {
+            Message::new("event.dice_deck_reshuffled", &[("cards", arg(cards))])
+        }
+        EventDieRolled { player, face } => Message::new(
+            "event.event_die_rolled",
+            &[("player", arg(player)), ("face", arg(face))],
+        ),
+        BarbariansAdvanced { position, length } => Message::new(
+            "event.barbarians_advanced",
+            &[("position", arg(position)), ("length", arg(length))],
+        ),
+        BarbarianAttack { strength } => {
+            Message::new("event.barbarian_attack", &[("strength", arg(strength))])
+        }
+        CommoditiesProduced { distributions } => Message::new(
+            "event.commodities_produced",
+            &[("distributions", arg(distributions))],
+        ),
+        CityImproved { player, track, level } => Message::new(
+            "event.city_improved",
+            &[("player", arg(player)), ("track", arg(track)), ("level", arg(level))],
+        ),
+        ProgressCardDrawn { player, track, card } => Message::new(
+            "event.progress_card_drawn",
+            &[("player", arg(player)), ("track", arg(track)), ("card", arg(card))],
+        ),
+        ResourcesDistributed { distributions } => Message::new(
+            "event.resources_distributed",
+            &[("distributions", arg(distributions))],
+        ),
+        SettlementBuilt { player, location } => Message::new(
+            "event.settlement_built",
+            &[("player", arg(player)), ("location", arg(location))],
+        ),
+        CityBuilt { player, location } => Message::new(
+            "event.city_built",
+            &[("player", arg(player)), ("location", arg(location))],
+        ),
+        RoadBuilt { player, location } => Message::new(
+            "event.road_built",
+            &[("player", arg(player)), ("location", arg(location))],
+        ),
+        DevelopmentCardPurchased { player, deck_remaining } => Message::new(
+            "event.development_card_purchased",
+            &[("player", arg(player)), ("deck_remaining", arg(deck_remaining))],
+        ),
+        DevelopmentCardDrawn { player, card } => Message::new(
+            "event.development_card_drawn",
+            &[("player", arg(player)), ("card", arg(card))],
+        ),
+        KnightPlayed { player } => Message::new("event.knight_played", &[("player", arg(player))]),
+        RoadBuildingPlayed { player } => {
+            Message::new("event.road_building_played", &[("player", arg(player))])
+        }
+        YearOfPlentyPlayed { player, resources } => Message::new(
+            "event.year_of_plenty_played",
+            &[("player", arg(player)), ("resources", arg(resources))],
+        ),
+        MonopolyPlayed { player, resource, total_stolen } => Message::new(
+            "event.monopoly_played",
+            &[
+                ("player", arg(player)),
+                ("resource", arg(resource)),
+                ("total_stolen", arg(total_stolen)),
+            ],
+        ),
+        RobberMoved { player, from, to } => Message::new(
+            "event.robber_moved",
+            &[("player", arg(player)), ("from", arg(from)), ("to", arg(to))],
+        ),
+        PirateMoved { player, from, to } => Message::new(
+            "event.pirate_moved",
+            &[("player", arg(player)), ("from", arg(from)), ("to", arg(to))],
+        ),
+        ResourceStolen { thief, victim, resource } => Message::new(
+            "event.resource_stolen",
+            &[("thief", arg(thief)), ("victim", arg(victim)), ("resource", arg(resource))],
+        ),
+        CardsDiscarded { player, count } => Message::new(
+            "event.cards_discarded",
+            &[("player", arg(player)), ("count", arg(count))],
+        ),
+        TradeProposed { offer } => Message::new(
+            "event.trade_proposed",
+            &[
+                ("player", arg(offer.from)),
+                ("to", arg(offer.to)),
+                ("offering", arg(&offer.offering)),
+                ("requesting", arg(&offer.requesting)),
+            ],
+        ),
+        TradeCompleted { player1, player2 } => Message::new(
+            "event.trade_completed",
+            &[("player1", arg(player1)), ("player2", arg(player2))],
+        ),
+        TradeCancelled => Message::new("event.trade_cancelled", &[]),
+        MaritimeTradeCompleted { player, gave, gave_count, received } => Message::new(
+            "event.maritime_trade_completed",
+            &[
+                ("player", arg(player)),
+                ("gave", arg(gave)),
+                ("gave_count", arg(gave_count)),
+                ("received", arg(received)),
+            ],
+        ),
+        LongestRoadChanged { previous, current, length } => Message::new(
+            "event.longest_road_changed",
+            &[("previous", arg(previous)), ("current", arg(current)), ("length", arg(length))],
+        ),
+        LargestArmyChanged { previous, current, knights } => Message::new(
+            "event.largest_army_changed",
+            &[("previous", arg(previous)), ("current", arg(current)), ("knights", arg(knights))],
+        ),
+        TurnEnded { player, next_player } => Message::new(
+            "event.turn_ended",
+            &[("player", arg(player)), ("next_player", arg(next_player))],
+        ),
+        PlayerConceded { player } => {
+            Message::new("event.player_conceded", &[("player", arg(player))])
+        }
+        EndGameProposed { player } => {
+            Message::new("event.end_game_proposed", &[("player", arg(player))])
+        }
+        EndGameVoteCast { player, accept } => Message::new(
+            "event.end_game_vote_cast",
+            &[("player", arg(player)), ("accept", arg(accept))],
+        ),
+        EndGameVoteFailed => Message::new("event.end_game_vote_failed", &[]),
+        GameEndedByAgreement { ranking } => {
+            Message::new("event.game_ended_by_agreement", &[("ranking", arg(ranking))])
+        }
+        NextPlayerConfirmed { player } => {
+            Message::new("event.next_player_confirmed", &[("player", arg(player))])
+        }
+        TimeExpired { player } => Message::new("event.time_expired", &[("player", arg(player))]),
+        TurnLimitReached { turns } => {
+            Message::new("event.turn_limit_reached", &[("turns", arg(turns))])
+        }
+        TeamWon { team, players } => Message::new(
+            "event.team_won",
+            &[("team", arg(team)), ("players", arg(players))],
+        ),
+        AchievementUnlocked { player, achievement } => Message::new(
+            "event.achievement_unlocked",
+            &[("player", arg(player)), ("achievement", arg(achievement.key()))],
+        ),
+        GameWon { player, victory_points } => Message::new(
+            "event.game_won",
+            &[("player", arg(player)), ("victory_points", arg(victory_points))],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Resource;
+    use crate::hex::{EdgeCoord, EdgeDirection, HexCoord};
+
+    #[test]
+    fn test_messages_have_stable_keys_and_args() {
+        let road = GameEvent::RoadBuilt {
+            player: 2,
+            location: EdgeCoord::new(HexCoord::new(0, 0), EdgeDirection::East),
+        };
+        let message = event_message(&road);
+        assert_eq!(message.key, "event.road_built");
+        assert_eq!(message.args["player"], 2);
+        assert!(message.args.contains_key("location"));
+
+        let error = GameError::WrongTradeRate {
+            resource: Resource::Ore,
+            rate: 3,
+        };
+        let message = error_message(&error);
+        assert_eq!(message.key, "error.wrong_trade_rate");
+        assert_eq!(
+            message.render("{resource} tukar {rate}:1 {unknown}"),
+            "Ore tukar 3:1 {unknown}"
+        );
+        assert_eq!(error_message(&GameError::NotYourTurn).args.len(), 0);
+    }
+}
@@ -0,0 +1,308 @@
+//! Rules the engine must never break, checked from the outside.
+//!
+//! [`check`] looks at a game as it stands: pieces on the board match what
+//! each player has left to build, victory points add up from the board and
+//! the cards, there is exactly one robber (unless a house rule keeps it off
+//! the board), and each award has at most one holder. [`check_move`] adds
+//! that no cards appeared or vanished in a move beyond what the bank paid
+//! out or took in.
+//!
+//! With the `strict-invariants` feature, `GameState::apply_action` runs
+//! [`assert_move`] after every move it accepts, so CI and fuzzing catch an
+//! engine bug where it happens instead of turns later.
+
+use crate::actions::{GameAction, GameEvent};
+use crate::board::{EdgeBuilding, PlayerId, Resource, TileType, VertexBuilding};
+use crate::game::{GamePhase, GameState};
+use crate::player::{costs, DevelopmentCard, ResourceHand};
+
+/// Pieces each player starts with
+const SETTLEMENTS: u32 = 5;
+const CITIES: u32 = 4;
+const ROADS: u32 = 15;
+
+/// Knights needed for Largest Army
+const MIN_LARGEST_ARMY: u32 = 3;
+
+/// What a move's card flows are checked against, taken just before it
+#[derive(Debug, Clone)]
+pub struct Before {
+    /// Cards of each kind held by all players together
+    cards: ResourceHand,
+    /// Roads are being placed with a Road Building card
+    free_roads: bool,
+}
+
+impl Before {
+    pub fn of(game: &GameState) -> Self {
+        Self {
+            cards: card_totals(game),
+            free_roads: matches!(game.phase, GamePhase::RoadBuildingInProgress { .. }),
+        }
+    }
+}
+
+fn card_totals(game: &GameState) -> ResourceHand {
+    let mut totals = ResourceHand::new();
+    for player in &game.players {
+        totals.add_hand(&player.resources);
+    }
+    totals
+}
+
+/// Every invariant `game` breaks, one line each
+pub fn check(game: &GameState) -> Result<(), Vec<String>> {
+    let mut broken = Vec::new();
+    let board = game.board.to_json_friendly();
+
+    for player in &game.players {
+        let id = player.id;
+        let settlements = board
+            .vertices
+            .iter()
+            .filter(|v| v.building == VertexBuilding::Settlement(id))
+            .count() as u32;
+        let cities = board
+            .vertices
+            .iter()
+            .filter(|v| v.building == VertexBuilding::City(id))
+            .count() as u32;
+        let roads = board
+            .edges
+            .iter()
+            .filter(|e| e.building == EdgeBuilding::Road(id))
+            .count() as u32;
+
+        let pieces = [
+            (
+                "settlements",
+                settlements,
+                SETTLEMENTS,
+                player.settlements_remaining,
+            ),
+            ("cities", cities, CITIES, player.cities_remaining),
+            ("roads", roads, ROADS, player.roads_remaining),
+        ];
+        for (piece, on_board, start, remaining) in pieces {
+            if on_board + remaining != start {
+                broken.push(format!(
+                    "seat {} has {} {} on the board and {} left, not {} in all",
+                    id, on_board, piece, remaining, start
+                ));
+            }
+        }
+
+        // Cards bought this turn only count from the next one
+        let vp_cards = player
+            .dev_cards
+            .iter()
+            .filter(|c| matches!(c, DevelopmentCard::VictoryPoint))
+            .count() as u32;
+        let expected = settlements
+            + 2 * cities
+            + 2 * player.has_longest_road as u32
+            + 2 * player.has_largest_army as u32
+            + vp_cards;
+        let counted = game.total_victory_points(id);
+        if counted != expected {
+            broken.push(format!(
+                "seat {} is counted {} VP but the board and cards make {}",
+                id, counted, expected
+            ));
+        }
+
+        if player.has_largest_army && player.played_knights < MIN_LARGEST_ARMY {
+            broken.push(format!(
+                "seat {} holds Largest Army with {} knights",
+                id, player.played_knights
+            ));
+        }
+    }
+
+    for (award, holders) in [
+        (
+            "Longest Road",
+            game.players.iter().filter(|p| p.has_longest_road).count(),
+        ),
+        (
+            "Largest Army",
+            game.players.iter().filter(|p| p.has_largest_army).count(),
+        ),
+    ] {
+        if holders > 1 {
+            broken.push(format!("{} players hold {}", holders, award));
+        }
+    }
+
+    let robbers: Vec<_> = board.tiles.iter().filter(|t| t.has_robber).collect();
+    match (robbers.as_slice(), game.board.robber_location()) {
+        ([tile], Some(robber)) if (tile.q, tile.r) == (robber.q, robber.r) => {
+            if tile.tile_type == TileType::Ocean {
+                broken.push(format!("the robber is at sea at ({}, {})", tile.q, tile.r));
+            }
+        }
+        ([tile], Some(robber)) => broken.push(format!(
+            "the robber is drawn at ({}, {}) but kept at ({}, {})",
+            tile.q, tile.r, robber.q, robber.r
+        )),
+        ([tile], None) => broken.push(format!(
+            "the robber is drawn at ({}, {}) but kept off the board",
+            tile.q, tile.r
+        )),
+        // Only the robber_from_turn rule keeps it off the board
+        ([], None) if game.options.robber_from_turn.is_some() => {}
+        ([], None) => broken.push("the robber is missing".to_string()),
+        (tiles, _) => broken.push(format!("{} tiles have the robber", tiles.len())),
+    }
+    match (game.options.pirate, game.board.pirate_location()) {
+        (true, Some(hex)) => {
+            if game.board.get_tile(&hex).map(|t| t.tile_type) != Some(TileType::Ocean) {
+                broken.push(format!(
+                    "the pirate is off the sea at ({}, {})",
+                    hex.q, hex.r
+                ));
+            }
+        }
+        (true, None) => broken.push("the pirate is missing".to_string()),
+        (false, Some(_)) => broken.push("there is a pirate without the pirate rule".to_string()),
+        (false, None) => {}
+    }
+
+    if let GamePhase::DiscardRequired { players_remaining } = &game.phase {
+        if players_remaining.is_empty() {
+            broken.push("a discard is required from nobody".to_string());
+        }
+    }
+
+    if broken.is_empty() {
+        Ok(())
+    } else {
+        Err(broken)
+    }
+}
+
+/// What the bank paid out (positive) or took in (negative) for a move
+fn bank_flow(before: &Before, action: &GameAction, events: &[GameEvent]) -> [i64; 5] {
+    let mut flow = [0i64; 5];
+    let index = |r: Resource| Resource::ALL.iter().position(|&x| x == r).unwrap_or(0);
+    let mut pay = |hand: &ResourceHand, sign: i64| {
+        for resource in Resource::ALL {
+            flow[index(resource)] += sign * hand.get(resource) as i64;
+        }
+    };
+    match action {
+        GameAction::BuildRoad(_) if !before.free_roads => pay(&costs::road(), -1),
+        GameAction::BuildSettlement(_) => pay(&costs::settlement(), -1),
+        GameAction::BuildCity(_) => pay(&costs::city(), -1),
+        GameAction::BuyDevelopmentCard => pay(&costs::development_card(), -1),
+        GameAction::DiscardCards(hand) => pay(hand, -1),
+        _ => {}
+    }
+    for event in events {
+        match event {
+            GameEvent::ResourcesDistributed { distributions } => {
+                for &(_, resource, amount) in distributions {
+                    flow[index(resource)] += amount as i64;
+                }
+            }
+            GameEvent::YearOfPlentyPlayed { resources, .. } => {
+                flow[index(resources.0)] += 1;
+                flow[index(resources.1)] += 1;
+            }
+            GameEvent::MaritimeTradeCompleted {
+                gave,
+                gave_count,
+                received,
+                ..
+            } => {
+                flow[index(*gave)] -= *gave_count as i64;
+                flow[index(*received)] += 1;
+            }
+            _ => {}
+        }
+    }
+    flow
+}
+
+/// Every invariant broken by making `action`, given the game just before
+/// it and the events it caused
+pub fn check_move(
+    before: &Before,
+    game: &GameState,
+    action: &GameAction,
+    events: &[GameEvent],
+) -> Result<(), Vec<String>> {
+    let mut broken = check(game).err().unwrap_or_default();
+    let after = card_totals(game);
+    let flow = bank_flow(before, action, events);
+    for (i, resource) in Resource::ALL.into_iter().enumerate() {
+        let expected = before.cards.get(resource) as i64 + flow[i];
+        if after.get(resource) as i64 != expected {
+            broken.push(format!(
+                "players hold {} {:?} but the bank's payments leave {}",
+                after.get(resource),
+                resource,
+                expected
+            ));
+        }
+    }
+    if broken.is_empty() {
+        Ok(())
+    } else {
+        Err(broken)
+    }
+}
+
+/// Panic with everything known about the move if it broke an invariant
+pub fn assert_move(
+    before: &Before,
+    game: &GameState,
+    player: PlayerId,
+    action: &GameAction,
+    events: &[GameEvent],
+) {
+    if let Err(broken) = check_move(before, game, action, events) {
+        panic!(
+            "seat {} played {:?} and broke the rules:\n  {}\nevents: {:?}\n{}",
+            player,
+            action,
+            broken.join("\n  "),
+            events,
+            game
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzing;
+    use crate::hex::{HexCoord, VertexCoord, VertexDirection};
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    fn test_invariants_hold_in_play_and_catch_tampering() {
+        // Random games through the fuzzing harness check every move
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..20 {
+            let mut data = vec![0; 4000];
+            rng.fill_bytes(&mut data);
+            fuzzing::run(&data);
+        }
+
+        let mut game = GameState::new_standard_4player();
+        assert_eq!(check(&game), Ok(()));
+
+        let vertex = VertexCoord::new(HexCoord::new(0, 0), VertexDirection::North);
+        game.board.place_settlement(vertex, 1);
+        let broken = check(&game).unwrap_err();
+        assert_eq!(broken.len(), 2);
+        assert!(broken[0].contains("seat 1 has 1 settlements"));
+
+        let before = Before::of(&game);
+        game.players[2].resources.add(Resource::Ore, 2);
+        let broken = check_move(&before, &game, &GameAction::EndTurn, &[]).unwrap_err();
+        assert!(broken.iter().any(|b| b.contains("2 Ore")));
+    }
+}
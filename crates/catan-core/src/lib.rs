@@ -19,19 +19,78 @@
 //! - [`player`]: Player state and resources (coming soon)
 //! - [`game`]: Game state machine (coming soon)
 
+mod arena;
+pub mod achievements;
 pub mod actions;
+pub mod analysis;
+pub mod barbarians;
 pub mod board;
 pub mod bot;
+pub mod cities_knights;
+pub mod clock;
+pub mod delta;
+pub mod dice;
+pub mod districts;
+pub mod evaluation;
+pub mod fairness;
+pub mod event_log;
+pub mod fuzzing;
 pub mod game;
+pub mod hand_tracker;
 pub mod hex;
+pub mod i18n;
+pub mod invariants;
+pub mod mcts;
+pub mod opening;
+pub mod options;
 pub mod player;
+pub mod rng;
+pub mod scenario;
+pub mod simulation;
+pub mod theme;
+pub mod tournament;
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
 // Re-export commonly used types
+pub use achievements::Achievement;
 pub use actions::{GameAction, GameEvent, TradeOffer};
-pub use board::{Board, EdgeBuilding, Harbor, PlayerId, Resource, Tile, TileType, VertexBuilding};
-pub use bot::{Bot, BotDifficulty};
-pub use game::{GameError, GamePhase, GameState, GameStateJson, SetupPlacing};
-pub use hex::{EdgeCoord, EdgeDirection, HexCoord, VertexCoord, VertexDirection};
-pub use player::{DevelopmentCard, Player, PlayerColor, ResourceHand};
+pub use analysis::{
+    analyze_game, AnalysisError, Decision, GameAnalysis, GameRecord, PlayerAccuracy, RecordedMove,
+    ReplayRecord,
+};
+pub use barbarians::{BarbarianTrack, EventDie, EventFace};
+pub use board::{
+    BalanceReport, Board, BoardError, BoardGenConfig, BoardGeometry, BoardTemplate, EdgeBuilding,
+    EdgeId, Harbor, PlayerId, RenderLayout, Resource, Tile, TileType, VertexBuilding, VertexId,
+};
+pub use bot::{
+    Bot, BotDifficulty, BotExplanation, BotPersonality, BotPlayer, BotWeights, BuildTarget,
+    CandidateScore,
+};
+pub use cities_knights::{
+    CitiesKnights, CitiesKnightsPlayer, Commodity, CommodityHand, Improvement, Improvements,
+    ProgressCard,
+};
+pub use clock::{FlagFallPolicy, GameClock, TimeControl};
+pub use delta::{StateDelta, StateDifference};
+pub use dice::{DiceMode, DiceStats};
+pub use districts::{DistrictPool, MrtLine, Region};
+pub use evaluation::{evaluate_position, win_probabilities};
+pub use fairness::{dice_rolls, verify_dice, FairnessError, SeedReveal};
+pub use event_log::{EventLog, SequencedEvent};
+pub use game::{
+    GameError, GamePhase, GameState, GameStateJson, Hint, Location, PhaseKind, Piece, SetupPlacing,
+};
+pub use hand_tracker::HandTracker;
+pub use hex::{EdgeCoord, EdgeDirection, HexCoord, HexCorner, VertexCoord, VertexDirection};
+pub use i18n::{error_message, event_message, Message};
+pub use mcts::MctsConfig;
+pub use opening::OpeningScore;
+pub use options::{Expansion, GameOptions, TeamVictory};
+pub use player::{DevCardCounts, DevelopmentCard, Player, PlayerColor, ResourceHand};
+pub use rng::GameRng;
+pub use scenario::{Scenario, ScenarioError, ScenarioTile, StartingPiece};
+pub use simulation::{SimGame, SimOutcome, SimSeat, SimulationConfig, SimulationReport};
+pub use theme::ThemePack;
+pub use tournament::{Entrant, Matchup, TournamentConfig, TournamentReport};
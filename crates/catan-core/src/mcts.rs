@@ -0,0 +1,361 @@
+//! Monte Carlo Tree Search for the Expert bot.
+//!
+//! The search is open-loop: tree nodes stand for sequences of actions rather
+//! than states. Every iteration replays them on a fresh clone of the game,
+//! reseeded with the iteration's own seed and with the deck reshuffled, so
+//! dice, steals and card draws are sampled anew each time. Only the actions
+//! still legal in that sample are considered at each node.
+//!
+//! Opponents' hands are read from the state as they are; only the deck
+//! order and future randomness are hidden from the search.
+
+use crate::actions::GameAction;
+use crate::board::PlayerId;
+use crate::bot::{bot_discard, Bot, BotDifficulty, BotPlayer};
+use crate::evaluation::win_probabilities;
+use crate::game::{GamePhase, GameState};
+use crate::rng::GameRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Search budget and tuning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MctsConfig {
+    /// Number of playouts per decision
+    pub iterations: u32,
+    /// Stop early once this much time has passed. Not available on
+    /// `wasm32-unknown-unknown`, where there is no clock; use `iterations`.
+    pub time_limit: Option<Duration>,
+    /// Actions played out after leaving the tree before the position is scored
+    pub rollout_depth: u32,
+    /// UCB exploration constant
+    pub exploration: f64,
+    /// Independent trees the playouts are split between, with their root
+    /// visits added up at the end. With the `parallel` feature they are
+    /// searched at the same time; the result is the same either way.
+    #[serde(default = "one_tree")]
+    pub trees: u32,
+}
+
+fn one_tree() -> u32 {
+    1
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 400,
+            time_limit: None,
+            rollout_depth: 80,
+            exploration: 1.4,
+            trees: 1,
+        }
+    }
+}
+
+struct Node {
+    /// Player who took the action leading here
+    player: PlayerId,
+    visits: u32,
+    total_reward: f64,
+    children: Vec<(GameAction, usize)>,
+}
+
+impl Node {
+    fn new(player: PlayerId) -> Self {
+        Self {
+            player,
+            visits: 0,
+            total_reward: 0.0,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Pick an action for `player` by searching from `game`. `rng` seeds the
+/// playouts, so a seeded generator makes the choice reproducible.
+pub fn search<R: Rng>(
+    game: &GameState,
+    player: PlayerId,
+    config: &MctsConfig,
+    rng: &mut R,
+) -> Option<GameAction> {
+    search_until(game, player, config, None, rng)
+}
+
+/// [`search`], stopping at `deadline` (or the configured time limit, if
+/// sooner) with the best action so far. Returns None if the deadline
+/// passed before a single playout finished.
+pub fn search_until<R: Rng>(
+    game: &GameState,
+    player: PlayerId,
+    config: &MctsConfig,
+    deadline: Option<Instant>,
+    rng: &mut R,
+) -> Option<GameAction> {
+    let root_actions = candidate_actions(game, player);
+    if root_actions.len() <= 1 {
+        return root_actions.into_iter().next();
+    }
+
+    let limit = config.time_limit.map(|limit| Instant::now() + limit);
+    let stop = match (limit, deadline) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let trees = config.trees.max(1);
+    let per_tree = config.iterations.max(1).div_ceil(trees);
+    let seeds: Vec<u64> = (0..trees).map(|_| rng.gen()).collect();
+
+    let grow = |seed: u64| {
+        let mut rng = GameRng::new(seed);
+        let mut tree = vec![Node::new(player)];
+        for _ in 0..per_tree {
+            if stop.is_some_and(|stop| Instant::now() >= stop) {
+                break;
+            }
+            iterate(game, player, &mut tree, config, rng.gen());
+        }
+        tree[0]
+            .children
+            .iter()
+            .map(|(action, child)| (action.clone(), tree[*child].visits))
+            .collect::<Vec<_>>()
+    };
+    #[cfg(feature = "parallel")]
+    let roots: Vec<Vec<(GameAction, u32)>> = {
+        use rayon::prelude::*;
+        seeds.into_par_iter().map(grow).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let roots: Vec<Vec<(GameAction, u32)>> = seeds.into_iter().map(grow).collect();
+
+    // Add up the visits, keeping actions in the order first seen
+    let mut visits: Vec<(GameAction, u32)> = Vec::new();
+    for (action, count) in roots.into_iter().flatten() {
+        match visits.iter_mut().find(|(seen, _)| *seen == action) {
+            Some((_, total)) => *total += count,
+            None => visits.push((action, count)),
+        }
+    }
+    visits
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(action, _)| action)
+}
+
+/// One select / expand / playout / backpropagate pass
+fn iterate(
+    game: &GameState,
+    player: PlayerId,
+    tree: &mut Vec<Node>,
+    config: &MctsConfig,
+    seed: u64,
+) {
+    let mut rng = GameRng::new(seed);
+    let mut state = game.clone();
+    state.reseed(rng.gen());
+    state.dev_card_deck.shuffle(&mut rng);
+
+    let mut path = vec![0];
+    let mut node = 0;
+    let mut actor = player;
+    loop {
+        if matches!(state.phase, GamePhase::Finished { .. }) {
+            break;
+        }
+        let actions = candidate_actions(&state, actor);
+        if actions.is_empty() {
+            break;
+        }
+
+        let untried: Vec<&GameAction> = actions
+            .iter()
+            .filter(|a| !tree[node].children.iter().any(|(child, _)| child == *a))
+            .collect();
+        let expand = !untried.is_empty();
+        let action = match untried.choose(&mut rng) {
+            Some(action) => (*action).clone(),
+            None => select(tree, node, &actions, config.exploration),
+        };
+
+        if state.apply_action(actor, action.clone()).is_err() {
+            break;
+        }
+        let child = if expand {
+            let id = tree.len();
+            tree.push(Node::new(actor));
+            tree[node].children.push((action, id));
+            id
+        } else {
+            tree[node]
+                .children
+                .iter()
+                .find(|(child, _)| *child == action)
+                .map(|(_, id)| *id)
+                .unwrap()
+        };
+        path.push(child);
+        node = child;
+
+        match state.waiting_on().first() {
+            Some(next) if !expand => actor = *next,
+            _ => break,
+        }
+    }
+
+    rollout(&mut state, config.rollout_depth, &mut rng);
+    let reward = outcome(&state);
+    for id in path {
+        let node = &mut tree[id];
+        node.visits += 1;
+        node.total_reward += reward.get(node.player as usize).copied().unwrap_or(0.0);
+    }
+}
+
+/// The child among the currently legal actions with the best UCB score
+fn select(tree: &[Node], node: usize, actions: &[GameAction], exploration: f64) -> GameAction {
+    let parent_visits = (tree[node].visits.max(1) as f64).ln();
+    let ucb = |id: usize| {
+        let child = &tree[id];
+        let visits = child.visits.max(1) as f64;
+        child.total_reward / visits + exploration * (parent_visits / visits).sqrt()
+    };
+
+    tree[node]
+        .children
+        .iter()
+        .filter(|(action, _)| actions.contains(action))
+        .max_by(|(_, a), (_, b)| ucb(*a).total_cmp(&ucb(*b)))
+        .map(|(action, _)| action.clone())
+        .expect("every legal action has been expanded")
+}
+
+/// Play on with quick Medium bots for every seat
+fn rollout(state: &mut GameState, depth: u32, rng: &mut GameRng) {
+    let mut bots: Vec<Bot> = (0..state.players.len())
+        .map(|p| Bot::with_seed(p as PlayerId, BotDifficulty::Medium, rng.gen()))
+        .collect();
+
+    for _ in 0..depth {
+        let Some(&actor) = state.waiting_on().first() else {
+            break;
+        };
+        let Some(action) = bots[actor as usize].choose_action(state) else {
+            break;
+        };
+        if state.apply_action(actor, action).is_err() {
+            break;
+        }
+    }
+}
+
+/// Actions the search considers: the valid ones, with a concrete discard in
+/// place of the placeholder and without player trades
+pub(crate) fn candidate_actions(state: &GameState, player: PlayerId) -> Vec<GameAction> {
+    state
+        .valid_actions(player)
+        .into_iter()
+        .filter_map(|action| match action {
+            GameAction::DiscardCards(_) => {
+                Some(GameAction::DiscardCards(bot_discard(state, player)))
+            }
+            GameAction::ProposeTrade(_) | GameAction::CounterTrade(_) => None,
+            action => Some(action),
+        })
+        .collect()
+}
+
+/// Score for every seat: 1 for a win (shared by teammates), 0 for a loss,
+/// and otherwise the estimated win chance, kept below a win
+fn outcome(state: &GameState) -> Vec<f64> {
+    let winner = state.get_winner();
+    let estimate = win_probabilities(state);
+
+    (0..state.players.len() as PlayerId)
+        .map(|p| match winner {
+            Some(w) if w == p || state.are_teammates(w, p) => 1.0,
+            Some(_) => 0.0,
+            None => 0.9 * estimate[p as usize] as f64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::GameOptions;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn quick() -> MctsConfig {
+        MctsConfig {
+            iterations: 30,
+            rollout_depth: 30,
+            ..MctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_search_picks_valid_action_reproducibly() {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let game = GameState::new_with_seed(3, names, GameOptions::default(), 11);
+        let player = game.current_player;
+
+        let first = search(&game, player, &quick(), &mut StdRng::seed_from_u64(5)).unwrap();
+        assert!(game.valid_actions(player).contains(&first));
+        let again = search(&game, player, &quick(), &mut StdRng::seed_from_u64(5)).unwrap();
+        assert_eq!(first, again);
+
+        // Split between trees, searched in parallel or not
+        let split = MctsConfig {
+            trees: 3,
+            ..quick()
+        };
+        let first = search(&game, player, &split, &mut StdRng::seed_from_u64(5)).unwrap();
+        assert!(game.valid_actions(player).contains(&first));
+        let again = search(&game, player, &split, &mut StdRng::seed_from_u64(5)).unwrap();
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn test_expert_bot_plays_through_setup() {
+        let mut game =
+            GameState::new_with_seed(2, vec!["A".into(), "B".into()], GameOptions::default(), 4);
+        let mut bots: Vec<Bot> = (0..2)
+            .map(|p| Bot::with_seed(p, BotDifficulty::Expert, p as u64).with_mcts_config(quick()))
+            .collect();
+
+        while matches!(game.phase, GamePhase::Setup { .. }) {
+            let player = game.current_player;
+            let action = bots[player as usize].choose_action(&game).unwrap();
+            game.apply_action(player, action).unwrap();
+        }
+        assert_eq!(game.phase, GamePhase::PreRoll);
+    }
+
+    #[test]
+    fn test_expired_deadline_still_answers() {
+        let names = vec!["A".into(), "B".into(), "C".into()];
+        let game = GameState::new_with_seed(3, names, GameOptions::default(), 11);
+        let player = game.current_player;
+        let slow = MctsConfig {
+            iterations: u32::MAX,
+            ..MctsConfig::default()
+        };
+
+        // No time at all: the search gives up and the bot falls back
+        let past = Some(Instant::now());
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(search_until(&game, player, &slow, past, &mut rng), None);
+        let mut bot = Bot::with_seed(player, BotDifficulty::Expert, 1).with_mcts_config(slow);
+        let action = bot.choose_action_until(&game, past).unwrap();
+        assert!(game.valid_actions(player).contains(&action));
+
+        // A short budget cuts off an otherwise endless search
+        let soon = Some(Instant::now() + Duration::from_millis(50));
+        assert!(bot.choose_action_until(&game, soon).is_some());
+    }
+}
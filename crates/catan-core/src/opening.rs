@@ -0,0 +1,178 @@
+//! Setup placement evaluation for bots.
+//!
+//! The opening settlements shape most of a game, so the Hard bot judges them
+//! on more than the pips at one corner: what both settlements produce
+//! together, whether a harbor matches that production, and which strong
+//! spots a placement takes away from players who have yet to place.
+
+use crate::board::{Harbor, PlayerId, Resource};
+use crate::game::{GamePhase, GameState};
+use crate::hex::VertexCoord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Breakdown of what a setup spot is worth to one player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpeningScore {
+    /// Pips at the spot itself
+    pub production: i32,
+    /// Distinct resources from this and our earlier settlements, plus a
+    /// bonus for each complete pair: brick and lumber for roads, ore and
+    /// grain for cities
+    pub diversity: i32,
+    /// How well the spot's harbor matches what we'd produce, and how well
+    /// its production matches a harbor we already own
+    pub harbor: i32,
+    /// Pips of top spots (this one and its neighbors) that settling here
+    /// takes away from opponents still to place
+    pub denial: i32,
+}
+
+impl OpeningScore {
+    /// Single number to rank spots by
+    pub fn total(&self) -> i32 {
+        2 * self.production + self.diversity + self.harbor + self.denial
+    }
+}
+
+/// Score settling `vertex` during setup for `player`
+pub fn evaluate_opening(
+    game: &GameState,
+    player: PlayerId,
+    vertex: &VertexCoord,
+) -> OpeningScore {
+    let board = &game.board;
+    let mut settlements: Vec<VertexCoord> = board
+        .vertices_by_id()
+        .iter()
+        .filter(|v| board.get_vertex(v).owner() == Some(player))
+        .copied()
+        .collect();
+    settlements.push(*vertex);
+
+    // Pips per resource across all our settlements, this one included
+    let produced = |resource: Resource| -> i32 {
+        settlements
+            .iter()
+            .map(|v| board.vertex_resource_pips(v, resource) as i32)
+            .sum()
+    };
+
+    let makes: HashSet<Resource> =
+        Resource::ALL.into_iter().filter(|r| produced(*r) > 0).collect();
+    let pair = |a, b| if makes.contains(&a) && makes.contains(&b) { 3 } else { 0 };
+    let diversity = 3 * makes.len() as i32
+        + pair(Resource::Brick, Resource::Lumber)
+        + pair(Resource::Ore, Resource::Grain);
+
+    let mut harbor = match board.harbor_at(vertex) {
+        Some(Harbor::Specific(resource)) => produced(resource) / 2,
+        Some(Harbor::Generic) => Resource::ALL.into_iter().map(&produced).sum::<i32>() / 6,
+        None => 0,
+    };
+    for owned in board.player_harbors(player) {
+        if let Harbor::Specific(resource) = owned {
+            harbor += board.vertex_resource_pips(vertex, resource) as i32 / 2;
+        }
+    }
+
+    OpeningScore {
+        production: board.vertex_pip_count(vertex) as i32,
+        diversity,
+        harbor,
+        denial: denial(game, player, vertex),
+    }
+}
+
+/// Pips of the top open spots, among this one and its neighbors, that
+/// opponents still placing would lose to a settlement here
+fn denial(game: &GameState, player: PlayerId, vertex: &VertexCoord) -> i32 {
+    if !matches!(game.phase, GamePhase::Setup { .. }) {
+        return 0;
+    }
+
+    // Setup gives everyone two settlements
+    let rivals = game
+        .players
+        .iter()
+        .filter(|p| p.id != player && !game.are_teammates(p.id, player))
+        .filter(|p| game.setup_settlements_placed(p.id) < 2)
+        .count();
+    if rivals == 0 {
+        return 0;
+    }
+
+    let top = game.board.best_open_vertices(2 * rivals);
+    std::iter::once(*vertex)
+        .chain(vertex.adjacent_vertices())
+        .filter_map(|v| top.iter().find(|(spot, _)| *spot == v))
+        .map(|(_, pips)| *pips as i32 / 2)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::GameOptions;
+
+    fn setup_game(players: u8) -> GameState {
+        let names = (0..players).map(|i| format!("P{}", i)).collect();
+        GameState::new_with_seed(players, names, GameOptions::default(), 21)
+    }
+
+    #[test]
+    fn test_second_settlement_values_new_resources() {
+        let mut game = setup_game(2);
+        let (first, _) = game.board.best_open_vertices(1)[0];
+        let spots: Vec<VertexCoord> =
+            game.board.best_open_vertices(60).into_iter().map(|(v, _)| v).collect();
+        let before: Vec<OpeningScore> =
+            spots.iter().map(|v| evaluate_opening(&game, 0, v)).collect();
+
+        game.board.place_settlement(first, 0);
+        for (spot, alone) in spots.iter().zip(before) {
+            if !game.board.satisfies_distance_rule(spot) {
+                continue;
+            }
+            let paired = evaluate_opening(&game, 0, spot);
+            assert_eq!(paired.production, alone.production);
+            // The first settlement can only add resources
+            assert!(paired.diversity >= alone.diversity);
+            let adds = Resource::ALL.into_iter().any(|r| {
+                game.board.vertex_resource_pips(&first, r) > 0
+                    && game.board.vertex_resource_pips(spot, r) == 0
+            });
+            if adds {
+                assert!(paired.diversity > alone.diversity);
+            }
+        }
+    }
+
+    #[test]
+    fn test_harbor_matches_production() {
+        let game = setup_game(2);
+        let board = &game.board;
+        for vertex in board.vertices_by_id() {
+            let score = evaluate_opening(&game, 0, vertex);
+            match board.harbor_at(vertex) {
+                Some(Harbor::Specific(resource)) => assert_eq!(
+                    score.harbor > 0,
+                    board.vertex_resource_pips(vertex, resource) >= 2
+                ),
+                None => assert_eq!(score.harbor, 0),
+                Some(Harbor::Generic) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_denial_only_counts_players_still_to_place() {
+        let mut game = setup_game(3);
+        let (best, pips) = game.board.best_open_vertices(1)[0];
+        assert!(evaluate_opening(&game, 0, &best).denial >= pips as i32 / 2);
+
+        // Once everyone has placed there is no one left to deny
+        game.phase = GamePhase::PreRoll;
+        assert_eq!(evaluate_opening(&game, 0, &best).denial, 0);
+    }
+}
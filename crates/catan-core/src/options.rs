@@ -0,0 +1,91 @@
+//! Configurable game rules.
+//!
+//! `GameOptions` collects the optional rule variations a host can pick when
+//! creating a game. Every option defaults to the standard rules.
+
+use crate::barbarians::EventDie;
+use crate::board::{BoardGenConfig, BoardTemplate};
+use crate::clock::TimeControl;
+use crate::dice::DiceMode;
+use crate::player::ResourceHand;
+use serde::{Deserialize, Serialize};
+
+/// How a team wins in team play
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TeamVictory {
+    /// The team wins as soon as any member reaches the usual victory points
+    #[default]
+    Individual,
+    /// The team wins when its members' victory points add up to `points`
+    Combined { points: u32 },
+}
+
+/// Expansion rules layered over the base game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expansion {
+    /// The base game only
+    #[default]
+    Base,
+    /// Commodities, city improvements and progress cards (see
+    /// [`cities_knights`](crate::cities_knights)). Always rolls the event
+    /// die, with the standard faces unless `event_die` says otherwise.
+    CitiesAndKnights,
+}
+
+/// Rule options chosen when a game is created
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameOptions {
+    /// Victory points needed to win (10 when not set)
+    pub victory_points_to_win: Option<u32>,
+    /// End the game after this many turns (not counting setup). The player
+    /// with the most victory points wins; ties go to the most knights played,
+    /// then the most resource cards in hand, then the earliest seat.
+    pub max_turns: Option<u32>,
+    /// Per-player time banks; None plays without a clock
+    pub time_control: Option<TimeControl>,
+    /// Pass-and-play on one device: pause for `ConfirmNextPlayer` whenever
+    /// the turn moves to another player
+    pub hot_seat: bool,
+    /// Team number for each seat (e.g. `[0, 1, 0, 1]` for 2v2); empty plays
+    /// without teams. Teammates can't rob each other and share awards.
+    pub teams: Vec<u8>,
+    /// Win condition when playing with teams
+    pub team_victory: TeamVictory,
+    /// Put the pirate on the sea; a rolled 7 may move it instead of the robber
+    pub pirate: bool,
+    /// Keep the robber off the board until the first 7 rolled after this
+    /// turn. Earlier 7s only make players discard. A knight still brings
+    /// the robber in.
+    pub robber_from_turn: Option<u32>,
+    /// A knight sends the robber back to the desert instead of moving it
+    /// to rob someone
+    pub knight_returns_robber_to_desert: bool,
+    /// Roll an event die alongside the dice and sail the barbarians toward
+    /// the island on its ship faces
+    pub event_die: Option<EventDie>,
+    /// Expansion to play with
+    pub expansion: Expansion,
+    /// Real dice or a balanced deck of rolls
+    pub dice: DiceMode,
+    /// Cards every player starts with, on top of what setup pays out
+    pub starting_hand: ResourceHand,
+    /// Skip setup: play the beginner board with everyone already on the
+    /// quick-start positions (see
+    /// [`beginner_quick_start`](crate::scenario::beginner_quick_start))
+    pub quick_start: bool,
+    /// Board layout to generate
+    pub board_template: BoardTemplate,
+    /// Placement rules for random boards
+    pub board_gen: BoardGenConfig,
+}
+
+impl GameOptions {
+    /// Standard rules with a turn limit
+    pub fn with_max_turns(max_turns: u32) -> Self {
+        Self {
+            max_turns: Some(max_turns),
+            ..Self::default()
+        }
+    }
+}
@@ -6,11 +6,14 @@
 //! - Development card types and deck management
 //! - Building costs
 
+use crate::achievements::Achievement;
 use crate::board::{PlayerId, Resource};
+use crate::game::{GameError, Piece};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 /// Player color for UI rendering
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -64,19 +67,19 @@ impl DevelopmentCard {
         let mut deck = Vec::with_capacity(25);
 
         // 14 Knights
-        deck.extend(std::iter::repeat(DevelopmentCard::Knight).take(14));
+        deck.extend(std::iter::repeat_n(DevelopmentCard::Knight, 14));
 
         // 5 Victory Points
-        deck.extend(std::iter::repeat(DevelopmentCard::VictoryPoint).take(5));
+        deck.extend(std::iter::repeat_n(DevelopmentCard::VictoryPoint, 5));
 
         // 2 Road Building
-        deck.extend(std::iter::repeat(DevelopmentCard::RoadBuilding).take(2));
+        deck.extend(std::iter::repeat_n(DevelopmentCard::RoadBuilding, 2));
 
         // 2 Year of Plenty
-        deck.extend(std::iter::repeat(DevelopmentCard::YearOfPlenty).take(2));
+        deck.extend(std::iter::repeat_n(DevelopmentCard::YearOfPlenty, 2));
 
         // 2 Monopoly
-        deck.extend(std::iter::repeat(DevelopmentCard::Monopoly).take(2));
+        deck.extend(std::iter::repeat_n(DevelopmentCard::Monopoly, 2));
 
         deck
     }
@@ -92,6 +95,68 @@ impl DevelopmentCard {
     }
 }
 
+/// How many development cards of each type, e.g. those played so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DevCardCounts {
+    pub knight: u32,
+    pub victory_point: u32,
+    pub road_building: u32,
+    pub year_of_plenty: u32,
+    pub monopoly: u32,
+}
+
+impl DevCardCounts {
+    /// Count each card in a list
+    pub fn of<'a>(cards: impl IntoIterator<Item = &'a DevelopmentCard>) -> Self {
+        let mut counts = Self::default();
+        for &card in cards {
+            counts.add(card, 1);
+        }
+        counts
+    }
+
+    fn slot(&mut self, card: DevelopmentCard) -> &mut u32 {
+        match card {
+            DevelopmentCard::Knight => &mut self.knight,
+            DevelopmentCard::VictoryPoint => &mut self.victory_point,
+            DevelopmentCard::RoadBuilding => &mut self.road_building,
+            DevelopmentCard::YearOfPlenty => &mut self.year_of_plenty,
+            DevelopmentCard::Monopoly => &mut self.monopoly,
+        }
+    }
+
+    /// Count of one card type
+    pub fn get(&self, card: DevelopmentCard) -> u32 {
+        match card {
+            DevelopmentCard::Knight => self.knight,
+            DevelopmentCard::VictoryPoint => self.victory_point,
+            DevelopmentCard::RoadBuilding => self.road_building,
+            DevelopmentCard::YearOfPlenty => self.year_of_plenty,
+            DevelopmentCard::Monopoly => self.monopoly,
+        }
+    }
+
+    pub fn add(&mut self, card: DevelopmentCard, amount: u32) {
+        *self.slot(card) += amount;
+    }
+
+    /// These counts less `other`'s, stopping at zero
+    pub fn minus(&self, other: &DevCardCounts) -> DevCardCounts {
+        DevCardCounts {
+            knight: self.knight.saturating_sub(other.knight),
+            victory_point: self.victory_point.saturating_sub(other.victory_point),
+            road_building: self.road_building.saturating_sub(other.road_building),
+            year_of_plenty: self.year_of_plenty.saturating_sub(other.year_of_plenty),
+            monopoly: self.monopoly.saturating_sub(other.monopoly),
+        }
+    }
+
+    /// Cards of every type together
+    pub fn total(&self) -> u32 {
+        self.knight + self.victory_point + self.road_building + self.year_of_plenty + self.monopoly
+    }
+}
+
 /// A hand of resources
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResourceHand {
@@ -180,23 +245,35 @@ impl ResourceHand {
             && self.wool >= cost.wool
     }
 
-    /// Subtract a cost (panics if insufficient)
-    pub fn subtract(&mut self, cost: &ResourceHand) {
-        assert!(self.can_afford(cost), "Cannot afford this cost");
+    /// What this hand is missing to cover a cost
+    pub fn shortfall(&self, cost: &ResourceHand) -> ResourceHand {
+        ResourceHand {
+            brick: cost.brick.saturating_sub(self.brick),
+            lumber: cost.lumber.saturating_sub(self.lumber),
+            ore: cost.ore.saturating_sub(self.ore),
+            grain: cost.grain.saturating_sub(self.grain),
+            wool: cost.wool.saturating_sub(self.wool),
+        }
+    }
+
+    /// Subtract a cost, leaving the hand alone if it falls short
+    pub fn subtract(&mut self, cost: &ResourceHand) -> Result<(), GameError> {
+        if !self.can_afford(cost) {
+            return Err(GameError::InsufficientResources {
+                missing: self.shortfall(cost),
+            });
+        }
         self.brick -= cost.brick;
         self.lumber -= cost.lumber;
         self.ore -= cost.ore;
         self.grain -= cost.grain;
         self.wool -= cost.wool;
+        Ok(())
     }
 
     /// Try to subtract, returning false if insufficient
     pub fn try_subtract(&mut self, cost: &ResourceHand) -> bool {
-        if !self.can_afford(cost) {
-            return false;
-        }
-        self.subtract(cost);
-        true
+        self.subtract(cost).is_ok()
     }
 
     /// Remove a random resource (for robber stealing)
@@ -224,7 +301,7 @@ impl ResourceHand {
         }
 
         let resource = *available.choose(rng)?;
-        self.subtract(&ResourceHand::single(resource, 1));
+        self.subtract(&ResourceHand::single(resource, 1)).ok()?;
         Some(resource)
     }
 
@@ -257,6 +334,27 @@ impl ResourceHand {
     }
 }
 
+impl fmt::Display for ResourceHand {
+    /// The non-zero counts, like `1 Brick, 2 Ore`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for resource in Resource::ALL {
+            let count = self.get(resource);
+            if count > 0 {
+                if !first {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{} {:?}", count, resource)?;
+                first = false;
+            }
+        }
+        if first {
+            write!(f, "nothing")?;
+        }
+        Ok(())
+    }
+}
+
 /// Building costs
 pub mod costs {
     use super::ResourceHand;
@@ -283,7 +381,7 @@ pub mod costs {
 }
 
 /// A single player's state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Player {
     /// Player ID (0-3)
     pub id: PlayerId,
@@ -309,6 +407,12 @@ pub struct Player {
     pub cities_remaining: u32,
     /// Number of roads remaining to build
     pub roads_remaining: u32,
+    /// Whether this player has resigned (skipped in turn order)
+    #[serde(default)]
+    pub conceded: bool,
+    /// Milestones this player has already reached
+    #[serde(default)]
+    pub achievements: Vec<Achievement>,
 }
 
 impl Player {
@@ -327,6 +431,8 @@ impl Player {
             settlements_remaining: 5,
             cities_remaining: 4,
             roads_remaining: 15,
+            conceded: false,
+            achievements: Vec::new(),
         }
     }
 
@@ -389,28 +495,44 @@ impl Player {
     }
 
     /// Buy a road (deduct resources and piece)
-    pub fn buy_road(&mut self) {
-        self.resources.subtract(&costs::road());
-        self.roads_remaining -= 1;
+    pub fn buy_road(&mut self) -> Result<(), GameError> {
+        let left = take_piece(self.roads_remaining, Piece::Road)?;
+        self.pay(&costs::road())?;
+        self.roads_remaining = left;
+        Ok(())
     }
 
     /// Buy a settlement
-    pub fn buy_settlement(&mut self) {
-        self.resources.subtract(&costs::settlement());
-        self.settlements_remaining -= 1;
+    pub fn buy_settlement(&mut self) -> Result<(), GameError> {
+        let left = take_piece(self.settlements_remaining, Piece::Settlement)?;
+        self.pay(&costs::settlement())?;
+        self.settlements_remaining = left;
+        Ok(())
     }
 
     /// Buy a city (returns the settlement piece)
-    pub fn buy_city(&mut self) {
-        self.resources.subtract(&costs::city());
-        self.cities_remaining -= 1;
+    pub fn buy_city(&mut self) -> Result<(), GameError> {
+        let left = take_piece(self.cities_remaining, Piece::City)?;
+        self.pay(&costs::city())?;
+        self.cities_remaining = left;
         self.settlements_remaining += 1; // Settlement piece returned
+        Ok(())
     }
 
     /// Buy a development card
-    pub fn buy_dev_card(&mut self, card: DevelopmentCard) {
-        self.resources.subtract(&costs::development_card());
+    pub fn buy_dev_card(&mut self, card: DevelopmentCard) -> Result<(), GameError> {
+        self.pay(&costs::development_card())?;
         self.dev_cards_bought_this_turn.push(card);
+        Ok(())
+    }
+
+    /// Pay for something bought
+    fn pay(&mut self, cost: &ResourceHand) -> Result<(), GameError> {
+        self.resources
+            .subtract(cost)
+            .map_err(|_| GameError::CannotAfford {
+                missing: self.resources.shortfall(cost),
+            })
     }
 
     /// Called at end of turn - move bought cards to playable pile
@@ -420,7 +542,7 @@ impl Player {
 
     /// Check if player has a playable development card of given type
     pub fn has_playable_dev_card(&self, card_type: DevelopmentCard) -> bool {
-        self.dev_cards.iter().any(|c| *c == card_type)
+        self.dev_cards.contains(&card_type)
     }
 
     /// Play a development card (removes it from hand)
@@ -437,6 +559,13 @@ impl Player {
     }
 }
 
+/// One fewer of a piece, if any are left
+fn take_piece(remaining: u32, piece: Piece) -> Result<u32, GameError> {
+    remaining
+        .checked_sub(1)
+        .ok_or(GameError::NoPiecesRemaining(piece))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -461,7 +590,16 @@ mod tests {
     fn test_resource_hand_subtract() {
         let mut hand = ResourceHand::with_amounts(3, 3, 3, 3, 3);
         let cost = ResourceHand::with_amounts(1, 1, 1, 1, 1);
-        hand.subtract(&cost);
+        hand.subtract(&cost).unwrap();
+        assert_eq!(hand, ResourceHand::with_amounts(2, 2, 2, 2, 2));
+
+        let short = ResourceHand::with_amounts(0, 0, 3, 0, 0);
+        assert_eq!(
+            hand.subtract(&short),
+            Err(GameError::InsufficientResources {
+                missing: ResourceHand::with_amounts(0, 0, 1, 0, 0)
+            })
+        );
         assert_eq!(hand, ResourceHand::with_amounts(2, 2, 2, 2, 2));
     }
 
@@ -511,7 +649,7 @@ mod tests {
         player.resources = ResourceHand::with_amounts(5, 5, 5, 5, 5);
 
         assert!(player.can_afford_road());
-        player.buy_road();
+        player.buy_road().unwrap();
         assert_eq!(player.roads_remaining, 14);
         assert_eq!(player.resources.brick, 4);
         assert_eq!(player.resources.lumber, 4);
@@ -523,9 +661,26 @@ mod tests {
         player.resources = ResourceHand::with_amounts(5, 5, 5, 5, 5);
         player.settlements_remaining = 3; // Placed 2 settlements
 
-        player.buy_city();
+        player.buy_city().unwrap();
         assert_eq!(player.cities_remaining, 3);
         assert_eq!(player.settlements_remaining, 4); // Got one back
+
+        // Refused purchases leave the player as they were
+        player.resources = ResourceHand::new();
+        assert_eq!(
+            player.buy_city(),
+            Err(GameError::CannotAfford {
+                missing: ResourceHand::with_amounts(0, 0, 3, 2, 0)
+            })
+        );
+        player.resources = ResourceHand::with_amounts(5, 5, 5, 5, 5);
+        player.cities_remaining = 0;
+        assert_eq!(
+            player.buy_city(),
+            Err(GameError::NoPiecesRemaining(Piece::City))
+        );
+        assert_eq!(player.resources, ResourceHand::with_amounts(5, 5, 5, 5, 5));
+        assert_eq!(player.settlements_remaining, 4);
     }
 
     #[test]
@@ -533,7 +688,7 @@ mod tests {
         let mut player = Player::new(0, "Test".to_string());
         player.resources = ResourceHand::with_amounts(5, 5, 5, 5, 5);
 
-        player.buy_dev_card(DevelopmentCard::Knight);
+        player.buy_dev_card(DevelopmentCard::Knight).unwrap();
 
         // Card is in bought_this_turn, not playable yet
         assert!(!player.has_playable_dev_card(DevelopmentCard::Knight));
@@ -0,0 +1,72 @@
+//! Seedable random numbers for game state.
+//!
+//! `GameRng` lives inside `GameState` and is serialized with it, so a game
+//! created from a seed rolls the same dice and shuffles the same deck every
+//! time it is replayed, and a cloned state can be reseeded to sample a
+//! different future (as the MCTS bot does).
+
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// SplitMix64: tiny, fast, and its whole state is one integer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    /// Start a stream from a seed
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl Default for GameRng {
+    /// A stream from a random seed
+    fn default() -> Self {
+        Self::new(rand::thread_rng().gen())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        let rolls: Vec<u8> = (0..20).map(|_| a.gen_range(1..=6)).collect();
+        assert_eq!(rolls, (0..20).map(|_| b.gen_range(1..=6)).collect::<Vec<u8>>());
+        assert!(rolls.iter().any(|&r| r != rolls[0]));
+
+        let mut c = GameRng::new(43);
+        assert_ne!(GameRng::new(42).next_u64(), c.next_u64());
+    }
+}
@@ -0,0 +1,281 @@
+//! Declarative scenario definitions.
+//!
+//! A `Scenario` describes a complete map (tiles, fixed numbers, harbors),
+//! any pieces already on the board, and the rule options to play with, so
+//! custom maps can be shipped as data files. Load one with
+//! [`Scenario::from_json`] (or `Scenario::from_ron` with the `ron` feature)
+//! and start a game with `GameState::from_scenario`.
+
+use crate::board::{BoardError, HarborPlacement, PlayerId, TileType};
+use crate::hex::{EdgeCoord, EdgeDirection, HexCoord, VertexCoord, VertexDirection};
+use crate::options::GameOptions;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors from loading or applying a scenario
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Could not parse scenario: {0}")]
+    Parse(String),
+
+    #[error("Tile ({}, {}) is listed twice", .0.q, .0.r)]
+    DuplicateTile(HexCoord),
+
+    #[error("Resource tile ({}, {}) has no dice number", .0.q, .0.r)]
+    MissingNumber(HexCoord),
+
+    #[error("Robber must start on a land tile")]
+    RobberNotOnLand,
+
+    #[error("Starting piece is not on land")]
+    PieceNotOnLand,
+
+    #[error("Starting piece belongs to unknown player {0}")]
+    UnknownPlayer(PlayerId),
+
+    #[error("Player {0} starts with more pieces than they own")]
+    TooManyPieces(PlayerId),
+
+    #[error("Scenario games need 2-4 players, not {0}")]
+    PlayerCount(usize),
+
+    #[error("Teams must assign every player")]
+    TeamsMismatch,
+
+    #[error("Two starting pieces are in the same place")]
+    PiecesOverlap,
+
+    #[error("Scenario board is invalid: {0:?}")]
+    InvalidBoard(Vec<BoardError>),
+}
+
+/// A tile in a scenario layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioTile {
+    pub coord: HexCoord,
+    pub tile_type: TileType,
+    /// Fixed dice number (required for resource tiles)
+    #[serde(default)]
+    pub dice_number: Option<u8>,
+}
+
+/// A piece already on the board when the game starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartingPiece {
+    Settlement { player: PlayerId, vertex: VertexCoord },
+    City { player: PlayerId, vertex: VertexCoord },
+    Road { player: PlayerId, edge: EdgeCoord },
+}
+
+impl StartingPiece {
+    /// Owner of the piece
+    pub fn player(&self) -> PlayerId {
+        match self {
+            StartingPiece::Settlement { player, .. }
+            | StartingPiece::City { player, .. }
+            | StartingPiece::Road { player, .. } => *player,
+        }
+    }
+}
+
+/// Each seat's settlements on the beginner board for a quick start, as
+/// `(q, r, corner, road)`: the settlement on that corner of hex `(q, r)` and
+/// its road along that hex's edge. The second one pays out, as in setup.
+const BEGINNER_POSITIONS: [[(i32, i32, VertexDirection, EdgeDirection); 2]; 4] = {
+    use EdgeDirection::*;
+    use VertexDirection::{North as Top, South as Bottom};
+    [
+        [(0, -1, Top, NorthEast), (1, 0, Bottom, SouthEast)],
+        [(-2, 1, Top, NorthEast), (2, -1, Bottom, SouthWest)],
+        [(1, -1, Top, NorthEast), (-1, 1, Bottom, SouthWest)],
+        [(-1, 0, Top, NorthWest), (0, 1, Bottom, SouthEast)],
+    ]
+};
+
+/// The pieces of a beginner quick start for the first `player_count`
+/// seats, each seat's settlement and road in the order they are placed
+pub fn beginner_quick_start(player_count: u8) -> Vec<StartingPiece> {
+    (0..player_count.min(4))
+        .flat_map(|player| {
+            let positions = BEGINNER_POSITIONS[player as usize];
+            positions.into_iter().flat_map(move |(q, r, corner, road)| {
+                let hex = HexCoord::new(q, r);
+                [
+                    StartingPiece::Settlement {
+                        player,
+                        vertex: VertexCoord::new(hex, corner),
+                    },
+                    StartingPiece::Road {
+                        player,
+                        edge: EdgeCoord::new(hex, road),
+                    },
+                ]
+            })
+        })
+        .collect()
+}
+
+/// A complete scenario definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Tile layout. Missing neighbors of listed tiles are filled with ocean.
+    pub tiles: Vec<ScenarioTile>,
+    #[serde(default)]
+    pub harbors: Vec<HarborPlacement>,
+    /// Robber start; defaults to the first desert
+    #[serde(default)]
+    pub robber: Option<HexCoord>,
+    #[serde(default)]
+    pub starting_pieces: Vec<StartingPiece>,
+    /// Start straight in the first turn instead of initial placement
+    #[serde(default)]
+    pub skip_setup: bool,
+    /// Special rules and win conditions
+    #[serde(default)]
+    pub options: GameOptions,
+}
+
+impl Scenario {
+    /// Parse a scenario from JSON
+    pub fn from_json(json: &str) -> Result<Self, ScenarioError> {
+        serde_json::from_str(json).map_err(|e| ScenarioError::Parse(e.to_string()))
+    }
+
+    /// Parse a scenario from RON
+    #[cfg(feature = "ron")]
+    pub fn from_ron(source: &str) -> Result<Self, ScenarioError> {
+        ron::from_str(source).map_err(|e| ScenarioError::Parse(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, Resource, VertexBuilding};
+    use crate::game::{GamePhase, GameState};
+    use crate::hex::VertexDirection;
+
+    const ISLAND: &str = r#"{
+        "name": "Little Island",
+        "tiles": [
+            { "coord": { "q": 0, "r": 0 }, "tile_type": "Desert" },
+            { "coord": { "q": 1, "r": 0 }, "tile_type": { "Resource": "Brick" }, "dice_number": 6 },
+            { "coord": { "q": 0, "r": 1 }, "tile_type": { "Resource": "Grain" }, "dice_number": 8 }
+        ],
+        "starting_pieces": [
+            { "City": { "player": 1, "vertex": { "hex": { "q": 1, "r": 0 }, "direction": "South" } } }
+        ],
+        "skip_setup": true,
+        "options": { "victory_points_to_win": 6 }
+    }"#;
+
+    #[test]
+    fn test_load_scenario_board() {
+        let scenario = Scenario::from_json(ISLAND).unwrap();
+        let board = Board::from_scenario(&scenario).unwrap();
+
+        assert_eq!(board.land_tiles().count(), 3);
+        assert_eq!(board.robber_location(), Some(HexCoord::new(0, 0)));
+        let brick = board.get_tile(&HexCoord::new(1, 0)).unwrap();
+        assert_eq!(brick.resource(), Some(Resource::Brick));
+        assert_eq!(brick.dice_number, Some(6));
+        assert!(board.ocean_tiles().count() > 0);
+    }
+
+    #[test]
+    fn test_game_from_scenario() {
+        let scenario = Scenario::from_json(ISLAND).unwrap();
+        let game = GameState::from_scenario(&scenario, vec!["A".into(), "B".into()]).unwrap();
+
+        let city = VertexCoord::new(HexCoord::new(1, 0), VertexDirection::South);
+        assert_eq!(game.board.get_vertex(&city), VertexBuilding::City(1));
+        assert_eq!(game.total_victory_points(1), 2);
+        assert_eq!(game.phase, GamePhase::PreRoll);
+        assert_eq!(game.options.victory_points_to_win, Some(6));
+    }
+
+    #[test]
+    fn test_scenario_rejects_unknown_player() {
+        let scenario = Scenario::from_json(ISLAND).unwrap();
+        let result = GameState::from_scenario(&scenario, vec!["A".into()]);
+        assert!(matches!(result, Err(ScenarioError::UnknownPlayer(1))));
+    }
+
+    #[test]
+    fn test_scenario_rejects_bad_player_counts_and_teams() {
+        let mut scenario = Scenario::from_json(ISLAND).unwrap();
+        scenario.starting_pieces.clear();
+        let names = |n| (0..n).map(|i| format!("P{}", i)).collect::<Vec<_>>();
+        assert!(matches!(
+            GameState::from_scenario(&scenario, names(1)),
+            Err(ScenarioError::PlayerCount(1))
+        ));
+        assert!(matches!(
+            GameState::from_scenario(&scenario, names(5)),
+            Err(ScenarioError::PlayerCount(5))
+        ));
+        assert!(matches!(
+            GameState::from_scenario(&scenario, names(257)),
+            Err(ScenarioError::PlayerCount(257))
+        ));
+
+        scenario.options.teams = vec![0, 1, 0];
+        assert!(matches!(
+            GameState::from_scenario(&scenario, names(2)),
+            Err(ScenarioError::TeamsMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_scenario_rejects_overlapping_and_crowded_pieces() {
+        let mut scenario = Scenario::from_json(ISLAND).unwrap();
+        let vertex = VertexCoord::new(HexCoord::new(1, 0), VertexDirection::South);
+        scenario
+            .starting_pieces
+            .push(StartingPiece::Settlement { player: 0, vertex });
+        let result = GameState::from_scenario(&scenario, vec!["A".into(), "B".into()]);
+        assert!(matches!(result, Err(ScenarioError::PiecesOverlap)));
+
+        // Right next to the city
+        let vertex = vertex.adjacent_vertices()[0];
+        scenario.starting_pieces[1] = StartingPiece::Settlement { player: 0, vertex };
+        let result = GameState::from_scenario(&scenario, vec!["A".into(), "B".into()]);
+        assert!(matches!(result, Err(ScenarioError::InvalidBoard(_))));
+    }
+
+    #[test]
+    fn test_scenario_rejects_more_pieces_than_a_player_owns() {
+        let mut scenario = Scenario::from_json(ISLAND).unwrap();
+        let city = scenario.starting_pieces[0];
+        scenario.starting_pieces.extend(std::iter::repeat_n(city, 4));
+        let result = GameState::from_scenario(&scenario, vec!["A".into(), "B".into()]);
+        assert!(matches!(result, Err(ScenarioError::TooManyPieces(1))));
+    }
+
+    #[test]
+    fn test_scenario_without_a_desert_starts_the_robber_off_the_board() {
+        let mut scenario = Scenario::from_json(ISLAND).unwrap();
+        scenario.tiles.retain(|t| t.tile_type != TileType::Desert);
+        let board = Board::from_scenario(&scenario).unwrap();
+
+        assert_eq!(board.robber_location(), None);
+        assert!(board.land_tiles().all(|t| !t.has_robber));
+        assert!(board.validate().is_ok());
+    }
+
+    #[test]
+    fn test_scenario_requires_numbers_on_resources() {
+        let json = r#"{
+            "name": "Broken",
+            "tiles": [{ "coord": { "q": 0, "r": 0 }, "tile_type": { "Resource": "Ore" } }]
+        }"#;
+        let scenario = Scenario::from_json(json).unwrap();
+        assert!(matches!(
+            Board::from_scenario(&scenario),
+            Err(ScenarioError::MissingNumber(_))
+        ));
+    }
+}
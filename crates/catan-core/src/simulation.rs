@@ -0,0 +1,408 @@
+//! Self-play: many bot-only games run side by side.
+//!
+//! Used to compare bots (win rate by seat, how long games take) and as an
+//! engine regression check. Every game is seeded, so any odd result can be
+//! replayed with [`simulate_game`]. A bot picking an action the engine
+//! rejects, or the engine panicking, is recorded rather than stopping the run.
+
+use crate::actions::GameEvent;
+use crate::analysis::{GameRecord, ReplayRecord};
+use crate::board::PlayerId;
+use crate::bot::{Bot, BotDifficulty, BotPersonality, BotPlayer};
+use crate::game::GameState;
+use crate::options::GameOptions;
+use crate::player::ResourceHand;
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(feature = "parallel"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(feature = "parallel"))]
+use std::sync::Mutex;
+#[cfg(not(feature = "parallel"))]
+use std::thread;
+
+/// The bot playing one seat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimSeat {
+    pub difficulty: BotDifficulty,
+    #[serde(default)]
+    pub personality: BotPersonality,
+}
+
+impl SimSeat {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        Self {
+            difficulty,
+            personality: BotPersonality::default(),
+        }
+    }
+}
+
+/// What to simulate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// One bot per seat, in turn order
+    pub seats: Vec<SimSeat>,
+    /// Number of games to play
+    pub games: u32,
+    /// Seed of the first game; game `i` uses `first_seed + i`
+    pub first_seed: u64,
+    /// Rules for every game
+    pub options: GameOptions,
+    /// Actions after which an unfinished game is abandoned
+    pub max_actions: u32,
+    /// Worker threads; 0 uses every available core
+    pub threads: usize,
+    /// Keep every game's moves, to write out or replay
+    #[serde(default)]
+    pub record: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seats: vec![SimSeat::new(BotDifficulty::Hard); 4],
+            games: 100,
+            first_seed: 0,
+            options: GameOptions::default(),
+            max_actions: 5000,
+            threads: 0,
+            record: false,
+        }
+    }
+}
+
+/// How a simulated game ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimOutcome {
+    /// Someone won, or the game otherwise reached its end
+    Finished { winner: Option<PlayerId> },
+    /// A bot had nothing to play although the game was waiting on it
+    Stalled { player: PlayerId },
+    /// `max_actions` ran out first
+    ActionLimit,
+    /// The engine refused an action a bot picked from its valid actions
+    Rejected { player: PlayerId, error: String },
+    /// The engine or a bot panicked
+    Panicked { message: String },
+}
+
+/// Result of one simulated game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimGame {
+    pub seed: u64,
+    pub outcome: SimOutcome,
+    /// Turns started (the setup round counts as none)
+    pub turns: u32,
+    /// Actions applied
+    pub actions: u32,
+    /// Cards each seat collected from dice rolls
+    #[serde(default)]
+    pub produced: Vec<ResourceHand>,
+    /// The moves, when the run was recording
+    #[serde(default)]
+    pub record: Option<ReplayRecord>,
+}
+
+/// Totals over a simulation run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationReport {
+    /// Every game, in seed order
+    pub games: Vec<SimGame>,
+    /// Wins by seat
+    pub wins: Vec<u32>,
+    /// Games that finished, with or without a winner
+    pub finished: u32,
+    /// Mean turns and actions of finished games
+    pub average_turns: f64,
+    pub average_actions: f64,
+    /// Cards each seat collected from dice rolls over every game
+    #[serde(default)]
+    pub produced: Vec<ResourceHand>,
+}
+
+impl SimulationReport {
+    /// Share of all games won by `seat`
+    pub fn win_rate(&self, seat: PlayerId) -> f64 {
+        let wins = self.wins.get(seat as usize).copied().unwrap_or(0);
+        if self.games.is_empty() {
+            0.0
+        } else {
+            wins as f64 / self.games.len() as f64
+        }
+    }
+
+    /// Games that ended with a rejected action or a panic
+    pub fn failures(&self) -> impl Iterator<Item = &SimGame> {
+        self.games.iter().filter(|g| {
+            matches!(
+                g.outcome,
+                SimOutcome::Rejected { .. } | SimOutcome::Panicked { .. }
+            )
+        })
+    }
+
+    fn from_games(games: Vec<SimGame>, seats: usize) -> Self {
+        let mut wins = vec![0; seats];
+        let mut produced = vec![ResourceHand::new(); seats];
+        let (mut finished, mut turns, mut actions) = (0, 0, 0);
+        for game in &games {
+            for (total, hand) in produced.iter_mut().zip(&game.produced) {
+                total.add_hand(hand);
+            }
+            if let SimOutcome::Finished { winner } = game.outcome {
+                finished += 1;
+                turns += game.turns as u64;
+                actions += game.actions as u64;
+                if let Some(w) = winner {
+                    wins[w as usize] += 1;
+                }
+            }
+        }
+        let average = |total: u64| {
+            if finished > 0 {
+                total as f64 / finished as f64
+            } else {
+                0.0
+            }
+        };
+
+        Self {
+            wins,
+            finished,
+            average_turns: average(turns),
+            average_actions: average(actions),
+            produced,
+            games,
+        }
+    }
+}
+
+/// Play every game in `config` across worker threads
+pub fn run_simulation(config: &SimulationConfig) -> SimulationReport {
+    let games = run_parallel(config.games as usize, config.threads, |i| {
+        simulate_game(config, config.first_seed + i as u64)
+    });
+    SimulationReport::from_games(games, config.seats.len())
+}
+
+/// Play one game from `seed` with the configured seats
+pub fn simulate_game(config: &SimulationConfig, seed: u64) -> SimGame {
+    let bots = config
+        .seats
+        .iter()
+        .enumerate()
+        .map(|(i, seat)| {
+            let bot_seed = seed.wrapping_mul(31).wrapping_add(i as u64);
+            let bot = Bot::with_seed(i as PlayerId, seat.difficulty, bot_seed)
+                .with_personality(seat.personality);
+            Box::new(bot) as Box<dyn BotPlayer>
+        })
+        .collect();
+    play_game(&config.options, seed, bots, config.max_actions, config.record)
+}
+
+/// Run `jobs` jobs on up to `threads` worker threads (0 for one per core)
+/// and return their results in job order. Uses rayon's pool with the
+/// `parallel` feature and scoped threads otherwise.
+pub(crate) fn run_parallel<T, F>(jobs: usize, threads: usize, job: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        let run = || (0..jobs).into_par_iter().map(&job).collect();
+        match threads {
+            0 => run(),
+            n => match rayon::ThreadPoolBuilder::new().num_threads(n).build() {
+                Ok(pool) => pool.install(run),
+                Err(_) => run(),
+            },
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(jobs));
+
+        thread::scope(|scope| {
+            for _ in 0..threads.min(jobs) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= jobs {
+                        break;
+                    }
+                    let result = job(i);
+                    results.lock().unwrap_or_else(|e| e.into_inner()).push((i, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+/// Play one game between `bots`, one per seat in turn order, catching
+/// panics from the engine or the bots
+pub(crate) fn play_game(
+    options: &GameOptions,
+    seed: u64,
+    mut bots: Vec<Box<dyn BotPlayer>>,
+    max_actions: u32,
+    record: bool,
+) -> SimGame {
+    let mut progress = Progress {
+        turns: 0,
+        actions: 0,
+        produced: vec![ResourceHand::new(); bots.len()],
+        record: None,
+    };
+    let played = panic::catch_unwind(AssertUnwindSafe(|| {
+        play(options, seed, &mut bots, max_actions, record, &mut progress)
+    }));
+    let outcome = played.unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        SimOutcome::Panicked { message }
+    });
+
+    SimGame {
+        seed,
+        outcome,
+        turns: progress.turns,
+        actions: progress.actions,
+        produced: progress.produced,
+        record: progress.record.as_ref().map(ReplayRecord::new),
+    }
+}
+
+/// How far a game got, kept outside it so a panic doesn't lose it
+struct Progress {
+    turns: u32,
+    actions: u32,
+    produced: Vec<ResourceHand>,
+    record: Option<GameRecord>,
+}
+
+fn play(
+    options: &GameOptions,
+    seed: u64,
+    bots: &mut [Box<dyn BotPlayer>],
+    max_actions: u32,
+    record: bool,
+    progress: &mut Progress,
+) -> SimOutcome {
+    let count = bots.len() as u8;
+    let names = (0..count).map(|i| format!("Bot {}", i + 1)).collect();
+    let mut game = GameState::new_with_seed(count, names, options.clone(), seed);
+    if record {
+        progress.record = Some(GameRecord::new(&game));
+    }
+
+    while progress.actions < max_actions {
+        progress.turns = game.turn_number;
+        if game.is_finished() {
+            return SimOutcome::Finished {
+                winner: game.get_winner(),
+            };
+        }
+        let Some(player) = game.waiting_on().into_iter().next() else {
+            return SimOutcome::Stalled {
+                player: game.current_player,
+            };
+        };
+        let Some(action) = bots[player as usize].choose_action(&game) else {
+            return SimOutcome::Stalled { player };
+        };
+        match game.apply_action(player, action.clone()) {
+            Ok(events) => {
+                for event in &events {
+                    if let GameEvent::ResourcesDistributed { distributions } = event {
+                        for &(seat, resource, amount) in distributions {
+                            if let Some(hand) = progress.produced.get_mut(seat as usize) {
+                                hand.add(resource, amount);
+                            }
+                        }
+                    }
+                }
+                if let Some(record) = &mut progress.record {
+                    record.push(player, action);
+                }
+                for bot in bots.iter_mut() {
+                    bot.observe(&game, &events);
+                }
+            }
+            Err(error) => {
+                return SimOutcome::Rejected {
+                    player,
+                    error: error.to_string(),
+                }
+            }
+        }
+        progress.actions += 1;
+    }
+
+    progress.turns = game.turn_number;
+    if game.is_finished() {
+        SimOutcome::Finished {
+            winner: game.get_winner(),
+        }
+    } else {
+        SimOutcome::ActionLimit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulation_is_reproducible_and_clean() {
+        let config = SimulationConfig {
+            seats: vec![
+                SimSeat::new(BotDifficulty::Hard),
+                SimSeat::new(BotDifficulty::Medium),
+                SimSeat::new(BotDifficulty::Easy),
+            ],
+            games: 6,
+            first_seed: 100,
+            threads: 3,
+            ..SimulationConfig::default()
+        };
+
+        let report = run_simulation(&config);
+        assert_eq!(report.games.len(), 6);
+        assert_eq!(report.failures().count(), 0);
+        assert_eq!(report.wins.iter().sum::<u32>(), report.finished);
+        assert_eq!(report.finished, 6);
+        assert!(report.average_turns > 0.0);
+
+        // Each game only depends on its seed, not on the thread it ran on
+        let again = simulate_game(&config, 102);
+        assert_eq!(report.games[2], again);
+        assert!(report.produced.iter().all(|hand| hand.total() > 0));
+        assert!(again.record.is_none());
+
+        // A recorded game replays to the same end
+        let recorded = simulate_game(&SimulationConfig { record: true, ..config }, 102);
+        let record = recorded.record.unwrap();
+        assert_eq!(record.moves.len() as u32, recorded.actions);
+        let end = record.to_record().replay().unwrap();
+        assert_eq!(
+            SimOutcome::Finished { winner: end.get_winner() },
+            recorded.outcome
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Display names for resources, tiles and awards.
+//!
+//! The engine only knows `Resource::Brick` and friends. A [`ThemePack`]
+//! maps them to the names players see, so the same game can be dressed as
+//! Singapore (the default), the classic island, or anything a host loads
+//! with [`ThemePack::from_json`]. Names a pack leaves out fall back to the
+//! Singapore ones.
+
+use crate::board::{Resource, TileType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Awards and achievements with a display name, by key
+const SINGAPORE_ACHIEVEMENTS: &[(&str, &str)] = &[
+    ("longest_road", "Longest Road"),
+    ("largest_army", "Largest Army"),
+    ("first_city", "Upgraded to Condo"),
+    ("monopoly_haul", "Chope Everything"),
+    ("won_without_army", "Win Liao, No Army"),
+    ("kiasu", "Kiasu"),
+];
+
+/// Display names for one theme
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemePack {
+    pub name: String,
+    /// Resource card names
+    pub resources: BTreeMap<Resource, String>,
+    /// Labels for each resource's tiles
+    pub resource_tiles: BTreeMap<Resource, String>,
+    pub desert: String,
+    pub ocean: String,
+    /// Award and achievement names, by key (e.g. `longest_road`)
+    pub achievements: BTreeMap<String, String>,
+}
+
+impl Default for ThemePack {
+    fn default() -> Self {
+        Self::singapore()
+    }
+}
+
+impl ThemePack {
+    /// Singapore landmarks, the game's own theme
+    pub fn singapore() -> Self {
+        let names = |name: fn(&Resource) -> &'static str| {
+            Resource::ALL
+                .iter()
+                .map(|r| (*r, name(r).to_string()))
+                .collect()
+        };
+        Self {
+            name: "Singapore".to_string(),
+            resources: names(Resource::singapore_name),
+            resource_tiles: names(Resource::singapore_name),
+            desert: "Bukit Timah".to_string(),
+            ocean: "Singapore Strait".to_string(),
+            achievements: SINGAPORE_ACHIEVEMENTS
+                .iter()
+                .map(|(key, name)| (key.to_string(), name.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The names from the original board game
+    pub fn classic() -> Self {
+        let pairs = |names: [&str; 5]| {
+            Resource::ALL
+                .into_iter()
+                .zip(names)
+                .map(|(r, name)| (r, name.to_string()))
+                .collect()
+        };
+        Self {
+            name: "Classic".to_string(),
+            resources: pairs(["Brick", "Lumber", "Ore", "Grain", "Wool"]),
+            resource_tiles: pairs(["Hills", "Forest", "Mountains", "Fields", "Pasture"]),
+            desert: "Desert".to_string(),
+            ocean: "Sea".to_string(),
+            ..Self::singapore()
+        }
+    }
+
+    /// Load a pack from JSON. Anything left out is taken from Singapore.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// What a resource card is called
+    pub fn resource_name(&self, resource: Resource) -> &str {
+        self.resources
+            .get(&resource)
+            .map_or(resource.singapore_name(), String::as_str)
+    }
+
+    /// What a tile is called
+    pub fn tile_label(&self, tile_type: TileType) -> &str {
+        match tile_type {
+            TileType::Resource(resource) => self
+                .resource_tiles
+                .get(&resource)
+                .map_or(resource.singapore_name(), String::as_str),
+            TileType::Desert => &self.desert,
+            TileType::Ocean => &self.ocean,
+        }
+    }
+
+    /// What an award or achievement is called, or its key if no pack
+    /// names it
+    pub fn achievement_name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.achievements
+            .get(key)
+            .map(String::as_str)
+            .or_else(|| {
+                SINGAPORE_ACHIEVEMENTS
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, name)| *name)
+            })
+            .unwrap_or(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_pack_fills_gaps_from_singapore() {
+        let pack = ThemePack::from_json(
+            r#"{
+                "name": "Kopitiam",
+                "resources": { "Grain": "Kaya Toast" },
+                "desert": "Void Deck",
+                "achievements": { "largest_army": "Biggest Auntie Gang" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(pack.resource_name(Resource::Grain), "Kaya Toast");
+        assert_eq!(pack.resource_name(Resource::Ore), "Jurong Industrial");
+        assert_eq!(pack.tile_label(TileType::Desert), "Void Deck");
+        assert_eq!(pack.tile_label(TileType::Ocean), "Singapore Strait");
+        assert_eq!(pack.achievement_name("largest_army"), "Biggest Auntie Gang");
+        assert_eq!(pack.achievement_name("longest_road"), "Longest Road");
+        assert_eq!(pack.achievement_name("unknown"), "unknown");
+
+        let classic = ThemePack::classic();
+        assert_eq!(classic.tile_label(TileType::Resource(Resource::Brick)), "Hills");
+        assert_eq!(ThemePack::default(), ThemePack::singapore());
+    }
+}
@@ -0,0 +1,256 @@
+//! Round-robin tournaments between bot configurations.
+//!
+//! Every pair of entrants plays a set of two-player games, swapping seats
+//! each game so neither side keeps the first move. Results feed Elo-style
+//! ratings, updated game by game in a fixed order so a tournament with the
+//! same seeds always ends with the same table however many threads ran it.
+
+use crate::board::PlayerId;
+use crate::bot::{Bot, BotPersonality, BotPlayer};
+use crate::options::GameOptions;
+use crate::simulation::{play_game, run_parallel, SimGame, SimOutcome, SimSeat};
+use serde::{Deserialize, Serialize};
+
+/// Builds an entrant's bot for a seat, from a seed
+pub type BotFactory = Box<dyn Fn(PlayerId, u64) -> Box<dyn BotPlayer> + Send + Sync>;
+
+/// Rating every entrant starts from
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// One side of the tournament
+pub struct Entrant {
+    pub name: String,
+    factory: BotFactory,
+}
+
+impl Entrant {
+    /// Enter any [`BotPlayer`], built fresh for every game
+    pub fn new<F>(name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(PlayerId, u64) -> Box<dyn BotPlayer> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            factory: Box::new(factory),
+        }
+    }
+
+    /// Enter the built-in [`Bot`] at a difficulty and personality
+    pub fn builtin(seat: SimSeat) -> Self {
+        let name = if seat.personality == BotPersonality::default() {
+            format!("{:?}", seat.difficulty)
+        } else {
+            format!("{:?} ({:?})", seat.difficulty, seat.personality)
+        };
+        Self::new(name, move |player, seed| {
+            let bot = Bot::with_seed(player, seat.difficulty, seed);
+            Box::new(bot.with_personality(seat.personality))
+        })
+    }
+}
+
+/// How the tournament is played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentConfig {
+    /// Games between every pair of entrants
+    pub games_per_pairing: u32,
+    /// Seed of the first game; later games count up from it
+    pub first_seed: u64,
+    /// Rules for every game; the player count is always two
+    pub options: GameOptions,
+    /// Actions after which an unfinished game counts as a draw
+    pub max_actions: u32,
+    /// Worker threads; 0 uses every available core
+    pub threads: usize,
+    /// How far one game moves the ratings
+    pub k_factor: f64,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            games_per_pairing: 20,
+            first_seed: 0,
+            options: GameOptions::default(),
+            max_actions: 5000,
+            threads: 0,
+            k_factor: 16.0,
+        }
+    }
+}
+
+/// Head-to-head results between two entrants (by index)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Matchup {
+    pub first: usize,
+    pub second: usize,
+    pub first_wins: u32,
+    pub second_wins: u32,
+    /// Games nobody won, including ones that hit the action limit
+    pub draws: u32,
+}
+
+/// Final table of a tournament
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TournamentReport {
+    /// Entrant names, in entry order
+    pub names: Vec<String>,
+    /// Rating of each entrant, in entry order
+    pub ratings: Vec<f64>,
+    pub matchups: Vec<Matchup>,
+    /// Games that ended with a rejected action or a panic; they count as
+    /// a loss for the side at fault and a draw otherwise
+    pub failures: Vec<SimGame>,
+}
+
+impl TournamentReport {
+    /// Names and ratings, strongest first
+    pub fn standings(&self) -> Vec<(&str, f64)> {
+        let mut table: Vec<(&str, f64)> = self
+            .names
+            .iter()
+            .map(String::as_str)
+            .zip(self.ratings.iter().copied())
+            .collect();
+        table.sort_by(|a, b| b.1.total_cmp(&a.1));
+        table
+    }
+}
+
+/// Play every pairing of `entrants` and rate them
+pub fn run_tournament(entrants: &[Entrant], config: &TournamentConfig) -> TournamentReport {
+    let mut matchups: Vec<Matchup> = (0..entrants.len())
+        .flat_map(|a| (a + 1..entrants.len()).map(move |b| (a, b)))
+        .map(|(first, second)| Matchup {
+            first,
+            second,
+            ..Matchup::default()
+        })
+        .collect();
+
+    let per_pairing = config.games_per_pairing as usize;
+    let games = run_parallel(matchups.len() * per_pairing, config.threads, |i| {
+        let matchup = &matchups[i / per_pairing];
+        let swapped = i % 2 == 1;
+        let seats = if swapped {
+            [matchup.second, matchup.first]
+        } else {
+            [matchup.first, matchup.second]
+        };
+        let seed = config.first_seed + i as u64;
+        let bots = seats
+            .iter()
+            .enumerate()
+            .map(|(seat, entrant)| {
+                let bot_seed = seed.wrapping_mul(31).wrapping_add(seat as u64);
+                (entrants[*entrant].factory)(seat as PlayerId, bot_seed)
+            })
+            .collect();
+        (seats, play_game(&config.options, seed, bots, config.max_actions, false))
+    });
+
+    let mut ratings = vec![INITIAL_RATING; entrants.len()];
+    let mut failures = Vec::new();
+    for (i, (seats, game)) in games.into_iter().enumerate() {
+        // Score for seat 0: 1 for a win, 0 for a loss, a half otherwise
+        let score = match &game.outcome {
+            SimOutcome::Finished { winner: Some(0) } => 1.0,
+            SimOutcome::Finished { winner: Some(_) } => 0.0,
+            SimOutcome::Rejected { player, .. } | SimOutcome::Stalled { player } => {
+                if *player == 0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            _ => 0.5,
+        };
+        if matches!(
+            game.outcome,
+            SimOutcome::Rejected { .. } | SimOutcome::Panicked { .. }
+        ) {
+            failures.push(game);
+        }
+
+        let matchup = &mut matchups[i / per_pairing];
+        let first_score = if seats[0] == matchup.first { score } else { 1.0 - score };
+        if first_score == 1.0 {
+            matchup.first_wins += 1;
+        } else if first_score == 0.0 {
+            matchup.second_wins += 1;
+        } else {
+            matchup.draws += 1;
+        }
+
+        let [a, b] = seats;
+        let expected = 1.0 / (1.0 + 10f64.powf((ratings[b] - ratings[a]) / 400.0));
+        let change = config.k_factor * (score - expected);
+        ratings[a] += change;
+        ratings[b] -= change;
+    }
+
+    TournamentReport {
+        names: entrants.iter().map(|e| e.name.clone()).collect(),
+        ratings,
+        matchups,
+        failures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::GameAction;
+    use crate::bot::BotDifficulty;
+    use crate::game::GameState;
+
+    /// Ends its turn whenever it can and otherwise does the least it must
+    struct Idle(PlayerId);
+
+    impl BotPlayer for Idle {
+        fn player_id(&self) -> PlayerId {
+            self.0
+        }
+
+        fn choose_action(&mut self, game: &GameState) -> Option<GameAction> {
+            let actions = game.valid_actions(self.0);
+            if actions.contains(&GameAction::EndTurn) {
+                return Some(GameAction::EndTurn);
+            }
+            if actions.iter().any(|a| matches!(a, GameAction::DiscardCards(_))) {
+                return Some(GameAction::DiscardCards(self.choose_discard(game)));
+            }
+            actions.into_iter().next()
+        }
+    }
+
+    #[test]
+    fn test_stronger_bot_rates_higher_and_results_repeat() {
+        let entrants = || {
+            vec![
+                Entrant::new("Idle", |player, _| Box::new(Idle(player)) as Box<dyn BotPlayer>),
+                Entrant::builtin(SimSeat::new(BotDifficulty::Hard)),
+            ]
+        };
+        let config = TournamentConfig {
+            games_per_pairing: 4,
+            max_actions: 3000,
+            threads: 2,
+            ..TournamentConfig::default()
+        };
+
+        let report = run_tournament(&entrants(), &config);
+        assert_eq!(report.names, vec!["Idle", "Hard"]);
+        assert_eq!(report.matchups.len(), 1);
+        let matchup = &report.matchups[0];
+        assert_eq!(matchup.first_wins + matchup.second_wins + matchup.draws, 4);
+        assert!(matchup.second_wins > matchup.first_wins);
+        assert_eq!(report.standings()[0].0, "Hard");
+        assert!(report.failures.is_empty());
+
+        // Ratings only move between the two, and replay exactly
+        assert!((report.ratings.iter().sum::<f64>() - 2.0 * INITIAL_RATING).abs() < 1e-9);
+        let again = run_tournament(&entrants(), &config);
+        assert_eq!(report.ratings, again.ratings);
+    }
+}
@@ -5,12 +5,22 @@
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "wasm")]
+use crate::analysis::{analyze_game, GameRecord, ReplayRecord};
 #[cfg(feature = "wasm")]
 use crate::game::GameState;
 #[cfg(feature = "wasm")]
 use crate::actions::GameAction;
 #[cfg(feature = "wasm")]
-use crate::bot::{Bot, BotDifficulty};
+use crate::bot::{Bot, BotDifficulty, BotPersonality, BotPlayer};
+#[cfg(feature = "wasm")]
+use crate::options::GameOptions;
+#[cfg(feature = "wasm")]
+use crate::fairness::SeedReveal;
+#[cfg(feature = "wasm")]
+use crate::theme::ThemePack;
+#[cfg(feature = "wasm")]
+use crate::actions::GameEvent;
 
 /// Initialize panic hook for better error messages in browser console
 #[cfg(feature = "wasm")]
@@ -24,6 +34,8 @@ pub fn init() {
 #[wasm_bindgen]
 pub struct WasmGame {
     state: GameState,
+    /// Every accepted action, for post-game analysis
+    record: GameRecord,
 }
 
 #[cfg(feature = "wasm")]
@@ -39,9 +51,27 @@ impl WasmGame {
             return Err(JsValue::from_str("Player count doesn't match names"));
         }
 
-        Ok(WasmGame {
-            state: GameState::new(player_count, player_names),
-        })
+        Ok(WasmGame::from_state(GameState::new(player_count, player_names)))
+    }
+
+    /// Create a new game with rule options given as JSON (see `GameOptions`)
+    #[wasm_bindgen(js_name = newWithOptions)]
+    pub fn new_with_options(
+        player_count: u8,
+        player_names_json: &str,
+        options_json: &str,
+    ) -> Result<WasmGame, JsValue> {
+        let player_names: Vec<String> = serde_json::from_str(player_names_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid player names: {}", e)))?;
+        let options: GameOptions = serde_json::from_str(options_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
+
+        if player_names.len() != player_count as usize {
+            return Err(JsValue::from_str("Player count doesn't match names"));
+        }
+
+        let state = GameState::new_with_options(player_count, player_names, options);
+        Ok(WasmGame::from_state(state))
     }
 
     /// Get the current game state as JSON
@@ -50,6 +80,19 @@ impl WasmGame {
         serde_json::to_string(&self.state).unwrap_or_else(|_| "{}".to_string())
     }
 
+    /// Get the JSON-friendly state as seen by one player (other hands hidden),
+    /// or with every hand hidden when no viewer is given
+    #[wasm_bindgen(js_name = getView)]
+    pub fn get_view(&self, viewer: Option<u8>) -> String {
+        serde_json::to_string(&self.state.view_for(viewer)).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Get the state a shared hot-seat screen may show right now
+    #[wasm_bindgen(js_name = getHotSeatView)]
+    pub fn get_hot_seat_view(&self) -> String {
+        serde_json::to_string(&self.state.hot_seat_view()).unwrap_or_else(|_| "{}".to_string())
+    }
+
     /// Get the current player ID
     #[wasm_bindgen(js_name = getCurrentPlayer)]
     pub fn get_current_player(&self) -> u8 {
@@ -76,14 +119,25 @@ impl WasmGame {
         let action: GameAction = serde_json::from_str(action_json)
             .map_err(|e| JsValue::from_str(&format!("Invalid action JSON: {}", e)))?;
 
-        match self.state.apply_action(player, action) {
+        match self.state.apply_action(player, action.clone()) {
             Ok(events) => {
+                self.record.push(player, action);
                 Ok(serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string()))
             }
             Err(e) => Err(JsValue::from_str(&format!("Action failed: {}", e))),
         }
     }
 
+    /// Advance the time banks by the milliseconds elapsed since the last call,
+    /// returns events JSON (empty when playing without a clock)
+    #[wasm_bindgen(js_name = tick)]
+    pub fn tick(&mut self, elapsed_ms: u32) -> String {
+        let events = self
+            .state
+            .tick(std::time::Duration::from_millis(elapsed_ms as u64));
+        serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string())
+    }
+
     /// Check if the game is finished
     #[wasm_bindgen(js_name = isFinished)]
     pub fn is_finished(&self) -> bool {
@@ -93,11 +147,7 @@ impl WasmGame {
     /// Get the winner (if game is finished)
     #[wasm_bindgen(js_name = getWinner)]
     pub fn get_winner(&self) -> Option<u8> {
-        if let crate::game::GamePhase::Finished { winner } = self.state.phase {
-            Some(winner)
-        } else {
-            None
-        }
+        self.state.get_winner()
     }
 
     /// Get victory points for a player
@@ -106,6 +156,12 @@ impl WasmGame {
         self.state.total_victory_points(player)
     }
 
+    /// Estimated win chance for every seat, for a spectator win bar
+    #[wasm_bindgen(js_name = getWinProbabilities)]
+    pub fn get_win_probabilities(&self) -> Vec<f32> {
+        crate::evaluation::win_probabilities(&self.state)
+    }
+
     /// Get the current phase as a string
     #[wasm_bindgen(js_name = getPhase)]
     pub fn get_phase(&self) -> String {
@@ -118,6 +174,12 @@ impl WasmGame {
         self.state.dice_roll.map(|(a, b)| vec![a, b])
     }
 
+    /// Counts, chi-square and droughts for the dice rolled so far, as JSON
+    #[wasm_bindgen(js_name = getDiceStats)]
+    pub fn get_dice_stats(&self) -> String {
+        serde_json::to_string(&self.state.dice_stats()).unwrap_or_default()
+    }
+
     /// Get board state as JSON (for rendering)
     /// Uses JSON-friendly representation with arrays instead of HashMaps
     #[wasm_bindgen(js_name = getBoard)]
@@ -126,6 +188,41 @@ impl WasmGame {
         serde_json::to_string(&board_json).unwrap_or_else(|_| "{}".to_string())
     }
 
+    /// Get the board fairness report as JSON
+    #[wasm_bindgen(js_name = getBalanceReport)]
+    pub fn get_balance_report(&self) -> String {
+        serde_json::to_string(&self.state.board.balance_report()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Get pixel positions of every tile, vertex and edge as JSON, for
+    /// drawing the board with the center hex at (origin_x, origin_y)
+    #[wasm_bindgen(js_name = getRenderGeometry)]
+    pub fn get_render_geometry(&self, hex_size: f64, origin_x: f64, origin_y: f64) -> String {
+        let layout = crate::board::RenderLayout { origin_x, origin_y };
+        let geometry = self.state.board.render_geometry(hex_size, layout);
+        serde_json::to_string(&geometry).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Get the `count` open settlement spots with the most pips as a JSON
+    /// array of `[vertex, pips]`, best first (for heatmaps)
+    #[wasm_bindgen(js_name = getBestOpenVertices)]
+    pub fn get_best_open_vertices(&self, count: u32) -> String {
+        let spots = self.state.board.best_open_vertices(count as usize);
+        serde_json::to_string(&spots).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the land vertices as a JSON array indexed by vertex ID
+    #[wasm_bindgen(js_name = getVertexIds)]
+    pub fn get_vertex_ids(&self) -> String {
+        serde_json::to_string(self.state.board.vertices_by_id()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the land edges as a JSON array indexed by edge ID
+    #[wasm_bindgen(js_name = getEdgeIds)]
+    pub fn get_edge_ids(&self) -> String {
+        serde_json::to_string(self.state.board.edges_by_id()).unwrap_or_else(|_| "[]".to_string())
+    }
+
     /// Get a specific player's state as JSON
     #[wasm_bindgen(js_name = getPlayer)]
     pub fn get_player(&self, player: u8) -> String {
@@ -137,29 +234,134 @@ impl WasmGame {
     }
 
     /// Get a bot's suggested action for a player
-    /// difficulty: "Easy", "Medium", or "Hard"
+    /// difficulty: "Easy", "Medium", "Hard", or "Expert"
+    /// personality (optional): "Balanced", "AggressiveExpander", "DevCardRush",
+    /// "PortTrader", or "Blocker"
     #[wasm_bindgen(js_name = getBotAction)]
-    pub fn get_bot_action(&self, player: u8, difficulty: &str) -> String {
-        let diff = match difficulty {
-            "Easy" => BotDifficulty::Easy,
-            "Medium" => BotDifficulty::Medium,
-            "Hard" => BotDifficulty::Hard,
-            _ => BotDifficulty::Medium,
-        };
-
-        let mut bot = Bot::new(player, diff);
+    pub fn get_bot_action(
+        &self,
+        player: u8,
+        difficulty: &str,
+        personality: Option<String>,
+    ) -> String {
+        let mut bot = make_bot(player, difficulty, personality);
         match bot.choose_action(&self.state) {
             Some(action) => serde_json::to_string(&action).unwrap_or_else(|_| "null".to_string()),
             None => "null".to_string(),
         }
     }
 
+    /// Get a bot's suggested action with its reasoning as JSON (see
+    /// `BotExplanation`), for "why did the bot do that" displays
+    #[wasm_bindgen(js_name = explainBotAction)]
+    pub fn explain_bot_action(
+        &self,
+        player: u8,
+        difficulty: &str,
+        personality: Option<String>,
+    ) -> String {
+        let mut bot = make_bot(player, difficulty, personality);
+        let explained = bot.choose_action_explained(&self.state);
+        serde_json::to_string(&explained).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Get coaching for a human player as JSON (see `Hint`): the move a
+    /// bot of the given difficulty would make, plus a discard or trade
+    /// answer when one is due
+    #[wasm_bindgen(js_name = getHint)]
+    pub fn get_hint(&self, player: u8, difficulty: &str) -> String {
+        let hint = self.state.hint(player, parse_difficulty(difficulty));
+        serde_json::to_string(&hint).unwrap_or_else(|_| "null".to_string())
+    }
+
     /// Get bot's discard suggestion when player must discard
     #[wasm_bindgen(js_name = getBotDiscard)]
     pub fn get_bot_discard(&self, player: u8) -> String {
         let discard = crate::bot::bot_discard(&self.state, player);
         serde_json::to_string(&discard).unwrap_or_else(|_| "{}".to_string())
     }
+
+    /// Grade every decision of the game so far, returns analysis JSON
+    #[wasm_bindgen(js_name = analyzeGame)]
+    pub fn analyze_game(&self) -> Result<String, JsValue> {
+        let analysis = analyze_game(&self.record)
+            .map_err(|e| JsValue::from_str(&format!("Analysis failed: {}", e)))?;
+        Ok(serde_json::to_string(&analysis).unwrap_or_else(|_| "{}".to_string()))
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl WasmGame {
+    fn from_state(state: GameState) -> Self {
+        let record = GameRecord::new(&state);
+        Self { state, record }
+    }
+}
+
+/// Check a finished game's dice against the seed the server revealed.
+/// Takes the replay's record, the commitment published at the start, the
+/// revealed seed and the rolls seen during the game, all but the commitment
+/// as JSON.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = verifyDice)]
+pub fn verify_dice(
+    record_json: &str,
+    commitment: &str,
+    seed_json: &str,
+    rolls_json: &str,
+) -> Result<(), JsValue> {
+    let parse_error = |e: serde_json::Error| JsValue::from_str(&format!("Invalid JSON: {}", e));
+    let record: ReplayRecord = serde_json::from_str(record_json).map_err(parse_error)?;
+    let seed: SeedReveal = serde_json::from_str(seed_json).map_err(parse_error)?;
+    let rolls: Vec<(u8, u8)> = serde_json::from_str(rolls_json).map_err(parse_error)?;
+    crate::fairness::verify_dice(&record.to_record(), commitment, &seed, &rolls)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// A built-in theme pack as JSON: `Classic`, or Singapore for any other
+/// name
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = themePack)]
+pub fn theme_pack(name: &str) -> String {
+    let pack = match name {
+        "Classic" => ThemePack::classic(),
+        _ => ThemePack::singapore(),
+    };
+    serde_json::to_string(&pack).unwrap_or_default()
+}
+
+/// The translation key and arguments for an event given as JSON
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = eventMessage)]
+pub fn event_message(event_json: &str) -> Result<String, JsValue> {
+    let event: GameEvent = serde_json::from_str(event_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+    serde_json::to_string(&crate::i18n::event_message(&event))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Build a bot from the names the JS side uses, falling back to a
+/// Balanced Medium bot
+#[cfg(feature = "wasm")]
+fn make_bot(player: u8, difficulty: &str, personality: Option<String>) -> Bot {
+    let diff = parse_difficulty(difficulty);
+    let personality = personality
+        .and_then(|name| serde_json::from_value(serde_json::Value::String(name)).ok())
+        .unwrap_or(BotPersonality::Balanced);
+
+    Bot::new(player, diff).with_personality(personality)
+}
+
+/// Difficulty from its JS name, Medium if unknown
+#[cfg(feature = "wasm")]
+fn parse_difficulty(name: &str) -> BotDifficulty {
+    match name {
+        "Easy" => BotDifficulty::Easy,
+        "Medium" => BotDifficulty::Medium,
+        "Hard" => BotDifficulty::Hard,
+        "Expert" => BotDifficulty::Expert,
+        _ => BotDifficulty::Medium,
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,72 @@
+//! Golden replays: whole recorded games that must keep ending the same way.
+//!
+//! Each file in `tests/golden/` is a replay record (seed, names, rules and
+//! every move, as written by `catan-sim --notation-dir`) plus the
+//! `final_hash` of the state it ends in. A rule change that alters how any
+//! of them plays out fails here until the fixtures are deliberately
+//! updated:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test -p catan-core --test golden_replays
+//! ```
+//!
+//! which also fills in the hash of a newly added record.
+
+use catan_core::analysis::ReplayRecord;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    #[serde(flatten)]
+    record: ReplayRecord,
+    #[serde(default)]
+    final_hash: String,
+}
+
+#[test]
+fn test_golden_replays() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no golden games in {}", dir.display());
+
+    let mut changed = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let json = std::fs::read_to_string(path).unwrap();
+        let mut golden: Golden = serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("{} is not a replay record: {}", name, e));
+
+        let game = golden
+            .record
+            .to_record()
+            .replay()
+            .unwrap_or_else(|e| panic!("{} no longer replays: {}", name, e));
+        assert!(game.is_finished(), "{} no longer ends the game", name);
+
+        let hash = game.state_hash();
+        if hash == golden.final_hash {
+            continue;
+        }
+        if update {
+            golden.final_hash = hash;
+            std::fs::write(path, serde_json::to_string_pretty(&golden).unwrap() + "\n").unwrap();
+        } else {
+            changed.push(format!(
+                "{} ends in {} instead of {}",
+                name, hash, golden.final_hash
+            ));
+        }
+    }
+    assert!(
+        changed.is_empty(),
+        "golden games ended differently; rerun with UPDATE_GOLDEN=1 if that is intended:\n  {}",
+        changed.join("\n  ")
+    );
+}
@@ -0,0 +1,151 @@
+//! Property tests for the hex coordinate algebra.
+//!
+//! Everything above the board (placement rules, road lengths, production)
+//! trusts that a corner or side has one name however it is reached, so the
+//! properties here are checked on coordinates far beyond any real board.
+
+use catan_core::{EdgeCoord, EdgeDirection, HexCoord, HexCorner, VertexCoord, VertexDirection};
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// Well past any board, small enough for exact pixel math
+const REACH: i32 = 1000;
+
+fn hex() -> impl Strategy<Value = HexCoord> {
+    (-REACH..=REACH, -REACH..=REACH).prop_map(|(q, r)| HexCoord::new(q, r))
+}
+
+fn vertex() -> impl Strategy<Value = VertexCoord> {
+    let direction = select(vec![VertexDirection::North, VertexDirection::South]);
+    (hex(), direction).prop_map(|(hex, direction)| VertexCoord::new(hex, direction))
+}
+
+fn edge() -> impl Strategy<Value = EdgeCoord> {
+    (hex(), select(EdgeDirection::ALL.to_vec()))
+        .prop_map(|(hex, direction)| EdgeCoord::new(hex, direction))
+}
+
+fn hex_size() -> impl Strategy<Value = f64> {
+    1.0..100.0
+}
+
+fn distance((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+    (x1 - x2).hypot(y1 - y2)
+}
+
+proptest! {
+    #[test]
+    fn test_canonical_forms_are_idempotent(v in vertex(), e in edge()) {
+        prop_assert_eq!(v.canonical(), v);
+        prop_assert_eq!(e.canonical(), e);
+        prop_assert_eq!(EdgeCoord::new(e.hex, e.direction), e);
+    }
+
+    #[test]
+    fn test_an_edge_has_one_name_from_either_side(
+        hex in hex(),
+        direction in select(EdgeDirection::ALL.to_vec()),
+    ) {
+        let edge = EdgeCoord::new(hex, direction);
+        let [a, b] = edge.touching_hexes();
+        prop_assert_eq!(a.distance_to(&b), 1);
+        for side in [a, b] {
+            let named: Vec<_> = side.edges().into_iter().filter(|&e| e == edge).collect();
+            prop_assert_eq!(named.len(), 1, "{:?} doesn't list {:?} once", side, edge);
+        }
+        let neighbor = hex.neighbor(direction);
+        let back = EdgeDirection::ALL
+            .into_iter()
+            .find(|&d| neighbor.neighbor(d) == hex)
+            .unwrap();
+        prop_assert_eq!(EdgeCoord::new(neighbor, back), edge);
+    }
+
+    #[test]
+    fn test_a_vertex_has_one_name_from_each_hex(v in vertex()) {
+        for hex in v.touching_hexes() {
+            let named = hex.vertices().into_iter().filter(|&c| c == v).count();
+            prop_assert_eq!(named, 1, "{:?} doesn't list {:?} once", hex, v);
+        }
+    }
+
+    #[test]
+    fn test_every_corner_of_a_hex_touches_it(
+        hex in hex(),
+        corner in select(HexCorner::ALL.to_vec()),
+    ) {
+        let v = VertexCoord::at_corner(hex, corner);
+        prop_assert!(v.touching_hexes().contains(&hex), "{:?} of {:?} is {:?}", corner, hex, v);
+    }
+
+    #[test]
+    fn test_edge_endpoints_are_symmetric(e in edge()) {
+        let [a, b] = e.endpoints();
+        prop_assert_ne!(a, b);
+        for end in [a, b] {
+            prop_assert!(end.touching_edges().contains(&e), "{:?} doesn't touch {:?}", end, e);
+        }
+        prop_assert!(a.adjacent_vertices().contains(&b));
+
+        let adjacent = e.adjacent_edges();
+        prop_assert_eq!(adjacent.len(), 4);
+        for other in adjacent {
+            prop_assert!(other.adjacent_edges().contains(&e));
+        }
+    }
+
+    #[test]
+    fn test_adjacent_vertices_are_symmetric(v in vertex()) {
+        let adjacent = v.adjacent_vertices();
+        for (i, u) in adjacent.iter().enumerate() {
+            prop_assert_ne!(*u, v);
+            prop_assert!(!adjacent[..i].contains(u));
+            prop_assert!(u.adjacent_vertices().contains(&v), "{:?} doesn't see {:?}", u, v);
+            prop_assert_ne!(u.direction, v.direction);
+        }
+    }
+
+    #[test]
+    fn test_pixels_round_trip(h in hex(), size in hex_size()) {
+        let (x, y) = h.to_pixel(size);
+        prop_assert_eq!(HexCoord::from_pixel(x, y, size), h);
+        // Anywhere well inside the hex still finds it
+        prop_assert_eq!(HexCoord::from_pixel(x + size * 0.4, y - size * 0.4, size), h);
+    }
+
+    #[test]
+    fn test_corners_and_sides_sit_on_their_hexes(v in vertex(), e in edge(), size in hex_size()) {
+        let tolerance = size * 1e-9 * REACH as f64;
+        for hex in v.touching_hexes() {
+            let gap = distance(v.to_pixel(size), hex.to_pixel(size));
+            prop_assert!((gap - size).abs() < tolerance, "{:?} is {} from {:?}", v, gap, hex);
+        }
+        let apothem = size * 3.0_f64.sqrt() / 2.0;
+        for hex in e.touching_hexes() {
+            let gap = distance(e.to_pixel(size), hex.to_pixel(size));
+            prop_assert!((gap - apothem).abs() < tolerance, "{:?} is {} from {:?}", e, gap, hex);
+        }
+    }
+
+    #[test]
+    fn test_distance_is_a_metric(a in hex(), b in hex(), c in hex()) {
+        prop_assert_eq!(a.distance_to(&b), b.distance_to(&a));
+        prop_assert_eq!(a.distance_to(&a), 0);
+        prop_assert!(a.distance_to(&c) <= a.distance_to(&b) + b.distance_to(&c));
+        for n in a.neighbors() {
+            prop_assert_eq!(a.distance_to(&n), 1);
+        }
+    }
+
+    #[test]
+    fn test_lines_step_between_neighbors(a in hex(), offset in (-20i32..=20, -20i32..=20)) {
+        let b = HexCoord::new(a.q + offset.0, a.r + offset.1);
+        let line = a.line_to(&b);
+        prop_assert_eq!(line.len() as u32, a.distance_to(&b) + 1);
+        prop_assert_eq!(line[0], a);
+        prop_assert_eq!(*line.last().unwrap(), b);
+        for pair in line.windows(2) {
+            prop_assert_eq!(pair[0].distance_to(&pair[1]), 1);
+        }
+    }
+}
@@ -269,7 +269,7 @@ fn test_maritime_trade() {
         assert_eq!(give, Resource::Brick);
         // Rate could be 2, 3, or 4 depending on harbor access
         assert!(
-            give_count >= 2 && give_count <= 4,
+            (2..=4).contains(&give_count),
             "Rate should be between 2:1 and 4:1, got {}:1",
             give_count
         );
@@ -330,6 +330,8 @@ fn test_development_card_purchase() {
         "Should be able to buy dev card with resources"
     );
 
+    // The dice may have added to the hand, so compare with just before buying
+    let before = game.players[player as usize].resources.clone();
     game.apply_action(player, GameAction::BuyDevelopmentCard)
         .unwrap();
 
@@ -349,11 +351,13 @@ fn test_development_card_purchase() {
         "Player should have card in bought pile"
     );
 
-    // Resources for dev card should be spent (ore, grain, wool)
-    // Note: dice roll may have given more resources, so we just check the dev card cost was deducted
-    let p = &game.players[player as usize];
-    // The player started with exactly 1 ore, 1 grain, 1 wool
-    // They may have gained resources from dice, but ore/grain/wool should be at original or higher minus 1
+    // One each of ore, grain and wool was spent
+    let after = &game.players[player as usize].resources;
+    assert_eq!(after.ore, before.ore - 1);
+    assert_eq!(after.grain, before.grain - 1);
+    assert_eq!(after.wool, before.wool - 1);
+    assert_eq!(after.brick, before.brick);
+    assert_eq!(after.lumber, before.lumber);
 }
 
 #[test]
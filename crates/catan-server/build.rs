@@ -0,0 +1,13 @@
+//! Generates the gRPC service from `proto/kopiatan.proto` when the `grpc`
+//! feature is on, with a protoc bundled as a dependency so none needs to
+//! be installed.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/kopiatan.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("bundled protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/kopiatan.proto").expect("kopiatan.proto compiles");
+    }
+}
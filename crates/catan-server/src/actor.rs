@@ -0,0 +1,1644 @@
+//! One task per room.
+//!
+//! Each room lives in its own task, which owns the [`GameRoom`] and works
+//! through [`RoomCommand`]s from a channel one at a time. A room's commands
+//! are handled in the order they arrive, nothing else ever touches the
+//! room, and busy rooms never hold up the others. Anything slow or timed,
+//! like a bot thinking or a disconnect grace period, runs in a task of its
+//! own that reports back to the room with another command.
+
+use crate::admin::RoomDetails;
+use crate::audit::AuditLog;
+use crate::protocol::{
+    BotSeat, Emote, ErrorCode, Replay, RoomInfo, RoomSettings, RoomStatus, ServerMessage, DELTAS,
+};
+use crate::rest::PlayerView;
+use crate::room::{BotTurn, GameRoom, RoomError};
+use crate::server::ServerState;
+use crate::tournament;
+use crate::webhook::{Webhook, WebhookEvent, WebhookSeat};
+use catan_core::{BotDifficulty, GameAction, GameStateJson, PlayerId, StateDelta};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, watch};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Deltas sent to a player between whole game states
+const DELTAS_PER_FULL_STATE: u32 = 50;
+
+/// Most people following one game
+const MAX_FOLLOWERS: usize = 200;
+
+/// Most claims to abandoned seats waiting on the host at once
+const MAX_CLAIMS: usize = 10;
+
+/// Something for a room to do
+pub enum RoomCommand {
+    Join {
+        player_id: Uuid,
+        player_name: String,
+    },
+    Rejoin {
+        player_id: Uuid,
+        old_id: Uuid,
+        token: Option<Uuid>,
+        last_seq: u64,
+        last_chat_seq: u64,
+    },
+    Leave {
+        player_id: Uuid,
+    },
+    /// Someone outside the room asks for an abandoned seat
+    ClaimSeat {
+        player_id: Uuid,
+        seat: PlayerId,
+        player_name: String,
+    },
+    /// The host's answer to a claim
+    AnswerSeatClaim {
+        player_id: Uuid,
+        claimant: Uuid,
+        approve: bool,
+    },
+    /// The player's connection closed
+    Disconnect {
+        player_id: Uuid,
+    },
+    AddBot {
+        player_id: Uuid,
+        seat: BotSeat,
+    },
+    Kick {
+        player_id: Uuid,
+        target: Uuid,
+    },
+    TransferHost {
+        player_id: Uuid,
+        target: Uuid,
+    },
+    Pause {
+        player_id: Uuid,
+    },
+    Resume {
+        player_id: Uuid,
+    },
+    UpdateSettings {
+        player_id: Uuid,
+        settings: RoomSettings,
+    },
+    SetWebhook {
+        player_id: Uuid,
+        url: Option<String>,
+    },
+    StartGame {
+        player_id: Uuid,
+    },
+    Action {
+        player_id: Uuid,
+        action: GameAction,
+        request_id: Option<String>,
+    },
+    Hint {
+        player_id: Uuid,
+        difficulty: BotDifficulty,
+    },
+    /// A player's hint has been worked out, or given up on
+    HintDone {
+        player_id: Uuid,
+    },
+    Chat {
+        player_id: Uuid,
+        message: String,
+        emote: Option<Emote>,
+    },
+    /// The player wants the whole game state again
+    Resync {
+        player_id: Uuid,
+    },
+    /// A disconnected player's grace period is over
+    GraceExpired {
+        player_id: Uuid,
+    },
+    /// Time for the next bot move
+    NextBotTurn,
+    /// A bot has made up its mind
+    BotMoved {
+        turn: Box<BotTurn>,
+        action: Option<GameAction>,
+    },
+    /// A bot panicked while thinking
+    BotFailed,
+    /// A turn or decision timer may have run out
+    TimerExpired,
+    /// Put the room away if nobody has been around for a while
+    CheckIdle,
+    /// The server is going down: tell everyone, save and stop
+    Shutdown {
+        done: oneshot::Sender<()>,
+    },
+    /// The game to watch again, as one player may see it
+    Replay {
+        player_id: Uuid,
+        reply: oneshot::Sender<Result<Replay, RoomError>>,
+    },
+    /// Someone without a seat wants the game's events as they happen
+    Follow {
+        player_id: Uuid,
+    },
+    Unfollow {
+        player_id: Uuid,
+    },
+    /// The room and game as one player sees them
+    View {
+        player_id: Uuid,
+        reply: oneshot::Sender<PlayerView>,
+    },
+    /// An operator wants to see everything about the room
+    Inspect {
+        reply: oneshot::Sender<RoomDetails>,
+    },
+    /// An operator removes a player; replies whether they were here
+    Expel {
+        target: Uuid,
+        reply: oneshot::Sender<bool>,
+    },
+    /// An operator closes the room, archiving it or forgetting it
+    Terminate {
+        archive: bool,
+        reason: String,
+        done: oneshot::Sender<()>,
+    },
+}
+
+impl RoomCommand {
+    /// Whether the room sent this itself or was asked by the server,
+    /// rather than a person doing something
+    fn is_housekeeping(&self) -> bool {
+        matches!(
+            self,
+            RoomCommand::GraceExpired { .. }
+                | RoomCommand::NextBotTurn
+                | RoomCommand::BotMoved { .. }
+                | RoomCommand::BotFailed
+                | RoomCommand::TimerExpired
+                | RoomCommand::CheckIdle
+                | RoomCommand::Shutdown { .. }
+                | RoomCommand::Replay { .. }
+                | RoomCommand::Follow { .. }
+                | RoomCommand::Unfollow { .. }
+                | RoomCommand::Inspect { .. }
+                | RoomCommand::Expel { .. }
+                | RoomCommand::Terminate { .. }
+        )
+    }
+}
+
+/// The way into a running room.
+#[derive(Clone)]
+pub struct RoomHandle {
+    commands: mpsc::UnboundedSender<RoomCommand>,
+    info: watch::Receiver<RoomInfo>,
+}
+
+impl RoomHandle {
+    /// Queue a command; false if the room has closed
+    pub fn send(&self, command: RoomCommand) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    /// The room as of its last command
+    pub fn info(&self) -> RoomInfo {
+        self.info.borrow().clone()
+    }
+
+    /// Queue a command that answers, and wait for the answer; None if the
+    /// room closes first
+    pub async fn ask<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> RoomCommand,
+    ) -> Option<T> {
+        let (reply, answer) = oneshot::channel();
+        if !self.send(command(reply)) {
+            return None;
+        }
+        answer.await.ok()
+    }
+}
+
+/// Start a task for `room`. The room saves itself and removes itself from
+/// the server once everyone has left; adding it to the server is up to
+/// the caller.
+pub fn spawn_room(room: GameRoom, state: &Arc<ServerState>) -> RoomHandle {
+    let (commands, receiver) = mpsc::unbounded_channel();
+    let (info_sender, info) = watch::channel(room.to_info());
+    let audit = AuditLog::open(room.id, state.audit_dir.as_deref());
+    let webhook = room
+        .webhook_url
+        .clone()
+        .zip(state.webhook_client.clone())
+        .map(|(url, client)| Webhook::new(room.id, url, client));
+    // A restored game's current turn has been announced already
+    let webhook_turn = room
+        .game
+        .as_ref()
+        .map(|g| (g.turn_number, g.current_player));
+    let actor = RoomActor {
+        audit,
+        webhook,
+        webhook_turn,
+        followers: HashSet::new(),
+        hinting: HashSet::new(),
+        claims: HashMap::new(),
+        room,
+        state: Arc::clone(state),
+        commands: commands.clone(),
+        info: info_sender,
+        timer_deadline: None,
+        last_activity: Instant::now(),
+        state_version: 0,
+        sent_states: HashMap::new(),
+    };
+    tokio::spawn(actor.run(receiver));
+    RoomHandle { commands, info }
+}
+
+/// Whether a room carries on after a command
+enum Flow {
+    Continue,
+    Close,
+}
+
+struct RoomActor {
+    room: GameRoom,
+    state: Arc<ServerState>,
+    /// For timers and thinking bots to report back
+    commands: mpsc::UnboundedSender<RoomCommand>,
+    info: watch::Sender<RoomInfo>,
+    /// Deadline of the room's timer when it was last announced
+    timer_deadline: Option<Instant>,
+    /// When a person last did anything here
+    last_activity: Instant,
+    /// Game states sent so far
+    state_version: u64,
+    /// The last state each player taking deltas was sent
+    sent_states: HashMap<Uuid, SentState>,
+    /// Where everything that happens here is written down
+    audit: AuditLog,
+    /// Where the host wants the game's lifecycle events sent
+    webhook: Option<Webhook>,
+    /// The turn last announced to the webhook, and whose it was
+    webhook_turn: Option<(u32, PlayerId)>,
+    /// People watching the game's events without a seat
+    followers: HashSet<Uuid>,
+    /// Players waiting on a hint
+    hinting: HashSet<Uuid>,
+    /// Newcomers waiting for the host to let them take a seat, with the
+    /// seat and the name they would play under
+    claims: HashMap<Uuid, (PlayerId, String)>,
+}
+
+/// A game state a player has, for working out what to send them next
+struct SentState {
+    version: u64,
+    state: GameStateJson,
+    /// Deltas sent since the last whole state
+    deltas: u32,
+}
+
+impl RoomActor {
+    async fn run(mut self, mut receiver: mpsc::UnboundedReceiver<RoomCommand>) {
+        self.start();
+        while let Some(command) = receiver.recv().await {
+            if let Flow::Close = self.handle(command) {
+                break;
+            }
+            self.check_timer();
+        }
+
+        // Anyone who was on their way in finds the room gone
+        receiver.close();
+        while let Ok(command) = receiver.try_recv() {
+            if let RoomCommand::Join { player_id, .. } | RoomCommand::Rejoin { player_id, .. } =
+                command
+            {
+                self.send_error(player_id, "Room not found".to_string());
+            }
+        }
+    }
+
+    /// Pick a restored game back up: people get the usual grace period to
+    /// rejoin, and bots carry on
+    fn start(&mut self) {
+        self.audit.lifecycle("opened", Some(self.room.host_id));
+        if self.room.status == RoomStatus::InGame {
+            let away: Vec<Uuid> = self
+                .room
+                .players
+                .values()
+                .filter(|p| !p.connected && p.bot.is_none() && !p.stand_in)
+                .map(|p| p.id)
+                .collect();
+            for player_id in away {
+                self.start_grace_timer(player_id);
+            }
+            self.schedule_bots();
+        }
+        self.changed();
+        self.check_timer();
+    }
+
+    fn handle(&mut self, command: RoomCommand) -> Flow {
+        if !command.is_housekeeping() {
+            self.last_activity = Instant::now();
+        }
+        match command {
+            RoomCommand::Join {
+                player_id,
+                player_name,
+            } => return self.join(player_id, player_name),
+            RoomCommand::Rejoin {
+                player_id,
+                old_id,
+                token,
+                last_seq,
+                last_chat_seq,
+            } => return self.rejoin(player_id, old_id, token, last_seq, last_chat_seq),
+            RoomCommand::Leave { player_id } => return self.remove_player(player_id),
+            RoomCommand::ClaimSeat {
+                player_id,
+                seat,
+                player_name,
+            } => self.claim_seat(player_id, seat, player_name),
+            RoomCommand::AnswerSeatClaim {
+                player_id,
+                claimant,
+                approve,
+            } => return self.answer_seat_claim(player_id, claimant, approve),
+            RoomCommand::Disconnect { player_id } => return self.disconnect(player_id),
+            RoomCommand::AddBot { player_id, seat } => self.add_bot(player_id, seat),
+            RoomCommand::Kick { player_id, target } => self.kick(player_id, target),
+            RoomCommand::TransferHost { player_id, target } => {
+                match self.room.transfer_host(player_id, target) {
+                    Ok(()) => {
+                        self.audit.lifecycle("host handed over", Some(target));
+                        self.broadcast(ServerMessage::RoomUpdated {
+                            room: self.room.to_info(),
+                        });
+                        self.changed();
+                    }
+                    Err(e) => self.send_error(player_id, e.to_string()),
+                }
+            }
+            RoomCommand::Pause { player_id } => self.set_paused(player_id, true),
+            RoomCommand::Resume { player_id } => self.set_paused(player_id, false),
+            RoomCommand::UpdateSettings {
+                player_id,
+                settings,
+            } => self.update_settings(player_id, settings),
+            RoomCommand::SetWebhook { player_id, url } => self.set_webhook(player_id, url),
+            RoomCommand::Replay { player_id, reply } => {
+                let _ = reply.send(self.room.replay(player_id));
+            }
+            RoomCommand::Follow { player_id } => self.follow(player_id),
+            RoomCommand::Unfollow { player_id } => {
+                self.followers.remove(&player_id);
+            }
+            RoomCommand::StartGame { player_id } => self.start_game(player_id),
+            RoomCommand::Action {
+                player_id,
+                action,
+                request_id,
+            } => self.apply_action(player_id, action, request_id),
+            RoomCommand::Hint {
+                player_id,
+                difficulty,
+            } => self.hint(player_id, difficulty),
+            RoomCommand::HintDone { player_id } => {
+                self.hinting.remove(&player_id);
+            }
+            RoomCommand::Chat {
+                player_id,
+                message,
+                emote,
+            } => self.chat(player_id, message, emote),
+            RoomCommand::Resync { player_id } => {
+                self.sent_states.remove(&player_id);
+                if let Some(game_state) = self.room.game_state_for(player_id) {
+                    self.send_state(player_id, &game_state);
+                }
+            }
+            RoomCommand::GraceExpired { player_id } => self.substitute_bot(player_id),
+            RoomCommand::NextBotTurn => self.next_bot_turn(),
+            RoomCommand::BotMoved { turn, action } => self.finish_bot_turn(*turn, action),
+            RoomCommand::BotFailed => {
+                error!("Bot in room {} panicked while thinking", self.room.id);
+                self.room.stop_bot_driver();
+            }
+            RoomCommand::TimerExpired => self.expire_timer(),
+            RoomCommand::CheckIdle => return self.check_idle(),
+            RoomCommand::Shutdown { done } => {
+                self.audit.lifecycle("server shutting down", None);
+                self.broadcast(ServerMessage::ServerShuttingDown);
+                self.changed();
+                let _ = done.send(());
+                return Flow::Close;
+            }
+            RoomCommand::View { player_id, reply } => {
+                let _ = reply.send(PlayerView {
+                    room: self.room.to_info(),
+                    game: self.room.game_state_for(player_id),
+                    valid_actions: self.room.valid_actions_for(player_id).unwrap_or_default(),
+                });
+            }
+            RoomCommand::Inspect { reply } => {
+                let _ = reply.send(self.details());
+            }
+            RoomCommand::Expel { target, reply } => {
+                let here = self.room.players.contains_key(&target);
+                let _ = reply.send(here);
+                if here {
+                    return self.expel(target);
+                }
+            }
+            RoomCommand::Terminate {
+                archive,
+                reason,
+                done,
+            } => {
+                self.terminate(archive, reason);
+                let _ = done.send(());
+                return Flow::Close;
+            }
+        }
+        Flow::Continue
+    }
+
+    fn join(&mut self, player_id: Uuid, player_name: String) -> Flow {
+        if let Err(e) = self.room.add_player(player_id, player_name) {
+            self.send_error(player_id, e.to_string());
+            return Flow::Continue;
+        }
+        self.audit.lifecycle("joined", Some(player_id));
+        let room_info = self.room.to_info();
+        self.state.send_to_player(
+            player_id,
+            ServerMessage::JoinedRoom {
+                room: room_info.clone(),
+                rejoin_token: self.room.rejoin_token(player_id),
+            },
+        );
+        self.broadcast_except(player_id, ServerMessage::RoomUpdated { room: room_info });
+        self.changed();
+        self.claim_player(player_id)
+    }
+
+    fn rejoin(
+        &mut self,
+        player_id: Uuid,
+        old_id: Uuid,
+        token: Option<Uuid>,
+        last_seq: u64,
+        last_chat_seq: u64,
+    ) -> Flow {
+        if let Err(e) = self.room.rejoin(old_id, player_id, token) {
+            self.send_error(player_id, e.to_string());
+            return Flow::Continue;
+        }
+        self.audit.lifecycle("rejoined", Some(player_id));
+        let room_info = self.room.to_info();
+        let mut catch_up = self.room.catch_up(player_id, last_seq, last_chat_seq);
+        if let ServerMessage::CatchUp { chat, .. } = &mut catch_up {
+            chat.retain(|line| {
+                !line
+                    .player_id
+                    .is_some_and(|speaker| self.state.chat.is_muted(player_id, speaker))
+            });
+        }
+        self.state.send_to_player(
+            player_id,
+            ServerMessage::JoinedRoom {
+                room: room_info.clone(),
+                rejoin_token: self.room.rejoin_token(player_id),
+            },
+        );
+        self.state.send_to_player(player_id, catch_up);
+        self.broadcast(ServerMessage::RoomUpdated { room: room_info });
+        self.broadcast_game_update();
+        self.schedule_bots();
+        self.changed();
+        self.claim_player(player_id)
+    }
+
+    fn claim_seat(&mut self, player_id: Uuid, seat: PlayerId, player_name: String) {
+        if let Err(e) = self.room.abandoned_seat(seat) {
+            self.send_error(player_id, e.to_string());
+            return;
+        }
+        if self.claims.len() >= MAX_CLAIMS && !self.claims.contains_key(&player_id) {
+            self.send_error(player_id, "Too many claims waiting on the host".to_string());
+            return;
+        }
+        self.claims.insert(player_id, (seat, player_name.clone()));
+        self.state.send_to_player(
+            self.room.host_id,
+            ServerMessage::SeatClaimed {
+                player_id,
+                player_name,
+                seat,
+            },
+        );
+    }
+
+    fn answer_seat_claim(&mut self, player_id: Uuid, claimant: Uuid, approve: bool) -> Flow {
+        if player_id != self.room.host_id {
+            self.send_error(player_id, RoomError::NotHost.to_string());
+            return Flow::Continue;
+        }
+        let Some((seat, name)) = self.claims.remove(&claimant) else {
+            self.send_error(player_id, "Nobody is waiting for that answer".to_string());
+            return Flow::Continue;
+        };
+        if !approve {
+            self.send_error(claimant, "The host turned down your claim".to_string());
+            return Flow::Continue;
+        }
+        if let Err(e) = self.room.take_over_seat(seat, claimant, name) {
+            self.send_error(claimant, e.to_string());
+            self.send_error(player_id, e.to_string());
+            return Flow::Continue;
+        }
+        self.audit.lifecycle("seat taken over", Some(claimant));
+        let room_info = self.room.to_info();
+        self.state.send_to_player(
+            claimant,
+            ServerMessage::JoinedRoom {
+                room: room_info.clone(),
+                rejoin_token: self.room.rejoin_token(claimant),
+            },
+        );
+        let catch_up = self.room.catch_up(claimant, 0, 0);
+        self.state.send_to_player(claimant, catch_up);
+        self.broadcast(ServerMessage::RoomUpdated { room: room_info });
+        self.broadcast_game_update();
+        self.schedule_bots();
+        self.changed();
+        self.claim_player(claimant)
+    }
+
+    /// Point the server at this room for a player who just came in. If
+    /// their connection closed meanwhile, the disconnect missed the room,
+    /// so handle it here.
+    fn claim_player(&mut self, player_id: Uuid) -> Flow {
+        self.state.player_rooms.insert(player_id, self.room.id);
+        if !self.state.player_senders.contains_key(&player_id)
+            && self.state.player_rooms.remove(&player_id).is_some()
+        {
+            return self.disconnect(player_id);
+        }
+        Flow::Continue
+    }
+
+    /// A player's connection closed. Their seat is kept during a game;
+    /// otherwise they leave.
+    fn disconnect(&mut self, player_id: Uuid) -> Flow {
+        self.sent_states.remove(&player_id);
+        if self.room.status != RoomStatus::InGame {
+            return self.remove_player(player_id);
+        }
+        self.room.set_player_connected(player_id, false);
+        self.audit.lifecycle("disconnected", Some(player_id));
+        self.start_grace_timer(player_id);
+        self.broadcast(ServerMessage::RoomUpdated {
+            room: self.room.to_info(),
+        });
+        self.changed();
+        Flow::Continue
+    }
+
+    fn remove_player(&mut self, player_id: Uuid) -> Flow {
+        self.sent_states.remove(&player_id);
+        let removed = self.room.remove_player(player_id);
+        if removed.is_ok() {
+            self.audit.lifecycle("left", Some(player_id));
+        }
+        if removed.unwrap_or(false) {
+            return self.close();
+        }
+        self.broadcast(ServerMessage::RoomUpdated {
+            room: self.room.to_info(),
+        });
+        self.changed();
+        Flow::Continue
+    }
+
+    /// Everyone has gone: forget the room
+    fn close(&mut self) -> Flow {
+        info!("Closing room {}", self.room.id);
+        self.audit.lifecycle("closed", None);
+        self.state.rooms.remove(&self.room.id);
+        self.state.forget_room(self.room.id);
+        Flow::Close
+    }
+
+    /// Put the room away once nobody has been connected or done anything
+    /// for the server's idle time. A room still waiting to start is just
+    /// dropped; a game, finished or abandoned, is archived.
+    fn check_idle(&mut self) -> Flow {
+        let someone_here = self
+            .room
+            .players
+            .values()
+            .any(|p| p.bot.is_none() && p.connected);
+        if someone_here || self.last_activity.elapsed() < self.state.room_idle_ttl {
+            return Flow::Continue;
+        }
+
+        let status = self.room.status;
+        self.audit.lifecycle("evicted while idle", None);
+        let total = self.state.evictions.count(status);
+        info!(
+            "Evicting idle room {} ({:?}), {} such rooms so far",
+            self.room.id, status, total
+        );
+        if status == RoomStatus::Waiting {
+            return self.close();
+        }
+        self.state.rooms.remove(&self.room.id);
+        self.state.archive_room(&self.room);
+        Flow::Close
+    }
+
+    fn add_bot(&mut self, player_id: Uuid, seat: BotSeat) {
+        match self.room.add_bot(player_id, seat) {
+            Ok(_) => {
+                self.broadcast(ServerMessage::RoomUpdated {
+                    room: self.room.to_info(),
+                });
+                self.changed();
+            }
+            Err(e) => self.send_error(player_id, e.to_string()),
+        }
+    }
+
+    fn kick(&mut self, player_id: Uuid, target: Uuid) {
+        if let Err(e) = self.room.kick_player(player_id, target) {
+            self.send_error(player_id, e.to_string());
+            return;
+        }
+        self.audit.lifecycle("kicked", Some(target));
+        let room_id = self.room.id;
+        self.state.player_rooms.remove_if(&target, |_, id| *id == room_id);
+        self.state.send_to_player(target, ServerMessage::Kicked);
+        self.broadcast(ServerMessage::RoomUpdated {
+            room: self.room.to_info(),
+        });
+        self.changed();
+    }
+
+    /// Everything about the room, for an operator
+    fn details(&self) -> RoomDetails {
+        RoomDetails {
+            room: self.room.to_info(),
+            game: self.room.game.as_ref().map(|g| Box::new(g.to_json_friendly())),
+            events: self.room.events.last_seq(),
+            chat_lines: self.room.chat.len(),
+            idle_secs: self.last_activity.elapsed().as_secs(),
+        }
+    }
+
+    /// An operator removes a player, whatever stage the room is at
+    fn expel(&mut self, target: Uuid) -> Flow {
+        self.audit.lifecycle("removed by an operator", Some(target));
+        let room_id = self.room.id;
+        self.state.player_rooms.remove_if(&target, |_, id| *id == room_id);
+        self.state.send_to_player(target, ServerMessage::Kicked);
+        self.remove_player(target)
+    }
+
+    /// An operator closes the room. Everyone in it is told and sent back
+    /// to the lobby.
+    fn terminate(&mut self, archive: bool, reason: String) {
+        let room_id = self.room.id;
+        info!("Operator closing room {}: {}", room_id, reason);
+        let what = if archive {
+            "ended by an operator"
+        } else {
+            "deleted by an operator"
+        };
+        self.audit.lifecycle(what, None);
+        self.broadcast(ServerMessage::RoomClosed { reason });
+        for player_id in self.room.players.keys() {
+            self.state.player_rooms.remove_if(player_id, |_, id| *id == room_id);
+        }
+        if archive {
+            self.state.rooms.remove(&room_id);
+            self.state.archive_room(&self.room);
+        } else {
+            self.close();
+        }
+    }
+
+    fn set_paused(&mut self, player_id: Uuid, paused: bool) {
+        let result = if paused {
+            self.room.pause(player_id)
+        } else {
+            self.room.resume(player_id)
+        };
+        if let Err(e) = result {
+            self.send_error(player_id, e.to_string());
+            return;
+        }
+        let what = if paused { "paused" } else { "resumed" };
+        self.audit.lifecycle(what, Some(player_id));
+        let player_name = self
+            .room
+            .players
+            .get(&player_id)
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        self.broadcast(ServerMessage::PauseChanged {
+            paused,
+            player_name,
+        });
+        self.broadcast(ServerMessage::RoomUpdated {
+            room: self.room.to_info(),
+        });
+        self.changed();
+        if !paused {
+            self.schedule_bots();
+        }
+    }
+
+    fn update_settings(&mut self, player_id: Uuid, settings: RoomSettings) {
+        match self.room.update_settings(player_id, settings) {
+            Ok(()) => {
+                self.broadcast(ServerMessage::RoomUpdated {
+                    room: self.room.to_info(),
+                });
+                self.changed();
+            }
+            Err(e) => self.send_error(player_id, e.to_string()),
+        }
+    }
+
+    fn follow(&mut self, player_id: Uuid) {
+        if !self.room.settings.public {
+            self.send_error(player_id, RoomError::NotPublic.to_string());
+            return;
+        }
+        let senders = &self.state.player_senders;
+        self.followers.retain(|id| senders.contains_key(id));
+        if self.followers.len() >= MAX_FOLLOWERS {
+            self.send_error(player_id, "Too many are following this game".to_string());
+            return;
+        }
+        self.followers.insert(player_id);
+        self.state.send_to_player(
+            player_id,
+            ServerMessage::FollowingGame {
+                room: self.room.to_info(),
+                events: self.room.spectator_events(0),
+            },
+        );
+    }
+
+    fn set_webhook(&mut self, player_id: Uuid, url: Option<String>) {
+        let Some(client) = self.state.webhook_client.clone() else {
+            self.send_error(player_id, "Webhooks are turned off on this server".to_string());
+            return;
+        };
+        if let Err(e) = self.room.set_webhook(player_id, url.clone()) {
+            self.send_error(player_id, e.to_string());
+            return;
+        }
+        let what = if url.is_some() {
+            "webhook set"
+        } else {
+            "webhook removed"
+        };
+        self.audit.lifecycle(what, Some(player_id));
+        self.webhook = url
+            .clone()
+            .map(|url| Webhook::new(self.room.id, url, client));
+        self.state
+            .send_to_player(player_id, ServerMessage::WebhookSet { url });
+        self.changed();
+    }
+
+    fn start_game(&mut self, player_id: Uuid) {
+        if let Err(e) = self.room.start_game(player_id) {
+            self.send_error(player_id, e.to_string());
+            return;
+        }
+        self.audit.lifecycle("game started", Some(player_id));
+        if let Some(webhook) = &self.webhook {
+            let players = self
+                .room
+                .player_order
+                .iter()
+                .filter_map(|id| self.room.players.get(id))
+                .filter_map(|p| {
+                    Some(WebhookSeat {
+                        seat: p.game_index?,
+                        player_id: p.id,
+                        name: p.name.clone(),
+                        bot: p.bot.is_some(),
+                    })
+                })
+                .collect();
+            webhook.send(WebhookEvent::GameStarted {
+                room_name: self.room.name.clone(),
+                players,
+            });
+        }
+        let current_player = self.room.get_current_player().unwrap();
+
+        let dice_commitment = self.room.dice_commitment();
+        for player_id in self.room.players.keys() {
+            if let Some(state) = self.room.game_state_for(*player_id) {
+                self.state.send_to_player(
+                    *player_id,
+                    ServerMessage::GameStarted {
+                        state,
+                        dice_commitment: dice_commitment.clone(),
+                    },
+                );
+            }
+        }
+        self.send_valid_actions();
+        self.broadcast(ServerMessage::TurnChanged {
+            player_id: current_player,
+        });
+        self.changed();
+
+        // Bots seated first take their turns right away
+        self.schedule_bots();
+    }
+
+    fn apply_action(&mut self, player_id: Uuid, action: GameAction, request_id: Option<String>) {
+        let since = self.room.events.last_seq();
+        let seat = self.room.players.get(&player_id).and_then(|p| p.game_index);
+        let started = Instant::now();
+        let (answer, applied) = self
+            .room
+            .apply_request(player_id, action.clone(), request_id);
+        let error = match &answer {
+            ServerMessage::ActionResult { error, .. } => error.as_deref(),
+            _ => None,
+        };
+        self.audit
+            .action(Some(player_id), seat, false, &action, error, started.elapsed());
+        // Send action result to the acting player
+        self.state.send_to_player(player_id, answer);
+
+        if applied {
+            // Broadcast what happened and the updated game state
+            self.broadcast_events(since);
+            self.broadcast_game_update();
+            self.changed();
+            self.schedule_bots();
+        }
+    }
+
+    fn chat(&mut self, player_id: Uuid, message: String, emote: Option<Emote>) {
+        let player_name = self
+            .room
+            .players
+            .get(&player_id)
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "Unknown".to_string());
+        let line = self.room.add_chat(player_id, player_name, message, emote);
+        let msg = ServerMessage::ChatMessage {
+            player_name: line.player_name,
+            message: line.message,
+            seq: line.seq,
+            player_id: line.player_id,
+            emote: line.emote,
+        };
+        for listener in self.room.players.keys() {
+            if !self.state.chat.is_muted(*listener, player_id) {
+                self.state.send_to_player(*listener, msg.clone());
+            }
+        }
+        self.changed();
+    }
+
+    /// Keep the game going with a bot if a player doesn't come back in time
+    fn start_grace_timer(&self, player_id: Uuid) {
+        self.after(
+            self.state.disconnect_grace,
+            RoomCommand::GraceExpired { player_id },
+        );
+    }
+
+    /// Put a bot in a disconnected player's seat if they are still away,
+    /// and let it catch up on any turns the game was waiting on.
+    fn substitute_bot(&mut self, player_id: Uuid) {
+        if !self
+            .room
+            .substitute_bot(player_id, self.state.disconnect_grace)
+        {
+            return;
+        }
+        info!("Bot standing in for {} in room {}", player_id, self.room.id);
+        self.audit.lifecycle("bot stood in", Some(player_id));
+        self.broadcast(ServerMessage::RoomUpdated {
+            room: self.room.to_info(),
+        });
+        self.changed();
+        self.schedule_bots();
+    }
+
+    /// Start playing the bots' turns, unless that is already under way.
+    /// Bot moves come one at a time, with a pause before each so people can
+    /// follow along, until the game waits on a person or ends.
+    fn schedule_bots(&mut self) {
+        if self.room.start_bot_driver() {
+            self.after(self.state.bot_move_delay, RoomCommand::NextBotTurn);
+        }
+    }
+
+    /// Hand the waiting bot to the blocking pool to think, so the room
+    /// keeps handling commands meanwhile
+    fn next_bot_turn(&mut self) {
+        let Some(mut turn) = self.room.next_bot_turn() else {
+            return;
+        };
+        let commands = self.commands.clone();
+        tokio::spawn(async move {
+            let thought = tokio::task::spawn_blocking(move || {
+                let action = turn.think();
+                (turn, action)
+            })
+            .await;
+            let _ = commands.send(match thought {
+                Ok((turn, action)) => RoomCommand::BotMoved {
+                    turn: Box::new(turn),
+                    action,
+                },
+                Err(_) => RoomCommand::BotFailed,
+            });
+        });
+    }
+
+    /// Work a hint out on the blocking pool, like a bot's move. A player
+    /// gets one hint at a time, so asking again and again can't tie the
+    /// pool up.
+    fn hint(&mut self, player_id: Uuid, difficulty: BotDifficulty) {
+        let (game, seat) = match self.room.hint_position(player_id) {
+            Ok(position) => position,
+            Err(e) => return self.send_error(player_id, e.to_string()),
+        };
+        if !self.hinting.insert(player_id) {
+            return self.state.send_to_player(
+                player_id,
+                ServerMessage::Error {
+                    message: "Still working out your last hint".to_string(),
+                    code: Some(ErrorCode::RateLimited),
+                },
+            );
+        }
+        let state = Arc::clone(&self.state);
+        let commands = self.commands.clone();
+        tokio::spawn(async move {
+            let hint = tokio::task::spawn_blocking(move || game.hint(seat, difficulty)).await;
+            if let Ok(hint) = hint {
+                state.send_to_player(player_id, ServerMessage::Hint { hint });
+            }
+            let _ = commands.send(RoomCommand::HintDone { player_id });
+        });
+    }
+
+    fn finish_bot_turn(&mut self, turn: BotTurn, action: Option<GameAction>) {
+        let since = self.room.events.last_seq();
+        let seat = turn.seat;
+        let started = Instant::now();
+        let Some(events) = self.room.finish_bot_turn(turn, action.clone()) else {
+            if let Some(action) = &action {
+                let error = Some("rejected by the game");
+                self.audit
+                    .action(None, Some(seat), true, action, error, started.elapsed());
+            }
+            return;
+        };
+        if !events.is_empty() {
+            if let Some(action) = &action {
+                let player_id = self.room.player_at(seat);
+                self.audit
+                    .action(player_id, Some(seat), true, action, None, started.elapsed());
+            }
+            self.broadcast_events(since);
+            self.broadcast_game_update();
+            self.changed();
+        }
+        self.after(self.state.bot_move_delay, RoomCommand::NextBotTurn);
+    }
+
+    /// Announce a timer the room has started, and set an alarm for it
+    fn check_timer(&mut self) {
+        let deadline = self.room.timer().map(|t| t.deadline);
+        if deadline == self.timer_deadline {
+            return;
+        }
+        self.timer_deadline = deadline;
+        let Some(timer) = self.room.timer() else {
+            return;
+        };
+        let remaining = timer.deadline.saturating_duration_since(Instant::now());
+        self.broadcast(ServerMessage::TimerStarted {
+            kind: timer.kind,
+            players: timer.players.iter().map(|&p| p as usize).collect(),
+            remaining_ms: remaining.as_millis() as u64,
+        });
+        self.after(remaining, RoomCommand::TimerExpired);
+    }
+
+    /// Play for anyone whose time is up
+    fn expire_timer(&mut self) {
+        let since = self.room.events.last_seq();
+        let Some((timer, events)) = self.room.expire_timer(Instant::now()) else {
+            return;
+        };
+        info!("{:?} timer ran out in room {}", timer.kind, self.room.id);
+        self.audit.lifecycle("timer ran out", None);
+        self.broadcast(ServerMessage::TurnTimedOut {
+            kind: timer.kind,
+            players: timer.players.iter().map(|&p| p as usize).collect(),
+        });
+        if !events.is_empty() {
+            self.broadcast_events(since);
+            self.broadcast_game_update();
+            self.changed();
+        }
+        self.schedule_bots();
+    }
+
+    /// Send this room `command` once `delay` has passed
+    fn after(&self, delay: Duration, command: RoomCommand) {
+        let commands = self.commands.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = commands.send(command);
+        });
+    }
+
+    /// Save the room and publish its new summary
+    fn changed(&mut self) {
+        self.announce_turn();
+        if let Some(result) = self.room.take_result() {
+            self.audit.lifecycle("game finished", None);
+            if let Some(webhook) = &self.webhook {
+                webhook.send(WebhookEvent::GameOver {
+                    result: result.clone(),
+                });
+            }
+            self.state.record_result(&result);
+            tournament::table_finished(&self.state, &result);
+        }
+        self.state.save_room(&self.room);
+        self.info.send_replace(self.room.to_info());
+    }
+
+    /// Tell the webhook whose turn it is, when the turn has passed
+    fn announce_turn(&mut self) {
+        if self.room.status != RoomStatus::InGame {
+            return;
+        }
+        let Some(turn) = self
+            .room
+            .game
+            .as_ref()
+            .map(|g| (g.turn_number, g.current_player))
+        else {
+            return;
+        };
+        if self.webhook_turn.replace(turn) == Some(turn) {
+            return;
+        }
+        if let Some(webhook) = &self.webhook {
+            let (turn, seat) = turn;
+            let player_id = self.room.player_at(seat);
+            let name = player_id
+                .and_then(|id| self.room.players.get(&id))
+                .map(|p| p.name.clone());
+            webhook.send(WebhookEvent::TurnChanged {
+                turn,
+                seat,
+                player_id,
+                name,
+            });
+        }
+    }
+
+    fn send_error(&self, player_id: Uuid, message: String) {
+        self.state.send_to_player(
+            player_id,
+            ServerMessage::Error {
+                message,
+                code: None,
+            },
+        );
+    }
+
+    /// Broadcast a message to all players in the room.
+    fn broadcast(&self, msg: ServerMessage) {
+        for player_id in self.room.players.keys() {
+            self.state.send_to_player(*player_id, msg.clone());
+        }
+    }
+
+    /// Broadcast a message to all players in the room except one.
+    fn broadcast_except(&self, except: Uuid, msg: ServerMessage) {
+        for player_id in self.room.players.keys() {
+            if *player_id != except {
+                self.state.send_to_player(*player_id, msg.clone());
+            }
+        }
+    }
+
+    /// Tell each player what they may do now. Trade partners and players
+    /// who must discard can act out of turn, so everyone gets their own.
+    fn send_valid_actions(&self) {
+        for player_id in self.room.players.keys() {
+            if let Some(actions) = self.room.valid_actions_for(*player_id) {
+                self.state
+                    .send_to_player(*player_id, ServerMessage::ValidActions { actions });
+            }
+        }
+    }
+
+    /// Send everyone the current game state, whose turn it is, and the
+    /// result once the game is over.
+    fn broadcast_game_update(&mut self) {
+        let Some(current_player) = self.room.get_current_player() else {
+            return;
+        };
+
+        // Each player sees only their own hand
+        self.state_version += 1;
+        let players: Vec<Uuid> = self.room.players.keys().copied().collect();
+        for player_id in players {
+            if let Some(game_state) = self.room.game_state_for(player_id) {
+                self.send_state(player_id, &game_state);
+            }
+        }
+        self.send_valid_actions();
+        self.broadcast(ServerMessage::TurnChanged {
+            player_id: current_player,
+        });
+        if let Some(ranking) = self.room.get_ranking() {
+            let (winner, winner_name) = self.room.get_winner().unzip();
+            self.broadcast(ServerMessage::GameOver {
+                winner,
+                winner_name,
+                ranking,
+                seed: self.room.revealed_seed(),
+            });
+        }
+    }
+
+    /// Send a player the latest game state. Players who asked for deltas
+    /// get what changed since the state they have, and the whole state
+    /// every so often in case they went wrong somewhere.
+    fn send_state(&mut self, player_id: Uuid, game_state: &GameStateJson) {
+        let version = self.state_version;
+        if !self.state.wants(player_id, DELTAS) {
+            self.state.send_to_player(
+                player_id,
+                ServerMessage::GameState {
+                    state: Box::new(game_state.clone()),
+                    version,
+                },
+            );
+            return;
+        }
+
+        match self.sent_states.get_mut(&player_id) {
+            Some(sent) if sent.deltas < DELTAS_PER_FULL_STATE => {
+                let delta = StateDelta::between(&sent.state, game_state);
+                self.state.send_to_player(
+                    player_id,
+                    ServerMessage::GameDelta {
+                        base: sent.version,
+                        version,
+                        delta: Box::new(delta),
+                    },
+                );
+                sent.version = version;
+                sent.state = game_state.clone();
+                sent.deltas += 1;
+            }
+            _ => {
+                self.state.send_to_player(
+                    player_id,
+                    ServerMessage::GameState {
+                        state: Box::new(game_state.clone()),
+                        version,
+                    },
+                );
+                let sent = SentState {
+                    version,
+                    state: game_state.clone(),
+                    deltas: 0,
+                };
+                self.sent_states.insert(player_id, sent);
+            }
+        }
+    }
+
+    /// Send everyone the events after `since`, each as that player may
+    /// see them.
+    fn broadcast_events(&self, since: u64) {
+        for player_id in self.room.players.keys() {
+            let events = self.room.events_for(*player_id, since);
+            if !events.is_empty() {
+                self.state
+                    .send_to_player(*player_id, ServerMessage::GameEvents { events });
+            }
+        }
+        if self.followers.is_empty() {
+            return;
+        }
+        let events = self.room.spectator_events(since);
+        for follower in self.followers.iter().filter(|_| !events.is_empty()) {
+            self.state.send_to_player(
+                *follower,
+                ServerMessage::FollowedEvents {
+                    game_id: self.room.id,
+                    events: events.clone(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catan_core::BotPersonality;
+    use std::sync::atomic::Ordering;
+
+    async fn next(receiver: &mut mpsc::UnboundedReceiver<ServerMessage>) -> ServerMessage {
+        tokio::time::timeout(Duration::from_secs(30), receiver.recv())
+            .await
+            .expect("room went quiet")
+            .expect("room hung up")
+    }
+
+    #[tokio::test]
+    async fn test_room_task_handles_commands_in_order() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::ZERO;
+        let state = Arc::new(state);
+
+        let host_id = Uuid::new_v4();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.player_senders.insert(host_id, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 3);
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        room.add_bot(host_id, seat).unwrap();
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+
+        for message in ["one", "two", "three"] {
+            handle.send(RoomCommand::Chat {
+                player_id: host_id,
+                message: message.to_string(),
+                emote: None,
+            });
+        }
+        handle.send(RoomCommand::StartGame { player_id: host_id });
+
+        for expected in 1..=3 {
+            match next(&mut receiver).await {
+                ServerMessage::ChatMessage { seq, .. } => assert_eq!(seq, expected),
+                other => panic!("expected chat, got {:?}", other),
+            }
+        }
+        assert!(matches!(
+            next(&mut receiver).await,
+            ServerMessage::GameStarted { .. }
+        ));
+
+        // The bots play until it is the host's turn
+        loop {
+            if let ServerMessage::TurnChanged { player_id: 0 } = next(&mut receiver).await {
+                break;
+            }
+        }
+        assert_eq!(handle.info().status, RoomStatus::InGame);
+
+        // The room closes once its only person leaves
+        handle.send(RoomCommand::Leave { player_id: host_id });
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while handle.send(RoomCommand::NextBotTurn) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("room stayed open");
+        assert!(state.rooms.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hints_are_worked_out_one_at_a_time() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::from_secs(3600);
+        let state = Arc::new(state);
+        let host_id = Uuid::new_v4();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.player_senders.insert(host_id, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 2);
+        room.add_player(Uuid::new_v4(), "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+
+        let hint = || RoomCommand::Hint {
+            player_id: host_id,
+            difficulty: BotDifficulty::Easy,
+        };
+        handle.send(hint());
+        handle.send(hint());
+        let mut hints = 0;
+        let mut refused = 0;
+        while hints == 0 {
+            match next(&mut receiver).await {
+                ServerMessage::Hint { .. } => hints += 1,
+                ServerMessage::Error {
+                    code: Some(ErrorCode::RateLimited),
+                    ..
+                } => refused += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(refused, 1);
+
+        // Once it is in, the player may ask again
+        handle.send(hint());
+        while !matches!(next(&mut receiver).await, ServerMessage::Hint { .. }) {}
+    }
+
+    #[tokio::test]
+    async fn test_only_the_seat_holder_can_rejoin() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::from_secs(3600);
+        let state = Arc::new(state);
+        let host_id = Uuid::new_v4();
+        let guest = Uuid::new_v4();
+        let watcher = Uuid::new_v4();
+        let back = Uuid::new_v4();
+        let (sender, mut watcher_inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(watcher, sender);
+        let (sender, mut back_inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(back, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 2);
+        room.settings.public = true;
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let token = room.rejoin_token(guest);
+        room.set_player_connected(guest, false);
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+
+        // A follower sees who sits where, but can't take a seat with that
+        handle.send(RoomCommand::Follow { player_id: watcher });
+        let ServerMessage::FollowingGame { room, .. } = next(&mut watcher_inbox).await else {
+            panic!("expected to follow the game");
+        };
+        let seen = room.players.iter().find(|p| !p.connected).unwrap().id;
+        assert_eq!(seen, guest);
+        let rejoin = |player_id, token| RoomCommand::Rejoin {
+            player_id,
+            old_id: guest,
+            token,
+            last_seq: 0,
+            last_chat_seq: 0,
+        };
+        handle.send(rejoin(watcher, None));
+        assert!(matches!(
+            next(&mut watcher_inbox).await,
+            ServerMessage::Error { .. }
+        ));
+
+        // The guest, back on a new connection, has the token
+        handle.send(rejoin(back, token));
+        let ServerMessage::JoinedRoom { rejoin_token, .. } = next(&mut back_inbox).await else {
+            panic!("expected the seat back");
+        };
+        assert!(rejoin_token.is_some() && rejoin_token != token);
+    }
+
+    #[tokio::test]
+    async fn test_a_newcomer_takes_over_an_abandoned_seat() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::from_secs(3600);
+        let state = Arc::new(state);
+        let host_id = Uuid::new_v4();
+        let guest = Uuid::new_v4();
+        let friend = Uuid::new_v4();
+        let (sender, mut host_inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(host_id, sender);
+        let (sender, mut friend_inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(friend, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 2);
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let seat = room.players[&guest].game_index.unwrap();
+        let host_seat = room.players[&host_id].game_index.unwrap();
+        room.set_player_connected(guest, false);
+        assert!(room.substitute_bot(guest, Duration::ZERO));
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+
+        // Only a seat its player has left can be claimed
+        let claim = |seat| RoomCommand::ClaimSeat {
+            player_id: friend,
+            seat,
+            player_name: "Friend".to_string(),
+        };
+        handle.send(claim(host_seat));
+        assert!(matches!(
+            next(&mut friend_inbox).await,
+            ServerMessage::Error { .. }
+        ));
+
+        handle.send(claim(seat));
+        loop {
+            if let ServerMessage::SeatClaimed { player_id, .. } = next(&mut host_inbox).await {
+                assert_eq!(player_id, friend);
+                break;
+            }
+        }
+        handle.send(RoomCommand::AnswerSeatClaim {
+            player_id: host_id,
+            claimant: friend,
+            approve: true,
+        });
+        let ServerMessage::JoinedRoom { room, .. } = next(&mut friend_inbox).await else {
+            panic!("expected to join the room");
+        };
+        let me = room.players.iter().find(|p| p.id == friend).unwrap();
+        assert_eq!(me.name, "Friend");
+        assert!(!me.stand_in);
+        let ServerMessage::CatchUp {
+            state: Some(view), ..
+        } = next(&mut friend_inbox).await
+        else {
+            panic!("expected the game so far");
+        };
+        assert_eq!(view.players[seat as usize].name, "Friend");
+        assert_eq!(state.player_rooms.get(&friend).map(|r| *r), Some(room_id));
+    }
+
+    #[tokio::test]
+    async fn test_rooms_left_empty_are_archived() {
+        use crate::storage::{MemoryStore, RoomStore};
+
+        let store = Arc::new(MemoryStore::new());
+        let mut state = ServerState::new().with_store(Box::new(Arc::clone(&store)));
+        state.room_idle_ttl = Duration::ZERO;
+        let state = Arc::new(state);
+        let host_id = Uuid::new_v4();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.player_senders.insert(host_id, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 2);
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+        handle.send(RoomCommand::StartGame { player_id: host_id });
+        while !matches!(next(&mut receiver).await, ServerMessage::GameStarted { .. }) {}
+
+        // Someone is still here, so the room stays
+        handle.send(RoomCommand::CheckIdle);
+        handle.send(RoomCommand::Chat {
+            player_id: host_id,
+            message: "still here".to_string(),
+            emote: None,
+        });
+        while !matches!(next(&mut receiver).await, ServerMessage::ChatMessage { .. }) {}
+        assert!(state.rooms.contains_key(&room_id));
+
+        // Once they have gone, the abandoned game is put away
+        handle.send(RoomCommand::Disconnect { player_id: host_id });
+        handle.send(RoomCommand::CheckIdle);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while handle.send(RoomCommand::Resync { player_id: host_id }) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("room stayed open");
+        assert!(state.rooms.is_empty());
+        assert!(store.load_all().unwrap().is_empty());
+        let archived = store.load_archived(room_id).unwrap().unwrap();
+        assert_eq!(archived.status, RoomStatus::InGame);
+        assert_eq!(state.evictions.abandoned.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deltas_keep_a_client_in_step() {
+        use crate::protocol::{Session, PROTOCOL_VERSION};
+
+        let state = Arc::new(ServerState::new());
+        let host_id = Uuid::new_v4();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.player_senders.insert(host_id, sender);
+        let session = Session {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: "test".to_string(),
+            features: vec![DELTAS.to_string()],
+        };
+        state.sessions.insert(host_id, session);
+
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        let handle = spawn_room(room, &state);
+        handle.send(RoomCommand::StartGame { player_id: host_id });
+
+        // The host places their first settlement and road, whoever goes
+        // first. Every update, the bot's too, builds on the last.
+        let mut client: Option<(u64, GameStateJson)> = None;
+        let (mut placed, mut waiting) = (0, false);
+        while placed < 2 || waiting {
+            match next(&mut receiver).await {
+                ServerMessage::GameState { state, version } => {
+                    assert!(client.is_none(), "only the first state comes whole");
+                    client = Some((version, *state));
+                    waiting = false;
+                }
+                ServerMessage::GameDelta {
+                    base,
+                    version,
+                    delta,
+                } => {
+                    let (have, game) = client.as_mut().expect("a state to build on");
+                    assert_eq!(base, *have);
+                    delta.apply(game);
+                    *have = version;
+                    waiting = false;
+                }
+                ServerMessage::ValidActions { actions } if placed < 2 => {
+                    if let Some(action) = actions.first() {
+                        handle.send(RoomCommand::Action {
+                            player_id: host_id,
+                            action: action.clone(),
+                            request_id: None,
+                        });
+                        placed += 1;
+                        waiting = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Asking again gets the same state, whole, once the bot has caught
+        // the client up
+        handle.send(RoomCommand::Resync { player_id: host_id });
+        let (mut version, mut game) = client.unwrap();
+        loop {
+            match next(&mut receiver).await {
+                ServerMessage::GameState { state, version: v } => {
+                    assert_eq!(v, version);
+                    assert_eq!(*state, game);
+                    break;
+                }
+                ServerMessage::GameDelta {
+                    base,
+                    version: v,
+                    delta,
+                } => {
+                    assert_eq!(base, version);
+                    delta.apply(&mut game);
+                    version = v;
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Routes for whoever runs the server.
+//!
+//! Served beside the health checks under `/admin`, and only when the server
+//! has an admin token; every request must carry it as
+//! `Authorization: Bearer <token>`.
+//!
+//! - `GET /admin/rooms`: every room running here.
+//! - `GET /admin/rooms/{id}`: one room, with its whole game state.
+//! - `POST /admin/rooms/{id}/end`: end the game and archive the room.
+//! - `DELETE /admin/rooms/{id}`: close the room and forget it.
+//! - `POST /admin/rooms/{id}/players/{player}/kick`: remove a player.
+//! - `POST /admin/announce`: send `{"message": ...}` to everyone connected.
+//!
+//! Closing a room takes an optional `{"reason": ...}` to show its players.
+
+use crate::actor::RoomCommand;
+use crate::protocol::{RoomInfo, ServerMessage};
+use crate::server::ServerState;
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use catan_core::GameStateJson;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// What a room's players are told when it is closed without a reason
+const DEFAULT_REASON: &str = "The room was closed by the server operator";
+
+/// Everything about a room, for an operator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomDetails {
+    pub room: RoomInfo,
+    /// The game as it stands, hands and deck included
+    pub game: Option<Box<GameStateJson>>,
+    /// Game events so far
+    pub events: u64,
+    pub chat_lines: usize,
+    /// Seconds since a person last did anything in the room
+    pub idle_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Announcement {
+    message: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Closing {
+    reason: Option<String>,
+}
+
+/// The admin routes, checking each request against the server's token
+pub fn router(state: Arc<ServerState>) -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/rooms", get(list_rooms))
+        .route("/rooms/:room_id", get(inspect_room).delete(delete_room))
+        .route("/rooms/:room_id/end", post(end_room))
+        .route("/rooms/:room_id/players/:player_id/kick", post(kick_player))
+        .route("/announce", post(announce))
+        .route_layer(middleware::from_fn_with_state(state, authorize))
+}
+
+/// Let a request through only with the admin token. Without a token set
+/// the routes aren't there at all.
+async fn authorize(
+    State(state): State<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let Some(token) = &state.admin_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if same(given.as_bytes(), token.as_bytes()) => Ok(next.run(request).await),
+        _ => {
+            warn!("Refused admin request to {}", request.uri());
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Compare without giving away how much of a guess was right, or how long
+/// the token is: both sides are hashed to the same length first
+fn same(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(&b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn list_rooms(State(state): State<Arc<ServerState>>) -> Json<Vec<RoomInfo>> {
+    Json(state.rooms.iter().map(|r| r.info()).collect())
+}
+
+async fn inspect_room(
+    State(state): State<Arc<ServerState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<RoomDetails>, StatusCode> {
+    let details = ask(&state, room_id, |reply| RoomCommand::Inspect { reply }).await?;
+    Ok(Json(details))
+}
+
+async fn end_room(
+    State(state): State<Arc<ServerState>>,
+    Path(room_id): Path<Uuid>,
+    closing: Option<Json<Closing>>,
+) -> Result<StatusCode, StatusCode> {
+    close_room(&state, room_id, true, closing).await
+}
+
+async fn delete_room(
+    State(state): State<Arc<ServerState>>,
+    Path(room_id): Path<Uuid>,
+    closing: Option<Json<Closing>>,
+) -> Result<StatusCode, StatusCode> {
+    close_room(&state, room_id, false, closing).await
+}
+
+async fn close_room(
+    state: &ServerState,
+    room_id: Uuid,
+    archive: bool,
+    closing: Option<Json<Closing>>,
+) -> Result<StatusCode, StatusCode> {
+    let Json(closing) = closing.unwrap_or_default();
+    let reason = closing.reason.unwrap_or_else(|| DEFAULT_REASON.to_string());
+    ask(state, room_id, |done| RoomCommand::Terminate {
+        archive,
+        reason,
+        done,
+    })
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn kick_player(
+    State(state): State<Arc<ServerState>>,
+    Path((room_id, player_id)): Path<(Uuid, Uuid)>,
+) -> StatusCode {
+    let command = |reply| RoomCommand::Expel {
+        target: player_id,
+        reply,
+    };
+    match ask(&state, room_id, command).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) | Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+async fn announce(
+    State(state): State<Arc<ServerState>>,
+    Json(announcement): Json<Announcement>,
+) -> StatusCode {
+    info!("Announcing to {} players", state.player_senders.len());
+    for sender in state.player_senders.iter() {
+        let _ = sender.send(ServerMessage::Announcement {
+            message: announcement.message.clone(),
+        });
+    }
+    StatusCode::NO_CONTENT
+}
+
+/// Send a room a command and wait for its answer. Not found if the room
+/// isn't running here, or closes first.
+async fn ask<T>(
+    state: &ServerState,
+    room_id: Uuid,
+    command: impl FnOnce(oneshot::Sender<T>) -> RoomCommand,
+) -> Result<T, StatusCode> {
+    let room = state
+        .rooms
+        .get(&room_id)
+        .map(|room| room.clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    room.ask(command).await.ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::spawn_room;
+    use crate::http::tests::request;
+    use crate::room::GameRoom;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_tokens_compare_by_content_not_prefix() {
+        assert!(same(b"secret", b"secret"));
+        assert!(!same(b"secret", b"secreT"));
+        assert!(!same(b"secret", b"secret2"));
+        assert!(!same(b"", b"secret"));
+    }
+
+    #[tokio::test]
+    async fn test_operators_can_inspect_and_close_rooms() {
+        let mut state = ServerState::new();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let host = Uuid::new_v4();
+        let (sender, mut inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(host, sender);
+        let room_id = Uuid::new_v4();
+        let room = GameRoom::new(room_id, host, "Host".to_string(), 4);
+        state.rooms.insert(room_id, spawn_room(room, &state));
+        state.player_rooms.insert(host, room_id);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = crate::http::router(Arc::clone(&state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let rooms = "/admin/rooms";
+        let room_path = format!("/admin/rooms/{}", room_id);
+
+        let (status, _) = request(addr, "GET", rooms, None, "").await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        let (status, _) = request(addr, "GET", rooms, Some("guess"), "").await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+
+        let (status, body) = request(addr, "GET", rooms, Some("secret"), "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let listed: Vec<RoomInfo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(listed[0].id, room_id);
+
+        let (status, body) = request(addr, "GET", &room_path, Some("secret"), "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let details: RoomDetails = serde_json::from_str(&body).unwrap();
+        assert_eq!(details.room.host_id, host);
+        assert!(details.game.is_none());
+
+        let hello = r#"{"message":"Back in five"}"#;
+        let (status, _) = request(addr, "POST", "/admin/announce", Some("secret"), hello).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        assert!(matches!(
+            inbox.recv().await,
+            Some(ServerMessage::Announcement { message }) if message == "Back in five"
+        ));
+
+        let reason = r#"{"reason":"Cheating"}"#;
+        let (status, _) = request(addr, "DELETE", &room_path, Some("secret"), reason).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        assert!(matches!(
+            inbox.recv().await,
+            Some(ServerMessage::RoomClosed { reason }) if reason == "Cheating"
+        ));
+        assert!(state.rooms.is_empty());
+        assert!(state.player_rooms.is_empty());
+        let (status, _) = request(addr, "GET", &room_path, Some("secret"), "").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+}
@@ -0,0 +1,189 @@
+//! An audit trail for each room.
+//!
+//! Every action applied in a room, and every change in its life (people
+//! joining and leaving, the game starting, pausing, ending), is logged as
+//! a structured `tracing` event under the `audit` target, with the room,
+//! the player and the outcome as fields. With an audit directory set, each
+//! room also appends the same entries as JSON lines to `<room id>.jsonl`
+//! there, so a disputed game can be gone through move by move afterwards.
+
+use catan_core::{GameAction, PlayerId};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// One line of a room's audit trail
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    /// Milliseconds since the Unix epoch
+    at_ms: u64,
+    room_id: Uuid,
+    #[serde(flatten)]
+    event: AuditEvent<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum AuditEvent<'a> {
+    /// An action someone, or a bot, tried
+    Action {
+        player_id: Option<Uuid>,
+        seat: Option<PlayerId>,
+        bot: bool,
+        action: &'a GameAction,
+        /// Why the action was turned away, if it was
+        error: Option<&'a str>,
+        duration_us: u64,
+    },
+    /// Something that happened to the room
+    Lifecycle {
+        what: &'a str,
+        player_id: Option<Uuid>,
+    },
+}
+
+/// Where one room's audit trail goes.
+pub struct AuditLog {
+    room_id: Uuid,
+    file: Option<BufWriter<File>>,
+}
+
+impl AuditLog {
+    /// A trail for a room, also kept in `dir` when given. A file that
+    /// can't be opened is logged, and the trail goes to tracing alone.
+    pub fn open(room_id: Uuid, dir: Option<&Path>) -> Self {
+        let file = dir.and_then(|dir| {
+            let path = dir.join(format!("{}.jsonl", room_id));
+            let opened = fs::create_dir_all(dir)
+                .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+            match opened {
+                Ok(file) => Some(BufWriter::new(file)),
+                Err(e) => {
+                    error!("Could not open audit file {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+        Self { room_id, file }
+    }
+
+    /// An action applied, or turned away, and how long it took
+    pub fn action(
+        &mut self,
+        player_id: Option<Uuid>,
+        seat: Option<PlayerId>,
+        bot: bool,
+        action: &GameAction,
+        error: Option<&str>,
+        duration: Duration,
+    ) {
+        let duration_us = duration.as_micros() as u64;
+        info!(
+            target: "audit",
+            room_id = %self.room_id,
+            player = ?player_id,
+            seat = ?seat,
+            bot,
+            action = ?action,
+            result = error.unwrap_or("ok"),
+            duration_us,
+            "action"
+        );
+        self.write(AuditEvent::Action {
+            player_id,
+            seat,
+            bot,
+            action,
+            error,
+            duration_us,
+        });
+    }
+
+    /// A change in the room's life, such as "joined" or "game started"
+    pub fn lifecycle(&mut self, what: &str, player_id: Option<Uuid>) {
+        info!(
+            target: "audit",
+            room_id = %self.room_id,
+            player = ?player_id,
+            what,
+            "lifecycle"
+        );
+        self.write(AuditEvent::Lifecycle { what, player_id });
+    }
+
+    fn write(&mut self, event: AuditEvent) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let entry = AuditEntry {
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            room_id: self.room_id,
+            event,
+        };
+        let written = serde_json::to_writer(&mut *file, &entry)
+            .map_err(std::io::Error::from)
+            .and_then(|_| file.write_all(b"\n"))
+            .and_then(|_| file.flush());
+        if let Err(e) = written {
+            error!(
+                "Could not write audit entry for room {}: {}",
+                self.room_id, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_file_gets_a_line_per_entry() {
+        let dir = std::env::temp_dir().join(format!("kopiatan-audit-{}", Uuid::new_v4()));
+        let room_id = Uuid::new_v4();
+        let player_id = Uuid::new_v4();
+        let mut log = AuditLog::open(room_id, Some(&dir));
+        log.lifecycle("game started", Some(player_id));
+        let action = GameAction::RollDice;
+        log.action(
+            Some(player_id),
+            Some(0),
+            false,
+            &action,
+            None,
+            Duration::from_micros(42),
+        );
+        log.action(
+            None,
+            Some(1),
+            true,
+            &action,
+            Some("Not your turn"),
+            Duration::ZERO,
+        );
+
+        let text = fs::read_to_string(dir.join(format!("{}.jsonl", room_id))).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["kind"], "lifecycle");
+        assert_eq!(lines[0]["what"], "game started");
+        assert_eq!(lines[1]["kind"], "action");
+        assert_eq!(lines[1]["action"], "RollDice");
+        assert_eq!(lines[1]["duration_us"], 42);
+        assert_eq!(lines[1]["error"], serde_json::Value::Null);
+        assert_eq!(lines[2]["bot"], true);
+        assert_eq!(lines[2]["error"], "Not your turn");
+        assert!(lines.iter().all(|l| l["room_id"] == room_id.to_string()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! Signed tickets that let a player keep one identity across connections.
+//!
+//! A ticket is the player's ID and when it was issued, signed with a
+//! secret only the server knows. A client that shows a valid ticket when it
+//! connects gets the same player ID back, so it can find its seat again.
+//! While tickets are in use, a seat only goes back to that ID, and a new
+//! connection with it takes over from an old one that hasn't closed yet.
+//! Anyone without one still plays as a guest under a fresh ID.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long a ticket stays good. Every connection gets a fresh one.
+pub const TICKET_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("Malformed ticket")]
+    Malformed,
+    #[error("Ticket signature does not match")]
+    BadSignature,
+    #[error("Ticket has expired")]
+    Expired,
+}
+
+/// What a ticket vouches for
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    player_id: Uuid,
+    /// Seconds since the Unix epoch
+    issued_at: u64,
+}
+
+/// Issues and checks tickets with the server's secret
+pub struct TicketSigner {
+    key: Vec<u8>,
+    lifetime: Duration,
+}
+
+impl TicketSigner {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: secret.as_ref().to_vec(),
+            lifetime: TICKET_LIFETIME,
+        }
+    }
+
+    /// Issue a ticket for a player, as `<claims>.<signature>` in URL-safe
+    /// base64 so it can ride in a query string
+    pub fn issue(&self, player_id: Uuid) -> String {
+        let claims = Claims {
+            player_id,
+            issued_at: now_secs(),
+        };
+        let body = serde_json::to_vec(&claims).expect("claims serialize");
+        let body = URL_SAFE_NO_PAD.encode(body);
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&body).finalize().into_bytes());
+        format!("{}.{}", body, signature)
+    }
+
+    /// The player a ticket belongs to, if it is genuine and still current
+    pub fn verify(&self, ticket: &str) -> Result<Uuid, AuthError> {
+        let (body, signature) = ticket.split_once('.').ok_or(AuthError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AuthError::Malformed)?;
+        self.mac(body)
+            .verify_slice(&signature)
+            .map_err(|_| AuthError::BadSignature)?;
+
+        let body = URL_SAFE_NO_PAD
+            .decode(body)
+            .map_err(|_| AuthError::Malformed)?;
+        let claims: Claims = serde_json::from_slice(&body).map_err(|_| AuthError::Malformed)?;
+        if now_secs().saturating_sub(claims.issued_at) > self.lifetime.as_secs() {
+            return Err(AuthError::Expired);
+        }
+        Ok(claims.player_id)
+    }
+
+    fn mac(&self, body: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC takes any key length");
+        mac.update(body.as_bytes());
+        mac
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tickets_keep_identity_and_reject_forgeries() {
+        let signer = TicketSigner::new("server secret");
+        let player_id = Uuid::new_v4();
+        let ticket = signer.issue(player_id);
+        assert_eq!(signer.verify(&ticket), Ok(player_id));
+
+        // Another server's ticket, or one with a swapped identity, is refused
+        let other = TicketSigner::new("other secret");
+        assert_eq!(other.verify(&ticket), Err(AuthError::BadSignature));
+        let forged = other.issue(Uuid::new_v4());
+        let (_, signature) = ticket.split_once('.').unwrap();
+        let (body, _) = forged.split_once('.').unwrap();
+        assert_eq!(
+            signer.verify(&format!("{}.{}", body, signature)),
+            Err(AuthError::BadSignature)
+        );
+        assert_eq!(signer.verify("not a ticket"), Err(AuthError::Malformed));
+
+        let expired = TicketSigner {
+            lifetime: Duration::ZERO,
+            ..TicketSigner::new("server secret")
+        };
+        let old = Claims {
+            player_id,
+            issued_at: now_secs() - 10,
+        };
+        let body = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&old).unwrap());
+        let signature = URL_SAFE_NO_PAD.encode(expired.mac(&body).finalize().into_bytes());
+        assert_eq!(
+            expired.verify(&format!("{}.{}", body, signature)),
+            Err(AuthError::Expired)
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! Load test for a running Kopiatan server.
+//!
+//! Starts many scripted WebSocket clients that sit down together at tables,
+//! fill the empty seats with bots and play random legal moves until the
+//! game ends, resigning once they have made enough moves. At the end it prints how
+//! long the server took to answer each kind of request and how often
+//! something went wrong:
+//!
+//! ```text
+//! catan-loadtest --url ws://127.0.0.1:8080 --clients 400 --players 3 --bots 1
+//! ```
+
+use anyhow::{anyhow, bail, Context};
+use catan_core::{BotDifficulty, BotPersonality, GameAction};
+use catan_server::protocol::{ClientMessage, RoomSettings, ServerMessage, PROTOCOL_VERSION};
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    name = "catan-loadtest",
+    about = "Play many games against a server at once"
+)]
+struct Args {
+    /// Server to connect to
+    #[arg(long, default_value = "ws://127.0.0.1:8080")]
+    url: String,
+    /// Clients to connect, split into tables of `--players`
+    #[arg(long, default_value_t = 100)]
+    clients: usize,
+    /// Scripted players at each table
+    #[arg(long, default_value_t = 3)]
+    players: usize,
+    /// Bots the host adds to each table
+    #[arg(long, default_value_t = 1)]
+    bots: usize,
+    /// Games each table plays, one after another
+    #[arg(long, default_value_t = 1)]
+    games: usize,
+    /// Most moves a client makes in one game before it leaves
+    #[arg(long, default_value_t = 500)]
+    max_moves: usize,
+    /// Pause before each move, in milliseconds
+    #[arg(long, default_value_t = 100)]
+    think_ms: u64,
+    /// Time over which the tables start, in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    ramp_ms: u64,
+    /// How long a client waits for the server before giving up, in seconds
+    #[arg(long, default_value_t = 120)]
+    timeout_secs: u64,
+}
+
+/// What every client saw, gathered for the report
+#[derive(Debug, Default)]
+struct Stats {
+    /// Time to an answer, by request
+    latencies: BTreeMap<&'static str, Vec<Duration>>,
+    /// Client failures, by what went wrong
+    errors: BTreeMap<String, u64>,
+    /// Error messages the server sent
+    server_errors: u64,
+    moves: u64,
+    rejected_moves: u64,
+    games_finished: u64,
+    /// Games given up on after `--max-moves`
+    resignations: u64,
+}
+
+impl Stats {
+    fn record(&mut self, request: &'static str, latency: Duration) {
+        self.latencies.entry(request).or_default().push(latency);
+    }
+
+    fn fail(&mut self, error: &anyhow::Error) {
+        *self.errors.entry(error.to_string()).or_default() += 1;
+    }
+
+    fn print(&mut self, clients: usize, elapsed: Duration) {
+        println!("Ran {} clients for {:.1}s", clients, elapsed.as_secs_f64());
+        println!(
+            "{:<12} {:>8} {:>9} {:>9} {:>9} {:>9}",
+            "request", "count", "p50 ms", "p90 ms", "p99 ms", "max ms"
+        );
+        for (request, latencies) in &mut self.latencies {
+            latencies.sort();
+            let ms = |q: f64| percentile(latencies, q).as_secs_f64() * 1000.0;
+            println!(
+                "{:<12} {:>8} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                request,
+                latencies.len(),
+                ms(0.5),
+                ms(0.9),
+                ms(0.99),
+                ms(1.0)
+            );
+        }
+        println!(
+            "games: {} seen through, {} resigned",
+            self.games_finished, self.resignations
+        );
+        println!(
+            "moves: {} made, {} rejected ({:.2}%)",
+            self.moves,
+            self.rejected_moves,
+            rate(self.rejected_moves, self.moves)
+        );
+        println!("server errors: {}", self.server_errors);
+        let failed: u64 = self.errors.values().sum();
+        println!(
+            "client failures: {} ({:.2}% of clients)",
+            failed,
+            rate(failed, clients as u64)
+        );
+        for (error, count) in &self.errors {
+            println!("  {:>6}  {}", count, error);
+        }
+    }
+}
+
+/// The latency below which `q` of the sorted `latencies` fall
+fn percentile(latencies: &[Duration], q: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (q * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+/// `part` as a percentage of `whole`
+fn rate(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+/// One scripted player's connection
+struct Client {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    timeout: Duration,
+    stats: Arc<Mutex<Stats>>,
+}
+
+impl Client {
+    /// Connect and say hello
+    async fn connect(args: &Args, stats: Arc<Mutex<Stats>>) -> anyhow::Result<Self> {
+        let started = Instant::now();
+        let (socket, _) = connect_async(args.url.as_str())
+            .await
+            .context("could not connect")?;
+        let mut client = Self {
+            socket,
+            timeout: Duration::from_secs(args.timeout_secs),
+            stats,
+        };
+        client
+            .send(&ClientMessage::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                client_name: "catan-loadtest".to_string(),
+                features: Vec::new(),
+            })
+            .await?;
+        loop {
+            if let ServerMessage::Welcome { .. } = client.recv().await? {
+                break;
+            }
+        }
+        client.record("connect", started);
+        Ok(client)
+    }
+
+    async fn send(&mut self, msg: &ClientMessage) -> anyhow::Result<()> {
+        let text = serde_json::to_string(msg)?;
+        self.socket
+            .send(Message::Text(text))
+            .await
+            .context("connection lost")
+    }
+
+    /// The next message from the server. Errors it sends are counted and
+    /// passed on like any other.
+    async fn recv(&mut self) -> anyhow::Result<ServerMessage> {
+        loop {
+            let frame = tokio::time::timeout(self.timeout, self.socket.next())
+                .await
+                .map_err(|_| anyhow!("timed out waiting for the server"))?;
+            match frame {
+                Some(Ok(Message::Text(text))) => {
+                    let msg = serde_json::from_str(&text).context("unreadable message")?;
+                    if let ServerMessage::Error { .. } = msg {
+                        self.stats.lock().unwrap().server_errors += 1;
+                    }
+                    return Ok(msg);
+                }
+                Some(Ok(Message::Close(_))) | None => bail!("connection closed"),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(anyhow!("connection lost: {}", e)),
+            }
+        }
+    }
+
+    fn record(&self, request: &'static str, started: Instant) {
+        self.stats
+            .lock()
+            .unwrap()
+            .record(request, started.elapsed());
+    }
+}
+
+/// Sit the host down and fill the table, returning once the game starts
+async fn host(
+    client: &mut Client,
+    args: &Args,
+    room: &watch::Sender<Option<Uuid>>,
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+    client
+        .send(&ClientMessage::CreateRoom {
+            player_name: "Host".to_string(),
+            max_players: (args.players + args.bots) as u8,
+            settings: RoomSettings::default(),
+        })
+        .await?;
+    loop {
+        match client.recv().await? {
+            ServerMessage::RoomCreated { room_id } => {
+                client.record("create_room", started);
+                room.send_replace(Some(room_id));
+            }
+            ServerMessage::JoinedRoom { room, .. } | ServerMessage::RoomUpdated { room }
+                if room.players.len() == args.players =>
+            {
+                break;
+            }
+            ServerMessage::Error { message, .. } => bail!("could not create a room: {}", message),
+            _ => {}
+        }
+    }
+    for _ in 0..args.bots {
+        client
+            .send(&ClientMessage::AddBot {
+                difficulty: BotDifficulty::Easy,
+                personality: BotPersonality::default(),
+            })
+            .await?;
+    }
+    let started = Instant::now();
+    client.send(&ClientMessage::StartGame).await?;
+    loop {
+        match client.recv().await? {
+            ServerMessage::GameStarted { .. } => {
+                client.record("start_game", started);
+                return Ok(());
+            }
+            ServerMessage::Error { message, .. } => bail!("could not start: {}", message),
+            _ => {}
+        }
+    }
+}
+
+/// Join the host's room, returning once the game starts
+async fn guest(
+    client: &mut Client,
+    seat: usize,
+    room: &mut watch::Receiver<Option<Uuid>>,
+) -> anyhow::Result<()> {
+    let room_id = room
+        .wait_for(Option::is_some)
+        .await
+        .map_err(|_| anyhow!("the host gave up"))?
+        .expect("a room");
+    let started = Instant::now();
+    client
+        .send(&ClientMessage::JoinRoom {
+            room_id,
+            player_name: format!("Guest {}", seat),
+        })
+        .await?;
+    loop {
+        match client.recv().await? {
+            ServerMessage::JoinedRoom { .. } => client.record("join_room", started),
+            ServerMessage::GameStarted { .. } => return Ok(()),
+            ServerMessage::Error { message, .. } => bail!("could not join: {}", message),
+            _ => {}
+        }
+    }
+}
+
+/// A move sent and waiting on its result
+struct Pending {
+    request_id: String,
+    action: GameAction,
+    started: Instant,
+}
+
+/// Make random legal moves until the game is over, resigning once we've
+/// made enough. We stay seated either way, as a seat left mid-game holds
+/// up the bots after it. Discards can't be picked from what is offered, so
+/// for those we ask for a hint and give up what it says.
+async fn play(client: &mut Client, args: &Args) -> anyhow::Result<()> {
+    // The moves on offer and whether we have yet to pick from them
+    let mut offered: Vec<GameAction> = Vec::new();
+    let mut fresh = false;
+    let mut pending: Option<Pending> = None;
+    let mut asked: Option<Instant> = None;
+    let mut moves = 0;
+    loop {
+        if fresh && pending.is_none() && asked.is_none() && !offered.is_empty() {
+            fresh = false;
+            if moves == args.max_moves {
+                let concede = ClientMessage::GameAction {
+                    action: GameAction::Concede,
+                    request_id: None,
+                };
+                client.send(&concede).await?;
+                client.stats.lock().unwrap().resignations += 1;
+            }
+            if moves >= args.max_moves {
+                moves += 1;
+                continue;
+            }
+            tokio::time::sleep(Duration::from_millis(args.think_ms)).await;
+            if offered
+                .iter()
+                .any(|a| matches!(a, GameAction::DiscardCards(_)))
+            {
+                let hint = ClientMessage::RequestHint {
+                    difficulty: Some(BotDifficulty::Easy),
+                };
+                client.send(&hint).await?;
+                asked = Some(Instant::now());
+                continue;
+            }
+            let action = offered
+                .choose(&mut rand::thread_rng())
+                .cloned()
+                .expect("a move");
+            pending = Some(make_move(client, action, &mut moves).await?);
+        }
+
+        match client.recv().await? {
+            ServerMessage::ValidActions { actions } => {
+                offered = actions;
+                fresh = true;
+            }
+            ServerMessage::Hint { hint } if asked.is_some() => {
+                client.record("hint", asked.take().expect("a hint asked for"));
+                let action = hint.discard.map(GameAction::DiscardCards).or(hint.action);
+                if let Some(action) = action {
+                    pending = Some(make_move(client, action, &mut moves).await?);
+                }
+            }
+            ServerMessage::ActionResult {
+                success,
+                request_id: Some(id),
+                ..
+            } if pending.as_ref().is_some_and(|p| p.request_id == id) => {
+                let Pending {
+                    action, started, ..
+                } = pending.take().expect("a pending move");
+                client.record("action", started);
+                let mut stats = client.stats.lock().unwrap();
+                stats.moves += 1;
+                if !success {
+                    stats.rejected_moves += 1;
+                    // Nothing else may be offered, so try another
+                    offered.retain(|a| *a != action);
+                    fresh = true;
+                }
+            }
+            ServerMessage::GameOver { .. } => {
+                client.stats.lock().unwrap().games_finished += 1;
+                client.send(&ClientMessage::LeaveRoom).await?;
+                return Ok(());
+            }
+            ServerMessage::Kicked => bail!("kicked from the room"),
+            ServerMessage::RoomClosed { reason } => bail!("room closed: {}", reason),
+            ServerMessage::ServerShuttingDown => bail!("server shutting down"),
+            _ => {}
+        }
+    }
+}
+
+/// Send our next move, numbering it so its result can be told apart
+async fn make_move(
+    client: &mut Client,
+    action: GameAction,
+    moves: &mut usize,
+) -> anyhow::Result<Pending> {
+    let request_id = moves.to_string();
+    *moves += 1;
+    client
+        .send(&ClientMessage::GameAction {
+            action: action.clone(),
+            request_id: Some(request_id.clone()),
+        })
+        .await?;
+    Ok(Pending {
+        request_id,
+        action,
+        started: Instant::now(),
+    })
+}
+
+/// Where a table's host announces the room for one game
+type RoomSlot = (watch::Sender<Option<Uuid>>, watch::Receiver<Option<Uuid>>);
+
+/// One seat at a table, for every game the table plays. Each game gets a
+/// room of its own, and the guests wait for the host to open it.
+async fn seat(args: Arc<Args>, stats: Arc<Mutex<Stats>>, rooms: Arc<[RoomSlot]>, seat: usize) {
+    let result = async {
+        let mut client = Client::connect(&args, Arc::clone(&stats)).await?;
+        for (sender, receiver) in rooms.iter() {
+            if seat == 0 {
+                host(&mut client, &args, sender).await?;
+            } else {
+                guest(&mut client, seat, &mut receiver.clone()).await?;
+            }
+            play(&mut client, &args).await?;
+        }
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        stats.lock().unwrap().fail(&e);
+    }
+}
+
+/// Run every table and gather what the clients saw
+async fn run(args: Args) -> anyhow::Result<Stats> {
+    if args.players == 0 {
+        bail!("a table needs at least one player");
+    }
+    if args.players + args.bots > u8::MAX as usize {
+        bail!("too many seats at a table");
+    }
+    let tables = args.clients / args.players;
+    if tables == 0 {
+        bail!("not enough clients for a table of {}", args.players);
+    }
+    let args = Arc::new(args);
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    let mut tasks = Vec::new();
+    for table in 0..tables {
+        let rooms: Arc<[RoomSlot]> = (0..args.games).map(|_| watch::channel(None)).collect();
+        let delay = Duration::from_millis(args.ramp_ms * table as u64 / tables as u64);
+        for s in 0..args.players {
+            let (args, stats, rooms) = (Arc::clone(&args), Arc::clone(&stats), Arc::clone(&rooms));
+            tasks.push(tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                seat(args, stats, rooms, s).await;
+            }));
+        }
+    }
+    for task in tasks {
+        task.await?;
+    }
+    let stats = std::mem::take(&mut *stats.lock().unwrap());
+    Ok(stats)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let clients = args.clients / args.players.max(1) * args.players;
+    let started = Instant::now();
+    let mut stats = run(args).await?;
+    stats.print(clients, started.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catan_server::server::{serve, ServerState};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_tables_play_against_a_server() {
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+        let ms: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&ms, 0.5), Duration::from_millis(50));
+        assert_eq!(percentile(&ms, 0.99), Duration::from_millis(99));
+        assert_eq!(percentile(&ms, 1.0), Duration::from_millis(100));
+
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::ZERO;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Arc::new(state)));
+
+        let args = Args::parse_from([
+            "catan-loadtest",
+            "--url",
+            &format!("ws://{}", addr),
+            "--clients",
+            "4",
+            "--players",
+            "2",
+            "--max-moves",
+            "20",
+            "--think-ms",
+            "0",
+            "--ramp-ms",
+            "0",
+            "--timeout-secs",
+            "10",
+        ]);
+        let stats = run(args).await.unwrap();
+        assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+        assert_eq!(stats.latencies["connect"].len(), 4);
+        assert_eq!(stats.latencies["create_room"].len(), 2);
+        assert_eq!(stats.latencies["join_room"].len(), 2);
+        assert!(stats.moves > 0);
+        assert_eq!(stats.games_finished, 4);
+    }
+}
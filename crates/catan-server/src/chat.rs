@@ -0,0 +1,198 @@
+//! Chat outside what a room keeps: the lobby channel, mutes and the chat
+//! rate limit.
+//!
+//! Room chat still goes through the room's task with everything else in the
+//! room. Everyone connected who isn't in a room hears the lobby channel.
+//! Each player has a mute list, and the server leaves lines from the players
+//! on it out of what it sends them, in rooms and in the lobby alike. Chat
+//! and emotes also have a bucket of their own, well under the one for
+//! messages in general, so nobody can flood a channel within that limit.
+//! Mute lists and buckets last as long as the connection.
+
+use crate::limits::{RateLimit, TokenBucket};
+use crate::protocol::{ErrorCode, ServerMessage};
+use crate::server::ServerState;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Default chat lines a player may send in a burst
+pub const CHAT_BURST: u32 = 5;
+
+/// Default chat lines a player may keep sending each second
+pub const CHAT_PER_SECOND: u32 = 1;
+
+/// Most players one player may mute
+pub const MAX_MUTES: usize = 200;
+
+pub struct ChatState {
+    /// How fast each player may chat
+    pub limit: RateLimit,
+    buckets: DashMap<Uuid, TokenBucket>,
+    /// Who each player has muted
+    mutes: DashMap<Uuid, HashSet<Uuid>>,
+    /// Lobby lines sent so far
+    lobby_seq: AtomicU64,
+}
+
+impl Default for ChatState {
+    fn default() -> Self {
+        Self {
+            limit: RateLimit {
+                burst: CHAT_BURST,
+                per_second: CHAT_PER_SECOND,
+            },
+            buckets: DashMap::new(),
+            mutes: DashMap::new(),
+            lobby_seq: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ChatState {
+    /// Take a token for a chat line, if the player has one
+    pub fn allow(&self, player_id: Uuid) -> bool {
+        let now = Instant::now();
+        self.buckets
+            .entry(player_id)
+            .or_insert_with(|| TokenBucket::new(self.limit, now))
+            .take(now)
+    }
+
+    /// Whether `listener` has muted `speaker`
+    pub fn is_muted(&self, listener: Uuid, speaker: Uuid) -> bool {
+        self.mutes
+            .get(&listener)
+            .is_some_and(|muted| muted.contains(&speaker))
+    }
+
+    /// Mute `target` for `player_id`, returning their mute list
+    pub fn mute(&self, player_id: Uuid, target: Uuid) -> Vec<Uuid> {
+        let mut muted = self.mutes.entry(player_id).or_default();
+        if target != player_id && muted.len() < MAX_MUTES {
+            muted.insert(target);
+        }
+        muted.iter().copied().collect()
+    }
+
+    /// Hear `target` again, returning the mute list left
+    pub fn unmute(&self, player_id: Uuid, target: Uuid) -> Vec<Uuid> {
+        let Some(mut muted) = self.mutes.get_mut(&player_id) else {
+            return Vec::new();
+        };
+        muted.remove(&target);
+        muted.iter().copied().collect()
+    }
+
+    /// Drop what was kept for a player whose connection closed
+    pub fn forget(&self, player_id: Uuid) {
+        self.buckets.remove(&player_id);
+        self.mutes.remove(&player_id);
+    }
+}
+
+/// Whether a player may chat now. If not, they are told to slow down.
+pub fn may_speak(state: &ServerState, player_id: Uuid) -> bool {
+    if state.chat.allow(player_id) {
+        return true;
+    }
+    state.send_to_player(
+        player_id,
+        ServerMessage::Error {
+            message: "Slow down, you're chatting too fast".to_string(),
+            code: Some(ErrorCode::RateLimited),
+        },
+    );
+    false
+}
+
+/// Send a line to everyone in the lobby who hasn't muted its speaker
+pub fn lobby_chat(state: &ServerState, player_id: Uuid, player_name: String, message: String) {
+    let message = message.trim();
+    if message.is_empty() {
+        return;
+    }
+    let msg = ServerMessage::LobbyChatMessage {
+        player_id,
+        player_name,
+        message: message.to_string(),
+        seq: state.chat.lobby_seq.fetch_add(1, Ordering::Relaxed) + 1,
+    };
+    let socket_players = state.sessions.iter().map(|s| *s.key());
+    let rest_players = state.rest_sessions.iter().map(|s| s.player_id);
+    let listeners: HashSet<Uuid> = socket_players.chain(rest_players).collect();
+    for listener in listeners {
+        if !state.player_rooms.contains_key(&listener) && !state.chat.is_muted(listener, player_id)
+        {
+            state.send_to_player(listener, msg.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ClientMessage, Session};
+    use crate::server::handle_message;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_lobby_chat_skips_muted_speakers_and_players_in_rooms() {
+        let state = Arc::new(ServerState::new());
+        let mut inboxes = Vec::new();
+        let players: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for &player_id in &players {
+            let (sender, inbox) = mpsc::unbounded_channel();
+            state.player_senders.insert(player_id, sender);
+            let session = Session {
+                protocol_version: crate::protocol::PROTOCOL_VERSION,
+                client_name: "test".to_string(),
+                features: Vec::new(),
+            };
+            state.sessions.insert(player_id, session);
+            inboxes.push(inbox);
+        }
+        let [ana, ben, cai] = players[..] else {
+            unreachable!()
+        };
+        state.player_rooms.insert(cai, Uuid::new_v4());
+
+        handle_message(ben, ClientMessage::Mute { player_id: ana }, &state);
+        assert!(matches!(
+            inboxes[1].try_recv(),
+            Ok(ServerMessage::MuteList { muted }) if muted == vec![ana]
+        ));
+        let say = |message: &str| ClientMessage::LobbyChat {
+            player_name: "Ana".to_string(),
+            message: message.to_string(),
+        };
+        handle_message(ana, say("anyone up for a game?"), &state);
+        assert!(matches!(
+            inboxes[0].try_recv(),
+            Ok(ServerMessage::LobbyChatMessage { seq: 1, .. })
+        ));
+        assert!(inboxes[1].try_recv().is_err());
+        assert!(inboxes[2].try_recv().is_err());
+
+        // Chat has its own, tighter limit
+        for _ in 1..CHAT_BURST {
+            handle_message(ana, say("hello?"), &state);
+        }
+        handle_message(ana, say("hello??"), &state);
+        let sent: Vec<_> = std::iter::from_fn(|| inboxes[0].try_recv().ok()).collect();
+        assert_eq!(sent.len(), CHAT_BURST as usize);
+        assert!(matches!(
+            sent.last(),
+            Some(ServerMessage::Error {
+                code: Some(ErrorCode::RateLimited),
+                ..
+            })
+        ));
+
+        handle_message(ben, ClientMessage::Unmute { player_id: ana }, &state);
+        assert!(!state.chat.is_muted(ben, ana));
+    }
+}
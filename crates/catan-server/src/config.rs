@@ -0,0 +1,633 @@
+//! Server settings.
+//!
+//! Settings start from the defaults, are read over from a TOML file given
+//! with `--config`, and then from command-line flags. Most flags can also
+//! be set through the environment variables the server has always read,
+//! such as `SERVER_ADDR` or `ROOM_DB`. Run with `--print-config` to see the
+//! settings that would be used, as TOML, secrets hidden.
+//!
+//! ```toml
+//! [server]
+//! addr = "0.0.0.0:8080"
+//! http_addr = "0.0.0.0:8081"
+//! grpc_addr = "0.0.0.0:50051"
+//!
+//! [tls]
+//! cert = "/etc/kopiatan/cert.pem"
+//! key = "/etc/kopiatan/key.pem"
+//!
+//! [storage]
+//! backend = "sqlite"
+//! path = "rooms.db"
+//!
+//! [rooms]
+//! max_rooms = 500
+//!
+//! [log]
+//! format = "json"
+//! ```
+
+use crate::auth::TicketSigner;
+use crate::chat;
+use crate::limits::{self, RateLimit};
+use crate::room::DEFAULT_DISCONNECT_GRACE;
+use crate::server::{
+    ServerState, BOT_MOVE_DELAY, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT, ROOM_IDLE_TTL,
+};
+use crate::storage::SqliteStore;
+use catan_core::BotDifficulty;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// What `--print-config` shows in place of a secret
+const HIDDEN: &str = "<hidden>";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid setting: {0}")]
+    Invalid(String),
+}
+
+/// Everything the server can be set up with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    /// Serve WebSockets over TLS; plain when left out
+    pub tls: Option<TlsConfig>,
+    pub storage: StorageConfig,
+    pub rooms: RoomsConfig,
+    pub timers: TimersConfig,
+    pub limits: LimitsConfig,
+    pub bots: BotsConfig,
+    pub log: LogConfig,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Where players connect over WebSocket
+    pub addr: SocketAddr,
+    /// Where the health checks and admin routes are served
+    pub http_addr: SocketAddr,
+    /// Where the gRPC API is served; off when left out
+    pub grpc_addr: Option<SocketAddr>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: ([0, 0, 0, 0], 8080).into(),
+            http_addr: ([0, 0, 0, 0], 8081).into(),
+            grpc_addr: None,
+        }
+    }
+}
+
+/// Certificate chain and private key, both PEM
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// Where rooms are saved to survive a restart.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// Not saved; rooms are lost on a restart
+    #[default]
+    Memory,
+    /// An SQLite file
+    Sqlite { path: PathBuf },
+    /// Redis, which several instances can share
+    Redis { url: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomsConfig {
+    /// Most rooms open at once; no limit when left out
+    pub max_rooms: Option<usize>,
+    /// Keep each room's audit trail as JSON lines in this directory
+    pub audit_dir: Option<PathBuf>,
+    /// Let hosts have their game's events POSTed to a webhook
+    pub webhooks: bool,
+}
+
+impl Default for RoomsConfig {
+    fn default() -> Self {
+        Self {
+            max_rooms: None,
+            audit_dir: None,
+            webhooks: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimersConfig {
+    /// Seconds a disconnected player's seat waits before a bot takes over
+    pub disconnect_grace_secs: u64,
+    /// Seconds between the pings sent to each client
+    pub heartbeat_interval_secs: u64,
+    /// Seconds a client may stay silent before it counts as gone
+    pub heartbeat_timeout_secs: u64,
+    /// Minutes a room with nobody connected is kept before it is put away
+    pub room_idle_mins: u64,
+}
+
+impl Default for TimersConfig {
+    fn default() -> Self {
+        Self {
+            disconnect_grace_secs: DEFAULT_DISCONNECT_GRACE.as_secs(),
+            heartbeat_interval_secs: HEARTBEAT_INTERVAL.as_secs(),
+            heartbeat_timeout_secs: HEARTBEAT_TIMEOUT.as_secs(),
+            room_idle_mins: ROOM_IDLE_TTL.as_secs() / 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Messages each connection may send in a burst
+    pub rate_limit_burst: u32,
+    /// Messages each connection may keep sending each second
+    pub rate_limit_per_sec: u32,
+    /// Largest message a client may send, in bytes
+    pub max_message_bytes: usize,
+    /// Chat lines and emotes each player may send in a burst
+    pub chat_burst: u32,
+    /// Chat lines and emotes each player may keep sending each second
+    pub chat_per_sec: u32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            rate_limit_burst: limits::BURST,
+            rate_limit_per_sec: limits::PER_SECOND,
+            max_message_bytes: limits::MAX_MESSAGE_SIZE,
+            chat_burst: chat::CHAT_BURST,
+            chat_per_sec: chat::CHAT_PER_SECOND,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotsConfig {
+    /// Milliseconds to pause before each bot move
+    pub move_delay_ms: u64,
+    /// How well the bot giving hints plays, and the best a player may
+    /// ask for
+    pub hint_difficulty: BotDifficulty,
+}
+
+impl Default for BotsConfig {
+    fn default() -> Self {
+        Self {
+            move_delay_ms: BOT_MOVE_DELAY.as_millis() as u64,
+            hint_difficulty: BotDifficulty::Hard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Which logs to keep, as a `tracing` filter such as `info` or
+    /// `catan_server=debug`
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Full,
+            level: "info".to_string(),
+        }
+    }
+}
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One readable line per event
+    Full,
+    /// Shorter lines
+    Compact,
+    /// Several lines per event, for reading by eye
+    Pretty,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Signs the tickets that keep a player's identity across connections
+    pub secret: Option<String>,
+    /// Opens the admin routes to whoever holds it
+    pub admin_token: Option<String>,
+}
+
+/// Command-line flags. Each one set wins over the config file.
+#[derive(Debug, Default, Parser)]
+#[command(name = "catan-server", about = "Kopiatan multiplayer game server")]
+pub struct Cli {
+    /// TOML file to read settings from
+    #[arg(long, env = "KOPIATAN_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Print the settings that would be used, as TOML, and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    #[arg(long, env = "SERVER_ADDR")]
+    pub addr: Option<SocketAddr>,
+    #[arg(long, env = "HTTP_ADDR")]
+    pub http_addr: Option<SocketAddr>,
+    /// Serve the gRPC API here
+    #[arg(long, env = "GRPC_ADDR")]
+    pub grpc_addr: Option<SocketAddr>,
+    /// PEM certificate chain; serves over TLS with --tls-key
+    #[arg(long, env = "TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    #[arg(long, env = "TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Save rooms to this SQLite file
+    #[arg(long, env = "ROOM_DB", conflicts_with = "redis_url")]
+    pub room_db: Option<PathBuf>,
+    /// Save rooms to Redis
+    #[arg(long, env = "REDIS_URL")]
+    pub redis_url: Option<String>,
+
+    #[arg(long, env = "MAX_ROOMS")]
+    pub max_rooms: Option<usize>,
+    #[arg(long, env = "AUDIT_DIR")]
+    pub audit_dir: Option<PathBuf>,
+    #[arg(long, env = "WEBHOOKS")]
+    pub webhooks: Option<bool>,
+
+    #[arg(long, env = "DISCONNECT_GRACE_SECS")]
+    pub disconnect_grace_secs: Option<u64>,
+    #[arg(long, env = "HEARTBEAT_INTERVAL_SECS")]
+    pub heartbeat_interval_secs: Option<u64>,
+    #[arg(long, env = "HEARTBEAT_TIMEOUT_SECS")]
+    pub heartbeat_timeout_secs: Option<u64>,
+    #[arg(long, env = "ROOM_IDLE_MINS")]
+    pub room_idle_mins: Option<u64>,
+
+    #[arg(long, env = "RATE_LIMIT_BURST")]
+    pub rate_limit_burst: Option<u32>,
+    #[arg(long, env = "RATE_LIMIT_PER_SEC")]
+    pub rate_limit_per_sec: Option<u32>,
+    #[arg(long, env = "MAX_MESSAGE_BYTES")]
+    pub max_message_bytes: Option<usize>,
+    #[arg(long, env = "CHAT_BURST")]
+    pub chat_burst: Option<u32>,
+    #[arg(long, env = "CHAT_PER_SEC")]
+    pub chat_per_sec: Option<u32>,
+
+    #[arg(long, env = "BOT_MOVE_DELAY_MS")]
+    pub bot_move_delay_ms: Option<u64>,
+
+    #[arg(long, env = "LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    #[arg(long, env = "RUST_LOG")]
+    pub log_level: Option<String>,
+
+    #[arg(long, env = "AUTH_SECRET", hide_env_values = true)]
+    pub auth_secret: Option<String>,
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+}
+
+impl Cli {
+    /// The settings to run with: the config file, if any, with the flags
+    /// laid over it, checked
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        self.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(flag: &Option<T>, setting: &mut T) {
+            if let Some(value) = flag {
+                *setting = value.clone();
+            }
+        }
+
+        set(&self.addr, &mut config.server.addr);
+        set(&self.http_addr, &mut config.server.http_addr);
+        if self.grpc_addr.is_some() {
+            config.server.grpc_addr = self.grpc_addr;
+        }
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            config.tls = Some(TlsConfig {
+                cert: cert.clone(),
+                key: key.clone(),
+            });
+        }
+        if let Some(path) = &self.room_db {
+            config.storage = StorageConfig::Sqlite { path: path.clone() };
+        }
+        if let Some(url) = &self.redis_url {
+            config.storage = StorageConfig::Redis { url: url.clone() };
+        }
+
+        if self.max_rooms.is_some() {
+            config.rooms.max_rooms = self.max_rooms;
+        }
+        if self.audit_dir.is_some() {
+            config.rooms.audit_dir = self.audit_dir.clone();
+        }
+        set(&self.webhooks, &mut config.rooms.webhooks);
+
+        let timers = &mut config.timers;
+        set(
+            &self.disconnect_grace_secs,
+            &mut timers.disconnect_grace_secs,
+        );
+        set(
+            &self.heartbeat_interval_secs,
+            &mut timers.heartbeat_interval_secs,
+        );
+        set(
+            &self.heartbeat_timeout_secs,
+            &mut timers.heartbeat_timeout_secs,
+        );
+        set(&self.room_idle_mins, &mut timers.room_idle_mins);
+
+        let limits = &mut config.limits;
+        set(&self.rate_limit_burst, &mut limits.rate_limit_burst);
+        set(&self.rate_limit_per_sec, &mut limits.rate_limit_per_sec);
+        set(&self.max_message_bytes, &mut limits.max_message_bytes);
+        set(&self.chat_burst, &mut limits.chat_burst);
+        set(&self.chat_per_sec, &mut limits.chat_per_sec);
+
+        set(&self.bot_move_delay_ms, &mut config.bots.move_delay_ms);
+        set(&self.log_format, &mut config.log.format);
+        set(&self.log_level, &mut config.log.level);
+
+        if self.auth_secret.is_some() {
+            config.auth.secret = self.auth_secret.clone();
+        }
+        if self.admin_token.is_some() {
+            config.auth.admin_token = self.admin_token.clone();
+        }
+    }
+}
+
+impl Config {
+    /// Read settings from a TOML file; anything it leaves out keeps its
+    /// default
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Catch settings the server couldn't run with, before it starts
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| Err(ConfigError::Invalid(message.to_string()));
+
+        if self.server.addr == self.server.http_addr {
+            return invalid("server.addr and server.http_addr must differ");
+        }
+        if let Some(grpc_addr) = self.server.grpc_addr {
+            if !cfg!(feature = "grpc") {
+                return invalid("grpc_addr needs the server built with the `grpc` feature");
+            }
+            if grpc_addr == self.server.addr || grpc_addr == self.server.http_addr {
+                return invalid("server.grpc_addr must differ from the other addresses");
+            }
+        }
+        if self.tls.is_some() && !cfg!(feature = "tls") {
+            return invalid("tls needs the server built with the `tls` feature");
+        }
+        match &self.storage {
+            StorageConfig::Memory => {}
+            StorageConfig::Sqlite { path } if path.as_os_str().is_empty() => {
+                return invalid("storage.path must name a file");
+            }
+            StorageConfig::Sqlite { .. } => {}
+            StorageConfig::Redis { .. } if !cfg!(feature = "redis") => {
+                return invalid("Redis storage needs the server built with the `redis` feature");
+            }
+            StorageConfig::Redis { url } if url.is_empty() => {
+                return invalid("storage.url must be a Redis URL");
+            }
+            StorageConfig::Redis { .. } => {}
+        }
+        if self.rooms.max_rooms == Some(0) {
+            return invalid("rooms.max_rooms must be at least 1");
+        }
+
+        let timers = &self.timers;
+        if timers.heartbeat_interval_secs == 0 {
+            return invalid("timers.heartbeat_interval_secs must be at least 1");
+        }
+        if timers.heartbeat_timeout_secs <= timers.heartbeat_interval_secs {
+            return invalid("timers.heartbeat_timeout_secs must be longer than the interval");
+        }
+        if timers.room_idle_mins == 0 {
+            return invalid("timers.room_idle_mins must be at least 1");
+        }
+
+        let limits = &self.limits;
+        if limits.rate_limit_burst == 0 || limits.rate_limit_per_sec == 0 {
+            return invalid("limits.rate_limit_burst and rate_limit_per_sec must be at least 1");
+        }
+        if limits.chat_burst == 0 || limits.chat_per_sec == 0 {
+            return invalid("limits.chat_burst and chat_per_sec must be at least 1");
+        }
+        if limits.max_message_bytes < 1024 {
+            return invalid("limits.max_message_bytes must be at least 1024");
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log.level) {
+            return Err(ConfigError::Invalid(format!("log.level: {}", e)));
+        }
+        Ok(())
+    }
+
+    /// The settings as TOML, with secrets hidden
+    pub fn to_toml(&self) -> String {
+        let mut shown = self.clone();
+        let auth = &mut shown.auth;
+        for secret in [&mut auth.secret, &mut auth.admin_token] {
+            if secret.is_some() {
+                *secret = Some(HIDDEN.to_string());
+            }
+        }
+        toml::to_string(&shown).expect("settings are always valid TOML")
+    }
+
+    /// A server set up with these settings, its store opened
+    pub fn state(&self) -> anyhow::Result<ServerState> {
+        let timers = &self.timers;
+        let mut state =
+            ServerState::with_disconnect_grace(Duration::from_secs(timers.disconnect_grace_secs));
+        state.heartbeat_interval = Duration::from_secs(timers.heartbeat_interval_secs);
+        state.heartbeat_timeout = Duration::from_secs(timers.heartbeat_timeout_secs);
+        state.room_idle_ttl = Duration::from_secs(timers.room_idle_mins * 60);
+
+        state.rate_limit = RateLimit {
+            burst: self.limits.rate_limit_burst,
+            per_second: self.limits.rate_limit_per_sec,
+        };
+        state.max_message_size = self.limits.max_message_bytes;
+        state.chat.limit = RateLimit {
+            burst: self.limits.chat_burst,
+            per_second: self.limits.chat_per_sec,
+        };
+        state.max_rooms = self.rooms.max_rooms;
+        state.audit_dir = self.rooms.audit_dir.clone();
+        if !self.rooms.webhooks {
+            state.webhook_client = None;
+        }
+        state.bot_move_delay = Duration::from_millis(self.bots.move_delay_ms);
+        state.hint_difficulty = self.bots.hint_difficulty;
+        state.admin_token = self.auth.admin_token.clone();
+
+        match &self.storage {
+            StorageConfig::Memory => {}
+            StorageConfig::Sqlite { path } => {
+                state = state.with_store(Box::new(SqliteStore::open(path)?));
+            }
+            #[cfg(feature = "redis")]
+            StorageConfig::Redis { url } => {
+                state = state.with_store(Box::new(crate::storage::RedisStore::open(url)?));
+            }
+            #[cfg(not(feature = "redis"))]
+            StorageConfig::Redis { .. } => {
+                anyhow::bail!("Redis storage needs the server built with the `redis` feature")
+            }
+        }
+        if let Some(secret) = &self.auth.secret {
+            state = state.with_tickets(TicketSigner::new(secret));
+        }
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            state.tls = Some(tls.acceptor()?);
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    /// Load the certificate and key for accepting TLS connections
+    fn acceptor(&self) -> anyhow::Result<tokio_rustls::TlsAcceptor> {
+        use anyhow::Context;
+        use std::fs::File;
+        use std::io::BufReader;
+        use std::sync::Arc;
+        use tokio_rustls::rustls::{crypto, ServerConfig};
+
+        let open = |path: &Path| {
+            File::open(path)
+                .map(BufReader::new)
+                .with_context(|| format!("Could not read {}", path.display()))
+        };
+        let certs = rustls_pemfile::certs(&mut open(&self.cert)?).collect::<Result<Vec<_>, _>>()?;
+        let key = rustls_pemfile::private_key(&mut open(&self.key)?)?
+            .with_context(|| format!("No private key in {}", self.key.display()))?;
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_no_client_auth()
+                .with_single_cert(certs, key)?;
+        Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_win_over_the_file_and_settings_are_checked() {
+        let path = std::env::temp_dir().join(format!("kopiatan-{}.toml", uuid::Uuid::new_v4()));
+        let file = r#"
+            [server]
+            addr = "127.0.0.1:9000"
+
+            [storage]
+            backend = "sqlite"
+            path = "rooms.db"
+
+            [timers]
+            heartbeat_interval_secs = 5
+
+            [auth]
+            secret = "hush"
+        "#;
+        std::fs::write(&path, file).unwrap();
+        let cli = Cli::try_parse_from([
+            "catan-server",
+            "--config",
+            path.to_str().unwrap(),
+            "--addr",
+            "127.0.0.1:9100",
+            "--log-format",
+            "json",
+        ])
+        .unwrap();
+        let config = cli.load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.server.addr, "127.0.0.1:9100".parse().unwrap());
+        assert_eq!(config.server.http_addr, ServerConfig::default().http_addr);
+        assert_eq!(
+            config.storage,
+            StorageConfig::Sqlite {
+                path: "rooms.db".into()
+            }
+        );
+        assert_eq!(config.timers.heartbeat_interval_secs, 5);
+        assert_eq!(config.log.format, LogFormat::Json);
+
+        // What is printed reads back as the same settings, less the secret
+        let printed = config.to_toml();
+        assert!(!printed.contains("hush"));
+        let read_back: Config = toml::from_str(&printed).unwrap();
+        assert_eq!(read_back.server, config.server);
+        assert_eq!(read_back.timers, config.timers);
+
+        let mut bad = config.clone();
+        bad.timers.heartbeat_timeout_secs = 5;
+        assert!(matches!(bad.validate(), Err(ConfigError::Invalid(_))));
+        let mut bad = config.clone();
+        bad.server.grpc_addr = Some(config.server.addr);
+        assert!(matches!(bad.validate(), Err(ConfigError::Invalid(_))));
+        // A misspelt setting is caught rather than ignored
+        assert!(toml::from_str::<Config>("[rooms]\nmax_room = 3").is_err());
+    }
+}
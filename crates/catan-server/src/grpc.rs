@@ -0,0 +1,385 @@
+//! The room and game APIs over gRPC, for bots and tooling.
+//!
+//! The service is described in `proto/kopiatan.proto`. It runs on sessions
+//! just like the REST API's, so a session opened here works the same way:
+//! the token goes with every call as `authorization: Bearer <token>`
+//! metadata, and `Subscribe` streams what a socket would have been sent.
+
+// Every call answers with tonic's `Status`, large as it is
+#![allow(clippy::result_large_err)]
+
+use crate::protocol::{
+    ClientMessage, PlayerInfo, RoomInfo, RoomQuery, RoomSettings, RoomStatus, ServerMessage,
+};
+use crate::rest::{self, RestSession, SessionError};
+use crate::server::ServerState;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+use tracing::info;
+use uuid::Uuid;
+
+/// The messages and service generated from `kopiatan.proto`
+pub mod proto {
+    tonic::include_proto!("kopiatan.v1");
+}
+
+use proto::kopiatan_server::{Kopiatan, KopiatanServer};
+use proto::{
+    ActionResult, CloseSessionRequest, CloseSessionResponse, CreateRoomRequest, GetStateRequest,
+    JoinRoomRequest, ListRoomsRequest, OpenSessionRequest, Player, PlayerView, Room, RoomList,
+    SendRequest, SendResponse, Session, SubmitActionRequest, SubscribeRequest,
+};
+
+/// Serve gRPC on `addr` until the server shuts down
+pub async fn run_grpc(addr: SocketAddr, state: Arc<ServerState>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("gRPC listening on {}", addr);
+    serve(listener, state).await
+}
+
+async fn serve(listener: TcpListener, state: Arc<ServerState>) -> anyhow::Result<()> {
+    let mut closing = state.closing.subscribe();
+    tonic::transport::Server::builder()
+        .add_service(KopiatanServer::new(Service { state }))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+            let _ = closing.wait_for(|&closing| closing).await;
+        })
+        .await?;
+    Ok(())
+}
+
+struct Service {
+    state: Arc<ServerState>,
+}
+
+impl Service {
+    /// The session a call's token belongs to
+    fn session<T>(&self, request: &Request<T>) -> Result<Arc<RestSession>, Status> {
+        request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| rest::find_session(&self.state, token))
+            .ok_or_else(|| Status::unauthenticated("Unknown session"))
+    }
+}
+
+impl From<SessionError> for Status {
+    fn from(e: SessionError) -> Self {
+        let message = e.to_string();
+        match e {
+            SessionError::ShuttingDown => Status::unavailable(message),
+            SessionError::NotInRoom => Status::failed_precondition(message),
+            SessionError::Refused(_) => Status::invalid_argument(message),
+            SessionError::NoAnswer => Status::deadline_exceeded(message),
+        }
+    }
+}
+
+impl From<RoomInfo> for Room {
+    fn from(room: RoomInfo) -> Self {
+        let status = match room.status {
+            RoomStatus::Waiting => proto::RoomStatus::Waiting,
+            RoomStatus::InGame => proto::RoomStatus::InGame,
+            RoomStatus::Finished => proto::RoomStatus::Finished,
+        };
+        Room {
+            id: room.id.to_string(),
+            name: room.name,
+            players: room.players.into_iter().map(Player::from).collect(),
+            max_players: room.max_players.into(),
+            host_id: room.host_id.to_string(),
+            status: status.into(),
+            paused: room.paused,
+            settings_json: to_json(&room.settings),
+        }
+    }
+}
+
+impl From<PlayerInfo> for Player {
+    fn from(player: PlayerInfo) -> Self {
+        Player {
+            id: player.id.to_string(),
+            name: player.name,
+            ready: player.ready,
+            connected: player.connected,
+            bot: player.bot.is_some(),
+            stand_in: player.stand_in,
+        }
+    }
+}
+
+impl From<ServerMessage> for proto::ServerMessage {
+    fn from(msg: ServerMessage) -> Self {
+        let json = serde_json::to_value(&msg).unwrap_or_default();
+        proto::ServerMessage {
+            r#type: json["type"].as_str().unwrap_or_default().to_string(),
+            json: json.to_string(),
+        }
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str, what: &str) -> Result<T, Status> {
+    serde_json::from_str(json)
+        .map_err(|e| Status::invalid_argument(format!("Invalid {}: {}", what, e)))
+}
+
+#[tonic::async_trait]
+impl Kopiatan for Service {
+    type SubscribeStream = ReceiverStream<Result<proto::ServerMessage, Status>>;
+
+    async fn open_session(
+        &self,
+        request: Request<OpenSessionRequest>,
+    ) -> Result<Response<Session>, Status> {
+        let session = rest::open_session(&self.state, request.into_inner().name)?;
+        Ok(Response::new(Session {
+            player_id: session.player_id.to_string(),
+            token: session.token,
+        }))
+    }
+
+    async fn close_session(
+        &self,
+        request: Request<CloseSessionRequest>,
+    ) -> Result<Response<CloseSessionResponse>, Status> {
+        let session = self.session(&request)?;
+        rest::end_session(&self.state, &session);
+        Ok(Response::new(CloseSessionResponse {}))
+    }
+
+    async fn list_rooms(
+        &self,
+        request: Request<ListRoomsRequest>,
+    ) -> Result<Response<RoomList>, Status> {
+        let query_json = &request.get_ref().query_json;
+        let query = if query_json.is_empty() {
+            RoomQuery::default()
+        } else {
+            from_json(query_json, "query")?
+        };
+        let (rooms, next_cursor) = self.state.list_rooms(&query);
+        Ok(Response::new(RoomList {
+            rooms: rooms.into_iter().map(Room::from).collect(),
+            next_cursor: next_cursor.map(|id| id.to_string()).unwrap_or_default(),
+        }))
+    }
+
+    async fn create_room(
+        &self,
+        request: Request<CreateRoomRequest>,
+    ) -> Result<Response<Room>, Status> {
+        let session = self.session(&request)?;
+        let request = request.into_inner();
+        let settings = if request.settings_json.is_empty() {
+            RoomSettings::default()
+        } else {
+            from_json(&request.settings_json, "settings")?
+        };
+        let max_players = u8::try_from(request.max_players)
+            .map_err(|_| Status::invalid_argument("Too many players"))?;
+        let room = session
+            .create_room(&self.state, max_players, settings)
+            .await?;
+        Ok(Response::new(room.into()))
+    }
+
+    async fn join_room(&self, request: Request<JoinRoomRequest>) -> Result<Response<Room>, Status> {
+        let session = self.session(&request)?;
+        let room_id = Uuid::parse_str(&request.get_ref().room_id)
+            .map_err(|_| Status::invalid_argument("Invalid room ID"))?;
+        let room = session.join_room(&self.state, room_id).await?;
+        Ok(Response::new(room.into()))
+    }
+
+    async fn send(&self, request: Request<SendRequest>) -> Result<Response<SendResponse>, Status> {
+        let session = self.session(&request)?;
+        let msg: ClientMessage = from_json(&request.get_ref().message_json, "message")?;
+        session.send(&self.state, msg);
+        Ok(Response::new(SendResponse {}))
+    }
+
+    async fn get_state(
+        &self,
+        request: Request<GetStateRequest>,
+    ) -> Result<Response<PlayerView>, Status> {
+        let session = self.session(&request)?;
+        let view = session.view(&self.state).await?;
+        Ok(Response::new(PlayerView {
+            room: Some(view.room.into()),
+            game_json: view.game.as_ref().map(to_json).unwrap_or_default(),
+            valid_actions_json: view.valid_actions.iter().map(to_json).collect(),
+        }))
+    }
+
+    async fn submit_action(
+        &self,
+        request: Request<SubmitActionRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let session = self.session(&request)?;
+        let request = request.into_inner();
+        let action = from_json(&request.action_json, "action")?;
+        let request_id = Some(request.request_id).filter(|id| !id.is_empty());
+        let answer = session.act(&self.state, action, request_id).await?;
+        let ServerMessage::ActionResult {
+            success,
+            events,
+            error,
+            error_code,
+            game_error,
+            request_id,
+        } = answer
+        else {
+            unreachable!("only an ActionResult answers an action")
+        };
+        Ok(Response::new(ActionResult {
+            success,
+            error: error.unwrap_or_default(),
+            events_json: events.iter().map(to_json).collect(),
+            request_id: request_id.unwrap_or_default(),
+            error_code: error_code.map_or(0, u32::from),
+            game_error_json: game_error.as_ref().map(to_json).unwrap_or_default(),
+        }))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let session = self.session(&request)?;
+        let (sender, receiver) = mpsc::channel(32);
+        tokio::spawn(async move {
+            while let Some(msg) = session.next_message().await {
+                if sender.send(Ok(msg.into())).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::kopiatan_client::KopiatanClient;
+    use super::*;
+    use tonic::metadata::MetadataValue;
+    use tonic::transport::Channel;
+
+    fn authorized<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        let value: MetadataValue<_> = format!("Bearer {}", token).parse().unwrap();
+        request.metadata_mut().insert("authorization", value);
+        request
+    }
+
+    #[tokio::test]
+    async fn test_a_game_can_be_started_and_played_over_grpc() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = std::time::Duration::ZERO;
+        let state = Arc::new(state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Arc::clone(&state)));
+        let mut client: KopiatanClient<Channel> =
+            KopiatanClient::connect(format!("http://{}", addr))
+                .await
+                .unwrap();
+
+        let refused = client
+            .get_state(authorized(GetStateRequest {}, "guess"))
+            .await
+            .unwrap_err();
+        assert_eq!(refused.code(), tonic::Code::Unauthenticated);
+
+        let session = client
+            .open_session(OpenSessionRequest {
+                name: "Script".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let token = session.token.as_str();
+        let mut messages = client
+            .subscribe(authorized(SubscribeRequest {}, token))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let create = CreateRoomRequest {
+            max_players: 2,
+            settings_json: String::new(),
+        };
+        let room = client
+            .create_room(authorized(create, token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(room.host_id, session.player_id);
+        assert_eq!(room.status(), proto::RoomStatus::Waiting);
+
+        for msg in [
+            r#"{"type":"AddBot","payload":{"difficulty":"Easy"}}"#,
+            r#"{"type":"StartGame"}"#,
+        ] {
+            let send = SendRequest {
+                message_json: msg.to_string(),
+            };
+            client.send(authorized(send, token)).await.unwrap();
+        }
+        loop {
+            let msg = messages.message().await.unwrap().unwrap();
+            if msg.r#type == "GameStarted" {
+                break;
+            }
+        }
+
+        // Wait until it is our move
+        let mut view = None;
+        for _ in 0..100 {
+            let polled = client
+                .get_state(authorized(GetStateRequest {}, token))
+                .await
+                .unwrap()
+                .into_inner();
+            if !polled.valid_actions_json.is_empty() {
+                view = Some(polled);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let view = view.expect("our turn came");
+        assert_eq!(
+            view.room.as_ref().unwrap().status(),
+            proto::RoomStatus::InGame
+        );
+        assert!(!view.game_json.is_empty());
+
+        let action = SubmitActionRequest {
+            action_json: view.valid_actions_json[0].clone(),
+            request_id: "mine".to_string(),
+        };
+        let result = client
+            .submit_action(authorized(action, token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(result.success);
+        assert_eq!(result.request_id, "mine");
+
+        client
+            .close_session(authorized(CloseSessionRequest {}, token))
+            .await
+            .unwrap();
+        assert!(state.rest_sessions.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Plain HTTP beside the WebSocket server.
+//!
+//! Orchestrators and load balancers can't speak the game protocol, so the
+//! server also answers a few HTTP routes on a port of its own:
+//!
+//! - `GET /healthz`: the process is up. Always 200 while it serves.
+//! - `GET /readyz`: the server can take players. 503 while the room store
+//!   doesn't answer or the server is shutting down, so traffic moves
+//!   elsewhere.
+//!
+//! Both reply with the server's [`Health`] as JSON. The operators' routes
+//! in [`crate::admin`] and the REST API in [`crate::rest`] live here too.
+
+use crate::admin;
+use crate::protocol::Health;
+use crate::rest;
+use crate::server::ServerState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::info;
+
+/// The HTTP routes, answering from `state`
+pub fn router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .nest("/admin", admin::router(Arc::clone(&state)))
+        .nest("/api", rest::router())
+        .with_state(state)
+}
+
+/// Serve the HTTP routes on `addr` until the server closes its connections
+pub async fn run_http(addr: SocketAddr, state: Arc<ServerState>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("HTTP endpoints listening on {}", addr);
+    let mut closing = state.closing.subscribe();
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            let _ = closing.wait_for(|&closing| closing).await;
+        })
+        .await?;
+    Ok(())
+}
+
+async fn healthz(State(state): State<Arc<ServerState>>) -> Json<Health> {
+    Json(state.health())
+}
+
+async fn readyz(State(state): State<Arc<ServerState>>) -> (StatusCode, Json<Health>) {
+    let health = state.health();
+    let status = if health.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Send a request and read back the status line and body
+    pub(crate) async fn request(
+        addr: SocketAddr,
+        method: &str,
+        path: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let auth = token
+            .map(|t| format!("Authorization: Bearer {}\r\n", t))
+            .unwrap_or_default();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n{}\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            auth,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    async fn get_status(addr: SocketAddr, path: &str) -> String {
+        request(addr, "GET", path, None, "").await.0
+    }
+
+    #[tokio::test]
+    async fn test_readiness_drops_when_shutting_down() {
+        let state = Arc::new(ServerState::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(Arc::clone(&state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        assert_eq!(get_status(addr, "/healthz").await, "HTTP/1.1 200 OK");
+        assert_eq!(get_status(addr, "/readyz").await, "HTTP/1.1 200 OK");
+
+        state.shutting_down.store(true, Ordering::Relaxed);
+        assert_eq!(get_status(addr, "/healthz").await, "HTTP/1.1 200 OK");
+        assert_eq!(
+            get_status(addr, "/readyz").await,
+            "HTTP/1.1 503 Service Unavailable"
+        );
+        assert!(!state.health().ready);
+    }
+}
@@ -1,5 +1,21 @@
 //! Kopiatan multiplayer server library.
 
+pub mod actor;
+pub mod admin;
+pub mod audit;
+pub mod auth;
+pub mod chat;
+pub mod config;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
+pub mod limits;
 pub mod protocol;
+pub mod replay;
+pub mod rest;
 pub mod room;
 pub mod server;
+pub mod stats;
+pub mod storage;
+pub mod tournament;
+pub mod webhook;
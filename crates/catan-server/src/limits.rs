@@ -0,0 +1,98 @@
+//! Limits on what one connection may send.
+//!
+//! Each connection gets a [`TokenBucket`]: every message, pings included,
+//! takes a token, and tokens come back at a steady rate up to a small
+//! burst. A client that runs dry has its messages dropped until it slows
+//! down, so one spammer can't drown a room. A message over
+//! [`MAX_MESSAGE_SIZE`] is refused by the WebSocket layer as it arrives,
+//! before it is buffered, and the connection is closed.
+
+use std::time::{Duration, Instant};
+
+/// Default largest message a client may send, in bytes. The biggest real
+/// message, a chat line or a trade, is far smaller.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Default messages a client may send in a burst
+pub const BURST: u32 = 20;
+
+/// Default messages a client may keep sending each second
+pub const PER_SECOND: u32 = 10;
+
+/// How fast one connection may send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            burst: BURST,
+            per_second: PER_SECOND,
+        }
+    }
+}
+
+/// Tokens left for one connection.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            refilled_at: now,
+        }
+    }
+
+    /// Take a token for a message, if there is one
+    pub fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.refilled_at = now;
+        self.tokens = (self.tokens + self.refill(elapsed)).min(self.limit.burst as f64);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&self, elapsed: Duration) -> f64 {
+        elapsed.as_secs_f64() * self.limit.per_second as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_a_burst_then_a_steady_rate() {
+        let limit = RateLimit {
+            burst: 3,
+            per_second: 2,
+        };
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(limit, start);
+        assert!((0..3).all(|_| bucket.take(start)));
+        assert!(!bucket.take(start));
+
+        // Half a second brings one token back, and no more
+        let later = start + Duration::from_millis(500);
+        assert!(bucket.take(later));
+        assert!(!bucket.take(later));
+
+        // A long rest refills only up to the burst
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!((0..5).filter(|_| bucket.take(much_later)).count(), 3);
+    }
+}
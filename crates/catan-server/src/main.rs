@@ -1,34 +1,87 @@
 //! Kopiatan multiplayer game server.
 
-use std::net::SocketAddr;
+use catan_server::config::{Cli, LogFormat};
+use catan_server::http;
+use catan_server::server;
+use clap::Parser;
 use std::sync::Arc;
-use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-
-mod protocol;
-mod room;
-mod server;
-
-use server::ServerState;
+use tracing::{error, info};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = cli.load()?;
+    if cli.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+
     // Initialize tracing
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    // Parse address from env or use default
-    let addr: SocketAddr = std::env::var("SERVER_ADDR")
-        .unwrap_or_else(|_| "0.0.0.0:8080".into())
-        .parse()?;
+    let registry = tracing_subscriber::registry().with(EnvFilter::try_new(&config.log.level)?);
+    match config.log.format {
+        LogFormat::Full => registry.with(fmt::layer()).init(),
+        LogFormat::Compact => registry.with(fmt::layer().compact()).init(),
+        LogFormat::Pretty => registry.with(fmt::layer().pretty()).init(),
+        LogFormat::Json => registry.with(fmt::layer().json()).init(),
+    }
 
     info!("Starting Kopiatan server...");
 
-    let state = Arc::new(ServerState::new());
+    let state = Arc::new(config.state()?);
+    let restored = server::restore_rooms(&state)?;
+    if restored > 0 {
+        info!("Restored {} rooms", restored);
+    }
+
+    // Health checks for orchestrators and the admin routes, on a port of
+    // their own
+    let http_addr = config.server.http_addr;
+    let http_state = Arc::clone(&state);
+    tokio::spawn(async move {
+        if let Err(e) = http::run_http(http_addr, http_state).await {
+            error!("HTTP endpoints stopped: {}", e);
+        }
+    });
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_addr) = config.server.grpc_addr {
+        let grpc_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = catan_server::grpc::run_grpc(grpc_addr, grpc_state).await {
+                error!("gRPC stopped: {}", e);
+            }
+        });
+    }
+
+    server::run_server(config.server.addr, state, shutdown_signal()).await
+}
+
+/// Ctrl-C, or SIGTERM from whatever runs the server
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Could not listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Could not listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    server::run_server(addr, state).await
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
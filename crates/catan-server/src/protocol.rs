@@ -1,33 +1,204 @@
 //! WebSocket protocol messages for Kopiatan multiplayer.
 
+use catan_core::{
+    BotDifficulty, BotPersonality, GameAction, GameError, GameEvent, GameOptions, GameStateJson,
+    Hint, ReplayRecord, SeedReveal, SequencedEvent, StateDelta,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Version of this protocol. Clients say which they speak in their hello.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Protocol versions this server can talk
+pub const SUPPORTED_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+
+/// Feature for MessagePack binary frames in place of JSON text
+pub const MSGPACK: &str = "msgpack";
+
+/// Feature for game state changes in place of the whole state each time
+pub const DELTAS: &str = "deltas";
+
+/// Optional features a client may ask for in its hello
+pub const SERVER_FEATURES: &[&str] = &[MSGPACK, DELTAS];
+
 /// Messages sent from client to server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum ClientMessage {
+    /// First message on every connection: the protocol version spoken and
+    /// the optional features wanted. Features the server doesn't know are
+    /// left off.
+    Hello {
+        protocol_version: u32,
+        client_name: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+
     /// Create a new game room
-    CreateRoom { player_name: String, max_players: u8 },
+    CreateRoom {
+        player_name: String,
+        max_players: u8,
+        #[serde(default)]
+        settings: RoomSettings,
+    },
 
     /// Join an existing room
     JoinRoom { room_id: Uuid, player_name: String },
 
+    /// Take back a seat after losing the connection, using the player ID
+    /// the seat had before, the rejoin token it was given, and the last
+    /// event and chat line received
+    Rejoin {
+        room_id: Uuid,
+        player_id: Uuid,
+        #[serde(default)]
+        token: Option<Uuid>,
+        #[serde(default)]
+        last_seq: u64,
+        #[serde(default)]
+        last_chat_seq: u64,
+    },
+
+    /// Ask to take over the seat of a player who left a game in progress,
+    /// playing on under our own name once the host approves
+    ClaimSeat {
+        room_id: Uuid,
+        seat: u8,
+        player_name: String,
+    },
+
+    /// Let a newcomer take the seat they claimed, or turn them down (host
+    /// only)
+    AnswerSeatClaim { player_id: Uuid, approve: bool },
+
     /// Leave current room
     LeaveRoom,
 
+    /// Fill a seat with a computer player (host only, before the game starts)
+    AddBot {
+        difficulty: BotDifficulty,
+        #[serde(default)]
+        personality: BotPersonality,
+    },
+
+    /// Remove someone from the room (host only, before the game starts)
+    KickPlayer { player_id: Uuid },
+
+    /// Make someone else the host (host only)
+    TransferHost { player_id: Uuid },
+
+    /// Stop the game for a while (host only)
+    PauseGame,
+
+    /// Carry on after a pause (host only)
+    ResumeGame,
+
+    /// Change how the game will be played (host only, before it starts)
+    UpdateRoomSettings { settings: RoomSettings },
+
+    /// POST the game's start, turn changes and end to a URL, or stop with
+    /// none (host only)
+    SetWebhook { url: Option<String> },
+
     /// Start the game (host only)
     StartGame,
 
-    /// Submit a game action
-    GameAction { action: serde_json::Value },
+    /// Submit a game action. A `request_id` comes back in the
+    /// `ActionResult`, so a client can tell which action it answers.
+    GameAction {
+        action: GameAction,
+        #[serde(default)]
+        request_id: Option<String>,
+    },
+
+    /// Ask what a bot would play in our seat (the server's hint
+    /// difficulty, Hard by default, unless a weaker one is given)
+    RequestHint {
+        #[serde(default)]
+        difficulty: Option<BotDifficulty>,
+    },
 
     /// Send chat message
     Chat { message: String },
 
-    /// Request room list
+    /// Send a quick-chat phrase to the room
+    Emote { emote: Emote },
+
+    /// Chat with everyone connected who isn't in a room
+    LobbyChat {
+        player_name: String,
+        message: String,
+    },
+
+    /// Stop hearing a player's chat, in rooms and the lobby
+    Mute { player_id: Uuid },
+
+    /// Hear a muted player again
+    Unmute { player_id: Uuid },
+
+    /// Ask for the whole game state again, when a delta doesn't follow on
+    /// from the state we have
+    ResyncState,
+
+    /// Request a game's replay: its moves once it is over, and its events.
+    /// A game still being played is shown to its players, or to anyone if
+    /// it is public.
+    GetReplay { game_id: Uuid },
+
+    /// Watch a public game's events as they happen, without a seat
+    FollowGame { game_id: Uuid },
+
+    /// Stop watching a game
+    UnfollowGame { game_id: Uuid },
+
+    /// Request room list: the first page of waiting rooms
     ListRooms,
 
+    /// Request a page of the waiting rooms that match a query
+    SearchRooms { query: RoomQuery },
+
+    /// Request the best rated players (the top 20 unless given)
+    GetLeaderboard {
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+
+    /// Request one player's record
+    GetPlayerStats { player_id: Uuid },
+
+    /// Open a tournament for others to enter, organized by us
+    CreateTournament {
+        name: String,
+        format: TournamentFormat,
+        /// Players at each table, 2 to 4
+        table_size: u8,
+        #[serde(default)]
+        settings: RoomSettings,
+    },
+
+    /// Enter a tournament that hasn't started yet
+    JoinTournament {
+        tournament_id: Uuid,
+        player_name: String,
+    },
+
+    /// Withdraw from a tournament that hasn't started yet
+    LeaveTournament { tournament_id: Uuid },
+
+    /// Seat the first round (organizer only)
+    StartTournament { tournament_id: Uuid },
+
+    /// Request the tournament list
+    ListTournaments,
+
+    /// Request one tournament's standings and rounds
+    GetTournament { tournament_id: Uuid },
+
+    /// Ask whether the server is healthy, as `/readyz` answers
+    GetHealth,
+
     /// Ping for keepalive
     Ping,
 }
@@ -36,54 +207,386 @@ pub enum ClientMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum ServerMessage {
-    /// Welcome message with assigned player ID
-    Welcome { player_id: Uuid },
+    /// Welcome message with assigned player ID, in answer to a hello. The
+    /// ticket, when the server issues them, brings the same ID back on the
+    /// next connection. `features` are those switched on for this
+    /// connection.
+    Welcome {
+        player_id: Uuid,
+        #[serde(default)]
+        ticket: Option<String>,
+        #[serde(default)]
+        supported_versions: Vec<u32>,
+        #[serde(default)]
+        features: Vec<String>,
+    },
 
     /// Room created successfully
     RoomCreated { room_id: Uuid },
 
     /// Joined room successfully
-    JoinedRoom { room: RoomInfo },
+    JoinedRoom {
+        room: RoomInfo,
+        /// Keep this to take the seat back with `Rejoin`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rejoin_token: Option<Uuid>,
+    },
 
     /// Left room successfully
     LeftRoom,
 
+    /// The host removed us from the room
+    Kicked,
+
     /// Room state updated (player joined/left)
     RoomUpdated { room: RoomInfo },
 
-    /// Game started
-    GameStarted { state: serde_json::Value },
+    /// Where the room's webhook now points, for the host
+    WebhookSet { url: Option<String> },
 
-    /// Game state updated
-    GameState { state: serde_json::Value },
+    /// Someone wants to take over an abandoned seat, for the host to answer
+    SeatClaimed {
+        player_id: Uuid,
+        player_name: String,
+        seat: u8,
+    },
+
+    /// Game started. The dice commitment is the hash of the seed that
+    /// every roll follows from, revealed with `GameOver`.
+    GameStarted {
+        state: Box<GameStateJson>,
+        #[serde(default)]
+        dice_commitment: Option<String>,
+    },
+
+    /// Game state updated. `version` counts the states the room has sent.
+    GameState {
+        state: Box<GameStateJson>,
+        #[serde(default)]
+        version: u64,
+    },
+
+    /// What changed since the state numbered `base`, for clients that
+    /// asked for deltas. A whole state still comes now and then.
+    GameDelta {
+        base: u64,
+        version: u64,
+        delta: Box<StateDelta>,
+    },
 
     /// Action applied successfully
     ActionResult {
         success: bool,
-        events: Vec<serde_json::Value>,
+        events: Vec<GameEvent>,
         error: Option<String>,
+        /// `GameError::code` when the game refused the action, for clients
+        /// that word the error themselves
+        #[serde(default)]
+        error_code: Option<u16>,
+        /// The game's refusal with its details (what was missing, where)
+        #[serde(default)]
+        game_error: Option<GameError>,
+        /// The `request_id` the action was sent with
+        #[serde(default)]
+        request_id: Option<String>,
     },
 
     /// Valid actions for current player
-    ValidActions { actions: Vec<serde_json::Value> },
+    ValidActions { actions: Vec<GameAction> },
 
     /// Current player changed
     TurnChanged { player_id: usize },
 
-    /// Chat message received
-    ChatMessage { player_name: String, message: String },
+    /// A turn or decision timer started; the server moves for `players`
+    /// if they haven't acted in `remaining_ms`
+    TimerStarted {
+        kind: TimerKind,
+        players: Vec<usize>,
+        remaining_ms: u64,
+    },
+
+    /// The game was paused or resumed; no moves are accepted while paused
+    PauseChanged { paused: bool, player_name: String },
+
+    /// Time ran out and the server moved for `players`
+    TurnTimedOut { kind: TimerKind, players: Vec<usize> },
+
+    /// Suggested move, sent only to the player who asked
+    Hint { hint: Hint },
+
+    /// Game events, numbered, as this player may see them
+    GameEvents { events: Vec<SequencedEvent> },
+
+    /// Everything a returning player missed: their view of the game and
+    /// the events and chat since what they last received
+    CatchUp {
+        state: Option<Box<GameStateJson>>,
+        events: Vec<SequencedEvent>,
+        chat: Vec<ChatLine>,
+    },
+
+    /// Chat message received. An emote's message is its English text.
+    ChatMessage {
+        player_name: String,
+        message: String,
+        #[serde(default)]
+        seq: u64,
+        #[serde(default)]
+        player_id: Option<Uuid>,
+        #[serde(default)]
+        emote: Option<Emote>,
+    },
+
+    /// A line on the lobby channel
+    LobbyChatMessage {
+        player_id: Uuid,
+        player_name: String,
+        message: String,
+        seq: u64,
+    },
+
+    /// Who we have muted
+    MuteList { muted: Vec<Uuid> },
+
+    /// List of available rooms. If there are more than fit on a page,
+    /// `next_cursor` asks for the next page.
+    RoomList {
+        rooms: Vec<RoomInfo>,
+        #[serde(default)]
+        next_cursor: Option<Uuid>,
+    },
+
+    /// Best rated players, best first
+    Leaderboard { players: Vec<PlayerStats> },
+
+    /// A player's record, if they have finished a game
+    PlayerStats {
+        player_id: Uuid,
+        stats: Option<PlayerStats>,
+    },
+
+    /// A game's replay, as asked for
+    Replay { replay: Box<Replay> },
+
+    /// Now following a game, with everything that happened in it so far
+    FollowingGame {
+        room: RoomInfo,
+        events: Vec<SequencedEvent>,
+    },
+
+    /// New events in a game we follow
+    FollowedEvents {
+        game_id: Uuid,
+        events: Vec<SequencedEvent>,
+    },
+
+    /// List of tournaments
+    TournamentList { tournaments: Vec<TournamentInfo> },
+
+    /// A tournament we asked about, organize or entered, as it stands now
+    TournamentUpdated { tournament: TournamentInfo },
+
+    /// Our table for a round has been opened. Take the seat with `Rejoin`,
+    /// giving our own player ID.
+    TournamentTable {
+        tournament_id: Uuid,
+        round: u32,
+        room_id: Uuid,
+    },
 
-    /// List of available rooms
-    RoomList { rooms: Vec<RoomInfo> },
+    /// How the server is doing
+    Health { health: Health },
 
     /// Error occurred
-    Error { message: String },
+    Error {
+        message: String,
+        /// What kind of error, for the ones a client may want to act on
+        #[serde(default)]
+        code: Option<ErrorCode>,
+    },
 
     /// Pong response
     Pong,
 
-    /// Game finished
-    GameOver { winner: usize, winner_name: String },
+    /// The server is going down. Games are saved and carry on once it is
+    /// back, so reconnect to take your seat again.
+    ServerShuttingDown,
+
+    /// A message from whoever runs the server, to everyone connected
+    Announcement { message: String },
+
+    /// An operator closed the room we were in
+    RoomClosed { reason: String },
+
+    /// Game finished (no winner when players agreed to end early)
+    GameOver {
+        winner: Option<usize>,
+        winner_name: Option<String>,
+        ranking: Vec<usize>,
+        /// What the dice commitment hid, to check the rolls against
+        #[serde(default)]
+        seed: Option<SeedReveal>,
+    },
+}
+
+impl ClientMessage {
+    /// Read a MessagePack frame. Either encoding is accepted from any client.
+    pub fn from_msgpack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        Self::deserialize(&mut rmp_serde::Deserializer::new(data).with_human_readable())
+    }
+}
+
+impl ServerMessage {
+    /// Write as MessagePack, keeping field names and writing IDs as strings
+    /// so it decodes to the same objects as the JSON
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        let mut data = Vec::new();
+        let mut serializer = rmp_serde::Serializer::new(&mut data)
+            .with_struct_map()
+            .with_human_readable();
+        self.serialize(&mut serializer)?;
+        Ok(data)
+    }
+}
+
+/// Errors a client can tell apart from the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// Sending too fast; messages are dropped until the client slows down
+    RateLimited,
+    /// A message was too big to read
+    MessageTooLarge,
+}
+
+/// How server messages are written on a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// JSON text frames, the default, easy to read in browser tools
+    Json,
+    /// MessagePack binary frames, smaller for the big state payloads
+    MessagePack,
+}
+
+/// What was agreed with a client when it connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub protocol_version: u32,
+    pub client_name: String,
+    /// Optional features on for this connection
+    pub features: Vec<String>,
+}
+
+impl Session {
+    /// Agree on a session from a connection's first message, which must be
+    /// a hello in a version this server speaks. Otherwise, what to tell the
+    /// client.
+    pub fn negotiate(first: ClientMessage) -> Result<Self, String> {
+        let ClientMessage::Hello {
+            protocol_version,
+            client_name,
+            mut features,
+        } = first
+        else {
+            return Err(format!(
+                "This server needs a newer client (protocol version {})",
+                PROTOCOL_VERSION
+            ));
+        };
+        if !SUPPORTED_VERSIONS.contains(&protocol_version) {
+            return Err(format!(
+                "Protocol version {} is not supported; this server speaks {:?}",
+                protocol_version, SUPPORTED_VERSIONS
+            ));
+        }
+        features.retain(|f| SERVER_FEATURES.contains(&f.as_str()));
+        Ok(Self {
+            protocol_version,
+            client_name,
+            features,
+        })
+    }
+
+    /// How to write to this client once welcomed. The welcome itself is
+    /// always JSON, so the client learns what it got.
+    pub fn encoding(&self) -> Encoding {
+        if self.features.iter().any(|f| f == MSGPACK) {
+            Encoding::MessagePack
+        } else {
+            Encoding::Json
+        }
+    }
+}
+
+/// How the server is doing, for health checks and load balancers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    /// Taking new players: storage answers and we aren't shutting down
+    pub ready: bool,
+    pub shutting_down: bool,
+    /// Why the room store didn't answer, if it didn't
+    pub storage_error: Option<String>,
+    pub rooms: usize,
+    pub connections: usize,
+}
+
+/// A player's record over every finished game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub player_id: Uuid,
+    /// Name in their latest game
+    pub name: String,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+    pub average_vp: f64,
+    /// Elo-style rating, starting at 1500
+    pub rating: f64,
+}
+
+/// A chat message kept for players catching up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub seq: u64,
+    pub player_name: String,
+    pub message: String,
+    #[serde(default)]
+    pub player_id: Option<Uuid>,
+    #[serde(default)]
+    pub emote: Option<Emote>,
+}
+
+/// Quick-chat phrases. They are sent as themselves so clients can show them
+/// in the player's language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emote {
+    Hello,
+    GoodLuck,
+    NiceRoll,
+    NiceMove,
+    Thanks,
+    Sorry,
+    Oops,
+    Hurry,
+    WellPlayed,
+    GoodGame,
+}
+
+impl Emote {
+    /// In English, for clients that don't know the emote
+    pub fn text(self) -> &'static str {
+        match self {
+            Emote::Hello => "Hello!",
+            Emote::GoodLuck => "Good luck!",
+            Emote::NiceRoll => "Nice roll!",
+            Emote::NiceMove => "Nice move!",
+            Emote::Thanks => "Thanks!",
+            Emote::Sorry => "Sorry!",
+            Emote::Oops => "Oops!",
+            Emote::Hurry => "Your turn!",
+            Emote::WellPlayed => "Well played!",
+            Emote::GoodGame => "Good game!",
+        }
+    }
 }
 
 /// Room information for clients.
@@ -95,6 +598,57 @@ pub struct RoomInfo {
     pub max_players: u8,
     pub host_id: Uuid,
     pub status: RoomStatus,
+    #[serde(default)]
+    pub settings: RoomSettings,
+    /// The host has paused the game
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// Rooms listed on a page when a query doesn't say
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Most rooms listed on a page
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// Which waiting rooms to list, and which page of them. Rooms are listed in
+/// the order of their IDs, so a page carries on from the last room of the
+/// one before even as rooms open and fill up in between.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomQuery {
+    /// Only tables for this many players
+    pub max_players: Option<u8>,
+    /// Only rooms with bots seated, or only rooms without
+    pub has_bots: Option<bool>,
+    /// Only rooms whose name contains this, ignoring case
+    pub name: Option<String>,
+    /// Only rooms playing with these options, such as `{"pirate": true}`
+    pub options: Option<serde_json::Map<String, serde_json::Value>>,
+    /// The last room of the page before
+    pub cursor: Option<Uuid>,
+    /// Rooms on the page, up to [`MAX_PAGE_SIZE`]
+    pub limit: Option<usize>,
+}
+
+impl RoomQuery {
+    /// Whether a room passes the filters, wherever the page is
+    pub fn matches(&self, room: &RoomInfo) -> bool {
+        let has_bots = room.players.iter().any(|p| p.bot.is_some());
+        let options = serde_json::to_value(&room.settings.options).unwrap_or_default();
+        self.max_players.is_none_or(|n| room.max_players == n)
+            && self.has_bots.is_none_or(|wanted| has_bots == wanted)
+            && self.name.as_ref().is_none_or(|name| {
+                room.name.to_lowercase().contains(&name.to_lowercase())
+            })
+            && self.options.as_ref().is_none_or(|wanted| {
+                wanted.iter().all(|(rule, value)| options.get(rule) == Some(value))
+            })
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
 }
 
 /// Player information in a room.
@@ -104,6 +658,66 @@ pub struct PlayerInfo {
     pub name: String,
     pub ready: bool,
     pub connected: bool,
+    /// Set for computer players
+    #[serde(default)]
+    pub bot: Option<BotSeat>,
+    /// A bot is playing for this player while they are disconnected
+    #[serde(default)]
+    pub stand_in: bool,
+}
+
+/// How a computer-controlled seat plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotSeat {
+    pub difficulty: BotDifficulty,
+    pub personality: BotPersonality,
+}
+
+/// How a room's game is played, chosen by the host before it starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomSettings {
+    /// Rules for the game: victory points, board, house rules
+    pub options: GameOptions,
+    /// Seed for the board, deck and dice; random when not set
+    pub seed: Option<u64>,
+    /// Time limits for turns and decisions
+    pub timers: TurnTimers,
+    /// How well a bot plays when standing in for a disconnected player
+    pub stand_in_difficulty: BotDifficulty,
+    /// Anyone may follow the game and see its replay while it is played
+    pub public: bool,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            options: GameOptions::default(),
+            seed: None,
+            timers: TurnTimers::default(),
+            stand_in_difficulty: BotDifficulty::Hard,
+            public: false,
+        }
+    }
+}
+
+/// Time limits a room plays with. Without a limit, the game waits for
+/// players as long as they take.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnTimers {
+    /// Seconds for a whole turn, after which it is ended for the player
+    pub turn_secs: Option<u64>,
+    /// Seconds for a decision the game stops for: discarding, moving the
+    /// robber, choosing whom to rob, or voting to end the game
+    pub decision_secs: Option<u64>,
+}
+
+/// Which kind of time limit a timer enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimerKind {
+    Turn,
+    Decision,
 }
 
 /// Room status.
@@ -113,3 +727,175 @@ pub enum RoomStatus {
     InGame,
     Finished,
 }
+
+/// A game to watch again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// The game's room
+    pub game_id: Uuid,
+    pub room: RoomInfo,
+    /// How the game started and every move, once the game is over
+    pub record: Option<ReplayRecord>,
+    /// Every event so far, as the one asking may see them
+    pub events: Vec<SequencedEvent>,
+    /// The hash of the seed, published when the game started
+    pub dice_commitment: Option<String>,
+    /// The seed and its salt, once the game is over
+    pub seed: Option<SeedReveal>,
+}
+
+/// How a tournament's rounds are drawn up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TournamentFormat {
+    /// Only each table's winner plays on, until one is left
+    SingleElimination,
+    /// Everyone plays every round against players with similar scores
+    Swiss { rounds: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TournamentStatus {
+    Registering,
+    Running,
+    Finished,
+}
+
+/// A tournament: who entered, how they are doing, and every round so far.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TournamentInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub organizer_id: Uuid,
+    pub format: TournamentFormat,
+    pub table_size: u8,
+    pub settings: RoomSettings,
+    pub status: TournamentStatus,
+    /// In the order they entered
+    pub entrants: Vec<EntrantInfo>,
+    pub rounds: Vec<RoundInfo>,
+    pub winner: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntrantInfo {
+    pub player_id: Uuid,
+    pub name: String,
+    /// For each game, one for every player finishing below them
+    pub points: u32,
+    pub wins: u32,
+    /// Knocked out of an elimination tournament
+    pub eliminated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundInfo {
+    pub number: u32,
+    pub tables: Vec<TableInfo>,
+}
+
+/// One game of a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableInfo {
+    /// The table's room; None for a bye, when a player was left over
+    pub room_id: Option<Uuid>,
+    /// Entrants in seat order
+    pub players: Vec<Uuid>,
+    /// Entrants from first place to last, once the game is over
+    pub ranking: Option<Vec<Uuid>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_and_events_travel_typed() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"GameAction","payload":{"action":"RollDice"}}"#)
+                .unwrap();
+        assert!(matches!(
+            msg,
+            ClientMessage::GameAction {
+                action: GameAction::RollDice,
+                request_id: None,
+            }
+        ));
+        // A malformed action is turned away with the message
+        let bad = r#"{"type":"GameAction","payload":{"action":"FlipTable"}}"#;
+        assert!(serde_json::from_str::<ClientMessage>(bad).is_err());
+
+        let result = ServerMessage::ActionResult {
+            success: true,
+            events: vec![GameEvent::TurnEnded {
+                player: 1,
+                next_player: 0,
+            }],
+            error: None,
+            error_code: None,
+            game_error: None,
+            request_id: Some("roll-1".to_string()),
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json["payload"]["events"][0],
+            serde_json::json!({ "TurnEnded": { "player": 1, "next_player": 0 } })
+        );
+        assert_eq!(json["payload"]["request_id"], "roll-1");
+        let back: ServerMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(back, ServerMessage::ActionResult { events, .. } if events.len() == 1));
+
+        // The request ID is optional, and comes back as given
+        let msg: ClientMessage = serde_json::from_str(
+            r#"{"type":"GameAction","payload":{"action":"EndTurn","request_id":"7"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            msg,
+            ClientMessage::GameAction { request_id: Some(id), .. } if id == "7"
+        ));
+    }
+
+    #[test]
+    fn test_msgpack_frames_match_the_json() {
+        let msg = ServerMessage::Welcome {
+            player_id: Uuid::new_v4(),
+            ticket: None,
+            supported_versions: SUPPORTED_VERSIONS.to_vec(),
+            features: vec![MSGPACK.to_string()],
+        };
+        let packed = msg.to_msgpack().unwrap();
+        let json = serde_json::to_vec(&msg).unwrap();
+        assert!(packed.len() < json.len());
+        // Read back generically, it is the same document as the JSON
+        let value: serde_json::Value = rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(value, serde_json::to_value(&msg).unwrap());
+
+        let hello = ClientMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: "bot".to_string(),
+            features: vec![MSGPACK.to_string()],
+        };
+        let data = rmp_serde::to_vec_named(&hello).unwrap();
+        let session = Session::negotiate(ClientMessage::from_msgpack(&data).unwrap()).unwrap();
+        assert_eq!(session.encoding(), Encoding::MessagePack);
+    }
+
+    #[test]
+    fn test_hello_agrees_a_version_and_features() {
+        let hello = |protocol_version, features: &[&str]| ClientMessage::Hello {
+            protocol_version,
+            client_name: "test".to_string(),
+            features: features.iter().map(|f| f.to_string()).collect(),
+        };
+        let session = Session::negotiate(hello(PROTOCOL_VERSION, &["teleport"])).unwrap();
+        assert_eq!(session.protocol_version, PROTOCOL_VERSION);
+        assert!(session.features.is_empty());
+        assert_eq!(session.encoding(), Encoding::Json);
+
+        let err = Session::negotiate(hello(PROTOCOL_VERSION + 1, &[])).unwrap_err();
+        assert!(err.contains("not supported"));
+        // A client from before the handshake starts with something else
+        let err = Session::negotiate(ClientMessage::ListRooms).unwrap_err();
+        assert!(err.contains("newer client"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Replays of games, whether still running or put away.
+//!
+//! Each room keeps a record of its game, the starting position and every
+//! move, beside the numbered events it sends players. A finished game's
+//! replay has both, so it can be played through exactly; a game still
+//! being played only shows its events so far, and only to its players
+//! unless the host made it public. A public game can also be followed as
+//! it is played, its events arriving as they happen.
+
+use crate::actor::RoomCommand;
+use crate::protocol::Replay;
+use crate::room::{GameRoom, RoomError};
+use crate::server::ServerState;
+use crate::storage::StoreError;
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Game not found")]
+    NotFound,
+    #[error(transparent)]
+    Room(#[from] RoomError),
+    #[error("Could not load the game: {0}")]
+    Store(#[from] StoreError),
+}
+
+/// A game's replay as `player_id` may see it, from its room while it runs
+/// here, otherwise from the rooms the store has put away
+pub async fn find(
+    state: &ServerState,
+    player_id: Uuid,
+    game_id: Uuid,
+) -> Result<Replay, ReplayError> {
+    let room = state.rooms.get(&game_id).map(|r| r.clone());
+    if let Some(room) = room {
+        let replay = room
+            .ask(|reply| RoomCommand::Replay { player_id, reply })
+            .await;
+        if let Some(replay) = replay {
+            return Ok(replay?);
+        }
+    }
+    let store = state.store.as_ref().ok_or(ReplayError::NotFound)?;
+    let snapshot = store.load_archived(game_id)?.ok_or(ReplayError::NotFound)?;
+    Ok(GameRoom::from_snapshot(snapshot)?.replay(player_id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::spawn_room;
+    use crate::protocol::{BotSeat, RoomSettings, ServerMessage};
+    use catan_core::{
+        dice_rolls, verify_dice, BotDifficulty, BotPersonality, GameAction, GameEvent,
+    };
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_games_can_be_followed_and_replayed() {
+        let mut state = ServerState::new();
+        // Keep the bot from moving, so the game waits on the host
+        state.bot_move_delay = Duration::from_secs(3600);
+        let state = Arc::new(state);
+        let host = Uuid::new_v4();
+        let spectator = Uuid::new_v4();
+        let (sender, mut inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(spectator, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host, "Host".to_string(), 2);
+        let settings = RoomSettings {
+            public: true,
+            ..RoomSettings::default()
+        };
+        room.update_settings(host, settings).unwrap();
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: BotPersonality::default(),
+        };
+        room.add_bot(host, seat).unwrap();
+        room.start_game(host).unwrap();
+        let handle = spawn_room(room, &state);
+        state.rooms.insert(room_id, handle.clone());
+
+        handle.send(RoomCommand::Follow {
+            player_id: spectator,
+        });
+        let msg = tokio::time::timeout(Duration::from_secs(5), inbox.recv()).await;
+        assert!(matches!(
+            msg,
+            Ok(Some(ServerMessage::FollowingGame { room, .. })) if room.id == room_id
+        ));
+
+        // Anyone may look at a public game, but its moves stay hidden
+        // until it is over
+        let replay = find(&state, spectator, room_id).await.unwrap();
+        assert!(replay.record.is_none());
+        assert!(replay.seed.is_none());
+        let commitment = replay.dice_commitment.expect("committed to a seed");
+        assert!(matches!(
+            find(&state, spectator, Uuid::new_v4()).await,
+            Err(ReplayError::NotFound)
+        ));
+
+        handle.send(RoomCommand::Action {
+            player_id: host,
+            action: GameAction::Concede,
+            request_id: None,
+        });
+        let msg = tokio::time::timeout(Duration::from_secs(5), inbox.recv()).await;
+        let Ok(Some(ServerMessage::FollowedEvents { game_id, events })) = msg else {
+            panic!("expected the game's events, got {:?}", msg);
+        };
+        assert_eq!(game_id, room_id);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e.event, GameEvent::PlayerConceded { .. })));
+
+        let replay = find(&state, spectator, room_id).await.unwrap();
+        assert!(serde_json::to_string(&replay).is_ok());
+        let record = replay.record.expect("the finished game's record");
+        assert_eq!(record.moves.len(), 1);
+        assert!(record.to_record().replay().unwrap().is_finished());
+
+        // The revealed seed opens the commitment and accounts for the dice
+        let seed = replay.seed.expect("the seed, now the game is over");
+        let rolls = dice_rolls(replay.events.iter().map(|e| &e.event));
+        verify_dice(&record.to_record(), &commitment, &seed, &rolls).unwrap();
+    }
+}
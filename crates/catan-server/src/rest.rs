@@ -0,0 +1,654 @@
+//! A plain HTTP way to play, for scripts, bots and platforms without
+//! WebSockets.
+//!
+//! A client opens a session and gets a token to send with every request
+//! after, as `Authorization: Bearer <token>`. The session stands in for a
+//! socket: whatever the server would have sent over one waits in the
+//! session's inbox until the client fetches it.
+//!
+//! - `POST /api/sessions`: `{"name": ...}`, answered with the player ID and
+//!   token.
+//! - `DELETE /api/sessions`: end the session, as if a socket had closed.
+//! - `GET /api/rooms`: rooms waiting for players, filtered by
+//!   `max_players`, `has_bots`, `name` and `options` (JSON) and paged with
+//!   `cursor` and `limit`. The next page's cursor comes back in the
+//!   `X-Next-Cursor` header.
+//! - `POST /api/rooms`: `{"max_players": ..., "settings": ...}`, to create
+//!   a room and join it.
+//! - `POST /api/rooms/{id}/join`: join a room.
+//! - `GET /api/state`: the room, the game as this player sees it and what
+//!   they may do.
+//! - `POST /api/actions`: `{"action": ..., "request_id": ...}`, answered
+//!   with the room's `ActionResult`.
+//! - `POST /api/messages`: any other message of the WebSocket protocol.
+//! - `GET /api/messages?wait=<secs>`: everything sent since the last fetch,
+//!   waiting up to `wait` seconds for something to arrive.
+//! - `GET /api/replays/{id}`: a game's replay, to download. Finished games
+//!   need no session.
+//! - `GET /api/replays/{id}/follow`: a public game's events as they happen,
+//!   as server-sent events, for spectators.
+//!
+//! Sessions nobody has used for [`SESSION_TTL`] are closed.
+
+use crate::actor::RoomCommand;
+use crate::protocol::{ClientMessage, Replay, RoomInfo, RoomQuery, RoomSettings, ServerMessage};
+use crate::replay::{self, ReplayError};
+use crate::room::RoomError;
+use crate::server::{handle_disconnect, handle_message, ServerState};
+use axum::extract::{FromRequestParts, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{async_trait, Json, Router};
+use catan_core::{GameAction, GameStateJson};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::info;
+use uuid::Uuid;
+
+/// How long a session lasts without a request
+pub const SESSION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Longest a client may wait for messages in one request
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// How long to wait for a room to answer a command
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a reader streaming messages keeps the inbox to itself at a
+/// time, so answers can still be waited for meanwhile
+const STREAM_SLICE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SessionError {
+    #[error("The server is shutting down")]
+    ShuttingDown,
+    #[error("Not in a room")]
+    NotInRoom,
+    #[error("{0}")]
+    Refused(String),
+    #[error("The room did not answer")]
+    NoAnswer,
+}
+
+/// The room and game as one player sees them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub room: RoomInfo,
+    /// Only the player's own hand is shown
+    pub game: Option<Box<GameStateJson>>,
+    pub valid_actions: Vec<GameAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub player_id: Uuid,
+    pub token: String,
+}
+
+/// A player without a connection, on the REST API or gRPC.
+pub struct RestSession {
+    pub player_id: Uuid,
+    pub name: String,
+    token: String,
+    inbox: tokio::sync::Mutex<Inbox>,
+    last_seen: Mutex<Instant>,
+}
+
+/// Messages sent to a session and not yet fetched
+struct Inbox {
+    receiver: mpsc::UnboundedReceiver<ServerMessage>,
+    /// Passed over while waiting for an answer, to go out with the next
+    /// fetch
+    held: VecDeque<ServerMessage>,
+}
+
+impl Inbox {
+    /// Everything waiting, giving the first up to `wait` to arrive
+    async fn take_all(&mut self, wait: Duration) -> Vec<ServerMessage> {
+        let mut messages: Vec<ServerMessage> = self.held.drain(..).collect();
+        if messages.is_empty() {
+            if let Ok(Some(msg)) = tokio::time::timeout(wait, self.receiver.recv()).await {
+                messages.push(msg);
+            }
+        }
+        while let Ok(msg) = self.receiver.try_recv() {
+            messages.push(msg);
+        }
+        messages
+    }
+
+    /// The first message `wanted` picks out, holding on to the others
+    async fn answer(&mut self, wanted: impl Fn(&ServerMessage) -> bool) -> Option<ServerMessage> {
+        if let Some(i) = self.held.iter().position(&wanted) {
+            return self.held.remove(i);
+        }
+        let deadline = tokio::time::Instant::now() + ANSWER_TIMEOUT;
+        while let Ok(Some(msg)) = tokio::time::timeout_at(deadline, self.receiver.recv()).await {
+            if wanted(&msg) {
+                return Some(msg);
+            }
+            self.held.push_back(msg);
+        }
+        None
+    }
+}
+
+/// Start a session for a new player
+pub fn open_session(state: &Arc<ServerState>, name: String) -> Result<SessionInfo, SessionError> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(SessionError::ShuttingDown);
+    }
+    let player_id = Uuid::new_v4();
+    let token = Uuid::new_v4().simple().to_string();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    state.player_senders.insert(player_id, sender);
+    let session = RestSession {
+        player_id,
+        name,
+        token: token.clone(),
+        inbox: tokio::sync::Mutex::new(Inbox {
+            receiver,
+            held: VecDeque::new(),
+        }),
+        last_seen: Mutex::new(Instant::now()),
+    };
+    state.rest_sessions.insert(token.clone(), Arc::new(session));
+    info!("New session for {}", player_id);
+    Ok(SessionInfo { player_id, token })
+}
+
+/// The session a token belongs to, now counted as used
+pub fn find_session(state: &ServerState, token: &str) -> Option<Arc<RestSession>> {
+    let session = state.rest_sessions.get(token).map(|s| Arc::clone(&s))?;
+    *session.last_seen.lock().unwrap() = Instant::now();
+    Some(session)
+}
+
+/// Let go of a session, as a connection does when it closes
+pub fn end_session(state: &Arc<ServerState>, session: &RestSession) {
+    state.rest_sessions.remove(&session.token);
+    state.player_senders.remove(&session.player_id);
+    handle_disconnect(session.player_id, state);
+}
+
+/// Close the sessions nobody has used for [`SESSION_TTL`]
+pub fn expire_sessions(state: &Arc<ServerState>) {
+    let expired: Vec<Arc<RestSession>> = state
+        .rest_sessions
+        .iter()
+        .filter(|s| s.last_seen.lock().unwrap().elapsed() >= SESSION_TTL)
+        .map(|s| Arc::clone(&s))
+        .collect();
+    for session in expired {
+        info!("Session for {} expired", session.player_id);
+        end_session(state, &session);
+    }
+}
+
+impl RestSession {
+    /// Handle a message as if it came over a connection
+    pub fn send(&self, state: &Arc<ServerState>, msg: ClientMessage) {
+        handle_message(self.player_id, msg, state);
+    }
+
+    /// Create a room, and wait to be let in
+    pub async fn create_room(
+        &self,
+        state: &Arc<ServerState>,
+        max_players: u8,
+        settings: RoomSettings,
+    ) -> Result<RoomInfo, SessionError> {
+        let msg = ClientMessage::CreateRoom {
+            player_name: self.name.clone(),
+            max_players,
+            settings,
+        };
+        self.joined(state, msg).await
+    }
+
+    /// Join a room, and wait to be let in
+    pub async fn join_room(
+        &self,
+        state: &Arc<ServerState>,
+        room_id: Uuid,
+    ) -> Result<RoomInfo, SessionError> {
+        let msg = ClientMessage::JoinRoom {
+            room_id,
+            player_name: self.name.clone(),
+        };
+        self.joined(state, msg).await
+    }
+
+    async fn joined(
+        &self,
+        state: &Arc<ServerState>,
+        msg: ClientMessage,
+    ) -> Result<RoomInfo, SessionError> {
+        let mut inbox = self.inbox.lock().await;
+        self.send(state, msg);
+        let answer = inbox
+            .answer(|msg| {
+                matches!(
+                    msg,
+                    ServerMessage::JoinedRoom { .. } | ServerMessage::Error { .. }
+                )
+            })
+            .await;
+        match answer {
+            Some(ServerMessage::JoinedRoom { room, .. }) => Ok(room),
+            Some(ServerMessage::Error { message, .. }) => Err(SessionError::Refused(message)),
+            _ => Err(SessionError::NoAnswer),
+        }
+    }
+
+    /// The room and game as this player sees them
+    pub async fn view(&self, state: &ServerState) -> Result<PlayerView, SessionError> {
+        let room = state
+            .player_room(self.player_id)
+            .ok_or(SessionError::NotInRoom)?;
+        let player_id = self.player_id;
+        room.ask(|reply| RoomCommand::View { player_id, reply })
+            .await
+            .ok_or(SessionError::NotInRoom)
+    }
+
+    /// Take an action and wait for the room's `ActionResult`
+    pub async fn act(
+        &self,
+        state: &Arc<ServerState>,
+        action: GameAction,
+        request_id: Option<String>,
+    ) -> Result<ServerMessage, SessionError> {
+        if state.player_room(self.player_id).is_none() {
+            return Err(SessionError::NotInRoom);
+        }
+        // The answer is picked out by its request ID, so every action gets one
+        let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let mut inbox = self.inbox.lock().await;
+        let msg = ClientMessage::GameAction {
+            action,
+            request_id: Some(request_id.clone()),
+        };
+        self.send(state, msg);
+        inbox
+            .answer(|msg| {
+                matches!(
+                    msg,
+                    ServerMessage::ActionResult { request_id: Some(id), .. } if *id == request_id
+                )
+            })
+            .await
+            .ok_or(SessionError::NoAnswer)
+    }
+
+    /// Everything sent since the last fetch, giving the first up to `wait`
+    /// to arrive
+    pub async fn fetch(&self, wait: Duration) -> Vec<ServerMessage> {
+        self.inbox.lock().await.take_all(wait).await
+    }
+
+    /// The next message sent, for streaming them as they come; None once
+    /// the session has ended
+    pub async fn next_message(&self) -> Option<ServerMessage> {
+        loop {
+            let mut inbox = self.inbox.lock().await;
+            if let Some(msg) = inbox.held.pop_front() {
+                return Some(msg);
+            }
+            match tokio::time::timeout(STREAM_SLICE, inbox.receiver.recv()).await {
+                Ok(msg) => return msg,
+                Err(_) => continue,
+            }
+        }
+    }
+}
+
+/// The session a request's token belongs to
+pub struct Player(Arc<RestSession>);
+
+#[async_trait]
+impl FromRequestParts<Arc<ServerState>> for Player {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<ServerState>,
+    ) -> Result<Self, ApiError> {
+        parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| find_session(state, token))
+            .map(Player)
+            .ok_or_else(|| ApiError(StatusCode::UNAUTHORIZED, "Unknown session".to_string()))
+    }
+}
+
+/// A request that couldn't be served, answered as `{"error": ...}`
+pub struct ApiError(StatusCode, String);
+
+impl From<SessionError> for ApiError {
+    fn from(e: SessionError) -> Self {
+        let status = match e {
+            SessionError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            SessionError::NotInRoom => StatusCode::CONFLICT,
+            SessionError::Refused(_) => StatusCode::BAD_REQUEST,
+            SessionError::NoAnswer => StatusCode::GATEWAY_TIMEOUT,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl From<ReplayError> for ApiError {
+    fn from(e: ReplayError) -> Self {
+        let status = match e {
+            ReplayError::NotFound => StatusCode::NOT_FOUND,
+            ReplayError::Room(RoomError::NotPublic) => StatusCode::FORBIDDEN,
+            ReplayError::Room(_) => StatusCode::CONFLICT,
+            ReplayError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, error) = self;
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NewSession {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewRoom {
+    max_players: u8,
+    #[serde(default)]
+    settings: RoomSettings,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmittedAction {
+    action: GameAction,
+    request_id: Option<String>,
+}
+
+/// A room query as query-string parameters, the options as JSON
+#[derive(Debug, Deserialize)]
+struct RoomSearch {
+    max_players: Option<u8>,
+    has_bots: Option<bool>,
+    name: Option<String>,
+    options: Option<String>,
+    cursor: Option<Uuid>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Poll {
+    /// Seconds to wait for a message if none is waiting
+    #[serde(default)]
+    wait: u64,
+}
+
+/// The REST routes
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/sessions", post(create_session).delete(close_session))
+        .route("/rooms", get(list_rooms).post(create_room))
+        .route("/rooms/:room_id/join", post(join_room))
+        .route("/state", get(view))
+        .route("/actions", post(submit_action))
+        .route("/messages", get(fetch_messages).post(send_message))
+        .route("/replays/:game_id", get(download_replay))
+        .route("/replays/:game_id/follow", get(follow_game))
+}
+
+async fn create_session(
+    State(state): State<Arc<ServerState>>,
+    Json(new): Json<NewSession>,
+) -> Result<(StatusCode, Json<SessionInfo>), ApiError> {
+    let session = open_session(&state, new.name)?;
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+async fn close_session(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+) -> StatusCode {
+    end_session(&state, &session);
+    StatusCode::NO_CONTENT
+}
+
+async fn list_rooms(
+    State(state): State<Arc<ServerState>>,
+    Query(search): Query<RoomSearch>,
+) -> Result<Response, ApiError> {
+    let options = search
+        .options
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid options: {}", e)))?;
+    let query = RoomQuery {
+        max_players: search.max_players,
+        has_bots: search.has_bots,
+        name: search.name,
+        options,
+        cursor: search.cursor,
+        limit: search.limit,
+    };
+    let (rooms, next_cursor) = state.list_rooms(&query);
+    let mut response = Json(rooms).into_response();
+    if let Some(cursor) = next_cursor {
+        let value = cursor.to_string().parse().expect("a UUID is a valid header value");
+        response.headers_mut().insert("x-next-cursor", value);
+    }
+    Ok(response)
+}
+
+async fn create_room(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+    Json(new): Json<NewRoom>,
+) -> Result<(StatusCode, Json<RoomInfo>), ApiError> {
+    let room = session
+        .create_room(&state, new.max_players, new.settings)
+        .await?;
+    Ok((StatusCode::CREATED, Json(room)))
+}
+
+async fn join_room(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<RoomInfo>, ApiError> {
+    Ok(Json(session.join_room(&state, room_id).await?))
+}
+
+async fn view(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+) -> Result<Json<PlayerView>, ApiError> {
+    Ok(Json(session.view(&state).await?))
+}
+
+async fn submit_action(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+    Json(submitted): Json<SubmittedAction>,
+) -> Result<Json<ServerMessage>, ApiError> {
+    let answer = session
+        .act(&state, submitted.action, submitted.request_id)
+        .await?;
+    Ok(Json(answer))
+}
+
+async fn send_message(
+    State(state): State<Arc<ServerState>>,
+    Player(session): Player,
+    Json(msg): Json<ClientMessage>,
+) -> StatusCode {
+    session.send(&state, msg);
+    StatusCode::ACCEPTED
+}
+
+async fn fetch_messages(
+    Player(session): Player,
+    Query(poll): Query<Poll>,
+) -> Json<Vec<ServerMessage>> {
+    let wait = Duration::from_secs(poll.wait).min(MAX_WAIT);
+    Json(session.fetch(wait).await)
+}
+
+async fn download_replay(
+    State(state): State<Arc<ServerState>>,
+    player: Option<Player>,
+    Path(game_id): Path<Uuid>,
+) -> Result<Json<Replay>, ApiError> {
+    // Without a session, only what anyone may see
+    let player_id = player.map_or(Uuid::nil(), |Player(session)| session.player_id);
+    Ok(Json(replay::find(&state, player_id, game_id).await?))
+}
+
+/// A spectator following a game, for as long as their stream is open
+struct Follower {
+    state: Arc<ServerState>,
+    id: Uuid,
+    game_id: Uuid,
+    receiver: mpsc::UnboundedReceiver<ServerMessage>,
+    closing: tokio::sync::watch::Receiver<bool>,
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        self.state.player_senders.remove(&self.id);
+        handle_message(
+            self.id,
+            ClientMessage::UnfollowGame {
+                game_id: self.game_id,
+            },
+            &self.state,
+        );
+    }
+}
+
+/// Stream a public game's events, each named for its message type. A
+/// refusal comes as an `Error` event, which ends the stream.
+async fn follow_game(
+    State(state): State<Arc<ServerState>>,
+    Path(game_id): Path<Uuid>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let id = Uuid::new_v4();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    state.player_senders.insert(id, sender);
+    handle_message(id, ClientMessage::FollowGame { game_id }, &state);
+    let follower = Follower {
+        closing: state.closing.subscribe(),
+        state,
+        id,
+        game_id,
+        receiver,
+    };
+    let stream = futures_util::stream::unfold(Some(follower), |follower| async move {
+        let mut follower = follower?;
+        let msg = tokio::select! {
+            msg = follower.receiver.recv() => msg?,
+            _ = follower.closing.wait_for(|&closing| closing) => return None,
+        };
+        let json = serde_json::to_value(&msg).unwrap_or_default();
+        let event = Event::default()
+            .event(json["type"].as_str().unwrap_or_default())
+            .data(json.to_string());
+        let next = (!matches!(msg, ServerMessage::Error { .. })).then_some(follower);
+        Some((Ok(event), next))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::request;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_a_game_can_be_played_over_plain_http() {
+        let mut state = ServerState::new();
+        state.bot_move_delay = Duration::ZERO;
+        let state = Arc::new(state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = crate::http::router(Arc::clone(&state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (status, _) = request(addr, "GET", "/api/state", Some("nope"), "").await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+
+        let (status, body) =
+            request(addr, "POST", "/api/sessions", None, r#"{"name":"Script"}"#).await;
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let session: SessionInfo = serde_json::from_str(&body).unwrap();
+        let token = Some(session.token.as_str());
+
+        let (status, body) =
+            request(addr, "POST", "/api/rooms", token, r#"{"max_players":2}"#).await;
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let room: RoomInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(room.host_id, session.player_id);
+
+        let add_bot = r#"{"type":"AddBot","payload":{"difficulty":"Easy"}}"#;
+        let (status, _) = request(addr, "POST", "/api/messages", token, add_bot).await;
+        assert_eq!(status, "HTTP/1.1 202 Accepted");
+        let start = r#"{"type":"StartGame"}"#;
+        request(addr, "POST", "/api/messages", token, start).await;
+
+        // Wait, as a script would, until it is our move
+        let mut view: Option<PlayerView> = None;
+        for _ in 0..100 {
+            let (_, body) = request(addr, "GET", "/api/state", token, "").await;
+            let polled: PlayerView = serde_json::from_str(&body).unwrap();
+            if !polled.valid_actions.is_empty() {
+                view = Some(polled);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let view = view.expect("our turn came");
+        assert!(view.game.is_some());
+
+        let action = serde_json::json!({ "action": view.valid_actions[0] }).to_string();
+        let (status, body) = request(addr, "POST", "/api/actions", token, &action).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let answer: ServerMessage = serde_json::from_str(&body).unwrap();
+        assert!(matches!(
+            answer,
+            ServerMessage::ActionResult { success: true, .. }
+        ));
+
+        // Everything else sent meanwhile is still there to fetch
+        let (_, body) = request(addr, "GET", "/api/messages", token, "").await;
+        let messages: Vec<ServerMessage> = serde_json::from_str(&body).unwrap();
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, ServerMessage::GameStarted { .. })));
+
+        let (status, _) = request(addr, "DELETE", "/api/sessions", token, "").await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        assert!(state.rest_sessions.is_empty());
+        assert!(!state.player_senders.contains_key(&session.player_id));
+    }
+}
@@ -1,11 +1,36 @@
 //! Game room management.
 
-use catan_core::{GameAction, GameEvent, GamePhase, GameState};
+use catan_core::{
+    BoardError, Bot, BotPlayer, EventLog, GameAction, GameError, GameEvent, GamePhase, GameRecord,
+    GameState, GameStateJson, PlayerId, ReplayRecord, SeedReveal, SequencedEvent, TeamVictory,
+};
 use std::collections::HashMap;
+use std::mem::Discriminant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use uuid::Uuid;
 
-use crate::protocol::{PlayerInfo, RoomInfo, RoomStatus};
+use crate::protocol::{
+    BotSeat, ChatLine, Emote, PlayerInfo, Replay, RoomInfo, RoomSettings, RoomStatus,
+    ServerMessage, TimerKind,
+};
+use crate::stats::{GameResult, SeatResult};
+use crate::storage::{PlayerSnapshot, RoomSnapshot};
+use crate::webhook;
+
+/// Most bot actions played in a row before handing back to the server, in
+/// case a bot ever stops making progress
+const MAX_BOT_ACTIONS: usize = 2000;
+
+/// Longest a bot may think about one action, to keep turns moving
+const BOT_THINK_TIME: Duration = Duration::from_secs(2);
+
+/// How long a disconnected player's seat waits before a bot takes over
+pub const DEFAULT_DISCONNECT_GRACE: Duration = Duration::from_secs(30);
+
+/// Most default moves made for a player whose time ran out, in case a
+/// phase offers no way forward
+const MAX_TIMEOUT_ACTIONS: usize = 16;
 
 #[derive(Debug, Error)]
 pub enum RoomError {
@@ -31,7 +56,40 @@ pub enum RoomError {
     NotYourTurn,
 
     #[error("Invalid action: {0}")]
-    InvalidAction(String),
+    InvalidAction(GameError),
+
+    #[error("Player is still connected")]
+    StillConnected,
+
+    #[error("The host can't kick themselves")]
+    CannotKickHost,
+
+    #[error("A bot can't be host")]
+    BotCannotHost,
+
+    #[error("Game is paused")]
+    GamePaused,
+
+    #[error("Game is not paused")]
+    NotPaused,
+
+    #[error("Invalid settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("Invalid webhook URL")]
+    InvalidWebhook,
+
+    #[error("The game is not open to spectators")]
+    NotPublic,
+
+    #[error("That seat's player hasn't been gone long enough to replace")]
+    SeatNotAbandoned,
+
+    #[error("That seat isn't yours to take back; ask the host with ClaimSeat")]
+    NotYourSeat,
+
+    #[error("Saved room is corrupt: {0:?}")]
+    CorruptSnapshot(Vec<BoardError>),
 }
 
 /// A player in a game room.
@@ -43,6 +101,18 @@ pub struct RoomPlayer {
     pub connected: bool,
     /// Index in the game (0-3), assigned when game starts
     pub game_index: Option<u8>,
+    /// Set for computer players
+    pub bot: Option<BotSeat>,
+    /// When a person lost their connection, while they are away
+    pub disconnected_since: Option<Instant>,
+    /// A bot is playing this person's seat until they come back
+    pub stand_in: bool,
+    /// The request ID of the last action they sent with one, and the
+    /// answer it got, so a retry gets the same answer
+    pub last_request: Option<(String, ServerMessage)>,
+    /// Secret given only to this person's connection, to take the seat
+    /// back with after losing it
+    pub rejoin_token: Uuid,
 }
 
 impl RoomPlayer {
@@ -53,6 +123,19 @@ impl RoomPlayer {
             ready: false,
             connected: true,
             game_index: None,
+            bot: None,
+            disconnected_since: None,
+            stand_in: false,
+            last_request: None,
+            rejoin_token: Uuid::new_v4(),
+        }
+    }
+
+    pub fn new_bot(id: Uuid, name: String, seat: BotSeat) -> Self {
+        Self {
+            ready: true,
+            bot: Some(seat),
+            ..Self::new(id, name)
         }
     }
 
@@ -62,6 +145,8 @@ impl RoomPlayer {
             name: self.name.clone(),
             ready: self.ready,
             connected: self.connected,
+            bot: self.bot,
+            stand_in: self.stand_in,
         }
     }
 }
@@ -78,6 +163,74 @@ pub struct GameRoom {
     pub player_order: Vec<Uuid>,
     /// The game state (once started)
     pub game: Option<GameState>,
+    /// Computer players by game index (once started)
+    bots: HashMap<PlayerId, Bot>,
+    /// Actions applied so far, to notice a bot thinking about a stale state
+    version: u64,
+    /// Whether a task is already playing the bots' turns
+    bot_driver_running: bool,
+    /// When the host paused the game, if it is paused
+    paused_at: Option<Instant>,
+    /// Every game event so far, numbered for players catching up
+    pub events: EventLog,
+    /// Chat so far, numbered the same way
+    pub chat: Vec<ChatLine>,
+    /// Rules, seed and time limits the host chose
+    pub settings: RoomSettings,
+    /// The limit currently running, if any
+    timer: Option<TurnTimer>,
+    /// Time a turn had left when a decision interrupted it
+    paused_turn: Option<(TimerKey, Duration)>,
+    /// When the game began, for the length of the game
+    started_at: Option<SystemTime>,
+    /// The finished game's result has been handed out
+    result_recorded: bool,
+    /// Where the game's lifecycle events are POSTed, if anywhere
+    pub webhook_url: Option<String>,
+    /// The starting position and every move, for the replay
+    record: Option<GameRecord>,
+    /// The game's seed, committed to at the start and revealed at the end
+    seed: Option<SeedReveal>,
+}
+
+/// A time limit running against the players the game waits on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnTimer {
+    pub kind: TimerKind,
+    /// Seats the game is waiting on, bots aside
+    pub players: Vec<PlayerId>,
+    pub deadline: Instant,
+    /// The turn or decision timed, so the timer only restarts for a new one
+    key: TimerKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimerKey {
+    Turn {
+        turn: u32,
+        player: PlayerId,
+    },
+    Decision {
+        turn: u32,
+        phase: Discriminant<GamePhase>,
+    },
+}
+
+/// A bot's move, taken out of the room so the bot can think while the
+/// room carries on
+pub struct BotTurn {
+    pub seat: PlayerId,
+    bot: Bot,
+    game: GameState,
+    version: u64,
+}
+
+impl BotTurn {
+    /// Let the bot decide, within the think time
+    pub fn think(&mut self) -> Option<GameAction> {
+        let deadline = Instant::now() + BOT_THINK_TIME;
+        self.bot.choose_action_until(&self.game, Some(deadline))
+    }
 }
 
 impl GameRoom {
@@ -94,7 +247,142 @@ impl GameRoom {
             players,
             player_order: vec![host_id],
             game: None,
+            bots: HashMap::new(),
+            version: 0,
+            bot_driver_running: false,
+            paused_at: None,
+            events: EventLog::new(),
+            chat: Vec::new(),
+            settings: RoomSettings::default(),
+            timer: None,
+            paused_turn: None,
+            started_at: None,
+            result_recorded: false,
+            webhook_url: None,
+            record: None,
+            seed: None,
+        }
+    }
+
+    /// A tournament table: everyone seated and the game under way. Like a
+    /// room brought back after a restart, nobody is connected yet, and the
+    /// players take their seats with a rejoin.
+    pub fn for_table(
+        id: Uuid,
+        name: String,
+        seats: &[(Uuid, String)],
+        settings: RoomSettings,
+    ) -> Result<Self, RoomError> {
+        let Some((host_id, host_name)) = seats.first().cloned() else {
+            return Err(RoomError::NotEnoughPlayers);
+        };
+        let mut room = Self::new(id, host_id, host_name, seats.len() as u8);
+        room.name = name;
+        for (player_id, name) in &seats[1..] {
+            room.players
+                .insert(*player_id, RoomPlayer::new(*player_id, name.clone()));
+            room.player_order.push(*player_id);
+        }
+        room.update_settings(host_id, settings)?;
+        room.start_game(host_id)?;
+        let now = Instant::now();
+        for player in room.players.values_mut() {
+            player.ready = true;
+            player.connected = false;
+            player.disconnected_since = Some(now);
         }
+        Ok(room)
+    }
+
+    /// Everything needed to bring the room back after a restart
+    pub fn snapshot(&self) -> RoomSnapshot {
+        RoomSnapshot {
+            id: self.id,
+            name: self.name.clone(),
+            max_players: self.max_players,
+            host_id: self.host_id,
+            status: self.status,
+            players: self
+                .player_order
+                .iter()
+                .filter_map(|id| self.players.get(id))
+                .map(|p| PlayerSnapshot {
+                    id: p.id,
+                    name: p.name.clone(),
+                    ready: p.ready,
+                    game_index: p.game_index,
+                    bot: p.bot,
+                    stand_in: p.stand_in,
+                    rejoin_token: p.rejoin_token,
+                })
+                .collect(),
+            game: self.game.clone(),
+            events: self.events.clone(),
+            chat: self.chat.clone(),
+            settings: self.settings.clone(),
+            paused: self.is_paused(),
+            started_at: self.started_at,
+            result_recorded: self.result_recorded,
+            webhook_url: self.webhook_url.clone(),
+            record: self.record.clone(),
+            seed: self.seed.clone(),
+        }
+    }
+
+    /// Bring back a saved room. Nobody is connected yet, so every person
+    /// starts out disconnected and can rejoin their seat. A saved game whose
+    /// board breaks its invariants is refused rather than played on.
+    pub fn from_snapshot(snapshot: RoomSnapshot) -> Result<Self, RoomError> {
+        if let Some(game) = &snapshot.game {
+            game.board.validate().map_err(RoomError::CorruptSnapshot)?;
+        }
+        let mut room =
+            Self::new(snapshot.id, snapshot.host_id, String::new(), snapshot.max_players);
+        room.name = snapshot.name;
+        room.status = snapshot.status;
+        room.settings = snapshot.settings;
+        room.players.clear();
+        room.player_order.clear();
+
+        for p in snapshot.players {
+            let mut player = RoomPlayer::new(p.id, p.name);
+            player.ready = p.ready;
+            player.game_index = p.game_index;
+            player.bot = p.bot;
+            player.stand_in = p.stand_in;
+            player.rejoin_token = p.rejoin_token;
+            if p.bot.is_none() {
+                player.connected = false;
+                player.disconnected_since = Some(Instant::now());
+            }
+
+            if let Some(idx) = p.game_index {
+                let bot = match (p.bot, p.stand_in) {
+                    (Some(seat), _) => {
+                        Some(Bot::new(idx, seat.difficulty).with_personality(seat.personality))
+                    }
+                    (None, true) => Some(Bot::new(idx, room.settings.stand_in_difficulty)),
+                    (None, false) => None,
+                };
+                if let Some(bot) = bot {
+                    room.bots.insert(idx, bot);
+                }
+            }
+            room.player_order.push(p.id);
+            room.players.insert(p.id, player);
+        }
+
+        room.game = snapshot.game;
+        room.events = snapshot.events;
+        room.chat = snapshot.chat;
+        room.paused_at = snapshot.paused.then(Instant::now);
+        room.started_at = snapshot.started_at;
+        room.result_recorded = snapshot.result_recorded;
+        room.webhook_url = snapshot.webhook_url;
+        room.record = snapshot.record;
+        room.seed = snapshot.seed;
+        room.update_timer();
+        Ok(room)
     }
 
     pub fn player_count(&self) -> usize {
@@ -118,6 +406,87 @@ impl GameRoom {
         Ok(())
     }
 
+    /// Remove someone, bot or person, from the lobby (host only)
+    pub fn kick_player(&mut self, requester_id: Uuid, player_id: Uuid) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        if self.status != RoomStatus::Waiting {
+            return Err(RoomError::GameAlreadyStarted);
+        }
+        if player_id == self.host_id {
+            return Err(RoomError::CannotKickHost);
+        }
+        // The host stays, so the room does too
+        self.remove_player(player_id).map(|_| ())
+    }
+
+    /// Hand the host's controls to another person in the room
+    pub fn transfer_host(&mut self, requester_id: Uuid, player_id: Uuid) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        let player = self.players.get(&player_id).ok_or(RoomError::PlayerNotInRoom)?;
+        if player.bot.is_some() {
+            return Err(RoomError::BotCannotHost);
+        }
+        self.host_id = player_id;
+        Ok(())
+    }
+
+    /// Change how the game will be played (host only, before it starts)
+    pub fn update_settings(
+        &mut self,
+        requester_id: Uuid,
+        settings: RoomSettings,
+    ) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        if self.status != RoomStatus::Waiting {
+            return Err(RoomError::GameAlreadyStarted);
+        }
+        check_settings(&settings, self.max_players)?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Send the game's lifecycle events to a URL, or stop sending them
+    /// (host only)
+    pub fn set_webhook(
+        &mut self,
+        requester_id: Uuid,
+        url: Option<String>,
+    ) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        if url.as_deref().is_some_and(|url| !webhook::valid_url(url)) {
+            return Err(RoomError::InvalidWebhook);
+        }
+        self.webhook_url = url;
+        Ok(())
+    }
+
+    /// Seat a computer player, returning its ID
+    pub fn add_bot(&mut self, requester_id: Uuid, seat: BotSeat) -> Result<Uuid, RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        if self.status != RoomStatus::Waiting {
+            return Err(RoomError::GameAlreadyStarted);
+        }
+        if self.is_full() {
+            return Err(RoomError::RoomFull);
+        }
+
+        let bot_id = Uuid::new_v4();
+        let name = format!("Bot {} ({:?})", self.player_order.len() + 1, seat.personality);
+        self.players.insert(bot_id, RoomPlayer::new_bot(bot_id, name, seat));
+        self.player_order.push(bot_id);
+        Ok(bot_id)
+    }
+
     pub fn remove_player(&mut self, player_id: Uuid) -> Result<bool, RoomError> {
         if !self.players.contains_key(&player_id) {
             return Err(RoomError::PlayerNotInRoom);
@@ -126,19 +495,147 @@ impl GameRoom {
         self.players.remove(&player_id);
         self.player_order.retain(|&id| id != player_id);
 
+        let humans: Vec<Uuid> = self
+            .player_order
+            .iter()
+            .copied()
+            .filter(|id| self.players.get(id).is_some_and(|p| p.bot.is_none()))
+            .collect();
+
         // If host left, assign new host
-        if player_id == self.host_id && !self.player_order.is_empty() {
-            self.host_id = self.player_order[0];
+        if player_id == self.host_id && !humans.is_empty() {
+            self.host_id = humans[0];
         }
 
-        // Return true if room is now empty
-        Ok(self.players.is_empty())
+        // Return true if no people are left; bots don't keep a room open
+        Ok(humans.is_empty())
     }
 
     pub fn set_player_connected(&mut self, player_id: Uuid, connected: bool) {
         if let Some(player) = self.players.get_mut(&player_id) {
             player.connected = connected;
+            player.disconnected_since = (!connected).then(Instant::now);
+        }
+    }
+
+    /// Hand a person's seat to a bot once they have been gone for `grace`.
+    /// Returns whether a bot took over.
+    pub fn substitute_bot(&mut self, player_id: Uuid, grace: Duration) -> bool {
+        if self.status != RoomStatus::InGame {
+            return false;
+        }
+        let Some(player) = self.players.get_mut(&player_id) else {
+            return false;
+        };
+        let away_long_enough = player.disconnected_since.is_some_and(|t| t.elapsed() >= grace);
+        if player.bot.is_some() || player.stand_in || !away_long_enough {
+            return false;
+        }
+        let Some(seat) = player.game_index else {
+            return false;
+        };
+
+        player.stand_in = true;
+        let difficulty = self.settings.stand_in_difficulty;
+        self.bots.insert(seat, Bot::new(seat, difficulty));
+        self.update_timer();
+        true
+    }
+
+    /// Give a disconnected person their seat back on a new connection,
+    /// taking it back from any stand-in bot. Only the same player, or a
+    /// connection showing the seat's rejoin token, may take it.
+    pub fn rejoin(
+        &mut self,
+        old_id: Uuid,
+        new_id: Uuid,
+        token: Option<Uuid>,
+    ) -> Result<(), RoomError> {
+        let player = self.players.get(&old_id).ok_or(RoomError::PlayerNotInRoom)?;
+        if player.bot.is_some() {
+            return Err(RoomError::PlayerNotInRoom);
+        }
+        if new_id != old_id && token != Some(player.rejoin_token) {
+            return Err(RoomError::NotYourSeat);
+        }
+        // The same player may come back before their old connection is
+        // noticed as gone
+        if player.connected && new_id != old_id {
+            return Err(RoomError::StillConnected);
+        }
+        self.reseat(old_id, new_id);
+        Ok(())
+    }
+
+    /// The secret `player_id` can take their seat back with
+    pub fn rejoin_token(&self, player_id: Uuid) -> Option<Uuid> {
+        self.players
+            .get(&player_id)
+            .filter(|p| p.bot.is_none())
+            .map(|p| p.rejoin_token)
+    }
+
+    /// Move a seat over to a new connection, with a fresh rejoin token
+    fn reseat(&mut self, old_id: Uuid, new_id: Uuid) {
+        let Some(mut player) = self.players.remove(&old_id) else {
+            return;
+        };
+        if player.stand_in {
+            if let Some(seat) = player.game_index {
+                self.bots.remove(&seat);
+            }
+        }
+        player.id = new_id;
+        player.connected = true;
+        player.disconnected_since = None;
+        player.stand_in = false;
+        player.rejoin_token = Uuid::new_v4();
+        self.players.insert(new_id, player);
+
+        for id in self.player_order.iter_mut().filter(|id| **id == old_id) {
+            *id = new_id;
+        }
+        if self.host_id == old_id {
+            self.host_id = new_id;
         }
+        self.update_timer();
+    }
+
+    /// Who holds a seat a newcomer may take over: a person who has been
+    /// gone long enough for a bot to stand in for them
+    pub fn abandoned_seat(&self, seat: PlayerId) -> Result<Uuid, RoomError> {
+        if self.status != RoomStatus::InGame {
+            return Err(RoomError::GameNotStarted);
+        }
+        let player = self
+            .player_at(seat)
+            .and_then(|id| self.players.get(&id))
+            .ok_or(RoomError::PlayerNotInRoom)?;
+        if player.bot.is_some() || player.connected || !player.stand_in {
+            return Err(RoomError::SeatNotAbandoned);
+        }
+        Ok(player.id)
+    }
+
+    /// Seat someone new in an abandoned seat, under their own name. They
+    /// play on from where the game is, with the seat's hand.
+    pub fn take_over_seat(
+        &mut self,
+        seat: PlayerId,
+        new_id: Uuid,
+        name: String,
+    ) -> Result<(), RoomError> {
+        let old_id = self.abandoned_seat(seat)?;
+        self.reseat(old_id, new_id);
+        let game = self.game.as_mut();
+        if let Some(player) = game.and_then(|g| g.players.get_mut(seat as usize)) {
+            player.name = name.clone();
+        }
+        if let Some(player) = self.players.get_mut(&new_id) {
+            player.name = name;
+            player.last_request = None;
+        }
+        Ok(())
     }
 
     pub fn start_game(&mut self, requester_id: Uuid) -> Result<(), RoomError> {
@@ -151,6 +648,12 @@ impl GameRoom {
         if self.players.len() < 2 {
             return Err(RoomError::NotEnoughPlayers);
         }
+        let teams = &self.settings.options.teams;
+        if !teams.is_empty() && teams.len() != self.players.len() {
+            return Err(RoomError::InvalidSettings(
+                "Teams must cover every seat".to_string(),
+            ));
+        }
 
         // Assign game indices to players
         for (idx, &player_id) in self.player_order.iter().enumerate() {
@@ -167,8 +670,30 @@ impl GameRoom {
             .collect();
 
         // Create game state
-        self.game = Some(GameState::new(player_names.len() as u8, player_names));
+        let count = player_names.len() as u8;
+        let options = self.settings.options.clone();
+        let game = match self.settings.seed {
+            Some(seed) => GameState::new_with_seed(count, player_names, options, seed),
+            None => GameState::new_with_options(count, player_names, options),
+        };
+        self.record = Some(GameRecord::new(&game));
+        self.seed = Some(SeedReveal::new(game.seed()));
+        self.game = Some(game);
         self.status = RoomStatus::InGame;
+        self.started_at = Some(SystemTime::now());
+
+        self.bots = self
+            .player_order
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, id)| {
+                let seat = self.players.get(id)?.bot?;
+                let idx = idx as PlayerId;
+                let bot = Bot::new(idx, seat.difficulty).with_personality(seat.personality);
+                Some((idx, bot))
+            })
+            .collect();
+        self.update_timer();
 
         Ok(())
     }
@@ -176,9 +701,12 @@ impl GameRoom {
     pub fn apply_action(
         &mut self,
         player_id: Uuid,
-        action: serde_json::Value,
+        action: GameAction,
     ) -> Result<Vec<GameEvent>, RoomError> {
-        let game = self.game.as_mut().ok_or(RoomError::GameNotStarted)?;
+        let game = self.game.as_ref().ok_or(RoomError::GameNotStarted)?;
+        if self.is_paused() {
+            return Err(RoomError::GamePaused);
+        }
 
         let player = self
             .players
@@ -188,44 +716,462 @@ impl GameRoom {
         let game_index = player.game_index.ok_or(RoomError::PlayerNotInRoom)?;
 
         // Check if it's this player's turn
-        // Exception: During DiscardRequired phase, any player in players_remaining can act
+        // Exception: During DiscardRequired phase, any player in players_remaining can act,
+        // anyone may concede or take part in an end-game vote at any time, and
+        // trade partners answer an offer
         let is_discard_phase = matches!(game.phase, GamePhase::DiscardRequired { .. });
-        if !is_discard_phase && game.current_player != game_index {
+        let is_out_of_turn = matches!(
+            action,
+            GameAction::Concede
+                | GameAction::ProposeEndGame
+                | GameAction::VoteEndGame(_)
+                | GameAction::AcceptTrade
+                | GameAction::RejectTrade
+        );
+        if !is_discard_phase && !is_out_of_turn && game.current_player != game_index {
             return Err(RoomError::NotYourTurn);
         }
 
-        // Parse and apply action
-        let action: GameAction = serde_json::from_value(action)
-            .map_err(|e| RoomError::InvalidAction(e.to_string()))?;
+        self.play(game_index, action)
+    }
+
+    /// Apply an action sent with a request ID only once. A client retrying
+    /// over a flaky connection sends the same ID again, and gets the first
+    /// answer back instead of ending its turn twice. Returns the answer for
+    /// the player and whether the action was applied just now.
+    pub fn apply_request(
+        &mut self,
+        player_id: Uuid,
+        action: GameAction,
+        request_id: Option<String>,
+    ) -> (ServerMessage, bool) {
+        let player = self.players.get(&player_id);
+        if let (Some((seen, answer)), Some(id)) = (
+            player.and_then(|p| p.last_request.as_ref()),
+            request_id.as_ref(),
+        ) {
+            if seen == id {
+                return (answer.clone(), false);
+            }
+        }
 
+        let (answer, applied) = match self.apply_action(player_id, action) {
+            Ok(events) => (
+                ServerMessage::ActionResult {
+                    success: true,
+                    events: self.hide_events(player_id, &events),
+                    error: None,
+                    error_code: None,
+                    game_error: None,
+                    request_id: request_id.clone(),
+                },
+                true,
+            ),
+            Err(e) => {
+                let game_error = match &e {
+                    RoomError::InvalidAction(error) => Some(error.clone()),
+                    _ => None,
+                };
+                (
+                    ServerMessage::ActionResult {
+                        success: false,
+                        events: vec![],
+                        error: Some(e.to_string()),
+                        error_code: game_error.as_ref().map(GameError::code),
+                        game_error,
+                        request_id: request_id.clone(),
+                    },
+                    false,
+                )
+            }
+        };
+        if let (Some(id), Some(player)) = (request_id, self.players.get_mut(&player_id)) {
+            player.last_request = Some((id, answer.clone()));
+        }
+        (answer, applied)
+    }
+
+    /// Apply an action for a seat, keeping the event log, bots and timer up
+    /// to date
+    fn play(&mut self, seat: PlayerId, action: GameAction) -> Result<Vec<GameEvent>, RoomError> {
+        let game = self.game.as_mut().ok_or(RoomError::GameNotStarted)?;
         let events = game
-            .apply_action(game_index, action)
-            .map_err(|e| RoomError::InvalidAction(e.to_string()))?;
+            .apply_action(seat, action.clone())
+            .map_err(RoomError::InvalidAction)?;
+        self.version += 1;
+        self.events.record(&events);
+        if let Some(record) = &mut self.record {
+            record.push(seat, action);
+        }
+        for bot in self.bots.values_mut() {
+            bot.observe(game, &events);
+        }
 
         // Check if game is finished
         if game.is_finished() {
             self.status = RoomStatus::Finished;
         }
+        self.update_timer();
 
         Ok(events)
     }
 
-    pub fn get_game_state(&self) -> Option<serde_json::Value> {
-        self.game.as_ref().map(|g| {
-            // Use JSON-friendly representation to avoid HashMap serialization issues
-            serde_json::to_value(g.to_json_friendly()).unwrap()
+    /// Let computer players act until the game waits on a person (or
+    /// ends), returning what happened
+    pub fn run_bots(&mut self) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        for _ in 0..MAX_BOT_ACTIONS {
+            let Some(mut turn) = self.next_bot_turn() else {
+                break;
+            };
+            let action = turn.think();
+            match self.finish_bot_turn(turn, action) {
+                Some(more) => events.extend(more),
+                None => break,
+            }
+        }
+        events
+    }
+
+    /// Claim the job of playing the bots' turns; false when a task
+    /// already has it. The claim ends once no bot is left to move.
+    pub fn start_bot_driver(&mut self) -> bool {
+        !std::mem::replace(&mut self.bot_driver_running, true)
+    }
+
+    /// Give up the claim without playing on, e.g. after a bot failed
+    pub fn stop_bot_driver(&mut self) {
+        self.bot_driver_running = false;
+    }
+
+    /// Take out the bot the game is waiting on, if any, along with a copy
+    /// of the game to think about. Ends the driver's claim when there is
+    /// none.
+    pub fn next_bot_turn(&mut self) -> Option<BotTurn> {
+        let playing = self.game.as_ref().filter(|g| !g.is_finished() && !self.is_paused());
+        let turn = playing.and_then(|game| {
+            let seat = game
+                .waiting_on()
+                .into_iter()
+                .find(|player| self.bots.contains_key(player))?;
+            let bot = self.bots.remove(&seat)?;
+            Some(BotTurn {
+                seat,
+                bot,
+                game: game.clone(),
+                version: self.version,
+            })
+        });
+        if turn.is_none() {
+            self.bot_driver_running = false;
+        }
+        turn
+    }
+
+    /// Play a bot's chosen action and put the bot back. Returns the
+    /// events, which are empty when the game moved on while the bot was
+    /// thinking and it should be asked again. Returns `None`, ending the
+    /// driver's claim, when the bot had no move or its move was rejected.
+    pub fn finish_bot_turn(
+        &mut self,
+        turn: BotTurn,
+        action: Option<GameAction>,
+    ) -> Option<Vec<GameEvent>> {
+        let BotTurn {
+            seat,
+            mut bot,
+            version,
+            ..
+        } = turn;
+        let still_bot = self.seat_is_bot(seat);
+        let stale = version != self.version || !still_bot || self.is_paused();
+        let game = self.game.as_mut()?;
+
+        let events = match &action {
+            _ if stale => Some(Vec::new()),
+            Some(action) => game.apply_action(seat, action.clone()).ok(),
+            None => None,
+        };
+        if let Some(events) = events.as_ref().filter(|e| !e.is_empty()) {
+            self.version += 1;
+            self.events.record(events);
+            if let (Some(record), Some(action)) = (&mut self.record, action) {
+                record.push(seat, action);
+            }
+            bot.observe(game, events);
+            for other in self.bots.values_mut() {
+                other.observe(game, events);
+            }
+        }
+        // A person who came back while their stand-in was thinking keeps
+        // their seat
+        if still_bot {
+            self.bots.insert(seat, bot);
+        }
+
+        if game.is_finished() {
+            self.status = RoomStatus::Finished;
+        }
+        if events.is_none() {
+            self.bot_driver_running = false;
+        }
+        self.update_timer();
+        events
+    }
+
+    /// Whether a bot plays the seat, for itself or standing in
+    fn seat_is_bot(&self, seat: PlayerId) -> bool {
+        self.player_order.get(seat as usize).is_some_and(|id| {
+            self.players
+                .get(id)
+                .is_some_and(|p| p.bot.is_some() || p.stand_in)
         })
     }
 
-    pub fn get_valid_actions(&self) -> Option<Vec<serde_json::Value>> {
-        self.game.as_ref().map(|g| {
-            g.valid_actions(g.current_player)
+    /// The time limit running now, if any. Timers stand still while the
+    /// game is paused.
+    pub fn timer(&self) -> Option<&TurnTimer> {
+        self.timer.as_ref().filter(|_| !self.is_paused())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Stop the game: no moves are accepted, bots wait and timers stand
+    /// still until it resumes (host only)
+    pub fn pause(&mut self, requester_id: Uuid) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        if self.status != RoomStatus::InGame {
+            return Err(RoomError::GameNotStarted);
+        }
+        if self.is_paused() {
+            return Err(RoomError::GamePaused);
+        }
+        self.paused_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Carry on after a pause, giving the timer back the time it had left
+    /// (host only)
+    pub fn resume(&mut self, requester_id: Uuid) -> Result<(), RoomError> {
+        if requester_id != self.host_id {
+            return Err(RoomError::NotHost);
+        }
+        let paused_at = self.paused_at.take().ok_or(RoomError::NotPaused)?;
+        if let Some(timer) = self.timer.as_mut() {
+            timer.deadline += paused_at.elapsed();
+        }
+        Ok(())
+    }
+
+    /// Start, keep or stop the timer to match what the game is waiting on.
+    /// A turn's clock stops while a decision interrupts it and picks up
+    /// again afterwards.
+    fn update_timer(&mut self) {
+        let now = Instant::now();
+        let wanted = self.game.as_ref().filter(|g| !g.is_finished()).and_then(|game| {
+            let turn = game.turn_number;
+            let (kind, key, secs) = if is_decision(&game.phase) {
+                let phase = std::mem::discriminant(&game.phase);
+                let key = TimerKey::Decision { turn, phase };
+                (TimerKind::Decision, key, self.settings.timers.decision_secs)
+            } else {
+                let key = TimerKey::Turn {
+                    turn,
+                    player: game.current_player,
+                };
+                (TimerKind::Turn, key, self.settings.timers.turn_secs)
+            };
+            let limit = Duration::from_secs(secs?);
+            let players: Vec<PlayerId> = game
+                .waiting_on()
                 .into_iter()
-                .map(|a| serde_json::to_value(a).unwrap())
-                .collect()
+                .filter(|&seat| !self.seat_is_bot(seat))
+                .collect();
+            (!players.is_empty()).then_some((kind, key, limit, players))
+        });
+
+        let previous = self.timer.take();
+        if let Some(old) = previous.as_ref().filter(|t| t.kind == TimerKind::Turn) {
+            if wanted.as_ref().map(|w| w.1) != Some(old.key) {
+                self.paused_turn = Some((old.key, old.deadline.saturating_duration_since(now)));
+            }
+        }
+        self.timer = wanted.map(|(kind, key, limit, players)| {
+            let deadline = match (&previous, self.paused_turn) {
+                (Some(old), _) if old.key == key => old.deadline,
+                (_, Some((paused, left))) if paused == key => now + left,
+                _ => now + limit,
+            };
+            TurnTimer {
+                kind,
+                players,
+                deadline,
+                key,
+            }
+        });
+    }
+
+    /// Move for whoever let the timer run out by `now`: finish the turn for
+    /// them, or make the pending decision (discarding the largest piles,
+    /// or moving the robber where it blocks nobody). Returns the timer and
+    /// what happened, or None if no time is up.
+    pub fn expire_timer(&mut self, now: Instant) -> Option<(TurnTimer, Vec<GameEvent>)> {
+        let timer = self.timer().filter(|t| t.deadline <= now)?.clone();
+        let mut events = Vec::new();
+        for &seat in &timer.players {
+            for _ in 0..MAX_TIMEOUT_ACTIONS {
+                let Some(game) = self.game.as_ref() else {
+                    break;
+                };
+                let due = game.waiting_on().contains(&seat)
+                    && (timer.kind == TimerKind::Turn || is_decision(&game.phase));
+                let Some(action) = game.auto_action(seat).filter(|_| due) else {
+                    break;
+                };
+                match self.play(seat, action) {
+                    Ok(more) => events.extend(more),
+                    Err(_) => break,
+                }
+            }
+        }
+        Some((timer, events))
+    }
+
+    /// The game and the player's seat in it, to work a hint out from
+    pub fn hint_position(&self, player_id: Uuid) -> Result<(GameState, PlayerId), RoomError> {
+        let game = self.game.as_ref().ok_or(RoomError::GameNotStarted)?;
+        let game_index = self
+            .players
+            .get(&player_id)
+            .and_then(|p| p.game_index)
+            .ok_or(RoomError::PlayerNotInRoom)?;
+        Ok((game.clone(), game_index))
+    }
+
+    /// Keep a chat message, returning the line to send
+    pub fn add_chat(
+        &mut self,
+        player_id: Uuid,
+        player_name: String,
+        message: String,
+        emote: Option<Emote>,
+    ) -> ChatLine {
+        let line = ChatLine {
+            seq: self.chat.len() as u64 + 1,
+            player_name,
+            message,
+            player_id: Some(player_id),
+            emote,
+        };
+        self.chat.push(line.clone());
+        line
+    }
+
+    /// Who sits in a seat, person or bot
+    pub fn player_at(&self, seat: PlayerId) -> Option<Uuid> {
+        self.players
+            .values()
+            .find(|p| p.game_index == Some(seat))
+            .map(|p| p.id)
+    }
+
+    /// The seat a player sees the game from; None for anyone without one
+    fn viewer(&self, player_id: Uuid) -> Option<PlayerId> {
+        self.players.get(&player_id).and_then(|p| p.game_index)
+    }
+
+    /// Events after `since` as a player may see them (spectators for
+    /// anyone without a seat)
+    pub fn events_for(&self, player_id: Uuid, since: u64) -> Vec<SequencedEvent> {
+        let viewer = self.viewer(player_id);
+        self.events
+            .since(since)
+            .iter()
+            .filter_map(|e| e.visible_to(viewer))
+            .collect()
+    }
+
+    /// The game to watch again: its moves once it is over, and its events
+    /// as `player_id` may see them. A game still being played is only
+    /// shown to its players, unless it is public.
+    pub fn replay(&self, player_id: Uuid) -> Result<Replay, RoomError> {
+        if self.game.is_none() {
+            return Err(RoomError::GameNotStarted);
+        }
+        let finished = self.status == RoomStatus::Finished;
+        if !finished && !self.settings.public && !self.players.contains_key(&player_id) {
+            return Err(RoomError::NotPublic);
+        }
+        Ok(Replay {
+            game_id: self.id,
+            room: self.to_info(),
+            record: self.record.as_ref().filter(|_| finished).map(ReplayRecord::new),
+            dice_commitment: self.dice_commitment(),
+            seed: self.revealed_seed(),
+            events: self.events_for(player_id, 0),
         })
     }
 
+    /// The hash the game's seed was committed to before the first roll
+    pub fn dice_commitment(&self) -> Option<String> {
+        self.seed.as_ref().map(SeedReveal::commitment)
+    }
+
+    /// The game's seed and salt, once the game is over and they can no
+    /// longer tell anyone what the dice will do
+    pub fn revealed_seed(&self) -> Option<SeedReveal> {
+        self.seed
+            .clone()
+            .filter(|_| self.status == RoomStatus::Finished)
+    }
+
+    /// Events after `since` as someone without a seat sees them
+    pub fn spectator_events(&self, since: u64) -> Vec<SequencedEvent> {
+        self.events
+            .since(since)
+            .iter()
+            .filter_map(|e| e.visible_to(None))
+            .collect()
+    }
+
+    /// Events as a player may see them, e.g. without the card stolen from
+    /// someone else
+    pub fn hide_events(&self, player_id: Uuid, events: &[GameEvent]) -> Vec<GameEvent> {
+        let viewer = self.viewer(player_id);
+        events.iter().filter_map(|e| e.visible_to(viewer)).collect()
+    }
+
+    /// What a player missed since the given event and chat line
+    pub fn catch_up(&self, player_id: Uuid, last_seq: u64, last_chat_seq: u64) -> ServerMessage {
+        let chat_start = (last_chat_seq as usize).min(self.chat.len());
+        ServerMessage::CatchUp {
+            state: self.game_state_for(player_id),
+            events: self.events_for(player_id, last_seq),
+            chat: self.chat[chat_start..].to_vec(),
+        }
+    }
+
+    /// The game as a player may see it: their own hand and cards, but only
+    /// how many everyone else holds. Everything shows once the game is over.
+    pub fn game_state_for(&self, player_id: Uuid) -> Option<Box<GameStateJson>> {
+        let viewer = self.viewer(player_id);
+        self.game.as_ref().map(|g| Box::new(g.view_for(viewer)))
+    }
+
+    /// What a player may do now: the current player's moves, but also
+    /// answering a trade offer or discarding out of turn. Nothing for
+    /// anyone without a seat.
+    pub fn valid_actions_for(&self, player_id: Uuid) -> Option<Vec<GameAction>> {
+        let viewer = self.viewer(player_id);
+        self.game
+            .as_ref()
+            .map(|g| viewer.map(|seat| g.valid_actions(seat)).unwrap_or_default())
+    }
+
     pub fn get_current_player(&self) -> Option<usize> {
         self.game.as_ref().map(|g| g.current_player as usize)
     }
@@ -238,6 +1184,48 @@ impl GameRoom {
         Some((winner_idx, winner_name))
     }
 
+    /// Final standings as player indices, if the game is finished
+    pub fn get_ranking(&self) -> Option<Vec<usize>> {
+        let game = self.game.as_ref()?;
+        let ranking = game.get_ranking()?;
+        Some(ranking.iter().map(|&p| p as usize).collect())
+    }
+
+    /// How the game went, the first time it is asked for once the game is
+    /// over
+    pub fn take_result(&mut self) -> Option<GameResult> {
+        if self.status != RoomStatus::Finished || self.result_recorded {
+            return None;
+        }
+        let game = self.game.as_ref()?;
+        let ranking = game.get_ranking()?.iter().map(|&p| p as usize).collect();
+        let now = SystemTime::now();
+        let players = self
+            .player_order
+            .iter()
+            .filter_map(|id| self.players.get(id))
+            .filter_map(|p| {
+                Some(SeatResult {
+                    player_id: p.id,
+                    name: p.name.clone(),
+                    victory_points: game.total_victory_points(p.game_index?),
+                    bot: p.bot.is_some(),
+                })
+            })
+            .collect();
+        let since = |t: SystemTime, earlier| t.duration_since(earlier).unwrap_or_default();
+
+        self.result_recorded = true;
+        Some(GameResult {
+            room_id: self.id,
+            finished_at: since(now, UNIX_EPOCH).as_secs(),
+            duration_secs: self.started_at.map_or(0, |t| since(now, t).as_secs()),
+            players,
+            winner: game.get_winner().map(|p| p as usize),
+            ranking,
+        })
+    }
+
     pub fn to_info(&self) -> RoomInfo {
         RoomInfo {
             id: self.id,
@@ -250,13 +1238,81 @@ impl GameRoom {
             max_players: self.max_players,
             host_id: self.host_id,
             status: self.status,
+            settings: self.settings.clone(),
+            paused: self.is_paused(),
         }
     }
 }
 
+/// Reject settings the server can't play with. Whether teams cover every
+/// seat is checked when the game starts, once everyone has sat down.
+pub fn check_settings(settings: &RoomSettings, max_players: u8) -> Result<(), RoomError> {
+    let invalid = |reason: &str| Err(RoomError::InvalidSettings(reason.to_string()));
+    let options = &settings.options;
+
+    if options.victory_points_to_win.is_some_and(|vp| !(3..=30).contains(&vp)) {
+        return invalid("victory points to win must be between 3 and 30");
+    }
+    if options.max_turns == Some(0) {
+        return invalid("a turn limit must allow at least one turn");
+    }
+    // The server times turns itself and never ticks the engine's clock
+    if options.time_control.is_some() {
+        return invalid("use turn timers instead of a game clock");
+    }
+    if options.hot_seat {
+        return invalid("hot-seat play is for a single device");
+    }
+    if options.teams.len() > max_players as usize {
+        return invalid("more team seats than the room has");
+    }
+    if matches!(options.team_victory, TeamVictory::Combined { .. }) && options.teams.is_empty() {
+        return invalid("a combined team victory needs teams");
+    }
+    if !(2..=3).contains(&options.board_gen.radius) {
+        return invalid("the board radius must be 2 or 3");
+    }
+    if !(1..=1000).contains(&options.board_gen.max_attempts) {
+        return invalid("board generation may take 1 to 1000 attempts");
+    }
+    if options.starting_hand.total() > 10 {
+        return invalid("a starting hand may hold at most 10 cards");
+    }
+    if let Some(die) = &options.event_die {
+        if die.faces.is_empty() {
+            return invalid("the event die needs at least one face");
+        }
+        if !(1..=20).contains(&die.track_length) {
+            return invalid("the barbarian track must be 1 to 20 steps long");
+        }
+    }
+    let timers = settings.timers;
+    if [timers.turn_secs, timers.decision_secs]
+        .into_iter()
+        .flatten()
+        .any(|secs| !(5..=3600).contains(&secs))
+    {
+        return invalid("timers must be between 5 seconds and an hour");
+    }
+    Ok(())
+}
+
+/// Phases where the game stops for a decision rather than playing on
+fn is_decision(phase: &GamePhase) -> bool {
+    matches!(
+        phase,
+        GamePhase::RobberMoveRequired
+            | GamePhase::RobberSteal { .. }
+            | GamePhase::DiscardRequired { .. }
+            | GamePhase::EndGameVote { .. }
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::TurnTimers;
+    use catan_core::{BotDifficulty, PhaseKind, ResourceHand, TradeOffer};
 
     #[test]
     fn test_create_room() {
@@ -310,4 +1366,498 @@ mod tests {
         assert_eq!(room.status, RoomStatus::InGame);
         assert!(room.game.is_some());
     }
+
+    #[test]
+    fn test_bots_fill_seats_and_play_their_turns() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 3);
+        let seat = BotSeat {
+            difficulty: catan_core::BotDifficulty::Medium,
+            personality: catan_core::BotPersonality::Blocker,
+        };
+
+        // Only the host adds bots
+        assert!(room.add_bot(Uuid::new_v4(), seat).is_err());
+        room.add_bot(host_id, seat).unwrap();
+        room.add_bot(host_id, seat).unwrap();
+        assert!(room.is_full());
+        assert_eq!(room.to_info().players[1].bot, Some(seat));
+
+        room.start_game(host_id).unwrap();
+        room.run_bots();
+        // Setup goes around until the host has to place
+        let game = room.game.as_ref().unwrap();
+        assert_eq!(game.waiting_on(), vec![0]);
+
+        // A room with only bots left closes
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        room.add_bot(host_id, seat).unwrap();
+        assert!(room.remove_player(host_id).unwrap());
+    }
+
+    #[test]
+    fn test_stand_in_bot_keeps_seat_until_rejoin() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let guest = Uuid::new_v4();
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+
+        // Nobody stands in for a connected player or before the grace period
+        assert!(!room.substitute_bot(host_id, Duration::ZERO));
+        room.set_player_connected(host_id, false);
+        assert!(!room.substitute_bot(host_id, Duration::from_secs(60)));
+        assert!(room.substitute_bot(host_id, Duration::ZERO));
+        assert!(room.to_info().players[0].stand_in);
+
+        // The stand-in plays the host's setup, then waits on the guest
+        room.run_bots();
+        let game = room.game.as_ref().unwrap();
+        assert_eq!(game.waiting_on(), vec![1]);
+
+        // Only a disconnected seat can be taken back, and only with its token
+        let new_id = Uuid::new_v4();
+        let guest_token = room.rejoin_token(guest);
+        assert!(matches!(
+            room.rejoin(guest, Uuid::new_v4(), guest_token),
+            Err(RoomError::StillConnected)
+        ));
+        let token = room.rejoin_token(host_id);
+        assert!(matches!(
+            room.rejoin(host_id, new_id, None),
+            Err(RoomError::NotYourSeat)
+        ));
+        assert!(matches!(
+            room.rejoin(host_id, new_id, guest_token),
+            Err(RoomError::NotYourSeat)
+        ));
+        room.rejoin(host_id, new_id, token).unwrap();
+        assert_ne!(room.rejoin_token(new_id), token);
+        // A player proven to be who they were may come back over a stale
+        // connection
+        room.rejoin(guest, guest, None).unwrap();
+        assert_eq!(room.host_id, new_id);
+        assert_eq!(room.player_order[0], new_id);
+        let info = room.to_info();
+        assert!(info.players[0].connected && !info.players[0].stand_in);
+        assert!(room.bots.is_empty());
+    }
+
+    #[test]
+    fn test_two_people_and_two_bots_play_a_four_seat_game() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 4);
+        let guest = Uuid::new_v4();
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        let seat = BotSeat {
+            difficulty: catan_core::BotDifficulty::Easy,
+            personality: catan_core::BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        room.add_bot(host_id, seat).unwrap();
+        assert!(room.add_bot(host_id, seat).is_err());
+        room.start_game(host_id).unwrap();
+
+        // The people answer with the default move; the bots move on their own
+        let people = [host_id, guest];
+        for _ in 0..200 {
+            room.run_bots();
+            let game = room.game.as_ref().unwrap();
+            if game.turn_number >= 3 {
+                break;
+            }
+            let seat = game.waiting_on()[0];
+            assert!(seat < 2, "a bot seat was left waiting");
+            let action = game.auto_action(seat).unwrap();
+            room.apply_action(people[seat as usize], action).unwrap();
+        }
+
+        let game = room.game.as_ref().unwrap();
+        assert!(game.turn_number >= 3);
+        for bot in 2..4 {
+            assert!(game.total_victory_points(bot) >= 2);
+        }
+    }
+
+    #[test]
+    fn test_bot_turns_are_claimed_once_and_dropped_when_stale() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let guest = Uuid::new_v4();
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let first = room.game.as_ref().unwrap().waiting_on()[0];
+        let away = room.player_order[first as usize];
+        room.set_player_connected(away, false);
+        assert!(room.substitute_bot(away, Duration::ZERO));
+
+        assert!(room.start_bot_driver());
+        assert!(!room.start_bot_driver());
+
+        // The player comes back while their stand-in is thinking
+        let mut turn = room.next_bot_turn().unwrap();
+        assert_eq!(turn.seat, first);
+        let action = turn.think();
+        let token = room.rejoin_token(away);
+        room.rejoin(away, Uuid::new_v4(), token).unwrap();
+        assert_eq!(room.finish_bot_turn(turn, action), Some(Vec::new()));
+        assert!(room.bots.is_empty());
+        assert_eq!(room.game.as_ref().unwrap().waiting_on(), vec![first]);
+
+        // With no bot to move, the claim is free again
+        assert!(room.next_bot_turn().is_none());
+        assert!(room.start_bot_driver());
+    }
+
+    #[test]
+    fn test_catch_up_sends_what_was_missed() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let guest = Uuid::new_v4();
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        room.add_chat(host_id, "Host".to_string(), "gl hf".to_string(), None);
+
+        let people = [host_id, guest];
+        for _ in 0..4 {
+            let game = room.game.as_ref().unwrap();
+            let seat = game.waiting_on()[0];
+            let action = game.auto_action(seat).unwrap();
+            room.apply_action(people[seat as usize], action).unwrap();
+        }
+        room.add_chat(guest, "Guest".to_string(), "nice spot".to_string(), None);
+        assert_eq!(room.events.last_seq(), 4);
+
+        let ServerMessage::CatchUp {
+            state,
+            events,
+            chat,
+        } = room.catch_up(guest, 2, 1)
+        else {
+            panic!("expected a catch-up");
+        };
+        assert!(state.is_some());
+        assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(chat.len(), 1);
+        assert_eq!(chat[0].message, "nice spot");
+    }
+
+    #[test]
+    fn test_timers_end_turns_and_make_decisions() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        room.add_player(Uuid::new_v4(), "Guest".to_string()).unwrap();
+        room.settings.timers = TurnTimers {
+            turn_secs: Some(60),
+            decision_secs: Some(20),
+        };
+        room.start_game(host_id).unwrap();
+
+        let timer = room.timer().unwrap().clone();
+        assert_eq!(timer.kind, TimerKind::Turn);
+        let seat = timer.players[0];
+        assert_eq!(room.game.as_ref().unwrap().waiting_on(), vec![seat]);
+        assert!(room.expire_timer(Instant::now()).is_none());
+
+        // Running out of time in setup places the pieces and passes the turn
+        let (_, events) = room.expire_timer(timer.deadline).unwrap();
+        assert!(!events.is_empty());
+        assert!(!room.game.as_ref().unwrap().waiting_on().contains(&seat));
+        let turn = room.timer().unwrap().clone();
+        assert_ne!(turn.players, timer.players);
+
+        // A decision stops the turn's clock until it is made
+        room.game.as_mut().unwrap().phase = GamePhase::RobberMoveRequired;
+        room.update_timer();
+        let decision = room.timer().unwrap().clone();
+        assert_eq!(decision.kind, TimerKind::Decision);
+        assert!(decision.deadline < turn.deadline);
+
+        let (_, events) = room.expire_timer(decision.deadline).unwrap();
+        let Some(GameEvent::RobberMoved { to, .. }) = events.first() else {
+            panic!("expected the robber to move, got {:?}", events);
+        };
+        let board = &room.game.as_ref().unwrap().board;
+        assert!(board.players_adjacent_to_hex(to).is_empty());
+        let resumed = room.timer().unwrap();
+        assert_eq!(resumed.kind, TimerKind::Turn);
+        assert_eq!(resumed.players, turn.players);
+        assert!(resumed.deadline >= turn.deadline);
+    }
+
+    #[test]
+    fn test_host_settings_are_checked_and_used() {
+        let host_id = Uuid::new_v4();
+        let guest_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 3);
+        room.add_player(guest_id, "Guest".to_string()).unwrap();
+
+        let mut settings = RoomSettings::default();
+        settings.options.victory_points_to_win = Some(6);
+        settings.options.board_template = catan_core::BoardTemplate::Beginner;
+        settings.seed = Some(99);
+        assert!(matches!(
+            room.update_settings(guest_id, settings.clone()),
+            Err(RoomError::NotHost)
+        ));
+
+        let mut too_many_points = settings.clone();
+        too_many_points.options.victory_points_to_win = Some(100);
+        let mut too_quick = settings.clone();
+        too_quick.timers.turn_secs = Some(1);
+        let mut blank_die = settings.clone();
+        blank_die.options.event_die = Some(catan_core::EventDie {
+            faces: Vec::new(),
+            track_length: 7,
+        });
+        let mut rich_start = settings.clone();
+        rich_start.options.starting_hand =
+            catan_core::ResourceHand::single(catan_core::Resource::Ore, 11);
+        for bad in [too_many_points, too_quick, blank_die, rich_start] {
+            assert!(matches!(
+                room.update_settings(host_id, bad),
+                Err(RoomError::InvalidSettings(_))
+            ));
+        }
+
+        // Teams have to cover everyone who sat down
+        let mut teams = settings.clone();
+        teams.options.teams = vec![0, 1, 0];
+        room.update_settings(host_id, teams).unwrap();
+        assert!(matches!(room.start_game(host_id), Err(RoomError::InvalidSettings(_))));
+
+        room.update_settings(host_id, settings.clone()).unwrap();
+        assert_eq!(room.to_info().settings, settings);
+        room.start_game(host_id).unwrap();
+        let game = room.game.as_ref().unwrap();
+        assert_eq!(game.seed(), 99);
+        assert_eq!(game.options.victory_points_to_win, Some(6));
+        assert!(matches!(
+            room.update_settings(host_id, settings),
+            Err(RoomError::GameAlreadyStarted)
+        ));
+    }
+
+    #[test]
+    fn test_host_kicks_players_and_hands_over() {
+        let host_id = Uuid::new_v4();
+        let guest_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 4);
+        room.add_player(guest_id, "Guest".to_string()).unwrap();
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: catan_core::BotPersonality::Balanced,
+        };
+        let bot_id = room.add_bot(host_id, seat).unwrap();
+
+        assert!(matches!(room.kick_player(guest_id, bot_id), Err(RoomError::NotHost)));
+        assert!(matches!(room.kick_player(host_id, host_id), Err(RoomError::CannotKickHost)));
+        room.kick_player(host_id, bot_id).unwrap();
+        assert_eq!(room.player_count(), 2);
+
+        assert!(matches!(room.transfer_host(guest_id, guest_id), Err(RoomError::NotHost)));
+        let bot_id = room.add_bot(host_id, seat).unwrap();
+        assert!(matches!(room.transfer_host(host_id, bot_id), Err(RoomError::BotCannotHost)));
+        room.transfer_host(host_id, guest_id).unwrap();
+        assert_eq!(room.host_id, guest_id);
+
+        // The new host can remove the old one
+        room.kick_player(guest_id, host_id).unwrap();
+        assert!(!room.players.contains_key(&host_id));
+        room.start_game(guest_id).unwrap();
+        assert!(matches!(
+            room.kick_player(guest_id, bot_id),
+            Err(RoomError::GameAlreadyStarted)
+        ));
+    }
+
+    #[test]
+    fn test_pause_freezes_moves_bots_and_timers() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: catan_core::BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        room.settings.timers.turn_secs = Some(60);
+        assert!(matches!(room.pause(host_id), Err(RoomError::GameNotStarted)));
+        room.start_game(host_id).unwrap();
+        room.run_bots();
+
+        let deadline = room.timer().unwrap().deadline;
+        room.pause(host_id).unwrap();
+        assert!(matches!(room.pause(host_id), Err(RoomError::GamePaused)));
+        assert!(room.timer().is_none());
+        assert!(room.expire_timer(deadline).is_none());
+        assert!(room.next_bot_turn().is_none());
+        assert!(room.to_info().paused);
+
+        let action = room.game.as_ref().unwrap().auto_action(0).unwrap();
+
+        assert!(matches!(
+            room.apply_action(host_id, action.clone()),
+            Err(RoomError::GamePaused)
+        ));
+
+        // The pause survives a restart
+        let restored = GameRoom::from_snapshot(room.snapshot()).unwrap();
+        assert!(restored.is_paused());
+
+        room.resume(host_id).unwrap();
+        assert!(matches!(room.resume(host_id), Err(RoomError::NotPaused)));
+        assert!(room.timer().unwrap().deadline >= deadline);
+        room.apply_action(host_id, action).unwrap();
+    }
+
+    #[test]
+    fn test_players_only_see_their_own_hands() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let seat = BotSeat {
+            difficulty: BotDifficulty::Easy,
+            personality: catan_core::BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        let onlooker = Uuid::new_v4();
+        room.start_game(host_id).unwrap();
+        for _ in 0..50 {
+            room.run_bots();
+            let game = room.game.as_ref().unwrap();
+            if game.turn_number >= 1 {
+                break;
+            }
+            let action = game.auto_action(0).unwrap();
+            room.apply_action(host_id, action).unwrap();
+        }
+
+        let game = room.game.as_ref().unwrap();
+        let hands: Vec<u32> = game.players.iter().map(|p| p.resources.total()).collect();
+        let view = room.game_state_for(host_id).unwrap();
+        assert_eq!(view.players[0].resources, game.players[0].resources);
+        assert_eq!(view.players[1].resources.total(), 0);
+        assert_eq!(view.hand_sizes, hands);
+
+        let view = room.game_state_for(onlooker).unwrap();
+        assert!(view.players.iter().all(|p| p.resources.total() == 0));
+        assert_eq!(view.hand_sizes, hands);
+
+        // A steal shows the card only to the two players in it
+        let stolen = GameEvent::ResourceStolen {
+            thief: 1,
+            victim: 0,
+            resource: Some(catan_core::Resource::Brick),
+        };
+        let seen = |player_id| room.hide_events(player_id, std::slice::from_ref(&stolen));
+        assert_eq!(seen(host_id), vec![stolen.clone()]);
+        assert_ne!(seen(onlooker), vec![stolen.clone()]);
+
+        // Only the buyer learns which card they drew
+        let drawn = GameEvent::DevelopmentCardDrawn {
+            player: room.players[&host_id].game_index.unwrap(),
+            card: catan_core::DevelopmentCard::Monopoly,
+        };
+        let seen = |player_id| room.hide_events(player_id, std::slice::from_ref(&drawn));
+        assert_eq!(seen(host_id), vec![drawn.clone()]);
+        assert!(seen(onlooker).is_empty());
+    }
+
+    #[test]
+    fn test_each_player_gets_their_own_valid_actions() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        let guest = Uuid::new_v4();
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        let onlooker = Uuid::new_v4();
+        room.start_game(host_id).unwrap();
+
+        let seat = |id: Uuid| room.players[&id].game_index.unwrap();
+        let (host_seat, guest_seat) = (seat(host_id), seat(guest));
+        let game = room.game.as_mut().unwrap();
+        game.current_player = host_seat;
+        game.phase = GamePhase::MainPhase;
+        game.players[host_seat as usize].resources = ResourceHand::with_amounts(1, 0, 0, 0, 0);
+        game.players[guest_seat as usize].resources = ResourceHand::with_amounts(0, 1, 0, 0, 0);
+        assert_eq!(room.valid_actions_for(guest), Some(vec![]));
+
+        let offer = TradeOffer::new(
+            host_seat,
+            Some(guest_seat),
+            ResourceHand::with_amounts(1, 0, 0, 0, 0),
+            ResourceHand::with_amounts(0, 1, 0, 0, 0),
+        );
+        room.apply_action(host_id, GameAction::ProposeTrade(offer)).unwrap();
+
+        // The guest may answer the offer though it isn't their turn
+        let answers = room.valid_actions_for(guest).unwrap();
+        assert!(answers.contains(&GameAction::AcceptTrade));
+        assert!(answers.contains(&GameAction::RejectTrade));
+        let host_actions = room.valid_actions_for(host_id).unwrap();
+        assert!(!host_actions.contains(&GameAction::AcceptTrade));
+        assert_eq!(room.valid_actions_for(onlooker), Some(vec![]));
+
+        room.apply_action(guest, GameAction::AcceptTrade).unwrap();
+        let game = room.game.as_ref().unwrap();
+        assert_eq!(game.players[guest_seat as usize].resources.total(), 1);
+        assert!(room.valid_actions_for(guest).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retried_actions_apply_once() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        room.add_player(Uuid::new_v4(), "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let game = room.game.as_mut().unwrap();
+        game.current_player = room.players[&host_id].game_index.unwrap();
+        game.phase = GamePhase::MainPhase;
+        let turn = game.turn_number;
+
+        let id = Some("end-1".to_string());
+        let (first, applied) = room.apply_request(host_id, GameAction::EndTurn, id.clone());
+        assert!(applied);
+        let (again, applied) = room.apply_request(host_id, GameAction::EndTurn, id);
+        assert!(!applied);
+        assert_eq!(
+            serde_json::to_value(&again).unwrap(),
+            serde_json::to_value(&first).unwrap()
+        );
+        assert_eq!(room.game.as_ref().unwrap().turn_number, turn + 1);
+
+        // A new ID is a new action, turned away now it's not our turn
+        let next = Some("end-2".to_string());
+        let (answer, applied) = room.apply_request(host_id, GameAction::EndTurn, next);
+        assert!(!applied);
+        assert!(matches!(
+            answer,
+            ServerMessage::ActionResult { success: false, request_id: Some(id), .. }
+                if id == "end-2"
+        ));
+    }
+
+    #[test]
+    fn test_refused_actions_carry_the_game_error() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+        room.add_player(Uuid::new_v4(), "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let game = room.game.as_mut().unwrap();
+        game.current_player = room.players[&host_id].game_index.unwrap();
+        game.phase = GamePhase::MainPhase;
+
+        let (answer, _) = room.apply_request(host_id, GameAction::RollDice, None);
+        let ServerMessage::ActionResult {
+            error_code,
+            game_error,
+            ..
+        } = answer
+        else {
+            panic!("expected an ActionResult, got {:?}", answer);
+        };
+        assert_eq!(error_code, Some(3));
+        assert!(matches!(
+            game_error,
+            Some(GameError::InvalidPhase { ref expected, .. }) if expected == &[PhaseKind::PreRoll]
+        ));
+    }
 }
@@ -1,70 +1,346 @@
 //! WebSocket server and connection handling.
 
-use crate::protocol::{ClientMessage, RoomStatus, ServerMessage};
-use crate::room::GameRoom;
+use crate::actor::{spawn_room, RoomCommand, RoomHandle};
+use crate::auth::TicketSigner;
+use crate::chat::{self, ChatState};
+use crate::limits::{RateLimit, TokenBucket, MAX_MESSAGE_SIZE};
+use crate::protocol::{
+    BotSeat, ClientMessage, Encoding, ErrorCode, Health, PlayerStats, RoomInfo, RoomQuery,
+    RoomStatus, ServerMessage, Session, SUPPORTED_VERSIONS,
+};
+use crate::replay;
+use crate::rest::{self, RestSession};
+use crate::room::{GameRoom, DEFAULT_DISCONNECT_GRACE};
+use crate::stats::{self, GameResult};
+use crate::storage::{RoomStore, StoreError};
+use crate::tournament::{self, Tournament};
+use crate::webhook;
+use catan_core::BotDifficulty;
 use dashmap::DashMap;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
+use std::future::Future;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_tungstenite::accept_hdr_async_with_config;
+use tokio_tungstenite::tungstenite::error::{CapacityError, Error as WsError};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+/// Default pause before each bot move
+pub const BOT_MOVE_DELAY: Duration = Duration::from_millis(600);
+
+/// Players on the leaderboard unless the client asks for some other number
+const LEADERBOARD_SIZE: usize = 20;
+
+/// How long a new connection has to say hello
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time between the pings we send each client
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Default silence after which a client counts as gone
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Default time a room with nobody connected is kept
+pub const ROOM_IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How often rooms are checked for being idle
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long shutting down waits for rooms to save and sockets to close
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// How long a closing connection has to send what is left for it
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A client's connection, plain or TLS
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+type WsSender = SplitSink<WebSocketStream<Box<dyn Socket>>, Message>;
+type WsReceiver = SplitStream<WebSocketStream<Box<dyn Socket>>>;
+
+/// Counts of idle rooms put away since the server started, by what state
+/// they were left in.
+#[derive(Debug, Default)]
+pub struct Evictions {
+    /// Waiting rooms, dropped
+    pub waiting: AtomicU64,
+    /// Finished games, archived
+    pub finished: AtomicU64,
+    /// Games everyone walked away from, archived
+    pub abandoned: AtomicU64,
+}
+
+impl Evictions {
+    /// Count one eviction of a room left in `status`, returning the
+    /// running total of that kind
+    pub fn count(&self, status: RoomStatus) -> u64 {
+        let counter = match status {
+            RoomStatus::Waiting => &self.waiting,
+            RoomStatus::Finished => &self.finished,
+            RoomStatus::InGame => &self.abandoned,
+        };
+        counter.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
 /// Server state shared across all connections.
 pub struct ServerState {
-    /// All active rooms
-    pub rooms: DashMap<Uuid, GameRoom>,
+    /// All active rooms, each run by its own task
+    pub rooms: DashMap<Uuid, RoomHandle>,
     /// Mapping from player ID to their room ID
     pub player_rooms: DashMap<Uuid, Uuid>,
     /// Mapping from player ID to their message sender
     pub player_senders: DashMap<Uuid, mpsc::UnboundedSender<ServerMessage>>,
+    /// What each connected player agreed to in their hello
+    pub sessions: DashMap<Uuid, Session>,
+    /// How long a disconnected player's seat waits before a bot takes over
+    pub disconnect_grace: Duration,
+    /// Pause before each bot move, so people can follow the game
+    pub bot_move_delay: Duration,
+    /// Time between the pings we send each client
+    pub heartbeat_interval: Duration,
+    /// How long a client may stay silent, pongs included, before we treat
+    /// the connection as dropped
+    pub heartbeat_timeout: Duration,
+    /// How fast each connection may send messages
+    pub rate_limit: RateLimit,
+    /// Largest message a client may send, in bytes
+    pub max_message_size: usize,
+    /// How long a room with nobody connected is kept before it is put away
+    pub room_idle_ttl: Duration,
+    /// Idle rooms put away so far
+    pub evictions: Evictions,
+    /// Set as soon as the server starts going down
+    pub shutting_down: AtomicBool,
+    /// Set once the server is going down, to close every connection
+    pub closing: watch::Sender<bool>,
+    /// Most rooms open at once, if there is a limit
+    pub max_rooms: Option<usize>,
+    /// How well the bot giving hints plays, and the best a player may
+    /// ask for
+    pub hint_difficulty: BotDifficulty,
+    /// Where each room's audit trail is also written, if anywhere
+    pub audit_dir: Option<PathBuf>,
+    /// Posts rooms' webhooks; None when webhooks are turned off
+    pub webhook_client: Option<reqwest::Client>,
+    /// Lets operators at the admin routes. Without it they are off.
+    pub admin_token: Option<String>,
+    /// Players on the REST API, by session token
+    pub rest_sessions: DashMap<String, Arc<RestSession>>,
+    /// Tournaments open, running or recently finished
+    pub tournaments: DashMap<Uuid, Tournament>,
+    /// The lobby channel, mute lists and chat rate limits
+    pub chat: ChatState,
+    /// Where rooms are saved to survive a restart, if anywhere
+    pub store: Option<Box<dyn RoomStore>>,
+    /// Signs the tickets that keep a player's identity across connections.
+    /// Without it everyone is a guest.
+    pub tickets: Option<TicketSigner>,
+    /// Accepts TLS connections, when the server serves them
+    #[cfg(feature = "tls")]
+    pub tls: Option<tokio_rustls::TlsAcceptor>,
 }
 
 impl ServerState {
     pub fn new() -> Self {
+        Self::with_disconnect_grace(DEFAULT_DISCONNECT_GRACE)
+    }
+
+    pub fn with_disconnect_grace(disconnect_grace: Duration) -> Self {
         Self {
             rooms: DashMap::new(),
             player_rooms: DashMap::new(),
             player_senders: DashMap::new(),
+            sessions: DashMap::new(),
+            disconnect_grace,
+            bot_move_delay: BOT_MOVE_DELAY,
+            heartbeat_interval: HEARTBEAT_INTERVAL,
+            heartbeat_timeout: HEARTBEAT_TIMEOUT,
+            rate_limit: RateLimit::default(),
+            max_message_size: MAX_MESSAGE_SIZE,
+            room_idle_ttl: ROOM_IDLE_TTL,
+            evictions: Evictions::default(),
+            shutting_down: AtomicBool::new(false),
+            closing: watch::channel(false).0,
+            max_rooms: None,
+            hint_difficulty: BotDifficulty::Hard,
+            audit_dir: None,
+            webhook_client: Some(webhook::client()),
+            admin_token: None,
+            rest_sessions: DashMap::new(),
+            tournaments: DashMap::new(),
+            chat: ChatState::default(),
+            store: None,
+            tickets: None,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 
-    /// Send a message to a specific player.
-    pub fn send_to_player(&self, player_id: Uuid, msg: ServerMessage) {
-        if let Some(sender) = self.player_senders.get(&player_id) {
-            let _ = sender.send(msg);
+    /// Save rooms to `store` as they change
+    pub fn with_store(mut self, store: Box<dyn RoomStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Hand out tickets so players keep their identity between connections
+    pub fn with_tickets(mut self, tickets: TicketSigner) -> Self {
+        self.tickets = Some(tickets);
+        self
+    }
+
+    /// Who a new connection is: the player its ticket names, or a new guest
+    pub fn identify(&self, ticket: Option<&str>) -> Uuid {
+        match (&self.tickets, ticket) {
+            (Some(tickets), Some(ticket)) => tickets.verify(ticket).unwrap_or_else(|e| {
+                warn!("Rejected ticket, connecting as a guest: {}", e);
+                Uuid::new_v4()
+            }),
+            _ => Uuid::new_v4(),
         }
     }
 
-    /// Broadcast a message to all players in a room.
-    pub fn broadcast_to_room(&self, room_id: Uuid, msg: ServerMessage) {
-        if let Some(room) = self.rooms.get(&room_id) {
-            for player_id in room.players.keys() {
-                self.send_to_player(*player_id, msg.clone());
+    /// Save a room's current state
+    pub fn save_room(&self, room: &GameRoom) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&room.snapshot()) {
+                error!("Could not save room {}: {}", room.id, e);
             }
         }
     }
 
-    /// Broadcast a message to all players in a room except one.
-    pub fn broadcast_to_room_except(&self, room_id: Uuid, except: Uuid, msg: ServerMessage) {
-        if let Some(room) = self.rooms.get(&room_id) {
-            for player_id in room.players.keys() {
-                if *player_id != except {
-                    self.send_to_player(*player_id, msg.clone());
-                }
+    /// Forget a room once it has closed
+    pub fn forget_room(&self, room_id: Uuid) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.remove(room_id) {
+                error!("Could not remove room {}: {}", room_id, e);
+            }
+        }
+    }
+
+    /// Put away a room that is over or abandoned, keeping its last state
+    pub fn archive_room(&self, room: &GameRoom) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.archive(&room.snapshot()) {
+                error!("Could not archive room {}: {}", room.id, e);
+            }
+        }
+    }
+
+    /// Keep a finished game's result for the leaderboard
+    pub fn record_result(&self, result: &GameResult) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.record_result(result) {
+                error!("Could not record result of room {}: {}", result.room_id, e);
+            }
+        }
+    }
+
+    /// Standings of everyone who has finished a game, best rated first.
+    /// Empty without a store to keep results in.
+    pub fn player_stats(&self) -> Vec<PlayerStats> {
+        let Some(store) = &self.store else {
+            return Vec::new();
+        };
+        match store.load_results() {
+            Ok(results) => stats::player_stats(&results),
+            Err(e) => {
+                error!("Could not load game results: {}", e);
+                Vec::new()
             }
         }
     }
 
-    /// Get list of waiting rooms.
-    pub fn get_waiting_rooms(&self) -> Vec<crate::protocol::RoomInfo> {
-        self.rooms
+    /// How the server is doing: whether the store answers, and whether we
+    /// are shutting down
+    pub fn health(&self) -> Health {
+        let storage_error = self
+            .store
+            .as_ref()
+            .and_then(|store| store.ping().err())
+            .map(|e| e.to_string());
+        let shutting_down = self.shutting_down.load(Ordering::Relaxed);
+        Health {
+            ready: storage_error.is_none() && !shutting_down,
+            shutting_down,
+            storage_error,
+            rooms: self.rooms.len(),
+            connections: self.player_senders.len(),
+        }
+    }
+
+    /// Send a message to a specific player.
+    pub fn send_to_player(&self, player_id: Uuid, msg: ServerMessage) {
+        if let Some(sender) = self.player_senders.get(&player_id) {
+            let _ = sender.send(msg);
+        }
+    }
+
+    /// Whether a player's connection has an optional feature on
+    pub fn wants(&self, player_id: Uuid, feature: &str) -> bool {
+        self.sessions
+            .get(&player_id)
+            .is_some_and(|s| s.features.iter().any(|f| f == feature))
+    }
+
+    /// The room a player is in, if any
+    pub fn player_room(&self, player_id: Uuid) -> Option<RoomHandle> {
+        let room_id = *self.player_rooms.get(&player_id)?;
+        self.rooms.get(&room_id).map(|room| room.clone())
+    }
+
+    /// Get list of waiting rooms, including those other instances saved
+    /// to a shared store.
+    pub fn get_waiting_rooms(&self) -> Vec<RoomInfo> {
+        let mut rooms: Vec<_> = self
+            .rooms
             .iter()
+            .map(|r| r.info())
             .filter(|r| r.status == RoomStatus::Waiting)
-            .map(|r| r.to_info())
-            .collect()
+            .collect();
+
+        let saved = self.store.as_ref().map(|s| s.load_all()).transpose();
+        match saved {
+            Ok(saved) => rooms.extend(
+                saved
+                    .into_iter()
+                    .flatten()
+                    .filter(|r| r.status == RoomStatus::Waiting && !self.rooms.contains_key(&r.id))
+                    .filter_map(|r| GameRoom::from_snapshot(r).ok())
+                    .map(|room| room.to_info()),
+            ),
+            Err(e) => error!("Could not list saved rooms: {}", e),
+        }
+        rooms
+    }
+
+    /// A page of the waiting rooms that match `query`, and the cursor for
+    /// the page after if there is one
+    pub fn list_rooms(&self, query: &RoomQuery) -> (Vec<RoomInfo>, Option<Uuid>) {
+        let mut rooms: Vec<_> = self
+            .get_waiting_rooms()
+            .into_iter()
+            .filter(|r| query.cursor.is_none_or(|after| r.id > after) && query.matches(r))
+            .collect();
+        rooms.sort_by_key(|r| r.id);
+        let size = query.page_size();
+        let next_cursor = (rooms.len() > size).then(|| rooms[size - 1].id);
+        rooms.truncate(size);
+        (rooms, next_cursor)
     }
 }
 
@@ -74,11 +350,85 @@ impl Default for ServerState {
     }
 }
 
-/// Run the WebSocket server.
-pub async fn run_server(addr: SocketAddr, state: Arc<ServerState>) -> anyhow::Result<()> {
+/// Run the WebSocket server until `shutdown` completes, then shut down
+/// gracefully.
+pub async fn run_server(
+    addr: SocketAddr,
+    state: Arc<ServerState>,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
     let listener = TcpListener::bind(addr).await?;
     info!("Kopiatan server listening on {}", addr);
+    tokio::spawn(clean_up_rooms(Arc::clone(&state)));
+    tokio::select! {
+        result = serve(listener, Arc::clone(&state)) => result,
+        _ = shutdown => {
+            shut_down(&state).await;
+            Ok(())
+        }
+    }
+}
 
+/// Let everyone know the server is going down, save every room and close
+/// every connection. New connections should already have stopped.
+pub async fn shut_down(state: &Arc<ServerState>) {
+    info!("Shutting down, saving {} rooms", state.rooms.len());
+    state.shutting_down.store(true, Ordering::Relaxed);
+    let rooms: Vec<RoomHandle> = state.rooms.iter().map(|r| r.value().clone()).collect();
+    let mut saved = Vec::new();
+    for room in rooms {
+        let (done, finished) = oneshot::channel();
+        if room.send(RoomCommand::Shutdown { done }) {
+            saved.push(finished);
+        }
+    }
+    let all_saved = async {
+        for finished in saved {
+            let _ = finished.await;
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_GRACE, all_saved).await.is_err() {
+        warn!("Some rooms did not save in time");
+    }
+
+    // Players in the lobby hear it too; those in rooms already have
+    for sender in state.player_senders.iter() {
+        if !state.player_rooms.contains_key(sender.key()) {
+            let _ = sender.send(ServerMessage::ServerShuttingDown);
+        }
+    }
+    // Each open connection watches for this, and stops watching once its
+    // socket is closed
+    state.closing.send_replace(true);
+    let all_closed = async {
+        while state.closing.receiver_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_GRACE, all_closed).await.is_err() {
+        warn!("Some connections did not close in time");
+    }
+}
+
+/// Ask every room now and then whether it has sat empty too long. Each
+/// room decides for itself and puts itself away. REST sessions nobody is
+/// using are closed, and old finished tournaments forgotten, at the same
+/// time.
+pub async fn clean_up_rooms(state: Arc<ServerState>) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let rooms: Vec<RoomHandle> = state.rooms.iter().map(|r| r.value().clone()).collect();
+        for room in rooms {
+            room.send(RoomCommand::CheckIdle);
+        }
+        rest::expire_sessions(&state);
+        tournament::forget_finished(&state);
+    }
+}
+
+/// Accept connections on a bound listener until it fails.
+pub async fn serve(listener: TcpListener, state: Arc<ServerState>) -> anyhow::Result<()> {
     while let Ok((stream, peer_addr)) = listener.accept().await {
         let state = Arc::clone(&state);
         tokio::spawn(async move {
@@ -91,49 +441,151 @@ pub async fn run_server(addr: SocketAddr, state: Arc<ServerState>) -> anyhow::Re
     Ok(())
 }
 
+/// Finish a TLS handshake, when the server serves TLS
+async fn secure(stream: TcpStream, state: &ServerState) -> std::io::Result<Box<dyn Socket>> {
+    #[cfg(feature = "tls")]
+    if let Some(tls) = &state.tls {
+        return Ok(Box::new(tls.accept(stream).await?));
+    }
+    #[cfg(not(feature = "tls"))]
+    let _ = state;
+    Ok(Box::new(stream))
+}
+
 /// Handle a single WebSocket connection.
 async fn handle_connection(
     stream: TcpStream,
     addr: SocketAddr,
     state: Arc<ServerState>,
 ) -> anyhow::Result<()> {
-    let ws_stream = accept_async(stream).await?;
+    let stream = secure(stream, &state).await?;
+    // A returning player shows their ticket as `?ticket=...`
+    let mut ticket = None;
+    // The callback's signature is tungstenite's to choose
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| {
+        ticket = request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("ticket="))
+                .map(str::to_string)
+        });
+        Ok(response)
+    };
+    // Oversized messages are refused as they come in, before they are
+    // buffered
+    let config = WebSocketConfig {
+        max_message_size: Some(state.max_message_size),
+        max_frame_size: Some(state.max_message_size),
+        ..WebSocketConfig::default()
+    };
+    let ws_stream = accept_hdr_async_with_config(stream, callback, Some(config)).await?;
     info!("New WebSocket connection from {}", addr);
 
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
-    // Assign a player ID
-    let player_id = Uuid::new_v4();
+    let session = match tokio::time::timeout(HELLO_TIMEOUT, hello(&mut ws_receiver)).await {
+        Ok(Some(first)) => match Session::negotiate(first) {
+            Ok(session) => session,
+            Err(message) => return refuse(ws_sender, message).await,
+        },
+        Ok(None) => return Ok(()),
+        Err(_) => return refuse(ws_sender, "No hello received".to_string()).await,
+    };
+
+    let player_id = state.identify(ticket.as_deref());
+    info!(
+        "{} is {} speaking protocol {}",
+        addr, session.client_name, session.protocol_version
+    );
 
-    // Create channel for outgoing messages
+    // Create channel for outgoing messages. One connection per identity:
+    // only a verified ticket names a player who is connected already, so
+    // they are back before their old connection was noticed as gone, and
+    // that one is dropped.
     let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
-    state.player_senders.insert(player_id, tx);
+    let ours = tx.downgrade();
+    if state.player_senders.insert(player_id, tx).is_some() {
+        info!("{} reconnected, dropping their old connection", player_id);
+    }
+    state.sessions.insert(player_id, session.clone());
 
-    // Send welcome message
-    let welcome = ServerMessage::Welcome { player_id };
+    // Send welcome message, with a fresh ticket to come back with
+    let welcome = ServerMessage::Welcome {
+        player_id,
+        ticket: state.tickets.as_ref().map(|t| t.issue(player_id)),
+        supported_versions: SUPPORTED_VERSIONS.to_vec(),
+        features: session.features.clone(),
+    };
     let msg_text = serde_json::to_string(&welcome)?;
-    ws_sender.send(Message::Text(msg_text.into())).await?;
+    ws_sender.send(Message::Text(msg_text)).await?;
 
-    // Spawn task to forward messages from channel to WebSocket
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if let Ok(text) = serde_json::to_string(&msg) {
-                if ws_sender.send(Message::Text(text.into())).await.is_err() {
+    // Spawn task to forward messages from channel to WebSocket, pinging
+    // the client now and then so a dropped connection shows up
+    let encoding = session.encoding();
+    let mut heartbeat = tokio::time::interval(state.heartbeat_interval);
+    let mut send_task = tokio::spawn(async move {
+        loop {
+            let frame = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => encode(&msg, encoding),
+                    None => break,
+                },
+                _ = heartbeat.tick() => Some(Message::Ping(Vec::new())),
+            };
+            if let Some(frame) = frame {
+                if ws_sender.send(frame).await.is_err() {
                     break;
                 }
             }
         }
+        let _ = ws_sender.close().await;
     });
 
-    // Handle incoming messages
-    while let Some(msg) = ws_receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
-                    handle_message(player_id, client_msg, &state);
-                } else {
-                    warn!("Invalid message from {}: {}", player_id, text);
+    // Handle incoming messages. A client that sends nothing, not even a
+    // pong, for too long has gone without saying so.
+    let mut bucket = TokenBucket::new(state.rate_limit, Instant::now());
+    // Whether the client has been told it is sending too fast
+    let mut limited = false;
+    let mut closing = state.closing.subscribe();
+    loop {
+        let next = tokio::select! {
+            next = tokio::time::timeout(state.heartbeat_timeout, ws_receiver.next()) => next,
+            _ = closing.wait_for(|&closing| closing) => break,
+        };
+        let msg = match next {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => {
+                warn!("No heartbeat from {}, dropping the connection", player_id);
+                break;
+            }
+        };
+        if let Ok(Message::Text(_) | Message::Binary(_) | Message::Ping(_)) = &msg {
+            // Say so once when a client starts sending too fast, then drop
+            // its messages quietly until it slows down
+            if !bucket.take(Instant::now()) {
+                if !limited {
+                    warn!("Rate limiting {}", player_id);
+                    state.send_to_player(
+                        player_id,
+                        ServerMessage::Error {
+                            message: "Sending too fast, slow down".to_string(),
+                            code: Some(ErrorCode::RateLimited),
+                        },
+                    );
                 }
+                limited = true;
+                continue;
+            }
+            limited = false;
+        }
+        let parsed = match msg {
+            Ok(Message::Text(text)) => {
+                serde_json::from_str::<ClientMessage>(&text).map_err(|e| e.to_string())
+            }
+            Ok(Message::Binary(data)) => {
+                ClientMessage::from_msgpack(&data).map_err(|e| e.to_string())
             }
             Ok(Message::Close(_)) => {
                 info!("Client {} closing connection", player_id);
@@ -142,239 +594,460 @@ async fn handle_connection(
             Ok(Message::Ping(data)) => {
                 state.send_to_player(player_id, ServerMessage::Pong);
                 let _ = data; // Just consume it
+                continue;
+            }
+            // The connection can't be read past an oversized message
+            Err(WsError::Capacity(CapacityError::MessageTooLong { .. })) => {
+                warn!("Oversized message from {}", player_id);
+                let limit = state.max_message_size;
+                state.send_to_player(
+                    player_id,
+                    ServerMessage::Error {
+                        message: format!("Messages are limited to {} bytes", limit),
+                        code: Some(ErrorCode::MessageTooLarge),
+                    },
+                );
+                break;
             }
             Err(e) => {
                 error!("WebSocket error from {}: {}", player_id, e);
                 break;
             }
-            _ => {}
+            _ => continue,
+        };
+        match parsed {
+            Ok(client_msg) => handle_message(player_id, client_msg, &state),
+            // Actions are checked here too, so say what was wrong
+            Err(e) => {
+                warn!("Invalid message from {}: {}", player_id, e);
+                state.send_to_player(
+                    player_id,
+                    ServerMessage::Error {
+                        message: format!("Invalid message: {}", e),
+                        code: None,
+                    },
+                );
+            }
         }
     }
 
-    // Clean up on disconnect
-    handle_disconnect(player_id, &state);
-    state.player_senders.remove(&player_id);
-    send_task.abort();
+    // Clean up on disconnect, unless a newer connection has taken over.
+    // The sender goes first, so a room that seats this player from here on
+    // can tell they have gone.
+    let current = state
+        .player_senders
+        .remove_if(&player_id, |_, tx| {
+            ours.upgrade().is_some_and(|ours| ours.same_channel(tx))
+        })
+        .is_some();
+    if current {
+        state.sessions.remove(&player_id);
+        handle_disconnect(player_id, &state);
+    }
+    // Let anything still queued go out and close the socket properly
+    if tokio::time::timeout(FLUSH_TIMEOUT, &mut send_task).await.is_err() {
+        send_task.abort();
+    }
 
     info!("Connection closed for {}", player_id);
     Ok(())
 }
 
-/// Handle a client message.
-fn handle_message(player_id: Uuid, msg: ClientMessage, state: &Arc<ServerState>) {
+/// A new connection's first message, or None if it closed first
+async fn hello(ws_receiver: &mut WsReceiver) -> Option<ClientMessage> {
+    while let Some(msg) = ws_receiver.next().await {
+        match msg {
+            // Anything unreadable is an old or foreign client
+            Ok(Message::Text(text)) => {
+                return Some(serde_json::from_str(&text).unwrap_or(ClientMessage::Ping));
+            }
+            Ok(Message::Binary(data)) => {
+                return Some(ClientMessage::from_msgpack(&data).unwrap_or(ClientMessage::Ping));
+            }
+            Ok(Message::Close(_)) | Err(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Write a message the way this connection agreed
+fn encode(msg: &ServerMessage, encoding: Encoding) -> Option<Message> {
+    match encoding {
+        Encoding::Json => serde_json::to_string(msg).ok().map(Message::Text),
+        Encoding::MessagePack => msg.to_msgpack().ok().map(Message::Binary),
+    }
+}
+
+/// Turn a connection away, telling the client why
+async fn refuse(mut ws_sender: WsSender, message: String) -> anyhow::Result<()> {
+    warn!("Refusing connection: {}", message);
+    let error = ServerMessage::Error {
+        message,
+        code: None,
+    };
+    ws_sender.send(Message::Text(serde_json::to_string(&error)?)).await?;
+    ws_sender.close().await?;
+    Ok(())
+}
+
+/// Handle a client message. Anything for a room goes to that room's task.
+pub(crate) fn handle_message(player_id: Uuid, msg: ClientMessage, state: &Arc<ServerState>) {
     match msg {
+        ClientMessage::Hello { .. } => {
+            state.send_to_player(
+                player_id,
+                ServerMessage::Error {
+                    message: "Already said hello".to_string(),
+                    code: None,
+                },
+            );
+        }
+
         ClientMessage::CreateRoom {
             player_name,
             max_players,
+            settings,
         } => {
+            if state.max_rooms.is_some_and(|max| state.rooms.len() >= max) {
+                state.send_to_player(
+                    player_id,
+                    ServerMessage::Error {
+                        message: "The server has no room for more games".to_string(),
+                        code: None,
+                    },
+                );
+                return;
+            }
             let room_id = Uuid::new_v4();
-            let room = GameRoom::new(room_id, player_id, player_name, max_players);
+            let mut room = GameRoom::new(room_id, player_id, player_name, max_players);
+            if let Err(e) = room.update_settings(player_id, settings) {
+                state.send_to_player(
+                    player_id,
+                    ServerMessage::Error {
+                        message: e.to_string(),
+                        code: None,
+                    },
+                );
+                return;
+            }
             let room_info = room.to_info();
+            let rejoin_token = room.rejoin_token(player_id);
 
-            state.rooms.insert(room_id, room);
+            state.rooms.insert(room_id, spawn_room(room, state));
             state.player_rooms.insert(player_id, room_id);
 
             state.send_to_player(player_id, ServerMessage::RoomCreated { room_id });
-            state.send_to_player(player_id, ServerMessage::JoinedRoom { room: room_info });
+            state.send_to_player(
+                player_id,
+                ServerMessage::JoinedRoom {
+                    room: room_info,
+                    rejoin_token,
+                },
+            );
         }
 
         ClientMessage::JoinRoom {
             room_id,
             player_name,
+        } => send_to_room(
+            room_id,
+            player_id,
+            RoomCommand::Join {
+                player_id,
+                player_name,
+            },
+            state,
+        ),
+
+        ClientMessage::Rejoin {
+            room_id,
+            player_id: old_id,
+            token,
+            last_seq,
+            last_chat_seq,
+        } => send_to_room(
+            room_id,
+            player_id,
+            RoomCommand::Rejoin {
+                player_id,
+                old_id,
+                // With tickets a seat only goes back to the identity the
+                // ticket proves
+                token: token.filter(|_| state.tickets.is_none()),
+                last_seq,
+                last_chat_seq,
+            },
+            state,
+        ),
+
+        ClientMessage::ClaimSeat {
+            room_id,
+            seat,
+            player_name,
         } => {
-            if let Some(mut room) = state.rooms.get_mut(&room_id) {
-                match room.add_player(player_id, player_name) {
-                    Ok(()) => {
-                        let room_info = room.to_info();
-                        state.player_rooms.insert(player_id, room_id);
-
-                        state
-                            .send_to_player(player_id, ServerMessage::JoinedRoom { room: room_info.clone() });
-
-                        // Notify other players
-                        drop(room); // Release lock before broadcasting
-                        state.broadcast_to_room_except(
-                            room_id,
-                            player_id,
-                            ServerMessage::RoomUpdated { room: room_info },
-                        );
-                    }
-                    Err(e) => {
-                        state.send_to_player(
-                            player_id,
-                            ServerMessage::Error {
-                                message: e.to_string(),
-                            },
-                        );
-                    }
-                }
-            } else {
+            if state.player_rooms.contains_key(&player_id) {
                 state.send_to_player(
                     player_id,
                     ServerMessage::Error {
-                        message: "Room not found".to_string(),
+                        message: "Leave your room first".to_string(),
+                        code: None,
                     },
                 );
+                return;
+            }
+            send_to_room(
+                room_id,
+                player_id,
+                RoomCommand::ClaimSeat {
+                    player_id,
+                    seat,
+                    player_name,
+                },
+                state,
+            )
+        }
+
+        ClientMessage::AnswerSeatClaim {
+            player_id: claimant,
+            approve,
+        } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::AnswerSeatClaim {
+                    player_id,
+                    claimant,
+                    approve,
+                });
             }
         }
 
         ClientMessage::LeaveRoom => {
             if let Some((_, room_id)) = state.player_rooms.remove(&player_id) {
-                let should_remove = {
-                    if let Some(mut room) = state.rooms.get_mut(&room_id) {
-                        let is_empty = room.remove_player(player_id).unwrap_or(false);
-
-                        if !is_empty {
-                            let room_info = room.to_info();
-                            drop(room);
-                            state.broadcast_to_room(room_id, ServerMessage::RoomUpdated { room: room_info });
-                        }
-
-                        is_empty
-                    } else {
-                        false
-                    }
+                if let Some(room) = state.rooms.get(&room_id).map(|r| r.clone()) {
+                    room.send(RoomCommand::Leave { player_id });
+                }
+                state.send_to_player(player_id, ServerMessage::LeftRoom);
+            }
+        }
+
+        ClientMessage::AddBot {
+            difficulty,
+            personality,
+        } => {
+            if let Some(room) = state.player_room(player_id) {
+                let seat = BotSeat {
+                    difficulty,
+                    personality,
                 };
+                room.send(RoomCommand::AddBot { player_id, seat });
+            }
+        }
 
-                if should_remove {
-                    state.rooms.remove(&room_id);
-                }
+        ClientMessage::KickPlayer { player_id: target } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Kick { player_id, target });
+            }
+        }
 
-                state.send_to_player(player_id, ServerMessage::LeftRoom);
+        ClientMessage::TransferHost { player_id: target } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::TransferHost { player_id, target });
+            }
+        }
+
+        ClientMessage::PauseGame => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Pause { player_id });
+            }
+        }
+
+        ClientMessage::ResumeGame => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Resume { player_id });
+            }
+        }
+
+        ClientMessage::UpdateRoomSettings { settings } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::UpdateSettings {
+                    player_id,
+                    settings,
+                });
+            }
+        }
+
+        ClientMessage::SetWebhook { url } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::SetWebhook { player_id, url });
             }
         }
 
         ClientMessage::StartGame => {
-            if let Some(&room_id) = state.player_rooms.get(&player_id).as_deref() {
-                if let Some(mut room) = state.rooms.get_mut(&room_id) {
-                    match room.start_game(player_id) {
-                        Ok(()) => {
-                            let game_state = room.get_game_state().unwrap();
-                            let valid_actions = room.get_valid_actions().unwrap();
-                            let current_player = room.get_current_player().unwrap();
-
-                            drop(room);
-
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::GameStarted { state: game_state },
-                            );
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::ValidActions {
-                                    actions: valid_actions,
-                                },
-                            );
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::TurnChanged {
-                                    player_id: current_player,
-                                },
-                            );
-                        }
-                        Err(e) => {
-                            state.send_to_player(
-                                player_id,
-                                ServerMessage::Error {
-                                    message: e.to_string(),
-                                },
-                            );
-                        }
-                    }
-                }
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::StartGame { player_id });
             }
         }
 
-        ClientMessage::GameAction { action } => {
-            if let Some(&room_id) = state.player_rooms.get(&player_id).as_deref() {
-                if let Some(mut room) = state.rooms.get_mut(&room_id) {
-                    match room.apply_action(player_id, action) {
-                        Ok(events) => {
-                            let game_state = room.get_game_state().unwrap();
-                            let valid_actions = room.get_valid_actions().unwrap();
-                            let current_player = room.get_current_player().unwrap();
-                            let winner = room.get_winner();
-
-                            drop(room);
-
-                            // Send action result to the acting player
-                            state.send_to_player(
-                                player_id,
-                                ServerMessage::ActionResult {
-                                    success: true,
-                                    events: events
-                                        .iter()
-                                        .map(|e| serde_json::to_value(e).unwrap())
-                                        .collect(),
-                                    error: None,
-                                },
-                            );
-
-                            // Broadcast updated game state
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::GameState { state: game_state },
-                            );
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::ValidActions {
-                                    actions: valid_actions,
-                                },
-                            );
-                            state.broadcast_to_room(
-                                room_id,
-                                ServerMessage::TurnChanged {
-                                    player_id: current_player,
-                                },
-                            );
-
-                            // Check for game over
-                            if let Some((winner_idx, winner_name)) = winner {
-                                state.broadcast_to_room(
-                                    room_id,
-                                    ServerMessage::GameOver {
-                                        winner: winner_idx,
-                                        winner_name,
-                                    },
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            state.send_to_player(
-                                player_id,
-                                ServerMessage::ActionResult {
-                                    success: false,
-                                    events: vec![],
-                                    error: Some(e.to_string()),
-                                },
-                            );
-                        }
-                    }
-                }
+        ClientMessage::GameAction { action, request_id } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Action {
+                    player_id,
+                    action,
+                    request_id,
+                });
+            }
+        }
+
+        ClientMessage::RequestHint { difficulty } => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Hint {
+                    player_id,
+                    difficulty: difficulty
+                        .map_or(state.hint_difficulty, |d| d.min(state.hint_difficulty)),
+                });
             }
         }
 
         ClientMessage::Chat { message } => {
-            if let Some(&room_id) = state.player_rooms.get(&player_id).as_deref() {
-                let player_name = state
-                    .rooms
-                    .get(&room_id)
-                    .and_then(|r| r.players.get(&player_id).map(|p| p.name.clone()))
-                    .unwrap_or_else(|| "Unknown".to_string());
-
-                state.broadcast_to_room(
-                    room_id,
-                    ServerMessage::ChatMessage {
-                        player_name,
+            if let Some(room) = state.player_room(player_id) {
+                if chat::may_speak(state, player_id) {
+                    room.send(RoomCommand::Chat {
+                        player_id,
                         message,
-                    },
-                );
+                        emote: None,
+                    });
+                }
+            }
+        }
+
+        ClientMessage::Emote { emote } => {
+            if let Some(room) = state.player_room(player_id) {
+                if chat::may_speak(state, player_id) {
+                    room.send(RoomCommand::Chat {
+                        player_id,
+                        message: emote.text().to_string(),
+                        emote: Some(emote),
+                    });
+                }
+            }
+        }
+
+        ClientMessage::LobbyChat {
+            player_name,
+            message,
+        } => {
+            if chat::may_speak(state, player_id) {
+                chat::lobby_chat(state, player_id, player_name, message);
+            }
+        }
+
+        ClientMessage::Mute { player_id: target } => {
+            let muted = state.chat.mute(player_id, target);
+            state.send_to_player(player_id, ServerMessage::MuteList { muted });
+        }
+
+        ClientMessage::Unmute { player_id: target } => {
+            let muted = state.chat.unmute(player_id, target);
+            state.send_to_player(player_id, ServerMessage::MuteList { muted });
+        }
+
+        ClientMessage::ResyncState => {
+            if let Some(room) = state.player_room(player_id) {
+                room.send(RoomCommand::Resync { player_id });
             }
         }
 
         ClientMessage::ListRooms => {
-            let rooms = state.get_waiting_rooms();
-            state.send_to_player(player_id, ServerMessage::RoomList { rooms });
+            let (rooms, next_cursor) = state.list_rooms(&RoomQuery::default());
+            state.send_to_player(player_id, ServerMessage::RoomList { rooms, next_cursor });
+        }
+
+        ClientMessage::SearchRooms { query } => {
+            let (rooms, next_cursor) = state.list_rooms(&query);
+            state.send_to_player(player_id, ServerMessage::RoomList { rooms, next_cursor });
+        }
+
+        ClientMessage::GetLeaderboard { limit } => {
+            let mut players = state.player_stats();
+            players.truncate(limit.unwrap_or(LEADERBOARD_SIZE));
+            state.send_to_player(player_id, ServerMessage::Leaderboard { players });
+        }
+
+        ClientMessage::GetReplay { game_id } => {
+            let state = Arc::clone(state);
+            tokio::spawn(async move {
+                let msg = match replay::find(&state, player_id, game_id).await {
+                    Ok(replay) => ServerMessage::Replay {
+                        replay: Box::new(replay),
+                    },
+                    Err(e) => ServerMessage::Error {
+                        message: e.to_string(),
+                        code: None,
+                    },
+                };
+                state.send_to_player(player_id, msg);
+            });
+        }
+
+        ClientMessage::FollowGame { game_id } => send_to_room(
+            game_id,
+            player_id,
+            RoomCommand::Follow { player_id },
+            state,
+        ),
+
+        ClientMessage::UnfollowGame { game_id } => {
+            if let Some(room) = state.rooms.get(&game_id) {
+                room.send(RoomCommand::Unfollow { player_id });
+            }
+        }
+
+        ClientMessage::CreateTournament {
+            name,
+            format,
+            table_size,
+            settings,
+        } => tournament::create(state, player_id, name, format, table_size, settings),
+
+        ClientMessage::JoinTournament {
+            tournament_id,
+            player_name,
+        } => tournament::join(state, player_id, tournament_id, player_name),
+
+        ClientMessage::LeaveTournament { tournament_id } => {
+            tournament::leave(state, player_id, tournament_id)
+        }
+
+        ClientMessage::StartTournament { tournament_id } => {
+            tournament::start(state, player_id, tournament_id)
+        }
+
+        ClientMessage::ListTournaments => {
+            let tournaments = tournament::list(state);
+            state.send_to_player(player_id, ServerMessage::TournamentList { tournaments });
+        }
+
+        ClientMessage::GetTournament { tournament_id } => {
+            tournament::show(state, player_id, tournament_id)
+        }
+
+        ClientMessage::GetHealth => {
+            let health = state.health();
+            state.send_to_player(player_id, ServerMessage::Health { health });
+        }
+
+        ClientMessage::GetPlayerStats { player_id: target } => {
+            let stats = state
+                .player_stats()
+                .into_iter()
+                .find(|s| s.player_id == target);
+            state.send_to_player(
+                player_id,
+                ServerMessage::PlayerStats {
+                    player_id: target,
+                    stats,
+                },
+            );
         }
 
         ClientMessage::Ping => {
@@ -383,28 +1056,383 @@ fn handle_message(player_id: Uuid, msg: ClientMessage, state: &Arc<ServerState>)
     }
 }
 
+/// Pass a command to a room by ID, picking the room up from the store if
+/// another instance saved it
+fn send_to_room(room_id: Uuid, player_id: Uuid, command: RoomCommand, state: &Arc<ServerState>) {
+    let room = state
+        .rooms
+        .get(&room_id)
+        .map(|r| r.clone())
+        .or_else(|| fetch_room(room_id, state));
+    if !room.is_some_and(|room| room.send(command)) {
+        state.send_to_player(
+            player_id,
+            ServerMessage::Error {
+                message: "Room not found".to_string(),
+                code: None,
+            },
+        );
+    }
+}
+
 /// Handle player disconnect.
-fn handle_disconnect(player_id: Uuid, state: &Arc<ServerState>) {
+pub(crate) fn handle_disconnect(player_id: Uuid, state: &Arc<ServerState>) {
+    state.chat.forget(player_id);
     if let Some((_, room_id)) = state.player_rooms.remove(&player_id) {
-        if let Some(mut room) = state.rooms.get_mut(&room_id) {
-            // Mark player as disconnected instead of removing during game
-            if room.status == RoomStatus::InGame {
-                room.set_player_connected(player_id, false);
-                let room_info = room.to_info();
-                drop(room);
-                state.broadcast_to_room(room_id, ServerMessage::RoomUpdated { room: room_info });
-            } else {
-                // Remove player if game hasn't started
-                let is_empty = room.remove_player(player_id).unwrap_or(false);
-                if is_empty {
-                    drop(room);
-                    state.rooms.remove(&room_id);
-                } else {
-                    let room_info = room.to_info();
-                    drop(room);
-                    state.broadcast_to_room(room_id, ServerMessage::RoomUpdated { room: room_info });
+        if let Some(room) = state.rooms.get(&room_id).map(|r| r.clone()) {
+            room.send(RoomCommand::Disconnect { player_id });
+        }
+    }
+}
+
+/// Load the rooms saved before a restart, returning how many came back.
+/// People in games in progress get the usual grace period to rejoin before
+/// bots take their seats. Corrupt rooms are logged and left where they are.
+pub fn restore_rooms(state: &Arc<ServerState>) -> Result<usize, StoreError> {
+    let Some(store) = &state.store else {
+        return Ok(0);
+    };
+    let mut count = 0;
+    for snapshot in store.load_all()? {
+        let room_id = snapshot.id;
+        match GameRoom::from_snapshot(snapshot) {
+            Ok(room) => {
+                state.rooms.insert(room_id, spawn_room(room, state));
+                count += 1;
+            }
+            Err(e) => error!("Could not restore room {}: {}", room_id, e),
+        }
+    }
+    Ok(count)
+}
+
+/// Pick up a room this instance doesn't have from the shared store, so a
+/// player can arrive at any instance.
+fn fetch_room(room_id: Uuid, state: &Arc<ServerState>) -> Option<RoomHandle> {
+    let store = state.store.as_ref()?;
+    let snapshot = match store.load(room_id) {
+        Ok(snapshot) => snapshot?,
+        Err(e) => {
+            error!("Could not load room {}: {}", room_id, e);
+            return None;
+        }
+    };
+    let room = match GameRoom::from_snapshot(snapshot) {
+        Ok(room) => room,
+        Err(e) => {
+            error!("Could not restore room {}: {}", room_id, e);
+            return None;
+        }
+    };
+    // Someone else may have picked it up meanwhile
+    let room = state.rooms.entry(room_id).or_insert_with(|| {
+        info!("Picked up room {} from the store", room_id);
+        spawn_room(room, state)
+    });
+    Some(room.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::PROTOCOL_VERSION;
+    use crate::storage::MemoryStore;
+    use tokio_tungstenite::{connect_async, MaybeTlsStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// Serve `state` on a free port
+    async fn start(state: &Arc<ServerState>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Arc::clone(state)));
+        addr
+    }
+
+    /// Connect and say hello, returning the player ID we were given
+    async fn join(addr: SocketAddr) -> (Client, Uuid) {
+        let (client, player_id, _) = join_with(format!("ws://{}", addr)).await;
+        (client, player_id)
+    }
+
+    /// Connect to `url` and say hello, returning the player ID and ticket
+    /// we were given
+    async fn join_with(url: String) -> (Client, Uuid, Option<String>) {
+        let (mut client, _) = connect_async(url).await.unwrap();
+        let hello = serde_json::json!({
+            "type": "Hello",
+            "payload": { "protocol_version": PROTOCOL_VERSION, "client_name": "test" },
+        });
+        client.send(Message::Text(hello.to_string())).await.unwrap();
+        let Some(Ok(Message::Text(welcome))) = client.next().await else {
+            panic!("no welcome");
+        };
+        let welcome: ServerMessage = serde_json::from_str(&welcome).unwrap();
+        let ServerMessage::Welcome {
+            player_id, ticket, ..
+        } = welcome
+        else {
+            panic!("expected a welcome");
+        };
+        (client, player_id, ticket)
+    }
+
+    /// Every server message until the connection closes
+    async fn rest(client: &mut Client) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        while let Some(Ok(frame)) = client.next().await {
+            if let Message::Text(text) = frame {
+                messages.push(serde_json::from_str(&text).unwrap());
+            }
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_rooms_are_filtered_and_paged() {
+        let state = Arc::new(ServerState::new());
+        for (i, max_players) in [4, 4, 4, 3, 2].into_iter().enumerate() {
+            let host_id = Uuid::new_v4();
+            let room_id = Uuid::new_v4();
+            let host = format!("Host {}", i);
+            let mut room = GameRoom::new(room_id, host_id, host, max_players);
+            if i == 1 {
+                let bot = BotSeat {
+                    difficulty: BotDifficulty::Easy,
+                    personality: Default::default(),
+                };
+                room.add_bot(host_id, bot).unwrap();
+            }
+            if i == 2 {
+                let mut settings = room.settings.clone();
+                settings.options.pirate = true;
+                room.update_settings(host_id, settings).unwrap();
+            }
+            state.rooms.insert(room_id, spawn_room(room, &state));
+        }
+
+        let list = |query: RoomQuery| state.list_rooms(&query);
+        let (rooms, next) = list(RoomQuery::default());
+        assert_eq!(rooms.len(), 5);
+        assert!(next.is_none());
+
+        let (rooms, _) = list(RoomQuery {
+            max_players: Some(4),
+            has_bots: Some(false),
+            ..RoomQuery::default()
+        });
+        let names: Vec<_> = rooms.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"Host 0's Game") && names.contains(&"Host 2's Game"));
+
+        let pirate = serde_json::json!({"pirate": true});
+        let (rooms, _) = list(RoomQuery {
+            options: pirate.as_object().cloned(),
+            ..RoomQuery::default()
+        });
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].name, "Host 2's Game");
+        let (rooms, _) = list(RoomQuery {
+            name: Some("host 3".to_string()),
+            ..RoomQuery::default()
+        });
+        assert_eq!(rooms[0].max_players, 3);
+
+        // Pages pick up after the last room of the one before
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (rooms, next) = list(RoomQuery {
+                cursor,
+                limit: Some(2),
+                ..RoomQuery::default()
+            });
+            assert!(rooms.len() <= 2);
+            seen.extend(rooms.into_iter().map(|r| r.id));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), 5);
+        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_silent_clients_are_dropped() {
+        let mut state = ServerState::new();
+        state.heartbeat_interval = Duration::from_millis(50);
+        state.heartbeat_timeout = Duration::from_millis(200);
+        let state = Arc::new(state);
+        let (mut client, player_id) = join(start(&state).await).await;
+
+        // Reading answers the server's pings, so the connection stays up
+        let until = tokio::time::Instant::now() + Duration::from_millis(400);
+        while tokio::time::timeout_at(until, client.next()).await.is_ok() {}
+        assert!(state.player_senders.contains_key(&player_id));
+
+        // A client that stops answering is let go
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!state.player_senders.contains_key(&player_id));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_are_refused() {
+        let mut state = ServerState::new();
+        state.max_message_size = 1024;
+        let state = Arc::new(state);
+        let (mut client, player_id) = join(start(&state).await).await;
+
+        let chat = serde_json::json!({
+            "type": "Chat",
+            "payload": { "message": "x".repeat(4096) },
+        });
+        client.send(Message::Text(chat.to_string())).await.unwrap();
+        // The rest of the message is left unread, so the socket may be
+        // reset before the error gets through
+        let messages = rest(&mut client).await;
+        assert!(messages.iter().all(|message| matches!(
+            message,
+            ServerMessage::Error {
+                code: Some(ErrorCode::MessageTooLarge),
+                ..
+            }
+        )));
+        assert!(!state.player_senders.contains_key(&player_id));
+    }
+
+    #[tokio::test]
+    async fn test_pings_count_against_the_rate_limit() {
+        let mut state = ServerState::new();
+        state.rate_limit = RateLimit {
+            burst: 2,
+            per_second: 0,
+        };
+        let state = Arc::new(state);
+        let (mut client, _) = join(start(&state).await).await;
+
+        for _ in 0..3 {
+            client.send(Message::Ping(Vec::new())).await.unwrap();
+        }
+        let mut limited = false;
+        let until = tokio::time::Instant::now() + Duration::from_millis(500);
+        while let Ok(Some(Ok(frame))) = tokio::time::timeout_at(until, client.next()).await {
+            if let Message::Text(text) = frame {
+                let message: ServerMessage = serde_json::from_str(&text).unwrap();
+                if let ServerMessage::Error { code, .. } = message {
+                    limited |= code == Some(ErrorCode::RateLimited);
                 }
             }
         }
+        assert!(limited);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_rooms_are_not_restored() {
+        use crate::room::RoomError;
+        use catan_core::{HexCoord, VertexCoord, VertexDirection};
+
+        let store = Arc::new(MemoryStore::new());
+        let state = Arc::new(ServerState::new().with_store(Box::new(Arc::clone(&store))));
+        let snapshots: Vec<_> = (0..2)
+            .map(|_| {
+                let host_id = Uuid::new_v4();
+                let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 2);
+                room.add_player(Uuid::new_v4(), "Guest".to_string()).unwrap();
+                room.start_game(host_id).unwrap();
+                room.snapshot()
+            })
+            .collect();
+        let (good, mut corrupt) = (snapshots[0].clone(), snapshots[1].clone());
+        // A settlement out at sea
+        let vertex = VertexCoord::new(HexCoord::new(9, 9), VertexDirection::North);
+        corrupt.game.as_mut().unwrap().board.place_settlement(vertex, 0);
+        assert!(matches!(
+            GameRoom::from_snapshot(corrupt.clone()),
+            Err(RoomError::CorruptSnapshot(_))
+        ));
+
+        store.save(&good).unwrap();
+        store.save(&corrupt).unwrap();
+        assert_eq!(restore_rooms(&state).unwrap(), 1);
+        assert!(state.rooms.contains_key(&good.id));
+        assert!(!state.rooms.contains_key(&corrupt.id));
+        assert!(fetch_room(corrupt.id, &state).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_a_ticket_holder_replaces_their_stale_connection() {
+        let state = Arc::new(ServerState::new().with_tickets(TicketSigner::new("secret")));
+        let addr = start(&state).await;
+        let (mut old, player_id, ticket) = join_with(format!("ws://{}", addr)).await;
+
+        let url = format!("ws://{}/?ticket={}", addr, ticket.unwrap());
+        let (_new, again, _) = join_with(url).await;
+        assert_eq!(again, player_id);
+
+        // The old connection is closed, and closing it leaves the new one be
+        rest(&mut old).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(state.player_senders.contains_key(&player_id));
+    }
+
+    #[tokio::test]
+    async fn test_tickets_hold_seats_for_their_identity() {
+        let state = Arc::new(ServerState::new().with_tickets(TicketSigner::new("secret")));
+        let host_id = Uuid::new_v4();
+        let guest = Uuid::new_v4();
+        let stranger = Uuid::new_v4();
+        let (sender, mut inbox) = mpsc::unbounded_channel();
+        state.player_senders.insert(stranger, sender);
+
+        let room_id = Uuid::new_v4();
+        let mut room = GameRoom::new(room_id, host_id, "Host".to_string(), 2);
+        room.add_player(guest, "Guest".to_string()).unwrap();
+        room.start_game(host_id).unwrap();
+        let token = room.rejoin_token(guest);
+        room.set_player_connected(guest, false);
+        state.rooms.insert(room_id, spawn_room(room, &state));
+
+        // Even the seat's token is no good to anyone but the guest
+        let rejoin = ClientMessage::Rejoin {
+            room_id,
+            player_id: guest,
+            token,
+            last_seq: 0,
+            last_chat_seq: 0,
+        };
+        handle_message(stranger, rejoin, &state);
+        let refused = tokio::time::timeout(Duration::from_secs(5), inbox.recv()).await;
+        assert!(matches!(
+            refused,
+            Ok(Some(ServerMessage::Error { .. }))
+        ));
+        assert!(!state.player_rooms.contains_key(&stranger));
+    }
+
+    #[tokio::test]
+    async fn test_shutting_down_saves_rooms_and_tells_everyone() {
+        let store = Arc::new(MemoryStore::new());
+        let state = Arc::new(ServerState::new().with_store(Box::new(Arc::clone(&store))));
+        let addr = start(&state).await;
+        let (mut host, _) = join(addr).await;
+        let create = serde_json::json!({
+            "type": "CreateRoom",
+            "payload": { "player_name": "Host", "max_players": 2 },
+        });
+        host.send(Message::Text(create.to_string())).await.unwrap();
+        host.next().await.unwrap().unwrap();
+        let (mut lobby, _) = join(addr).await;
+
+        shut_down(&state).await;
+        assert!(state.player_senders.is_empty());
+        assert_eq!(store.load_all().unwrap().len(), 1);
+        for client in [&mut host, &mut lobby] {
+            let messages = rest(client).await;
+            assert!(matches!(
+                messages.last(),
+                Some(ServerMessage::ServerShuttingDown)
+            ));
+        }
     }
 }
@@ -0,0 +1,192 @@
+//! Results of finished games, and the standings worked out from them.
+//!
+//! Every finished game is kept as a [`GameResult`] in the room store.
+//! Standings are worked out afresh from all of them: games played, wins,
+//! average victory points and an Elo-style rating. A game counts as a
+//! head-to-head match between each pair of people in it, decided by who
+//! placed higher. Bots are left out of the standings, and a game against
+//! only bots leaves ratings alone.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::protocol::PlayerStats;
+use crate::storage::StoreError;
+
+/// Rating everyone starts at
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// Most rating a player can gain or lose in one game
+const K_FACTOR: f64 = 32.0;
+
+/// How a finished game went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub room_id: Uuid,
+    /// Seconds since the Unix epoch
+    pub finished_at: u64,
+    pub duration_secs: u64,
+    /// Seats in turn order
+    pub players: Vec<SeatResult>,
+    /// Seat of the winner, if the game wasn't ended by agreement
+    pub winner: Option<usize>,
+    /// Seats from first place to last
+    pub ranking: Vec<usize>,
+}
+
+/// One seat's part in a finished game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeatResult {
+    pub player_id: Uuid,
+    pub name: String,
+    pub victory_points: u32,
+    /// A computer player rather than a person
+    pub bot: bool,
+}
+
+impl GameResult {
+    pub fn encode(&self) -> Result<String, StoreError> {
+        ron::to_string(self).map_err(|e| StoreError::Encoding(e.to_string()))
+    }
+
+    pub fn decode(data: &str) -> Result<Self, StoreError> {
+        ron::from_str(data).map_err(|e| StoreError::Encoding(e.to_string()))
+    }
+}
+
+/// Standings for everyone who has played, best rated first
+pub fn player_stats(results: &[GameResult]) -> Vec<PlayerStats> {
+    let mut results: Vec<&GameResult> = results.iter().collect();
+    results.sort_by_key(|r| r.finished_at);
+
+    let mut stats: HashMap<Uuid, PlayerStats> = HashMap::new();
+    let mut total_vp: HashMap<Uuid, u32> = HashMap::new();
+    for result in results {
+        // (player, place) for each person in the game
+        let people: Vec<(Uuid, usize)> = result
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, seat)| !seat.bot)
+            .map(|(i, seat)| {
+                let place = result.ranking.iter().position(|&s| s == i);
+                (seat.player_id, place.unwrap_or(result.players.len()))
+            })
+            .collect();
+
+        for (i, seat) in result.players.iter().enumerate().filter(|(_, s)| !s.bot) {
+            let entry = stats.entry(seat.player_id).or_insert_with(|| PlayerStats {
+                player_id: seat.player_id,
+                name: seat.name.clone(),
+                games: 0,
+                wins: 0,
+                win_rate: 0.0,
+                average_vp: 0.0,
+                rating: INITIAL_RATING,
+            });
+            entry.name = seat.name.clone();
+            entry.games += 1;
+            if result.winner == Some(i) {
+                entry.wins += 1;
+            }
+            *total_vp.entry(seat.player_id).or_default() += seat.victory_points;
+        }
+
+        // Everyone's change comes from their rating before this game
+        let rating = |id: &Uuid| stats.get(id).map_or(INITIAL_RATING, |s| s.rating);
+        let k = K_FACTOR / people.len().saturating_sub(1).max(1) as f64;
+        let changes: Vec<(Uuid, f64)> = people
+            .iter()
+            .map(|(id, place)| {
+                let change: f64 = people
+                    .iter()
+                    .filter(|(other, _)| other != id)
+                    .map(|(other, other_place)| {
+                        let expected =
+                            1.0 / (1.0 + 10f64.powf((rating(other) - rating(id)) / 400.0));
+                        let score = match place.cmp(other_place) {
+                            std::cmp::Ordering::Less => 1.0,
+                            std::cmp::Ordering::Equal => 0.5,
+                            std::cmp::Ordering::Greater => 0.0,
+                        };
+                        k * (score - expected)
+                    })
+                    .sum();
+                (*id, change)
+            })
+            .collect();
+        for (id, change) in changes {
+            if let Some(entry) = stats.get_mut(&id) {
+                entry.rating += change;
+            }
+        }
+    }
+
+    let mut stats: Vec<PlayerStats> = stats
+        .into_values()
+        .map(|mut s| {
+            s.win_rate = s.wins as f64 / s.games as f64;
+            s.average_vp = total_vp[&s.player_id] as f64 / s.games as f64;
+            s
+        })
+        .collect();
+    stats.sort_by(|a, b| b.rating.total_cmp(&a.rating).then(b.wins.cmp(&a.wins)));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seat(player_id: Uuid, name: &str, victory_points: u32, bot: bool) -> SeatResult {
+        SeatResult {
+            player_id,
+            name: name.to_string(),
+            victory_points,
+            bot,
+        }
+    }
+
+    #[test]
+    fn test_standings_count_wins_points_and_rating() {
+        let (ann, ben, bot) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let game = |finished_at, players, winner, ranking| GameResult {
+            room_id: Uuid::new_v4(),
+            finished_at,
+            duration_secs: 600,
+            players,
+            winner,
+            ranking,
+        };
+        let results = vec![
+            game(
+                1,
+                vec![seat(ann, "Ann", 10, false), seat(ben, "Ben", 6, false)],
+                Some(0),
+                vec![0, 1],
+            ),
+            // Ben beats a bot, but no person, so ratings stay put
+            game(
+                2,
+                vec![seat(ben, "Benjamin", 10, false), seat(bot, "Bot", 4, true)],
+                Some(0),
+                vec![0, 1],
+            ),
+        ];
+
+        let stats = player_stats(&results);
+        assert_eq!(stats.len(), 2);
+        let (first, second) = (&stats[0], &stats[1]);
+        assert_eq!(first.player_id, ann);
+        assert_eq!((first.games, first.wins), (1, 1));
+        assert_eq!(first.average_vp, 10.0);
+        assert_eq!(first.rating, INITIAL_RATING + 16.0);
+
+        assert_eq!(second.name, "Benjamin");
+        assert_eq!((second.games, second.wins), (2, 1));
+        assert_eq!(second.win_rate, 0.5);
+        assert_eq!(second.average_vp, 8.0);
+        assert_eq!(second.rating, INITIAL_RATING - 16.0);
+    }
+}
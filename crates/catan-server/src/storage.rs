@@ -0,0 +1,596 @@
+//! Saving rooms so games survive a server restart.
+//!
+//! Rooms are saved as [`RoomSnapshot`]s after every change and loaded back
+//! on startup. Snapshots are written as RON rather than JSON because the
+//! game state keys maps by board coordinates, which JSON can't express.
+//!
+//! Several server instances can share one store (Redis, or SQLite on a
+//! shared disk): an instance picks a room up from the store when a player
+//! arrives for it. Two instances must not host the same room at once, so
+//! route each room's players to one instance, e.g. by room ID.
+//!
+//! The store also keeps the result of every finished game, which the
+//! leaderboard is worked out from.
+
+use catan_core::{EventLog, GameRecord, GameState, SeedReveal};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::protocol::{BotSeat, ChatLine, RoomSettings, RoomStatus};
+use crate::stats::GameResult;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Storage error: {0}")]
+    Backend(String),
+
+    #[error("Could not encode room: {0}")]
+    Encoding(String),
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+/// Where rooms are kept between restarts.
+pub trait RoomStore: Send + Sync {
+    /// Save a room, replacing any earlier copy
+    fn save(&self, room: &RoomSnapshot) -> Result<(), StoreError>;
+
+    /// Forget a room that has closed
+    fn remove(&self, room_id: Uuid) -> Result<(), StoreError>;
+
+    /// One saved room, if there is one
+    fn load(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError>;
+
+    /// Every saved room
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, StoreError>;
+
+    /// Keep the result of a finished game, replacing any earlier copy
+    fn record_result(&self, result: &GameResult) -> Result<(), StoreError>;
+
+    /// Every game result kept
+    fn load_results(&self) -> Result<Vec<GameResult>, StoreError>;
+
+    /// Put away a room that is over or abandoned: it no longer comes back
+    /// on a restart, but its last state is kept
+    fn archive(&self, room: &RoomSnapshot) -> Result<(), StoreError>;
+
+    /// An archived room, if there is one
+    fn load_archived(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError>;
+
+    /// Check the store can be reached
+    fn ping(&self) -> Result<(), StoreError>;
+}
+
+/// A store shared between owners, such as server instances in one process
+impl<S: RoomStore + ?Sized> RoomStore for Arc<S> {
+    fn save(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        (**self).save(room)
+    }
+
+    fn remove(&self, room_id: Uuid) -> Result<(), StoreError> {
+        (**self).remove(room_id)
+    }
+
+    fn load(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        (**self).load(room_id)
+    }
+
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, StoreError> {
+        (**self).load_all()
+    }
+
+    fn record_result(&self, result: &GameResult) -> Result<(), StoreError> {
+        (**self).record_result(result)
+    }
+
+    fn load_results(&self) -> Result<Vec<GameResult>, StoreError> {
+        (**self).load_results()
+    }
+
+    fn archive(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        (**self).archive(room)
+    }
+
+    fn load_archived(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        (**self).load_archived(room_id)
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        (**self).ping()
+    }
+}
+
+/// Everything needed to bring a room back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    pub max_players: u8,
+    pub host_id: Uuid,
+    pub status: RoomStatus,
+    /// Seats in turn order
+    pub players: Vec<PlayerSnapshot>,
+    pub game: Option<GameState>,
+    pub events: EventLog,
+    pub chat: Vec<ChatLine>,
+    #[serde(default)]
+    pub settings: RoomSettings,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub started_at: Option<SystemTime>,
+    /// The finished game's result has gone to the store
+    #[serde(default)]
+    pub result_recorded: bool,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// The starting position and every move, for the replay
+    #[serde(default)]
+    pub record: Option<GameRecord>,
+    /// Kept secret until the game is over
+    #[serde(default)]
+    pub seed: Option<SeedReveal>,
+}
+
+/// A seat in a saved room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    pub ready: bool,
+    pub game_index: Option<u8>,
+    pub bot: Option<BotSeat>,
+    pub stand_in: bool,
+    /// Rooms saved before seats had tokens get new ones
+    #[serde(default = "Uuid::new_v4")]
+    pub rejoin_token: Uuid,
+}
+
+impl RoomSnapshot {
+    pub fn encode(&self) -> Result<String, StoreError> {
+        ron::to_string(self).map_err(|e| StoreError::Encoding(e.to_string()))
+    }
+
+    pub fn decode(data: &str) -> Result<Self, StoreError> {
+        ron::from_str(data).map_err(|e| StoreError::Encoding(e.to_string()))
+    }
+}
+
+/// Rooms kept in memory, lost when the process exits. For tests and for
+/// instances sharing a process.
+#[derive(Default)]
+pub struct MemoryStore {
+    rooms: Mutex<HashMap<Uuid, RoomSnapshot>>,
+    results: Mutex<HashMap<Uuid, GameResult>>,
+    archived: Mutex<HashMap<Uuid, RoomSnapshot>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn rooms(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, RoomSnapshot>> {
+        self.rooms.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn results(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, GameResult>> {
+        self.results.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn archived(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, RoomSnapshot>> {
+        self.archived.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RoomStore for MemoryStore {
+    fn save(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        self.rooms().insert(room.id, room.clone());
+        Ok(())
+    }
+
+    fn remove(&self, room_id: Uuid) -> Result<(), StoreError> {
+        self.rooms().remove(&room_id);
+        Ok(())
+    }
+
+    fn load(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        Ok(self.rooms().get(&room_id).cloned())
+    }
+
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, StoreError> {
+        Ok(self.rooms().values().cloned().collect())
+    }
+
+    fn record_result(&self, result: &GameResult) -> Result<(), StoreError> {
+        self.results().insert(result.room_id, result.clone());
+        Ok(())
+    }
+
+    fn load_results(&self) -> Result<Vec<GameResult>, StoreError> {
+        Ok(self.results().values().cloned().collect())
+    }
+
+    fn archive(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        self.rooms().remove(&room.id);
+        self.archived().insert(room.id, room.clone());
+        Ok(())
+    }
+
+    fn load_archived(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        Ok(self.archived().get(&room_id).cloned())
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// Rooms kept in an SQLite database file.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A database that lives only as long as the store
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS rooms (id TEXT PRIMARY KEY, data TEXT NOT NULL)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS results (room_id TEXT PRIMARY KEY, data TEXT NOT NULL)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS archived_rooms (id TEXT PRIMARY KEY, data TEXT NOT NULL)",
+            [],
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RoomStore for SqliteStore {
+    fn save(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        let data = room.encode()?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO rooms (id, data) VALUES (?1, ?2)",
+            params![room.id.to_string(), data],
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, room_id: Uuid) -> Result<(), StoreError> {
+        self.conn().execute(
+            "DELETE FROM rooms WHERE id = ?1",
+            params![room_id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn load(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM rooms WHERE id = ?1")?;
+        let mut rows = stmt.query(params![room_id.to_string()])?;
+        match rows.next()? {
+            Some(row) => RoomSnapshot::decode(&row.get::<_, String>(0)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM rooms")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.map(|data| RoomSnapshot::decode(&data?)).collect()
+    }
+
+    fn record_result(&self, result: &GameResult) -> Result<(), StoreError> {
+        let data = result.encode()?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO results (room_id, data) VALUES (?1, ?2)",
+            params![result.room_id.to_string(), data],
+        )?;
+        Ok(())
+    }
+
+    fn load_results(&self) -> Result<Vec<GameResult>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM results")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.map(|data| GameResult::decode(&data?)).collect()
+    }
+
+    fn archive(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        let data = room.encode()?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO archived_rooms (id, data) VALUES (?1, ?2)",
+            params![room.id.to_string(), data],
+        )?;
+        tx.execute("DELETE FROM rooms WHERE id = ?1", params![room.id.to_string()])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_archived(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM archived_rooms WHERE id = ?1")?;
+        let mut rows = stmt.query(params![room_id.to_string()])?;
+        match rows.next()? {
+            Some(row) => RoomSnapshot::decode(&row.get::<_, String>(0)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        self.conn().query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+/// Rooms kept in Redis, one key per room plus a set of room IDs. Game
+/// results go in one hash keyed by room ID, archived rooms in another.
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    client: redis::Client,
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for StoreError {
+    fn from(e: redis::RedisError) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    /// Connect to the server at `url` (e.g. `redis://127.0.0.1/`), keeping
+    /// keys under `kopiatan:`
+    pub fn open(url: &str) -> Result<Self, StoreError> {
+        let client = redis::Client::open(url)?;
+        // Fail now rather than on the first save
+        client.get_connection()?;
+        Ok(Self {
+            client,
+            prefix: "kopiatan".to_string(),
+        })
+    }
+
+    fn room_key(&self, room_id: Uuid) -> String {
+        format!("{}:room:{}", self.prefix, room_id)
+    }
+
+    fn index_key(&self) -> String {
+        format!("{}:rooms", self.prefix)
+    }
+
+    fn results_key(&self) -> String {
+        format!("{}:results", self.prefix)
+    }
+
+    fn archive_key(&self) -> String {
+        format!("{}:archive", self.prefix)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl RoomStore for RedisStore {
+    fn save(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        let data = room.encode()?;
+        let mut conn = self.client.get_connection()?;
+        redis::pipe()
+            .atomic()
+            .set(self.room_key(room.id), data)
+            .sadd(self.index_key(), room.id.to_string())
+            .query::<()>(&mut conn)?;
+        Ok(())
+    }
+
+    fn remove(&self, room_id: Uuid) -> Result<(), StoreError> {
+        let mut conn = self.client.get_connection()?;
+        redis::pipe()
+            .atomic()
+            .del(self.room_key(room_id))
+            .srem(self.index_key(), room_id.to_string())
+            .query::<()>(&mut conn)?;
+        Ok(())
+    }
+
+    fn load(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        let mut conn = self.client.get_connection()?;
+        let data: Option<String> = redis::cmd("GET")
+            .arg(self.room_key(room_id))
+            .query(&mut conn)?;
+        data.map(|d| RoomSnapshot::decode(&d)).transpose()
+    }
+
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, StoreError> {
+        let mut conn = self.client.get_connection()?;
+        let ids: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(self.index_key())
+            .query(&mut conn)?;
+        let mut rooms = Vec::with_capacity(ids.len());
+        for id in ids {
+            let data: Option<String> = redis::cmd("GET")
+                .arg(format!("{}:room:{}", self.prefix, id))
+                .query(&mut conn)?;
+            if let Some(data) = data {
+                rooms.push(RoomSnapshot::decode(&data)?);
+            }
+        }
+        Ok(rooms)
+    }
+
+    fn record_result(&self, result: &GameResult) -> Result<(), StoreError> {
+        let data = result.encode()?;
+        let mut conn = self.client.get_connection()?;
+        redis::cmd("HSET")
+            .arg(self.results_key())
+            .arg(result.room_id.to_string())
+            .arg(data)
+            .query::<()>(&mut conn)?;
+        Ok(())
+    }
+
+    fn load_results(&self) -> Result<Vec<GameResult>, StoreError> {
+        let mut conn = self.client.get_connection()?;
+        let results: Vec<String> = redis::cmd("HVALS")
+            .arg(self.results_key())
+            .query(&mut conn)?;
+        results.iter().map(|d| GameResult::decode(d)).collect()
+    }
+
+    fn archive(&self, room: &RoomSnapshot) -> Result<(), StoreError> {
+        let data = room.encode()?;
+        let mut conn = self.client.get_connection()?;
+        redis::pipe()
+            .atomic()
+            .hset(self.archive_key(), room.id.to_string(), data)
+            .del(self.room_key(room.id))
+            .srem(self.index_key(), room.id.to_string())
+            .query::<()>(&mut conn)?;
+        Ok(())
+    }
+
+    fn load_archived(&self, room_id: Uuid) -> Result<Option<RoomSnapshot>, StoreError> {
+        let mut conn = self.client.get_connection()?;
+        let data: Option<String> = redis::cmd("HGET")
+            .arg(self.archive_key())
+            .arg(room_id.to_string())
+            .query(&mut conn)?;
+        data.map(|d| RoomSnapshot::decode(&d)).transpose()
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        let mut conn = self.client.get_connection()?;
+        redis::cmd("PING").query::<()>(&mut conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::GameRoom;
+
+    #[test]
+    fn test_rooms_come_back_from_sqlite() {
+        let host_id = Uuid::new_v4();
+        let mut room = GameRoom::new(Uuid::new_v4(), host_id, "Host".to_string(), 3);
+        let seat = BotSeat {
+            difficulty: catan_core::BotDifficulty::Easy,
+            personality: catan_core::BotPersonality::Balanced,
+        };
+        room.add_bot(host_id, seat).unwrap();
+        room.add_bot(host_id, seat).unwrap();
+        room.start_game(host_id).unwrap();
+        room.run_bots();
+        room.add_chat(host_id, "Host".to_string(), "hi".to_string(), None);
+
+        let store = SqliteStore::in_memory().unwrap();
+        store.save(&room.snapshot()).unwrap();
+        let mut saved = store.load_all().unwrap();
+        assert_eq!(saved.len(), 1);
+        let mut restored = GameRoom::from_snapshot(saved.remove(0)).unwrap();
+
+        assert_eq!(restored.to_info().players.len(), 3);
+        assert!(!restored.players[&host_id].connected);
+        assert_eq!(restored.chat, room.chat);
+        assert_eq!(restored.events.last_seq(), room.events.last_seq());
+
+        // The game carries on exactly where it was
+        let before = room.game.as_ref().unwrap();
+        let after = restored.game.as_ref().unwrap();
+        assert_eq!(after.waiting_on(), vec![0]);
+        let action = before.auto_action(0).unwrap();
+        assert_eq!(after.auto_action(0), Some(action.clone()));
+        room.apply_action(host_id, action.clone()).unwrap();
+        restored.apply_action(host_id, action).unwrap();
+        room.run_bots();
+        restored.run_bots();
+        assert_eq!(
+            room.game.as_ref().unwrap().turn_number,
+            restored.game.as_ref().unwrap().turn_number
+        );
+
+        store.remove(room.id).unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+
+        // An archived room is kept, but no longer comes back on a restart
+        store.save(&room.snapshot()).unwrap();
+        store.archive(&room.snapshot()).unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+        let archived = store.load_archived(room.id).unwrap().unwrap();
+        assert_eq!(archived.chat, room.chat);
+    }
+
+    #[tokio::test]
+    async fn test_instances_sharing_a_store_see_each_others_rooms() {
+        use crate::actor::spawn_room;
+        use crate::server::ServerState;
+
+        let shared = Arc::new(MemoryStore::new());
+        let first = Arc::new(ServerState::new().with_store(Box::new(shared.clone())));
+        let second = ServerState::new().with_store(Box::new(shared.clone()));
+
+        let room_id = Uuid::new_v4();
+        let room = GameRoom::new(room_id, Uuid::new_v4(), "Host".to_string(), 4);
+        first.save_room(&room);
+        first.rooms.insert(room_id, spawn_room(room, &first));
+
+        // Listed once on each instance
+        assert_eq!(first.get_waiting_rooms().len(), 1);
+        let listed = second.get_waiting_rooms();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, room_id);
+        assert!(shared.load(room_id).unwrap().is_some());
+
+        first.forget_room(room_id);
+        assert!(second.get_waiting_rooms().is_empty());
+    }
+
+    #[test]
+    fn test_results_are_kept_once_per_game() {
+        let store = SqliteStore::in_memory().unwrap();
+        let mut result = GameResult {
+            room_id: Uuid::new_v4(),
+            finished_at: 1,
+            duration_secs: 60,
+            players: Vec::new(),
+            winner: None,
+            ranking: Vec::new(),
+        };
+        store.record_result(&result).unwrap();
+        result.duration_secs = 90;
+        store.record_result(&result).unwrap();
+        assert_eq!(store.load_results().unwrap(), vec![result]);
+    }
+}
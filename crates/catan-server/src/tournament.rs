@@ -0,0 +1,596 @@
+//! Tournaments: players enter, the server seats each round at tables of
+//! its own, and the results draw up the next round.
+//!
+//! An organizer opens a tournament with `CreateTournament` and starts it
+//! once enough players have entered. Each table is a room with the game
+//! already under way; its players are sent the room ID and take their
+//! seats with `Rejoin`, giving their own player ID. A bot stands in for
+//! anyone who doesn't come within the usual grace period, so every table
+//! finishes. When the last table of a round is over, the next is drawn up:
+//!
+//! - single elimination: each table's winner plays on, until one is left;
+//! - Swiss: everyone plays every round, seated with those on similar
+//!   points, for a set number of rounds.
+//!
+//! Entrants are known by player ID, so they should connect with a ticket
+//! to keep it between connections. Tournaments are kept in memory only.
+
+use crate::actor::spawn_room;
+use crate::protocol::{
+    EntrantInfo, RoomSettings, RoundInfo, ServerMessage, TableInfo, TournamentFormat,
+    TournamentInfo, TournamentStatus,
+};
+use crate::room::{check_settings, GameRoom, RoomError};
+use crate::server::ServerState;
+use crate::stats::GameResult;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Most players one tournament takes
+pub const MAX_ENTRANTS: usize = 256;
+
+/// Most rounds a Swiss tournament may have
+const MAX_SWISS_ROUNDS: u32 = 20;
+
+/// How long a finished tournament is kept for people to look at
+pub const FINISHED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Error)]
+pub enum TournamentError {
+    #[error("Tournament not found")]
+    NotFound,
+    #[error("Not the organizer")]
+    NotOrganizer,
+    #[error("Tournament already started")]
+    AlreadyStarted,
+    #[error("Already entered")]
+    AlreadyEntered,
+    #[error("Not entered")]
+    NotEntered,
+    #[error("Tournament is full")]
+    Full,
+    #[error("Not enough players")]
+    NotEnoughPlayers,
+    #[error("Invalid tournament: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Room(#[from] RoomError),
+}
+
+/// A tournament and the rounds played so far.
+pub struct Tournament {
+    info: TournamentInfo,
+    finished_at: Option<Instant>,
+}
+
+impl Tournament {
+    pub fn new(
+        id: Uuid,
+        organizer_id: Uuid,
+        name: String,
+        format: TournamentFormat,
+        table_size: u8,
+        settings: RoomSettings,
+    ) -> Result<Self, TournamentError> {
+        let invalid = |reason: &str| Err(TournamentError::Invalid(reason.to_string()));
+        if name.trim().is_empty() {
+            return invalid("a tournament needs a name");
+        }
+        if !(2..=4).contains(&table_size) {
+            return invalid("tables seat 2 to 4 players");
+        }
+        if let TournamentFormat::Swiss { rounds } = format {
+            if !(1..=MAX_SWISS_ROUNDS).contains(&rounds) {
+                return invalid("a Swiss tournament plays 1 to 20 rounds");
+            }
+        }
+        check_settings(&settings, table_size)?;
+        // Tables may come out smaller than asked for, so teams can't be
+        // fixed up front
+        if !settings.options.teams.is_empty() {
+            return invalid("tournament games can't be played in teams");
+        }
+
+        Ok(Self {
+            info: TournamentInfo {
+                id,
+                name,
+                organizer_id,
+                format,
+                table_size,
+                settings,
+                status: TournamentStatus::Registering,
+                entrants: Vec::new(),
+                rounds: Vec::new(),
+                winner: None,
+            },
+            finished_at: None,
+        })
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.info.id
+    }
+
+    pub fn to_info(&self) -> TournamentInfo {
+        self.info.clone()
+    }
+
+    pub fn register(&mut self, player_id: Uuid, name: String) -> Result<(), TournamentError> {
+        if self.info.status != TournamentStatus::Registering {
+            return Err(TournamentError::AlreadyStarted);
+        }
+        if self.entrant(player_id).is_some() {
+            return Err(TournamentError::AlreadyEntered);
+        }
+        if self.info.entrants.len() >= MAX_ENTRANTS {
+            return Err(TournamentError::Full);
+        }
+        self.info.entrants.push(EntrantInfo {
+            player_id,
+            name,
+            points: 0,
+            wins: 0,
+            eliminated: false,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, player_id: Uuid) -> Result<(), TournamentError> {
+        if self.info.status != TournamentStatus::Registering {
+            return Err(TournamentError::AlreadyStarted);
+        }
+        if self.entrant(player_id).is_none() {
+            return Err(TournamentError::NotEntered);
+        }
+        self.info.entrants.retain(|e| e.player_id != player_id);
+        Ok(())
+    }
+
+    /// Close entries and draw up the first round (organizer only)
+    pub fn start(&mut self, requester_id: Uuid) -> Result<(), TournamentError> {
+        if requester_id != self.info.organizer_id {
+            return Err(TournamentError::NotOrganizer);
+        }
+        if self.info.status != TournamentStatus::Registering {
+            return Err(TournamentError::AlreadyStarted);
+        }
+        if self.info.entrants.len() < 2 {
+            return Err(TournamentError::NotEnoughPlayers);
+        }
+        self.info.status = TournamentStatus::Running;
+        self.next_round();
+        Ok(())
+    }
+
+    /// Whether a room is one of the tables of the round being played
+    pub fn has_table(&self, room_id: Uuid) -> bool {
+        self.info
+            .rounds
+            .last()
+            .is_some_and(|r| r.tables.iter().any(|t| t.room_id == Some(room_id)))
+    }
+
+    /// Take in how a table's game finished, with its seats from first
+    /// place to last. Once every table is done, the next round is drawn
+    /// up, or the tournament ends.
+    pub fn record(&mut self, room_id: Uuid, ranking: &[usize]) {
+        let Some(round) = self.info.rounds.last_mut() else {
+            return;
+        };
+        let Some(table) = round
+            .tables
+            .iter_mut()
+            .find(|t| t.room_id == Some(room_id) && t.ranking.is_none())
+        else {
+            return;
+        };
+        let mut places: Vec<Uuid> = ranking
+            .iter()
+            .filter_map(|&seat| table.players.get(seat).copied())
+            .collect();
+        // Anyone the game left out of its ranking comes last
+        for player_id in &table.players {
+            if !places.contains(player_id) {
+                places.push(*player_id);
+            }
+        }
+        score(
+            &mut self.info.entrants,
+            self.info.format,
+            &places,
+            places.len(),
+        );
+        table.ranking = Some(places);
+
+        if round.tables.iter().all(|t| t.ranking.is_some()) {
+            self.next_round();
+        }
+    }
+
+    /// Everyone who hears about changes: the organizer and the entrants
+    fn audience(&self) -> Vec<Uuid> {
+        let mut audience = vec![self.info.organizer_id];
+        audience.extend(
+            self.info
+                .entrants
+                .iter()
+                .map(|e| e.player_id)
+                .filter(|&id| id != self.info.organizer_id),
+        );
+        audience
+    }
+
+    fn entrant(&self, player_id: Uuid) -> Option<&EntrantInfo> {
+        self.info.entrants.iter().find(|e| e.player_id == player_id)
+    }
+
+    /// Seat the players still in at new tables, or finish when the format
+    /// says there are no more rounds to play
+    fn next_round(&mut self) {
+        let mut playing: Vec<&EntrantInfo> = self
+            .info
+            .entrants
+            .iter()
+            .filter(|e| !e.eliminated)
+            .collect();
+        if let TournamentFormat::Swiss { .. } = self.info.format {
+            playing.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)));
+        }
+        let playing: Vec<Uuid> = playing.iter().map(|e| e.player_id).collect();
+
+        let finished = match self.info.format {
+            TournamentFormat::SingleElimination => playing.len() <= 1,
+            TournamentFormat::Swiss { rounds } => self.info.rounds.len() as u32 >= rounds,
+        };
+        if finished {
+            self.info.status = TournamentStatus::Finished;
+            self.info.winner = playing.first().copied();
+            self.finished_at = Some(Instant::now());
+            return;
+        }
+
+        let table_size = self.info.table_size as usize;
+        let mut tables = Vec::new();
+        for players in split(&playing, table_size) {
+            if let [bye] = players[..] {
+                // Someone left over wins the table they would have had
+                score(
+                    &mut self.info.entrants,
+                    self.info.format,
+                    &[bye],
+                    table_size,
+                );
+                tables.push(TableInfo {
+                    room_id: None,
+                    players,
+                    ranking: Some(vec![bye]),
+                });
+            } else {
+                tables.push(TableInfo {
+                    room_id: Some(Uuid::new_v4()),
+                    players,
+                    ranking: None,
+                });
+            }
+        }
+        self.info.rounds.push(RoundInfo {
+            number: self.info.rounds.len() as u32 + 1,
+            tables,
+        });
+    }
+}
+
+/// Give points for a table's places, as if it had `seats` players: one for
+/// each player below. Only the winner goes on in an elimination.
+fn score(entrants: &mut [EntrantInfo], format: TournamentFormat, places: &[Uuid], seats: usize) {
+    for (place, player_id) in places.iter().enumerate() {
+        let Some(entrant) = entrants.iter_mut().find(|e| e.player_id == *player_id) else {
+            continue;
+        };
+        entrant.points += (seats - 1 - place) as u32;
+        if place == 0 {
+            entrant.wins += 1;
+        } else if format == TournamentFormat::SingleElimination {
+            entrant.eliminated = true;
+        }
+    }
+}
+
+/// Split players into as few tables as seat them all, as evenly as can be
+fn split(players: &[Uuid], table_size: usize) -> Vec<Vec<Uuid>> {
+    let count = players.len().div_ceil(table_size);
+    let mut tables = Vec::with_capacity(count);
+    let mut rest = players;
+    for i in 0..count {
+        let size = players.len() / count + usize::from(i < players.len() % count);
+        let (table, after) = rest.split_at(size);
+        tables.push(table.to_vec());
+        rest = after;
+    }
+    tables
+}
+
+pub fn create(
+    state: &Arc<ServerState>,
+    player_id: Uuid,
+    name: String,
+    format: TournamentFormat,
+    table_size: u8,
+    settings: RoomSettings,
+) {
+    let id = Uuid::new_v4();
+    match Tournament::new(id, player_id, name, format, table_size, settings) {
+        Ok(tournament) => {
+            info!("Tournament {} opened by {}", id, player_id);
+            let info = tournament.to_info();
+            state.tournaments.insert(id, tournament);
+            state.send_to_player(
+                player_id,
+                ServerMessage::TournamentUpdated { tournament: info },
+            );
+        }
+        Err(e) => send_error(state, player_id, e),
+    }
+}
+
+pub fn join(state: &Arc<ServerState>, player_id: Uuid, tournament_id: Uuid, name: String) {
+    update(state, player_id, tournament_id, |t| {
+        t.register(player_id, name)
+    });
+}
+
+pub fn leave(state: &Arc<ServerState>, player_id: Uuid, tournament_id: Uuid) {
+    update(state, player_id, tournament_id, |t| t.withdraw(player_id));
+}
+
+pub fn start(state: &Arc<ServerState>, player_id: Uuid, tournament_id: Uuid) {
+    update(state, player_id, tournament_id, |t| t.start(player_id));
+}
+
+pub fn list(state: &ServerState) -> Vec<TournamentInfo> {
+    state.tournaments.iter().map(|t| t.to_info()).collect()
+}
+
+pub fn show(state: &Arc<ServerState>, player_id: Uuid, tournament_id: Uuid) {
+    match state.tournaments.get(&tournament_id).map(|t| t.to_info()) {
+        Some(tournament) => {
+            state.send_to_player(player_id, ServerMessage::TournamentUpdated { tournament })
+        }
+        None => send_error(state, player_id, TournamentError::NotFound),
+    }
+}
+
+/// A room's game is over; if it was a tournament table, note the result
+/// and open the next round's tables when it completed a round
+pub fn table_finished(state: &Arc<ServerState>, result: &GameResult) {
+    let Some(mut tournament) = state
+        .tournaments
+        .iter_mut()
+        .find(|t| t.has_table(result.room_id))
+    else {
+        return;
+    };
+    let rounds = tournament.info.rounds.len();
+    tournament.record(result.room_id, &result.ranking);
+    let drawn = tournament.info.rounds.len() > rounds;
+    let audience = tournament.audience();
+    let info = tournament.to_info();
+    drop(tournament);
+
+    if drawn {
+        open_tables(state, &info);
+    }
+    if info.status == TournamentStatus::Finished {
+        info!("Tournament {} finished", info.id);
+    }
+    announce(state, &audience, &info);
+}
+
+/// Forget tournaments that finished a while ago
+pub fn forget_finished(state: &ServerState) {
+    state
+        .tournaments
+        .retain(|_, t| t.finished_at.is_none_or(|at| at.elapsed() < FINISHED_TTL));
+}
+
+/// Make a change to a tournament, then tell everyone in it, and whoever
+/// asked, how it stands
+fn update(
+    state: &Arc<ServerState>,
+    player_id: Uuid,
+    tournament_id: Uuid,
+    change: impl FnOnce(&mut Tournament) -> Result<(), TournamentError>,
+) {
+    let changed = match state.tournaments.get_mut(&tournament_id) {
+        Some(mut tournament) => {
+            let rounds = tournament.info.rounds.len();
+            change(&mut tournament).map(|()| {
+                let drawn = tournament.info.rounds.len() > rounds;
+                (drawn, tournament.audience(), tournament.to_info())
+            })
+        }
+        None => Err(TournamentError::NotFound),
+    };
+    match changed {
+        Ok((drawn, mut audience, info)) => {
+            if drawn {
+                open_tables(state, &info);
+            }
+            if !audience.contains(&player_id) {
+                audience.push(player_id);
+            }
+            announce(state, &audience, &info);
+        }
+        Err(e) => send_error(state, player_id, e),
+    }
+}
+
+/// Open a room for each table of the latest round and send its players
+/// there
+fn open_tables(state: &Arc<ServerState>, tournament: &TournamentInfo) {
+    let Some(round) = tournament.rounds.last() else {
+        return;
+    };
+    for (i, table) in round.tables.iter().enumerate() {
+        let Some(room_id) = table.room_id else {
+            continue;
+        };
+        let seats: Vec<(Uuid, String)> = table
+            .players
+            .iter()
+            .filter_map(|id| tournament.entrants.iter().find(|e| e.player_id == *id))
+            .map(|e| (e.player_id, e.name.clone()))
+            .collect();
+        let name = format!(
+            "{}: round {}, table {}",
+            tournament.name,
+            round.number,
+            i + 1
+        );
+        let settings = tournament.settings.clone();
+        let room = match GameRoom::for_table(room_id, name, &seats, settings) {
+            Ok(room) => room,
+            Err(e) => {
+                error!(
+                    "Could not open table {} of {}: {}",
+                    room_id, tournament.id, e
+                );
+                continue;
+            }
+        };
+        state.rooms.insert(room_id, spawn_room(room, state));
+        for &player_id in &table.players {
+            state.send_to_player(
+                player_id,
+                ServerMessage::TournamentTable {
+                    tournament_id: tournament.id,
+                    round: round.number,
+                    room_id,
+                },
+            );
+        }
+    }
+}
+
+fn announce(state: &ServerState, audience: &[Uuid], tournament: &TournamentInfo) {
+    for &player_id in audience {
+        state.send_to_player(
+            player_id,
+            ServerMessage::TournamentUpdated {
+                tournament: tournament.clone(),
+            },
+        );
+    }
+}
+
+fn send_error(state: &ServerState, player_id: Uuid, e: TournamentError) {
+    state.send_to_player(
+        player_id,
+        ServerMessage::Error {
+            message: e.to_string(),
+            code: None,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(format: TournamentFormat, table_size: u8, entrants: usize) -> Tournament {
+        let organizer = Uuid::new_v4();
+        let settings = RoomSettings::default();
+        let name = "Cup".to_string();
+        let mut t = Tournament::new(
+            Uuid::new_v4(),
+            organizer,
+            name,
+            format,
+            table_size,
+            settings,
+        )
+        .unwrap();
+        for i in 0..entrants {
+            t.register(Uuid::new_v4(), format!("P{}", i)).unwrap();
+        }
+        t.start(organizer).unwrap();
+        t
+    }
+
+    /// Finish every open table of the latest round, seat order winning
+    fn play_round(t: &mut Tournament) {
+        let tables = t.info.rounds.last().unwrap().tables.clone();
+        for table in tables {
+            if let Some(room_id) = table.room_id {
+                let ranking: Vec<usize> = (0..table.players.len()).collect();
+                t.record(room_id, &ranking);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounds_are_drawn_up_until_there_is_a_winner() {
+        // Five at tables of two: two games and a bye each round until one
+        // player is left
+        let mut cup = tournament(TournamentFormat::SingleElimination, 2, 5);
+        let first = &cup.info.rounds[0].tables;
+        let sizes: Vec<usize> = first.iter().map(|t| t.players.len()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        assert!(first[2].room_id.is_none() && first[2].ranking.is_some());
+        assert!(!cup.has_table(Uuid::new_v4()));
+        assert!(matches!(
+            cup.register(Uuid::new_v4(), "Late".to_string()),
+            Err(TournamentError::AlreadyStarted)
+        ));
+
+        play_round(&mut cup);
+        assert_eq!(cup.info.rounds.len(), 2);
+        assert_eq!(
+            cup.info.entrants.iter().filter(|e| !e.eliminated).count(),
+            3
+        );
+        while cup.info.status == TournamentStatus::Running {
+            play_round(&mut cup);
+        }
+        let winner = cup.info.winner.unwrap();
+        assert_eq!(winner, cup.info.entrants[0].player_id);
+        assert_eq!(
+            cup.info.entrants.iter().filter(|e| !e.eliminated).count(),
+            1
+        );
+
+        // Swiss: everyone plays every round, and the most points win
+        let mut swiss = tournament(TournamentFormat::Swiss { rounds: 2 }, 3, 6);
+        play_round(&mut swiss);
+        // The two table winners meet in the second round
+        let second = swiss.info.rounds[1].tables[0].players.clone();
+        let leaders: Vec<Uuid> = swiss.info.rounds[0]
+            .tables
+            .iter()
+            .map(|t| t.players[0])
+            .collect();
+        assert!(leaders.iter().all(|p| second.contains(p)));
+        play_round(&mut swiss);
+        assert_eq!(swiss.info.status, TournamentStatus::Finished);
+        assert_eq!(swiss.info.winner, Some(second[0]));
+        assert!(swiss.info.entrants.iter().all(|e| !e.eliminated));
+
+        // A room's own game finds its way to the tournament's table
+        let seats: Vec<(Uuid, String)> = (0..3)
+            .map(|i| (Uuid::new_v4(), format!("P{}", i)))
+            .collect();
+        let room = GameRoom::for_table(
+            Uuid::new_v4(),
+            "T".to_string(),
+            &seats,
+            RoomSettings::default(),
+        )
+        .unwrap();
+        assert!(room.game.is_some());
+        assert!(room.players.values().all(|p| !p.connected));
+    }
+}
@@ -0,0 +1,306 @@
+//! Game lifecycle events sent to a URL the host gives.
+//!
+//! A host can point their room at a webhook with `SetWebhook`. The room
+//! then POSTs a JSON object there when the game starts, each time the turn
+//! passes, and when the game is over, so bots and tournament software can
+//! follow games without holding a socket open:
+//!
+//! ```json
+//! {"event": "turn_changed", "at_ms": 1700000000000, "room_id": "...",
+//!  "turn": 4, "seat": 1, "player_id": "...", "name": "Ana"}
+//! ```
+//!
+//! Events go out one at a time in the order they happened. One that can't
+//! be delivered is tried again a few times, then dropped, as are events
+//! that arrive while a room already has a full queue.
+//!
+//! Webhooks only ever reach public addresses: URLs naming a loopback,
+//! private, link-local or unspecified IP are refused up front, host names
+//! are refused if they resolve to one, and redirects aren't followed, so a
+//! host can't use the server to reach its own network.
+
+use crate::stats::GameResult;
+use catan_core::PlayerId;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Longest URL a webhook may have
+pub const MAX_URL_LEN: usize = 2048;
+
+/// Tries for each event before it is dropped
+const ATTEMPTS: u32 = 3;
+
+/// How long a receiver has to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events a room may have waiting to go out
+const QUEUE_LEN: usize = 64;
+
+/// Pause before the first retry, doubled for each one after
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// What is POSTed, as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Milliseconds since the Unix epoch
+    pub at_ms: u64,
+    pub room_id: Uuid,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    GameStarted {
+        room_name: String,
+        /// Seats in turn order
+        players: Vec<WebhookSeat>,
+    },
+    TurnChanged {
+        turn: u32,
+        seat: PlayerId,
+        player_id: Option<Uuid>,
+        name: Option<String>,
+    },
+    GameOver {
+        result: GameResult,
+    },
+}
+
+/// A seat at the start of a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookSeat {
+    pub seat: PlayerId,
+    pub player_id: Uuid,
+    pub name: String,
+    pub bot: bool,
+}
+
+/// Whether `url` is one the server will POST to. Host names are checked
+/// again when they are resolved, by the client from [`client`].
+pub fn valid_url(url: &str) -> bool {
+    if url.len() > MAX_URL_LEN {
+        return false;
+    }
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    // IPv6 hosts keep their brackets
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.parse().map_or(true, public_ip)
+}
+
+/// Whether `ip` is out on the internet rather than on the server's own
+/// machine or network
+pub fn public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => public_ipv4(ip),
+            None => public_ipv6(ip),
+        },
+    }
+}
+
+fn public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // "This network" and carrier-grade NAT
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)))
+}
+
+fn public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// Resolves host names the usual way, but only to public addresses
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The client webhooks should be posted with: it only connects to public
+/// addresses, doesn't follow redirects and gives up after [`TIMEOUT`]
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .timeout(TIMEOUT)
+        .build()
+        .expect("webhook client settings are valid")
+}
+
+/// A room's webhook, with a task delivering its events in order. The task
+/// finishes what is queued and stops once the webhook is dropped.
+pub struct Webhook {
+    room_id: Uuid,
+    queue: mpsc::Sender<WebhookPayload>,
+}
+
+impl Webhook {
+    pub fn new(room_id: Uuid, url: String, client: reqwest::Client) -> Self {
+        let (queue, receiver) = mpsc::channel(QUEUE_LEN);
+        tokio::spawn(deliver(url, client, receiver));
+        Self { room_id, queue }
+    }
+
+    pub fn send(&self, event: WebhookEvent) {
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let payload = WebhookPayload {
+            at_ms,
+            room_id: self.room_id,
+            event,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.queue.try_send(payload) {
+            warn!("Dropped webhook for room {}: queue is full", self.room_id);
+        }
+    }
+}
+
+async fn deliver(
+    url: String,
+    client: reqwest::Client,
+    mut receiver: mpsc::Receiver<WebhookPayload>,
+) {
+    while let Some(payload) = receiver.recv().await {
+        let mut delay = RETRY_DELAY;
+        for attempt in 1..=ATTEMPTS {
+            let sent = client
+                .post(&url)
+                .timeout(TIMEOUT)
+                .json(&payload)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match sent {
+                Ok(_) => break,
+                Err(e) if attempt == ATTEMPTS => {
+                    warn!(
+                        "Dropped webhook for room {} after {} tries: {}",
+                        payload.room_id, ATTEMPTS, e
+                    );
+                }
+                Err(_) => {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_internal_addresses_are_rejected() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://10.0.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(!valid_url(url), "{} should be rejected", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_names_resolving_to_internal_addresses_are_not_posted_to() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("http://localhost:{}/hook", addr.port());
+        assert!(valid_url(&url));
+        let sent = client().post(&url).send().await;
+        assert!(sent.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_are_posted_in_order() {
+        assert!(valid_url("https://discord.com/api/webhooks/1/abc"));
+        assert!(!valid_url("ftp://example.com/hook"));
+        assert!(!valid_url("not a url"));
+        assert!(valid_url("http://93.184.216.34/hook"));
+
+        let (received, mut inbox) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/hook",
+            post(move |Json(payload): Json<WebhookPayload>| async move {
+                let _ = received.send(payload);
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let room_id = Uuid::new_v4();
+        let url = format!("http://{}/hook", addr);
+        let webhook = Webhook::new(room_id, url, reqwest::Client::new());
+        for turn in 1..=3 {
+            webhook.send(WebhookEvent::TurnChanged {
+                turn,
+                seat: 0,
+                player_id: None,
+                name: None,
+            });
+        }
+        for turn in 1..=3 {
+            let payload = inbox.recv().await.unwrap();
+            assert_eq!(payload.room_id, room_id);
+            assert!(matches!(
+                payload.event,
+                WebhookEvent::TurnChanged { turn: t, .. } if t == turn
+            ));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Plays bot-only games without a server and reports how they went.
+//!
+//! ```text
+//! catan-sim --games 1000 --bots hard,medium,easy,easy --seed 42 --options vp=10
+//! ```
+//!
+//! Prints each seat's win rate and the cards it collected from rolls, how
+//! long games ran and every game that broke a rule. With `--notation-dir`
+//! each game's moves are also written out as a replay record. Exits with
+//! an error when any game broke a rule, so it can run as a check.
+
+use anyhow::{bail, Context};
+use catan_core::{
+    BoardTemplate, BotDifficulty, BotPersonality, GameOptions, Resource, SimOutcome, SimSeat,
+    SimulationConfig, SimulationReport,
+};
+use clap::Parser;
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "catan-sim",
+    about = "Play bot-only games and report the results"
+)]
+struct Args {
+    /// Games to play
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+    /// One bot per seat in turn order, as `difficulty` or
+    /// `difficulty:personality` (e.g. `hard:blocker`)
+    #[arg(
+        long,
+        default_value = "hard,hard,hard,hard",
+        value_delimiter = ',',
+        value_parser = parse_seat
+    )]
+    bots: Vec<SimSeat>,
+    /// Seed of the first game; each game after it adds one
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Rules as `key=value` pairs: `vp`, `turns`, `pirate` and `board`
+    /// (`random` or `beginner`)
+    #[arg(long, default_value = "", value_parser = parse_options)]
+    options: GameOptions,
+    /// Actions after which a game is abandoned
+    #[arg(long, default_value_t = 5000)]
+    max_actions: u32,
+    /// Worker threads; 0 uses every core
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+    /// Write each game's moves to `<dir>/game-<seed>.json`
+    #[arg(long)]
+    notation_dir: Option<PathBuf>,
+}
+
+fn parse_seat(s: &str) -> Result<SimSeat, String> {
+    let (difficulty, personality) = s.trim().split_once(':').unwrap_or((s, ""));
+    let difficulty = match difficulty.trim().to_ascii_lowercase().as_str() {
+        "easy" => BotDifficulty::Easy,
+        "medium" => BotDifficulty::Medium,
+        "hard" => BotDifficulty::Hard,
+        "expert" => BotDifficulty::Expert,
+        other => return Err(format!("unknown difficulty '{}'", other)),
+    };
+    let personality = match personality.trim().to_ascii_lowercase().as_str() {
+        "" | "balanced" => BotPersonality::Balanced,
+        "expander" => BotPersonality::AggressiveExpander,
+        "devcards" => BotPersonality::DevCardRush,
+        "trader" => BotPersonality::PortTrader,
+        "blocker" => BotPersonality::Blocker,
+        other => return Err(format!("unknown personality '{}'", other)),
+    };
+    Ok(SimSeat {
+        difficulty,
+        personality,
+    })
+}
+
+fn parse_options(s: &str) -> Result<GameOptions, String> {
+    let mut options = GameOptions::default();
+    for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+        let number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' needs a number, not '{}'", key, value))
+        };
+        match key {
+            "vp" => options.victory_points_to_win = Some(number()?),
+            "turns" => options.max_turns = Some(number()?),
+            "pirate" => {
+                options.pirate = value
+                    .parse()
+                    .map_err(|_| format!("'pirate' is true or false, not '{}'", value))?
+            }
+            "board" => {
+                options.board_template = match value {
+                    "random" => BoardTemplate::Random,
+                    "beginner" => BoardTemplate::Beginner,
+                    _ => return Err(format!("unknown board '{}'", value)),
+                }
+            }
+            _ => return Err(format!("unknown option '{}'", key)),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if !(2..=6).contains(&args.bots.len()) {
+        bail!("games need 2 to 6 seats, not {}", args.bots.len());
+    }
+    let config = SimulationConfig {
+        seats: args.bots.clone(),
+        games: args.games,
+        first_seed: args.seed,
+        options: args.options.clone(),
+        max_actions: args.max_actions,
+        threads: args.threads,
+        record: args.notation_dir.is_some(),
+    };
+
+    let started = Instant::now();
+    let report = catan_core::simulation::run_simulation(&config);
+    print(&args, &report);
+    println!("Took {:.1?}", started.elapsed());
+
+    if let Some(dir) = &args.notation_dir {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        for game in &report.games {
+            let Some(record) = &game.record else {
+                continue;
+            };
+            let path = dir.join(format!("game-{}.json", game.seed));
+            let json = serde_json::to_string_pretty(record)?;
+            std::fs::write(&path, json).with_context(|| format!("writing {}", path.display()))?;
+        }
+        println!("Wrote {} games to {}", report.games.len(), dir.display());
+    }
+
+    let failures = report.failures().count();
+    if failures > 0 {
+        bail!("{} of {} games broke a rule", failures, report.games.len());
+    }
+    Ok(())
+}
+
+fn print(args: &Args, report: &SimulationReport) {
+    let games = report.games.len();
+    println!(
+        "{} games from seed {}, {} finished, {:.1} turns and {:.1} actions on average",
+        games, args.seed, report.finished, report.average_turns, report.average_actions
+    );
+
+    println!();
+    print!("{:<6}{:<28}{:>7}{:>8}", "Seat", "Bot", "Wins", "Rate");
+    for resource in Resource::ALL {
+        print!("{:>8}", format!("{:?}", resource));
+    }
+    println!();
+    let per_game = |n: u32| n as f64 / games.max(1) as f64;
+    for (seat, bot) in args.bots.iter().enumerate() {
+        let name = format!("{:?} {:?}", bot.difficulty, bot.personality);
+        print!(
+            "{:<6}{:<28}{:>7}{:>7.1}%",
+            seat,
+            name,
+            report.wins[seat],
+            report.win_rate(seat as u8) * 100.0
+        );
+        for resource in Resource::ALL {
+            print!("{:>8.1}", per_game(report.produced[seat].get(resource)));
+        }
+        println!();
+    }
+    println!("(cards collected from rolls, per game)");
+
+    let count = |f: fn(&SimOutcome) -> bool| report.games.iter().filter(|g| f(&g.outcome)).count();
+    let stalled = count(|o| matches!(o, SimOutcome::Stalled { .. }));
+    let limited = count(|o| matches!(o, SimOutcome::ActionLimit));
+    if stalled + limited > 0 {
+        println!();
+        println!(
+            "{} games stalled and {} hit the action limit",
+            stalled, limited
+        );
+    }
+
+    let failures: Vec<_> = report.failures().collect();
+    if !failures.is_empty() {
+        println!();
+        println!("Rule errors:");
+        for game in failures {
+            match &game.outcome {
+                SimOutcome::Rejected { player, error } => println!(
+                    "  seed {}: seat {} was refused after {} actions: {}",
+                    game.seed, player, game.actions, error
+                ),
+                SimOutcome::Panicked { message } => println!(
+                    "  seed {}: panicked after {} actions: {}",
+                    game.seed, game.actions, message
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bots_and_options() {
+        let args = Args::try_parse_from(["catan-sim", "--bots", "hard, medium:blocker,easy"]);
+        let seats = args.unwrap().bots;
+        assert_eq!(seats.len(), 3);
+        assert_eq!(seats[1].difficulty, BotDifficulty::Medium);
+        assert_eq!(seats[1].personality, BotPersonality::Blocker);
+        assert!(parse_seat("silly").is_err());
+        assert!(parse_seat("easy:silly").is_err());
+
+        let options = parse_options("vp=12, turns=80,pirate,board=beginner").unwrap();
+        assert_eq!(options.victory_points_to_win, Some(12));
+        assert_eq!(options.max_turns, Some(80));
+        assert!(options.pirate);
+        assert_eq!(options.board_template, BoardTemplate::Beginner);
+        assert_eq!(parse_options("").unwrap(), GameOptions::default());
+        assert!(parse_options("vp=ten").is_err());
+        assert!(parse_options("fog=1").is_err());
+    }
+}
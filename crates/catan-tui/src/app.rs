@@ -0,0 +1,593 @@
+//! What the screen shows and what the keys do.
+
+use crate::board::{self, resource_name, seat_color, Spot};
+use crate::session::Update;
+use catan_client::RoomInfo;
+use catan_core::{
+    EdgeCoord, GameAction, GamePhase, GameStateJson, HexCoord, PlayerId, Resource, ResourceHand,
+    TradeOffer, VertexCoord, VertexDirection,
+};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+
+/// Log lines kept
+const LOG_LINES: usize = 200;
+
+/// What the keys asked for, for the session to carry out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Quit,
+    Act(GameAction),
+    Start,
+}
+
+/// Cards being picked for a discard or a trade
+#[derive(Debug, Clone, PartialEq)]
+struct HandEditor {
+    kind: EditorKind,
+    give: ResourceHand,
+    take: ResourceHand,
+    /// Which resource the cursor is on
+    row: usize,
+    /// Whether the cursor is on the cards asked for
+    taking: bool,
+    /// Who a trade is offered to, or anyone
+    to: Option<PlayerId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditorKind {
+    /// Give up this many cards
+    Discard(u32),
+    Trade,
+    Counter,
+}
+
+#[derive(Default)]
+pub struct App {
+    state: Option<GameStateJson>,
+    seat: Option<PlayerId>,
+    room: Option<RoomInfo>,
+    actions: Vec<GameAction>,
+    list: ListState,
+    log: Vec<String>,
+    error: Option<String>,
+    editor: Option<HandEditor>,
+    /// Resigning needs a second press
+    resigning: bool,
+}
+
+impl App {
+    pub fn update(&mut self, update: Update) {
+        match update {
+            Update::State(state, seat) => {
+                self.state = Some(*state);
+                self.seat = seat;
+            }
+            Update::Actions(actions) => {
+                if actions != self.actions {
+                    self.list.select((!actions.is_empty()).then_some(0));
+                    self.actions = actions;
+                    self.error = None;
+                }
+            }
+            Update::Room(room) => self.room = Some(*room),
+            Update::Log(line) => {
+                self.log.push(line);
+                if self.log.len() > LOG_LINES {
+                    self.log.remove(0);
+                }
+            }
+            Update::Error(message) => self.error = Some(message),
+        }
+    }
+
+    fn my_hand(&self) -> ResourceHand {
+        self.state
+            .as_ref()
+            .zip(self.seat)
+            .and_then(|(state, seat)| state.players.get(seat as usize))
+            .map(|p| p.resources.clone())
+            .unwrap_or_default()
+    }
+
+    fn name(&self, player: PlayerId) -> String {
+        self.state
+            .as_ref()
+            .and_then(|s| s.players.get(player as usize))
+            .map_or_else(|| format!("Player {}", player + 1), |p| p.name.clone())
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> Option<Command> {
+        if let Some(editor) = &mut self.editor {
+            let done = editor_key(editor, key.code, self.seat)?;
+            self.editor = None;
+            return done.map(Command::Act);
+        }
+        let resigning = std::mem::take(&mut self.resigning);
+        let selected = self
+            .list
+            .selected()
+            .and_then(|i| self.actions.get(i))
+            .cloned();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Command::Quit),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.list.select_previous();
+                None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.list.select_next();
+                None
+            }
+            KeyCode::PageUp => {
+                self.list.scroll_up_by(10);
+                None
+            }
+            KeyCode::PageDown => {
+                self.list.scroll_down_by(10);
+                None
+            }
+            KeyCode::Enter => match selected? {
+                GameAction::DiscardCards(_) => {
+                    let must = self.my_hand().total() / 2;
+                    self.editor = Some(HandEditor::new(EditorKind::Discard(must)));
+                    None
+                }
+                action => Some(Command::Act(action)),
+            },
+            KeyCode::Char('t') if self.actions.contains(&GameAction::EndTurn) => {
+                self.editor = Some(HandEditor::new(EditorKind::Trade));
+                None
+            }
+            KeyCode::Char('c') if self.actions.contains(&GameAction::AcceptTrade) => {
+                self.editor = Some(HandEditor::new(EditorKind::Counter));
+                None
+            }
+            KeyCode::Char('e') if self.state.is_some() => {
+                Some(Command::Act(GameAction::ProposeEndGame))
+            }
+            KeyCode::Char('x') if self.state.is_some() => {
+                if resigning {
+                    return Some(Command::Act(GameAction::Concede));
+                }
+                self.resigning = true;
+                self.error = Some("Press x again to resign".to_string());
+                None
+            }
+            KeyCode::Char('s') if self.state.is_none() => Some(Command::Start),
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, log, help] = Layout::vertical([
+            Constraint::Min(10),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [board_area, side] =
+            Layout::horizontal([Constraint::Min(40), Constraint::Length(48)]).areas(main);
+
+        match &self.state {
+            Some(state) => {
+                let highlight = self
+                    .list
+                    .selected()
+                    .and_then(|i| self.actions.get(i))
+                    .and_then(spot);
+                let lines = board::render(&state.board, highlight);
+                frame.render_widget(
+                    Paragraph::new(lines).block(Block::bordered().title("Board")),
+                    board_area,
+                );
+            }
+            None => frame.render_widget(self.lobby(), board_area),
+        }
+
+        let [players, hand, actions] = Layout::vertical([
+            Constraint::Length(
+                self.state
+                    .as_ref()
+                    .map_or(3, |s| s.players.len() as u16 + 3),
+            ),
+            Constraint::Length(5),
+            Constraint::Min(5),
+        ])
+        .areas(side);
+        if let Some(state) = &self.state {
+            frame.render_widget(self.players(state), players);
+            frame.render_widget(self.hand(state), hand);
+        }
+        match &self.editor {
+            Some(editor) => frame.render_widget(self.editor_view(editor), actions),
+            None => {
+                let items: Vec<ListItem> = self
+                    .actions
+                    .iter()
+                    .map(|a| ListItem::new(describe(a, |p| self.name(p))))
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::bordered().title("Your moves"))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                frame.render_stateful_widget(list, actions, &mut self.list);
+            }
+        }
+
+        let mut lines: Vec<Line> = self
+            .log
+            .iter()
+            .rev()
+            .take(log.height.saturating_sub(2) as usize)
+            .rev()
+            .map(|l| Line::from(l.as_str()))
+            .collect();
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(
+                error.as_str(),
+                Style::default().fg(Color::LightRed),
+            ));
+        }
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title("Log"))
+                .wrap(Wrap { trim: true }),
+            log,
+        );
+        let keys = if self.editor.is_some() {
+            "↑↓ card  ←→ count  tab give/ask  p partner  enter send  esc back"
+        } else {
+            "↑↓ pick  enter play  t trade  c counter  e end game  x resign  s start  q quit"
+        };
+        frame.render_widget(
+            Line::styled(keys, Style::default().fg(Color::DarkGray)),
+            help,
+        );
+    }
+
+    fn lobby(&self) -> Paragraph<'_> {
+        let mut lines = vec![Line::from("Waiting for the game to start")];
+        if let Some(room) = &self.room {
+            lines.push(Line::from(format!("Room {} ({})", room.name, room.id)));
+            lines.push(Line::from(format!(
+                "{} of {} seats taken",
+                room.players.len(),
+                room.max_players
+            )));
+            for (seat, player) in room.players.iter().enumerate() {
+                let what = if player.bot.is_some() { " (bot)" } else { "" };
+                lines.push(Line::styled(
+                    format!("  {}{}", player.name, what),
+                    Style::default().fg(seat_color(seat as PlayerId)),
+                ));
+            }
+            lines.push(Line::from("The host presses s to start"));
+        }
+        Paragraph::new(lines).block(Block::bordered().title("Room"))
+    }
+
+    fn players(&self, state: &GameStateJson) -> Paragraph<'_> {
+        let mut lines = vec![Line::from(format!(
+            "Turn {}  {}  dice {}",
+            state.turn_number,
+            phase_name(&state.phase),
+            state
+                .dice_roll
+                .map_or("-".to_string(), |(a, b)| format!("{}+{}", a, b))
+        ))];
+        for (i, p) in state.players.iter().enumerate() {
+            let seat = i as PlayerId;
+            let turn = if seat == state.current_player {
+                "▶"
+            } else {
+                " "
+            };
+            let mut badges = String::new();
+            if p.has_longest_road {
+                badges.push_str(" road");
+            }
+            if p.has_largest_army {
+                badges.push_str(" army");
+            }
+            if p.conceded {
+                badges.push_str(" resigned");
+            }
+            lines.push(Line::from(vec![
+                Span::raw(turn),
+                Span::styled(
+                    format!("{:<12}", p.name),
+                    Style::default().fg(seat_color(seat)),
+                ),
+                Span::raw(format!(
+                    "{:>2}vp {:>2} cards {:>2} dev {} kn{}",
+                    state.victory_points.get(i).copied().unwrap_or(0),
+                    state.hand_sizes.get(i).copied().unwrap_or(0),
+                    state.dev_card_counts.get(i).copied().unwrap_or(0),
+                    p.played_knights,
+                    badges
+                )),
+            ]));
+        }
+        Paragraph::new(lines).block(Block::bordered().title("Players"))
+    }
+
+    fn hand(&self, state: &GameStateJson) -> Paragraph<'_> {
+        let Some(me) = self.seat.and_then(|s| state.players.get(s as usize)) else {
+            return Paragraph::new("Watching").block(Block::bordered().title("Hand"));
+        };
+        let mut lines = vec![Line::from(hand_text(&me.resources))];
+        let cards: Vec<String> = me.dev_cards.iter().map(|c| format!("{:?}", c)).collect();
+        if !cards.is_empty() {
+            lines.push(Line::from(cards.join(", ")));
+        }
+        if let Some(trade) = &state.pending_trade {
+            lines.push(Line::from(format!(
+                "{} offers {}",
+                self.name(trade.offer.from),
+                trade_text(&trade.offer)
+            )));
+        }
+        Paragraph::new(lines)
+            .block(Block::bordered().title("Hand"))
+            .wrap(Wrap { trim: true })
+    }
+
+    fn editor_view(&self, editor: &HandEditor) -> Paragraph<'_> {
+        let title = match editor.kind {
+            EditorKind::Discard(n) => format!("Discard {} cards", n),
+            EditorKind::Trade => "Offer a trade".to_string(),
+            EditorKind::Counter => "Counter the offer".to_string(),
+        };
+        let mut lines = vec![Line::from("          give  ask")];
+        for (row, resource) in Resource::ALL.into_iter().enumerate() {
+            let cell = |taking: bool, count: u32| {
+                let style = if row == editor.row && taking == editor.taking {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!("{:>4}", count), style)
+            };
+            let mut spans = vec![
+                Span::raw(format!("{:<8}", resource_name(resource))),
+                cell(false, editor.give.get(resource)),
+            ];
+            if !matches!(editor.kind, EditorKind::Discard(_)) {
+                spans.push(Span::raw("  "));
+                spans.push(cell(true, editor.take.get(resource)));
+            }
+            lines.push(Line::from(spans));
+        }
+        if matches!(editor.kind, EditorKind::Trade) {
+            let to = editor.to.map_or("anyone".to_string(), |p| self.name(p));
+            lines.push(Line::from(format!("To: {}", to)));
+        }
+        Paragraph::new(lines).block(Block::bordered().title(title))
+    }
+}
+
+impl HandEditor {
+    fn new(kind: EditorKind) -> Self {
+        Self {
+            kind,
+            give: ResourceHand::new(),
+            take: ResourceHand::new(),
+            row: 0,
+            taking: false,
+            to: None,
+        }
+    }
+}
+
+/// Handle a key in the card picker. Returns None to stay in it, or the
+/// move to make (if any) once it closes.
+fn editor_key(
+    editor: &mut HandEditor,
+    key: KeyCode,
+    seat: Option<PlayerId>,
+) -> Option<Option<GameAction>> {
+    let trading = !matches!(editor.kind, EditorKind::Discard(_));
+    let resource = Resource::ALL[editor.row];
+    let hand = if editor.taking {
+        &mut editor.take
+    } else {
+        &mut editor.give
+    };
+    match key {
+        KeyCode::Esc => return Some(None),
+        KeyCode::Up | KeyCode::Char('k') => editor.row = editor.row.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            editor.row = (editor.row + 1).min(Resource::ALL.len() - 1)
+        }
+        KeyCode::Right | KeyCode::Char('+') | KeyCode::Char('l') => hand.add(resource, 1),
+        KeyCode::Left | KeyCode::Char('-') | KeyCode::Char('h') => {
+            hand.set(resource, hand.get(resource).saturating_sub(1))
+        }
+        KeyCode::Tab if trading => editor.taking = !editor.taking,
+        KeyCode::Char('p') if editor.kind == EditorKind::Trade => {
+            // Anyone, then each seat but ours in turn
+            loop {
+                editor.to = match editor.to {
+                    None => Some(0),
+                    Some(p) if p >= 5 => None,
+                    Some(p) => Some(p + 1),
+                };
+                if editor.to.is_none() || editor.to != seat {
+                    break;
+                }
+            }
+        }
+        KeyCode::Enter => {
+            let offer = || TradeOffer {
+                from: seat.unwrap_or(0),
+                to: editor.to,
+                offering: editor.give.clone(),
+                requesting: editor.take.clone(),
+            };
+            return Some(Some(match editor.kind {
+                EditorKind::Discard(_) => GameAction::DiscardCards(editor.give.clone()),
+                EditorKind::Trade => GameAction::ProposeTrade(offer()),
+                EditorKind::Counter => GameAction::CounterTrade(offer()),
+            }));
+        }
+        _ => {}
+    }
+    None
+}
+
+fn phase_name(phase: &GamePhase) -> &'static str {
+    match phase {
+        GamePhase::Setup { .. } => "setup",
+        GamePhase::PreRoll => "roll",
+        GamePhase::RobberMoveRequired => "move robber",
+        GamePhase::RobberSteal { .. } => "steal",
+        GamePhase::DiscardRequired { .. } => "discard",
+        GamePhase::MainPhase => "build and trade",
+        GamePhase::RoadBuildingInProgress { .. } => "road building",
+        GamePhase::AwaitingNextPlayer { .. } => "handoff",
+        GamePhase::EndGameVote { .. } => "vote to end",
+        GamePhase::Finished { .. } => "game over",
+    }
+}
+
+fn hex_text(hex: &HexCoord) -> String {
+    format!("({},{})", hex.q, hex.r)
+}
+
+fn vertex_text(vertex: &VertexCoord) -> String {
+    let pole = match vertex.direction {
+        VertexDirection::North => "N",
+        VertexDirection::South => "S",
+    };
+    format!("{} {}", hex_text(&vertex.hex), pole)
+}
+
+fn edge_text(edge: &EdgeCoord) -> String {
+    format!("{} {:?}", hex_text(&edge.hex), edge.direction)
+}
+
+fn hand_text(hand: &ResourceHand) -> String {
+    let parts: Vec<String> = Resource::ALL
+        .into_iter()
+        .filter(|r| hand.get(*r) > 0)
+        .map(|r| format!("{} {}", hand.get(r), resource_name(r)))
+        .collect();
+    if parts.is_empty() {
+        "nothing".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn trade_text(offer: &TradeOffer) -> String {
+    format!(
+        "{} for {}",
+        hand_text(&offer.offering),
+        hand_text(&offer.requesting)
+    )
+}
+
+/// A move, in words
+pub fn describe(action: &GameAction, name: impl Fn(PlayerId) -> String) -> String {
+    match action {
+        GameAction::PlaceInitialSettlement(v) => {
+            format!("Place a settlement at {}", vertex_text(v))
+        }
+        GameAction::PlaceInitialRoad(e) => format!("Place a road at {}", edge_text(e)),
+        GameAction::RollDice => "Roll the dice".to_string(),
+        GameAction::MoveRobber(h) => format!("Move the robber to {}", hex_text(h)),
+        GameAction::MovePirate(h) => format!("Move the pirate to {}", hex_text(h)),
+        GameAction::StealFrom(p) => format!("Steal from {}", name(*p)),
+        GameAction::DiscardCards(_) => "Choose cards to discard".to_string(),
+        GameAction::BuildRoad(e) => format!("Build a road at {}", edge_text(e)),
+        GameAction::BuildSettlement(v) => format!("Build a settlement at {}", vertex_text(v)),
+        GameAction::BuildCity(v) => format!("Build a city at {}", vertex_text(v)),
+        GameAction::BuyDevelopmentCard => "Buy a development card".to_string(),
+        GameAction::BuildCityImprovement(track) => format!("Improve your cities in {:?}", track),
+        GameAction::PlayKnight => "Play a knight".to_string(),
+        GameAction::PlayRoadBuilding(a, b) => {
+            format!("Road building: {} and {}", edge_text(a), edge_text(b))
+        }
+        GameAction::PlayYearOfPlenty(a, b) => {
+            format!(
+                "Year of plenty: {} and {}",
+                resource_name(*a),
+                resource_name(*b)
+            )
+        }
+        GameAction::PlayMonopoly(r) => format!("Monopoly on {}", resource_name(*r)),
+        GameAction::ProposeTrade(offer) => format!("Offer {}", trade_text(offer)),
+        GameAction::CounterTrade(offer) => format!("Counter with {}", trade_text(offer)),
+        GameAction::AcceptTrade => "Accept the trade".to_string(),
+        GameAction::RejectTrade => "Turn the trade down".to_string(),
+        GameAction::CancelTrade => "Withdraw the trade".to_string(),
+        GameAction::MaritimeTrade {
+            give,
+            give_count,
+            receive,
+        } => format!(
+            "Trade {} {} for 1 {}",
+            give_count,
+            resource_name(*give),
+            resource_name(*receive)
+        ),
+        GameAction::EndTurn => "End the turn".to_string(),
+        GameAction::ConfirmNextPlayer => "Take the turn".to_string(),
+        GameAction::ProposeEndGame => "Propose ending the game".to_string(),
+        GameAction::VoteEndGame(true) => "Agree to end the game".to_string(),
+        GameAction::VoteEndGame(false) => "Play on".to_string(),
+        GameAction::Concede => "Resign".to_string(),
+    }
+}
+
+/// Where on the board a move happens, if anywhere
+pub fn spot(action: &GameAction) -> Option<Spot> {
+    match action {
+        GameAction::PlaceInitialSettlement(v)
+        | GameAction::BuildSettlement(v)
+        | GameAction::BuildCity(v) => Some(Spot::Vertex(*v)),
+        GameAction::PlaceInitialRoad(e)
+        | GameAction::BuildRoad(e)
+        | GameAction::PlayRoadBuilding(e, _) => Some(Spot::Edge(*e)),
+        GameAction::MoveRobber(h) | GameAction::MovePirate(h) => Some(Spot::Hex(*h)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Command> {
+        app.on_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_trade_editor_builds_an_offer() {
+        let mut app = App::default();
+        app.update(Update::Actions(vec![GameAction::EndTurn]));
+        assert_eq!(press(&mut app, KeyCode::Char('c')), None);
+        assert!(app.editor.is_none());
+
+        press(&mut app, KeyCode::Char('t'));
+        for code in [KeyCode::Right, KeyCode::Right, KeyCode::Tab, KeyCode::Down] {
+            assert_eq!(press(&mut app, code), None);
+        }
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Char('p'));
+        let Some(Command::Act(GameAction::ProposeTrade(offer))) = press(&mut app, KeyCode::Enter)
+        else {
+            panic!("expected a trade offer");
+        };
+        assert_eq!(offer.offering.get(Resource::ALL[0]), 2);
+        assert_eq!(offer.requesting.get(Resource::ALL[1]), 1);
+        assert_eq!(offer.to, Some(0));
+        assert!(app.editor.is_none());
+    }
+}
@@ -0,0 +1,299 @@
+//! The board drawn in text.
+//!
+//! Hexes are pointy-topped and laid out on a grid of characters, each one
+//! five rows tall and nine columns wide, overlapping its neighbours:
+//!
+//! ```text
+//!   / · \
+//! ·       ·
+//! |Lum  8 |
+//! · \   / ·
+//!     ·
+//! ```
+//!
+//! A corner or side belongs to up to three hexes, but every hex puts it on
+//! the same cell, so buildings are drawn once wherever they are.
+
+use catan_core::board::BoardJson;
+use catan_core::{
+    EdgeBuilding, EdgeCoord, EdgeDirection, Harbor, HexCoord, PlayerId, Resource, TileType,
+    VertexBuilding, VertexCoord, VertexDirection,
+};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// A place on the board an action points at, to pick out when drawing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spot {
+    Hex(HexCoord),
+    Vertex(VertexCoord),
+    Edge(EdgeCoord),
+}
+
+/// The color each seat plays in
+pub fn seat_color(player: PlayerId) -> Color {
+    match player {
+        0 => Color::Red,
+        1 => Color::Blue,
+        2 => Color::Yellow,
+        3 => Color::White,
+        4 => Color::Green,
+        _ => Color::Magenta,
+    }
+}
+
+/// Short name of a resource
+pub fn resource_name(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Brick => "Brick",
+        Resource::Lumber => "Lumber",
+        Resource::Ore => "Ore",
+        Resource::Grain => "Grain",
+        Resource::Wool => "Wool",
+    }
+}
+
+fn resource_color(resource: Resource) -> Color {
+    match resource {
+        Resource::Brick => Color::LightRed,
+        Resource::Lumber => Color::Green,
+        Resource::Ore => Color::Gray,
+        Resource::Grain => Color::LightYellow,
+        Resource::Wool => Color::LightGreen,
+    }
+}
+
+/// Where the middle of a hex goes, in columns and rows
+fn center(hex: HexCoord) -> (i32, i32) {
+    (8 * hex.q + 4 * hex.r, 3 * hex.r)
+}
+
+fn vertex_cell(vertex: VertexCoord) -> (i32, i32) {
+    let (x, y) = center(vertex.hex);
+    match vertex.direction {
+        VertexDirection::North => (x, y - 2),
+        VertexDirection::South => (x, y + 2),
+    }
+}
+
+/// A side sits halfway between its corners, rounding up the screen
+fn edge_cell(edge: EdgeCoord) -> (i32, i32, char) {
+    let (x, y) = center(edge.hex);
+    match edge.direction {
+        EdgeDirection::NorthEast => (x + 2, y - 2, '\\'),
+        EdgeDirection::East => (x + 4, y, '|'),
+        EdgeDirection::SouthEast => (x + 2, y + 1, '/'),
+        EdgeDirection::SouthWest => (x - 2, y + 1, '\\'),
+        EdgeDirection::West => (x - 4, y, '|'),
+        EdgeDirection::NorthWest => (x - 2, y - 2, '/'),
+    }
+}
+
+/// A grid of styled characters
+struct Canvas {
+    left: i32,
+    top: i32,
+    width: usize,
+    cells: Vec<Vec<(char, Style)>>,
+}
+
+impl Canvas {
+    fn put(&mut self, x: i32, y: i32, c: char, style: Style) {
+        let (col, row) = ((x - self.left) as usize, (y - self.top) as usize);
+        if let Some(cell) = self.cells.get_mut(row).and_then(|r| r.get_mut(col)) {
+            *cell = (c, style);
+        }
+    }
+
+    fn text(&mut self, x: i32, y: i32, text: &str, style: Style) {
+        for (i, c) in text.chars().enumerate() {
+            self.put(x + i as i32, y, c, style);
+        }
+    }
+
+    fn into_lines(self) -> Vec<Line<'static>> {
+        self.cells
+            .into_iter()
+            .map(|row| {
+                let spans: Vec<Span> = row
+                    .into_iter()
+                    .map(|(c, style)| Span::styled(c.to_string(), style))
+                    .collect();
+                debug_assert!(spans.len() == self.width);
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// Draw `board`, picking out `highlight`
+pub fn render(board: &BoardJson, highlight: Option<Spot>) -> Vec<Line<'static>> {
+    let centers: Vec<(i32, i32)> = board
+        .tiles
+        .iter()
+        .map(|t| center(HexCoord::new(t.q, t.r)))
+        .collect();
+    let left = centers.iter().map(|c| c.0).min().unwrap_or(0) - 4;
+    let right = centers.iter().map(|c| c.0).max().unwrap_or(0) + 4;
+    let top = centers.iter().map(|c| c.1).min().unwrap_or(0) - 2;
+    let bottom = centers.iter().map(|c| c.1).max().unwrap_or(0) + 2;
+    let width = (right - left + 1) as usize;
+    let mut canvas = Canvas {
+        left,
+        top,
+        width,
+        cells: vec![vec![(' ', Style::default()); width]; (bottom - top + 1) as usize],
+    };
+    let picked = Style::default()
+        .fg(Color::Black)
+        .bg(Color::LightCyan)
+        .add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    for tile in &board.tiles {
+        let hex = HexCoord::new(tile.q, tile.r);
+        let (x, y) = center(hex);
+        let (name, style) = match tile.tile_type {
+            TileType::Resource(r) => (
+                &resource_name(r)[..3],
+                Style::default().fg(resource_color(r)),
+            ),
+            TileType::Desert => ("Des", Style::default().fg(Color::Yellow)),
+            TileType::Ocean => ("~~~", Style::default().fg(Color::Blue)),
+        };
+        let style = if highlight == Some(Spot::Hex(hex)) {
+            picked
+        } else {
+            style
+        };
+        canvas.text(x - 3, y, name, style);
+        if let Some(number) = tile.dice_number {
+            let hot = if matches!(number, 6 | 8) {
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            canvas.text(x + 1, y, &format!("{:>2}", number), hot);
+        }
+        if tile.has_robber {
+            canvas.text(
+                x - 1,
+                y + 1,
+                "R",
+                Style::default().fg(Color::Black).bg(Color::Gray),
+            );
+        }
+        if board.pirate == Some(hex) {
+            canvas.text(
+                x - 1,
+                y + 1,
+                "P",
+                Style::default().fg(Color::Black).bg(Color::Gray),
+            );
+        }
+    }
+
+    for harbor in &board.harbors {
+        // The label goes in the sea beside the dock
+        let sea = harbor.edge.touching_hexes().into_iter().find(|h| {
+            board
+                .tiles
+                .iter()
+                .any(|t| t.q == h.q && t.r == h.r && t.tile_type == TileType::Ocean)
+        });
+        if let Some(sea) = sea {
+            let (x, y) = center(sea);
+            let label = match harbor.harbor_type {
+                Harbor::Generic => "3:1".to_string(),
+                Harbor::Specific(r) => format!("2:{}", &resource_name(r)[..2]),
+            };
+            canvas.text(x - 2, y - 1, &label, Style::default().fg(Color::Cyan));
+        }
+    }
+
+    for edge in &board.edges {
+        let coord = EdgeCoord::new(HexCoord::new(edge.hex_q, edge.hex_r), edge.direction);
+        let (x, y, c) = edge_cell(coord);
+        let style = match edge.building {
+            EdgeBuilding::Road(p) => Style::default()
+                .fg(seat_color(p))
+                .add_modifier(Modifier::BOLD),
+            EdgeBuilding::Empty => dim,
+        };
+        let style = if highlight == Some(Spot::Edge(coord)) {
+            picked
+        } else {
+            style
+        };
+        canvas.put(x, y, c, style);
+    }
+
+    for vertex in &board.vertices {
+        let coord = VertexCoord::new(HexCoord::new(vertex.hex_q, vertex.hex_r), vertex.direction);
+        let (x, y) = vertex_cell(coord);
+        let (c, style) = match vertex.building {
+            VertexBuilding::Settlement(p) => ('s', Style::default().fg(seat_color(p))),
+            VertexBuilding::City(p) => ('C', Style::default().fg(seat_color(p))),
+            VertexBuilding::Empty => ('·', dim),
+        };
+        let style = if highlight == Some(Spot::Vertex(coord)) {
+            picked
+        } else {
+            style.add_modifier(Modifier::BOLD)
+        };
+        canvas.put(x, y, c, style);
+    }
+
+    canvas.into_lines()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catan_core::{GameState, HexCorner};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_shared_corners_and_sides_land_on_one_cell() {
+        let game = GameState::new(3, vec!["A".into(), "B".into(), "C".into()]);
+        let board = game.board.to_json_friendly();
+
+        // Every way of naming a corner or side puts it on the same cell,
+        // and no two of them share one
+        let mut cells = HashMap::new();
+        for tile in board
+            .tiles
+            .iter()
+            .filter(|t| t.tile_type != TileType::Ocean)
+        {
+            let hex = HexCoord::new(tile.q, tile.r);
+            for corner in HexCorner::ALL {
+                let vertex = VertexCoord::at_corner(hex, corner);
+                let cell = vertex_cell(vertex);
+                assert_eq!(
+                    *cells.entry(cell).or_insert(Spot::Vertex(vertex)),
+                    Spot::Vertex(vertex)
+                );
+            }
+            for direction in EdgeDirection::ALL {
+                let edge = EdgeCoord::new(hex, direction);
+                let (x, y, c) = edge_cell(edge);
+                assert_eq!(
+                    *cells.entry((x, y)).or_insert(Spot::Edge(edge)),
+                    Spot::Edge(edge)
+                );
+                assert!("/\\|".contains(c));
+            }
+        }
+
+        let lines = render(&board, None);
+        let text: String = lines
+            .iter()
+            .flat_map(|l| l.spans.iter().map(|s| s.content.to_string()))
+            .collect();
+        assert_eq!(text.matches('·').count(), board.vertices.len());
+        assert!(text.contains("Des"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Play Kopiatan in the terminal, against bots on this machine or with
+//! others on a server.
+
+mod app;
+mod board;
+mod session;
+
+use app::{App, Command};
+use catan_core::BotDifficulty;
+use clap::{Parser, Subcommand};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use session::{Local, Online, Session};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(name = "catan-tui", about = "Play Kopiatan in the terminal")]
+struct Args {
+    #[command(subcommand)]
+    mode: Mode,
+}
+
+#[derive(Subcommand, Debug)]
+enum Mode {
+    /// Play against bots on this machine
+    Local {
+        #[arg(long, default_value = "You")]
+        name: String,
+        /// How many bots to play against
+        #[arg(long, default_value_t = 3)]
+        bots: u8,
+        #[arg(long, default_value = "medium", value_parser = parse_difficulty)]
+        difficulty: BotDifficulty,
+        /// Pause before each bot move, in milliseconds
+        #[arg(long, default_value_t = 600)]
+        delay_ms: u64,
+    },
+    /// Play on a server, opening a room or joining one
+    Online {
+        #[arg(long, default_value = "ws://127.0.0.1:8080")]
+        url: String,
+        #[arg(long, default_value = "You")]
+        name: String,
+        /// Join this room instead of opening one
+        #[arg(long)]
+        room: Option<Uuid>,
+        /// Seats in a room we open
+        #[arg(long, default_value_t = 4)]
+        seats: u8,
+        /// Bots to seat in a room we open
+        #[arg(long, default_value_t = 0)]
+        bots: u8,
+        #[arg(long, default_value = "medium", value_parser = parse_difficulty)]
+        difficulty: BotDifficulty,
+    },
+}
+
+fn parse_difficulty(s: &str) -> Result<BotDifficulty, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "easy" => Ok(BotDifficulty::Easy),
+        "medium" => Ok(BotDifficulty::Medium),
+        "hard" => Ok(BotDifficulty::Hard),
+        "expert" => Ok(BotDifficulty::Expert),
+        _ => Err(format!("unknown difficulty '{}'", s)),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let session = match args.mode {
+        Mode::Local {
+            name,
+            bots,
+            difficulty,
+            delay_ms,
+        } => Session::Local(Box::new(Local::new(
+            name,
+            bots.clamp(1, 5),
+            difficulty,
+            Duration::from_millis(delay_ms),
+        ))),
+        Mode::Online {
+            url,
+            name,
+            room,
+            seats,
+            bots,
+            difficulty,
+        } => Session::Online(Box::new(
+            Online::connect(&url, &name, room, seats, bots, difficulty).await?,
+        )),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, session).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(terminal: &mut ratatui::DefaultTerminal, mut session: Session) -> anyhow::Result<()> {
+    // Reading keys blocks, so it gets a thread of its own
+    let (keys, mut pressed) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if keys.send(key).is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    });
+
+    let mut app = App::default();
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        tokio::select! {
+            key = pressed.recv() => {
+                let Some(key) = key else {
+                    return Ok(());
+                };
+                match app.on_key(key) {
+                    Some(Command::Quit) => return Ok(()),
+                    Some(Command::Act(action)) => session.act(action),
+                    Some(Command::Start) => session.start(),
+                    None => {}
+                }
+            }
+            update = session.next() => app.update(update),
+        }
+    }
+}
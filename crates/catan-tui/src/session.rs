@@ -0,0 +1,246 @@
+//! Where the game being shown is played: here against bots, or on a server.
+
+use catan_client::{Client, ClientMessage, RoomInfo, ServerMessage};
+use catan_core::{
+    Bot, BotDifficulty, BotPersonality, BotPlayer, GameAction, GameState, GameStateJson, PlayerId,
+};
+use std::collections::VecDeque;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Something for the screen to show
+#[derive(Debug)]
+pub enum Update {
+    /// The game as it stands, and the seat we play
+    State(Box<GameStateJson>, Option<PlayerId>),
+    /// What we may do now
+    Actions(Vec<GameAction>),
+    /// Who is in the room, before the game starts
+    Room(Box<RoomInfo>),
+    Log(String),
+    Error(String),
+}
+
+pub enum Session {
+    Local(Box<Local>),
+    Online(Box<Online>),
+}
+
+impl Session {
+    /// The next update, waiting for bots or the server if need be
+    pub async fn next(&mut self) -> Update {
+        match self {
+            Session::Local(local) => local.next().await,
+            Session::Online(online) => online.next().await,
+        }
+    }
+
+    pub fn act(&mut self, action: GameAction) {
+        match self {
+            Session::Local(local) => local.act(action),
+            Session::Online(online) => online.send(ClientMessage::GameAction {
+                action,
+                request_id: None,
+            }),
+        }
+    }
+
+    /// Start the game, for a host waiting in the room
+    pub fn start(&mut self) {
+        if let Session::Online(online) = self {
+            online.send(ClientMessage::StartGame);
+        }
+    }
+}
+
+/// A game on this machine, with us in the first seat and bots in the rest
+pub struct Local {
+    game: GameState,
+    bots: Vec<Bot>,
+    /// Pause before each bot move, so they can be followed
+    delay: Duration,
+    queue: VecDeque<Update>,
+}
+
+/// The seat we play in a local game
+const SEAT: PlayerId = 0;
+
+impl Local {
+    pub fn new(name: String, bots: u8, difficulty: BotDifficulty, delay: Duration) -> Self {
+        let mut names = vec![name];
+        names.extend((1..=bots).map(|i| format!("Bot {}", i)));
+        let game = GameState::new(bots + 1, names);
+        let bots = (1..=bots).map(|seat| Bot::new(seat, difficulty)).collect();
+        let mut local = Self {
+            game,
+            bots,
+            delay,
+            queue: VecDeque::new(),
+        };
+        local.show();
+        local
+    }
+
+    fn show(&mut self) {
+        let state = Box::new(self.game.to_json_friendly());
+        self.queue.push_back(Update::State(state, Some(SEAT)));
+        let actions = self.game.valid_actions(SEAT);
+        self.queue.push_back(Update::Actions(actions));
+    }
+
+    fn apply(&mut self, player: PlayerId, action: GameAction) {
+        match self.game.apply_action(player, action) {
+            Ok(events) => {
+                for event in events {
+                    self.queue.push_back(Update::Log(format!("{:?}", event)));
+                }
+                self.show();
+            }
+            Err(e) => self.queue.push_back(Update::Error(e.to_string())),
+        }
+    }
+
+    fn act(&mut self, action: GameAction) {
+        self.apply(SEAT, action);
+    }
+
+    async fn next(&mut self) -> Update {
+        loop {
+            if let Some(update) = self.queue.pop_front() {
+                return update;
+            }
+            // Any bot with something to do, in or out of turn, goes next
+            let waiting = self
+                .bots
+                .iter()
+                .position(|bot| !self.game.valid_actions(bot.player_id()).is_empty());
+            let Some(index) = waiting else {
+                return std::future::pending().await;
+            };
+            tokio::time::sleep(self.delay).await;
+            let bot = &mut self.bots[index];
+            let seat = bot.player_id();
+            match bot.choose_action(&self.game) {
+                Some(action) => self.apply(seat, action),
+                // A bot that can't decide gives up its seat rather than
+                // hold the game up
+                None => self.apply(seat, GameAction::Concede),
+            }
+        }
+    }
+}
+
+/// A game on a server
+pub struct Online {
+    client: Client,
+    /// The room as last heard, whose order of players is the seat order
+    room: Option<RoomInfo>,
+    queue: VecDeque<Update>,
+    closed: bool,
+}
+
+impl Online {
+    /// Open a room for `seats` players, filling `bots` of them, or join
+    /// `room_id` if given
+    pub async fn connect(
+        url: &str,
+        name: &str,
+        room_id: Option<Uuid>,
+        seats: u8,
+        bots: u8,
+        difficulty: BotDifficulty,
+    ) -> anyhow::Result<Self> {
+        let mut client = Client::connect(url, "catan-tui").await?;
+        let mut room = None;
+        match room_id {
+            Some(room_id) => room = Some(client.join(room_id, name).await?),
+            None => {
+                client.create_room(name, seats, Default::default()).await?;
+                for _ in 0..bots {
+                    let personality = BotPersonality::default();
+                    room = Some(client.add_bot(difficulty, personality).await?);
+                }
+            }
+        }
+        let queue = room
+            .iter()
+            .map(|r| Update::Room(Box::new(r.clone())))
+            .collect();
+        Ok(Self {
+            client,
+            room,
+            queue,
+            closed: false,
+        })
+    }
+
+    fn send(&mut self, msg: ClientMessage) {
+        if self.client.send(msg).is_err() {
+            self.queue
+                .push_back(Update::Error("Lost the connection".to_string()));
+        }
+    }
+
+    fn seat(&self) -> Option<PlayerId> {
+        let me = self.client.player_id();
+        let room = self.room.as_ref()?;
+        room.players
+            .iter()
+            .position(|p| p.id == me)
+            .map(|i| i as PlayerId)
+    }
+
+    async fn next(&mut self) -> Update {
+        loop {
+            if let Some(update) = self.queue.pop_front() {
+                return update;
+            }
+            if self.closed {
+                return std::future::pending().await;
+            }
+            let msg = match self.client.next_event().await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    self.closed = true;
+                    return Update::Error(e.to_string());
+                }
+            };
+            let update = match msg {
+                ServerMessage::JoinedRoom { room, .. } | ServerMessage::RoomUpdated { room } => {
+                    self.room = Some(room.clone());
+                    Update::Room(Box::new(room))
+                }
+                ServerMessage::GameStarted { state, .. }
+                | ServerMessage::GameState { state, .. } => Update::State(state, self.seat()),
+                ServerMessage::ValidActions { actions } => Update::Actions(actions),
+                ServerMessage::ActionResult {
+                    success: false,
+                    error,
+                    ..
+                } => Update::Error(error.unwrap_or_else(|| "Move rejected".to_string())),
+                ServerMessage::GameEvents { events } => {
+                    self.queue.extend(
+                        events
+                            .into_iter()
+                            .map(|e| Update::Log(format!("{:?}", e.event))),
+                    );
+                    continue;
+                }
+                ServerMessage::ChatMessage {
+                    player_name,
+                    message,
+                    ..
+                } => Update::Log(format!("<{}> {}", player_name, message)),
+                ServerMessage::GameOver { winner_name, .. } => Update::Log(match winner_name {
+                    Some(name) => format!("Game over: {} wins", name),
+                    None => "Game over".to_string(),
+                }),
+                ServerMessage::Error { message, .. } => Update::Error(message),
+                ServerMessage::Kicked => Update::Error("Kicked from the room".to_string()),
+                ServerMessage::RoomClosed { reason } => Update::Error(reason),
+                _ => continue,
+            };
+            return update;
+        }
+    }
+}
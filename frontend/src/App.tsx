@@ -7,13 +7,23 @@ import { StatsPanel } from "./components/StatsPanel";
 import { MultiplayerBoard } from "./components/MultiplayerBoard";
 import { MultiplayerHUD } from "./components/MultiplayerHUD";
 import { SoundControl } from "./components/SoundControl";
-import { gameStore, initializeGame, isFinished, getWinner, getVictoryPoints } from "./stores/gameStore";
+import {
+  gameStore,
+  initializeGame,
+  isFinished,
+  getWinner,
+  getVictoryPoints,
+  analyzeGame,
+} from "./stores/gameStore";
 import {
   multiplayerStore,
   setEventHandlers,
   sendGameAction,
   isMyTurn,
   getMyPlayerIndex,
+  isHost,
+  pauseGame,
+  resumeGame,
 } from "./stores/multiplayerStore";
 import { SoundManager } from "./utils/SoundManager";
 import "./App.css";
@@ -50,7 +60,7 @@ function App() {
     },
     onGameOver: (_winner, winnerName) => {
       SoundManager.play('victory');
-      console.log(`Game over! Winner: ${winnerName}`);
+      console.log(winnerName ? `Game over! Winner: ${winnerName}` : "Game over! Ended by agreement");
     },
   });
 
@@ -233,6 +243,19 @@ function App() {
                 <h2>🎉 Game Over!</h2>
                 <p>Winner: {gameStore.state?.players[getWinner()!]?.name}</p>
                 <p>Victory Points: {getVictoryPoints(getWinner()!)}</p>
+                <Show when={analyzeGame()}>
+                  {(analysis) => (
+                    <div class="game-analysis">
+                      <h4>Accuracy</h4>
+                      {analysis().players.map((p) => (
+                        <p>
+                          {gameStore.state?.players[p.player]?.name}: {p.accuracy.toFixed(1)}%
+                          ({p.blunders} blunders)
+                        </p>
+                      ))}
+                    </div>
+                  )}
+                </Show>
                 <button onClick={() => setMode("menu")}>Back to Menu</button>
               </div>
             </Show>
@@ -295,9 +318,27 @@ function App() {
               currentPlayer={multiplayerStore.currentPlayer}
               isMyTurn={isMyTurn()}
               myPlayerIndex={getMyPlayerIndex()}
+              timer={multiplayerStore.timer}
               onAction={handleMultiplayerAction}
             />
 
+            <Show when={multiplayerStore.room?.status === "InGame"}>
+              <div class="pause-panel">
+                <Show when={multiplayerStore.room?.paused}>
+                  <p class="pause-notice">⏸ Game paused</p>
+                </Show>
+                <Show when={isHost()}>
+                  <button
+                    onClick={() =>
+                      multiplayerStore.room?.paused ? resumeGame() : pauseGame()
+                    }
+                  >
+                    {multiplayerStore.room?.paused ? "Resume" : "Pause"}
+                  </button>
+                </Show>
+              </div>
+            </Show>
+
             <Show when={multiplayerStore.room?.status === "Finished"}>
               <div class="game-over">
                 <h2>🎉 Game Over!</h2>
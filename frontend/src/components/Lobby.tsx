@@ -1,4 +1,4 @@
-import { Show, For, createSignal, onMount, onCleanup } from "solid-js";
+import { Show, For, createSignal, createEffect, onMount, onCleanup } from "solid-js";
 import type { Component } from "solid-js";
 import {
   multiplayerStore,
@@ -8,11 +8,21 @@ import {
   joinRoom,
   leaveRoom,
   startGame,
+  addBot,
+  updateRoomSettings,
+  kickPlayer,
+  transferHost,
   listRooms,
+  getLeaderboard,
+  getPlayerStats,
   sendChat,
   isHost,
   canStartGame,
 } from "../stores/multiplayerStore";
+import type { BotDifficulty, RoomSettings } from "../stores/multiplayerStore";
+
+// Seconds for discards, robber moves and votes in a timed game
+const DECISION_SECS = 30;
 
 interface LobbyProps {
   onGameStart: () => void;
@@ -21,12 +31,23 @@ interface LobbyProps {
 export const Lobby: Component<LobbyProps> = (_props) => {
   const [playerName, setPlayerName] = createSignal("Player");
   const [maxPlayers, setMaxPlayers] = createSignal(4);
+  // Seconds per turn; 0 plays without a time limit
+  const [turnSecs, setTurnSecs] = createSignal(0);
   const [serverUrl, setServerUrl] = createSignal("ws://localhost:8080");
   const [chatInput, setChatInput] = createSignal("");
+  const [botDifficulty, setBotDifficulty] = createSignal<BotDifficulty>("Medium");
   const [view, setView] = createSignal<"connect" | "browse" | "room">("connect");
 
   let refreshInterval: number | undefined;
 
+  // Back to the room list when a room is lost: kicked, or it couldn't be joined
+  createEffect(() => {
+    if (view() === "room" && !multiplayerStore.room && multiplayerStore.error) {
+      setView("browse");
+      listRooms();
+    }
+  });
+
   onMount(() => {
     // Refresh room list periodically when browsing
     refreshInterval = setInterval(() => {
@@ -47,6 +68,7 @@ export const Lobby: Component<LobbyProps> = (_props) => {
       await connect(serverUrl());
       setView("browse");
       listRooms();
+      getLeaderboard();
     } catch (e) {
       console.error("Failed to connect:", e);
     }
@@ -58,10 +80,25 @@ export const Lobby: Component<LobbyProps> = (_props) => {
   }
 
   function handleCreateRoom() {
-    createRoom(playerName(), maxPlayers());
+    const timed = turnSecs() > 0;
+    createRoom(playerName(), maxPlayers(), {
+      timers: {
+        turn_secs: timed ? turnSecs() : null,
+        decision_secs: timed ? DECISION_SECS : null,
+      },
+    });
     setView("room");
   }
 
+  // Send the room's settings with one change made (host only)
+  function changeSettings(change: (settings: RoomSettings) => void) {
+    const current = multiplayerStore.room?.settings;
+    if (!current) return;
+    const settings: RoomSettings = JSON.parse(JSON.stringify(current));
+    change(settings);
+    updateRoomSettings(settings);
+  }
+
   function handleJoinRoom(roomId: string) {
     joinRoom(roomId, playerName());
     setView("room");
@@ -142,6 +179,15 @@ export const Lobby: Component<LobbyProps> = (_props) => {
                 <option value={3}>3 Players</option>
                 <option value={4}>4 Players</option>
               </select>
+              <select
+                value={turnSecs()}
+                onChange={(e) => setTurnSecs(parseInt(e.target.value))}
+              >
+                <option value={0}>No turn timer</option>
+                <option value={60}>60s turns</option>
+                <option value={90}>90s turns</option>
+                <option value={120}>120s turns</option>
+              </select>
               <button onClick={handleCreateRoom} class="btn-primary">
                 Create Room
               </button>
@@ -180,6 +226,61 @@ export const Lobby: Component<LobbyProps> = (_props) => {
               Refresh
             </button>
           </div>
+
+          <div class="leaderboard-section">
+            <h3>Leaderboard</h3>
+            <Show
+              when={multiplayerStore.leaderboard.length > 0}
+              fallback={<p class="no-rooms">No finished games yet.</p>}
+            >
+              <table class="leaderboard">
+                <thead>
+                  <tr>
+                    <th>#</th>
+                    <th>Player</th>
+                    <th>Rating</th>
+                    <th>Games</th>
+                    <th>Win rate</th>
+                    <th>Avg VP</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  <For each={multiplayerStore.leaderboard}>
+                    {(entry, i) => (
+                      <tr
+                        classList={{
+                          "leaderboard-me": entry.player_id === multiplayerStore.playerId,
+                        }}
+                      >
+                        <td>{i() + 1}</td>
+                        <td>{entry.name}</td>
+                        <td>{Math.round(entry.rating)}</td>
+                        <td>{entry.games}</td>
+                        <td>{Math.round(entry.win_rate * 100)}%</td>
+                        <td>{entry.average_vp.toFixed(1)}</td>
+                      </tr>
+                    )}
+                  </For>
+                </tbody>
+              </table>
+            </Show>
+            <Show when={multiplayerStore.myStats}>
+              {(stats) => (
+                <p class="my-stats">
+                  You: {Math.round(stats().rating)} rating, {stats().wins}/{stats().games} wins
+                </p>
+              )}
+            </Show>
+            <button
+              onClick={() => {
+                getLeaderboard();
+                if (multiplayerStore.playerId) getPlayerStats(multiplayerStore.playerId);
+              }}
+              class="btn-refresh"
+            >
+              Refresh
+            </button>
+          </div>
         </div>
       </Show>
 
@@ -211,10 +312,25 @@ export const Lobby: Component<LobbyProps> = (_props) => {
                       {player.id === multiplayerStore.playerId && (
                         <span class="you-badge">You</span>
                       )}
+                      {player.bot && <span class="bot-badge">Bot ({player.bot.difficulty})</span>}
                     </span>
                     <span class="connection-status">
-                      {player.connected ? "Connected" : "Disconnected"}
+                      {player.connected
+                        ? "Connected"
+                        : player.stand_in
+                          ? "Disconnected (bot playing)"
+                          : "Disconnected"}
                     </span>
+                    <Show when={isHost() && player.id !== multiplayerStore.room!.host_id}>
+                      <span class="player-actions">
+                        <Show when={!player.bot}>
+                          <button onClick={() => transferHost(player.id)}>Make Host</button>
+                        </Show>
+                        <button onClick={() => kickPlayer(player.id)} class="btn-secondary">
+                          Kick
+                        </button>
+                      </span>
+                    </Show>
                   </div>
                 )}
               </For>
@@ -226,6 +342,104 @@ export const Lobby: Component<LobbyProps> = (_props) => {
             </div>
           </div>
 
+          <Show when={multiplayerStore.room!.settings}>
+            {(settings) => (
+              <div class="room-settings">
+                <h3>Game Settings</h3>
+                <fieldset disabled={!isHost()}>
+                  <label>
+                    Victory points
+                    <select
+                      value={settings().options.victory_points_to_win ?? 10}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          s.options.victory_points_to_win = parseInt(e.target.value);
+                        })
+                      }
+                    >
+                      <For each={[6, 8, 10, 12, 15]}>
+                        {(vp) => <option value={vp}>{vp}</option>}
+                      </For>
+                    </select>
+                  </label>
+                  <label>
+                    Board
+                    <select
+                      value={settings().options.board_template ?? "Random"}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          s.options.board_template = e.target.value as "Random" | "Beginner";
+                        })
+                      }
+                    >
+                      <option value="Random">Random</option>
+                      <option value="Beginner">Beginner</option>
+                    </select>
+                  </label>
+                  <label>
+                    Seed
+                    <input
+                      type="number"
+                      placeholder="Random"
+                      value={settings().seed ?? ""}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          s.seed = e.target.value === "" ? null : parseInt(e.target.value);
+                        })
+                      }
+                    />
+                  </label>
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={settings().options.pirate ?? false}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          s.options.pirate = e.target.checked;
+                        })
+                      }
+                    />
+                    Pirate
+                  </label>
+                  <label>
+                    Turn timer
+                    <select
+                      value={settings().timers.turn_secs ?? 0}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          const secs = parseInt(e.target.value);
+                          s.timers.turn_secs = secs > 0 ? secs : null;
+                          s.timers.decision_secs = secs > 0 ? DECISION_SECS : null;
+                        })
+                      }
+                    >
+                      <option value={0}>None</option>
+                      <option value={60}>60s</option>
+                      <option value={90}>90s</option>
+                      <option value={120}>120s</option>
+                    </select>
+                  </label>
+                  <label>
+                    Stand-in bots
+                    <select
+                      value={settings().stand_in_difficulty}
+                      onChange={(e) =>
+                        changeSettings((s) => {
+                          s.stand_in_difficulty = e.target.value as BotDifficulty;
+                        })
+                      }
+                    >
+                      <option value="Easy">Easy</option>
+                      <option value="Medium">Medium</option>
+                      <option value="Hard">Hard</option>
+                      <option value="Expert">Expert</option>
+                    </select>
+                  </label>
+                </fieldset>
+              </div>
+            )}
+          </Show>
+
           <Show when={isHost()}>
             <div class="host-controls">
               <button
@@ -238,6 +452,22 @@ export const Lobby: Component<LobbyProps> = (_props) => {
               <Show when={!canStartGame()}>
                 <p class="hint">Need at least 2 players to start</p>
               </Show>
+              <Show
+                when={multiplayerStore.room!.players.length < multiplayerStore.room!.max_players}
+              >
+                <div class="add-bot">
+                  <select
+                    value={botDifficulty()}
+                    onChange={(e) => setBotDifficulty(e.target.value as BotDifficulty)}
+                  >
+                    <option value="Easy">Easy</option>
+                    <option value="Medium">Medium</option>
+                    <option value="Hard">Hard</option>
+                    <option value="Expert">Expert</option>
+                  </select>
+                  <button onClick={() => addBot(botDifficulty())}>Add Bot</button>
+                </div>
+              </Show>
             </div>
           </Show>
 
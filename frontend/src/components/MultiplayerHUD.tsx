@@ -1,6 +1,7 @@
-import { Show, For, createMemo, createSignal, createEffect, onMount } from "solid-js";
+import { Show, For, createMemo, createSignal, createEffect, onMount, onCleanup } from "solid-js";
 import type { Component } from "solid-js";
 import { SoundManager } from "../utils/SoundManager";
+import type { RunningTimer } from "../stores/multiplayerStore";
 
 interface MultiplayerHUDProps {
   gameState: any;
@@ -8,6 +9,7 @@ interface MultiplayerHUDProps {
   currentPlayer: number;
   isMyTurn: boolean;
   myPlayerIndex: number | null;
+  timer: RunningTimer | null;
   onAction: (action: any) => void;
 }
 
@@ -104,6 +106,23 @@ function TurnBadge(props: { isMyTurn: boolean; playerName: string }) {
   );
 }
 
+// Seconds left before the server moves for the players it is waiting on
+function TimerCountdown(props: { timer: RunningTimer; mine: boolean }) {
+  const [now, setNow] = createSignal(Date.now());
+  const secondsLeft = () => Math.max(0, Math.ceil((props.timer.deadline - now()) / 1000));
+  const interval = setInterval(() => setNow(Date.now()), 250);
+  onCleanup(() => clearInterval(interval));
+
+  return (
+    <div
+      class="turn-timer"
+      classList={{ "timer-mine": props.mine, "timer-low": secondsLeft() <= 10 }}
+    >
+      ⏱ {props.timer.kind === "Decision" ? "Decide" : "Turn"}: {secondsLeft()}s
+    </div>
+  );
+}
+
 // Phase indicator with icon
 function PhaseIndicator(props: { phase: { text: string; type: string; placing?: string } }) {
   const phaseIcons: Record<string, string> = {
@@ -446,6 +465,14 @@ export const MultiplayerHUD: Component<MultiplayerHUDProps> = (props) => {
       <div class="hud-header">
         <TurnBadge isMyTurn={props.isMyTurn} playerName={currentPlayerName()} />
         <PhaseIndicator phase={phaseInfo()} />
+        <Show when={props.timer}>
+          {(timer) => (
+            <TimerCountdown
+              timer={timer()}
+              mine={props.myPlayerIndex !== null && timer().players.includes(props.myPlayerIndex)}
+            />
+          )}
+        </Show>
       </div>
 
       {/* Dice Display */}
@@ -1,6 +1,6 @@
 import { Show, For, createMemo, createSignal, createEffect } from "solid-js";
 import type { Component } from "solid-js";
-import { gameStore, applyAction, getVictoryPoints } from "../stores/gameStore";
+import { gameStore, applyAction, getHint, getVictoryPoints } from "../stores/gameStore";
 import type { Resource } from "../types/game";
 import { TradePanel } from "./TradePanel";
 
@@ -65,6 +65,7 @@ function ResourceCard(props: { resource: Resource; count: number }) {
 
 export const PlayerHUD: Component = () => {
   const [showTrade, setShowTrade] = createSignal(false);
+  const [hint, setHint] = createSignal<string | null>(null);
 
   const currentPlayer = createMemo(() => {
     if (!gameStore.state?.players) return null;
@@ -149,6 +150,22 @@ export const PlayerHUD: Component = () => {
     applyAction("BuyDevelopmentCard");
   }
 
+  function handleHint() {
+    const h = getHint(gameStore.currentPlayer);
+    if (!h?.action) {
+      setHint(null);
+      return;
+    }
+    const name = typeof h.action === "string" ? h.action : Object.keys(h.action)[0];
+    setHint(name.replace(/([a-z])([A-Z])/g, "$1 $2"));
+  }
+
+  createEffect(() => {
+    // A hint only applies to the position it was asked for
+    gameStore.validActions;
+    setHint(null);
+  });
+
   return (
     <div class="multiplayer-hud">
       <Show when={currentPlayer()}>
@@ -276,6 +293,17 @@ export const PlayerHUD: Component = () => {
                   <span class="button-text">End Turn</span>
                 </button>
               </Show>
+
+              <button class="action-button action-secondary" onClick={handleHint}>
+                <span class="button-icon">💡</span>
+                <span class="button-text">Hint</span>
+              </button>
+              <Show when={hint()}>
+                <div class="action-hint">
+                  <span class="hint-icon">💡</span>
+                  <span>Try: {hint()}</span>
+                </div>
+              </Show>
             </div>
 
             {/* Resources */}
@@ -154,4 +154,45 @@ export function getVictoryPoints(playerId: number): number {
   return store.game?.getVictoryPoints(playerId) ?? 0;
 }
 
+export interface Hint {
+  action: any | null;
+  discard: any | null;
+  accept_trade: boolean | null;
+}
+
+// What a bot would do in the player's place
+export function getHint(playerId: number, difficulty = "Hard"): Hint | null {
+  const json = store.game?.getHint(playerId, difficulty);
+  return json ? JSON.parse(json) : null;
+}
+
+export interface PlayerAccuracy {
+  player: number;
+  decisions: number;
+  accuracy: number;
+  average_loss: number;
+  best_moves: number;
+  blunders: number;
+}
+
+export interface GameAnalysis {
+  decisions: any[];
+  players: PlayerAccuracy[];
+}
+
+// Grade every decision played so far against the engine's evaluator
+export function analyzeGame(): GameAnalysis | null {
+  try {
+    const json = store.game?.analyzeGame();
+    return json ? JSON.parse(json) : null;
+  } catch (e) {
+    console.error("Analysis failed:", e);
+    return null;
+  }
+}
+
+export function getWinProbabilities(): number[] {
+  return Array.from(store.game?.getWinProbabilities() ?? []);
+}
+
 export { store as gameStore };
@@ -1,5 +1,6 @@
 import { createSignal } from "solid-js";
 import { createStore } from "solid-js/store";
+import type { GameAction } from "../types/game";
 
 // Message types matching server protocol
 interface ClientMessage {
@@ -19,6 +20,56 @@ interface RoomInfo {
   max_players: number;
   host_id: string;
   status: "Waiting" | "InGame" | "Finished";
+  settings?: RoomSettings;
+  paused?: boolean;
+}
+
+// How a room's game is played, chosen by the host. `options` mirrors the
+// engine's GameOptions; fields left out keep the standard rules.
+export interface RoomSettings {
+  options: {
+    victory_points_to_win?: number | null;
+    max_turns?: number | null;
+    board_template?: "Random" | "Beginner";
+    pirate?: boolean;
+    teams?: number[];
+    [rule: string]: any;
+  };
+  seed: number | null;
+  timers: TurnTimers;
+  stand_in_difficulty: BotDifficulty;
+  // Anyone may watch and follow the game
+  public?: boolean;
+}
+
+// Time limits a room plays with; null means no limit
+export interface TurnTimers {
+  turn_secs: number | null;
+  decision_secs: number | null;
+}
+
+export type TimerKind = "Turn" | "Decision";
+
+export interface RunningTimer {
+  kind: TimerKind;
+  // Seats the server will move for when time runs out
+  players: number[];
+  // Local time (ms since epoch) the timer runs out
+  deadline: number;
+}
+
+export type BotDifficulty = "Easy" | "Medium" | "Hard" | "Expert";
+
+export type BotPersonality =
+  | "Balanced"
+  | "AggressiveExpander"
+  | "DevCardRush"
+  | "PortTrader"
+  | "Blocker";
+
+interface BotSeat {
+  difficulty: BotDifficulty;
+  personality: BotPersonality;
 }
 
 interface PlayerInfo {
@@ -26,6 +77,19 @@ interface PlayerInfo {
   name: string;
   ready: boolean;
   connected: boolean;
+  bot?: BotSeat | null;
+  stand_in?: boolean;
+}
+
+// A player's record over every finished game
+export interface PlayerStats {
+  player_id: string;
+  name: string;
+  games: number;
+  wins: number;
+  win_rate: number;
+  average_vp: number;
+  rating: number;
 }
 
 export interface MultiplayerStore {
@@ -33,11 +97,24 @@ export interface MultiplayerStore {
   playerId: string | null;
   room: RoomInfo | null;
   rooms: RoomInfo[];
+  leaderboard: PlayerStats[];
+  // Our own record, once we have finished a game
+  myStats: PlayerStats | null;
   gameState: any | null;
-  validActions: any[];
+  // Number of the game state we have, for following deltas
+  stateVersion: number;
+  validActions: GameAction[];
   currentPlayer: number;
   error: string | null;
+  hint: any | null;
+  timer: RunningTimer | null;
   chatMessages: { playerName: string; message: string }[];
+  // Numbered game events received so far, newest last
+  events: { seq: number; event: any }[];
+  lastSeq: number;
+  lastChatSeq: number;
+  // Secret the server gave us for taking our seat back
+  rejoinToken: string | null;
 }
 
 const [store, setStore] = createStore<MultiplayerStore>({
@@ -45,11 +122,20 @@ const [store, setStore] = createStore<MultiplayerStore>({
   playerId: null,
   room: null,
   rooms: [],
+  leaderboard: [],
+  myStats: null,
   gameState: null,
+  stateVersion: 0,
   validActions: [],
   currentPlayer: 0,
   error: null,
+  hint: null,
+  timer: null,
   chatMessages: [],
+  events: [],
+  lastSeq: 0,
+  lastChatSeq: 0,
+  rejoinToken: null,
 });
 
 const [socket, setSocket] = createSignal<WebSocket | null>(null);
@@ -57,27 +143,48 @@ const [socket, setSocket] = createSignal<WebSocket | null>(null);
 // Event handlers that can be set by components
 let onGameStarted: ((state: any) => void) | null = null;
 let onGameStateUpdate: ((state: any) => void) | null = null;
-let onGameOver: ((winner: number, winnerName: string) => void) | null = null;
+let onGameOver: ((winner: number | null, winnerName: string | null) => void) | null = null;
 
 export function setEventHandlers(handlers: {
   onGameStarted?: (state: any) => void;
   onGameStateUpdate?: (state: any) => void;
-  onGameOver?: (winner: number, winnerName: string) => void;
+  onGameOver?: (winner: number | null, winnerName: string | null) => void;
 }) {
   onGameStarted = handlers.onGameStarted || null;
   onGameStateUpdate = handlers.onGameStateUpdate || null;
   onGameOver = handlers.onGameOver || null;
 }
 
+// The server's signed ticket for our identity, kept across visits so we
+// come back as the same player
+const TICKET_KEY = "kopiatan-ticket";
+
+// Protocol version we speak, sent in our hello
+const PROTOCOL_VERSION = 1;
+
+// Tags each action we send; the server echoes it in the ActionResult
+let nextRequestId = 1;
+let lastRequestId: string | null = null;
+
 export async function connect(serverUrl: string = "ws://localhost:8080"): Promise<void> {
   return new Promise((resolve, reject) => {
     try {
-      const ws = new WebSocket(serverUrl);
+      const ticket = localStorage.getItem(TICKET_KEY);
+      const url = ticket ? `${serverUrl}?ticket=${encodeURIComponent(ticket)}` : serverUrl;
+      const ws = new WebSocket(url);
 
       ws.onopen = () => {
         console.log("Connected to game server");
         setStore("connected", true);
         setSocket(ws);
+        send({
+          type: "Hello",
+          payload: {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: "kopiatan-web",
+            features: ["deltas"],
+          },
+        });
         resolve();
       };
 
@@ -127,13 +234,78 @@ function send(msg: ClientMessage) {
   }
 }
 
+// The state with a delta's changes: fields it carries replace ours, and
+// players it carries replace theirs by ID
+function applyDelta(state: any, delta: any): any {
+  const { players, ...fields } = delta;
+  const next = { ...state, ...fields };
+  if (players) {
+    next.players = [...state.players];
+    for (const player of players) {
+      next.players[player.id] = player;
+    }
+  }
+  return next;
+}
+
+// The seat we last held, so a dropped connection can take it back
+const SESSION_KEY = "kopiatan-seat";
+
+function rememberSeat(roomId: string | null, playerId: string | null) {
+  if (roomId && playerId) {
+    const seat = {
+      roomId,
+      playerId,
+      token: store.rejoinToken,
+      lastSeq: store.lastSeq,
+      lastChatSeq: store.lastChatSeq,
+    };
+    sessionStorage.setItem(SESSION_KEY, JSON.stringify(seat));
+  } else {
+    sessionStorage.removeItem(SESSION_KEY);
+  }
+}
+
+function addEvents(events: { seq: number; event: any }[]) {
+  const fresh = events.filter((e) => e.seq > store.lastSeq);
+  if (fresh.length === 0) return;
+  setStore("events", (prev) => [...prev, ...fresh]);
+  setStore("lastSeq", fresh[fresh.length - 1].seq);
+  rememberSeat(store.room?.id ?? null, store.playerId);
+}
+
+function addChat(line: { player_name: string; message: string; seq?: number }) {
+  setStore("chatMessages", (prev) => [
+    ...prev,
+    { playerName: line.player_name, message: line.message },
+  ]);
+  if (line.seq) {
+    setStore("lastChatSeq", line.seq);
+    rememberSeat(store.room?.id ?? null, store.playerId);
+  }
+}
+
 function handleServerMessage(msg: ServerMessage) {
   console.log("Received:", msg.type, msg.payload);
 
   switch (msg.type) {
-    case "Welcome":
+    case "Welcome": {
       setStore("playerId", msg.payload.player_id);
+      if (msg.payload.ticket) {
+        localStorage.setItem(TICKET_KEY, msg.payload.ticket);
+      }
+      getPlayerStats(msg.payload.player_id);
+      const saved = sessionStorage.getItem(SESSION_KEY);
+      if (saved) {
+        // Saved again once the server gives the seat back
+        const { roomId, playerId, token, lastSeq, lastChatSeq } = JSON.parse(saved);
+        setStore("lastSeq", lastSeq ?? 0);
+        setStore("lastChatSeq", lastChatSeq ?? 0);
+        rememberSeat(null, null);
+        rejoinRoom(roomId, playerId, token ?? null);
+      }
       break;
+    }
 
     case "RoomCreated":
       // Room ID is in payload
@@ -141,12 +313,40 @@ function handleServerMessage(msg: ServerMessage) {
 
     case "JoinedRoom":
       setStore("room", msg.payload.room);
+      setStore("rejoinToken", msg.payload.rejoin_token ?? null);
       setStore("error", null);
+      rememberSeat(msg.payload.room.id, store.playerId);
       break;
 
     case "LeftRoom":
+      rememberSeat(null, null);
+      setStore("room", null);
+      setStore("gameState", null);
+      setStore("timer", null);
+      break;
+
+    case "ServerShuttingDown":
+      // Our seat is saved; it can be taken back once the server is up
+      setStore("error", "The server is restarting. Reconnect in a moment to carry on.");
+      break;
+
+    case "Kicked":
+      rememberSeat(null, null);
+      setStore("room", null);
+      setStore("gameState", null);
+      setStore("error", "You were removed from the room");
+      break;
+
+    case "RoomClosed":
+      rememberSeat(null, null);
       setStore("room", null);
       setStore("gameState", null);
+      setStore("timer", null);
+      setStore("error", msg.payload.reason);
+      break;
+
+    case "Announcement":
+      setStore("error", msg.payload.message);
       break;
 
     case "RoomUpdated":
@@ -162,12 +362,33 @@ function handleServerMessage(msg: ServerMessage) {
 
     case "GameState":
       setStore("gameState", msg.payload.state);
+      setStore("stateVersion", msg.payload.version ?? 0);
       if (onGameStateUpdate) {
         onGameStateUpdate(msg.payload.state);
       }
       break;
 
+    case "GameDelta": {
+      const { base, version, delta } = msg.payload;
+      if (!store.gameState || base !== store.stateVersion) {
+        // Missed something: start again from a whole state
+        send({ type: "ResyncState" });
+        break;
+      }
+      const state = applyDelta(store.gameState, delta);
+      setStore("gameState", state);
+      setStore("stateVersion", version);
+      if (onGameStateUpdate) {
+        onGameStateUpdate(state);
+      }
+      break;
+    }
+
     case "ActionResult":
+      // Only the answer to our latest action decides what we show
+      if (msg.payload.request_id && msg.payload.request_id !== lastRequestId) {
+        break;
+      }
       if (!msg.payload.success) {
         setStore("error", msg.payload.error || "Action failed");
       } else {
@@ -177,6 +398,29 @@ function handleServerMessage(msg: ServerMessage) {
 
     case "ValidActions":
       setStore("validActions", msg.payload.actions);
+      setStore("hint", null);
+      break;
+
+    case "TimerStarted":
+      setStore("timer", {
+        kind: msg.payload.kind,
+        players: msg.payload.players,
+        deadline: Date.now() + msg.payload.remaining_ms,
+      });
+      break;
+
+    case "TurnTimedOut":
+      setStore("timer", null);
+      break;
+
+    case "PauseChanged":
+      // A fresh TimerStarted follows on resume
+      if (store.room) setStore("room", "paused", msg.payload.paused);
+      if (msg.payload.paused) setStore("timer", null);
+      break;
+
+    case "Hint":
+      setStore("hint", msg.payload.hint);
       break;
 
     case "TurnChanged":
@@ -184,19 +428,38 @@ function handleServerMessage(msg: ServerMessage) {
       break;
 
     case "ChatMessage":
-      setStore("chatMessages", (prev) => [
-        ...prev,
-        {
-          playerName: msg.payload.player_name,
-          message: msg.payload.message,
-        },
-      ]);
+      addChat(msg.payload);
+      break;
+
+    case "GameEvents":
+      addEvents(msg.payload.events);
+      break;
+
+    case "CatchUp":
+      addEvents(msg.payload.events);
+      msg.payload.chat.forEach(addChat);
+      if (msg.payload.state) {
+        setStore("gameState", msg.payload.state);
+        if (onGameStarted) {
+          onGameStarted(msg.payload.state);
+        }
+      }
       break;
 
     case "RoomList":
       setStore("rooms", msg.payload.rooms);
       break;
 
+    case "Leaderboard":
+      setStore("leaderboard", msg.payload.players);
+      break;
+
+    case "PlayerStats":
+      if (msg.payload.player_id === store.playerId) {
+        setStore("myStats", msg.payload.stats);
+      }
+      break;
+
     case "Error":
       setStore("error", msg.payload.message);
       break;
@@ -217,13 +480,37 @@ function handleServerMessage(msg: ServerMessage) {
 }
 
 // Client actions
-export function createRoom(playerName: string, maxPlayers: number = 4) {
+export function createRoom(
+  playerName: string,
+  maxPlayers: number = 4,
+  settings: Partial<RoomSettings> = {},
+) {
   send({
     type: "CreateRoom",
-    payload: { player_name: playerName, max_players: maxPlayers },
+    payload: { player_name: playerName, max_players: maxPlayers, settings },
   });
 }
 
+export function kickPlayer(playerId: string) {
+  send({ type: "KickPlayer", payload: { player_id: playerId } });
+}
+
+export function transferHost(playerId: string) {
+  send({ type: "TransferHost", payload: { player_id: playerId } });
+}
+
+export function pauseGame() {
+  send({ type: "PauseGame" });
+}
+
+export function resumeGame() {
+  send({ type: "ResumeGame" });
+}
+
+export function updateRoomSettings(settings: RoomSettings) {
+  send({ type: "UpdateRoomSettings", payload: { settings } });
+}
+
 export function joinRoom(roomId: string, playerName: string) {
   send({
     type: "JoinRoom",
@@ -231,18 +518,51 @@ export function joinRoom(roomId: string, playerName: string) {
   });
 }
 
+export function rejoinRoom(roomId: string, playerId: string, token: string | null) {
+  send({
+    type: "Rejoin",
+    payload: {
+      room_id: roomId,
+      player_id: playerId,
+      token,
+      last_seq: store.lastSeq,
+      last_chat_seq: store.lastChatSeq,
+    },
+  });
+}
+
 export function leaveRoom() {
   send({ type: "LeaveRoom" });
 }
 
+export function addBot(
+  difficulty: BotDifficulty,
+  personality: BotPersonality = "Balanced"
+) {
+  send({
+    type: "AddBot",
+    payload: { difficulty, personality },
+  });
+}
+
 export function startGame() {
   send({ type: "StartGame" });
 }
 
-export function sendGameAction(action: any) {
+export function sendGameAction(action: GameAction): string {
+  const requestId = String(nextRequestId++);
+  lastRequestId = requestId;
   send({
     type: "GameAction",
-    payload: { action },
+    payload: { action, request_id: requestId },
+  });
+  return requestId;
+}
+
+export function requestHint(difficulty?: BotDifficulty) {
+  send({
+    type: "RequestHint",
+    payload: { difficulty: difficulty ?? null },
   });
 }
 
@@ -257,6 +577,14 @@ export function listRooms() {
   send({ type: "ListRooms" });
 }
 
+export function getLeaderboard(limit?: number) {
+  send({ type: "GetLeaderboard", payload: { limit } });
+}
+
+export function getPlayerStats(playerId: string) {
+  send({ type: "GetPlayerStats", payload: { player_id: playerId } });
+}
+
 export function ping() {
   send({ type: "Ping" });
 }
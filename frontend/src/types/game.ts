@@ -67,8 +67,16 @@ export interface Player {
   settlements_remaining: number;
   cities_remaining: number;
   roads_remaining: number;
+  conceded: boolean;
+  achievements: Achievement[];
 }
 
+export type Achievement =
+  | "FirstCity"
+  | "MonopolyHaul"
+  | "WonWithoutArmy"
+  | "Kiasu";
+
 export type DevelopmentCard =
   | "Knight"
   | "VictoryPoint"
@@ -84,13 +92,22 @@ export type GamePhase =
   | { DiscardRequired: { players_remaining: PlayerId[] } }
   | "MainPhase"
   | { RoadBuildingInProgress: { roads_remaining: number } }
-  | { Finished: { winner: PlayerId } };
+  | { AwaitingNextPlayer: { resume: GamePhase } }
+  | {
+      EndGameVote: {
+        proposer: PlayerId;
+        accepted: PlayerId[];
+        resume: GamePhase;
+      };
+    }
+  | { Finished: { winner: PlayerId | null; ranking: PlayerId[] } };
 
 export type GameAction =
   | { PlaceInitialSettlement: VertexCoord }
   | { PlaceInitialRoad: EdgeCoord }
   | "RollDice"
   | { MoveRobber: HexCoord }
+  | { MovePirate: HexCoord }
   | { StealFrom: PlayerId }
   | { DiscardCards: ResourceHand }
   | { BuildRoad: EdgeCoord }
@@ -107,7 +124,11 @@ export type GameAction =
   | { CounterTrade: TradeOffer }
   | "CancelTrade"
   | { MaritimeTrade: { give: Resource; give_count: number; receive: Resource } }
-  | "EndTurn";
+  | "EndTurn"
+  | "ConfirmNextPlayer"
+  | "ProposeEndGame"
+  | { VoteEndGame: boolean }
+  | "Concede";
 
 export interface TradeOffer {
   from: PlayerId;